@@ -0,0 +1,1275 @@
+use std::env;
+use std::error::Error;
+use std::io::{BufWriter, Write};
+use std::num::{NonZeroU32, NonZeroU64};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use futures::channel::mpsc;
+use futures::StreamExt;
+use schema::binary::{self, OrdersFile};
+use schema::journal;
+use schema::proto::server::{
+    server_client::ServerClient, ApproveFlightRequest, ExplainFlightRequest, GetOrderRequest,
+    LogLevel, NetworkConditions, PreviewScheduleRequest, SetFleetSizeRequest, StreamLogsRequest,
+    SwapSchedulerRequest,
+};
+use schema::scenario;
+use schema::units::Units;
+use schema::{
+    json, time, DemandPrior, Destination, DestinationChanges, DestinationId, DestinationMatcher,
+    EnergyModel, Flight, FlightExplanation, FlightPhases, Matching, Order, OrderReceipt,
+    OrderStatus, Priority, RangeReduction, ReturnPolicy, Sector, Speed, SpeedSchedule, ToFromProto,
+};
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+use scheduler_core::{
+    sweep_csv, AlertRule, Comparison, CsvRunner, GroundFleet, PackingStrategy, RoutePlanner,
+    RunReport, SummaryFormat, SurgePolicy, Sweep,
+};
+
+const USAGE: &str = "\
+Headless runner which simulates a scenario without the gRPC server
+
+USAGE:
+    cli [OPTIONS]
+    cli swap-scheduler <naive|forecasting> [--server <URI>]
+    cli preview <DESTINATION>... [--resupply] [--units <UNITS>] [--server <URI>]
+    cli update-destinations [--add <DESTINATION>]... [--deactivate <NAME>]... [--server <URI>]
+    cli get-order <ID> [--units <UNITS>] [--server <URI>]
+    cli explain-flight <FLIGHT_ID> [--units <UNITS>] [--server <URI>]
+    cli set-fleet-size <CARRIERS> [--server <URI>]
+    cli drain [--server <URI>]
+    cli approve-flight <FLIGHT_ID> [--server <URI>]
+    cli get-scenario [--server <URI>]
+    cli set-network-conditions <DELAY_MS> [--jitter <MS>] [--server <URI>]
+    cli list-subscribers [--server <URI>]
+    cli stream-logs [--level <LEVEL>] [--target <PREFIX>] [--server <URI>]
+    cli compare <BASELINE> <CANDIDATE> [--json] [--max-regression <PERCENT>]
+    cli sweep [--carriers <N,...>] [--carrier-speeds <MPS,...>] [--schedulers <NAME,...>]
+              [--jobs <N>] [--output <PATH>] [--destinations <PATH>] [--orders <PATH>]
+    cli convert <INPUT> <OUTPUT>
+    cli scenario diff <destinations|orders> <BASE> <VARIANT> [--json]
+    cli scenario merge <destinations|orders> <BASE> <VARIANT>... [--output <PATH>]
+
+SUBCOMMANDS:
+    swap-scheduler         Swap the scheduler of the simulation running on a server, handing over
+                           its queued orders & in-flight carriers (server defaults to $SERVER_URI)
+    preview                Show the flights a server would launch right now if emergency orders
+                           (or resupplies with `--resupply`) were placed for the destinations,
+                           without placing them
+    update-destinations    Add destinations to (as `name, north_m, east_m[, region]`) or deactivate
+                           destinations in the simulation running on a server. Orders for
+                           deactivated destinations are rejected, including those already queued
+    get-order              Show how far an order queued by a server has got, from queued through
+                           scheduled, launched & delivered (or failed)
+    explain-flight         Show why a flight launched by a server was packed as it was: the orders
+                           considered for it, & why each it didn't carry was left out
+    set-fleet-size         Add or retire idle carriers in the simulation running on a server, to
+                           see how the size of the fleet affects the backlog. Carriers in flight
+                           aren't retired, so the fleet may end up larger than asked for
+    drain                  Stop the simulation running on a server from taking orders, finishing
+                           it once the flights already launched have landed, e.g. ahead of a
+                           restart
+    approve-flight         Approve an emergency flight held on the ground awaiting approval by a
+                           server, launching it at the next minute
+    get-scenario           Show the destinations of the scenario a server runs, along with how
+                           many orders its demand priors expect for each per day
+    set-network-conditions Hold back the events a server sends its subscribers for this many
+                           milliseconds (plus up to `--jitter` more), e.g. to see how clients cope
+                           with a laggy network. Zero turns it off
+    list-subscribers       Show the clients subscribed to a server's streams: who they are, how
+                           long they've been connected & how far behind they've fallen
+    stream-logs            Tail the records a server logs at `--level` & above (`error`, `warn`,
+                           `info` by default, `debug` or `trace`), from targets starting with
+                           `--target`, e.g. `scheduler_core::scheduler`, until interrupted
+    compare                Compare the KPIs of two JSON run reports of the same scenario, e.g. with
+                           different schedulers. Exits with an error if any KPI of the candidate is
+                           worse than the baseline by more than `--max-regression` percent
+    sweep                  Simulate a scenario for every combination of carrier counts, speeds &
+                           schedulers (`naive` or `forecasting`), several at a time, writing a CSV
+                           of KPIs for each combination to `--output` (defaults to stdout)
+    convert                Convert an orders CSV to the compact binary format, which runs stream
+                           from rather than loading every order up front, or a binary file back
+    scenario diff          Show the destinations or orders added, removed & changed in a variant of
+                           a scenario file. Destinations are matched up by name, & orders by their
+                           time, destination & priority
+    scenario merge         Merge variants of the same scenario file, applying each one's changes to
+                           the base, to `--output` (defaults to stdout). Fails, listing them, if any
+                           variants' changes conflict
+
+OPTIONS:
+    --destinations <PATH>  Destinations CSV (defaults to the sample scenario)
+    --orders <PATH>        Orders CSV or binary file (defaults to the sample scenario), or `-` to
+                           read newline-delimited JSON orders from stdin as they arrive
+    --origin <NEST>        Nest which carriers launch from & return to, as `name, north_m, east_m`
+                           in the same frame as the destinations (defaults to `ORIGIN, 0, 0`)
+    --range-reductions <PATH>
+                           CSV of `time, factor[, carrier]` conditions reducing the range carriers
+                           have left, e.g. headwinds, recalling flights which fall short
+    --packing <STRATEGY>   How orders are packed into carriers: `priority` (the default), `first-fit`,
+                           `best-fit`, `worst-fit`, or `plugin:<PATH>` to load one from a library
+    --route-planner <PLANNER>
+                           Order the stops of each flight as they were packed, `packed` (the
+                           default), or to spend the least energy, `energy`
+    --return-policy <POLICY>
+                           Have carriers fly back to the origin after their last stop, `return`
+                           (the default), or wait there for their next flight, `wait`
+    --fairness <SECONDS>   Pack the resupplies of destinations which have had orders waiting this
+                           long without a flight to them ahead of perishables & other resupplies
+    --energy-model <MODEL> Estimate the energy each flight spends from `carrier_kg, payload_kg,
+                           j_per_kg_m[, wind_east_mps, wind_north_mps]` (defaults to `20, 1.75,
+                           0.5` in still air), reported per flight & planned by `energy`
+    --takeoff <SECONDS>    Time carriers hold in place taking off from the origin & each stop
+    --landing <SECONDS>    Time carriers hold in place landing at each stop & back at the origin
+    --accelerate <SECONDS> Time carriers take to reach cruise speed after taking off
+    --decelerate <SECONDS> Time carriers take to slow to a stop before landing or turning back
+    --service <SECONDS>    Time carriers spend at each stop handing over orders, either fixed or
+                           sampled for each stop from a range, e.g. `30` or `20-60`
+    --separation <METERS>  Keep carriers out of each other's grid cells of this size, holding
+                           launches which would conflict
+    --approval-timeout <SECONDS>
+                           Hold emergency flights on the ground until they're approved, which
+                           headless runs can only do automatically after this long
+    --sectors <PATH>       CSV of `name, west_m, north_m, east_m, south_m, max_flights` sectors of
+                           airspace, holding launches which would put too many carriers in one
+    --surge <EMERGENCIES>/<MINUTES>
+                           Give every carrier over to emergencies while this many arrive within
+                           this many minutes, e.g. `6/30`, alerting as each surge starts & ends
+    --ground <VEHICLES>[, <SPEED>, <RANGE>]
+                           Deliver by road as well as by air with this many ground vehicles,
+                           optionally with their speed in meters per second & range in meters
+    --alert-rules <RULES>  Raise an alert once a condition holds for long enough, e.g.
+                           `pending_emergencies > 3 for 5m; available_carriers == 0 for 10m`
+    --compress-gaps <MINUTES>
+                           Skip past gaps between orders longer than this many minutes, e.g.
+                           idle nights in a log spanning weeks, bringing later orders forward
+    --aliases <PATH>       CSV of `alias, destination` names orders may use for destinations
+    --matching <MODE>      How loosely orders' destinations are matched: `exact` (the default),
+                           `case-insensitive` or `fuzzy`, rejecting names which match several
+    --plan <PATH>          Flight plan to warm-start the scheduler from, following it until the
+                           orders deviate from it
+    --export-plan <PATH>   Write the flights launched by the run as a plan, for use with `--plan`
+    --orders-csv <PATH>    Write each order's wait before launch & the distance its flights went
+                           for it as CSV, e.g. to attribute the cost of the run to its orders
+    --summary <PATH>       Write a summary of the run with charts & tables, as HTML or Markdown by
+                           the path's extension (`.html` or `.md`)
+    --speed <SPEED>        Simulation speed, e.g. `real-time`, `20x` or `1/2x`
+                           (defaults to real-time when reading from stdin, otherwise 200x)
+    --speed-schedule <WINDOWS>
+                           Speeds to run at through each simulated day instead, as comma-separated
+                           `START-END=SPEED` windows, e.g. `22:00-07:00=500x, 07:00-22:00=50x,
+                           10:00-10:30=real-time`. Later windows win where they overlap
+    --start <TIMESTAMP>    Simulated time to start from when reading from stdin, in seconds or as
+                           a UTC date & time like `2023-10-14T23:00:00Z` (defaults to now)
+    --units <UNITS>        Units the report is displayed in, `metric` (the default) or `imperial`
+    --json                 Print the final report as JSON
+    -h, --help             Print this message";
+
+/// Where orders for the simulation come from
+enum OrderSource {
+    Csv(String),
+    Stdin,
+}
+
+struct Args {
+    destinations_csv_path: String,
+    orders: OrderSource,
+    origin: Destination,
+    range_reductions_csv_path: Option<String>,
+    flight_phases: FlightPhases,
+    packing: Option<Arc<dyn PackingStrategy>>,
+    route_planner: RoutePlanner,
+    return_policy: ReturnPolicy,
+    fairness: Option<u64>,
+    energy: EnergyModel,
+    separation_m: Option<NonZeroU64>,
+    approval_timeout_s: Option<NonZeroU64>,
+    sectors_csv_path: Option<String>,
+    surge: Option<SurgePolicy>,
+    alert_rules: Vec<AlertRule>,
+    ground: GroundFleet,
+    compress_gaps_minutes: Option<NonZeroU32>,
+    aliases_csv_path: Option<String>,
+    matching: Matching,
+    plan_path: Option<String>,
+    export_plan_path: Option<String>,
+    orders_csv_path: Option<String>,
+    summary: Option<(String, SummaryFormat)>,
+    speed: Option<Speed>,
+    speed_schedule: Option<SpeedSchedule>,
+    start_time: Option<u64>,
+    units: Units,
+    json: bool,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Option<Self>, String> {
+        let mut parsed = Self {
+            destinations_csv_path: schema::SAMPLE_DESTINATIONS_CSV_PATH.to_string(),
+            orders: OrderSource::Csv(schema::SAMPLE_ORDERS_CSV_PATH.to_string()),
+            origin: Destination::default_origin(),
+            range_reductions_csv_path: None,
+            flight_phases: FlightPhases::default(),
+            packing: None,
+            route_planner: RoutePlanner::default(),
+            return_policy: ReturnPolicy::default(),
+            fairness: None,
+            energy: EnergyModel::default(),
+            separation_m: None,
+            approval_timeout_s: None,
+            sectors_csv_path: None,
+            surge: None,
+            alert_rules: vec![],
+            ground: GroundFleet::default(),
+            compress_gaps_minutes: None,
+            aliases_csv_path: None,
+            matching: Matching::Exact,
+            plan_path: None,
+            export_plan_path: None,
+            orders_csv_path: None,
+            summary: None,
+            speed: None,
+            speed_schedule: None,
+            start_time: None,
+            units: Units::default(),
+            json: false,
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("missing value for {}", arg))
+            };
+
+            match arg.as_str() {
+                "--destinations" => parsed.destinations_csv_path = value()?,
+                "--orders" => {
+                    parsed.orders = match value()?.as_str() {
+                        "-" => OrderSource::Stdin,
+                        path => OrderSource::Csv(path.to_string()),
+                    }
+                }
+                "--origin" => parsed.origin = value()?.parse()?,
+                "--range-reductions" => parsed.range_reductions_csv_path = Some(value()?),
+                "--packing" => parsed.packing = Some(scheduler_core::packing_strategy(&value()?)?),
+                "--route-planner" => parsed.route_planner = value()?.parse()?,
+                "--return-policy" => parsed.return_policy = value()?.parse()?,
+                "--fairness" => parsed.fairness = Some(seconds(&value()?)?),
+                "--energy-model" => parsed.energy = value()?.parse()?,
+                "--takeoff" => parsed.flight_phases.takeoff_s = seconds(&value()?)?,
+                "--landing" => parsed.flight_phases.landing_s = seconds(&value()?)?,
+                "--accelerate" => parsed.flight_phases.accelerate_s = seconds(&value()?)?,
+                "--decelerate" => parsed.flight_phases.decelerate_s = seconds(&value()?)?,
+                "--service" => parsed.flight_phases.service = value()?.parse()?,
+                "--separation" => {
+                    let value = value()?;
+                    parsed.separation_m = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("invalid separation: {}", value))?,
+                    )
+                }
+                "--approval-timeout" => {
+                    let value = value()?;
+                    parsed.approval_timeout_s = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("invalid approval timeout: {}", value))?,
+                    )
+                }
+                "--sectors" => parsed.sectors_csv_path = Some(value()?),
+                "--surge" => parsed.surge = Some(value()?.parse()?),
+                "--ground" => parsed.ground = value()?.parse()?,
+                "--alert-rules" => parsed.alert_rules = AlertRule::parse_all(&value()?)?,
+                "--compress-gaps" => {
+                    let value = value()?;
+                    parsed.compress_gaps_minutes = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("invalid gap: {}", value))?,
+                    )
+                }
+                "--aliases" => parsed.aliases_csv_path = Some(value()?),
+                "--matching" => parsed.matching = value()?.parse()?,
+                "--plan" => parsed.plan_path = Some(value()?),
+                "--export-plan" => parsed.export_plan_path = Some(value()?),
+                "--orders-csv" => parsed.orders_csv_path = Some(value()?),
+                "--summary" => {
+                    let path = value()?;
+                    parsed.summary = Some((path.clone(), SummaryFormat::from_path(&path)?));
+                }
+                "--speed" => parsed.speed = Some(value()?.parse()?),
+                "--speed-schedule" => parsed.speed_schedule = Some(value()?.parse()?),
+                "--start" => parsed.start_time = Some(time::parse_timestamp(&value()?)?),
+                "--units" => parsed.units = value()?.parse()?,
+                "--json" => parsed.json = true,
+                "-h" | "--help" => return Ok(None),
+                _ => return Err(format!("unrecognized argument: {}", arg)),
+            }
+        }
+
+        Ok(Some(parsed))
+    }
+}
+
+#[tokio::main]
+pub async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
+    dotenv::dotenv().ok();
+    env_logger::init();
+
+    let mut args = env::args().skip(1).peekable();
+    match args.peek().map(String::as_str) {
+        Some("swap-scheduler") => return swap_scheduler(args.skip(1)).await,
+        Some("preview") => return preview_schedule(args.skip(1)).await,
+        Some("update-destinations") => return update_destinations(args.skip(1)).await,
+        Some("get-order") => return get_order(args.skip(1)).await,
+        Some("explain-flight") => return explain_flight(args.skip(1)).await,
+        Some("set-fleet-size") => return set_fleet_size(args.skip(1)).await,
+        Some("drain") => return drain(args.skip(1)).await,
+        Some("approve-flight") => return approve_flight(args.skip(1)).await,
+        Some("get-scenario") => return get_scenario(args.skip(1)).await,
+        Some("set-network-conditions") => return set_network_conditions(args.skip(1)).await,
+        Some("list-subscribers") => return list_subscribers(args.skip(1)).await,
+        Some("stream-logs") => return stream_logs(args.skip(1)).await,
+        Some("compare") => return compare(args.skip(1)),
+        Some("sweep") => return sweep(args.skip(1)).await,
+        Some("convert") => return convert(args.skip(1)).await,
+        Some("scenario") => return scenario(args.skip(1)).await,
+        _ => {}
+    }
+
+    let args = match Args::parse(args) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return Ok(());
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let destinations = Destination::from_csv(&args.destinations_csv_path).await?;
+    let mut runner = match &args.orders {
+        OrderSource::Csv(path) if binary::is_binary(path) => CsvRunner::new(destinations, vec![])
+            .with_orders_file(OrdersFile::open(path)?)
+            .with_speed(
+                args.speed
+                    .unwrap_or(Speed::fast_forward(200).expect("speed")),
+            ),
+        OrderSource::Csv(path) => CsvRunner::new(destinations, Order::from_csv(path).await?)
+            .with_speed(
+                args.speed
+                    .unwrap_or(Speed::fast_forward(200).expect("speed")),
+            ),
+        OrderSource::Stdin => {
+            let (tx, rx) = mpsc::unbounded();
+            tokio::spawn(read_orders(tx));
+
+            CsvRunner::new(destinations, vec![])
+                .with_speed(args.speed.unwrap_or_default())
+                .with_start_time(args.start_time.unwrap_or_else(now))
+                .with_live_orders(rx)
+        }
+    };
+    runner = runner
+        .with_origin(args.origin)
+        .with_flight_phases(args.flight_phases)
+        .with_route_planner(args.route_planner)
+        .with_return_policy(args.return_policy)
+        .with_energy_model(args.energy);
+    if let Some(packing) = args.packing {
+        runner = runner.with_packing(packing);
+    }
+    if let Some(schedule) = args.speed_schedule {
+        runner = runner.with_speed_schedule(schedule);
+    }
+    if let Some(starvation_s) = args.fairness {
+        runner = runner.with_fairness(starvation_s);
+    }
+    if let Some(separation_m) = args.separation_m {
+        runner = runner.with_separation(separation_m);
+    }
+    if let Some(timeout_s) = args.approval_timeout_s {
+        runner = runner.with_approvals(timeout_s);
+    }
+    if let Some(policy) = args.surge {
+        runner = runner.with_surge(policy);
+    }
+    runner = runner
+        .with_alert_rules(args.alert_rules)
+        .with_ground_fleet(args.ground);
+    if let Some(minutes) = args.compress_gaps_minutes {
+        runner = runner.with_gap_compression(minutes);
+    }
+    if let Some(path) = &args.range_reductions_csv_path {
+        runner = runner.with_range_reductions(RangeReduction::from_csv(path).await?);
+    }
+    if let Some(path) = &args.sectors_csv_path {
+        runner = runner.with_sectors(Sector::from_csv(path).await?);
+    }
+    if args.aliases_csv_path.is_some() || args.matching != Matching::Exact {
+        let matcher = match &args.aliases_csv_path {
+            Some(path) => DestinationMatcher::from_aliases_csv(path).await?,
+            None => DestinationMatcher::default(),
+        };
+        runner = runner.with_destination_matcher(matcher.with_matching(args.matching));
+    }
+    if let Some(path) = &args.plan_path {
+        runner = runner.with_plan(journal::decode_plan(&std::fs::read(path)?)?);
+    }
+
+    // Nothing is rendered, but the streams are drained so they don't accumulate
+    let updates = runner.stream_updates().expect("update stream");
+    let alerts = runner.stream_alerts().expect("alert stream");
+    let drain = futures::stream::select(updates.map(|_| ()), alerts.map(|_| ())).collect::<()>();
+    let run = runner.run_with_defaults();
+    drop(runner);
+
+    let (report, _) = futures::join!(run, drain);
+    let report = report?;
+    if let Some(path) = &args.export_plan_path {
+        journal::write_plan(
+            BufWriter::new(std::fs::File::create(path)?),
+            &report.flight_plan(),
+        )?;
+    }
+    if let Some(path) = &args.orders_csv_path {
+        std::fs::write(path, report.orders_csv())?;
+    }
+    if let Some((path, format)) = &args.summary {
+        std::fs::write(path, report.summary(*format, args.units))?;
+    }
+    match args.json {
+        true => println!("{:#}", report.to_json()),
+        false => print!("{}", report.display(args.units)),
+    }
+
+    Ok(())
+}
+
+/// Asks a running server to swap the scheduler used by its simulation
+async fn swap_scheduler(
+    mut args: impl Iterator<Item = String>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut scheduler = None;
+    let mut server_uri = default_server_uri();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--server" => server_uri = args.next().ok_or("missing value for --server")?,
+            _ if scheduler.is_none() => scheduler = Some(arg),
+            _ => return Err(format!("unrecognized argument: {}", arg).into()),
+        }
+    }
+    let scheduler = scheduler.ok_or("missing scheduler name")?;
+
+    let mut client = ServerClient::connect(server_uri).await?;
+    client
+        .swap_scheduler(SwapSchedulerRequest {
+            scheduler: scheduler.clone(),
+        })
+        .await?;
+    println!("swapped scheduler for {}", scheduler);
+
+    Ok(())
+}
+
+/// Asks a running server which flights it would launch for some hypothetical orders
+async fn preview_schedule(
+    mut args: impl Iterator<Item = String>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut destinations = vec![];
+    let mut priority = Priority::Emergency;
+    let mut server_uri = default_server_uri();
+    let mut units = Units::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--server" => server_uri = args.next().ok_or("missing value for --server")?,
+            "--resupply" => priority = Priority::Resupply,
+            "--units" => units = args.next().ok_or("missing value for --units")?.parse()?,
+            _ => destinations.push(arg),
+        }
+    }
+
+    let orders = destinations
+        .iter()
+        .map(|destination| {
+            Order {
+                time: 0, // Placed at the server's current time
+                destination: DestinationId::from(destination.as_str()),
+                priority,
+                ..Default::default()
+            }
+            .into_proto()
+        })
+        .collect();
+
+    let mut client = ServerClient::connect(server_uri).await?;
+    let preview = client
+        .preview_schedule(PreviewScheduleRequest { orders })
+        .await?
+        .into_inner();
+
+    let launch_time = preview
+        .time
+        .and_then(time::from_proto_timestamp)
+        .ok_or("invalid preview time")?;
+    println!("flights launched at {}:", units.format_time(launch_time));
+    for flight in preview.flights {
+        let flight = Flight::try_from_proto(flight)
+            .map_err(|e| format!("invalid flight in preview: {}", e))?;
+        let stops = flight
+            .orders
+            .iter()
+            .map(|order| format!("{} ({})", order.destination, order.priority))
+            .collect::<Vec<_>>();
+        println!("  carrier {}: {}", flight.carrier, stops.join(" -> "));
+    }
+
+    Ok(())
+}
+
+/// Asks a running server to add or deactivate destinations in its simulation
+async fn update_destinations(
+    mut args: impl Iterator<Item = String>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut changes = DestinationChanges::default();
+    let mut server_uri = default_server_uri();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--server" => server_uri = args.next().ok_or("missing value for --server")?,
+            "--add" => changes
+                .added
+                .push(args.next().ok_or("missing value for --add")?.parse()?),
+            "--deactivate" => changes.deactivated.push(DestinationId::from(
+                args.next()
+                    .ok_or("missing value for --deactivate")?
+                    .as_str(),
+            )),
+            _ => return Err(format!("unrecognized argument: {}", arg).into()),
+        }
+    }
+    if changes.added.is_empty() && changes.deactivated.is_empty() {
+        return Err("nothing to add or deactivate".into());
+    }
+
+    let (added, deactivated) = (changes.added.len(), changes.deactivated.len());
+    let mut client = ServerClient::connect(server_uri).await?;
+    let changes: schema::proto::server::DestinationChanges = changes.into_proto();
+    client.update_destinations(changes).await?;
+    println!("added {} & deactivated {} destinations", added, deactivated);
+
+    Ok(())
+}
+
+/// Asks a running server to add or retire carriers
+async fn set_fleet_size(
+    mut args: impl Iterator<Item = String>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut carriers = None;
+    let mut server_uri = default_server_uri();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--server" => server_uri = args.next().ok_or("missing value for --server")?,
+            _ if carriers.is_none() => {
+                carriers = Some(
+                    arg.parse::<u32>()
+                        .map_err(|_| format!("invalid number of carriers: {}", arg))?,
+                )
+            }
+            _ => return Err(format!("unrecognized argument: {}", arg).into()),
+        }
+    }
+    let carriers = carriers.ok_or("missing number of carriers")?;
+
+    let mut client = ServerClient::connect(server_uri).await?;
+    let resized = client
+        .set_fleet_size(SetFleetSizeRequest { carriers })
+        .await?
+        .into_inner();
+    match resized.carriers == carriers {
+        true => println!("resized the fleet to {} carriers", resized.carriers),
+        false => println!(
+            "resized the fleet to {} carriers, as the rest are in flight",
+            resized.carriers
+        ),
+    }
+
+    Ok(())
+}
+
+/// Asks a running server to simulate network delay for its subscribers
+async fn set_network_conditions(
+    mut args: impl Iterator<Item = String>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut delay_ms = None;
+    let mut jitter_ms = 0;
+    let mut server_uri = default_server_uri();
+
+    let millis = |arg: &str| {
+        arg.parse::<u64>()
+            .map_err(|_| format!("invalid number of milliseconds: {}", arg))
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--jitter" => jitter_ms = millis(&args.next().ok_or("missing value for --jitter")?)?,
+            "--server" => server_uri = args.next().ok_or("missing value for --server")?,
+            _ if delay_ms.is_none() => delay_ms = Some(millis(&arg)?),
+            _ => return Err(format!("unrecognized argument: {}", arg).into()),
+        }
+    }
+    let delay_ms = delay_ms.ok_or("missing delay")?;
+
+    let mut client = ServerClient::connect(server_uri).await?;
+    client
+        .set_network_conditions(NetworkConditions {
+            delay: Some(Duration::from_millis(delay_ms).try_into()?),
+            jitter: Some(Duration::from_millis(jitter_ms).try_into()?),
+        })
+        .await?;
+    match delay_ms == 0 && jitter_ms == 0 {
+        true => println!("stopped simulating network delay"),
+        false => println!(
+            "simulating {}ms of network delay with up to {}ms of jitter",
+            delay_ms, jitter_ms
+        ),
+    }
+
+    Ok(())
+}
+
+/// Asks a running server who's subscribed to its streams & how they're keeping up
+async fn list_subscribers(
+    mut args: impl Iterator<Item = String>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut server_uri = default_server_uri();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--server" => server_uri = args.next().ok_or("missing value for --server")?,
+            _ => return Err(format!("unrecognized argument: {}", arg).into()),
+        }
+    }
+
+    let mut client = ServerClient::connect(server_uri).await?;
+    let subscribers = client.list_subscribers(()).await?.into_inner().subscribers;
+    println!("{} subscribers", subscribers.len());
+    for subscriber in subscribers {
+        let [connected, lag] = [subscriber.connected, subscriber.lag].map(|duration| {
+            duration
+                .and_then(|duration| Duration::try_from(duration).ok())
+                .unwrap_or_default()
+        });
+        let client = match subscriber.client_name.is_empty() {
+            true => "anonymous client".to_string(),
+            false => format!("{} {}", subscriber.client_name, subscriber.client_version),
+        };
+        println!(
+            "  {} ({}, {}): connected for {}s, {} events waiting ({}ms behind), {} updates dropped",
+            subscriber.id,
+            subscriber.stream,
+            client.trim_end(),
+            connected.as_secs(),
+            subscriber.waiting,
+            lag.as_millis(),
+            subscriber.dropped_updates
+        );
+    }
+
+    Ok(())
+}
+
+/// Prints the records logged by a running server as they're logged
+async fn stream_logs(
+    mut args: impl Iterator<Item = String>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut server_uri = default_server_uri();
+    let mut request = StreamLogsRequest::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--level" => {
+                let level = args.next().ok_or("missing value for --level")?;
+                request.level = match level.as_str() {
+                    "error" => LogLevel::Error,
+                    "warn" => LogLevel::Warning,
+                    "info" => LogLevel::Info,
+                    "debug" => LogLevel::Debug,
+                    "trace" => LogLevel::Trace,
+                    _ => return Err(format!("invalid log level: {}", level).into()),
+                } as i32;
+            }
+            "--target" => request.target = args.next().ok_or("missing value for --target")?,
+            "--server" => server_uri = args.next().ok_or("missing value for --server")?,
+            _ => return Err(format!("unrecognized argument: {}", arg).into()),
+        }
+    }
+
+    let mut client = ServerClient::connect(server_uri).await?;
+    let mut events = client.stream_logs(request).await?.into_inner();
+    while let Some(event) = events.message().await? {
+        let time = event
+            .time
+            .clone()
+            .and_then(|time| SystemTime::try_from(time).ok())
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default();
+        let level = match event.level() {
+            LogLevel::Error => "ERROR",
+            LogLevel::Warning => "WARN",
+            LogLevel::Info => "INFO",
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Trace => "TRACE",
+        };
+        println!(
+            "{} {:<5} {}: {}",
+            time::format_short(time.as_secs()),
+            level,
+            event.target,
+            event.message
+        );
+    }
+
+    Ok(())
+}
+
+/// Asks a running server to stop taking orders & finish once its flights have landed
+async fn drain(mut args: impl Iterator<Item = String>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut server_uri = default_server_uri();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--server" => server_uri = args.next().ok_or("missing value for --server")?,
+            _ => return Err(format!("unrecognized argument: {}", arg).into()),
+        }
+    }
+
+    let mut client = ServerClient::connect(server_uri).await?;
+    let drained = client.drain(()).await?.into_inner();
+    println!(
+        "draining, the simulation finishes once {} flights have landed",
+        drained.flights
+    );
+
+    Ok(())
+}
+
+/// Approves a flight a running server is holding on the ground for approval
+async fn approve_flight(
+    mut args: impl Iterator<Item = String>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut flight_id = None;
+    let mut server_uri = default_server_uri();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--server" => server_uri = args.next().ok_or("missing value for --server")?,
+            _ if flight_id.is_none() => flight_id = Some(arg),
+            _ => return Err(format!("unrecognized argument: {}", arg).into()),
+        }
+    }
+    let flight_id = flight_id.ok_or("missing flight id")?;
+
+    let mut client = ServerClient::connect(server_uri).await?;
+    client
+        .approve_flight(ApproveFlightRequest {
+            flight_id: flight_id.clone(),
+        })
+        .await?;
+    println!(
+        "approved flight {}, it launches at the next minute",
+        flight_id
+    );
+
+    Ok(())
+}
+
+/// Asks a running server for its scenario's destinations & demand priors
+async fn get_scenario(
+    mut args: impl Iterator<Item = String>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut server_uri = default_server_uri();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--server" => server_uri = args.next().ok_or("missing value for --server")?,
+            _ => return Err(format!("unrecognized argument: {}", arg).into()),
+        }
+    }
+
+    let mut client = ServerClient::connect(server_uri).await?;
+    let scenario = client.get_scenario(()).await?.into_inner();
+    let priors = scenario
+        .demand_priors
+        .into_iter()
+        .map(DemandPrior::try_from_proto)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("invalid demand prior: {}", e))?;
+
+    for destination in scenario.destinations {
+        let destination = Destination::try_from_proto(destination)
+            .map_err(|e| format!("invalid destination: {}", e))?;
+        let expected = |priority| {
+            priors
+                .iter()
+                .filter(|prior| prior.destination == destination.name && prior.priority == priority)
+                .fold(0.0, |total, prior| total + prior.orders_per_day)
+        };
+        println!(
+            "{} ({}, {}): {:.1} emergencies & {:.1} resupplies expected each day",
+            destination.name,
+            destination.north_m,
+            destination.east_m,
+            expected(Priority::Emergency),
+            expected(Priority::Resupply)
+        );
+    }
+    Ok(())
+}
+
+/// Asks a running server for the lifecycle of one of its orders
+async fn get_order(
+    mut args: impl Iterator<Item = String>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut order_id = None;
+    let mut server_uri = default_server_uri();
+    let mut units = Units::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--server" => server_uri = args.next().ok_or("missing value for --server")?,
+            "--units" => units = args.next().ok_or("missing value for --units")?.parse()?,
+            _ if order_id.is_none() => {
+                order_id = Some(
+                    arg.parse::<u64>()
+                        .map_err(|_| format!("invalid order id: {}", arg))?,
+                )
+            }
+            _ => return Err(format!("unrecognized argument: {}", arg).into()),
+        }
+    }
+    let order_id = order_id.ok_or("missing order id")?;
+
+    let mut client = ServerClient::connect(server_uri).await?;
+    let receipt = client
+        .get_order(GetOrderRequest { order_id })
+        .await?
+        .into_inner();
+    let receipt = OrderReceipt::try_from_proto(receipt)
+        .map_err(|e| format!("invalid order receipt: {}", e))?;
+
+    let order = &receipt.order;
+    println!(
+        "order {} for {} ({}): {:?}",
+        order.id, order.destination, order.priority, receipt.status
+    );
+    println!("  queued at {}", units.format_time(receipt.queued_at));
+    if order.quantity > 1 {
+        println!("  {} packages", order.quantity);
+    }
+    for shipment in &receipt.shipments {
+        println!(
+            "  {} packages on carrier {} (flight {}), launching at {} & delivering at {}",
+            shipment.packages,
+            shipment.carrier,
+            shipment.flight,
+            units.format_time(shipment.launch_time),
+            units.format_time(shipment.delivery_time)
+        );
+    }
+    if let (Some(carrier), Some(launch_time)) = (receipt.carrier, receipt.launch_time) {
+        let launch = match receipt.status {
+            OrderStatus::Scheduled => "launching",
+            _ => "launched",
+        };
+        println!(
+            "  carrier {} {} at {}",
+            carrier,
+            launch,
+            units.format_time(launch_time)
+        );
+        if let Some(flight) = receipt.flight {
+            println!("  flight {}", flight);
+        }
+    }
+    if let (Some(wait_s), Some(marginal_m)) = (receipt.wait_s, receipt.marginal_distance_m) {
+        println!(
+            "  waited {} to launch, adding {} to its flights",
+            schema::units::format_duration(wait_s),
+            units.format_distance(marginal_m as f64)
+        );
+    }
+    if let Some(delivery_time) = receipt.delivery_time {
+        let delivery = match receipt.status {
+            OrderStatus::Delivered => "delivered",
+            _ => "delivering",
+        };
+        println!("  {} at {}", delivery, units.format_time(delivery_time));
+    }
+    if let Some(proof) = &receipt.proof {
+        println!(
+            "  dropped at {}m north, {}m east (recipient code {}, photo {})",
+            proof.drop_north_m, proof.drop_east_m, proof.recipient_code, proof.photo_hash
+        );
+    }
+    if let Some(failure) = &receipt.failure {
+        println!("  failed: {}", failure);
+    }
+
+    Ok(())
+}
+
+/// Asks a running server for the decisions behind one of its flights
+async fn explain_flight(
+    mut args: impl Iterator<Item = String>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut flight_id = None;
+    let mut server_uri = default_server_uri();
+    let mut units = Units::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--server" => server_uri = args.next().ok_or("missing value for --server")?,
+            "--units" => units = args.next().ok_or("missing value for --units")?.parse()?,
+            _ if flight_id.is_none() => flight_id = Some(arg),
+            _ => return Err(format!("unrecognized argument: {}", arg).into()),
+        }
+    }
+    let flight_id = flight_id.ok_or("missing flight id")?;
+
+    let mut client = ServerClient::connect(server_uri).await?;
+    let explanation = client
+        .explain_flight(ExplainFlightRequest { flight_id })
+        .await?
+        .into_inner();
+    let explanation = FlightExplanation::try_from_proto(explanation)
+        .map_err(|e| format!("invalid flight explanation: {}", e))?;
+
+    println!(
+        "flight {} on carrier {} ({:?}), packed at {}",
+        explanation.flight,
+        explanation.carrier,
+        explanation.mode,
+        units.format_time(explanation.packed_at)
+    );
+    println!(
+        "  {} orders queued, {} carriers available & {} held in reserve, {} orders per carrier",
+        explanation.queued,
+        explanation.available_carriers,
+        explanation.reserved_carriers,
+        explanation.capacity
+    );
+    for considered in &explanation.considered {
+        println!(
+            "  order {} for {} ({}): {}",
+            considered.order, considered.destination, considered.priority, considered.outcome
+        );
+    }
+    println!("  stopped: {}", explanation.stopped);
+
+    Ok(())
+}
+
+/// Compares the KPIs of two run reports
+fn compare(mut args: impl Iterator<Item = String>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut paths = vec![];
+    let mut json = false;
+    let mut max_regression = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--max-regression" => {
+                let value = args.next().ok_or("missing value for --max-regression")?;
+                max_regression = Some(
+                    value
+                        .trim_end_matches('%')
+                        .parse::<f64>()
+                        .map_err(|_| format!("invalid percentage: {}", value))?,
+                );
+            }
+            _ => paths.push(arg),
+        }
+    }
+    let [baseline, candidate] = <[String; 2]>::try_from(paths)
+        .map_err(|_| "expected the paths of a baseline & a candidate report")?;
+
+    let read = |path: &str| -> Result<RunReport, Box<dyn Error + Send + Sync>> {
+        let value = json::Value::parse(&std::fs::read_to_string(path)?)?;
+        RunReport::from_json(&value).map_err(|e| format!("{}: {}", path, e).into())
+    };
+    let comparison = Comparison::new(&read(&baseline)?, &read(&candidate)?);
+
+    let tolerance = max_regression.unwrap_or_default();
+    match json {
+        true => println!("{:#}", comparison.to_json(tolerance)),
+        false => print!("{}", comparison),
+    }
+
+    if let Some(tolerance) = max_regression {
+        let regressions = comparison
+            .regressions(tolerance)
+            .map(|kpi| kpi.name)
+            .collect::<Vec<_>>();
+        if !regressions.is_empty() {
+            eprintln!(
+                "regressed by more than {}%: {}",
+                tolerance,
+                regressions.join(", ")
+            );
+            std::process::exit(1);
+        }
+    }
+
+    Ok(())
+}
+
+/// Simulates a scenario across a grid of parameters
+async fn sweep(mut args: impl Iterator<Item = String>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut destinations_csv_path = schema::SAMPLE_DESTINATIONS_CSV_PATH.to_string();
+    let mut orders_csv_path = schema::SAMPLE_ORDERS_CSV_PATH.to_string();
+    let mut carriers = None;
+    let mut carrier_speeds_mps = None;
+    let mut schedulers = None;
+    let mut jobs = None;
+    let mut output = None;
+
+    fn list<T: std::str::FromStr>(value: &str) -> Result<Vec<T>, String> {
+        value
+            .split(',')
+            .map(|item| {
+                item.trim()
+                    .parse()
+                    .map_err(|_| format!("invalid value: {}", item))
+            })
+            .collect()
+    }
+
+    while let Some(arg) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("missing value for {}", arg))?;
+        match arg.as_str() {
+            "--destinations" => destinations_csv_path = value,
+            "--orders" => orders_csv_path = value,
+            "--carriers" => carriers = Some(list(&value)?),
+            "--carrier-speeds" => carrier_speeds_mps = Some(list(&value)?),
+            "--schedulers" => schedulers = Some(list(&value)?),
+            "--jobs" => jobs = Some(value.parse().map_err(|_| "invalid number of jobs")?),
+            "--output" => output = Some(value),
+            _ => return Err(format!("unrecognized argument: {}", arg).into()),
+        }
+    }
+
+    let mut sweep = Sweep::new(
+        Destination::from_csv(&destinations_csv_path).await?,
+        load_orders(&orders_csv_path).await?,
+    );
+    if let Some(carriers) = carriers {
+        sweep = sweep.with_carriers(carriers);
+    }
+    if let Some(carrier_speeds_mps) = carrier_speeds_mps {
+        sweep = sweep.with_carrier_speeds_mps(carrier_speeds_mps);
+    }
+    if let Some(schedulers) = schedulers {
+        sweep = sweep.with_schedulers(schedulers);
+    }
+    if let Some(jobs) = jobs {
+        sweep = sweep.with_jobs(jobs);
+    }
+
+    let results = tokio::task::spawn_blocking(move || sweep.run()).await?;
+    let csv = sweep_csv(&results);
+    match output {
+        Some(path) => std::fs::write(path, csv)?,
+        None => print!("{}", csv),
+    }
+
+    Ok(())
+}
+
+/// Converts orders between CSV & the binary format, whichever the input isn't in
+async fn convert(
+    mut args: impl Iterator<Item = String>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let (Some(input), Some(output), None) = (args.next(), args.next(), args.next()) else {
+        return Err("expected an input & an output path".into());
+    };
+
+    let file = std::fs::File::create(&output)?;
+    let count = match binary::is_binary(&input) {
+        true => {
+            let mut writer = BufWriter::new(file);
+            let mut count = 0;
+            for order in OrdersFile::open(&input)?.orders()? {
+                writeln!(writer, "{}", order?.to_csv_line())?;
+                count += 1;
+            }
+            writer.flush()?;
+            count
+        }
+        false => {
+            let orders = Order::from_csv(&input).await?;
+            binary::write_orders(file, &orders)?;
+            orders.len()
+        }
+    };
+    eprintln!("converted {} orders from {} to {}", count, input, output);
+
+    Ok(())
+}
+
+/// Diffs or merges variants of a scenario's destinations or orders
+async fn scenario(
+    mut args: impl Iterator<Item = String>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let command = args.next().ok_or("expected `diff` or `merge`")?;
+    let kind = args.next().ok_or("expected `destinations` or `orders`")?;
+    let mut paths = vec![];
+    let mut json = false;
+    let mut output = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--output" => output = Some(args.next().ok_or("missing value for --output")?),
+            _ => paths.push(arg),
+        }
+    }
+
+    match kind.as_str() {
+        "destinations" => {
+            let mut files = vec![];
+            for path in &paths {
+                files.push(Destination::from_csv(path).await?);
+            }
+            scenario_files(&command, &paths, files, json, output)
+        }
+        "orders" => {
+            let mut files = vec![];
+            for path in &paths {
+                files.push(load_orders(path).await?);
+            }
+            scenario_files(&command, &paths, files, json, output)
+        }
+        _ => Err(format!("unrecognized scenario file: {}", kind).into()),
+    }
+}
+
+fn scenario_files<T: scenario::Entry>(
+    command: &str,
+    paths: &[String],
+    mut files: Vec<Vec<T>>,
+    json: bool,
+    output: Option<String>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    match command {
+        "diff" => {
+            let [base, variant] = <[Vec<T>; 2]>::try_from(files)
+                .map_err(|_| "expected the paths of a base & a variant")?;
+            let diff = scenario::Diff::new(&base, &variant);
+            match json {
+                true => println!("{:#}", diff.to_json()),
+                false => {
+                    print!("{}", diff);
+                    eprintln!(
+                        "{} added, {} removed, {} changed",
+                        diff.added.len(),
+                        diff.removed.len(),
+                        diff.changed.len()
+                    );
+                }
+            }
+        }
+        "merge" => {
+            if files.len() < 2 {
+                return Err("expected the paths of a base & at least one variant".into());
+            }
+            let base = files.remove(0);
+            let variants = paths[1..]
+                .iter()
+                .map(String::as_str)
+                .zip(files)
+                .collect::<Vec<_>>();
+            let merged = scenario::merge(&base, &variants)?;
+            let mut writer: Box<dyn Write> = match output {
+                Some(path) => Box::new(BufWriter::new(std::fs::File::create(path)?)),
+                None => Box::new(std::io::stdout().lock()),
+            };
+            for entry in &merged {
+                writeln!(writer, "{}", entry.to_csv_line())?;
+            }
+            writer.flush()?;
+            eprintln!(
+                "merged {} variants into {} entries",
+                variants.len(),
+                merged.len()
+            );
+        }
+        _ => return Err(format!("unrecognized scenario command: {}", command).into()),
+    }
+
+    Ok(())
+}
+
+/// Loads orders from either a CSV or binary orders file
+async fn load_orders(path: &str) -> Result<Vec<Order>, Box<dyn Error + Send + Sync>> {
+    match binary::is_binary(path) {
+        true => Ok(OrdersFile::open(path)?.read_all()?),
+        false => Order::from_csv(path).await,
+    }
+}
+
+fn seconds(value: &str) -> Result<u64, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid number of seconds: {}", value))
+}
+
+fn default_server_uri() -> String {
+    env::var("SERVER_URI").unwrap_or_else(|_| "http://localhost:50051".to_string())
+}
+
+/// Reads newline-delimited JSON orders from stdin until it is closed
+async fn read_orders(orders: mpsc::UnboundedSender<Order>) {
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+
+    loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => break,
+            Err(e) => {
+                log::error!("failed to read stdin: {}", e);
+                break;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        // Piped in by whoever runs the simulation, so their names are resolved as the run reaches them
+        let order = json::Value::parse(&line)
+            .and_then(|value| Order::from_json(&value, |name| Ok(DestinationId::intern(name))));
+        match order {
+            Ok(order) => {
+                if orders.unbounded_send(order).is_err() {
+                    break;
+                }
+            }
+            Err(e) => log::error!("invalid order `{}`: {}", line, e),
+        }
+    }
+}
+
+/// Returns the current timestamp in seconds
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since_epoch| since_epoch.as_secs())
+        .unwrap_or_default()
+}
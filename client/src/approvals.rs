@@ -0,0 +1,98 @@
+use iced::widget::{button, column, row, text};
+use iced::{Alignment, Color, Element};
+use schema::{Alert, AlertKind, FlightId, StatusUpdate};
+
+use super::Message;
+
+/// Emergency flights the server is holding on the ground until they're approved, as raised by
+/// its approval pending alerts
+#[derive(Default)]
+pub struct Approvals {
+    pending: Vec<Alert>,
+    /// Flight most recently approved, or why it couldn't be
+    status: Option<Result<FlightId, String>>,
+}
+
+impl Approvals {
+    pub fn alert(&mut self, alert: &Alert) {
+        let Some(flight) = alert
+            .flight
+            .filter(|_| alert.kind == AlertKind::ApprovalPending)
+        else {
+            return;
+        };
+        if !self
+            .pending
+            .iter()
+            .any(|pending| pending.flight == Some(flight))
+        {
+            self.pending.push(alert.clone());
+        }
+    }
+
+    /// Forgets the flights which have launched or are gone, whether approved here or elsewhere
+    pub fn observe(&mut self, update: &StatusUpdate) {
+        self.pending.retain(|alert| {
+            update
+                .flights
+                .iter()
+                .any(|flight| Some(flight.id) == alert.flight && flight.launch_time > update.time)
+        });
+    }
+
+    pub fn approved(&mut self, flight: FlightId, result: Result<(), String>) {
+        if result.is_ok() {
+            self.pending.retain(|alert| alert.flight != Some(flight));
+        }
+        self.status = Some(result.map(|_| flight));
+    }
+
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        self.status = None;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty() && self.status.is_none()
+    }
+}
+
+/// Admin control approving the emergency flights held on the ground, which launch at the next
+/// minute once approved
+pub fn view(approvals: &Approvals) -> Element<'_, Message> {
+    let mut content = column(
+        approvals
+            .pending
+            .iter()
+            .filter_map(|alert| {
+                let flight = alert.flight?;
+                Some(
+                    row![
+                        text(&alert.message).size(14),
+                        button(text("Approve").size(14)).on_press(Message::ApproveFlight(flight)),
+                    ]
+                    .spacing(10)
+                    .align_items(Alignment::Center)
+                    .into(),
+                )
+            })
+            .collect(),
+    )
+    .spacing(5)
+    .padding([0, 10]);
+
+    match &approvals.status {
+        Some(Ok(flight)) => {
+            content = content.push(text(format!("Approved flight {}", flight)).size(14))
+        }
+        Some(Err(e)) => {
+            content = content.push(
+                text(format!("Approval error: {}", e))
+                    .size(14)
+                    .style(Color::from_rgb8(200, 0, 0)),
+            )
+        }
+        None => {}
+    }
+    content.into()
+}
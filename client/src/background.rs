@@ -0,0 +1,91 @@
+use std::fs::File;
+
+use iced::Color;
+
+use crate::config::BackgroundConfig;
+
+/// Most cells the background is drawn with along its longest side, as the canvas draws it as a
+/// grid of filled cells rather than as an image
+const MAX_CELLS: u32 = 160;
+
+/// Image drawn beneath the map, e.g. a site map or satellite snapshot, downsampled to a grid of
+/// cells which are placed on the map by the scenario coordinates of the image's corners
+pub struct Background {
+    pub config: BackgroundConfig,
+    /// Number of columns & rows of cells
+    pub columns: u32,
+    pub rows: u32,
+    /// Average color of each cell, row by row from the top (north west) corner
+    pub cells: Vec<Color>,
+}
+
+impl Background {
+    /// Loads & downsamples the PNG image the config points to
+    pub fn load(config: &BackgroundConfig) -> Result<Self, String> {
+        let invalid = |e: png::DecodingError| format!("invalid image {}: {}", config.path, e);
+        let file = File::open(&config.path)
+            .map_err(|e| format!("failed to open {}: {}", config.path, e))?;
+        let mut decoder = png::Decoder::new(file);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(invalid)?;
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).map_err(invalid)?;
+        let channels = match info.color_type {
+            png::ColorType::Grayscale => 1,
+            png::ColorType::GrayscaleAlpha => 2,
+            png::ColorType::Rgb => 3,
+            png::ColorType::Rgba => 4,
+            png::ColorType::Indexed => return Err(format!("unsupported image {}", config.path)),
+        };
+        let pixel = |x: u32, y: u32| {
+            let i = (y as usize * info.line_size) + x as usize * channels;
+            let p = &pixels[i..i + channels];
+            match channels {
+                1 => [p[0], p[0], p[0], 255],
+                2 => [p[0], p[0], p[0], p[1]],
+                3 => [p[0], p[1], p[2], 255],
+                _ => [p[0], p[1], p[2], p[3]],
+            }
+        };
+
+        let (width, height) = (info.width, info.height);
+        let cell = width.max(height).div_ceil(MAX_CELLS).max(1);
+        let (columns, rows) = (width.div_ceil(cell), height.div_ceil(cell));
+        let mut cells = Vec::with_capacity((columns * rows) as usize);
+        for row in 0..rows {
+            for column in 0..columns {
+                let (mut sum, mut count) = ([0u32; 4], 0);
+                for y in row * cell..((row + 1) * cell).min(height) {
+                    for x in column * cell..((column + 1) * cell).min(width) {
+                        for (total, channel) in sum.iter_mut().zip(pixel(x, y)) {
+                            *total += channel as u32;
+                        }
+                        count += 1;
+                    }
+                }
+                let [r, g, b, a] = sum.map(|total| (total / count) as u8);
+                cells.push(Color::from_rgba8(
+                    r,
+                    g,
+                    b,
+                    a as f32 / 255.0 * config.opacity,
+                ));
+            }
+        }
+
+        log::info!(
+            "loaded background {} ({}x{}, drawn as {}x{} cells)",
+            config.path,
+            width,
+            height,
+            columns,
+            rows
+        );
+        Ok(Self {
+            config: config.clone(),
+            columns,
+            rows,
+            cells,
+        })
+    }
+}
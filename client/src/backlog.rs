@@ -0,0 +1,36 @@
+use iced::widget::{container, row, text};
+use iced::{theme, Color, Element, Length};
+use schema::units;
+use schema::Backlog;
+
+use super::Message;
+
+/// Wait after which the oldest order in the backlog is highlighted, in __seconds__
+const LONG_WAIT_S: u64 = 30 * 60;
+
+/// Orders waiting to be scheduled by priority, & how long the oldest has waited. Nothing is shown
+/// for servers which don't report their backlog.
+pub fn view<'a>(backlog: Option<&Backlog>, current_time: u64) -> Element<'a, Message> {
+    let Some(backlog) = backlog else {
+        return row![].into();
+    };
+
+    let mut content = row![
+        text("Backlog").size(14),
+        text(format!("{} emergencies", backlog.emergencies)).size(14),
+        text(format!("{} resupplies", backlog.resupplies)).size(14),
+    ]
+    .spacing(20);
+    if let Some(wait_s) = backlog.oldest_wait_s(current_time) {
+        let wait = text(format!("oldest waiting {}", units::format_duration(wait_s))).size(14);
+        content = content.push(match wait_s >= LONG_WAIT_S {
+            true => wait.style(Color::from_rgb8(230, 120, 0)),
+            false => wait,
+        });
+    }
+
+    container(content.padding([5, 10]))
+        .width(Length::Fill)
+        .style(theme::Container::Box)
+        .into()
+}
@@ -0,0 +1,47 @@
+use iced::widget::{button, column, container, horizontal_space, row, text};
+use iced::{theme, Alignment, Color, Element, Length};
+use schema::{Alert, AlertKind};
+
+use super::Message;
+
+/// Maximum number of alert banners displayed at once, older alerts are dropped
+pub const MAX_BANNERS: usize = 5;
+
+pub fn view<'a>(alerts: &[Alert]) -> Element<'a, Message> {
+    column(
+        alerts
+            .iter()
+            .enumerate()
+            .map(|(i, alert)| {
+                let color = match alert.kind {
+                    AlertKind::EmergencyWaiting
+                    | AlertKind::DeliveryWindowMissed
+                    | AlertKind::RuleTriggered
+                    | AlertKind::ApprovalPending => Color::from_rgb8(230, 120, 0),
+                    AlertKind::CarrierFailure | AlertKind::SloViolated => {
+                        Color::from_rgb8(200, 0, 0)
+                    }
+                    AlertKind::LaunchHeld | AlertKind::SurgeEnded => Color::from_rgb8(0, 110, 200),
+                    AlertKind::SurgeStarted | AlertKind::SimulationFaulted => {
+                        Color::from_rgb8(200, 0, 0)
+                    }
+                };
+
+                container(
+                    row![
+                        text(&alert.message).style(color),
+                        horizontal_space(Length::Fill),
+                        button(text("Dismiss").size(14)).on_press(Message::DismissAlert(i)),
+                    ]
+                    .align_items(Alignment::Center)
+                    .padding(5),
+                )
+                .width(Length::Fill)
+                .style(theme::Container::Box)
+                .into()
+            })
+            .collect(),
+    )
+    .spacing(5)
+    .into()
+}
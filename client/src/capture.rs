@@ -0,0 +1,157 @@
+use std::time::{Duration, Instant};
+
+use iced::widget::{button, row, text, text_input};
+use iced::window::Screenshot;
+use iced::{Alignment, Color, Element, Length, Rectangle};
+use schema::time;
+
+use super::Message;
+use crate::gif;
+
+/// Wall-clock time between the frames of a recording
+const FRAME_INTERVAL: Duration = Duration::from_millis(200);
+/// Most frames a recording may hold, after which it's saved
+const MAX_FRAMES: usize = 600;
+
+/// A recording of the map in progress, saved as an animated GIF once it's finished
+pub struct Recording {
+    /// Simulated time the recording started at, in __seconds__
+    started_at: u64,
+    /// Simulated time the recording finishes at, unless it's stopped first
+    pub until: Option<u64>,
+    /// Size of the frames, taken from the first
+    size: Option<(u32, u32)>,
+    /// Frames captured so far, quantized to the GIF palette
+    frames: Vec<Vec<u8>>,
+    last_frame: Option<Instant>,
+}
+
+impl Recording {
+    pub fn new(started_at: u64, minutes: Option<u64>) -> Self {
+        Self {
+            started_at,
+            until: minutes.map(|minutes| started_at + minutes * 60),
+            size: None,
+            frames: vec![],
+            last_frame: None,
+        }
+    }
+
+    /// Whether it's time to capture another frame
+    pub fn frame_due(&self) -> bool {
+        self.last_frame
+            .is_none_or(|last| last.elapsed() >= FRAME_INTERVAL)
+    }
+
+    /// Marks a frame as requested, so that another isn't requested until the next is due
+    pub fn requested_frame(&mut self) {
+        self.last_frame = Some(Instant::now());
+    }
+
+    /// Adds a captured frame, skipping any which don't match the size of the first, e.g. after
+    /// the window was resized. Returns whether the recording is full.
+    pub fn push(&mut self, frame: Screenshot) -> bool {
+        let size = (frame.size.width, frame.size.height);
+        if *self.size.get_or_insert(size) == size {
+            self.frames.push(gif::quantize(&frame.bytes));
+        }
+        self.frames.len() >= MAX_FRAMES
+    }
+
+    /// Encodes the recording as a GIF, returning the path it was written to
+    pub fn save(self, finished_at: u64) -> Result<String, String> {
+        let Some((width, height)) = self.size else {
+            return Err("no frames were recorded".to_string());
+        };
+        let path = format!("map-{}-{}.gif", self.started_at, finished_at);
+        let delay_cs = (FRAME_INTERVAL.as_millis() / 10) as u16;
+        let gif = gif::encode(width as u16, height as u16, &self.frames, delay_cs);
+        std::fs::write(&path, gif).map_err(|e| e.to_string())?;
+        Ok(path)
+    }
+}
+
+/// Crops a screenshot of the window to the map, given the map's bounds & the size of the window
+/// in logical pixels
+pub fn crop_to_map(
+    screenshot: Screenshot,
+    map_bounds: Option<Rectangle>,
+    window_size: (u32, u32),
+) -> Screenshot {
+    let Some(bounds) = map_bounds else {
+        return screenshot;
+    };
+    let scale = screenshot.size.width as f32 / window_size.0.max(1) as f32;
+    let region = Rectangle {
+        x: (bounds.x * scale).round() as u32,
+        y: (bounds.y * scale).round() as u32,
+        width: (bounds.width * scale).round() as u32,
+        height: (bounds.height * scale).round() as u32,
+    };
+    screenshot.crop(region).unwrap_or(screenshot)
+}
+
+/// Writes a screenshot as a PNG, returning the path it was written to
+pub fn save_png(screenshot: Screenshot, simulated_time: u64) -> Result<String, String> {
+    let path = format!("map-{}.png", simulated_time);
+    let file = std::fs::File::create(&path).map_err(|e| e.to_string())?;
+    let mut encoder = png::Encoder::new(
+        std::io::BufWriter::new(file),
+        screenshot.size.width,
+        screenshot.size.height,
+    );
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&screenshot.bytes))
+        .map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+/// Controls for saving a screenshot of the map, or recording it over a stretch of simulated time
+pub fn view<'a>(
+    minutes: &str,
+    recording: Option<&Recording>,
+    status: Option<&Result<String, String>>,
+) -> Element<'a, Message> {
+    let mut content = row![button(text("Screenshot")).on_press(Message::TakeScreenshot)]
+        .spacing(10)
+        .padding([0, 10])
+        .align_items(Alignment::Center);
+
+    content = match recording {
+        Some(recording) => content
+            .push(button(text("Stop recording")).on_press(Message::ToggleRecording))
+            .push(
+                text(match recording.until {
+                    Some(until) => format!(
+                        "Recording {} frames until {}",
+                        recording.frames.len(),
+                        time::format_short(until)
+                    ),
+                    None => format!("Recording {} frames", recording.frames.len()),
+                })
+                .size(14),
+            ),
+        None => content
+            .push(
+                text_input("Minutes to record, e.g. 30", minutes)
+                    .on_input(Message::RecordingMinutesChanged)
+                    .on_submit(Message::ToggleRecording)
+                    .width(Length::Fixed(200.0)),
+            )
+            .push(button(text("Record")).on_press(Message::ToggleRecording)),
+    };
+
+    match status {
+        Some(Ok(path)) => content.push(text(format!("Saved {}", path)).size(14)),
+        Some(Err(e)) => content.push(
+            text(format!("Capture error: {}", e))
+                .size(14)
+                .style(Color::from_rgb8(200, 0, 0)),
+        ),
+        None => content,
+    }
+    .into()
+}
@@ -1,46 +1,67 @@
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use iced::futures::channel::mpsc;
 use iced::futures::sink::SinkExt;
 use iced::futures::stream::{BoxStream, StreamExt};
 use iced::futures::{self, FutureExt};
 use iced::subscription::{self, Subscription};
-use tonic::transport::Channel;
+use tonic::transport::{Channel, Endpoint};
 use tonic::Status;
+use ulid::Ulid;
 
 use schema::proto::server::server_client::{self, ServerClient};
-use schema::{StatusUpdate, ToFromProto};
+use schema::proto::server::{ApproveFlightRequest, MonitorRequest, SetFleetSizeRequest};
+use schema::{
+    Alert, ClientConfig, ConversionError, DestinationChanges, FlightId, MonitorEvent, StatusUpdate,
+    ToFromProto,
+};
 
-type SchedulerClient = server_client::ServerClient<Channel>;
-type UpdatesStream = BoxStream<'static, StatusUpdate>;
+use crate::retry::{Backoff, RetryPolicy};
 
-const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+type SchedulerClient = server_client::ServerClient<Channel>;
+type UpdatesStream = BoxStream<'static, Event>;
 
-pub fn connect(server_uri: String) -> Subscription<Event> {
+/// Maintains a connection to the server, reconnecting whenever it is lost, backing off as the
+/// policy allows while the server can't be reached. A new connection is made whenever the URI
+/// changes, and the connection is closed once the subscription is dropped.
+pub fn connect(server_uri: String, policy: RetryPolicy) -> Subscription<Event> {
     struct Connect;
 
     subscription::channel(
-        std::any::TypeId::of::<Connect>(),
+        (std::any::TypeId::of::<Connect>(), server_uri.clone()),
         100,
-        |events| async move {
+        move |events| async move {
             let (sender, receiver) = mpsc::channel(100);
             let state = State::Disconnected {
                 receiver,
                 sender,
                 events,
                 server_uri,
+                backoff: Backoff::new(policy),
             };
 
             futures::stream::unfold(state, |state| async move {
                 match state {
                     mut state @ State::Disconnected { .. } => {
-                        match ServerClient::connect(state.server_uri().to_string()).await {
-                            Ok(client) => {
+                        let connected = match Endpoint::from_shared(state.server_uri().to_string())
+                        {
+                            Ok(endpoint) => {
+                                endpoint
+                                    .connect_timeout(policy.connect_timeout)
+                                    .connect()
+                                    .await
+                            }
+                            Err(e) => Err(e),
+                        };
+                        match connected {
+                            Ok(channel) => {
+                                state.backoff().succeeded();
                                 let _ = state
                                     .events()
                                     .send(Event::Connected(Client::Connected {
-                                        client: client.clone(),
+                                        client: ServerClient::new(channel),
                                         sender: state.sender(),
+                                        rpc_timeout: policy.rpc_timeout,
                                     }))
                                     .await;
 
@@ -48,8 +69,14 @@ pub fn connect(server_uri: String) -> Subscription<Event> {
                             }
                             Err(e) => {
                                 log::warn!("connection failed: {:?}", e);
-                                tokio::time::sleep(RECONNECT_DELAY).await;
-                                let _ = state.events().send(Event::Disconnected).await;
+                                let _ =
+                                    state.events().send(Event::Disconnected(describe(&e))).await;
+                                let delay = state.backoff().failed();
+                                if state.backoff().is_open() {
+                                    let until = Instant::now() + delay;
+                                    let _ = state.events().send(Event::Backoff { until }).await;
+                                }
+                                tokio::time::sleep(delay).await;
 
                                 Some(((), state.disconnected()))
                             }
@@ -61,17 +88,17 @@ pub fn connect(server_uri: String) -> Subscription<Event> {
                         sender,
                         events,
                         server_uri,
-                        ..
+                        backoff,
                     } => match receiver.next().await {
                         Some(connection) => {
                             log::info!("subscribed");
                             connection
-                                .map(|update| {
-                                    log::info!("received status update");
+                                .map(|event| {
+                                    log::info!("received monitor event");
                                     let mut events = events.clone();
 
                                     async move {
-                                        let _ = events.send(Event::StatusUpdate(update)).await;
+                                        let _ = events.send(event).await;
                                     }
                                 })
                                 .buffered(1)
@@ -79,6 +106,12 @@ pub fn connect(server_uri: String) -> Subscription<Event> {
                                 .await;
 
                             log::info!("disconnected");
+                            let _ = events
+                                .clone()
+                                .send(Event::Disconnected(
+                                    "lost connection to the server".to_string(),
+                                ))
+                                .await;
                             Some((
                                 (),
                                 State::Disconnected {
@@ -86,6 +119,7 @@ pub fn connect(server_uri: String) -> Subscription<Event> {
                                     sender,
                                     events,
                                     server_uri,
+                                    backoff,
                                 },
                             ))
                         }
@@ -99,6 +133,7 @@ pub fn connect(server_uri: String) -> Subscription<Event> {
                                     sender,
                                     events,
                                     server_uri,
+                                    backoff,
                                 },
                             ))
                         }
@@ -119,12 +154,14 @@ enum State {
         sender: mpsc::Sender<UpdatesStream>,
         events: mpsc::Sender<Event>,
         server_uri: String,
+        backoff: Backoff,
     },
     Disconnected {
         receiver: mpsc::Receiver<UpdatesStream>,
         sender: mpsc::Sender<UpdatesStream>,
         events: mpsc::Sender<Event>,
         server_uri: String,
+        backoff: Backoff,
     },
 }
 
@@ -136,11 +173,13 @@ impl State {
                 sender,
                 events,
                 server_uri,
+                backoff,
             } => Self::Connected {
                 receiver,
                 sender,
                 events,
                 server_uri,
+                backoff,
             },
             x => x,
         }
@@ -153,12 +192,13 @@ impl State {
                 sender,
                 events,
                 server_uri,
-                ..
+                backoff,
             } => Self::Disconnected {
                 receiver,
                 sender,
                 events,
                 server_uri,
+                backoff,
             },
             x => x,
         }
@@ -178,6 +218,12 @@ impl State {
         }
     }
 
+    fn backoff(&mut self) -> &mut Backoff {
+        match self {
+            Self::Connected { backoff, .. } | Self::Disconnected { backoff, .. } => backoff,
+        }
+    }
+
     fn sender(&self) -> mpsc::Sender<UpdatesStream> {
         match self {
             Self::Connected { sender, .. } | Self::Disconnected { sender, .. } => sender.clone(),
@@ -185,43 +231,121 @@ impl State {
     }
 }
 
+/// Describes an error along with each of the errors which caused it
+fn describe(error: &dyn std::error::Error) -> String {
+    let mut description = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        description.push_str(&format!(": {}", cause));
+        source = cause.source();
+    }
+    description
+}
+
 #[derive(Debug, Clone)]
 pub enum Event {
     Connected(Client),
-    Disconnected,
+    /// Failed to connect, with a description of what went wrong
+    Disconnected(String),
+    /// Connecting has failed too many times in a row, so won't be retried until the given time
+    Backoff {
+        until: Instant,
+    },
     StatusUpdate(StatusUpdate),
+    SimulationReset,
+    Alert(Alert),
+    /// Destinations added to or deactivated in the simulation since it started
+    DestinationChanges(DestinationChanges),
+    /// Identifies the simulation the events which follow belong to
+    SimulationStarted(Ulid),
+    /// An event from the server couldn't be decoded, with a description of why
+    DecodeFailed(String),
+    /// How the server would like the client to behave, sent as the stream starts
+    ClientConfig(ClientConfig),
+}
+
+impl Event {
+    /// Returns the event for a monitor event, if it's of any interest to the application
+    pub fn from_monitor_event(event: MonitorEvent) -> Option<Self> {
+        match event {
+            MonitorEvent::StatusUpdate(update) => Some(Self::StatusUpdate(update)),
+            MonitorEvent::SimulationReset => Some(Self::SimulationReset),
+            MonitorEvent::DestinationChanges(changes) => Some(Self::DestinationChanges(changes)),
+            MonitorEvent::SimulationStarted(id) => Some(Self::SimulationStarted(id)),
+            MonitorEvent::ClientConfig(config) => Some(Self::ClientConfig(config)),
+            // Only used by the server to detect dead connections
+            MonitorEvent::Heartbeat => None,
+        }
+    }
+
+    fn decode_failed(what: &str, error: ConversionError) -> Self {
+        log::warn!("failed to decode {}: {}", what, error);
+        Self::DecodeFailed(format!("{}: {}", what, error))
+    }
 }
 
 #[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
 pub enum Client {
     Pending,
     Connected {
         client: SchedulerClient,
         sender: mpsc::Sender<UpdatesStream>,
+        /// Longest to wait for each RPC to be answered
+        rpc_timeout: Duration,
     },
 }
 
 impl Client {
     pub fn monitor(&self) -> impl futures::Future<Output = Result<(), Status>> {
         log::info!("attempt subscription");
-        let Client::Connected { client, sender, .. } = self else {
+        let Client::Connected {
+            client,
+            sender,
+            rpc_timeout,
+        } = self
+        else {
             log::warn!("no connection");
             return futures::future::ready(Err(Status::unavailable("no connection"))).boxed();
         };
-        let mut client = client.clone();
+        let (mut monitor_client, mut alerts_client) = (client.clone(), client.clone());
         let mut sender = sender.clone();
+        let rpc_timeout = *rpc_timeout;
 
         async move {
-            match client.monitor(()).await.map(tonic::Response::into_inner) {
-                Ok(stream) => match sender
+            // Every update is animated, so none are coalesced
+            let monitor = monitor_client.monitor(MonitorRequest {
+                client_name: env!("CARGO_PKG_NAME").to_string(),
+                client_version: env!("CARGO_PKG_VERSION").to_string(),
+                ..Default::default()
+            });
+            let subscribed = match tokio::time::timeout(
+                rpc_timeout,
+                futures::future::try_join(monitor, alerts_client.alerts(())),
+            )
+            .await
+            {
+                Ok(subscribed) => subscribed,
+                Err(_) => Err(Status::deadline_exceeded("timed out subscribing")),
+            };
+            match subscribed {
+                Ok((updates, alerts)) => match sender
                     .send(
-                        stream
-                            .filter_map(|proto| async move {
-                                proto
-                                    .ok()
-                                    .and_then(|proto| StatusUpdate::try_from_proto(proto))
-                            })
-                            .boxed(),
+                        futures::stream::select(
+                            updates.into_inner().filter_map(|proto| async move {
+                                match MonitorEvent::try_from_proto(proto.ok()?) {
+                                    Ok(event) => Event::from_monitor_event(event),
+                                    Err(e) => Some(Event::decode_failed("monitor event", e)),
+                                }
+                            }),
+                            alerts.into_inner().filter_map(|proto| async move {
+                                match Alert::try_from_proto(proto.ok()?) {
+                                    Ok(alert) => Some(Event::Alert(alert)),
+                                    Err(e) => Some(Event::decode_failed("alert", e)),
+                                }
+                            }),
+                        )
+                        .boxed(),
                     )
                     .await
                 {
@@ -239,4 +363,62 @@ impl Client {
         }
         .boxed()
     }
+
+    /// Asks the server to add or retire carriers, resolving to the size of the fleet once resized
+    pub fn set_fleet_size(
+        &self,
+        carriers: u32,
+    ) -> impl futures::Future<Output = Result<u32, Status>> {
+        let Client::Connected {
+            client,
+            rpc_timeout,
+            ..
+        } = self
+        else {
+            return futures::future::ready(Err(Status::unavailable("no connection"))).boxed();
+        };
+        let mut client = client.clone();
+        let rpc_timeout = *rpc_timeout;
+
+        async move {
+            match tokio::time::timeout(
+                rpc_timeout,
+                client.set_fleet_size(SetFleetSizeRequest { carriers }),
+            )
+            .await
+            {
+                Ok(response) => response.map(|response| response.into_inner().carriers),
+                Err(_) => Err(Status::deadline_exceeded("timed out resizing the fleet")),
+            }
+        }
+        .boxed()
+    }
+
+    /// Asks the server to launch an emergency flight it's holding on the ground for approval
+    pub fn approve_flight(
+        &self,
+        flight: FlightId,
+    ) -> impl futures::Future<Output = Result<(), Status>> {
+        let Client::Connected {
+            client,
+            rpc_timeout,
+            ..
+        } = self
+        else {
+            return futures::future::ready(Err(Status::unavailable("no connection"))).boxed();
+        };
+        let mut client = client.clone();
+        let rpc_timeout = *rpc_timeout;
+
+        async move {
+            let request = ApproveFlightRequest {
+                flight_id: flight.to_string(),
+            };
+            match tokio::time::timeout(rpc_timeout, client.approve_flight(request)).await {
+                Ok(response) => response.map(|_| ()),
+                Err(_) => Err(Status::deadline_exceeded("timed out approving the flight")),
+            }
+        }
+        .boxed()
+    }
 }
@@ -0,0 +1,176 @@
+use std::env;
+use std::path::PathBuf;
+
+use schema::json::Value;
+use schema::units::Units;
+
+/// Number of recently used server addresses which are remembered
+const MAX_RECENT_SERVERS: usize = 5;
+
+/// Client settings persisted between sessions in a local JSON file
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Config {
+    /// Server addresses most recently connected to, most recent first
+    pub recent_servers: Vec<String>,
+    /// Units distances & times are displayed in
+    pub units: Units,
+    /// Image drawn beneath the map, if any
+    pub background: Option<BackgroundConfig>,
+    /// Whether the sectors of the airspace are overlaid on the map, for scenarios which have them
+    pub show_sectors: bool,
+    /// Whether desktop notifications are raised for critical alerts while the window is in the
+    /// background
+    pub notify_alerts: bool,
+    /// Whether a sound is played for critical alerts
+    pub play_alert_sound: bool,
+}
+
+/// PNG image drawn beneath the map, along with where its edges lie in the scenario's coordinates
+/// (the same frame as the destinations, in meters) so that it lines up with them
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackgroundConfig {
+    pub path: String,
+    pub west_m: f32,
+    pub north_m: f32,
+    pub east_m: f32,
+    pub south_m: f32,
+    /// How opaque the image is drawn, so that the map stays legible over it
+    pub opacity: f32,
+}
+
+impl BackgroundConfig {
+    const DEFAULT_OPACITY: f32 = 0.5;
+
+    fn from_json(value: &Value) -> Option<Self> {
+        let coordinate = |key| value.get(key).and_then(Value::as_f64).map(|m| m as f32);
+        Some(Self {
+            path: value.get("path").and_then(Value::as_str)?.to_string(),
+            west_m: coordinate("west_m")?,
+            north_m: coordinate("north_m")?,
+            east_m: coordinate("east_m")?,
+            south_m: coordinate("south_m")?,
+            opacity: value
+                .get("opacity")
+                .and_then(Value::as_f64)
+                .map_or(Self::DEFAULT_OPACITY, |opacity| {
+                    (opacity as f32).clamp(0.0, 1.0)
+                }),
+        })
+    }
+
+    fn to_json(&self) -> Value {
+        Value::Object(vec![
+            ("path".to_string(), self.path.as_str().into()),
+            ("west_m".to_string(), self.west_m.into()),
+            ("north_m".to_string(), self.north_m.into()),
+            ("east_m".to_string(), self.east_m.into()),
+            ("south_m".to_string(), self.south_m.into()),
+            ("opacity".to_string(), self.opacity.into()),
+        ])
+    }
+}
+
+impl Config {
+    /// Location of the config file, `$CLIENT_CONFIG_PATH` if set,
+    /// otherwise within the user's config directory
+    pub fn path() -> Option<PathBuf> {
+        if let Ok(path) = env::var("CLIENT_CONFIG_PATH") {
+            return Some(PathBuf::from(path));
+        }
+
+        env::var("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|_| env::var("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .ok()
+            .map(|dir| dir.join("scheduler").join("client.json"))
+    }
+
+    /// Loads the config file, falling back to the defaults if it doesn't exist or is invalid
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else {
+            return Self::default();
+        };
+
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => Value::parse(&contents)
+                .map(|value| Self::from_json(&value))
+                .unwrap_or_else(|e| {
+                    log::warn!("ignoring invalid config {}: {}", path.display(), e);
+                    Self::default()
+                }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, format!("{:#}\n", self.to_json()))
+    }
+
+    /// Moves a server address to the front of the recently used addresses
+    pub fn remember_server(&mut self, server_uri: &str) {
+        self.recent_servers.retain(|uri| uri != server_uri);
+        self.recent_servers.insert(0, server_uri.to_string());
+        self.recent_servers.truncate(MAX_RECENT_SERVERS);
+    }
+
+    fn from_json(value: &Value) -> Self {
+        Self {
+            recent_servers: value
+                .get("recent_servers")
+                .and_then(Value::as_array)
+                .unwrap_or_default()
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect(),
+            units: value
+                .get("units")
+                .and_then(Value::as_str)
+                .and_then(|units| units.parse().ok())
+                .unwrap_or_default(),
+            background: value.get("background").and_then(|background| {
+                let config = BackgroundConfig::from_json(background);
+                if config.is_none() {
+                    log::warn!("ignoring background without a path & the coordinates of its edges");
+                }
+                config
+            }),
+            show_sectors: value
+                .get("show_sectors")
+                .and_then(Value::as_bool)
+                .unwrap_or_default(),
+            notify_alerts: value
+                .get("notify_alerts")
+                .and_then(Value::as_bool)
+                .unwrap_or_default(),
+            play_alert_sound: value
+                .get("play_alert_sound")
+                .and_then(Value::as_bool)
+                .unwrap_or_default(),
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        let mut fields = vec![
+            (
+                "recent_servers".to_string(),
+                self.recent_servers.clone().into(),
+            ),
+            ("units".to_string(), self.units.to_string().into()),
+            ("show_sectors".to_string(), self.show_sectors.into()),
+            ("notify_alerts".to_string(), self.notify_alerts.into()),
+            ("play_alert_sound".to_string(), self.play_alert_sound.into()),
+        ];
+        if let Some(background) = &self.background {
+            fields.push(("background".to_string(), background.to_json()));
+        }
+        Value::Object(fields)
+    }
+}
@@ -0,0 +1,100 @@
+use std::time::Instant;
+
+use iced::widget::{button, column, pick_list, row, text, text_input};
+use iced::{Alignment, Color, Element, Length};
+
+use super::Message;
+use crate::client::Client;
+
+/// Whether the client is trying to reach a server, & how that's going
+pub enum Status<'a> {
+    Disconnected,
+    Connecting(&'a str),
+    Connected(&'a str),
+}
+
+impl<'a> Status<'a> {
+    pub fn new(target: Option<&'a str>, client: &Client) -> Self {
+        match (target, client) {
+            (None, _) => Self::Disconnected,
+            (Some(uri), Client::Pending) => Self::Connecting(uri),
+            (Some(uri), Client::Connected { .. }) => Self::Connected(uri),
+        }
+    }
+}
+
+/// Settings for the server connection, along with its status, the latest connection error, when
+/// retries are held off until after failing repeatedly, & how many events couldn't be decoded
+pub fn view<'a>(
+    server_uri: &str,
+    recent_servers: &[String],
+    status: Status<'_>,
+    error: Option<&str>,
+    backoff_until: Option<Instant>,
+    decode_failures: &(usize, Option<String>),
+) -> Element<'a, Message> {
+    let is_disconnected = matches!(status, Status::Disconnected);
+
+    let mut input = text_input("Server address, e.g. http://localhost:50051", server_uri)
+        .width(Length::Fixed(320.0));
+    if is_disconnected {
+        input = input
+            .on_input(Message::ServerUriChanged)
+            .on_submit(Message::Connect);
+    }
+
+    let action = match is_disconnected {
+        true => button(text("Connect")).on_press(Message::Connect),
+        false => button(text("Disconnect")).on_press(Message::Disconnect),
+    };
+
+    let recent = pick_list(
+        recent_servers.to_vec(),
+        None::<String>,
+        Message::ServerUriChanged,
+    )
+    .placeholder("Recent");
+
+    let status = match status {
+        Status::Disconnected => text("Disconnected"),
+        Status::Connecting(uri) => text(format!("Connecting to {}…", uri)),
+        Status::Connected(uri) => text(format!("Connected to {}", uri)),
+    };
+
+    let mut content = column![
+        row![input, action, recent]
+            .spacing(10)
+            .align_items(Alignment::Center),
+        status.size(14),
+    ]
+    .spacing(5)
+    .padding(10);
+    if let Some(error) = error {
+        content = content.push(
+            text(format!("Connection error: {}", error))
+                .size(14)
+                .style(Color::from_rgb8(200, 0, 0)),
+        );
+    }
+    if let Some(until) = backoff_until.filter(|until| *until > Instant::now()) {
+        content = content.push(
+            text(format!(
+                "Server unreachable, retrying in {}s",
+                (until - Instant::now()).as_secs() + 1
+            ))
+            .size(14),
+        );
+    }
+    if let (failures @ 1.., Some(latest)) = decode_failures {
+        content = content.push(
+            text(format!(
+                "{} events from the server couldn't be decoded, most recently {}",
+                failures, latest
+            ))
+            .size(14)
+            .style(Color::from_rgb8(200, 0, 0)),
+        );
+    }
+
+    content.into()
+}
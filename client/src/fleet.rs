@@ -0,0 +1,41 @@
+use iced::widget::{row, slider, text};
+use iced::{Alignment, Color, Element, Length};
+
+use super::Message;
+
+/// Most carriers the fleet may be resized to
+pub const MAX_CARRIERS: u32 = 50;
+/// Carriers the server flies unless it's told otherwise
+pub const DEFAULT_CARRIERS: u32 = 10;
+
+/// Admin control resizing the fleet of the simulation, to see how its size affects the backlog.
+/// The fleet is only resized once the slider is released.
+pub fn view<'a>(carriers: u32, status: Option<&Result<u32, String>>) -> Element<'a, Message> {
+    let content = row![
+        text("Carriers").size(14),
+        slider(1..=MAX_CARRIERS, carriers, Message::FleetSizeChanged)
+            .on_release(Message::SetFleetSize)
+            .width(Length::Fixed(200.0)),
+        text(carriers).size(14),
+    ]
+    .spacing(10)
+    .padding([0, 10])
+    .align_items(Alignment::Center);
+
+    match status {
+        Some(Ok(resized)) if *resized != carriers => content.push(
+            text(format!(
+                "Resized to {} carriers, the rest are in flight",
+                resized
+            ))
+            .size(14),
+        ),
+        Some(Err(e)) => content.push(
+            text(format!("Resize error: {}", e))
+                .size(14)
+                .style(Color::from_rgb8(200, 0, 0)),
+        ),
+        _ => content,
+    }
+    .into()
+}
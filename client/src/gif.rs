@@ -0,0 +1,137 @@
+//! A minimal encoder for animated GIFs, drawing every frame from the same palette of 216 evenly
+//! spaced colors so that frames can be quantized as they're captured
+
+use std::collections::HashMap;
+
+/// Levels of each of red, green & blue in the palette
+const LEVELS: u8 = 6;
+/// Bits of each LZW code before any are added to the table, covering a 256 color palette
+const MIN_CODE_SIZE: u8 = 8;
+const CLEAR_CODE: u16 = 1 << MIN_CODE_SIZE;
+const END_CODE: u16 = CLEAR_CODE + 1;
+/// Codes beyond which the table is cleared & started again
+const MAX_CODES: u16 = 4096;
+
+/// Reduces RGBA pixels to indices into the palette
+pub fn quantize(rgba: &[u8]) -> Vec<u8> {
+    let level = |c: u8| ((c as u16 * (LEVELS as u16 - 1) + 127) / 255) as u8;
+    rgba.chunks_exact(4)
+        .map(|pixel| (level(pixel[0]) * LEVELS + level(pixel[1])) * LEVELS + level(pixel[2]))
+        .collect()
+}
+
+/// Encodes frames of palette indices, each the provided width & height, as an animated GIF which
+/// loops forever, showing each frame for the provided hundredths of a second
+pub fn encode(width: u16, height: u16, frames: &[Vec<u8>], delay_cs: u16) -> Vec<u8> {
+    let mut gif = b"GIF89a".to_vec();
+
+    // Logical screen descriptor, followed by a global color table of 256 entries
+    gif.extend(width.to_le_bytes());
+    gif.extend(height.to_le_bytes());
+    gif.extend([0xf7, 0, 0]);
+    let channel = |level: u8| (level as u16 * 255 / (LEVELS as u16 - 1)) as u8;
+    for i in 0..LEVELS.pow(3) {
+        let (r, g, b) = (i / (LEVELS * LEVELS), (i / LEVELS) % LEVELS, i % LEVELS);
+        gif.extend([channel(r), channel(g), channel(b)]);
+    }
+    // The rest of the table is unused
+    gif.extend(vec![0; (256 - LEVELS.pow(3) as usize) * 3]);
+
+    // Netscape extension, so that the animation loops
+    gif.extend([0x21, 0xff, 0x0b]);
+    gif.extend(b"NETSCAPE2.0");
+    gif.extend([0x03, 0x01, 0x00, 0x00, 0x00]);
+
+    for frame in frames {
+        // Graphic control extension with the frame's delay
+        gif.extend([0x21, 0xf9, 0x04, 0x00]);
+        gif.extend(delay_cs.to_le_bytes());
+        gif.extend([0x00, 0x00]);
+
+        // Image descriptor covering the whole screen, using the global color table
+        gif.push(0x2c);
+        gif.extend([0, 0, 0, 0]);
+        gif.extend(width.to_le_bytes());
+        gif.extend(height.to_le_bytes());
+        gif.push(0);
+
+        gif.push(MIN_CODE_SIZE);
+        for block in lzw(frame).chunks(u8::MAX as usize) {
+            gif.push(block.len() as u8);
+            gif.extend(block);
+        }
+        gif.push(0);
+    }
+
+    gif.push(0x3b);
+    gif
+}
+
+/// Compresses palette indices with the variable-length LZW codes GIF images are made up of
+fn lzw(indices: &[u8]) -> Vec<u8> {
+    let mut codes = BitWriter::default();
+    let mut table = HashMap::<(u16, u8), u16>::new();
+    let mut code_size = MIN_CODE_SIZE + 1;
+    let mut next_code = END_CODE + 1;
+    codes.write(CLEAR_CODE, code_size);
+
+    let Some((&first, rest)) = indices.split_first() else {
+        codes.write(END_CODE, code_size);
+        return codes.finish();
+    };
+    let mut prefix = first as u16;
+    for &index in rest {
+        if let Some(&code) = table.get(&(prefix, index)) {
+            prefix = code;
+            continue;
+        }
+
+        codes.write(prefix, code_size);
+        match next_code < MAX_CODES {
+            true => {
+                table.insert((prefix, index), next_code);
+                next_code += 1;
+                if next_code > 1 << code_size && code_size < 12 {
+                    code_size += 1;
+                }
+            }
+            false => {
+                codes.write(CLEAR_CODE, code_size);
+                table.clear();
+                code_size = MIN_CODE_SIZE + 1;
+                next_code = END_CODE + 1;
+            }
+        }
+        prefix = index as u16;
+    }
+    codes.write(prefix, code_size);
+    codes.write(END_CODE, code_size);
+    codes.finish()
+}
+
+/// Packs codes into bytes, least significant bit first
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    bits: u8,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, size: u8) {
+        self.buffer |= (code as u32) << self.bits;
+        self.bits += size;
+        while self.bits >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
@@ -0,0 +1,257 @@
+use std::collections::{HashMap, HashSet};
+
+use iced::widget::{column, container, row, text};
+use iced::{theme, Alignment, Element, Length};
+use schema::json::Value;
+use schema::units::Units;
+use schema::{
+    CarrierMode, Destination, DestinationId, Flight, FlightId, Order, OrderId, StatusUpdate,
+};
+
+use super::map;
+use super::Message;
+
+/// Cumulative counters for KPIs which can't be derived from a single `StatusUpdate`
+#[derive(Default)]
+pub struct Kpis {
+    /// Orders known to the client, used to determine how many are still waiting for a carrier
+    orders: Vec<Order>,
+    /// Orders which have been seen aboard a flight
+    scheduled: HashSet<Order>,
+    /// Flights from the most recent update, by identifier
+    active_flights: HashMap<FlightId, Flight>,
+    /// Number of orders carried by flights which have since landed
+    delivered: usize,
+}
+
+impl Kpis {
+    pub fn new(orders: Vec<Order>) -> Self {
+        Self {
+            orders,
+            ..Default::default()
+        }
+    }
+
+    /// Replace the orders known to the client, keeping the counters
+    pub fn set_orders(&mut self, orders: Vec<Order>) {
+        self.orders = orders;
+    }
+
+    /// Clear all of the counters, e.g. after the simulation has been reset
+    pub fn reset(&mut self) {
+        *self = Self::new(std::mem::take(&mut self.orders));
+    }
+
+    /// Update the counters from the latest `StatusUpdate`
+    pub fn observe(&mut self, update: &StatusUpdate) {
+        let flights = update
+            .flights
+            .iter()
+            .map(|flight| (flight.id, flight.clone()))
+            .collect::<HashMap<_, _>>();
+
+        // Any flight which is no longer active has landed & made all of its deliveries
+        self.delivered += self
+            .active_flights
+            .iter()
+            .filter(|(id, _)| !flights.contains_key(id))
+            .map(|(_, flight)| flight.orders.len())
+            .sum::<usize>();
+        self.scheduled.extend(
+            flights
+                .values()
+                .flat_map(|flight| flight.orders.iter().cloned()),
+        );
+        self.active_flights = flights;
+    }
+
+    /// Renders the counters so that they can be restored with `restore`
+    pub fn to_json(&self) -> Value {
+        let flights = self
+            .active_flights
+            .values()
+            .map(|flight| {
+                Value::Object(vec![
+                    ("id".to_string(), flight.id.to_string().into()),
+                    ("orders".to_string(), orders_json(&flight.orders)),
+                ])
+            })
+            .collect();
+
+        Value::Object(vec![
+            ("scheduled".to_string(), orders_json(&self.scheduled)),
+            ("active_flights".to_string(), Value::Array(flights)),
+            ("delivered".to_string(), self.delivered.into()),
+        ])
+    }
+
+    /// Restores counters rendered with `to_json`, keeping the orders known to the client. Flights
+    /// which were active keep only their orders until the next update, by which time those which
+    /// are missing from it have landed.
+    pub fn restore(&mut self, value: &Value) -> Result<(), String> {
+        let orders = |value: Option<&Value>| {
+            value
+                .and_then(Value::as_array)
+                .ok_or("missing orders")?
+                .iter()
+                .map(order_from_json)
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let active_flights = value
+            .get("active_flights")
+            .and_then(Value::as_array)
+            .ok_or("missing `active_flights`")?
+            .iter()
+            .map(|flight| {
+                let id = flight
+                    .get("id")
+                    .and_then(Value::as_str)
+                    .ok_or("missing flight id")?
+                    .parse()?;
+                let flight = Flight {
+                    id,
+                    orders: orders(flight.get("orders"))?,
+                    ..Default::default()
+                };
+                Ok((id, flight))
+            })
+            .collect::<Result<HashMap<_, _>, String>>()?;
+
+        self.scheduled = orders(value.get("scheduled"))?.into_iter().collect();
+        self.active_flights = active_flights;
+        self.delivered = value
+            .get("delivered")
+            .and_then(Value::as_u64)
+            .ok_or("missing `delivered`")? as usize;
+
+        Ok(())
+    }
+
+    /// Returns the number of orders placed by `current_time` which haven't yet been scheduled
+    fn pending(&self, current_time: u64) -> usize {
+        self.orders
+            .iter()
+            .filter(|order| order.time <= current_time && !self.scheduled.contains(order))
+            .count()
+    }
+
+    /// Returns the number of orders delivered, including drops already made by active flights
+    fn delivered(
+        &self,
+        destinations: &HashMap<DestinationId, Destination>,
+        origin: &Destination,
+        current_time: u64,
+    ) -> usize {
+        self.delivered
+            + self
+                .active_flights
+                .values()
+                .map(|flight| {
+                    let (metric, speed_mps) = map::travel(flight.mode);
+                    let (_, _, remaining) = flight.current_position(
+                        metric,
+                        destinations,
+                        origin,
+                        current_time,
+                        speed_mps,
+                    );
+                    flight.orders.len() - remaining
+                })
+                .sum::<usize>()
+    }
+}
+
+/// Renders orders along with the identifiers they were queued with
+fn orders_json<'a>(orders: impl IntoIterator<Item = &'a Order>) -> Value {
+    Value::Array(
+        orders
+            .into_iter()
+            .map(|order| {
+                let Value::Object(mut fields) = order.to_json() else {
+                    unreachable!("orders are rendered as objects")
+                };
+                fields.push(("id".to_string(), order.id.0.into()));
+                Value::Object(fields)
+            })
+            .collect(),
+    )
+}
+
+fn order_from_json(value: &Value) -> Result<Order, String> {
+    Ok(Order {
+        id: OrderId(
+            value
+                .get("id")
+                .and_then(Value::as_u64)
+                .ok_or("missing order id")?,
+        ),
+        // Saved by the client itself, for destinations it has seen before
+        ..Order::from_json(value, |name| Ok(DestinationId::intern(name)))?
+    })
+}
+
+pub fn view<'a>(
+    kpis: &Kpis,
+    destinations: &HashMap<DestinationId, Destination>,
+    origin: &Destination,
+    update: Option<&StatusUpdate>,
+    // Number of carriers in the fleet, not counting ground vehicles
+    carriers: u32,
+    perceived_time_millis: u64,
+    units: Units,
+) -> Element<'a, Message> {
+    let current_time = perceived_time_millis / 1000;
+    let values = match update {
+        Some(update) => [
+            units.format_time(current_time),
+            update.flights.len().to_string(),
+            (carriers as usize)
+                .saturating_sub(
+                    update
+                        .flights
+                        .iter()
+                        .filter(|flight| flight.mode == CarrierMode::Aerial)
+                        .count(),
+                )
+                .to_string(),
+            // Estimated from the scenario's orders for servers which don't report their backlog
+            update
+                .backlog
+                .map_or_else(|| kpis.pending(current_time), |backlog| backlog.len())
+                .to_string(),
+            kpis.delivered(destinations, origin, current_time)
+                .to_string(),
+            update.speed.to_string(),
+        ],
+        None => Default::default(),
+    };
+
+    let labels = [
+        "Time",
+        "Active flights",
+        "Carriers available",
+        "Orders pending",
+        "Delivered today",
+        "Speed",
+    ];
+
+    let items = labels.into_iter().zip(values).map(|(label, value)| {
+        column![
+            text(label).size(14),
+            text(if value.is_empty() {
+                "–".to_string()
+            } else {
+                value
+            })
+            .size(20)
+        ]
+        .align_items(Alignment::Center)
+        .width(Length::Fill)
+        .into()
+    });
+
+    container(row(items.collect()).padding(10))
+        .width(Length::Fill)
+        .style(theme::Container::Box)
+        .into()
+}
@@ -1,15 +1,46 @@
-use std::collections::HashMap;
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
 use std::env;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use iced::executor;
 use iced::widget::{column, container, text};
-use iced::{theme, Application, Command, Element, Length, Settings, Theme};
-use schema::{Destination, DestinationName, Speed, StatusUpdate};
+use iced::window::Screenshot;
+use iced::{theme, window, Application, Command, Element, Length, Rectangle, Settings, Theme};
+use schema::units::Units;
+use schema::{
+    Alert, ClientConfig, Destination, DestinationChanges, DestinationId, FlightId, Order,
+    SimulatedClock, Speed, StatusUpdate, ThemeHint,
+};
 
+mod approvals;
+mod background;
+mod backlog;
+mod banner;
+mod capture;
 mod client;
+mod config;
+mod connection;
+mod fleet;
+mod gif;
+mod kpi;
 mod map;
+mod notify;
+mod replay;
+mod retry;
+mod sectors;
+mod session;
+use approvals::Approvals;
+use background::Background;
+use capture::Recording;
 use client::Client;
+use config::Config;
+use kpi::Kpis;
+use notify::Notifier;
+use replay::Replay;
+use retry::{Backoff, RetryPolicy};
+use session::Session;
+use ulid::Ulid;
 
 const CLIENT_FRAME_RATE: u64 = 20;
 
@@ -17,54 +48,197 @@ pub fn main() -> iced::Result {
     dotenv::dotenv().ok();
     env_logger::init();
 
-    let gateway_uri = env::var("SERVER_URI").unwrap_or("http://localhost:50051".to_string());
-
     Gui::run(Settings {
-        flags: gateway_uri,
+        // Connect immediately if a server was provided, otherwise wait for the user to pick one
+        flags: env::var("SERVER_URI").ok(),
+        // The session is saved before the window closes
+        exit_on_close_request: false,
         ..Default::default()
     })
 }
 
 struct Gui {
-    gateway_uri: String,
+    /// Server address entered in the connection settings
+    server_uri: String,
+    /// Server which the client is connected or connecting to, if any
+    target_uri: Option<String>,
+    /// Most recent error encountered connecting to the server
+    connection_error: Option<String>,
+    /// Timeouts for requests to the server, & how retries back off when they fail
+    retry_policy: RetryPolicy,
+    /// Backs off from subscribing to the server's events while subscribing keeps failing
+    monitor_backoff: Backoff,
+    /// Time until which retries are held off after failing repeatedly, if they are
+    backoff_until: Option<Instant>,
+    /// Number of events from the server which couldn't be decoded, & why the latest couldn't
+    decode_failures: (usize, Option<String>),
+    /// Journal file entered in the replay controls
+    journal_path: String,
+    /// Journal being played back instead of monitoring a server, if any
+    replay: Option<Replay>,
+    /// Most recent error encountered opening a journal
+    journal_error: Option<String>,
+    config: Config,
+    /// Image drawn beneath the map, loaded from the config on startup
+    background: Option<Background>,
     client: Client,
-    destinations: HashMap<DestinationName, Destination>,
+    destinations: HashMap<DestinationId, Destination>,
+    /// Destinations as loaded from the scenario, before any changes made during the simulation
+    scenario_destinations: HashMap<DestinationId, Destination>,
+    /// Destinations which no longer accept orders, drawn greyed out on the map
+    deactivated: HashSet<DestinationId>,
+    /// Nest which carriers launch from & return to
+    origin: Destination,
+    /// Simulation the server is currently running, once it has said which
+    simulation_id: Option<Ulid>,
     latest_update: Option<StatusUpdate>,
-    perceived_time_millis: u64,
+    /// Time the client is displaying, animated between updates at their speed
+    perceived_time: SimulatedClock,
     is_monitoring: bool,
+    kpis: Kpis,
+    /// Units distances & times are displayed in, from `UNITS` if set, otherwise the server's
+    /// config, otherwise the local config
+    units: Units,
+    /// How the server would like the client to behave, as it said when the stream started
+    server_config: ClientConfig,
+    alerts: Vec<Alert>,
+    notifier: Notifier,
+    /// Simulated minutes entered for the length of a recording, or empty to record until stopped
+    recording_minutes: String,
+    /// Recording of the map in progress, if any
+    recording: Option<Recording>,
+    /// Path the most recent screenshot or recording was saved to, or why it couldn't be
+    capture_status: Option<Result<String, String>>,
+    /// Where the map was last drawn in the window
+    map_bounds: Cell<Option<Rectangle>>,
+    /// Size of the window in logical pixels
+    window_size: (u32, u32),
+    is_focused: bool,
+    is_minimized: bool,
+    /// Whether controls altering the simulation are shown, from `ADMIN`
+    admin: bool,
+    /// Number of carriers chosen for the fleet with the admin controls
+    fleet_size: u32,
+    /// Size the fleet was last resized to, or why it couldn't be
+    fleet_status: Option<Result<u32, String>>,
+    /// Number of carriers in the fleet, as of the last time it was resized
+    carriers: u32,
+    /// Emergency flights awaiting approval with the admin controls
+    approvals: Approvals,
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
+    ScenarioLoaded(Result<(Vec<Destination>, Destination, Vec<Order>), String>),
     StatusUpdate(StatusUpdate),
+    SimulationStarted(Ulid),
+    SimulationReset,
+    Alert(Alert),
+    DestinationChanges(DestinationChanges),
+    DismissAlert(usize),
+    WindowFocused(bool),
+    WindowResized(u32, u32),
+    CloseRequested,
     IncrementPerceivedTime,
     MonitorRequestSuccess,
     MonitorRequestFailed,
     Connected(Client),
-    Disconnected,
+    Disconnected(String),
+    /// Connecting keeps failing, so won't be retried until the given time
+    Backoff(Instant),
+    /// An event from the server couldn't be decoded
+    DecodeFailed(String),
+    ClientConfig(ClientConfig),
+    ServerUriChanged(String),
+    Connect,
+    Disconnect,
+    JournalPathChanged(String),
+    OpenJournal,
+    JournalLoaded(String, Result<Vec<client::Event>, String>),
+    CloseJournal,
+    ToggleReplayPaused,
+    ReplaySpeedChanged(Speed),
+    TakeScreenshot,
+    ScreenshotTaken(Screenshot),
+    RecordingMinutesChanged(String),
+    ToggleRecording,
+    FrameCaptured(Screenshot),
+    CaptureSaved(Result<String, String>),
+    FleetSizeChanged(u32),
+    SetFleetSize,
+    FleetResized(Result<u32, String>),
+    ApproveFlight(FlightId),
+    FlightApproved(FlightId, Result<(), String>),
+    ShowSectors(bool),
+    NotifyAlerts(bool),
+    PlayAlertSound(bool),
 }
 
 impl Application for Gui {
     type Message = Message;
     type Theme = Theme;
     type Executor = executor::Default;
-    type Flags = String;
+    type Flags = Option<String>;
+
+    fn new(server_uri: Option<String>) -> (Gui, Command<Message>) {
+        let config = Config::load();
+        let units = env::var("UNITS")
+            .ok()
+            .and_then(|units| units.parse().ok())
+            .unwrap_or(config.units);
+        let target_uri = server_uri.clone();
+        let retry_policy = RetryPolicy::from_env();
+        let server_uri = server_uri
+            .or_else(|| config.recent_servers.first().cloned())
+            .unwrap_or_else(|| "http://localhost:50051".to_string());
+        let background = config.background.as_ref().and_then(|background| {
+            Background::load(background)
+                .map_err(|e| log::warn!("not drawing background: {}", e))
+                .ok()
+        });
 
-    fn new(gateway_uri: String) -> (Gui, Command<Message>) {
         (
             Gui {
-                gateway_uri,
+                server_uri,
+                target_uri,
+                connection_error: None,
+                retry_policy,
+                monitor_backoff: Backoff::new(retry_policy),
+                backoff_until: None,
+                decode_failures: (0, None),
+                journal_path: String::new(),
+                replay: None,
+                journal_error: None,
+                config,
+                background,
                 client: Client::Pending,
-                destinations: Destination::from_csv(schema::SAMPLE_DESTINATIONS_CSV_PATH)
-                    .expect("destinations")
-                    .into_iter()
-                    .map(|d| (d.name.clone(), d))
-                    .collect(),
+                destinations: HashMap::new(),
+                scenario_destinations: HashMap::new(),
+                deactivated: HashSet::new(),
+                origin: Destination::default_origin(),
+                simulation_id: None,
                 latest_update: None,
-                perceived_time_millis: 0,
+                perceived_time: SimulatedClock::default(),
                 is_monitoring: false,
+                kpis: Kpis::default(),
+                units,
+                server_config: ClientConfig::default(),
+                alerts: vec![],
+                notifier: Notifier::default(),
+                recording_minutes: String::new(),
+                recording: None,
+                capture_status: None,
+                map_bounds: Cell::new(None),
+                window_size: window::Settings::default().size,
+                is_focused: true,
+                is_minimized: false,
+                admin: env::var("ADMIN").is_ok_and(|v| v == "1" || v == "true"),
+                fleet_size: fleet::DEFAULT_CARRIERS,
+                fleet_status: None,
+                carriers: fleet::DEFAULT_CARRIERS,
+                approvals: Approvals::default(),
             },
-            Command::none(),
+            Command::perform(load_scenario(), Message::ScenarioLoaded),
         )
     }
 
@@ -74,37 +248,182 @@ impl Application for Gui {
 
     fn update(&mut self, message: Message) -> Command<Message> {
         match message {
+            Message::ScenarioLoaded(Ok((destinations, origin, orders))) => {
+                self.destinations = destinations.into_iter().map(|d| (d.name, d)).collect();
+                self.scenario_destinations = self.destinations.clone();
+                self.origin = origin;
+                // Counters may already have been restored from a previous session
+                self.kpis.set_orders(orders);
+                self.check_destination_names();
+
+                Command::none()
+            }
+
+            Message::ScenarioLoaded(Err(e)) => {
+                log::error!("failed to load scenario: {}", e);
+
+                Command::none()
+            }
+
             Message::StatusUpdate(update) => {
-                self.perceived_time_millis = update.time * 1000;
+                self.perceived_time = SimulatedClock::new(update.time * 1000, update.speed);
+                self.kpis.observe(&update);
+                self.approvals.observe(&update);
                 self.latest_update = Some(update);
 
                 Command::none()
             }
 
-            Message::IncrementPerceivedTime => {
-                if let Some(update) = self.latest_update.as_ref() {
-                    self.perceived_time_millis += match update.speed {
-                        Speed::RealTime => 50,
-                        Speed::FastForward(n) => n.get() as u64 * 50,
-                        Speed::SlowMotion(n) => 50 / (n.get() as u64),
+            // Pick up where a previous session left off if it was monitoring the same simulation
+            Message::SimulationStarted(id) => {
+                self.simulation_id = Some(id);
+                let session = Session::load().filter(|session| session.simulation_id == id);
+                if let Some(session) = session.filter(|_| self.replay.is_none()) {
+                    match session.restore(&mut self.kpis) {
+                        Ok(()) => log::info!("restored session for simulation {}", id),
+                        Err(e) => log::warn!("failed to restore session: {}", e),
                     }
                 }
 
                 Command::none()
             }
 
+            Message::SimulationReset => {
+                log::info!("simulation reset");
+                self.simulation_id = None;
+                self.perceived_time = SimulatedClock::default();
+                self.latest_update = None;
+                self.kpis.reset();
+                self.alerts.clear();
+                self.destinations = self.scenario_destinations.clone();
+                self.deactivated.clear();
+                self.fleet_size = fleet::DEFAULT_CARRIERS;
+                self.fleet_status = None;
+                self.carriers = fleet::DEFAULT_CARRIERS;
+                self.approvals.clear();
+
+                Command::none()
+            }
+
+            Message::Alert(alert) => {
+                // Journals are played back for review, not to be acted on
+                if self.replay.is_none() {
+                    self.notifier.alert(
+                        &alert,
+                        self.config.notify_alerts,
+                        self.config.play_alert_sound,
+                        self.is_paused(),
+                    );
+                    self.approvals.alert(&alert);
+                }
+                self.alerts.push(alert);
+                if self.alerts.len() > banner::MAX_BANNERS {
+                    self.alerts.remove(0);
+                }
+
+                Command::none()
+            }
+
+            // Deactivated destinations stay on the map, as carriers may still be delivering to them
+            Message::DestinationChanges(changes) => {
+                for destination in changes.added {
+                    self.deactivated.remove(&destination.name);
+                    self.destinations.insert(destination.name, destination);
+                }
+                self.deactivated.extend(changes.deactivated);
+
+                Command::none()
+            }
+
+            Message::DismissAlert(i) => {
+                if i < self.alerts.len() {
+                    self.alerts.remove(i);
+                }
+
+                Command::none()
+            }
+
+            Message::WindowFocused(is_focused) => {
+                self.is_focused = is_focused;
+
+                Command::none()
+            }
+
+            // Minimized windows are resized to zero on some platforms
+            Message::WindowResized(width, height) => {
+                self.is_minimized = width == 0 || height == 0;
+                if !self.is_minimized {
+                    self.window_size = (width, height);
+                }
+
+                Command::none()
+            }
+
+            Message::CloseRequested => {
+                let simulation_id = self.simulation_id.filter(|_| self.replay.is_none());
+                if let Some(id) = simulation_id {
+                    if let Err(e) = Session::new(id, &self.kpis).save() {
+                        log::warn!("failed to save session: {}", e);
+                    }
+                }
+
+                window::close()
+            }
+
+            Message::IncrementPerceivedTime => {
+                if self.replay.as_ref().is_some_and(|replay| replay.is_paused) {
+                    return Command::none();
+                }
+
+                if self.latest_update.is_some() {
+                    self.perceived_time.advance(1000 / CLIENT_FRAME_RATE);
+                }
+
+                // Journals are played back as if their events were arriving from a server
+                let due = match self.replay.as_mut() {
+                    Some(replay) => replay.due(
+                        self.perceived_time.now_millis(),
+                        self.latest_update.is_some(),
+                    ),
+                    None => vec![],
+                };
+                for event in due {
+                    let _ = self.update(event.into());
+                }
+
+                let current_time = self.perceived_time.now_millis() / 1000;
+                match self.recording.as_mut() {
+                    Some(recording)
+                        if recording.until.is_some_and(|until| current_time >= until) =>
+                    {
+                        self.finish_recording()
+                    }
+                    Some(recording) if recording.frame_due() => {
+                        recording.requested_frame();
+                        window::screenshot(Message::FrameCaptured)
+                    }
+                    _ => Command::none(),
+                }
+            }
+
             Message::MonitorRequestSuccess => {
                 self.is_monitoring = true;
+                self.monitor_backoff.succeeded();
+                self.backoff_until = None;
 
                 Command::none()
             }
 
             Message::MonitorRequestFailed => {
                 if matches!(&self.client, Client::Connected { .. }) {
+                    let delay = self.monitor_backoff.failed();
+                    if self.monitor_backoff.is_open() {
+                        self.backoff_until = Some(Instant::now() + delay);
+                    }
                     let monitor_fut = self.client.monitor();
                     Command::perform(
                         async move {
-                            tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                            tokio::time::sleep(delay).await;
                             monitor_fut.await
                         },
                         |res| match res {
@@ -120,6 +439,10 @@ impl Application for Gui {
             Message::Connected(client) => {
                 log::info!("client connected");
                 self.client = client;
+                self.connection_error = None;
+                self.backoff_until = None;
+                // Until the server says otherwise, as it may not be the one previously connected to
+                self.server_config = ClientConfig::default();
 
                 Command::perform(self.client.monitor(), |res| match res {
                     Ok(_) => Message::MonitorRequestSuccess,
@@ -127,55 +450,488 @@ impl Application for Gui {
                 })
             }
 
-            Message::Disconnected => {
-                log::info!("client disconnected");
+            Message::Disconnected(error) => {
+                log::info!("client disconnected: {}", error);
+                self.client = Client::Pending;
+                self.connection_error = Some(error);
+
+                Command::none()
+            }
+
+            Message::Backoff(until) => {
+                log::info!(
+                    "backing off from connecting for {:?}",
+                    until - Instant::now()
+                );
+                self.backoff_until = Some(until);
+
+                Command::none()
+            }
+
+            Message::DecodeFailed(error) => {
+                self.decode_failures.0 += 1;
+                self.decode_failures.1 = Some(error);
+
+                Command::none()
+            }
+
+            Message::ClientConfig(config) => {
+                log::info!("applying config from the server: {:?}", config);
+                if let Some(units) = config.units.filter(|_| env::var("UNITS").is_err()) {
+                    self.units = units;
+                }
+                self.server_config = config;
+                self.check_destination_names();
+
+                Command::none()
+            }
+
+            Message::ServerUriChanged(server_uri) => {
+                self.server_uri = server_uri;
+
+                Command::none()
+            }
+
+            Message::Connect => {
+                if self.replay.take().is_some() {
+                    let _ = self.update(Message::SimulationReset);
+                }
+                let server_uri = self.server_uri.trim().to_string();
+                log::info!("connecting to {}", server_uri);
+                self.config.remember_server(&server_uri);
+                if let Err(e) = self.config.save() {
+                    log::warn!("failed to save config: {}", e);
+                }
+                self.connection_error = None;
+                self.target_uri = Some(server_uri);
+
+                Command::none()
+            }
+
+            Message::Disconnect => {
+                log::info!("disconnecting");
+                // Dropping the connection subscription closes the connection
+                self.target_uri = None;
                 self.client = Client::Pending;
+                self.is_monitoring = false;
+
+                Command::none()
+            }
+
+            Message::JournalPathChanged(journal_path) => {
+                self.journal_path = journal_path;
+
+                Command::none()
+            }
+
+            Message::OpenJournal => {
+                let path = self.journal_path.trim().to_string();
+                log::info!("opening journal {}", path);
+
+                Command::perform(load_journal(path.clone()), move |result| {
+                    Message::JournalLoaded(path, result)
+                })
+            }
+
+            Message::JournalLoaded(path, Ok(events)) => {
+                // Replays take the place of any connection to a server
+                let _ = self.update(Message::Disconnect);
+                let _ = self.update(Message::SimulationReset);
+                self.journal_error = None;
+                self.replay = Some(Replay::new(path, events));
+
+                Command::none()
+            }
+
+            Message::JournalLoaded(path, Err(e)) => {
+                log::error!("failed to open journal {}: {}", path, e);
+                self.journal_error = Some(e);
+
+                Command::none()
+            }
+
+            Message::CloseJournal => {
+                self.replay = None;
+                let _ = self.update(Message::SimulationReset);
+
+                Command::none()
+            }
+
+            Message::ToggleReplayPaused => {
+                if let Some(replay) = self.replay.as_mut() {
+                    replay.is_paused = !replay.is_paused;
+                }
+
+                Command::none()
+            }
+
+            Message::TakeScreenshot => window::screenshot(Message::ScreenshotTaken),
+
+            Message::ScreenshotTaken(screenshot) => {
+                let screenshot =
+                    capture::crop_to_map(screenshot, self.map_bounds.get(), self.window_size);
+                let current_time = self.perceived_time.now_millis() / 1000;
+
+                Command::perform(
+                    async move { capture::save_png(screenshot, current_time) },
+                    Message::CaptureSaved,
+                )
+            }
+
+            Message::RecordingMinutesChanged(minutes) => {
+                self.recording_minutes = minutes;
+
+                Command::none()
+            }
+
+            Message::ToggleRecording => {
+                if self.recording.is_some() {
+                    return self.finish_recording();
+                }
+
+                let minutes = self.recording_minutes.trim();
+                let minutes = match minutes.is_empty() {
+                    true => None,
+                    false => match minutes.parse::<u64>() {
+                        Ok(minutes) => Some(minutes),
+                        Err(_) => {
+                            self.capture_status =
+                                Some(Err(format!("invalid minutes to record: {}", minutes)));
+                            return Command::none();
+                        }
+                    },
+                };
+                self.capture_status = None;
+                self.recording = Some(Recording::new(
+                    self.perceived_time.now_millis() / 1000,
+                    minutes,
+                ));
+
+                Command::none()
+            }
+
+            Message::FrameCaptured(screenshot) => {
+                let screenshot =
+                    capture::crop_to_map(screenshot, self.map_bounds.get(), self.window_size);
+                let is_full = self
+                    .recording
+                    .as_mut()
+                    .is_some_and(|recording| recording.push(screenshot));
+                match is_full {
+                    true => self.finish_recording(),
+                    false => Command::none(),
+                }
+            }
+
+            Message::CaptureSaved(result) => {
+                match &result {
+                    Ok(path) => log::info!("saved {}", path),
+                    Err(e) => log::error!("failed to save capture: {}", e),
+                }
+                self.capture_status = Some(result);
+
+                Command::none()
+            }
+
+            Message::FleetSizeChanged(carriers) => {
+                self.fleet_size = carriers;
+
+                Command::none()
+            }
+
+            Message::SetFleetSize => {
+                log::info!("resizing the fleet to {} carriers", self.fleet_size);
+                Command::perform(self.client.set_fleet_size(self.fleet_size), |result| {
+                    Message::FleetResized(result.map_err(|status| status.message().to_string()))
+                })
+            }
+
+            Message::FleetResized(result) => {
+                match &result {
+                    Ok(carriers) => {
+                        log::info!("resized the fleet to {} carriers", carriers);
+                        self.carriers = *carriers;
+                    }
+                    Err(e) => log::error!("failed to resize the fleet: {}", e),
+                }
+                self.fleet_status = Some(result);
+
+                Command::none()
+            }
+
+            Message::ApproveFlight(flight) => {
+                log::info!("approving flight {}", flight);
+                Command::perform(self.client.approve_flight(flight), move |result| {
+                    Message::FlightApproved(
+                        flight,
+                        result.map_err(|status| status.message().to_string()),
+                    )
+                })
+            }
+
+            Message::FlightApproved(flight, result) => {
+                match &result {
+                    Ok(()) => log::info!("approved flight {}", flight),
+                    Err(e) => log::error!("failed to approve flight {}: {}", flight, e),
+                }
+                self.approvals.approved(flight, result);
+
+                Command::none()
+            }
+
+            Message::ShowSectors(show) => {
+                self.config.show_sectors = show;
+                if let Err(e) = self.config.save() {
+                    log::warn!("failed to save config: {}", e);
+                }
+
+                Command::none()
+            }
+
+            Message::NotifyAlerts(notify) => {
+                self.config.notify_alerts = notify;
+                if let Err(e) = self.config.save() {
+                    log::warn!("failed to save config: {}", e);
+                }
+
+                Command::none()
+            }
+
+            Message::PlayAlertSound(play) => {
+                self.config.play_alert_sound = play;
+                if let Err(e) = self.config.save() {
+                    log::warn!("failed to save config: {}", e);
+                }
+
+                Command::none()
+            }
+
+            Message::ReplaySpeedChanged(speed) => {
+                if let Some(replay) = self.replay.as_mut() {
+                    replay.speed = speed;
+                    // Animate at the new speed right away, rather than from the next update
+                    if let Some(update) = self.latest_update.as_mut() {
+                        update.speed = speed;
+                    }
+                }
 
                 Command::none()
             }
         }
     }
 
-    fn view(&self) -> Element<Message> {
+    fn view(&self) -> Element<'_, Message> {
         let content: Element<Message> = match &self.latest_update {
-            Some(update) => map::view(&self.destinations, update, self.perceived_time_millis),
+            Some(update) => map::view(
+                &self.destinations,
+                &self.deactivated,
+                &self.origin,
+                update,
+                self.carriers,
+                self.perceived_time.now_millis(),
+                self.units,
+                self.server_config.extents,
+                self.background.as_ref(),
+                self.config
+                    .show_sectors
+                    .then_some(update.sectors.as_slice()),
+                &self.map_bounds,
+            ),
             None => text("Waiting for update…").into(),
         };
         let with_connection_status: Element<Message> = match &self.client {
-            Client::Pending => text("Client disconnected, attempting to connect…").into(),
-            Client::Connected { .. } => column![
-                text("Connected to server"),
-                container(content).padding(20).style(theme::Container::Box)
-            ]
-            .align_items(iced::Alignment::Center)
-            .into(),
+            Client::Pending if self.replay.is_none() => text("Not connected to a server").into(),
+            _ => container(content)
+                .padding(20)
+                .style(theme::Container::Box)
+                .into(),
         };
 
-        container(with_connection_status)
-            .width(Length::Fill)
-            .height(Length::Fill)
-            .center_x()
-            .center_y()
-            .into()
+        let mut controls = column![
+            connection::view(
+                &self.server_uri,
+                &self.config.recent_servers,
+                connection::Status::new(self.target_uri.as_deref(), &self.client),
+                self.connection_error.as_deref(),
+                self.backoff_until,
+                &self.decode_failures,
+            ),
+            replay::view(
+                &self.journal_path,
+                self.replay.as_ref(),
+                self.journal_error.as_deref()
+            ),
+            capture::view(
+                &self.recording_minutes,
+                self.recording.as_ref(),
+                self.capture_status.as_ref(),
+            ),
+            notify::view(self.config.notify_alerts, self.config.play_alert_sound),
+        ];
+        let admin = self.admin || self.server_config.has_feature("admin");
+        if admin && matches!(self.client, Client::Connected { .. }) {
+            controls = controls.push(fleet::view(self.fleet_size, self.fleet_status.as_ref()));
+            if !self.approvals.is_empty() {
+                controls = controls.push(approvals::view(&self.approvals));
+            }
+        }
+
+        column![
+            controls,
+            kpi::view(
+                &self.kpis,
+                &self.destinations,
+                &self.origin,
+                self.latest_update.as_ref(),
+                self.carriers,
+                self.perceived_time.now_millis(),
+                self.units,
+            ),
+            backlog::view(
+                self.latest_update
+                    .as_ref()
+                    .and_then(|update| update.backlog.as_ref()),
+                self.perceived_time.now_millis() / 1000,
+            ),
+            sectors::view(
+                self.latest_update
+                    .as_ref()
+                    .map_or(&[], |update| update.sectors.as_slice()),
+                self.config.show_sectors,
+            ),
+            banner::view(&self.alerts),
+            container(with_connection_status)
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .center_x()
+                .center_y()
+        ]
+        .into()
+    }
+
+    fn theme(&self) -> Theme {
+        match self.server_config.theme {
+            Some(ThemeHint::Dark) => Theme::Dark,
+            Some(ThemeHint::Light) | None => Theme::Light,
+        }
     }
 
     fn subscription(&self) -> iced::Subscription<Self::Message> {
-        iced::Subscription::batch(vec![
-            client::connect(self.gateway_uri.to_string()).map(Into::into),
-            iced::time::every(Duration::from_millis(
-                1000 / (CLIENT_FRAME_RATE - (CLIENT_FRAME_RATE / 10)),
-            ))
-            .map(|_| Message::IncrementPerceivedTime),
-        ])
+        let mut subscriptions = vec![iced::subscription::events_with(
+            |event, _status| match event {
+                iced::Event::Window(window::Event::Focused) => Some(Message::WindowFocused(true)),
+                iced::Event::Window(window::Event::Unfocused) => {
+                    Some(Message::WindowFocused(false))
+                }
+                iced::Event::Window(window::Event::Resized { width, height }) => {
+                    Some(Message::WindowResized(width, height))
+                }
+                iced::Event::Window(window::Event::CloseRequested) => Some(Message::CloseRequested),
+                _ => None,
+            },
+        )];
+        if let Some(server_uri) = &self.target_uri {
+            subscriptions
+                .push(client::connect(server_uri.to_string(), self.retry_policy).map(Into::into));
+        }
+
+        // Stop animating while nobody is looking at the window. Updates from the server continue
+        // to be received, with only the latest being kept until rendering resumes.
+        if !self.is_paused() {
+            subscriptions.push(
+                iced::time::every(Duration::from_millis(
+                    1000 / (CLIENT_FRAME_RATE - (CLIENT_FRAME_RATE / 10)),
+                ))
+                .map(|_| Message::IncrementPerceivedTime),
+            );
+        }
+
+        iced::Subscription::batch(subscriptions)
     }
 }
 
+impl Gui {
+    /// Whether rendering is currently paused because the window is unfocused or minimized
+    fn is_paused(&self) -> bool {
+        !self.is_focused || self.is_minimized
+    }
+
+    /// Stops recording the map, saving what was recorded in the background
+    fn finish_recording(&mut self) -> Command<Message> {
+        let Some(recording) = self.recording.take() else {
+            return Command::none();
+        };
+        let finished_at = self.perceived_time.now_millis() / 1000;
+
+        Command::perform(
+            async move { recording.save(finished_at) },
+            Message::CaptureSaved,
+        )
+    }
+
+    /// Warns of destinations in the client's scenario which the server's doesn't have, as orders &
+    /// flights for the server's destinations are matched up with the client's by name
+    fn check_destination_names(&self) {
+        let names = &self.server_config.destination_names;
+        if names.is_empty() || self.scenario_destinations.is_empty() {
+            return;
+        }
+        let mut unknown = self
+            .scenario_destinations
+            .keys()
+            .map(|id| id.as_str())
+            .filter(|name| !names.iter().any(|known| known == name))
+            .collect::<Vec<_>>();
+        if !unknown.is_empty() {
+            unknown.sort();
+            log::warn!(
+                "the server's scenario doesn't have {} of this client's destinations: {}",
+                unknown.len(),
+                unknown.join(", ")
+            );
+        }
+    }
+}
+
+/// Loads the sample destinations & orders used to render the map & KPIs, along with the nest
+/// from `ORIGIN` if it has been moved
+async fn load_scenario() -> Result<(Vec<Destination>, Destination, Vec<Order>), String> {
+    let origin = match std::env::var("ORIGIN") {
+        Ok(origin) => origin.parse()?,
+        Err(_) => Destination::default_origin(),
+    };
+    let (destinations, orders) = iced::futures::try_join!(
+        Destination::from_csv(schema::SAMPLE_DESTINATIONS_CSV_PATH),
+        Order::from_csv(schema::SAMPLE_ORDERS_CSV_PATH),
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok((destinations, origin, orders))
+}
+
+/// Reads the events recorded in a journal by the server, or in each segment of a journal directory
+async fn load_journal(path: String) -> Result<Vec<client::Event>, String> {
+    let events = schema::journal::read(&path)?;
+
+    Ok(events
+        .into_iter()
+        .filter_map(client::Event::from_monitor_event)
+        .collect())
+}
+
 impl From<client::Event> for Message {
     fn from(event: client::Event) -> Self {
         match event {
             client::Event::Connected(sender) => Self::Connected(sender),
-            client::Event::Disconnected => Self::Disconnected,
+            client::Event::Disconnected(error) => Self::Disconnected(error),
+            client::Event::Backoff { until } => Self::Backoff(until),
             client::Event::StatusUpdate(update) => Self::StatusUpdate(update),
+            client::Event::SimulationReset => Self::SimulationReset,
+            client::Event::Alert(alert) => Self::Alert(alert),
+            client::Event::DestinationChanges(changes) => Self::DestinationChanges(changes),
+            client::Event::SimulationStarted(id) => Self::SimulationStarted(id),
+            client::Event::DecodeFailed(error) => Self::DecodeFailed(error),
+            client::Event::ClientConfig(config) => Self::ClientConfig(config),
         }
     }
 }
@@ -1,45 +1,107 @@
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::collections::{hash_map::DefaultHasher, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::time::Instant;
 
+use iced::mouse::{self, ScrollDelta};
 use iced::widget::canvas;
-use iced::widget::canvas::{Path, Text};
+use iced::widget::canvas::{event, Path, Stroke, Text};
 use iced::Color;
 use iced::Size;
-use iced::{Element, Length, Point, Renderer, Theme};
-use schema::{Destination, DestinationName, StatusUpdate};
+use iced::{Element, Length, Point, Rectangle, Renderer, Theme, Vector};
+use schema::geometry::{DistanceMetric, Euclidean, Manhattan};
+use schema::units::Units;
+use schema::{
+    CarrierMode, Destination, DestinationId, FlightId, FlightPhase, MapExtents, Priority,
+    SectorOccupancy, StatusUpdate,
+};
 
 use super::Message;
+use crate::background::Background;
+use crate::sectors::FULL_COLOR;
 
 // TODO: these should come from BE
-const TOTAL_CARRIERS: usize = 10;
-const CARRIER_SPEED_MPS: u64 = 30;
+pub const CARRIER_SPEED_MPS: u64 = 30;
+pub const GROUND_SPEED_MPS: u64 = 15;
 
+/// Returns the metric distances are measured by, & the speed in meters per second, of carriers
+/// travelling by the provided mode
+pub fn travel(mode: CarrierMode) -> (&'static dyn DistanceMetric, u64) {
+    match mode {
+        CarrierMode::Aerial => (&Euclidean, CARRIER_SPEED_MPS),
+        CarrierMode::Ground => (&Manhattan, GROUND_SPEED_MPS),
+    }
+}
+
+/// Zoom level below which destinations are collapsed into a single symbol for their region
+const CLUSTER_ZOOM: f32 = 2.0;
+const MIN_ZOOM: f32 = 1.0;
+const MAX_ZOOM: f32 = 8.0;
+/// Size in pixels of the scenario along its longest axis, when not zoomed
+const MAP_SIZE: f32 = 550.0;
+/// Roughly how many grid lines span the width of the map, whatever the zoom
+const GRID_LINES: f32 = 6.0;
+
+/// Fastest a carrier's symbol turns toward its heading, so that it swings round at each stop
+/// rather than snapping to the next leg
+const TURN_RADIANS_PER_S: f32 = 2.0 * std::f32::consts::PI;
+
+/// Seconds of shelf life left at which a perishable order on board is marked at risk of spoiling
+const AT_RISK_S: u64 = 10 * 60;
+
+#[allow(clippy::too_many_arguments)]
 pub fn view<'a>(
-    destinations: &HashMap<DestinationName, Destination>,
+    destinations: &HashMap<DestinationId, Destination>,
+    deactivated: &HashSet<DestinationId>,
+    origin: &Destination,
     update: &StatusUpdate,
+    // Number of carriers in the fleet, not counting ground vehicles
+    carriers: u32,
     // Optimistic client representation of "scheduler-time"
     perceived_time_millis: u64,
+    units: Units,
+    // Bounds to frame the map to, rather than fitting it to the destinations
+    framed: Option<MapExtents>,
+    background: Option<&'a Background>,
+    // Carriers in each sector of airspace, if the sectors are overlaid
+    sectors: Option<&'a [SectorOccupancy]>,
+    // Where the map was last drawn in the window, for screenshots to be cropped to
+    bounds: &'a Cell<Option<Rectangle>>,
 ) -> Element<'a, Message> {
-    let (max_x, max_y) = destinations
-        .values()
-        .fold((0.0f32, 0.0f32), |(x, y), dest| {
-            (x.max(dest.east_m as f32), y.max(dest.north_m as f32))
-        });
-    let (min_x, min_y) = destinations
-        .values()
-        .fold((0.0f32, 0.0f32), |(x, y), dest| {
-            (x.min(dest.east_m as f32), y.min(dest.north_m as f32))
-        });
-
-    let (scale_x, scale_y) = (max_x - min_x, max_y - min_y);
-    let origin = ((0.0 - min_x) / scale_x, (0.0 - min_y) / scale_y);
+    let extents = match framed {
+        Some(framed) => Extents::framing(framed),
+        None => Extents::new(destinations, origin),
+    };
 
     let dest_positions = destinations
         .values()
         .map(|dest| {
-            let y = ((dest.north_m as f32 * -1.0) - min_y) / scale_y;
-            let x = (dest.east_m as f32 - min_x) / scale_x;
+            let (x, y) = extents.normalize(dest.east_m as f32, dest.north_m as f32);
 
-            (dest.name.to_string(), x, y)
+            DestinationSymbol {
+                name: dest.name.to_string(),
+                region: dest.region.clone(),
+                x,
+                y,
+                deactivated: deactivated.contains(&dest.name),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // Each region is represented by a symbol at the center of its destinations
+    let mut regions = HashMap::<String, (usize, f32, f32)>::new();
+    for dest in &dest_positions {
+        if let Some(region) = &dest.region {
+            let (count, sum_x, sum_y) = regions.entry(region.to_string()).or_default();
+            *count += 1;
+            *sum_x += dest.x;
+            *sum_y += dest.y;
+        }
+    }
+    let region_positions = regions
+        .into_iter()
+        .map(|(region, (count, sum_x, sum_y))| {
+            (region, count, sum_x / count as f32, sum_y / count as f32)
         })
         .collect::<Vec<_>>();
 
@@ -47,85 +109,751 @@ pub fn view<'a>(
         .flights
         .iter()
         .map(|flight| {
-            let (east_m, north_m, n) = flight.current_position(
-                destinations,
-                perceived_time_millis / 1000,
-                CARRIER_SPEED_MPS,
-            );
+            let current_time = perceived_time_millis / 1000;
+            let (metric, speed_mps) = travel(flight.mode);
+            let (east_m, north_m, n) =
+                flight.current_position(metric, destinations, origin, current_time, speed_mps);
+            let phase = flight.phase(metric, destinations, origin, current_time, speed_mps);
+
+            let (x, y) = extents.normalize(east_m, north_m);
+            // Carriers head wherever they're about to move to along their current leg
+            let (next_east_m, next_north_m, _) =
+                flight.current_position(metric, destinations, origin, current_time + 1, speed_mps);
+            let (next_x, next_y) = extents.normalize(next_east_m, next_north_m);
 
-            let y = ((north_m * -1.0) - min_y) / scale_y;
-            let x = (east_m - min_x) / scale_x;
+            // Orders are delivered in turn, so those still on board are the last few
+            let on_board = &flight.orders[flight.orders.len().saturating_sub(n)..];
+            let payload = match on_board {
+                [] => Payload::Empty,
+                _ if on_board
+                    .iter()
+                    .any(|order| order.priority == Priority::Emergency) =>
+                {
+                    Payload::Emergency
+                }
+                _ => Payload::Resupply,
+            };
+            let at_risk = on_board.iter().any(|order| {
+                order.shelf_life.is_some_and(|shelf_life| {
+                    flight.launch_time + shelf_life < current_time + AT_RISK_S
+                })
+            });
+
+            // Consecutive orders for the same destination are handed over at a single stop, &
+            // diverted carriers head straight home without making the rest
+            let mut stops = Vec::<(DestinationId, u32)>::new();
+            if flight.diversion.is_none() {
+                for order in on_board {
+                    match stops.last_mut() {
+                        Some((destination, packages)) if *destination == order.destination => {
+                            *packages += order.quantity;
+                        }
+                        _ => stops.push((order.destination, order.quantity)),
+                    }
+                }
+            }
+            let stops = stops
+                .into_iter()
+                .filter_map(|(destination, packages)| {
+                    let dest = destinations.get(&destination)?;
+                    let (x, y) = extents.normalize(dest.east_m as f32, dest.north_m as f32);
+                    Some((x, y, packages))
+                })
+                .collect();
 
-            (n, x, y)
+            CarrierSymbol {
+                flight: flight.id,
+                orders: n,
+                x,
+                y,
+                heading: heading(next_x - x, next_y - y),
+                payload,
+                diverted: flight.diversion.is_some(),
+                at_risk,
+                stops,
+                grounded: phase != FlightPhase::Cruising,
+                ground: flight.mode == CarrierMode::Ground,
+            }
         })
         .collect::<Vec<_>>();
 
+    // Only the carriers move between updates, so the rest of the map (including any background)
+    // is redrawn only when the view, the destinations or the units they're labelled in change
+    let mut hasher = DefaultHasher::new();
+    for dest in &dest_positions {
+        (
+            &dest.name,
+            dest.x.to_bits(),
+            dest.y.to_bits(),
+            dest.deactivated,
+        )
+            .hash(&mut hasher);
+    }
+    for (region, count, x, y) in &region_positions {
+        (region, count, x.to_bits(), y.to_bits()).hash(&mut hasher);
+    }
+    units.hash(&mut hasher);
+
     canvas(MapCanvas {
         dest_positions,
+        region_positions,
+        static_fingerprint: hasher.finish(),
         carrier_positions,
-        origin,
-        cache: Default::default(),
+        carriers,
+        origin_name: origin.name.to_string(),
+        origin: extents.normalize(origin.east_m as f32, origin.north_m as f32),
+        extents,
+        units,
+        background,
+        sectors: sectors.unwrap_or_default(),
+        bounds,
     })
     .width(Length::Fixed(600.0))
     .height(Length::Fixed(600.0))
     .into()
 }
 
-struct MapCanvas {
-    dest_positions: Vec<(String, f32, f32)>,
-    carrier_positions: Vec<(usize, f32, f32)>,
+struct MapCanvas<'a> {
+    dest_positions: Vec<DestinationSymbol>,
+    /// Name, number of destinations & center of each region
+    region_positions: Vec<(String, usize, f32, f32)>,
+    /// Fingerprint of everything drawn on the static layer other than the view
+    static_fingerprint: u64,
+    carrier_positions: Vec<CarrierSymbol>,
+    /// Number of carriers in the fleet, not counting ground vehicles
+    carriers: u32,
+    origin_name: String,
     origin: (f32, f32),
-    cache: canvas::Cache,
+    extents: Extents,
+    /// Units the grid & scale bar are labelled in
+    units: Units,
+    background: Option<&'a Background>,
+    /// Sectors overlaid on the map, along with the carriers in them
+    sectors: &'a [SectorOccupancy],
+    bounds: &'a Cell<Option<Rectangle>>,
 }
 
-impl<'a, Message> canvas::Program<Message, Renderer> for MapCanvas {
-    type State = ();
+struct DestinationSymbol {
+    name: String,
+    region: Option<String>,
+    x: f32,
+    y: f32,
+    /// Whether the destination no longer accepts orders
+    deactivated: bool,
+}
 
-    fn draw(
+struct CarrierSymbol {
+    flight: FlightId,
+    /// Orders remaining
+    orders: usize,
+    x: f32,
+    y: f32,
+    /// Direction the carrier is moving in, in radians clockwise from north, unless it's holding
+    /// position (e.g. while taking off or at a stop)
+    heading: Option<f32>,
+    payload: Payload,
+    /// Whether the carrier is returning to base early
+    diverted: bool,
+    /// Whether a perishable order on board is close to spoiling, or has spoiled
+    at_risk: bool,
+    /// Position of each stop the carrier has yet to make, in the order it makes them, & the
+    /// number of packages it's to deliver there
+    stops: Vec<(f32, f32, u32)>,
+    /// Whether the carrier is taking off or landing
+    grounded: bool,
+    /// Whether the carrier is a ground vehicle, driving rather than flying
+    ground: bool,
+}
+
+/// Most urgent of the orders a carrier has on board, which it's colored by
+#[derive(Clone, Copy)]
+enum Payload {
+    /// Carrying at least one emergency order
+    Emergency,
+    /// Carrying only resupply orders
+    Resupply,
+    /// Returning to the nest with nothing left to deliver
+    Empty,
+}
+
+/// Bounds of the scenario in meters, which positions on the map are relative to
+#[derive(Clone, Copy)]
+struct Extents {
+    min_east_m: f32,
+    max_north_m: f32,
+    /// Extent of the scenario along its longest axis, so that both axes share the same scale
+    span_m: f32,
+}
+
+impl Extents {
+    /// Bounds of the destinations & the origin
+    fn new(destinations: &HashMap<DestinationId, Destination>, origin: &Destination) -> Self {
+        let (x, y) = (origin.east_m as f32, origin.north_m as f32);
+        let (min_x, min_y, max_x, max_y) =
+            destinations
+                .values()
+                .fold((x, y, x, y), |(min_x, min_y, max_x, max_y), dest| {
+                    let (x, y) = (dest.east_m as f32, dest.north_m as f32);
+                    (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y))
+                });
+
+        Self {
+            min_east_m: min_x,
+            max_north_m: max_y,
+            span_m: (max_x - min_x).max(max_y - min_y).max(1.0),
+        }
+    }
+
+    /// Bounds provided by the server, rather than fitted to the scenario
+    fn framing(extents: MapExtents) -> Self {
+        Self {
+            min_east_m: extents.west_m as f32,
+            max_north_m: extents.north_m as f32,
+            span_m: ((extents.east_m - extents.west_m).max(extents.north_m - extents.south_m)
+                as f32)
+                .max(1.0),
+        }
+    }
+
+    /// Position relative to the extents, with north up
+    fn normalize(&self, east_m: f32, north_m: f32) -> (f32, f32) {
+        (
+            (east_m - self.min_east_m) / self.span_m,
+            (self.max_north_m - north_m) / self.span_m,
+        )
+    }
+
+    fn denormalize(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            self.min_east_m + x * self.span_m,
+            self.max_north_m - y * self.span_m,
+        )
+    }
+}
+
+/// Zoom & pan of the map, which persists across updates
+pub struct MapState {
+    zoom: f32,
+    offset: Vector,
+    /// Cursor position at which the map was last dragged from, while dragging
+    dragging_from: Option<Point>,
+    /// Background, grid, destinations & regions, which are cached until the view or the
+    /// destinations change
+    static_layer: canvas::Cache,
+    /// Fingerprint of the destinations the static layer was last drawn with
+    static_fingerprint: Cell<u64>,
+    /// Direction each carrier's symbol was last drawn facing, & when, which it turns from toward
+    /// the carrier's heading
+    headings: RefCell<HashMap<FlightId, (f32, Instant)>>,
+}
+
+impl Default for MapState {
+    fn default() -> Self {
+        Self {
+            zoom: MIN_ZOOM,
+            offset: Vector::new(0.0, 0.0),
+            dragging_from: None,
+            static_layer: canvas::Cache::new(),
+            static_fingerprint: Cell::new(0),
+            headings: RefCell::default(),
+        }
+    }
+}
+
+impl MapState {
+    /// Maps a position relative to the bounds of the scenario onto the canvas
+    fn project(&self, x: f32, y: f32) -> Point {
+        Point::new(MAP_SIZE * x * self.zoom, (MAP_SIZE * y + 50.0) * self.zoom) + self.offset
+    }
+
+    /// Maps a point on the canvas to a position relative to the bounds of the scenario
+    fn unproject(&self, point: Point) -> (f32, f32) {
+        let point = point - self.offset;
+        (
+            point.x / (MAP_SIZE * self.zoom),
+            (point.y / self.zoom - 50.0) / MAP_SIZE,
+        )
+    }
+}
+
+impl MapCanvas<'_> {
+    /// Draws the cells of the background beneath the rest of the map, stretched between where
+    /// its edges lie in the scenario so that it pans & zooms along with the destinations
+    fn draw_background(&self, frame: &mut canvas::Frame, state: &MapState, bounds: Rectangle) {
+        let Some(background) = self.background else {
+            return;
+        };
+        let config = &background.config;
+        let to_canvas = |east_m, north_m| {
+            let (x, y) = self.extents.normalize(east_m, north_m);
+            state.project(x, y)
+        };
+        let north_west = to_canvas(config.west_m, config.north_m);
+        let south_east = to_canvas(config.east_m, config.south_m);
+        let cell = Size::new(
+            (south_east.x - north_west.x) / background.columns as f32,
+            (south_east.y - north_west.y) / background.rows as f32,
+        );
+        if cell.width <= 0.0 || cell.height <= 0.0 {
+            return;
+        }
+
+        let canvas = Rectangle::new(Point::ORIGIN, bounds.size());
+        for (i, color) in background.cells.iter().enumerate() {
+            let (column, row) = (i as u32 % background.columns, i as u32 / background.columns);
+            let position = Point::new(
+                north_west.x + column as f32 * cell.width,
+                north_west.y + row as f32 * cell.height,
+            );
+            // Cells off the canvas are skipped, as most are while zoomed in
+            if canvas.intersects(&Rectangle::new(position, cell)) {
+                frame.fill_rectangle(position, cell, *color);
+            }
+        }
+    }
+
+    /// Draws grid lines at coordinates which are round in the display units, labelled in them,
+    /// along with a scale bar of the same distance
+    fn draw_grid(
         &self,
-        _state: &Self::State,
-        renderer: &Renderer,
-        _theme: &Theme,
-        bounds: iced::Rectangle,
-        _cursor: iced::mouse::Cursor,
-    ) -> Vec<canvas::Geometry> {
-        let size = bounds.size();
-        let (width, height) = (550.0, 550.0);
-        let graph = self.cache.draw(renderer, size, |frame| {
-            let position = Point::new(width * self.origin.0, height * self.origin.1 + 50.0);
+        frame: &mut canvas::Frame,
+        state: &MapState,
+        bounds: Rectangle,
+        ink: Color,
+    ) {
+        let (west, north) = {
+            let (x, y) = state.unproject(Point::ORIGIN);
+            self.extents.denormalize(x, y)
+        };
+        let (east, south) = {
+            let (x, y) = state.unproject(Point::new(bounds.width, bounds.height));
+            self.extents.denormalize(x, y)
+        };
+        let unit_m = self.units.distance_unit_m() as f32;
+        let step_m = grid_step(((east - west) / GRID_LINES).max(1.0) / unit_m) * unit_m;
+        let to_canvas = |east_m, north_m| {
+            let (x, y) = self.extents.normalize(east_m, north_m);
+            state.project(x, y)
+        };
+
+        let color = Color::from_rgb8(200, 200, 200);
+        let label = |content, position| Text {
+            content,
+            position,
+            color: Color::from_rgb8(140, 140, 140),
+            size: 12.0,
+            ..Default::default()
+        };
+
+        let eastings = ((west / step_m).ceil() as i64..=(east / step_m).floor() as i64)
+            .map(|i| i as f32 * step_m)
+            .collect::<Vec<_>>();
+        let northings = ((south / step_m).ceil() as i64..=(north / step_m).floor() as i64)
+            .map(|i| i as f32 * step_m)
+            .collect::<Vec<_>>();
+
+        let grid = Path::new(|builder| {
+            for east_m in &eastings {
+                let x = to_canvas(*east_m, 0.0).x;
+                builder.move_to(Point::new(x, 0.0));
+                builder.line_to(Point::new(x, bounds.height));
+            }
+            for north_m in &northings {
+                let y = to_canvas(0.0, *north_m).y;
+                builder.move_to(Point::new(0.0, y));
+                builder.line_to(Point::new(bounds.width, y));
+            }
+        });
+        frame.stroke(&grid, Stroke::default().with_color(color).with_width(1.0));
+
+        for east_m in &eastings {
+            let x = to_canvas(*east_m, 0.0).x;
+            frame.fill_text(label(
+                self.units.format_distance(*east_m as f64),
+                Point::new(x + 2.0, 2.0),
+            ));
+        }
+        for north_m in &northings {
+            let y = to_canvas(0.0, *north_m).y;
+            frame.fill_text(label(
+                self.units.format_distance(*north_m as f64),
+                Point::new(2.0, y + 2.0),
+            ));
+        }
+
+        // Scale bar in the bottom left corner
+        let length = to_canvas(step_m, 0.0).x - to_canvas(0.0, 0.0).x;
+        let (left, bottom) = (20.0, bounds.height - 20.0);
+        let bar = Path::new(|builder| {
+            builder.move_to(Point::new(left, bottom - 5.0));
+            builder.line_to(Point::new(left, bottom));
+            builder.line_to(Point::new(left + length, bottom));
+            builder.line_to(Point::new(left + length, bottom - 5.0));
+        });
+        frame.stroke(&bar, Stroke::default().with_width(2.0).with_color(ink));
+        frame.fill_text(Text {
+            content: self.units.format_distance(step_m as f64),
+            position: Point::new(left, bottom - 20.0),
+            color: ink,
+            ..Default::default()
+        });
+    }
+
+    /// Outlines each sector, labelled with the carriers in it & shaded while it's full. Sectors
+    /// are drawn with the carriers, since their occupancy changes with every update.
+    fn draw_sectors(&self, frame: &mut canvas::Frame, state: &MapState, ink: Color) {
+        for occupancy in self.sectors {
+            let extents = occupancy.sector.extents;
+            let to_canvas = |east_m: i64, north_m: i64| {
+                let (x, y) = self.extents.normalize(east_m as f32, north_m as f32);
+                state.project(x, y)
+            };
+            let north_west = to_canvas(extents.west_m, extents.north_m);
+            let south_east = to_canvas(extents.east_m, extents.south_m);
+            let size = Size::new(south_east.x - north_west.x, south_east.y - north_west.y);
+
+            let full = occupancy.is_full();
+            if full {
+                frame.fill_rectangle(
+                    north_west,
+                    size,
+                    Color {
+                        a: 0.1,
+                        ..FULL_COLOR
+                    },
+                );
+            }
+            let outline = Path::rectangle(north_west, size);
+            let color = match full {
+                true => FULL_COLOR,
+                false => Color { a: 0.5, ..ink },
+            };
+            frame.stroke(
+                &outline,
+                Stroke::default().with_color(color).with_width(1.5),
+            );
             frame.fill_text(Text {
                 content: format!(
-                    "Origin ({} carriers available)",
-                    TOTAL_CARRIERS - self.carrier_positions.len()
+                    "{} {}/{}",
+                    occupancy.sector.name, occupancy.flights, occupancy.sector.max_flights
                 ),
+                position: Point::new(north_west.x + 4.0, north_west.y + 4.0),
+                color,
+                size: 12.0,
+                ..Default::default()
+            });
+        }
+    }
+
+    /// Draws the destinations, collapsing those in a region into a single symbol when zoomed out
+    fn draw_destinations(&self, frame: &mut canvas::Frame, state: &MapState, ink: Color) {
+        let is_clustered = state.zoom < CLUSTER_ZOOM;
+
+        for dest in &self.dest_positions {
+            if is_clustered && dest.region.is_some() {
+                continue;
+            }
+
+            let position = state.project(dest.x, dest.y);
+            let dot = Path::circle(position, 5.0);
+            let color = match dest.deactivated {
+                true => Color::from_rgb8(180, 180, 180),
+                false => ink,
+            };
+            frame.fill(&dot, color);
+            frame.fill_text(Text {
+                content: dest.name.to_string(),
                 position,
+                color,
                 ..Default::default()
             });
+        }
 
-            for (name, x, y) in &self.dest_positions {
-                let position = Point::new(width * x, height * y + 50.0);
-                let dot = Path::circle(position, 5.0);
-                frame.fill(&dot, Color::BLACK);
+        if is_clustered {
+            for (region, count, x, y) in &self.region_positions {
+                let position = state.project(*x, *y);
+                let dot = Path::circle(position, 10.0);
+                frame.fill(&dot, Color::from_rgb8(90, 90, 90));
                 frame.fill_text(Text {
-                    content: name.to_string(),
-                    position,
+                    content: format!("{} ({})", region, count),
+                    position: Point::new(position.x + 12.0, position.y),
+                    color: ink,
                     ..Default::default()
                 });
             }
+        }
+    }
+}
 
-            for (n, x, y) in &self.carrier_positions {
-                let position = Point::new(width * x, height * y + 50.0);
-                let symbol = Path::rectangle(position, Size::new(10.0, 10.0));
-                frame.fill(&symbol, Color::from_rgb8(0, 0, 255));
-                frame.fill_text(Text {
-                    content: n.to_string(),
-                    position: Point::new(position.x, position.y + 15.0),
-                    color: Color::from_rgb8(0, 0, 255),
-                    ..Default::default()
-                });
+impl MapCanvas<'_> {
+    /// Draws the route a carrier has left to fly through its remaining stops, marking each with
+    /// the number of packages to deliver there
+    fn draw_stops(
+        &self,
+        frame: &mut canvas::Frame,
+        state: &MapState,
+        carrier: &CarrierSymbol,
+        position: Point,
+        color: Color,
+    ) {
+        if carrier.stops.is_empty() {
+            return;
+        }
+
+        // Carriers are drawn from their top left corner, so the route leaves from their center
+        let stops = carrier
+            .stops
+            .iter()
+            .map(|(x, y, packages)| (state.project(*x, *y), *packages))
+            .collect::<Vec<_>>();
+        let route = Path::new(|builder| {
+            builder.move_to(Point::new(position.x + 5.0, position.y + 5.0));
+            for (stop, _) in &stops {
+                builder.line_to(*stop);
+            }
+        });
+        frame.stroke(
+            &route,
+            Stroke::default()
+                .with_width(1.0)
+                .with_color(Color { a: 0.35, ..color }),
+        );
+
+        for (stop, packages) in stops {
+            frame.stroke(
+                &Path::circle(stop, 4.0),
+                Stroke::default().with_width(1.5).with_color(color),
+            );
+            frame.fill_text(Text {
+                content: packages.to_string(),
+                position: Point::new(stop.x + 6.0, stop.y - 14.0),
+                color,
+                size: 12.0,
+                ..Default::default()
+            });
+        }
+    }
+}
+
+/// Direction of a movement across the map, in radians clockwise from north, if there is any
+fn heading(dx: f32, dy: f32) -> Option<f32> {
+    // Positions on the map have north up, so moving north is moving toward smaller y
+    (dx != 0.0 || dy != 0.0).then(|| dx.atan2(-dy))
+}
+
+/// Turns from one direction toward another along the shortest way round, by at most the angle
+/// provided
+fn turn_toward(from: f32, to: f32, max_radians: f32) -> f32 {
+    use std::f32::consts::{PI, TAU};
+
+    let difference = (to - from + PI).rem_euclid(TAU) - PI;
+    from + difference.clamp(-max_radians, max_radians)
+}
+
+/// Arrow pointing in a direction around its center. Ground vehicles are drawn as plain triangles,
+/// & aerial carriers as darts with a notch in their tail.
+fn arrow(center: Point, heading: f32, size: f32, ground: bool) -> Path {
+    let (sin, cos) = heading.sin_cos();
+    // From distances ahead of & to the right of the center
+    let point = |ahead: f32, right: f32| {
+        Point::new(
+            center.x + size * (ahead * sin + right * cos),
+            center.y + size * (right * sin - ahead * cos),
+        )
+    };
+    Path::new(|builder| {
+        builder.move_to(point(0.6, 0.0));
+        builder.line_to(point(-0.5, 0.5));
+        if !ground {
+            builder.line_to(point(-0.2, 0.0));
+        }
+        builder.line_to(point(-0.5, -0.5));
+        builder.close();
+    })
+}
+
+/// Rounds a distance up to the nearest 1, 2 or 5 times a power of ten
+fn grid_step(min: f32) -> f32 {
+    let magnitude = 10f32.powf(min.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|multiple| multiple * magnitude)
+        .find(|step| *step >= min)
+        .unwrap_or(10.0 * magnitude)
+}
+
+impl<Message> canvas::Program<Message, Renderer> for MapCanvas<'_> {
+    type State = MapState;
+
+    fn update(
+        &self,
+        state: &mut Self::State,
+        event: canvas::Event,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> (event::Status, Option<Message>) {
+        let Some(position) = cursor.position_in(bounds) else {
+            state.dragging_from = None;
+            return (event::Status::Ignored, None);
+        };
+
+        match event {
+            // Zoom around the cursor
+            canvas::Event::Mouse(mouse::Event::WheelScrolled { delta }) => {
+                let lines = match delta {
+                    ScrollDelta::Lines { y, .. } => y,
+                    ScrollDelta::Pixels { y, .. } => y / 60.0,
+                };
+                let zoom = (state.zoom * 1.25f32.powf(lines)).clamp(MIN_ZOOM, MAX_ZOOM);
+                let anchor = Vector::new(position.x, position.y);
+                state.offset = anchor + (state.offset - anchor) * (zoom / state.zoom);
+                state.zoom = zoom;
+                state.static_layer.clear();
+
+                (event::Status::Captured, None)
             }
+
+            // Pan by dragging
+            canvas::Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
+                state.dragging_from = Some(position);
+
+                (event::Status::Captured, None)
+            }
+            canvas::Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+                state.dragging_from = None;
+
+                (event::Status::Captured, None)
+            }
+            canvas::Event::Mouse(mouse::Event::CursorMoved { .. }) => match state.dragging_from {
+                Some(from) => {
+                    state.offset = state.offset + (position - from);
+                    state.dragging_from = Some(position);
+                    state.static_layer.clear();
+                    (event::Status::Captured, None)
+                }
+                None => (event::Status::Ignored, None),
+            },
+
+            // Reset the view
+            canvas::Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Right)) => {
+                *state = MapState::default();
+
+                (event::Status::Captured, None)
+            }
+
+            _ => (event::Status::Ignored, None),
+        }
+    }
+
+    fn draw(
+        &self,
+        state: &Self::State,
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<canvas::Geometry> {
+        self.bounds.set(Some(bounds));
+        // Labels are drawn in the theme's text color, so that they stay legible in a dark theme
+        let ink = theme.palette().text;
+        let fingerprint = self.static_fingerprint ^ u64::from(u32::from_be_bytes(ink.into_rgba8()));
+        if state.static_fingerprint.replace(fingerprint) != fingerprint {
+            state.static_layer.clear();
+        }
+        let static_layer = state.static_layer.draw(renderer, bounds.size(), |frame| {
+            self.draw_background(frame, state, bounds);
+            self.draw_grid(frame, state, bounds, ink);
+            self.draw_destinations(frame, state, ink);
+        });
+
+        let mut frame = canvas::Frame::new(renderer, bounds.size());
+        self.draw_sectors(&mut frame, state, ink);
+        let position = state.project(self.origin.0, self.origin.1);
+        frame.fill_text(Text {
+            content: format!(
+                "{} ({} carriers available)",
+                self.origin_name,
+                (self.carriers as usize).saturating_sub(
+                    self.carrier_positions
+                        .iter()
+                        .filter(|carrier| !carrier.ground)
+                        .count()
+                )
+            ),
+            position,
+            color: ink,
+            ..Default::default()
         });
 
-        vec![graph]
+        // Each carrier's symbol turns toward its heading from where it last faced, keeping its
+        // last heading while it holds position. Carriers which have landed are forgotten.
+        let now = Instant::now();
+        let mut headings = state.headings.borrow_mut();
+        headings.retain(|flight, _| {
+            self.carrier_positions
+                .iter()
+                .any(|carrier| carrier.flight == *flight)
+        });
+
+        // Carriers taking off or landing pulse, about once a second
+        let pulse = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| (elapsed.as_secs_f32() * std::f32::consts::TAU).sin())
+            .unwrap_or_default();
+        for carrier in &self.carrier_positions {
+            let position = state.project(carrier.x, carrier.y);
+            let color = match (carrier.diverted, carrier.payload) {
+                (true, _) => Color::from_rgb8(230, 120, 0),
+                (false, Payload::Emergency) => Color::from_rgb8(220, 0, 0),
+                (false, Payload::Resupply) => Color::from_rgb8(0, 0, 255),
+                (false, Payload::Empty) => Color::from_rgb8(140, 140, 140),
+            };
+            self.draw_stops(&mut frame, state, carrier, position, color);
+            let size = match carrier.grounded {
+                true => 10.0 + 4.0 * pulse,
+                false => 10.0,
+            };
+            let top_left = Point::new(
+                position.x - (size - 10.0) / 2.0,
+                position.y - (size - 10.0) / 2.0,
+            );
+            let facing = match headings.get(&carrier.flight) {
+                Some((facing, at)) => {
+                    let max_radians = TURN_RADIANS_PER_S * (now - *at).as_secs_f32();
+                    carrier.heading.map_or(*facing, |heading| {
+                        turn_toward(*facing, heading, max_radians)
+                    })
+                }
+                None => carrier.heading.unwrap_or_default(),
+            };
+            headings.insert(carrier.flight, (facing, now));
+            let center = Point::new(position.x + 5.0, position.y + 5.0);
+            frame.fill(&arrow(center, facing, size, carrier.ground), color);
+            if carrier.at_risk {
+                frame.stroke(
+                    &Path::rectangle(
+                        Point::new(top_left.x - 3.0, top_left.y - 3.0),
+                        Size::new(size + 6.0, size + 6.0),
+                    ),
+                    Stroke::default()
+                        .with_width(2.0)
+                        .with_color(Color::from_rgb8(230, 180, 0)),
+                );
+            }
+            if carrier.grounded {
+                frame.stroke(
+                    &Path::circle(Point::new(position.x + 5.0, position.y + 5.0), 12.0),
+                    Stroke::default().with_width(1.0).with_color(color),
+                );
+            }
+            frame.fill_text(Text {
+                content: carrier.orders.to_string(),
+                position: Point::new(position.x, position.y + 15.0),
+                color,
+                ..Default::default()
+            });
+        }
+
+        vec![static_layer, frame.into_geometry()]
     }
 }
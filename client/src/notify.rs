@@ -0,0 +1,149 @@
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use iced::widget::{checkbox, row};
+use iced::Element;
+use schema::{Alert, AlertKind};
+
+use super::Message;
+
+/// Shortest wall-clock time between notifications, so that a burst of alerts doesn't bury the
+/// desktop. Alerts raised in between are still shown in the banners.
+const MIN_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Whether an alert is worth interrupting an operator for, e.g. an emergency left waiting, a
+/// carrier failing mid-flight or a flight awaiting approval
+pub fn is_critical(kind: AlertKind) -> bool {
+    match kind {
+        AlertKind::EmergencyWaiting
+        | AlertKind::CarrierFailure
+        | AlertKind::SurgeStarted
+        | AlertKind::SimulationFaulted
+        | AlertKind::ApprovalPending => true,
+        AlertKind::SloViolated
+        | AlertKind::DeliveryWindowMissed
+        | AlertKind::LaunchHeld
+        | AlertKind::SurgeEnded
+        | AlertKind::RuleTriggered => false,
+    }
+}
+
+/// Raises desktop notifications &/or plays a sound for critical alerts, as enabled in the config
+#[derive(Default)]
+pub struct Notifier {
+    last_notified: Option<Instant>,
+}
+
+impl Notifier {
+    /// Notifies of an alert if it's critical. Desktop notifications are only raised while the
+    /// window is in the background, whereas the sound is played either way.
+    pub fn alert(&mut self, alert: &Alert, desktop: bool, sound: bool, is_backgrounded: bool) {
+        let desktop = desktop && is_backgrounded;
+        if !is_critical(alert.kind) || !(desktop || sound) {
+            return;
+        }
+        if self
+            .last_notified
+            .is_some_and(|last| last.elapsed() < MIN_INTERVAL)
+        {
+            return;
+        }
+        self.last_notified = Some(Instant::now());
+
+        if desktop {
+            spawn(notification("Scheduler alert", &alert.message));
+        }
+        if sound {
+            spawn(alert_sound());
+        }
+    }
+}
+
+/// Command raising a desktop notification on this platform, if it has one
+fn notification(summary: &str, body: &str) -> Option<Command> {
+    if cfg!(target_os = "macos") {
+        let mut command = Command::new("osascript");
+        // Passed as arguments rather than spliced into the script, so that they needn't be escaped
+        command.args([
+            "-e",
+            "on run argv",
+            "-e",
+            "display notification (item 2 of argv) with title (item 1 of argv)",
+            "-e",
+            "end run",
+            summary,
+            body,
+        ]);
+        Some(command)
+    } else if cfg!(unix) {
+        let mut command = Command::new("notify-send");
+        command.args(["--urgency=critical", "--app-name=Scheduler", summary, body]);
+        Some(command)
+    } else {
+        None
+    }
+}
+
+/// Command playing the platform's warning sound, if it has one
+fn alert_sound() -> Option<Command> {
+    if cfg!(target_os = "macos") {
+        let mut command = Command::new("afplay");
+        command.arg("/System/Library/Sounds/Sosumi.aiff");
+        Some(command)
+    } else if cfg!(windows) {
+        let mut command = Command::new("powershell");
+        command.args([
+            "-NoProfile",
+            "-Command",
+            "[System.Media.SystemSounds]::Exclamation.Play()",
+        ]);
+        Some(command)
+    } else if cfg!(unix) {
+        let mut command = Command::new("canberra-gtk-play");
+        command.args(["--id=dialog-warning", "--description=Scheduler alert"]);
+        Some(command)
+    } else {
+        None
+    }
+}
+
+/// Runs a command in the background, waiting on it so that it doesn't linger once it exits
+fn spawn(command: Option<Command>) {
+    let Some(mut command) = command else {
+        log::debug!("notifications aren't supported on this platform");
+        return;
+    };
+    std::thread::spawn(move || {
+        let status = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+        match status {
+            Ok(status) if !status.success() => {
+                log::warn!("{:?} failed to notify: {}", command.get_program(), status)
+            }
+            Ok(_) => {}
+            Err(e) => log::warn!("couldn't run {:?}: {}", command.get_program(), e),
+        }
+    });
+}
+
+/// Toggles for desktop notifications & the sound played for critical alerts
+pub fn view<'a>(desktop: bool, sound: bool) -> Element<'a, Message> {
+    row![
+        checkbox(
+            "Notify of critical alerts in the background",
+            desktop,
+            Message::NotifyAlerts
+        )
+        .size(14)
+        .text_size(14),
+        checkbox("Play a sound", sound, Message::PlayAlertSound)
+            .size(14)
+            .text_size(14),
+    ]
+    .spacing(20)
+    .padding([5, 10])
+    .into()
+}
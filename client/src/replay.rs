@@ -0,0 +1,123 @@
+use iced::widget::{button, pick_list, row, text, text_input};
+use iced::{Alignment, Color, Element, Length};
+use schema::Speed;
+
+use super::Message;
+use crate::client::Event;
+
+/// Speeds which journals can be played back at, as multiples of real-time
+const SPEEDS: [u16; 6] = [1, 10, 50, 100, 200, 500];
+
+/// Playback of a journal recorded by the server, in place of a connection to one
+pub struct Replay {
+    pub path: String,
+    events: Vec<Event>,
+    /// Index of the next event to be played
+    position: usize,
+    pub speed: Speed,
+    pub is_paused: bool,
+}
+
+impl Replay {
+    pub fn new(path: String, events: Vec<Event>) -> Self {
+        Self {
+            path,
+            events,
+            position: 0,
+            speed: Speed::fast_forward(200).expect("speed"),
+            is_paused: false,
+        }
+    }
+
+    /// Returns the events which are due once the client has reached the perceived time, played at
+    /// the replay's speed. Updates are due once the previous update has been animated up to them.
+    pub fn due(&mut self, mut perceived_time_millis: u64, mut has_update: bool) -> Vec<Event> {
+        let mut due = vec![];
+        while let Some(event) = self.events.get(self.position) {
+            match event {
+                Event::StatusUpdate(update)
+                    if has_update && update.time * 1000 > perceived_time_millis =>
+                {
+                    break
+                }
+                Event::StatusUpdate(update) => {
+                    perceived_time_millis = update.time * 1000;
+                    has_update = true;
+                    let mut update = update.clone();
+                    update.speed = self.speed;
+                    due.push(Event::StatusUpdate(update));
+                }
+                Event::SimulationReset => {
+                    has_update = false;
+                    due.push(Event::SimulationReset);
+                }
+                event => due.push(event.clone()),
+            }
+            self.position += 1;
+        }
+        due
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.position == self.events.len()
+    }
+}
+
+/// Controls for opening a journal & playing it back
+pub fn view<'a>(
+    journal_path: &str,
+    replay: Option<&Replay>,
+    error: Option<&str>,
+) -> Element<'a, Message> {
+    let input = text_input("Journal file, e.g. journal.bin", journal_path)
+        .on_input(Message::JournalPathChanged)
+        .on_submit(Message::OpenJournal)
+        .width(Length::Fixed(320.0));
+    let mut content = row![
+        input,
+        button(text("Open journal")).on_press(Message::OpenJournal)
+    ]
+    .spacing(10)
+    .padding([0, 10])
+    .align_items(Alignment::Center);
+
+    if let Some(replay) = replay {
+        let toggle = match replay.is_paused {
+            true => button(text("Play")),
+            false => button(text("Pause")),
+        };
+        let status = match replay.is_finished() {
+            true => format!("Finished replaying {}", replay.path),
+            false => format!(
+                "Replaying {} ({}/{} events)",
+                replay.path,
+                replay.position,
+                replay.events.len()
+            ),
+        };
+        content = content
+            .push(toggle.on_press(Message::ToggleReplayPaused))
+            .push(pick_list(
+                SPEEDS
+                    .iter()
+                    .map(|rate| match rate {
+                        1 => Speed::RealTime,
+                        rate => Speed::fast_forward(*rate).expect("speed"),
+                    })
+                    .collect::<Vec<_>>(),
+                Some(replay.speed),
+                Message::ReplaySpeedChanged,
+            ))
+            .push(button(text("Close")).on_press(Message::CloseJournal))
+            .push(text(status).size(14));
+    }
+    if let Some(error) = error {
+        content = content.push(
+            text(format!("Journal error: {}", error))
+                .size(14)
+                .style(Color::from_rgb8(200, 0, 0)),
+        );
+    }
+
+    content.into()
+}
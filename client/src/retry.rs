@@ -0,0 +1,100 @@
+use std::env;
+use std::time::Duration;
+
+/// How long the client waits on the server, & how it backs off from retrying once it fails
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Longest to wait for a connection to the server to be made
+    pub connect_timeout: Duration,
+    /// Longest to wait for each RPC to be answered, or for a stream to be opened
+    pub rpc_timeout: Duration,
+    /// Delay before retrying the first failure, doubling with each failure after it
+    pub initial_backoff: Duration,
+    /// Longest delay between retries
+    pub max_backoff: Duration,
+    /// Failures in a row after which the circuit opens, holding off retries for the longest delay
+    pub failure_threshold: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(5),
+            rpc_timeout: Duration::from_secs(10),
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(60),
+            failure_threshold: 5,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Reads the policy from `CONNECT_TIMEOUT_S`, `RPC_TIMEOUT_S`, `MAX_BACKOFF_S` &
+    /// `FAILURE_THRESHOLD` where they're set, falling back to the defaults
+    pub fn from_env() -> Self {
+        let var = |name: &str| {
+            env::var(name)
+                .ok()
+                .and_then(|value| value.parse::<u64>().ok())
+        };
+        let seconds =
+            |name: &str, default: Duration| var(name).map_or(default, Duration::from_secs);
+        let default = Self::default();
+
+        Self {
+            connect_timeout: seconds("CONNECT_TIMEOUT_S", default.connect_timeout),
+            rpc_timeout: seconds("RPC_TIMEOUT_S", default.rpc_timeout),
+            initial_backoff: default.initial_backoff,
+            max_backoff: seconds("MAX_BACKOFF_S", default.max_backoff),
+            failure_threshold: var("FAILURE_THRESHOLD")
+                .map_or(default.failure_threshold, |threshold| threshold as u32),
+        }
+    }
+}
+
+/// Exponential backoff with jitter between attempts at something which keeps failing, e.g.
+/// connecting to the server. After enough failures in a row the circuit opens, & attempts are
+/// held off for the longest delay until one succeeds.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    policy: RetryPolicy,
+    /// Attempts which have failed since the last success
+    failures: u32,
+}
+
+impl Backoff {
+    pub fn new(policy: RetryPolicy) -> Self {
+        Self {
+            policy,
+            failures: 0,
+        }
+    }
+
+    /// Records a failed attempt, returning how long to wait before the next
+    pub fn failed(&mut self) -> Duration {
+        self.failures = self.failures.saturating_add(1);
+        let delay = match self.is_open() {
+            true => self.policy.max_backoff,
+            false => self
+                .policy
+                .initial_backoff
+                .saturating_mul(2_u32.saturating_pow(self.failures - 1))
+                .min(self.policy.max_backoff),
+        };
+
+        // Wait somewhere between half & all of the delay, so that clients which failed together
+        // don't all retry together
+        let jitter = (ulid::Ulid::new().random() % 1_000) as u32;
+        delay / 2 + (delay / 2) * jitter / 1_000
+    }
+
+    /// Records a successful attempt, so that the next failure is retried quickly again
+    pub fn succeeded(&mut self) {
+        self.failures = 0;
+    }
+
+    /// Whether attempts have failed often enough in a row to hold off for the longest delay
+    pub fn is_open(&self) -> bool {
+        self.failures >= self.policy.failure_threshold
+    }
+}
@@ -0,0 +1,40 @@
+use iced::widget::{checkbox, container, row, text};
+use iced::{theme, Color, Element, Length};
+use schema::SectorOccupancy;
+
+use super::Message;
+
+/// Color sectors are outlined in, & full sectors are labelled in
+pub const FULL_COLOR: Color = Color::from_rgb(0.86, 0.24, 0.0);
+
+/// Carriers in each sector of airspace, along with whether the sectors are overlaid on the map.
+/// Nothing is shown unless the scenario divides the airspace into sectors.
+pub fn view<'a>(sectors: &[SectorOccupancy], show_overlay: bool) -> Element<'a, Message> {
+    if sectors.is_empty() {
+        return row![].into();
+    }
+
+    let mut content = row![
+        text("Sectors").size(14),
+        checkbox("Show on map", show_overlay, Message::ShowSectors)
+            .size(14)
+            .text_size(14),
+    ]
+    .spacing(20);
+    for occupancy in sectors {
+        let label = text(format!(
+            "{} {}/{}",
+            occupancy.sector.name, occupancy.flights, occupancy.sector.max_flights
+        ))
+        .size(14);
+        content = content.push(match occupancy.is_full() {
+            true => label.style(FULL_COLOR),
+            false => label,
+        });
+    }
+
+    container(content.padding([5, 10]))
+        .width(Length::Fill)
+        .style(theme::Container::Box)
+        .into()
+}
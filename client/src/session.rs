@@ -0,0 +1,83 @@
+use std::env;
+use std::path::PathBuf;
+
+use schema::json::Value;
+use ulid::Ulid;
+
+use crate::config::Config;
+use crate::kpi::Kpis;
+
+/// History accumulated while monitoring a simulation, saved when the client exits so that it can
+/// be picked back up if the client is restarted while the same simulation is still running
+pub struct Session {
+    pub simulation_id: Ulid,
+    /// Counters of the KPIs, see `Kpis::to_json`
+    kpis: Value,
+}
+
+impl Session {
+    pub fn new(simulation_id: Ulid, kpis: &Kpis) -> Self {
+        Self {
+            simulation_id,
+            kpis: kpis.to_json(),
+        }
+    }
+
+    /// Location of the session file, `$CLIENT_SESSION_PATH` if set, otherwise alongside the
+    /// config file
+    pub fn path() -> Option<PathBuf> {
+        if let Ok(path) = env::var("CLIENT_SESSION_PATH") {
+            return Some(PathBuf::from(path));
+        }
+
+        Config::path().map(|path| path.with_file_name("session.json"))
+    }
+
+    /// Loads the most recently saved session, if there is a valid one
+    pub fn load() -> Option<Self> {
+        let path = Self::path()?;
+        let contents = std::fs::read_to_string(&path).ok()?;
+
+        Value::parse(&contents)
+            .and_then(|value| Self::from_json(&value))
+            .map_err(|e| log::warn!("ignoring invalid session {}: {}", path.display(), e))
+            .ok()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, format!("{:#}\n", self.to_json()))
+    }
+
+    /// Restores the KPIs accumulated during the session
+    pub fn restore(&self, kpis: &mut Kpis) -> Result<(), String> {
+        kpis.restore(&self.kpis)
+    }
+
+    fn from_json(value: &Value) -> Result<Self, String> {
+        Ok(Self {
+            simulation_id: value
+                .get("simulation_id")
+                .and_then(Value::as_str)
+                .and_then(|id| id.parse().ok())
+                .ok_or("missing `simulation_id`")?,
+            kpis: value.get("kpis").cloned().ok_or("missing `kpis`")?,
+        })
+    }
+
+    fn to_json(&self) -> Value {
+        Value::Object(vec![
+            (
+                "simulation_id".to_string(),
+                self.simulation_id.to_string().into(),
+            ),
+            ("kpis".to_string(), self.kpis.clone()),
+        ])
+    }
+}
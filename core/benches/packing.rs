@@ -0,0 +1,69 @@
+//! Compares the packing strategies of `NaiveScheduler`, both by how long `launch_flights` takes
+//! with each of them & by how well they pack the same queue of orders into the fleet.
+//!
+//! Run with `cargo bench -p scheduler-core --bench packing`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use scheduler_core::{packing_strategy, NaiveScheduler};
+use schema::testkit;
+use schema::{Order, Priority, Scheduler};
+
+const LAUNCHES: u32 = 20;
+const PENDING: usize = 1_000;
+
+fn orders(count: usize) -> Vec<Order> {
+    let destinations = ["Near", "Middle", "Far"];
+    (0..count)
+        .map(|i| {
+            let priority = match i % 10 {
+                0 => Priority::Emergency,
+                _ => Priority::Resupply,
+            };
+            testkit::order(i as u64, destinations[i % destinations.len()], priority)
+        })
+        .collect()
+}
+
+/// Average time taken to launch flights, along with the average number of orders per flight
+fn launch(strategy: &str) -> (Duration, f64) {
+    let destinations = testkit::destinations(&[
+        ("Near", 1_000, 0),
+        ("Middle", 20_000, 5_000),
+        ("Far", 60_000, -10_000),
+    ]);
+    let mut scheduler = NaiveScheduler::new(destinations, 10, 3, 30, 160_000)
+        .with_packing(packing_strategy(strategy).expect("strategy"));
+    for order in orders(PENDING) {
+        scheduler.queue_order(order);
+    }
+
+    let (mut elapsed, mut flights, mut packed) = (Duration::ZERO, 0, 0);
+    for launch in 0..LAUNCHES {
+        // Far enough apart for every carrier to have landed
+        let current_time = PENDING as u64 + launch as u64 * 10_000;
+
+        let started = Instant::now();
+        let launched = black_box(scheduler.launch_flights(current_time))
+            .map(|flight| flight.orders.len())
+            .collect::<Vec<_>>();
+        elapsed += started.elapsed();
+
+        flights += launched.len();
+        packed += launched.iter().sum::<usize>();
+    }
+
+    (elapsed / LAUNCHES, packed as f64 / flights.max(1) as f64)
+}
+
+fn main() {
+    println!(
+        "{:>10} {:>16} {:>18}",
+        "strategy", "launch_flights", "orders per flight"
+    );
+    for strategy in ["priority", "first-fit", "best-fit", "worst-fit"] {
+        let (elapsed, load) = launch(strategy);
+        println!("{:>10} {:>16?} {:>18.2}", strategy, elapsed, load);
+    }
+}
@@ -0,0 +1,91 @@
+//! Measures how the time taken by `NaiveScheduler::launch_flights` grows with the number of
+//! pending orders. Orders are kept in a priority queue, so each launch only pays for the orders it
+//! packs, rather than re-sorting the whole queue as the scheduler used to.
+//!
+//! Run with `cargo bench -p scheduler-core --bench scheduler`.
+
+use std::cmp::Ordering;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use scheduler_core::NaiveScheduler;
+use schema::testkit;
+use schema::{Order, Priority, Scheduler};
+
+const LAUNCHES: u32 = 20;
+
+fn orders(count: usize) -> Vec<Order> {
+    let destinations = ["Near", "Middle", "Far"];
+    (0..count)
+        .map(|i| {
+            let priority = match i % 10 {
+                0 => Priority::Emergency,
+                _ => Priority::Resupply,
+            };
+            testkit::order(i as u64, destinations[i % destinations.len()], priority)
+        })
+        .collect()
+}
+
+/// Average time taken to launch flights with the given number of pending orders
+fn launch(pending: usize) -> Duration {
+    let destinations = testkit::destinations(&[
+        ("Near", 1_000, 0),
+        ("Middle", 20_000, 5_000),
+        ("Far", 60_000, -10_000),
+    ]);
+    let mut scheduler = NaiveScheduler::new(destinations, 10, 3, 30, 160_000);
+    for order in orders(pending) {
+        scheduler.queue_order(order);
+    }
+
+    let mut elapsed = Duration::ZERO;
+    for launch in 0..LAUNCHES {
+        // Far enough apart for every carrier to have landed
+        let current_time = pending as u64 + launch as u64 * 10_000;
+
+        let started = Instant::now();
+        black_box(scheduler.launch_flights(current_time).count());
+        elapsed += started.elapsed();
+    }
+
+    elapsed / LAUNCHES
+}
+
+/// Average time taken to sort the pending orders by priority, as every launch used to
+fn resort(pending: usize) -> Duration {
+    let mut orders = orders(pending);
+
+    let mut elapsed = Duration::ZERO;
+    for _ in 0..LAUNCHES {
+        let started = Instant::now();
+        orders.sort_unstable_by(|a, b| match (a.priority, b.priority) {
+            (Priority::Emergency, Priority::Resupply) => Ordering::Greater,
+            (Priority::Resupply, Priority::Emergency) => Ordering::Less,
+            _ => Ordering::Equal,
+        });
+        elapsed += started.elapsed();
+        black_box(&orders);
+
+        // Sorting an already sorted queue is unrealistically cheap, as new orders arrive between
+        // launches
+        orders.rotate_left(pending / 3);
+    }
+
+    elapsed / LAUNCHES
+}
+
+fn main() {
+    println!(
+        "{:>10} {:>16} {:>22}",
+        "pending", "launch_flights", "re-sort (previously)"
+    );
+    for pending in [100, 1_000, 10_000, 100_000] {
+        println!(
+            "{:>10} {:>16?} {:>22?}",
+            pending,
+            launch(pending),
+            resort(pending)
+        );
+    }
+}
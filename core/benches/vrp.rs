@@ -0,0 +1,89 @@
+//! Compares `VrpScheduler` with the `NaiveScheduler` whose flights it routes, both by how long
+//! `launch_flights` takes with each & by the distance flown delivering the same queue of orders.
+//!
+//! Run with `cargo bench -p scheduler-core --features vrp --bench vrp`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use scheduler_core::{NaiveScheduler, SimulatedScheduler, VrpScheduler};
+use schema::testkit;
+use schema::{Order, Priority};
+
+const LAUNCHES: u32 = 20;
+const PENDING: usize = 1_000;
+
+/// Destinations scattered around the origin, in every direction
+const DESTINATIONS: [(&str, i64, i64); 8] = [
+    ("North", 20_000, 2_000),
+    ("NorthEast", 12_000, 15_000),
+    ("East", -1_000, 25_000),
+    ("SouthEast", -18_000, 14_000),
+    ("South", -22_000, -3_000),
+    ("SouthWest", -10_000, -17_000),
+    ("West", 4_000, -24_000),
+    ("NorthWest", 16_000, -13_000),
+];
+
+fn orders(count: usize) -> Vec<Order> {
+    (0..count)
+        .map(|i| {
+            let priority = match i % 10 {
+                0 => Priority::Emergency,
+                _ => Priority::Resupply,
+            };
+            // Consecutive orders go a different way, as they would from a busy region
+            let (destination, _, _) = DESTINATIONS[i * 3 % DESTINATIONS.len()];
+            testkit::order(i as u64, destination, priority)
+        })
+        .collect()
+}
+
+/// Average time taken to launch flights, along with the total distance in kilometers flown by the
+/// flights & the number of flights launched
+fn launch(mut scheduler: Box<dyn SimulatedScheduler>) -> (Duration, f64, usize) {
+    for order in orders(PENDING) {
+        scheduler.queue_order(order);
+    }
+
+    let (mut elapsed, mut distance_m, mut flights) = (Duration::ZERO, 0.0, 0);
+    for launch in 0..LAUNCHES {
+        // Far enough apart for every carrier to have landed
+        let current_time = PENDING as u64 + launch as u64 * 10_000;
+
+        let started = Instant::now();
+        let launched = black_box(scheduler.launch_flights(current_time))
+            .cloned()
+            .collect::<Vec<_>>();
+        elapsed += started.elapsed();
+
+        flights += launched.len();
+        distance_m += launched
+            .iter()
+            .map(|flight| scheduler.flight_distance(flight) as f64)
+            .sum::<f64>();
+    }
+
+    (elapsed / LAUNCHES, distance_m / 1_000.0, flights)
+}
+
+fn main() {
+    let scheduler =
+        || NaiveScheduler::new(testkit::destinations(&DESTINATIONS), 10, 3, 30, 160_000);
+
+    println!(
+        "{:>10} {:>16} {:>14} {:>10}",
+        "scheduler", "launch_flights", "km flown", "flights"
+    );
+    let schedulers: [(&str, Box<dyn SimulatedScheduler>); 2] = [
+        ("naive", Box::new(scheduler())),
+        ("vrp", Box::new(VrpScheduler::new(scheduler()))),
+    ];
+    for (name, scheduler) in schedulers {
+        let (elapsed, distance_km, flights) = launch(scheduler);
+        println!(
+            "{:>10} {:>16?} {:>14.1} {:>10}",
+            name, elapsed, distance_km, flights
+        );
+    }
+}
@@ -0,0 +1,546 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use schema::{Alert, AlertKind, Flight, Order, Priority, Sector};
+
+/// Measurements of a simulation taken each tick, which alert rules are evaluated against
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Gauges {
+    /// Emergency orders waiting for a carrier
+    pub pending_emergencies: usize,
+    /// Orders of any priority waiting for a carrier
+    pub pending_orders: usize,
+    /// Carriers landed at the origin & free to launch
+    pub available_carriers: usize,
+    /// Flights in the air or held on the ground
+    pub active_flights: usize,
+}
+
+/// One of the `Gauges` an alert rule watches
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Gauge {
+    PendingEmergencies,
+    PendingOrders,
+    AvailableCarriers,
+    ActiveFlights,
+}
+
+impl Gauge {
+    const ALL: [Self; 4] = [
+        Self::PendingEmergencies,
+        Self::PendingOrders,
+        Self::AvailableCarriers,
+        Self::ActiveFlights,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::PendingEmergencies => "pending_emergencies",
+            Self::PendingOrders => "pending_orders",
+            Self::AvailableCarriers => "available_carriers",
+            Self::ActiveFlights => "active_flights",
+        }
+    }
+
+    fn read(&self, gauges: &Gauges) -> usize {
+        match self {
+            Self::PendingEmergencies => gauges.pending_emergencies,
+            Self::PendingOrders => gauges.pending_orders,
+            Self::AvailableCarriers => gauges.available_carriers,
+            Self::ActiveFlights => gauges.active_flights,
+        }
+    }
+}
+
+/// How a gauge is compared with a rule's threshold
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operator {
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+    Equal,
+    NotEqual,
+}
+
+impl Operator {
+    const ALL: [Self; 6] = [
+        Self::GreaterOrEqual,
+        Self::LessOrEqual,
+        Self::Equal,
+        Self::NotEqual,
+        Self::Greater,
+        Self::Less,
+    ];
+
+    fn symbol(&self) -> &'static str {
+        match self {
+            Self::Greater => ">",
+            Self::GreaterOrEqual => ">=",
+            Self::Less => "<",
+            Self::LessOrEqual => "<=",
+            Self::Equal => "==",
+            Self::NotEqual => "!=",
+        }
+    }
+
+    fn holds(&self, value: usize, threshold: usize) -> bool {
+        match self {
+            Self::Greater => value > threshold,
+            Self::GreaterOrEqual => value >= threshold,
+            Self::Less => value < threshold,
+            Self::LessOrEqual => value <= threshold,
+            Self::Equal => value == threshold,
+            Self::NotEqual => value != threshold,
+        }
+    }
+}
+
+/// A condition an operator wants to hear about once it has held for a while, e.g. emergencies
+/// piling up or every carrier being busy
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AlertRule {
+    pub gauge: Gauge,
+    pub operator: Operator,
+    pub threshold: usize,
+    /// Seconds the condition must hold for before an alert is raised
+    pub for_s: u64,
+}
+
+impl AlertRule {
+    /// Parses rules separated by semicolons, e.g.
+    /// `pending_emergencies > 3 for 5m; available_carriers == 0 for 10m`
+    pub fn parse_all(s: &str) -> Result<Vec<Self>, String> {
+        s.split(';')
+            .filter(|rule| !rule.trim().is_empty())
+            .map(str::parse)
+            .collect()
+    }
+}
+
+impl std::str::FromStr for AlertRule {
+    type Err = String;
+
+    /// Parses a rule like `pending_emergencies > 3 for 5m`, comparing one of the gauges with a
+    /// threshold for a number of minutes
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid alert rule `{}`, expected <GAUGE> <OPERATOR> <THRESHOLD> for <MINUTES>m",
+                s.trim()
+            )
+        };
+        let (condition, minutes) = s.split_once(" for ").ok_or_else(invalid)?;
+        let operator = Operator::ALL
+            .into_iter()
+            .find(|operator| condition.contains(operator.symbol()))
+            .ok_or_else(invalid)?;
+        let (gauge, threshold) = condition
+            .split_once(operator.symbol())
+            .ok_or_else(invalid)?;
+        let gauge = Gauge::ALL
+            .into_iter()
+            .find(|known| known.name() == gauge.trim())
+            .ok_or_else(|| {
+                format!(
+                    "unknown gauge `{}` in alert rule, expected one of: {}",
+                    gauge.trim(),
+                    Gauge::ALL.map(|gauge| gauge.name()).join(", ")
+                )
+            })?;
+        let minutes = minutes.trim().trim_end_matches('m');
+
+        Ok(Self {
+            gauge,
+            operator,
+            threshold: threshold.trim().parse().map_err(|_| invalid())?,
+            for_s: minutes.parse::<u64>().map_err(|_| invalid())? * 60,
+        })
+    }
+}
+
+impl fmt::Display for AlertRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} for {}m",
+            self.gauge.name(),
+            self.operator.symbol(),
+            self.threshold,
+            self.for_s / 60
+        )
+    }
+}
+
+/// An alert rule along with how long its condition has held
+struct RuleState {
+    rule: AlertRule,
+    /// Time the condition started holding, if it currently does
+    since: Option<u64>,
+    /// Whether an alert has been raised since the condition started holding
+    alerted: bool,
+}
+
+/// Watches the progress of a simulation, raising `Alert`s for conditions an operator should know about
+pub struct AlertMonitor {
+    /// Seconds an emergency order may wait for a carrier before an alert is raised
+    emergency_wait_threshold_s: u64,
+    /// Seconds within which emergency orders should be delivered
+    emergency_delivery_slo_s: u64,
+    /// Emergency orders which have already raised an alert for waiting too long
+    waiting_alerted: HashSet<Order>,
+    /// Emergency orders which have already raised an alert for violating the SLO
+    slo_alerted: HashSet<Order>,
+    /// Launched emergency orders which haven't been delivered yet, with their delivery times
+    in_flight: Vec<(Order, u64)>,
+    /// Rules configured by the operator
+    rules: Vec<RuleState>,
+}
+
+impl Default for AlertMonitor {
+    fn default() -> Self {
+        Self::new(5 * 60, Self::DEFAULT_DELIVERY_SLO_S)
+    }
+}
+
+impl AlertMonitor {
+    /// Seconds within which emergency orders should be delivered, unless configured otherwise
+    pub const DEFAULT_DELIVERY_SLO_S: u64 = 30 * 60;
+
+    pub fn new(emergency_wait_threshold_s: u64, emergency_delivery_slo_s: u64) -> Self {
+        Self {
+            emergency_wait_threshold_s,
+            emergency_delivery_slo_s,
+            waiting_alerted: HashSet::new(),
+            slo_alerted: HashSet::new(),
+            in_flight: Vec::new(),
+            rules: Vec::new(),
+        }
+    }
+
+    /// Raise an alert whenever one of the provided rules holds for as long as it allows
+    pub fn with_rules(mut self, rules: impl IntoIterator<Item = AlertRule>) -> Self {
+        self.rules.extend(rules.into_iter().map(|rule| RuleState {
+            rule,
+            since: None,
+            alerted: false,
+        }));
+        self
+    }
+
+    /// Returns an alert for each rule which has now held for as long as it allows. Rules alert
+    /// once each time their condition starts holding.
+    pub fn evaluate_rules(&mut self, current_time: u64, gauges: &Gauges) -> Vec<Alert> {
+        let mut alerts = vec![];
+        for state in &mut self.rules {
+            let AlertRule {
+                gauge,
+                operator,
+                threshold,
+                for_s,
+            } = state.rule;
+            let value = gauge.read(gauges);
+            if !operator.holds(value, threshold) {
+                state.since = None;
+                state.alerted = false;
+                continue;
+            }
+
+            let since = *state.since.get_or_insert(current_time);
+            if !state.alerted && current_time - since >= for_s {
+                state.alerted = true;
+                alerts.push(Alert {
+                    time: current_time,
+                    kind: AlertKind::RuleTriggered,
+                    message: format!(
+                        "Alert rule `{}` triggered, {} is {}",
+                        state.rule,
+                        gauge.name(),
+                        value
+                    ),
+                    flight: None,
+                });
+            }
+        }
+        alerts
+    }
+
+    /// Track the delivery times of any emergency orders carried by a newly launched flight
+    pub fn observe_launch(&mut self, flight: &Flight, delivery_times: Vec<u64>) {
+        self.in_flight.extend(
+            flight
+                .orders
+                .iter()
+                .cloned()
+                .zip(delivery_times)
+                .filter(|(order, _)| matches!(order.priority, Priority::Emergency)),
+        );
+    }
+
+    /// Returns any alerts raised as of the current time
+    pub fn evaluate<'a>(
+        &mut self,
+        current_time: u64,
+        unfulfilled_orders: impl Iterator<Item = &'a Order>,
+    ) -> Vec<Alert> {
+        let mut alerts = vec![];
+
+        for order in
+            unfulfilled_orders.filter(|order| matches!(order.priority, Priority::Emergency))
+        {
+            let waited = current_time.saturating_sub(order.time);
+
+            if waited > self.emergency_wait_threshold_s
+                && self.waiting_alerted.insert(order.clone())
+            {
+                alerts.push(Alert {
+                    time: current_time,
+                    kind: AlertKind::EmergencyWaiting,
+                    message: format!(
+                        "Emergency order for {} has waited more than {} minutes for a carrier",
+                        order.destination,
+                        self.emergency_wait_threshold_s / 60
+                    ),
+                    flight: None,
+                });
+            }
+
+            if waited > self.emergency_delivery_slo_s {
+                alerts.extend(self.slo_violation(current_time, order));
+            }
+        }
+
+        let in_flight = std::mem::take(&mut self.in_flight);
+        for (order, delivery_time) in in_flight {
+            if delivery_time <= current_time {
+                continue;
+            }
+
+            if current_time.saturating_sub(order.time) > self.emergency_delivery_slo_s {
+                alerts.extend(self.slo_violation(current_time, &order));
+            } else {
+                self.in_flight.push((order, delivery_time));
+            }
+        }
+
+        alerts
+    }
+
+    /// Returns the next time after the current one at which an alert could be raised, if nothing
+    /// else changes in the meantime (no orders are queued, launched or delivered, & the gauges hold
+    /// steady), so that quiet stretches of a run can be skipped
+    pub fn next_deadline<'a>(
+        &self,
+        current_time: u64,
+        unfulfilled_orders: impl Iterator<Item = &'a Order>,
+    ) -> Option<u64> {
+        let waiting = unfulfilled_orders
+            .filter(|order| matches!(order.priority, Priority::Emergency))
+            .flat_map(|order| {
+                let waited = (!self.waiting_alerted.contains(order))
+                    .then_some(order.time + self.emergency_wait_threshold_s + 1);
+                let late = (!self.slo_alerted.contains(order))
+                    .then_some(order.time + self.emergency_delivery_slo_s + 1);
+                waited.into_iter().chain(late)
+            });
+        let in_flight = self.in_flight.iter().filter_map(|(order, delivery_time)| {
+            let late = order.time + self.emergency_delivery_slo_s + 1;
+            (late < *delivery_time && !self.slo_alerted.contains(order)).then_some(late)
+        });
+        let rules = self.rules.iter().filter_map(|state| match state.since {
+            Some(since) if !state.alerted => Some(since + state.rule.for_s),
+            _ => None,
+        });
+
+        waiting
+            .chain(in_flight)
+            .chain(rules)
+            .map(|time| time.max(current_time + 1))
+            .min()
+    }
+
+    /// Returns an alert for an order which the scheduler gave up on because it could no longer be
+    /// delivered within its window
+    pub fn missed_window(&self, current_time: u64, order: &Order) -> Alert {
+        Alert {
+            time: current_time,
+            kind: AlertKind::DeliveryWindowMissed,
+            message: format!(
+                "Order for {} can no longer be delivered within its delivery window",
+                order.destination
+            ),
+            flight: None,
+        }
+    }
+
+    /// Returns an alert for a flight which was cut short because its carrier no longer had the range
+    /// to complete it
+    pub fn return_to_base(&self, current_time: u64, flight: &Flight, requeued: usize) -> Alert {
+        Alert {
+            time: current_time,
+            kind: AlertKind::CarrierFailure,
+            message: format!(
+                "Carrier {} is short of range & returning to base early, {} orders requeued",
+                flight.carrier, requeued
+            ),
+            flight: Some(flight.id),
+        }
+    }
+
+    /// Returns an alert for a launch which was held to keep its carrier separated from the others,
+    /// or until a sector it would fly through has room if one was full
+    pub fn launch_held(
+        &self,
+        current_time: u64,
+        flight: &Flight,
+        hold_s: u64,
+        full_sector: Option<&Sector>,
+    ) -> Alert {
+        let reason = match full_sector {
+            Some(sector) => format!("until sector {} has room", sector.name),
+            None => "to keep its separation".to_string(),
+        };
+        Alert {
+            time: current_time,
+            kind: AlertKind::LaunchHeld,
+            message: format!(
+                "Carrier {} is holding for {}s before launching {}",
+                flight.carrier, hold_s, reason
+            ),
+            flight: Some(flight.id),
+        }
+    }
+
+    /// Returns an alert for a flight carrying emergencies which is held on the ground until it's
+    /// approved, or until the provided time at which it's approved automatically
+    pub fn approval_pending(&self, current_time: u64, flight: &Flight, deadline: u64) -> Alert {
+        let emergencies = flight
+            .orders
+            .iter()
+            .filter(|order| order.priority == Priority::Emergency)
+            .count();
+        Alert {
+            time: current_time,
+            kind: AlertKind::ApprovalPending,
+            message: format!(
+                "Flight {} on carrier {} with {} emergencies awaits approval, launching anyway in {}s",
+                flight.id,
+                flight.carrier,
+                emergencies,
+                deadline.saturating_sub(current_time)
+            ),
+            flight: Some(flight.id),
+        }
+    }
+
+    /// Returns an alert for the start of a surge of emergencies, during which every carrier is
+    /// given over to them
+    pub fn surge_started(&self, current_time: u64) -> Alert {
+        Alert {
+            time: current_time,
+            kind: AlertKind::SurgeStarted,
+            message: "Emergencies are surging, all carriers are given over to emergencies"
+                .to_string(),
+            flight: None,
+        }
+    }
+
+    /// Returns an alert for the end of a surge of emergencies, once resupplies are flown again
+    pub fn surge_ended(&self, current_time: u64) -> Alert {
+        Alert {
+            time: current_time,
+            kind: AlertKind::SurgeEnded,
+            message: "Emergency surge has ended, resupplies are being flown again".to_string(),
+            flight: None,
+        }
+    }
+
+    /// Returns an alert for an order which has violated the SLO, unless one was already raised
+    fn slo_violation(&mut self, current_time: u64, order: &Order) -> Option<Alert> {
+        self.slo_alerted.insert(order.clone()).then(|| Alert {
+            time: current_time,
+            kind: AlertKind::SloViolated,
+            message: format!(
+                "Emergency order for {} was not delivered within {} minutes",
+                order.destination,
+                self.emergency_delivery_slo_s / 60
+            ),
+            flight: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_alert_rules() {
+        let rules =
+            AlertRule::parse_all("pending_emergencies > 3 for 5m; available_carriers == 0 for 10")
+                .expect("rules");
+        assert_eq!(
+            rules[0],
+            AlertRule {
+                gauge: Gauge::PendingEmergencies,
+                operator: Operator::Greater,
+                threshold: 3,
+                for_s: 300,
+            }
+        );
+        assert_eq!(rules[1].to_string(), "available_carriers == 0 for 10m");
+        assert!("pending_emergencies > 3".parse::<AlertRule>().is_err());
+        assert!("queue_depth > 3 for 5m".parse::<AlertRule>().is_err());
+
+        let mut monitor = AlertMonitor::default().with_rules(rules);
+        let gauges = |pending_emergencies, available_carriers| Gauges {
+            pending_emergencies,
+            available_carriers,
+            ..Default::default()
+        };
+        // Rules alert once their condition has held for long enough, & only once while it holds
+        assert!(monitor.evaluate_rules(0, &gauges(4, 1)).is_empty());
+        assert!(monitor.evaluate_rules(240, &gauges(5, 1)).is_empty());
+        let alerts = monitor.evaluate_rules(300, &gauges(4, 1));
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].kind, AlertKind::RuleTriggered);
+        assert!(monitor.evaluate_rules(360, &gauges(4, 1)).is_empty());
+
+        // Conditions which stop holding start over
+        assert!(monitor.evaluate_rules(420, &gauges(2, 0)).is_empty());
+        assert!(monitor.evaluate_rules(480, &gauges(4, 0)).is_empty());
+        assert!(monitor.evaluate_rules(720, &gauges(4, 0)).is_empty());
+        let alerts = monitor.evaluate_rules(1_020, &gauges(4, 0));
+        assert_eq!(alerts.len(), 2);
+        assert!(alerts[1].message.contains("available_carriers is 0"));
+    }
+
+    #[test]
+    fn test_next_deadline() {
+        let mut monitor = AlertMonitor::new(300, 1_800);
+        let emergency = schema::testkit::order(100, "Toronto", Priority::Emergency);
+        let resupply = schema::testkit::order(100, "Toronto", Priority::Resupply);
+        assert_eq!(monitor.next_deadline(200, [&resupply].into_iter()), None);
+
+        // Waiting emergencies alert once they've waited past the threshold, & then the SLO
+        assert_eq!(
+            monitor.next_deadline(200, [&emergency].into_iter()),
+            Some(401)
+        );
+        assert_eq!(monitor.evaluate(401, [&emergency].into_iter()).len(), 1);
+        assert_eq!(
+            monitor.next_deadline(401, [&emergency].into_iter()),
+            Some(1_901)
+        );
+
+        // In flight, they alert if they're due to arrive after the SLO
+        let flight = Flight {
+            orders: vec![emergency.clone()],
+            ..Default::default()
+        };
+        monitor.observe_launch(&flight, vec![2_000]);
+        assert_eq!(monitor.next_deadline(500, std::iter::empty()), Some(1_901));
+        monitor.evaluate(1_901, std::iter::empty());
+        assert_eq!(monitor.next_deadline(1_901, std::iter::empty()), None);
+    }
+}
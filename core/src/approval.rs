@@ -0,0 +1,159 @@
+use std::collections::HashSet;
+use std::num::NonZeroU64;
+
+use schema::{CarrierId, Flight, FlightId, Priority};
+
+use crate::SimulatedScheduler;
+
+/// Holds flights carrying emergencies on the ground until they're approved, as a pilot in
+/// command would have to sign off on them, approving them automatically once they've waited too
+/// long. Flights are held a launch slot at a time, so that they launch at the first slot after
+/// they're approved.
+pub struct Approvals {
+    auto_approve_s: u64,
+    pending: Vec<Pending>,
+    approved: HashSet<FlightId>,
+}
+
+struct Pending {
+    flight: FlightId,
+    carrier: CarrierId,
+    /// Time the flight is approved at if nobody approves it sooner
+    deadline: u64,
+}
+
+impl Approvals {
+    /// Seconds between launch slots, at which approved flights launch
+    const SLOT_S: u64 = 60;
+
+    pub fn new(auto_approve_s: NonZeroU64) -> Self {
+        Self {
+            auto_approve_s: auto_approve_s.get(),
+            pending: vec![],
+            approved: HashSet::new(),
+        }
+    }
+
+    /// Whether a flight has to be approved before it launches
+    pub fn requires_approval(flight: &Flight) -> bool {
+        flight
+            .orders
+            .iter()
+            .any(|order| order.priority == Priority::Emergency)
+    }
+
+    /// Holds a launched flight until it's approved, returning the time it will be approved
+    /// automatically
+    pub fn request(
+        &mut self,
+        scheduler: &mut dyn SimulatedScheduler,
+        flight: &Flight,
+        current_time: u64,
+    ) -> u64 {
+        let deadline = flight.launch_time + self.auto_approve_s;
+        scheduler.hold_launch(flight.carrier, (current_time + Self::SLOT_S).min(deadline));
+        self.pending.push(Pending {
+            flight: flight.id,
+            carrier: flight.carrier,
+            deadline,
+        });
+        deadline
+    }
+
+    /// Approves a flight waiting to launch, which launches at the next launch slot
+    pub fn approve(&mut self, id: FlightId) -> Result<(), String> {
+        if !self.is_pending(id) {
+            return Err(format!("flight {} isn't awaiting approval", id));
+        }
+        self.approved.insert(id);
+        Ok(())
+    }
+
+    pub fn is_pending(&self, id: FlightId) -> bool {
+        self.pending.iter().any(|pending| pending.flight == id)
+    }
+
+    /// Flights awaiting approval, along with the time each is approved automatically
+    pub fn pending(&self) -> impl Iterator<Item = (FlightId, u64)> + '_ {
+        self.pending
+            .iter()
+            .map(|pending| (pending.flight, pending.deadline))
+    }
+
+    /// Releases the flights which have been approved, or have waited long enough to be approved
+    /// automatically, holding the rest until the next launch slot. Returns the released flights,
+    /// as they'll now launch.
+    pub fn release(
+        &mut self,
+        scheduler: &mut dyn SimulatedScheduler,
+        current_time: u64,
+    ) -> Vec<Flight> {
+        let mut released = vec![];
+        for pending in std::mem::take(&mut self.pending) {
+            let approved = self.approved.remove(&pending.flight);
+            let next_slot = current_time + Self::SLOT_S;
+            let hold_until = match approved {
+                true => current_time,
+                false => next_slot.min(pending.deadline),
+            };
+            let Some(flight) = scheduler.hold_launch(pending.carrier, hold_until) else {
+                continue;
+            };
+            if approved || pending.deadline < next_slot {
+                if !approved {
+                    tracing::info!("flight {} approved automatically", pending.flight);
+                }
+                released.push(flight.clone());
+            } else {
+                self.pending.push(pending);
+            }
+        }
+        released
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use schema::{testkit, Scheduler};
+
+    use super::*;
+    use crate::NaiveScheduler;
+
+    #[test]
+    fn test_release() {
+        let destinations = testkit::destinations(&[("Near", 3_000, 0)]);
+        let mut scheduler = NaiveScheduler::new(destinations, 2, 3, 30, 160_000);
+        scheduler.queue_order(testkit::order(0, "Near", Priority::Emergency));
+        let flight = scheduler
+            .launch_flights(60)
+            .next()
+            .cloned()
+            .expect("flight");
+        assert!(Approvals::requires_approval(&flight));
+
+        // Held a slot at a time, until it's approved automatically
+        let mut approvals = Approvals::new(NonZeroU64::new(150).expect("timeout"));
+        assert_eq!(approvals.request(&mut scheduler, &flight, 60), 210);
+        assert!(approvals.release(&mut scheduler, 60).is_empty());
+        assert!(approvals.release(&mut scheduler, 120).is_empty());
+        let released = approvals.release(&mut scheduler, 180);
+        assert_eq!(released.len(), 1);
+        assert_eq!(released[0].launch_time, 210);
+        assert!(!approvals.is_pending(flight.id));
+        assert!(approvals.approve(flight.id).is_err());
+
+        // Approved flights launch at the next slot
+        scheduler.queue_order(testkit::order(0, "Near", Priority::Emergency));
+        let flight = scheduler
+            .launch_flights(240)
+            .next()
+            .cloned()
+            .expect("flight");
+        approvals.request(&mut scheduler, &flight, 240);
+        assert_eq!(approvals.pending().collect::<Vec<_>>(), [(flight.id, 390)]);
+        approvals.approve(flight.id).expect("approved");
+        let released = approvals.release(&mut scheduler, 300);
+        assert_eq!(released.len(), 1);
+        assert_eq!(released[0].launch_time, 300);
+    }
+}
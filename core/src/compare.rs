@@ -0,0 +1,487 @@
+use std::fmt;
+
+use schema::{json::Value, DestinationId};
+
+use crate::{CarrierStats, RunReport};
+
+/// Whether an increase in a KPI is an improvement or a regression
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Better {
+    Higher,
+    Lower,
+}
+
+/// A single KPI of two runs of the same scenario
+#[derive(Clone, Debug, PartialEq)]
+pub struct KpiDelta {
+    pub name: &'static str,
+    pub baseline: f64,
+    pub candidate: f64,
+    pub better: Better,
+    /// Number of decimal places the KPI is displayed with
+    precision: usize,
+}
+
+impl KpiDelta {
+    /// Change from the baseline as a percentage of it, if the baseline isn't zero
+    pub fn percent_change(&self) -> Option<f64> {
+        match (self.baseline, self.candidate) {
+            (baseline, candidate) if baseline == candidate => Some(0.0),
+            (0.0, _) => None,
+            (baseline, candidate) => Some((candidate - baseline) / baseline.abs() * 100.0),
+        }
+    }
+
+    /// Whether the candidate is worse than the baseline by more than a percentage of it
+    pub fn regressed(&self, tolerance_percent: f64) -> bool {
+        let worse = match self.better {
+            Better::Higher => self.candidate < self.baseline,
+            Better::Lower => self.candidate > self.baseline,
+        };
+
+        worse
+            && self
+                .percent_change()
+                .is_none_or(|change| change.abs() > tolerance_percent)
+    }
+
+    pub fn to_json(&self, tolerance_percent: f64) -> Value {
+        Value::Object(vec![
+            ("name".to_string(), self.name.into()),
+            ("baseline".to_string(), self.baseline.into()),
+            ("candidate".to_string(), self.candidate.into()),
+            (
+                "better".to_string(),
+                match self.better {
+                    Better::Higher => "higher",
+                    Better::Lower => "lower",
+                }
+                .into(),
+            ),
+            ("percent_change".to_string(), self.percent_change().into()),
+            (
+                "regressed".to_string(),
+                self.regressed(tolerance_percent).into(),
+            ),
+        ])
+    }
+}
+
+/// Average seconds the orders delivered to a destination waited in two runs of the same scenario,
+/// if any were delivered to it in each
+#[derive(Clone, Debug, PartialEq)]
+pub struct LatencyDelta {
+    pub destination: DestinationId,
+    pub baseline: Option<f64>,
+    pub candidate: Option<f64>,
+}
+
+impl LatencyDelta {
+    pub fn to_json(&self) -> Value {
+        Value::Object(vec![
+            ("destination".to_string(), self.destination.as_str().into()),
+            ("baseline_s".to_string(), self.baseline.into()),
+            ("candidate_s".to_string(), self.candidate.into()),
+        ])
+    }
+}
+
+/// Side-by-side KPIs of two runs of the same scenario, e.g. with different schedulers
+#[derive(Clone, Debug, PartialEq)]
+pub struct Comparison {
+    pub kpis: Vec<KpiDelta>,
+    /// Latency of each destination delivered to in either run, by name
+    pub destinations: Vec<LatencyDelta>,
+}
+
+impl Comparison {
+    pub fn new(baseline: &RunReport, candidate: &RunReport) -> Self {
+        Self {
+            kpis: KPIS
+                .iter()
+                .map(|kpi| KpiDelta {
+                    name: kpi.name,
+                    baseline: (kpi.value)(baseline),
+                    candidate: (kpi.value)(candidate),
+                    better: kpi.better,
+                    precision: kpi.precision,
+                })
+                .collect(),
+            destinations: latency_deltas(baseline, candidate),
+        }
+    }
+
+    /// Returns the KPIs which are worse than the baseline by more than a percentage of it
+    pub fn regressions(&self, tolerance_percent: f64) -> impl Iterator<Item = &KpiDelta> {
+        self.kpis
+            .iter()
+            .filter(move |kpi| kpi.regressed(tolerance_percent))
+    }
+
+    pub fn to_json(&self, tolerance_percent: f64) -> Value {
+        Value::Object(vec![
+            (
+                "kpis".to_string(),
+                Value::Array(
+                    self.kpis
+                        .iter()
+                        .map(|kpi| kpi.to_json(tolerance_percent))
+                        .collect(),
+                ),
+            ),
+            (
+                "destinations".to_string(),
+                Value::Array(
+                    self.destinations
+                        .iter()
+                        .map(LatencyDelta::to_json)
+                        .collect(),
+                ),
+            ),
+            (
+                "regressions".to_string(),
+                self.regressions(tolerance_percent).count().into(),
+            ),
+        ])
+    }
+}
+
+/// A KPI which can be measured from a run report
+struct Kpi {
+    name: &'static str,
+    better: Better,
+    /// Number of decimal places the KPI is displayed with
+    precision: usize,
+    value: fn(&RunReport) -> f64,
+}
+
+const KPIS: &[Kpi] = &[
+    Kpi {
+        name: "orders_delivered",
+        better: Better::Higher,
+        precision: 0,
+        value: |report| report.orders_delivered as f64,
+    },
+    Kpi {
+        name: "unfulfilled_orders",
+        better: Better::Lower,
+        precision: 0,
+        value: |report| report.unfulfilled_orders as f64,
+    },
+    Kpi {
+        name: "missed_windows",
+        better: Better::Lower,
+        precision: 0,
+        value: |report| report.missed_windows as f64,
+    },
+    Kpi {
+        name: "spoiled",
+        better: Better::Lower,
+        precision: 0,
+        value: |report| report.spoiled as f64,
+    },
+    Kpi {
+        name: "returns_to_base",
+        better: Better::Lower,
+        precision: 0,
+        value: |report| report.returns_to_base as f64,
+    },
+    Kpi {
+        name: "separation_holds",
+        better: Better::Lower,
+        precision: 0,
+        value: |report| report.separation_holds as f64,
+    },
+    Kpi {
+        name: "sector_holds",
+        better: Better::Lower,
+        precision: 0,
+        value: |report| report.sector_holds as f64,
+    },
+    Kpi {
+        name: "rule_alerts",
+        better: Better::Lower,
+        precision: 0,
+        value: |report| report.rule_alerts as f64,
+    },
+    Kpi {
+        name: "duration_s",
+        better: Better::Lower,
+        precision: 0,
+        value: |report| report.end_time.saturating_sub(report.start_time) as f64,
+    },
+    Kpi {
+        name: "flights",
+        better: Better::Lower,
+        precision: 0,
+        value: |report| total(report, |stats| stats.flights as f64),
+    },
+    Kpi {
+        name: "distance_km",
+        better: Better::Lower,
+        precision: 1,
+        value: |report| total(report, |stats| stats.distance_m as f64) / 1000.0,
+    },
+    Kpi {
+        name: "energy_kwh",
+        better: Better::Lower,
+        precision: 2,
+        value: |report| report.energy_j() / 3.6e6,
+    },
+    Kpi {
+        name: "airborne_s",
+        better: Better::Lower,
+        precision: 0,
+        value: |report| total(report, |stats| stats.airborne_s as f64),
+    },
+    Kpi {
+        name: "average_load_factor",
+        better: Better::Higher,
+        precision: 3,
+        value: |report| {
+            // Weighted by the number of flights of each carrier
+            match total(report, |stats| stats.flights as f64) {
+                flights if flights > 0.0 => {
+                    total(report, |stats| {
+                        stats.average_load_factor as f64 * stats.flights as f64
+                    }) / flights
+                }
+                _ => 0.0,
+            }
+        },
+    },
+    Kpi {
+        name: "average_latency_s",
+        better: Better::Lower,
+        precision: 0,
+        value: RunReport::average_latency_s,
+    },
+    Kpi {
+        name: "max_latency_s",
+        better: Better::Lower,
+        precision: 0,
+        value: |report| report.max_latency_s() as f64,
+    },
+    Kpi {
+        name: "latency_gini",
+        better: Better::Lower,
+        precision: 3,
+        value: RunReport::latency_gini,
+    },
+];
+
+impl RunReport {
+    /// Returns the value of each of the KPIs compared between runs, by name
+    pub fn kpis(&self) -> impl Iterator<Item = (&'static str, f64)> + '_ {
+        KPIS.iter().map(|kpi| (kpi.name, (kpi.value)(self)))
+    }
+}
+
+/// Returns the names of the KPIs compared between runs
+pub(crate) fn kpi_names() -> impl Iterator<Item = &'static str> {
+    KPIS.iter().map(|kpi| kpi.name)
+}
+
+/// Returns the formatted value of each of the KPIs compared between runs, in the same order as
+/// their names
+pub(crate) fn report_kpis(report: &RunReport) -> impl Iterator<Item = String> + '_ {
+    KPIS.iter().map(|kpi| {
+        format!(
+            "{:.precision$}",
+            (kpi.value)(report),
+            precision = kpi.precision
+        )
+    })
+}
+
+fn total(report: &RunReport, value: impl Fn(&CarrierStats) -> f64) -> f64 {
+    report.carriers.iter().map(value).sum()
+}
+
+fn latency_deltas(baseline: &RunReport, candidate: &RunReport) -> Vec<LatencyDelta> {
+    let average = |report: &RunReport, destination| {
+        report
+            .destinations
+            .iter()
+            .find(|latency| latency.destination == destination)
+            .map(|latency| latency.average_s)
+    };
+    let mut destinations = baseline
+        .destinations
+        .iter()
+        .chain(&candidate.destinations)
+        .map(|latency| latency.destination)
+        .collect::<Vec<_>>();
+    destinations.sort_by_key(|destination| destination.as_str());
+    destinations.dedup();
+
+    destinations
+        .into_iter()
+        .map(|destination| LatencyDelta {
+            destination,
+            baseline: average(baseline, destination),
+            candidate: average(candidate, destination),
+        })
+        .collect()
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<20} {:>12} {:>12} {:>10}",
+            "kpi", "baseline", "candidate", "change"
+        )?;
+        for kpi in &self.kpis {
+            let change = match kpi.percent_change() {
+                Some(change) => format!("{:+.1}%", change),
+                None => "n/a".to_string(),
+            };
+            let verdict = match kpi.regressed(0.0) {
+                true => " (worse)",
+                false if kpi.baseline != kpi.candidate => " (better)",
+                false => "",
+            };
+
+            writeln!(
+                f,
+                "{:<20} {:>12.precision$} {:>12.precision$} {:>10}{}",
+                kpi.name,
+                kpi.baseline,
+                kpi.candidate,
+                change,
+                verdict,
+                precision = kpi.precision
+            )?;
+        }
+
+        if !self.destinations.is_empty() {
+            writeln!(
+                f,
+                "\n{:<20} {:>12} {:>12} {:>10}",
+                "average latency (s)", "baseline", "candidate", "change"
+            )?;
+        }
+        let format = |latency: Option<f64>| match latency {
+            Some(latency) => format!("{:.0}", latency),
+            None => "-".to_string(),
+        };
+        for latency in &self.destinations {
+            let change = match (latency.baseline, latency.candidate) {
+                (Some(baseline), Some(candidate)) => format!("{:+.0}", candidate - baseline),
+                _ => "n/a".to_string(),
+            };
+            writeln!(
+                f,
+                "{:<20} {:>12} {:>12} {:>10}",
+                latency.destination.as_str(),
+                format(latency.baseline),
+                format(latency.candidate),
+                change
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use schema::{CarrierId, FlightId};
+
+    use crate::{DestinationLatency, FlightEnergy};
+
+    use super::*;
+
+    #[test]
+    fn test_comparison() {
+        let report = |orders_delivered, flights| RunReport {
+            start_time: 0,
+            end_time: 3_600,
+            orders_delivered,
+            unfulfilled_orders: 10 - orders_delivered,
+            carriers: vec![CarrierStats {
+                carrier: CarrierId(0),
+                flights,
+                distance_m: 10_000.0,
+                average_load_factor: 0.5,
+                ..Default::default()
+            }],
+            flight_energy: vec![FlightEnergy {
+                flight: FlightId::new(),
+                carrier: CarrierId(0),
+                launch_time: 60,
+                orders: 2,
+                energy_j: 1.8e6,
+            }],
+            ..Default::default()
+        };
+        let baseline = report(8, 4);
+        let candidate = report(9, 5);
+
+        // Reports survive a round trip through JSON
+        let parsed = RunReport::from_json(&baseline.to_json()).expect("report");
+        assert_eq!(
+            Comparison::new(&baseline, &parsed).regressions(0.0).count(),
+            0
+        );
+
+        let kpis = candidate.kpis().collect::<Vec<_>>();
+        assert_eq!(kpis[0], ("orders_delivered", 9.0));
+        assert!(kpis.contains(&("distance_km", 10.0)));
+        assert!(kpis.contains(&("energy_kwh", 0.5)));
+
+        let comparison = Comparison::new(&baseline, &candidate);
+        let delivered = &comparison.kpis[0];
+        assert_eq!(delivered.percent_change(), Some(12.5));
+        assert!(!delivered.regressed(0.0));
+
+        // More flights are only a regression beyond the tolerance
+        let regressions = comparison
+            .regressions(0.0)
+            .map(|kpi| kpi.name)
+            .collect::<Vec<_>>();
+        assert_eq!(regressions, ["flights"]);
+        assert_eq!(comparison.regressions(25.0).count(), 0);
+    }
+
+    #[test]
+    fn test_latency() {
+        let report = |latencies: &[(&str, f64, u64)]| RunReport {
+            destinations: latencies
+                .iter()
+                .map(|(destination, average_s, max_s)| DestinationLatency {
+                    destination: DestinationId::from(*destination),
+                    deliveries: 2,
+                    average_s: *average_s,
+                    max_s: *max_s,
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let baseline = report(&[("Near", 100.0, 150), ("Far", 300.0, 400)]);
+        let candidate = report(&[("Near", 200.0, 250), ("Far", 200.0, 250)]);
+
+        assert_eq!(baseline.average_latency_s(), 200.0);
+        assert_eq!(baseline.max_latency_s(), 400);
+        assert_eq!(baseline.latency_gini(), 0.25);
+        assert_eq!(candidate.latency_gini(), 0.0);
+        let parsed = RunReport::from_json(&baseline.to_json()).expect("report");
+        assert_eq!(parsed.destinations, baseline.destinations);
+
+        // Spreading the waiting more evenly is an improvement, even at the same average
+        let comparison = Comparison::new(&baseline, &candidate);
+        assert_eq!(comparison.regressions(0.0).count(), 0);
+        let gini = comparison
+            .kpis
+            .iter()
+            .find(|kpi| kpi.name == "latency_gini")
+            .expect("gini");
+        assert_eq!((gini.baseline, gini.candidate), (0.25, 0.0));
+
+        // Each destination is compared by name
+        let far = &comparison.destinations[0];
+        assert_eq!(far.destination, DestinationId::from("Far"));
+        assert_eq!((far.baseline, far.candidate), (Some(300.0), Some(200.0)));
+        assert!(comparison.to_string().contains("average latency"));
+    }
+}
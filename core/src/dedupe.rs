@@ -0,0 +1,58 @@
+use std::collections::{HashSet, VecDeque};
+
+/// Idempotency keys of recently accepted submissions, so that retries of a submission which was
+/// already accepted aren't queued again. Holds a bounded number of keys, forgetting the oldest.
+pub struct DedupeCache {
+    capacity: usize,
+    keys: HashSet<String>,
+    /// Keys in the order they were accepted, oldest first
+    accepted: VecDeque<String>,
+}
+
+impl DedupeCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            keys: HashSet::new(),
+            accepted: VecDeque::new(),
+        }
+    }
+
+    /// Whether a submission with the key has been accepted (& not yet forgotten)
+    pub fn contains(&self, key: &str) -> bool {
+        self.keys.contains(key)
+    }
+
+    /// Remembers that a submission with the key has been accepted
+    pub fn insert(&mut self, key: String) {
+        if self.capacity == 0 || !self.keys.insert(key.clone()) {
+            return;
+        }
+
+        self.accepted.push_back(key);
+        if self.accepted.len() > self.capacity {
+            if let Some(oldest) = self.accepted.pop_front() {
+                self.keys.remove(&oldest);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_dedupe_cache() {
+        let mut cache = DedupeCache::new(2);
+        cache.insert("a".to_string());
+        cache.insert("b".to_string());
+        cache.insert("a".to_string());
+        assert!(cache.contains("a") && cache.contains("b"));
+
+        // The oldest key is forgotten once the cache is full
+        cache.insert("c".to_string());
+        assert!(!cache.contains("a"));
+        assert!(cache.contains("b") && cache.contains("c"));
+    }
+}
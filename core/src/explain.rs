@@ -0,0 +1,165 @@
+use schema::{
+    CarrierMode, ConsideredOrder, Flight, FlightExplanation, Order, PackingOutcome, PackingStop,
+};
+
+/// Records the decisions made while packing the flights launched at a time, from which each
+/// flight's `FlightExplanation` is built once they've been given carriers
+pub(crate) struct PackingTrace {
+    packed_at: u64,
+    queued: usize,
+    /// Carriers available & held in reserve, indexed by mode
+    carriers: [(usize, usize); 2],
+    considered: Vec<Considered>,
+    stopped: PackingStop,
+}
+
+struct Considered {
+    order: Order,
+    /// Bins tried for the order which it wasn't packed into, & why
+    rejected: Vec<(CarrierMode, usize, PackingOutcome)>,
+    fate: Fate,
+}
+
+enum Fate {
+    Packed(CarrierMode, usize),
+    Waiting,
+    GaveUp(PackingOutcome),
+}
+
+impl PackingTrace {
+    pub(crate) fn new(
+        packed_at: u64,
+        queued: usize,
+        available: [usize; 2],
+        reserved: [usize; 2],
+    ) -> Self {
+        Self {
+            packed_at,
+            queued,
+            carriers: [(available[0], reserved[0]), (available[1], reserved[1])],
+            considered: vec![],
+            stopped: PackingStop::QueueEmpty,
+        }
+    }
+
+    /// Starts considering the next order, which waits for a later launch unless it's packed
+    pub(crate) fn consider(&mut self, order: &Order) {
+        self.considered.push(Considered {
+            order: order.clone(),
+            rejected: vec![],
+            fate: Fate::Waiting,
+        });
+    }
+
+    /// The order being considered wasn't packed into a bin it was tried in
+    pub(crate) fn reject(&mut self, mode: CarrierMode, bin: usize, outcome: PackingOutcome) {
+        if let Some(considered) = self.considered.last_mut() {
+            considered.rejected.push((mode, bin, outcome));
+        }
+    }
+
+    pub(crate) fn pack(&mut self, mode: CarrierMode, bin: usize) {
+        if let Some(considered) = self.considered.last_mut() {
+            considered.fate = Fate::Packed(mode, bin);
+        }
+    }
+
+    pub(crate) fn give_up(&mut self, outcome: PackingOutcome) {
+        if let Some(considered) = self.considered.last_mut() {
+            considered.fate = Fate::GaveUp(outcome);
+        }
+    }
+
+    pub(crate) fn stop(&mut self, stopped: PackingStop) {
+        self.stopped = stopped;
+    }
+
+    /// Explains each launched flight, given along with the mode & bin it was packed from
+    pub(crate) fn explain(
+        &self,
+        launched: &[(CarrierMode, usize, &Flight)],
+        capacity: usize,
+    ) -> Vec<FlightExplanation> {
+        let carrier = |mode, bin| {
+            launched
+                .iter()
+                .find(|(m, b, _)| (*m, *b) == (mode, bin))
+                .map(|(_, _, flight)| flight.carrier)
+        };
+        launched
+            .iter()
+            .map(|(mode, bin, flight)| {
+                let (available, reserved) = self.carriers[*mode as usize];
+                let considered = self
+                    .considered
+                    .iter()
+                    .map(|considered| {
+                        let rejected = considered
+                            .rejected
+                            .iter()
+                            .find(|(m, b, _)| (m, b) == (mode, bin))
+                            .map(|(_, _, outcome)| *outcome);
+                        let outcome = match (&considered.fate, rejected) {
+                            (Fate::Packed(m, b), _) if (m, b) == (mode, bin) => {
+                                PackingOutcome::Packed
+                            }
+                            (_, Some(outcome)) => outcome,
+                            (Fate::Packed(m, b), None) => carrier(*m, *b)
+                                .map_or(PackingOutcome::NotTried, PackingOutcome::PackedElsewhere),
+                            (Fate::GaveUp(outcome), None) => *outcome,
+                            (Fate::Waiting, None) => PackingOutcome::NotTried,
+                        };
+                        ConsideredOrder {
+                            order: considered.order.id,
+                            destination: considered.order.destination,
+                            priority: considered.order.priority,
+                            outcome,
+                        }
+                    })
+                    .collect();
+                FlightExplanation {
+                    flight: flight.id,
+                    carrier: flight.carrier,
+                    mode: *mode,
+                    packed_at: self.packed_at,
+                    capacity,
+                    queued: self.queued,
+                    available_carriers: available,
+                    reserved_carriers: reserved,
+                    considered,
+                    stopped: self.stopped,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Explains a flight launched as planned ahead of time, each of its orders being packed into it
+pub(crate) fn explain_planned(
+    flight: &Flight,
+    queued: usize,
+    available: usize,
+    capacity: usize,
+) -> FlightExplanation {
+    FlightExplanation {
+        flight: flight.id,
+        carrier: flight.carrier,
+        mode: flight.mode,
+        packed_at: flight.launch_time,
+        capacity,
+        queued,
+        available_carriers: available,
+        reserved_carriers: 0,
+        considered: flight
+            .orders
+            .iter()
+            .map(|order| ConsideredOrder {
+                order: order.id,
+                destination: order.destination,
+                priority: order.priority,
+                outcome: PackingOutcome::Packed,
+            })
+            .collect(),
+        stopped: PackingStop::Planned,
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+
+use schema::{DemandPrior, DestinationId, Forecaster, Priority};
+
+use crate::DemandProfile;
+
+const DAY_S: u64 = DemandProfile::BUCKET_S * DemandProfile::BUCKETS_PER_DAY as u64;
+
+/// Forecasts demand as the average number of orders placed at the same time of day in previous runs.
+/// Demand priors from the scenario, if any, count as one more day of history.
+#[derive(Default, Clone, Debug)]
+pub struct HistoricalAverage {
+    totals: HashMap<(Priority, DestinationId), Vec<u32>>,
+    days: u32,
+    /// Orders expected each day, by priority & destination
+    priors: HashMap<(Priority, DestinationId), f32>,
+}
+
+impl HistoricalAverage {
+    /// Starts the forecast from the provided priors, rather than from nothing
+    pub fn with_priors(mut self, priors: &[DemandPrior]) -> Self {
+        for prior in priors {
+            *self
+                .priors
+                .entry((prior.priority, prior.destination))
+                .or_default() += prior.orders_per_day;
+        }
+        self
+    }
+
+    /// Whether there's anything to forecast from, either priors or previous runs
+    pub fn is_empty(&self) -> bool {
+        self.days == 0 && self.priors.is_empty()
+    }
+
+    /// Add the demand from a completed run (covering a single day) to the history
+    pub fn observe(&mut self, demand: &DemandProfile) {
+        for (key, counts) in demand.counts() {
+            let totals = self
+                .totals
+                .entry(*key)
+                .or_insert_with(|| vec![0; DemandProfile::BUCKETS_PER_DAY]);
+            for (total, count) in totals.iter_mut().zip(counts) {
+                *total += count;
+            }
+        }
+        self.days += 1;
+    }
+
+    /// Returns the number of days of history which have been observed
+    pub fn days(&self) -> u32 {
+        self.days
+    }
+}
+
+impl Forecaster for HistoricalAverage {
+    fn expected_orders(
+        &self,
+        priority: Priority,
+        current_time: u64,
+        window_s: u64,
+    ) -> HashMap<DestinationId, f32> {
+        if self.is_empty() {
+            return HashMap::new();
+        }
+
+        let end_time = current_time + window_s;
+        let bucket_s = DemandProfile::BUCKET_S;
+
+        let mut expected = HashMap::<DestinationId, f32>::new();
+        for ((_, destination), totals) in self.totals.iter().filter(|((p, _), _)| *p == priority) {
+            // Prorate the buckets which only partially overlap with the window
+            *expected.entry(*destination).or_default() += (current_time / bucket_s
+                ..end_time.div_ceil(bucket_s))
+                .map(|bucket| {
+                    let overlap =
+                        end_time.min((bucket + 1) * bucket_s) - current_time.max(bucket * bucket_s);
+                    let total = totals[bucket as usize % DemandProfile::BUCKETS_PER_DAY];
+                    total as f32 * overlap as f32 / bucket_s as f32
+                })
+                .sum::<f32>();
+        }
+        for ((_, destination), orders_per_day) in
+            self.priors.iter().filter(|((p, _), _)| *p == priority)
+        {
+            *expected.entry(*destination).or_default() +=
+                orders_per_day * window_s as f32 / DAY_S as f32;
+        }
+
+        let days = self.days + !self.priors.is_empty() as u32;
+        for orders in expected.values_mut() {
+            *orders /= days as f32;
+        }
+        expected
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use schema::Order;
+
+    use super::*;
+
+    #[test]
+    fn test_historical_average() {
+        let order = |time, priority| Order {
+            time,
+            destination: DestinationId::from("Bigelow"),
+            priority,
+            ..Default::default()
+        };
+
+        let mut forecaster = HistoricalAverage::default();
+        for orders in [1, 3] {
+            let mut demand = DemandProfile::default();
+            for _ in 0..orders {
+                demand.record(&order(60, Priority::Emergency));
+            }
+            demand.record(&order(60, Priority::Resupply));
+            forecaster.observe(&demand);
+        }
+
+        let expected = forecaster.expected_orders(Priority::Emergency, 0, 30 * 60);
+        assert_eq!(expected.get(&DestinationId::from("Bigelow")), Some(&2.0));
+
+        // Only half of the first bucket falls within the window
+        let expected = forecaster.expected_orders(Priority::Emergency, 15 * 60, 30 * 60);
+        assert_eq!(expected.get(&DestinationId::from("Bigelow")), Some(&1.0));
+
+        let expected = forecaster.expected_orders(Priority::Resupply, 0, 30 * 60);
+        assert_eq!(expected.get(&DestinationId::from("Bigelow")), Some(&1.0));
+    }
+
+    #[test]
+    fn test_priors() {
+        let prior = |destination: &str, orders_per_day| DemandPrior {
+            destination: DestinationId::from(destination),
+            priority: Priority::Emergency,
+            orders_per_day,
+        };
+        let mut forecaster =
+            HistoricalAverage::default().with_priors(&[prior("Bigelow", 96.0), prior("Ray", 48.0)]);
+        assert!(!forecaster.is_empty());
+
+        // Priors are spread evenly through the day
+        let expected = forecaster.expected_orders(Priority::Emergency, 0, 30 * 60);
+        assert_eq!(expected.get(&DestinationId::from("Bigelow")), Some(&2.0));
+        assert_eq!(expected.get(&DestinationId::from("Ray")), Some(&1.0));
+        assert!(forecaster
+            .expected_orders(Priority::Resupply, 0, 30 * 60)
+            .is_empty());
+
+        // ...& are averaged with the runs observed after them as another day
+        let mut demand = DemandProfile::default();
+        for _ in 0..4 {
+            demand.record(&Order {
+                time: 60,
+                destination: DestinationId::from("Bigelow"),
+                priority: Priority::Emergency,
+                ..Default::default()
+            });
+        }
+        forecaster.observe(&demand);
+        let expected = forecaster.expected_orders(Priority::Emergency, 0, 30 * 60);
+        assert_eq!(expected.get(&DestinationId::from("Bigelow")), Some(&3.0));
+        assert_eq!(expected.get(&DestinationId::from("Ray")), Some(&0.5));
+    }
+}
@@ -0,0 +1,53 @@
+//! The simulation engine: schedulers, the runner which plays a scenario through them, & the
+//! reports, forecasts & alerts worked out along the way. Embedded by the gRPC server, the cli &
+//! the Python bindings alike, without depending on any of the server's networking.
+
+mod alerts;
+mod approval;
+mod compare;
+mod dedupe;
+mod explain;
+mod forecast;
+mod packing;
+mod plugin;
+mod proof;
+mod rate_limit;
+mod routing;
+mod runner;
+mod safety;
+mod scheduler;
+mod sectors;
+mod separation;
+mod stats;
+mod summary;
+mod surge;
+mod sweep;
+mod throttle;
+#[cfg(feature = "vrp")]
+mod vrp;
+
+pub use alerts::{AlertMonitor, AlertRule, Gauge, Gauges, Operator};
+pub use approval::Approvals;
+pub use compare::{Better, Comparison, KpiDelta, LatencyDelta};
+pub use forecast::HistoricalAverage;
+pub use packing::{
+    packing_strategy, BestFit, Bins, FirstFit, PackingStrategy, PriorityFit, WorstFit,
+};
+pub use plugin::{PluginBin, PluginOrder, PluginStrategy, PLUGIN_ABI_VERSION};
+pub use routing::RoutePlanner;
+pub use runner::{
+    Checkpoint, Command, CsvRunner, SchedulerKind, Submitted, DEFAULT_MAX_UPDATES_PER_SECOND,
+};
+pub use safety::SafetyMonitor;
+pub use scheduler::{GroundFleet, Handover, NaiveScheduler, SimulatedScheduler};
+pub use sectors::SectorMap;
+pub use separation::Airspace;
+pub use stats::{
+    CarrierStats, DemandProfile, DestinationLatency, FlightEnergy, OrderOutcome, ReportDisplay,
+    RunReport, SectorStats, SettledOrder, StatsCollector,
+};
+pub use summary::SummaryFormat;
+pub use surge::{SurgeDetector, SurgePolicy};
+pub use sweep::{sweep_csv, Sweep, SweepConfig, SweepResults, SweepScheduler};
+#[cfg(feature = "vrp")]
+pub use vrp::VrpScheduler;
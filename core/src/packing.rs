@@ -0,0 +1,205 @@
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use schema::{Order, Priority};
+
+use crate::PluginStrategy;
+
+/// Decides which of the available carriers each order is packed into when flights are launched,
+/// see `NaiveScheduler::with_packing`
+pub trait PackingStrategy: Send + Sync {
+    /// Returns the bins in the order they should be tried for an order. The order is packed into
+    /// the first with the range (& timing) to deliver it.
+    fn candidates<'b>(&self, bins: &'b Bins, order: &Order)
+        -> Box<dyn Iterator<Item = usize> + 'b>;
+}
+
+impl PackingStrategy for Arc<dyn PackingStrategy> {
+    fn candidates<'b>(
+        &self,
+        bins: &'b Bins,
+        order: &Order,
+    ) -> Box<dyn Iterator<Item = usize> + 'b> {
+        self.as_ref().candidates(bins, order)
+    }
+}
+
+/// Looks up a built-in strategy by name, or loads a plugin from `plugin:<path>`
+pub fn packing_strategy(name: &str) -> Result<Arc<dyn PackingStrategy>, String> {
+    if let Some(path) = name.strip_prefix("plugin:") {
+        return Ok(Arc::new(PluginStrategy::load(path)?));
+    }
+    match name {
+        "priority" => Ok(Arc::new(PriorityFit)),
+        "first-fit" => Ok(Arc::new(FirstFit)),
+        "best-fit" => Ok(Arc::new(BestFit)),
+        "worst-fit" => Ok(Arc::new(WorstFit)),
+        _ => Err(format!(
+            "unknown packing strategy `{}`, expected `priority`, `first-fit`, `best-fit`, `worst-fit` or `plugin:<path>`",
+            name
+        )),
+    }
+}
+
+/// Minimizes the delivery time of emergencies by packing them into the least full carriers, and
+/// maximizes utilization by packing resupplies into the most full
+pub struct PriorityFit;
+
+impl PackingStrategy for PriorityFit {
+    fn candidates<'b>(
+        &self,
+        bins: &'b Bins,
+        order: &Order,
+    ) -> Box<dyn Iterator<Item = usize> + 'b> {
+        match order.priority {
+            Priority::Emergency => Box::new(bins.by_distance()),
+            Priority::Resupply => Box::new(bins.by_load()),
+        }
+    }
+}
+
+/// Packs each order into the first carrier with room for it
+pub struct FirstFit;
+
+impl PackingStrategy for FirstFit {
+    fn candidates<'b>(
+        &self,
+        bins: &'b Bins,
+        _order: &Order,
+    ) -> Box<dyn Iterator<Item = usize> + 'b> {
+        Box::new(0..bins.len())
+    }
+}
+
+/// Packs each order into the carrier with the least range left which can still deliver it
+pub struct BestFit;
+
+impl PackingStrategy for BestFit {
+    fn candidates<'b>(
+        &self,
+        bins: &'b Bins,
+        _order: &Order,
+    ) -> Box<dyn Iterator<Item = usize> + 'b> {
+        Box::new(bins.by_distance().rev())
+    }
+}
+
+/// Packs each order into the carrier with the most range left, spreading orders across the fleet
+pub struct WorstFit;
+
+impl PackingStrategy for WorstFit {
+    fn candidates<'b>(
+        &self,
+        bins: &'b Bins,
+        _order: &Order,
+    ) -> Box<dyn Iterator<Item = usize> + 'b> {
+        Box::new(bins.by_distance())
+    }
+}
+
+/// Orders being packed into one of the available carriers
+#[derive(Debug)]
+pub(crate) struct Bin {
+    pub(crate) distance_allocated: u64,
+    pub(crate) orders: Vec<Order>,
+    /// Packages making up the orders, each taking up one of the carrier's slots
+    pub(crate) packages: usize,
+}
+
+/// Bins indexed by their allocated distance & number of packages,
+/// so that the best bin for each order can be found without re-sorting them
+pub struct Bins {
+    bins: Vec<Bin>,
+    /// Bins by the distance allocated to them, least first
+    by_distance: BTreeSet<(u64, usize)>,
+    /// Bins by the number of packages packed into them, most first
+    by_load: BTreeSet<(Reverse<usize>, usize)>,
+}
+
+impl Bins {
+    pub(crate) fn new(count: usize) -> Self {
+        Self {
+            bins: (0..count)
+                .map(|_| Bin {
+                    distance_allocated: 0,
+                    orders: vec![],
+                    packages: 0,
+                })
+                .collect(),
+            by_distance: (0..count).map(|i| (0, i)).collect(),
+            by_load: (0..count).map(|i| (Reverse(0), i)).collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bins.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bins.is_empty()
+    }
+
+    /// Returns the bins by the distance allocated to them, least first
+    pub fn by_distance(&self) -> impl DoubleEndedIterator<Item = usize> + '_ {
+        self.by_distance.iter().map(|(_, i)| *i)
+    }
+
+    /// Returns the bins by the number of packages packed into them, most first
+    pub fn by_load(&self) -> impl DoubleEndedIterator<Item = usize> + '_ {
+        self.by_load.iter().map(|(_, i)| *i)
+    }
+
+    pub(crate) fn get(&self, i: usize) -> &Bin {
+        &self.bins[i]
+    }
+
+    pub(crate) fn push(&mut self, i: usize, order: Order, distance: u64) {
+        let bin = &mut self.bins[i];
+        self.by_distance.remove(&(bin.distance_allocated, i));
+        self.by_load.remove(&(Reverse(bin.packages), i));
+
+        bin.packages += order.quantity as usize;
+        bin.orders.push(order);
+        bin.distance_allocated += distance;
+
+        self.by_distance.insert((bin.distance_allocated, i));
+        self.by_load.insert((Reverse(bin.packages), i));
+    }
+
+    pub(crate) fn into_bins(self) -> Vec<Bin> {
+        self.bins
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use schema::testkit;
+
+    use super::*;
+
+    #[test]
+    fn test_strategies() {
+        // One empty bin, one with two short legs & one with a single long leg
+        let mut bins = Bins::new(3);
+        bins.push(1, testkit::order(0, "Near", Priority::Resupply), 10_000);
+        bins.push(1, testkit::order(0, "Near", Priority::Resupply), 10_000);
+        bins.push(2, testkit::order(0, "Far", Priority::Resupply), 50_000);
+
+        let order = |priority| testkit::order(0, "Near", priority);
+        let candidates = |strategy: &str, priority| {
+            packing_strategy(strategy)
+                .expect("strategy")
+                .candidates(&bins, &order(priority))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(candidates("priority", Priority::Emergency), vec![0, 1, 2]);
+        assert_eq!(candidates("priority", Priority::Resupply), vec![1, 2, 0]);
+        assert_eq!(candidates("first-fit", Priority::Resupply), vec![0, 1, 2]);
+        assert_eq!(candidates("best-fit", Priority::Emergency), vec![2, 1, 0]);
+        assert_eq!(candidates("worst-fit", Priority::Resupply), vec![0, 1, 2]);
+
+        assert!(packing_strategy("random").is_err());
+    }
+}
@@ -0,0 +1,185 @@
+//! Experimental host for packing strategies implemented outside of the crate, loaded from native
+//! dynamic libraries (`.so`, `.dylib` or `.dll`) with `PACKING=plugin:<path>`. Plugins may be
+//! written in any language able to export C functions:
+//!
+//! - `uint32_t scheduler_plugin_abi_version(void)`, returning `PLUGIN_ABI_VERSION`
+//! - `size_t scheduler_plugin_candidates(const PluginBin *bins, size_t bins_len,
+//!   const PluginOrder *order, size_t *candidates)`, mirroring `PackingStrategy::candidates`
+//!
+//! Plugins write the indices of the bins to try for the order into `candidates`, which has room for
+//! `bins_len` of them, returning how many they wrote. Indices which don't name a bin are ignored.
+//! Neither function may hold onto any of the pointers it's passed.
+//!
+//! Only packing is pluggable so far: there's no ABI for whole `Scheduler` implementations (which
+//! would need to carry flights, receipts & checkpoints across it), nor for loading plugins as WASM.
+
+use std::ffi::c_char;
+
+use libloading::{Library, Symbol};
+use schema::{Order, Priority};
+
+use crate::{Bins, PackingStrategy};
+
+/// Version of the plugin ABI, raised whenever any of its types or functions change
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+/// Orders already packed into a carrier, as passed to plugins
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PluginBin {
+    /// Meters of range allocated to the orders so far
+    pub distance_allocated_m: u64,
+    /// Number of orders packed into the carrier
+    pub orders: u64,
+}
+
+/// Order being packed, as passed to plugins
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct PluginOrder {
+    /// Timestamp in __seconds__ at which the order was placed
+    pub time: u64,
+    /// 0 for emergencies, 1 for resupplies
+    pub priority: u32,
+    /// UTF-8 name of the destination, which isn't nul-terminated
+    pub destination: *const c_char,
+    pub destination_len: usize,
+}
+
+type AbiVersionFn = unsafe extern "C" fn() -> u32;
+type CandidatesFn =
+    unsafe extern "C" fn(*const PluginBin, usize, *const PluginOrder, *mut usize) -> usize;
+
+/// Packing strategy implemented by a plugin, see the module documentation
+pub struct PluginStrategy {
+    candidates: CandidatesFn,
+    /// Kept loaded for as long as its functions may be called
+    _library: Option<Library>,
+}
+
+impl PluginStrategy {
+    /// Loads the plugin at the provided path, checking that it implements this version of the ABI
+    pub fn load(path: &str) -> Result<Self, String> {
+        let invalid = |e: libloading::Error| format!("invalid plugin {}: {}", path, e);
+        // Safety: loading runs the library's initialisers, which can't be checked. Plugins are
+        // trusted to the same degree as the server itself.
+        let library = unsafe { Library::new(path) }.map_err(invalid)?;
+        let (version, candidates) = unsafe {
+            let version: Symbol<AbiVersionFn> = library
+                .get(b"scheduler_plugin_abi_version\0")
+                .map_err(invalid)?;
+            let candidates: Symbol<CandidatesFn> = library
+                .get(b"scheduler_plugin_candidates\0")
+                .map_err(invalid)?;
+            (version(), *candidates)
+        };
+        if version != PLUGIN_ABI_VERSION {
+            return Err(format!(
+                "plugin {} implements version {} of the ABI, expected {}",
+                path, version, PLUGIN_ABI_VERSION
+            ));
+        }
+
+        tracing::info!("loaded packing plugin {}", path);
+        Ok(Self {
+            candidates,
+            _library: Some(library),
+        })
+    }
+}
+
+impl PackingStrategy for PluginStrategy {
+    fn candidates<'b>(
+        &self,
+        bins: &'b Bins,
+        order: &Order,
+    ) -> Box<dyn Iterator<Item = usize> + 'b> {
+        let plugin_bins = (0..bins.len())
+            .map(|i| {
+                let bin = bins.get(i);
+                PluginBin {
+                    distance_allocated_m: bin.distance_allocated,
+                    orders: bin.orders.len() as u64,
+                }
+            })
+            .collect::<Vec<_>>();
+        let destination = order.destination.as_str();
+        let plugin_order = PluginOrder {
+            time: order.time,
+            priority: match order.priority {
+                Priority::Emergency => 0,
+                Priority::Resupply => 1,
+            },
+            destination: destination.as_ptr().cast(),
+            destination_len: destination.len(),
+        };
+
+        let mut candidates = vec![0; plugin_bins.len()];
+        // Safety: each pointer is valid for the duration of the call, & `candidates` has room
+        // for as many indices as there are bins
+        let written = unsafe {
+            (self.candidates)(
+                plugin_bins.as_ptr(),
+                plugin_bins.len(),
+                &plugin_order,
+                candidates.as_mut_ptr(),
+            )
+        };
+        candidates.truncate(written.min(plugin_bins.len()));
+        candidates.retain(|i| *i < plugin_bins.len());
+        Box::new(candidates.into_iter())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use schema::testkit;
+
+    use super::*;
+
+    /// Tries emergencies in the emptiest bins, & the rest in reverse, along with a bin which
+    /// doesn't exist
+    unsafe extern "C" fn candidates(
+        bins: *const PluginBin,
+        bins_len: usize,
+        order: *const PluginOrder,
+        candidates: *mut usize,
+    ) -> usize {
+        let bins = std::slice::from_raw_parts(bins, bins_len);
+        let candidates = std::slice::from_raw_parts_mut(candidates, bins_len);
+        let mut indices = (0..bins_len).collect::<Vec<_>>();
+        match (*order).priority {
+            0 => indices.sort_by_key(|i| bins[*i].orders),
+            _ => {
+                indices.reverse();
+                indices[0] = 99;
+            }
+        }
+        candidates.copy_from_slice(&indices);
+        // Claims to have written more than it had room for
+        bins_len + 2
+    }
+
+    #[test]
+    fn test_plugin_strategy() {
+        let strategy = PluginStrategy {
+            candidates,
+            _library: None,
+        };
+        let mut bins = Bins::new(3);
+        bins.push(0, testkit::order(0, "Near", Priority::Resupply), 10_000);
+        bins.push(0, testkit::order(0, "Near", Priority::Resupply), 10_000);
+        bins.push(2, testkit::order(0, "Far", Priority::Resupply), 50_000);
+
+        let candidates = |priority| {
+            strategy
+                .candidates(&bins, &testkit::order(0, "Near", priority))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(candidates(Priority::Emergency), vec![1, 2, 0]);
+        // Indices outside of the bins are dropped
+        assert_eq!(candidates(Priority::Resupply), vec![1, 0]);
+
+        assert!(PluginStrategy::load("/nonexistent/plugin.so").is_err());
+    }
+}
@@ -0,0 +1,72 @@
+use schema::{CarrierId, DeliveryProof, Destination, Order};
+
+/// Furthest in meters that a simulated drop lands from its destination, each way
+const DROP_SPREAD_M: i64 = 3;
+
+/// Simulates the proof collected as a carrier drops an order off at its destination. The drop
+/// lands within a few meters of the destination, & the photo hash & recipient code are derived
+/// from the delivery itself, so that replaying a run reproduces the same proofs.
+pub(crate) fn simulate(
+    order: &Order,
+    carrier: CarrierId,
+    destination: &Destination,
+    delivery_time: u64,
+) -> DeliveryProof {
+    let mut seed = order.id.0 ^ (carrier.0 as u64).rotate_left(32) ^ delivery_time.rotate_left(16);
+    for byte in destination.name.as_str().as_bytes() {
+        seed = (seed ^ *byte as u64).wrapping_mul(0x100_0000_01b3);
+    }
+    let mut next = move || {
+        seed = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        splitmix64(seed)
+    };
+
+    let spread = |value: u64| (value % (2 * DROP_SPREAD_M as u64 + 1)) as i64 - DROP_SPREAD_M;
+    let drop_north_m = destination.north_m + spread(next());
+    let drop_east_m = destination.east_m + spread(next());
+    let photo_hash = (0..4).map(|_| format!("{:016x}", next())).collect();
+    let recipient_code = format!("{:06}", next() % 1_000_000);
+    DeliveryProof {
+        drop_north_m,
+        drop_east_m,
+        photo_hash,
+        recipient_code,
+    }
+}
+
+/// Finalizer of the SplitMix64 generator, scrambling a value into a well-distributed one
+fn splitmix64(mut value: u64) -> u64 {
+    value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    value ^ (value >> 31)
+}
+
+#[cfg(test)]
+mod test {
+    use schema::{testkit, DestinationId, OrderId, Priority};
+
+    use super::*;
+
+    #[test]
+    fn test_simulate() {
+        let destination = testkit::destinations(&[("Toronto", 1_000, -2_000)])
+            .remove(&DestinationId::from("Toronto"))
+            .expect("destination");
+        let order = Order {
+            id: OrderId(3),
+            ..testkit::order(0, "Toronto", Priority::Resupply)
+        };
+        let proof = simulate(&order, CarrierId(1), &destination, 600);
+
+        // The drop lands close to the destination, & the same delivery gets the same proof
+        assert!((proof.drop_north_m - 1_000).abs() <= DROP_SPREAD_M);
+        assert!((proof.drop_east_m + 2_000).abs() <= DROP_SPREAD_M);
+        assert_eq!(proof.photo_hash.len(), 64);
+        assert_eq!(proof.recipient_code.len(), 6);
+        assert_eq!(simulate(&order, CarrierId(1), &destination, 600), proof);
+
+        // ...while other deliveries get others
+        let other = simulate(&order, CarrierId(2), &destination, 600);
+        assert_ne!(other.photo_hash, proof.photo_hash);
+    }
+}
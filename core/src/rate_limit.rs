@@ -0,0 +1,75 @@
+use tokio::time::Instant;
+
+/// Token-bucket limiting how often something may happen in wall-clock time.
+/// The bucket holds a single token, so events can't burst above the configured rate.
+pub struct TokenBucket {
+    /// Tokens added to the bucket each second
+    rate_per_second: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    const CAPACITY: f64 = 1.0;
+
+    /// Creates a full bucket which refills at the provided rate
+    pub fn new(rate_per_second: u32) -> Self {
+        Self {
+            rate_per_second: rate_per_second as f64,
+            tokens: Self::CAPACITY,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Takes a token from the bucket if one is available
+    pub fn try_take(&mut self) -> bool {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate_per_second).min(Self::CAPACITY);
+        self.last_refill = now;
+
+        match self.tokens >= 1.0 {
+            true => {
+                self.tokens -= 1.0;
+                true
+            }
+            false => false,
+        }
+    }
+
+    /// Returns when the next token will be available, which may already have passed
+    pub fn ready_at(&self) -> Instant {
+        let missing = (1.0 - self.tokens).max(0.0);
+        self.last_refill + std::time::Duration::from_secs_f64(missing / self.rate_per_second)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_token_bucket() {
+        let mut bucket = TokenBucket::new(10);
+
+        // Starts full, without allowing bursts
+        assert!(bucket.try_take());
+        assert!(!bucket.try_take());
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!bucket.try_take());
+        assert_eq!(
+            bucket.ready_at(),
+            Instant::now() + Duration::from_millis(50)
+        );
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(bucket.try_take());
+
+        // Idle time doesn't accumulate more than a single token
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        assert!(bucket.try_take());
+        assert!(!bucket.try_take());
+    }
+}
@@ -0,0 +1,79 @@
+use itertools::Itertools;
+use schema::Flight;
+
+/// Most orders on a flight for which every order they could be delivered in is tried, beyond which
+/// stops are swapped pairwise for as long as that saves energy
+const MAX_EXHAUSTIVE_ORDERS: usize = 6;
+
+/// Decides the order a flight's carrier visits its stops in, see `NaiveScheduler::with_route_planner`
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RoutePlanner {
+    /// Visit the stops in the order their orders were packed, most urgent first, each leg being
+    /// measured from the last stop
+    #[default]
+    Packed,
+    /// Reorder the stops of each aerial flight to spend the least energy, according to the
+    /// scheduler's `EnergyModel`, while still meeting every order's delivery window & shelf life
+    /// within the carrier's range. Heavier payloads are dropped earlier & headwinds avoided where
+    /// that saves more than the extra distance costs.
+    Energy,
+}
+
+impl std::str::FromStr for RoutePlanner {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "packed" => Ok(Self::Packed),
+            "energy" => Ok(Self::Energy),
+            _ => Err(format!(
+                "unknown route planner `{}`, expected `packed` or `energy`",
+                s
+            )),
+        }
+    }
+}
+
+/// Reorders a flight's orders to spend the least energy, among the orderings which are feasible.
+/// The orders are left as they are unless an ordering spends less.
+pub(crate) fn plan_least_energy(
+    flight: &mut Flight,
+    energy_j: impl Fn(&Flight) -> f32,
+    feasible: impl Fn(&Flight) -> bool,
+) {
+    let packed = flight.orders.clone();
+    let mut best = (energy_j(flight), packed.clone());
+    let mut try_ordering = |flight: &mut Flight, ordering: &[usize]| {
+        flight.orders = ordering.iter().map(|i| packed[*i].clone()).collect();
+        let spent = energy_j(flight);
+        let improved = spent < best.0 && feasible(flight);
+        if improved {
+            best = (spent, flight.orders.clone());
+        }
+        improved
+    };
+
+    match packed.len() {
+        0..=1 => {}
+        n if n <= MAX_EXHAUSTIVE_ORDERS => {
+            for ordering in (0..n).permutations(n) {
+                try_ordering(flight, &ordering);
+            }
+        }
+        n => {
+            let mut ordering = (0..n).collect::<Vec<_>>();
+            let mut improved = true;
+            while improved {
+                improved = false;
+                for (i, j) in (0..n).tuple_combinations() {
+                    ordering.swap(i, j);
+                    match try_ordering(flight, &ordering) {
+                        true => improved = true,
+                        false => ordering.swap(i, j),
+                    }
+                }
+            }
+        }
+    }
+    flight.orders = best.1;
+}
@@ -0,0 +1,2763 @@
+use std::{
+    cell::Cell,
+    collections::{HashMap, HashSet},
+    future::Future,
+    num::{NonZeroU32, NonZeroU64},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures::{
+    channel::{mpsc, oneshot},
+    stream::Peekable,
+    FutureExt, Stream, StreamExt,
+};
+use schema::binary::{self, OrdersFile};
+use schema::time;
+use schema::{
+    Alert, Backlog, CarrierMode, Destination, DestinationChanges, DestinationId,
+    DestinationMatcher, DestinationNames, EnergyModel, Flight, FlightExplanation, FlightId,
+    FlightPhases, Forecaster, LoadProgress, Order, OrderId, OrderReceipt, Priority, RangeReduction,
+    ReturnPolicy, Runner, Sector, SectorOccupancy, Speed, SpeedSchedule, StatusUpdate,
+};
+
+use crate::dedupe::DedupeCache;
+use crate::rate_limit::TokenBucket;
+use crate::sectors::{self, SectorMap};
+use crate::throttle::Throttle;
+use crate::{
+    Airspace, AlertMonitor, AlertRule, Approvals, Gauges, GroundFleet, Handover, HistoricalAverage,
+    NaiveScheduler, PackingStrategy, PriorityFit, RoutePlanner, RunReport, SafetyMonitor,
+    SettledOrder, SimulatedScheduler, StatsCollector, SurgePolicy,
+};
+
+type Success = <CsvRunner as Runner<NaiveScheduler>>::Success;
+type Error = <CsvRunner as Runner<NaiveScheduler>>::Error;
+type Response = Pin<Box<dyn Future<Output = Result<Success, Error>>>>;
+
+/// Longest the runner goes without checking for commands & live orders while it skips through
+/// stretches of the run in which nothing happens
+const QUIET_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Default limit on the status updates emitted each second of wall-clock time,
+/// regardless of whether we are fast-forwarding
+pub const DEFAULT_MAX_UPDATES_PER_SECOND: u32 = 4;
+
+/// Default parameters of the carriers controlled by the scheduler
+pub(crate) const DEFAULT_CARRIERS: usize = 10;
+pub(crate) const MAX_ORDERS_PER_CARRIER: usize = 3;
+pub(crate) const DEFAULT_CARRIER_SPEED_MPS: u64 = 30;
+pub(crate) const CARRIER_RANGE_M: u64 = 160_000;
+
+/// Number of idempotency keys remembered by a run, after which the oldest are forgotten
+const DEDUPE_CAPACITY: usize = 10_000;
+
+/// Why orders are turned away once a run is draining, see `Command::Drain`
+const DRAINING: &str = "the run is draining, so isn't taking more orders";
+
+/// Parameters controlling the pace of a run
+#[derive(Clone)]
+struct RunOptions {
+    speed: Speed,
+    /// Speeds to play the run at through each day, in place of `speed` within their windows
+    speed_schedule: Option<SpeedSchedule>,
+    /// Timestamp in __seconds__ to start the simulation from, instead of the first order
+    start_time: Option<u64>,
+    max_updates_per_second: u32,
+    /// Side in meters of the grid cells which no two carriers may share at the same second
+    separation_m: Option<NonZeroU64>,
+    /// Simulated seconds between checkpoints of the run, if any are taken
+    checkpoint_interval_s: Option<u64>,
+    /// Longest gap in __seconds__ between scripted orders, beyond which the rest is skipped
+    compress_gaps_s: Option<NonZeroU64>,
+    /// Seconds after which flights carrying emergencies are approved automatically, if they have
+    /// to be approved at all
+    auto_approve_s: Option<NonZeroU64>,
+}
+
+/// Schedulers which can be swapped in while a simulation is running
+#[derive(Clone, Debug)]
+pub enum SchedulerKind {
+    /// `NaiveScheduler` holding a fixed number of carriers in reserve
+    Naive,
+    /// `NaiveScheduler` reserving carriers for the emergencies forecast from previous demand
+    Forecasting(HistoricalAverage),
+}
+
+/// Whether a submission of orders was queued
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Submitted {
+    /// The orders were queued with the provided identifiers, in the order they were submitted
+    Accepted(Vec<OrderId>),
+    /// A submission with the same idempotency key was already queued, so this one was ignored
+    AlreadyKnown,
+}
+
+/// Commands altering a run which is in progress
+#[derive(Debug)]
+pub enum Command {
+    /// Replace the scheduler, handing over its queued orders & in-flight carriers
+    SwapScheduler(SchedulerKind),
+    /// Queue orders placed at the current simulated time. None of the orders are queued if any
+    /// are invalid, in which case the reply describes what was wrong. Retries of a submission with
+    /// the same idempotency key are only queued once.
+    QueueOrders(
+        Vec<Order>,
+        Option<String>,
+        oneshot::Sender<Result<Submitted, String>>,
+    ),
+    /// Reply with the flights that would be launched at the current simulated time if the orders
+    /// were queued, along with that time, without committing to any of them
+    PreviewSchedule(
+        Vec<Order>,
+        oneshot::Sender<Result<(u64, Vec<Flight>), String>>,
+    ),
+    /// Add & deactivate destinations, replying with what was wrong if any of the deactivated
+    /// destinations are unknown. Queued orders for deactivated destinations are rejected.
+    UpdateDestinations(DestinationChanges, oneshot::Sender<Result<(), String>>),
+    /// Reply with the lifecycle of an order as of the current simulated time, if it was queued
+    GetOrder(OrderId, oneshot::Sender<Option<OrderReceipt>>),
+    /// Reply with the decisions behind a flight, if it was launched during the run
+    ExplainFlight(FlightId, oneshot::Sender<Option<FlightExplanation>>),
+    /// Add or retire idle carriers until the fleet is the provided size, replying with its size
+    /// once resized, which may be larger if more carriers are in flight
+    SetFleetSize(usize, oneshot::Sender<usize>),
+    /// Stop accepting & scheduling orders, finishing the run once the flights already scheduled
+    /// have landed, e.g. ahead of a restart. Replies with the number of flights left to land.
+    /// Orders still queued are reported as drained rather than unfulfilled.
+    Drain(oneshot::Sender<usize>),
+    /// Approve a flight carrying emergencies to launch at the next launch slot, replying with
+    /// what was wrong if it isn't awaiting approval
+    ApproveFlight(FlightId, oneshot::Sender<Result<(), String>>),
+}
+
+/// State of a run at the end of a simulated minute, from which another run can resume it, e.g.
+/// after the scheduler has panicked
+#[derive(Clone)]
+pub struct Checkpoint {
+    /// Timestamp in __seconds__ of the minute the checkpoint was taken at
+    pub time: u64,
+    /// Timestamp the run started at, so that a resumed run still reports on all of it
+    started_at: u64,
+    destinations: HashMap<DestinationId, Destination>,
+    deactivated: HashSet<DestinationId>,
+    scheduler: Handover,
+    stats: StatsCollector,
+}
+
+/// Orders scripted ahead of a run, in the order they're placed
+struct ScriptedOrders {
+    orders: Box<dyn Iterator<Item = Result<Order, String>> + Send>,
+    /// Time the last of the orders is placed at, if there are any
+    last_time: Option<u64>,
+}
+
+/// Brings scripted orders forward past any gap since the previous order longer than the maximum,
+/// so that idle stretches of a replayed log are skipped instantly
+struct GapCompressor {
+    max_gap_s: Option<NonZeroU64>,
+    /// Time the previous order was originally placed at
+    previous: Option<u64>,
+    /// Time the previous order is placed at once compressed
+    last_time: Option<u64>,
+    /// Seconds skipped so far
+    compressed_s: u64,
+}
+
+impl GapCompressor {
+    fn new(max_gap_s: Option<NonZeroU64>) -> Self {
+        Self {
+            max_gap_s,
+            previous: None,
+            last_time: None,
+            compressed_s: 0,
+        }
+    }
+
+    fn compress(&mut self, mut order: Order) -> Order {
+        let Some(max_gap_s) = self.max_gap_s else {
+            return order;
+        };
+        if let Some(previous) = self.previous {
+            self.compressed_s += order
+                .time
+                .saturating_sub(previous)
+                .saturating_sub(max_gap_s.get());
+        }
+        self.previous = Some(order.time);
+
+        let compressed_s = self.compressed_s;
+        let shift = move |time: u64| time.saturating_sub(compressed_s);
+        order.time -= compressed_s;
+        order.earliest_delivery = order.earliest_delivery.map(shift);
+        order.latest_delivery = order.latest_delivery.map(shift);
+        self.last_time = Some(order.time);
+        order
+    }
+}
+
+/// What a run simulates
+struct RunInputs {
+    destinations: HashMap<DestinationId, Destination>,
+    orders: ScriptedOrders,
+    range_reductions: Vec<RangeReduction>,
+    matcher: DestinationMatcher,
+    names: DestinationNames,
+    alert_rules: Vec<AlertRule>,
+    sectors: Vec<Sector>,
+    /// Checkpoint of an earlier run to resume from, if any
+    resume: Option<Checkpoint>,
+}
+
+/// Channels through which a run communicates while it is in progress
+struct Channels {
+    updates: mpsc::UnboundedSender<StatusUpdate>,
+    alerts: mpsc::UnboundedSender<Alert>,
+    destination_changes: mpsc::UnboundedSender<DestinationChanges>,
+    checkpoints: mpsc::UnboundedSender<Checkpoint>,
+    settled_orders: mpsc::UnboundedSender<SettledOrder>,
+    live_orders: Option<mpsc::UnboundedReceiver<Order>>,
+    commands: Option<mpsc::UnboundedReceiver<Command>>,
+}
+
+/// Simulation runner which exercises a `Scheduler` using data provided by a CSV
+pub struct CsvRunner {
+    options: RunOptions,
+    destinations: HashMap<DestinationId, Destination>,
+    /// Nest which carriers launch from & return to
+    origin: Destination,
+    orders: Vec<Order>,
+    /// Binary file the scripted orders are streamed from instead, if any
+    orders_file: Option<OrdersFile>,
+    /// Conditions reducing the range of carriers in flight over the course of the run
+    range_reductions: Vec<RangeReduction>,
+    /// Time carriers take to take off & land at each stop
+    flight_phases: FlightPhases,
+    /// Decides which carrier each order is packed into
+    packing: Arc<dyn PackingStrategy>,
+    /// Decides the order each flight visits its stops in
+    route_planner: RoutePlanner,
+    /// Where carriers go once they've made their last stop
+    return_policy: ReturnPolicy,
+    /// Seconds after which the resupplies of destinations starved of flights are boosted, if any
+    fairness: Option<u64>,
+    /// Estimates the energy carriers spend on their flights
+    energy: EnergyModel,
+    /// Emergency arrival rate at which every carrier is given over to emergencies, if any
+    surge: Option<SurgePolicy>,
+    /// Ground vehicles making deliveries alongside the carriers
+    ground: GroundFleet,
+    /// Flights planned ahead that the scheduler follows until the orders deviate from them, if any
+    plan: Option<Vec<Flight>>,
+    /// Conditions raising alerts once they've held for long enough
+    alert_rules: Vec<AlertRule>,
+    /// Sectors of airspace which only so many carriers may fly in at once
+    sectors: Vec<Sector>,
+    /// Resolves aliased & loosely matched destination names in orders, if any
+    matcher: Option<DestinationMatcher>,
+    /// Kept resolving against the destinations of each run, for orders taken from outside
+    names: DestinationNames,
+    /// Checkpoint of an earlier run to resume from, taken by the first run
+    resume: Mutex<Option<Checkpoint>>,
+    status_updates_sender: mpsc::UnboundedSender<StatusUpdate>,
+    status_updates_receiver: Option<mpsc::UnboundedReceiver<StatusUpdate>>,
+    alerts_sender: mpsc::UnboundedSender<Alert>,
+    alerts_receiver: Option<mpsc::UnboundedReceiver<Alert>>,
+    destination_changes_sender: mpsc::UnboundedSender<DestinationChanges>,
+    destination_changes_receiver: Option<mpsc::UnboundedReceiver<DestinationChanges>>,
+    checkpoints_sender: mpsc::UnboundedSender<Checkpoint>,
+    checkpoints_receiver: Option<mpsc::UnboundedReceiver<Checkpoint>>,
+    settled_orders_sender: mpsc::UnboundedSender<SettledOrder>,
+    settled_orders_receiver: Option<mpsc::UnboundedReceiver<SettledOrder>>,
+    /// Orders placed while the simulation is running, taken by the first run
+    live_orders: Mutex<Option<mpsc::UnboundedReceiver<Order>>>,
+    commands_sender: mpsc::UnboundedSender<Command>,
+    /// Commands for the run in progress, taken by the first run
+    commands_receiver: Mutex<Option<mpsc::UnboundedReceiver<Command>>>,
+}
+
+impl CsvRunner {
+    /// Loads the destinations & orders CSVs concurrently, logging progress as they are parsed.
+    /// Orders may instead be in the binary format of `schema::binary`, in which case they're
+    /// streamed from the file as each run reaches them.
+    pub async fn from_csv_paths(
+        destinations_csv_path: &str,
+        orders_csv_path: &str,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let log_progress = |path: &str| {
+            let path = path.to_string();
+            move |progress: LoadProgress| {
+                tracing::info!(
+                    "loading {}: {}% ({} records)",
+                    path,
+                    progress.percent(),
+                    progress.records
+                )
+            }
+        };
+
+        if binary::is_binary(orders_csv_path) {
+            let orders_file = OrdersFile::open(orders_csv_path)?;
+            tracing::info!(
+                "streaming {} orders from {}",
+                orders_file.count,
+                orders_csv_path
+            );
+            let destinations = Destination::from_csv_with_progress(
+                destinations_csv_path,
+                log_progress(destinations_csv_path),
+            )
+            .await?;
+            return Ok(Self::new(destinations, vec![]).with_orders_file(orders_file));
+        }
+        let (destinations, orders) = futures::try_join!(
+            Destination::from_csv_with_progress(
+                destinations_csv_path,
+                log_progress(destinations_csv_path)
+            ),
+            Order::from_csv_with_progress(orders_csv_path, log_progress(orders_csv_path)),
+        )?;
+
+        Ok(Self::new(destinations, orders))
+    }
+
+    pub fn new(destinations: Vec<Destination>, orders: Vec<Order>) -> Self {
+        let destinations: HashMap<DestinationId, Destination> = destinations
+            .into_iter()
+            .map(|dest| (dest.name, dest))
+            .collect();
+
+        let (tx, rx) = mpsc::unbounded();
+        let (alerts_tx, alerts_rx) = mpsc::unbounded();
+        let (changes_tx, changes_rx) = mpsc::unbounded();
+        let (commands_tx, commands_rx) = mpsc::unbounded();
+        let (checkpoints_tx, checkpoints_rx) = mpsc::unbounded();
+        let (settled_tx, settled_rx) = mpsc::unbounded();
+
+        Self {
+            options: RunOptions {
+                speed: Default::default(),
+                speed_schedule: None,
+                start_time: None,
+                max_updates_per_second: DEFAULT_MAX_UPDATES_PER_SECOND,
+                separation_m: None,
+                auto_approve_s: None,
+                checkpoint_interval_s: None,
+                compress_gaps_s: None,
+            },
+            destinations,
+            origin: Destination::default_origin(),
+            orders,
+            orders_file: None,
+            range_reductions: vec![],
+            flight_phases: FlightPhases::default(),
+            packing: Arc::new(PriorityFit),
+            route_planner: RoutePlanner::default(),
+            return_policy: ReturnPolicy::default(),
+            fairness: None,
+            energy: EnergyModel::default(),
+            surge: None,
+            ground: GroundFleet::default(),
+            plan: None,
+            alert_rules: vec![],
+            sectors: vec![],
+            matcher: None,
+            names: DestinationNames::default(),
+            resume: Mutex::new(None),
+            status_updates_sender: tx,
+            status_updates_receiver: Some(rx),
+            alerts_sender: alerts_tx,
+            alerts_receiver: Some(alerts_rx),
+            destination_changes_sender: changes_tx,
+            destination_changes_receiver: Some(changes_rx),
+            checkpoints_sender: checkpoints_tx,
+            checkpoints_receiver: Some(checkpoints_rx),
+            settled_orders_sender: settled_tx,
+            settled_orders_receiver: Some(settled_rx),
+            live_orders: Mutex::new(None),
+            commands_sender: commands_tx,
+            commands_receiver: Mutex::new(Some(commands_rx)),
+        }
+    }
+
+    /// Run with the provided `Speed`
+    pub fn with_speed(mut self, speed: Speed) -> Self {
+        self.options.speed = speed;
+        self
+    }
+
+    /// Change speed through each simulated day as the provided schedule has it, e.g. running
+    /// overnight faster than during the day. The run's own speed applies outside the schedule's
+    /// windows, & subscribers are sent whichever speed is being played at with each update.
+    pub fn with_speed_schedule(mut self, schedule: SpeedSchedule) -> Self {
+        self.options.speed_schedule = Some(schedule);
+        self
+    }
+
+    /// Limit the status updates emitted each second of wall-clock time to the provided rate
+    pub fn with_max_updates_per_second(mut self, max_updates_per_second: NonZeroU32) -> Self {
+        self.options.max_updates_per_second = max_updates_per_second.get();
+        self
+    }
+
+    /// Start the simulation at the provided timestamp in __seconds__
+    pub fn with_start_time(mut self, start_time: u64) -> Self {
+        self.options.start_time = Some(start_time);
+        self
+    }
+
+    /// Launch carriers from the provided nest, rather than the default origin
+    pub fn with_origin(mut self, origin: Destination) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    /// Reduce the range of carriers in flight as the provided conditions come into effect.
+    /// Carriers which no longer have the range to complete their flights return to base early.
+    pub fn with_range_reductions(mut self, reductions: Vec<RangeReduction>) -> Self {
+        self.range_reductions = reductions;
+        self
+    }
+
+    /// Hold carriers in place for the provided times as they take off from & land at each stop
+    pub fn with_flight_phases(mut self, flight_phases: FlightPhases) -> Self {
+        self.flight_phases = flight_phases;
+        self
+    }
+
+    /// Pack orders into carriers using the provided strategy, rather than by priority
+    pub fn with_packing(mut self, packing: impl PackingStrategy + 'static) -> Self {
+        self.packing = Arc::new(packing);
+        self
+    }
+
+    /// Order the stops of each flight with the provided planner, rather than as they were packed
+    pub fn with_route_planner(mut self, route_planner: RoutePlanner) -> Self {
+        self.route_planner = route_planner;
+        self
+    }
+
+    /// Have carriers wait at the last stop of each flight until their next, rather than return to
+    /// the origin, see `NaiveScheduler::with_return_policy`
+    pub fn with_return_policy(mut self, return_policy: ReturnPolicy) -> Self {
+        self.return_policy = return_policy;
+        self
+    }
+
+    /// Boost the resupplies of destinations which have gone the provided number of seconds without
+    /// a flight launched to them, see `NaiveScheduler::with_fairness`
+    pub fn with_fairness(mut self, starvation_s: u64) -> Self {
+        self.fairness = Some(starvation_s);
+        self
+    }
+
+    /// Estimate the energy carriers spend on each flight with the provided model, rather than the
+    /// default, both to plan routes by & for the run's report
+    pub fn with_energy_model(mut self, energy: EnergyModel) -> Self {
+        self.energy = energy;
+        self
+    }
+
+    /// Give every carrier over to emergencies while they arrive faster than the provided policy
+    /// allows, including carriers held on the ground with resupplies packed.
+    /// The start & end of each surge are raised as alerts.
+    pub fn with_surge(mut self, policy: SurgePolicy) -> Self {
+        self.surge = Some(policy);
+        self
+    }
+
+    /// Make deliveries with the provided ground vehicles as well as the carriers
+    pub fn with_ground_fleet(mut self, ground: GroundFleet) -> Self {
+        self.ground = ground;
+        self
+    }
+
+    /// Warm-start the scheduler from a plan of flights computed ahead of time, e.g. by a previous
+    /// run (see `RunReport::flight_plan`), re-planning only once the orders deviate from it
+    pub fn with_plan(mut self, flights: Vec<Flight>) -> Self {
+        self.plan = Some(flights);
+        self
+    }
+
+    /// Raise an alert whenever one of the provided rules holds for as long as it allows
+    pub fn with_alert_rules(mut self, rules: Vec<AlertRule>) -> Self {
+        self.alert_rules = rules;
+        self
+    }
+
+    /// Resolve the destination names of orders with the provided matcher, both those loaded before
+    /// the run & those submitted while it's in progress
+    pub fn with_destination_matcher(mut self, matcher: DestinationMatcher) -> Self {
+        self.matcher = Some(matcher);
+        self
+    }
+
+    /// Keep the provided names resolving against the destinations of each run as they change, so
+    /// that orders from outside can be resolved before they're submitted
+    pub fn with_destination_names(mut self, names: DestinationNames) -> Self {
+        self.names = names;
+        self
+    }
+
+    /// Keep carriers separated by dividing the airspace into a grid of square cells with sides of
+    /// the provided length, holding launches which would put two carriers in the same cell at the
+    /// same second. Carriers may always share the cell containing the nest.
+    pub fn with_separation(mut self, separation_m: NonZeroU64) -> Self {
+        self.options.separation_m = Some(separation_m);
+        self
+    }
+
+    /// Hold flights carrying emergencies on the ground until they're approved with
+    /// `Command::ApproveFlight`, as a pilot in command would have to, approving them automatically
+    /// once they've waited the provided number of seconds. An alert is raised for each flight as
+    /// it starts waiting.
+    pub fn with_approvals(mut self, auto_approve_s: NonZeroU64) -> Self {
+        self.options.auto_approve_s = Some(auto_approve_s);
+        self
+    }
+
+    /// Divide the airspace into the provided sectors, holding launches which would put more
+    /// carriers in a sector at once than it allows. The carriers in each sector are included in
+    /// status updates & the run's report.
+    pub fn with_sectors(mut self, sectors: Vec<Sector>) -> Self {
+        self.sectors = sectors;
+        self
+    }
+
+    /// Take a checkpoint of the run every provided number of simulated minutes, see
+    /// `stream_checkpoints`
+    pub fn with_checkpoints(mut self, every_minutes: NonZeroU32) -> Self {
+        self.options.checkpoint_interval_s = Some(every_minutes.get() as u64 * 60);
+        self
+    }
+
+    /// Skip past any gap between scripted orders longer than the provided number of minutes, e.g.
+    /// idle nights in a log spanning weeks, so that it lasts just that long. Later orders & their
+    /// delivery windows are brought forward together, keeping their order & reported latencies.
+    pub fn with_gap_compression(mut self, max_gap_minutes: NonZeroU32) -> Self {
+        self.options.compress_gaps_s = NonZeroU64::new(max_gap_minutes.get() as u64 * 60);
+        self
+    }
+
+    /// Resume a run from a checkpoint of it, rather than starting it over. Scripted orders placed
+    /// at or before the checkpoint are skipped, having already been queued by the earlier run.
+    pub fn with_resume(self, checkpoint: Checkpoint) -> Self {
+        *self.resume.lock().expect("resume") = Some(checkpoint);
+        self
+    }
+
+    /// Stream the scripted orders from a binary orders file as each run reaches them, in place of
+    /// any orders the runner was created with
+    pub fn with_orders_file(mut self, orders_file: OrdersFile) -> Self {
+        self.orders = vec![];
+        self.orders_file = Some(orders_file);
+        self
+    }
+
+    /// Queue orders received from the provided channel as they arrive, placed at the simulated time
+    /// of their arrival. Once the channel closes, the run ends as soon as every order is delivered.
+    pub fn with_live_orders(self, orders: mpsc::UnboundedReceiver<Order>) -> Self {
+        *self.live_orders.lock().expect("live orders") = Some(orders);
+        self
+    }
+
+    /// Returns a stream of status updates
+    /// TODO: refactor runner to manage subscriptions in addition to gateway server
+    pub fn stream_updates(&mut self) -> Option<impl Stream<Item = StatusUpdate>> {
+        self.status_updates_receiver.take()
+    }
+
+    /// Returns a stream of alerts raised during the simulation
+    pub fn stream_alerts(&mut self) -> Option<impl Stream<Item = Alert>> {
+        self.alerts_receiver.take()
+    }
+
+    /// Returns a stream of the changes made to the destinations while the simulation is running
+    pub fn stream_destination_changes(&mut self) -> Option<impl Stream<Item = DestinationChanges>> {
+        self.destination_changes_receiver.take()
+    }
+
+    /// Returns a stream of the checkpoints taken during the simulation, if it takes any
+    pub fn stream_checkpoints(&mut self) -> Option<impl Stream<Item = Checkpoint>> {
+        self.checkpoints_receiver.take()
+    }
+
+    /// Returns a stream of the orders whose lifecycles end during the simulation, as each is
+    /// delivered, given up on or turned away
+    pub fn stream_settled_orders(&mut self) -> Option<impl Stream<Item = SettledOrder>> {
+        self.settled_orders_receiver.take()
+    }
+
+    /// Returns a sender for commands to alter the simulation while it is running
+    pub fn commands(&self) -> mpsc::UnboundedSender<Command> {
+        self.commands_sender.clone()
+    }
+
+    /// Run with the default inputs & carrier parameters
+    pub fn run_with_defaults(&self) -> Response {
+        self.run(self.default_scheduler())
+    }
+
+    /// Run with the default inputs & carrier parameters,
+    /// reserving carriers for the emergencies expected by the provided `Forecaster`
+    pub fn run_with_forecaster(&self, forecaster: impl Forecaster + 'static) -> Response {
+        self.run(self.default_scheduler().with_forecaster(forecaster))
+    }
+
+    fn default_scheduler(&self) -> NaiveScheduler {
+        let scheduler = Self::build_scheduler(self.destinations.clone(), SchedulerKind::Naive)
+            .with_origin(self.origin.clone())
+            .with_flight_phases(self.flight_phases)
+            .with_packing(self.packing.clone())
+            .with_route_planner(self.route_planner)
+            .with_return_policy(self.return_policy)
+            .with_energy_model(self.energy)
+            .with_ground_fleet(self.ground);
+        let scheduler = match &self.plan {
+            Some(flights) => scheduler.with_plan(flights.clone()),
+            None => scheduler,
+        };
+        let scheduler = match self.fairness {
+            Some(starvation_s) => scheduler.with_fairness(starvation_s),
+            None => scheduler,
+        };
+        match self.surge {
+            Some(policy) => scheduler.with_surge(policy),
+            None => scheduler,
+        }
+    }
+
+    /// Constructs a scheduler of the provided kind using the default carrier parameters
+    fn build_scheduler(
+        destinations: HashMap<DestinationId, Destination>,
+        kind: SchedulerKind,
+    ) -> NaiveScheduler {
+        let scheduler = NaiveScheduler::new(
+            destinations,
+            DEFAULT_CARRIERS,
+            MAX_ORDERS_PER_CARRIER,
+            DEFAULT_CARRIER_SPEED_MPS,
+            CARRIER_RANGE_M,
+        );
+
+        match kind {
+            SchedulerKind::Naive => scheduler,
+            SchedulerKind::Forecasting(forecaster) => scheduler.with_forecaster(forecaster),
+        }
+    }
+
+    async fn run_inner(
+        options: RunOptions,
+        channels: Channels,
+        inputs: RunInputs,
+        mut scheduler: Box<dyn SimulatedScheduler>,
+    ) -> Result<Success, Error> {
+        let Channels {
+            mut updates,
+            mut alerts,
+            mut destination_changes,
+            checkpoints,
+            mut settled_orders,
+            live_orders,
+            commands,
+        } = channels;
+        // Peeked at while skipping quiet stretches, to pick up from the next second if anything
+        // arrives
+        let mut live_orders = live_orders.map(StreamExt::peekable);
+        let mut commands = commands.map(StreamExt::peekable);
+        let RunInputs {
+            mut destinations,
+            orders,
+            range_reductions,
+            matcher,
+            names,
+            alert_rules,
+            sectors,
+            resume,
+        } = inputs;
+        let RunOptions {
+            speed,
+            speed_schedule,
+            start_time,
+            separation_m,
+            checkpoint_interval_s,
+            compress_gaps_s,
+            auto_approve_s,
+            ..
+        } = options;
+        let resumed_from = resume.as_ref().map(|checkpoint| checkpoint.time);
+        // The first order which fails to stream ends the run
+        let stream_error = Mutex::new(None);
+        // Gaps are compressed before skipping what a checkpoint has already queued, since its
+        // times are on the compressed timeline
+        let compressor = Mutex::new(GapCompressor::new(compress_gaps_s));
+        let mut orders_iter = orders
+            .orders
+            .map_while(|order| {
+                order
+                    .map_err(|e| *stream_error.lock().expect("stream error") = Some(e))
+                    .ok()
+            })
+            .map(|order| compressor.lock().expect("compressor").compress(order))
+            .skip_while(|order| resumed_from.is_some_and(|time| order.time <= time))
+            .peekable();
+        // A resumed run picks up from the second after its checkpoint
+        let start_time = resumed_from.map(|time| time + 1).or(start_time);
+        let first_launch_time = start_time
+            .or_else(|| orders_iter.peek().map(|order| order.time))
+            .ok_or_else(|| {
+                stream_error
+                    .lock()
+                    .expect("stream error")
+                    .take()
+                    .unwrap_or_else(|| "No orders".to_string())
+            })?;
+        // Unless orders are arriving live, the run ends at the midnight following the last order
+        let mut end_time = time::end_of_day(
+            orders
+                .last_time
+                .map_or(first_launch_time, |last| last.max(first_launch_time)),
+        );
+
+        let mut alert_monitor = AlertMonitor::default().with_rules(alert_rules);
+        let mut approvals = auto_approve_s.map(Approvals::new);
+        let mut safety_monitor = SafetyMonitor::new(
+            scheduler.carrier_range_m(),
+            scheduler.carrier_speed_mps(),
+            range_reductions,
+        );
+        let mut stats =
+            StatsCollector::new(scheduler.carriers(), scheduler.max_orders_per_carrier());
+        let mut idempotency_keys = DedupeCache::new(DEDUPE_CAPACITY);
+        let mut deactivated = HashSet::new();
+        let mut started_at = first_launch_time;
+        if let Some(checkpoint) = resume {
+            let changes = DestinationChanges {
+                added: checkpoint
+                    .destinations
+                    .values()
+                    .filter(|destination| !destinations.contains_key(&destination.name))
+                    .cloned()
+                    .collect(),
+                deactivated: checkpoint.deactivated.iter().copied().collect(),
+            };
+            scheduler = scheduler.restore(checkpoint.scheduler);
+            scheduler.update_destinations(&changes);
+            destinations = checkpoint.destinations;
+            deactivated = checkpoint.deactivated;
+            stats = checkpoint.stats;
+            started_at = checkpoint.started_at;
+        }
+        names.update(&matcher, &destinations);
+        let mut surging = scheduler.is_surging();
+        let mut draining = false;
+        // Scripted orders turned away once draining, which are counted as drained
+        let mut turned_away = 0;
+
+        enum Event {
+            Idle(u64),
+            Orders(Vec<Order>, u64),
+            Launch {
+                orders: Vec<Order>,
+                current_time: u64,
+            },
+        }
+
+        impl Event {
+            fn current_time(&self) -> u64 {
+                match self {
+                    Self::Idle(t)
+                    | Self::Orders(_, t)
+                    | Self::Launch {
+                        current_time: t, ..
+                    } => *t,
+                }
+            }
+        }
+
+        // Map orders/launches into events happening every second (or the second a quiet stretch is
+        // skipped to), along with when the next scripted order is placed, if any are still to come
+        let resume_at = Cell::new(first_launch_time);
+        let events = std::iter::from_fn(|| {
+            let current_time = resume_at.get();
+            resume_at.set(current_time + 1);
+            // Every order placed by now is queued, as several may be placed in the same second
+            let orders =
+                std::iter::from_fn(|| orders_iter.next_if(|order| order.time <= current_time))
+                    .collect::<Vec<_>>();
+            let event = match current_time % 60 {
+                // Launch every minute, which may occur on the same second as incoming orders
+                0 => Event::Launch {
+                    orders,
+                    current_time,
+                },
+
+                // Queue orders at the appropriate time
+                _ if !orders.is_empty() => Event::Orders(orders, current_time),
+
+                // Otherwise just idling until the next second
+                _ => Event::Idle(current_time),
+            };
+            Some((event, orders_iter.peek().map(|order| order.time)))
+        });
+
+        // Slows the run down while each simulated second takes longer than it should at the speed
+        let mut throttle = Throttle::new(speed);
+        let mut update_limit = TokenBucket::new(options.max_updates_per_second);
+        let mut latest_update_sent = false;
+
+        for (event, next_scripted) in events {
+            let more_scripted = next_scripted.is_some();
+            if let Some(e) = stream_error.lock().expect("stream error").take() {
+                return Err(e);
+            }
+            let tick_started = tokio::time::Instant::now();
+            let current_time = event.current_time();
+            if live_orders.is_none() && current_time > end_time {
+                break;
+            }
+            if let Some(schedule) = &speed_schedule {
+                let scheduled = schedule.speed_at(current_time).unwrap_or(speed);
+                if scheduled != throttle.requested() {
+                    tracing::info!(
+                        "speed schedule changes the speed from {} to {} at {}",
+                        throttle.requested(),
+                        scheduled,
+                        time::format_short(current_time)
+                    );
+                    throttle.request(scheduled);
+                }
+            }
+            // Launches happen once a simulated minute, so the work of each minute is traced from
+            // its launch onwards
+            let minute = matches!(event, Event::Launch { .. })
+                .then(|| tracing::debug_span!("minute", time = current_time).entered());
+
+            while let Some(Some(Some(command))) =
+                commands.as_mut().map(|rx| rx.next().now_or_never())
+            {
+                match command {
+                    Command::SwapScheduler(kind) => {
+                        tracing::info!("swapping scheduler for {:?}", kind);
+                        // The carriers keep the capacity, speed & range they had, as the safety
+                        // monitor & stats were set up with them
+                        scheduler = Box::new(
+                            Self::build_scheduler(destinations.clone(), kind)
+                                .take_over(scheduler.hand_over()),
+                        );
+                    }
+                    Command::QueueOrders(_, _, reply) if draining => {
+                        let _ = reply.send(Err(DRAINING.to_string()));
+                    }
+                    Command::QueueOrders(mut orders, key, reply) => {
+                        if key
+                            .as_ref()
+                            .is_some_and(|key| idempotency_keys.contains(key))
+                        {
+                            tracing::info!("ignoring repeated submission {:?}", key);
+                            let _ = reply.send(Ok(Submitted::AlreadyKnown));
+                            continue;
+                        }
+
+                        let result = resolve_destinations(
+                            &matcher,
+                            &destinations,
+                            &deactivated,
+                            &mut orders,
+                        )
+                        .map(|()| {
+                            let mut ids = vec![];
+                            for order in orders {
+                                let order = Order {
+                                    time: current_time,
+                                    ..order
+                                };
+                                let order = stats.record_order(order, current_time);
+                                ids.push(order.id);
+                                scheduler.queue_order(order);
+                            }
+                            if let Some(key) = key {
+                                idempotency_keys.insert(key);
+                            }
+                            Submitted::Accepted(ids)
+                        });
+                        let _ = reply.send(result);
+                    }
+                    Command::PreviewSchedule(_, reply) if draining => {
+                        let _ = reply.send(Err(DRAINING.to_string()));
+                    }
+                    Command::PreviewSchedule(mut orders, reply) => {
+                        let result = resolve_destinations(
+                            &matcher,
+                            &destinations,
+                            &deactivated,
+                            &mut orders,
+                        )
+                        .map(|()| {
+                            let orders = orders
+                                .into_iter()
+                                .map(|order| Order {
+                                    time: current_time,
+                                    ..order
+                                })
+                                .collect();
+                            (
+                                current_time,
+                                scheduler.preview_flights(orders, current_time),
+                            )
+                        });
+                        let _ = reply.send(result);
+                    }
+                    Command::UpdateDestinations(changes, reply) => {
+                        let unknown = changes.deactivated.iter().find(|name| {
+                            !destinations.contains_key(name)
+                                && !changes.added.iter().any(|added| added.name == **name)
+                        });
+                        if let Some(name) = unknown {
+                            let _ = reply.send(Err(format!("unknown destination: {}", name)));
+                            continue;
+                        }
+
+                        tracing::info!(
+                            "adding {} & deactivating {} destinations",
+                            changes.added.len(),
+                            changes.deactivated.len()
+                        );
+                        for destination in &changes.added {
+                            deactivated.remove(&destination.name);
+                            destinations.insert(destination.name, destination.clone());
+                        }
+                        deactivated.extend(changes.deactivated.iter().copied());
+                        scheduler.update_destinations(&changes);
+                        names.update(&matcher, &destinations);
+                        let _ = destination_changes.start_send(changes);
+                        let _ = reply.send(Ok(()));
+                    }
+                    Command::GetOrder(id, reply) => {
+                        let _ = reply.send(stats.receipt(id, current_time));
+                    }
+                    Command::ExplainFlight(id, reply) => {
+                        let _ = reply.send(stats.explanation(id));
+                    }
+                    Command::SetFleetSize(carriers, reply) => {
+                        let carriers = scheduler.set_fleet_size(carriers);
+                        tracing::info!("resized the fleet to {} carriers", carriers);
+                        let _ = reply.send(carriers);
+                    }
+                    Command::Drain(reply) => {
+                        let flights = airborne(scheduler.as_ref(), current_time);
+                        if !draining {
+                            tracing::info!(
+                                "draining, with {} flights left to land & {} orders unscheduled",
+                                flights,
+                                scheduler.unfulfilled_orders().count()
+                            );
+                        }
+                        draining = true;
+                        let _ = reply.send(flights);
+                    }
+                    Command::ApproveFlight(id, reply) => {
+                        let result = match approvals.as_mut() {
+                            Some(approvals) => approvals.approve(id),
+                            None => Err("flights don't need approval in this run".to_string()),
+                        };
+                        if result.is_ok() {
+                            tracing::info!("flight {} approved", id);
+                        }
+                        let _ = reply.send(result);
+                    }
+                }
+            }
+
+            // Live orders are placed at the time they're received
+            let mut live_orders_closed = false;
+            if let Some(receiver) = live_orders.as_mut() {
+                loop {
+                    match receiver.next().now_or_never() {
+                        Some(Some(_)) if draining => {
+                            tracing::warn!("ignoring order, {}", DRAINING);
+                        }
+                        Some(Some(mut order)) => {
+                            let orders = std::slice::from_mut(&mut order);
+                            if let Err(e) =
+                                resolve_destinations(&matcher, &destinations, &deactivated, orders)
+                            {
+                                tracing::error!("ignoring order: {}", e);
+                                continue;
+                            }
+                            let order = Order {
+                                time: current_time,
+                                ..order
+                            };
+                            let order = stats.record_order(order, current_time);
+                            scheduler.queue_order(order);
+                        }
+                        Some(None) => {
+                            live_orders_closed = true;
+                            break;
+                        }
+                        None => break,
+                    }
+                }
+            }
+
+            // Once draining, scripted orders are turned away & no more flights are launched
+            let event = match event {
+                Event::Launch {
+                    orders,
+                    current_time,
+                }
+                | Event::Orders(orders, current_time)
+                    if draining =>
+                {
+                    if !orders.is_empty() {
+                        tracing::warn!("turning away {} orders, {}", orders.len(), DRAINING);
+                    }
+                    turned_away += orders.len();
+                    Event::Idle(current_time)
+                }
+                event => event,
+            };
+            match event {
+                Event::Launch {
+                    orders,
+                    current_time,
+                } => {
+                    for order in orders {
+                        let order = stats.record_order(order, current_time);
+                        scheduler.queue_order(order);
+                    }
+
+                    let mut launched = scheduler
+                        .launch_flights(current_time)
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    stats.record_explanations(scheduler.take_explanations());
+                    if (separation_m.is_some() || !sectors.is_empty()) && !launched.is_empty() {
+                        for alert in hold_launches(
+                            scheduler.as_mut(),
+                            &mut launched,
+                            separation_m,
+                            &sectors,
+                            current_time,
+                            &mut stats,
+                            &alert_monitor,
+                        ) {
+                            tracing::warn!("alert: {}", alert.message);
+                            let _ = alerts.start_send(alert);
+                        }
+                    }
+                    for flight in scheduler.take_commandeered() {
+                        tracing::warn!(
+                            "carrier {} unpacked before launching to fly emergencies",
+                            flight.carrier
+                        );
+                        stats.record_commandeered(&flight, scheduler.flight_distance(&flight));
+                    }
+                    if scheduler.is_surging() != surging {
+                        surging = !surging;
+                        let alert = match surging {
+                            true => alert_monitor.surge_started(current_time),
+                            false => alert_monitor.surge_ended(current_time),
+                        };
+                        tracing::warn!("alert: {}", alert.message);
+                        let _ = alerts.start_send(alert);
+                    }
+                    for flight in &launched {
+                        // Flights awaiting approval are only recorded once they're released
+                        if let Some(approvals) = approvals
+                            .as_mut()
+                            .filter(|_| Approvals::requires_approval(flight))
+                        {
+                            let deadline =
+                                approvals.request(scheduler.as_mut(), flight, current_time);
+                            let alert =
+                                alert_monitor.approval_pending(current_time, flight, deadline);
+                            tracing::warn!("alert: {}", alert.message);
+                            let _ = alerts.start_send(alert);
+                            continue;
+                        }
+                        record_launch(
+                            scheduler.as_ref(),
+                            flight,
+                            &destinations,
+                            &mut stats,
+                            &mut alert_monitor,
+                        );
+                    }
+                }
+
+                Event::Orders(orders, _) => {
+                    for order in orders {
+                        let order = stats.record_order(order, current_time);
+                        scheduler.queue_order(order);
+                    }
+                }
+
+                Event::Idle(_) => {}
+            }
+
+            // Launch the flights which have been approved, at the launch slot following approval
+            if let Some(approvals) = approvals.as_mut().filter(|_| current_time % 60 == 0) {
+                for flight in approvals.release(scheduler.as_mut(), current_time) {
+                    record_launch(
+                        scheduler.as_ref(),
+                        &flight,
+                        &destinations,
+                        &mut stats,
+                        &mut alert_monitor,
+                    );
+                }
+            }
+
+            // Recall carriers which no longer have the range to complete their flights. Range
+            // reductions model flying conditions, so ground vehicles aren't affected by them, nor
+            // flights which are yet to be approved.
+            let at_risk = safety_monitor.at_risk(
+                current_time,
+                scheduler
+                    .active_flights()
+                    .filter(|flight| {
+                        flight.mode == CarrierMode::Aerial
+                            && !approvals
+                                .as_ref()
+                                .is_some_and(|approvals| approvals.is_pending(flight.id))
+                    })
+                    .map(|flight| (flight, scheduler.flight_distance(flight))),
+            );
+            for carrier in at_risk {
+                let Some(planned) = scheduler
+                    .active_flights()
+                    .find(|flight| flight.carrier == carrier)
+                    .cloned()
+                else {
+                    continue;
+                };
+                let Some(diverted) = scheduler.return_to_base(carrier, current_time).cloned()
+                else {
+                    tracing::warn!("carrier {} is short of range on its way back", carrier);
+                    continue;
+                };
+
+                stats.record_return_to_base(
+                    &planned,
+                    scheduler.flight_distance(&planned),
+                    &diverted,
+                    scheduler.flight_distance(&diverted),
+                    scheduler.end_time(&diverted),
+                    scheduler.flight_energy_j(&diverted),
+                );
+                let alert = alert_monitor.return_to_base(
+                    current_time,
+                    &diverted,
+                    planned.orders.len() - diverted.orders.len(),
+                );
+                tracing::warn!("alert: {}", alert.message);
+                let _ = alerts.start_send(alert);
+            }
+
+            for order in scheduler.take_rejected_orders() {
+                stats.record_cancelled(&order, "destination deactivated");
+                tracing::warn!(
+                    "rejected order for deactivated destination {}",
+                    order.destination
+                );
+            }
+
+            for order in scheduler.take_missed_windows() {
+                stats.record_missed_window(&order);
+                let alert = alert_monitor.missed_window(current_time, &order);
+                tracing::warn!("alert: {}", alert.message);
+                let _ = alerts.start_send(alert);
+            }
+
+            for order in scheduler.take_spoiled() {
+                stats.record_spoiled(&order);
+                tracing::warn!(
+                    "gave up on perishable order for {}, which would spoil before delivery",
+                    order.destination
+                );
+            }
+
+            for order in scheduler.take_deferred() {
+                stats.record_deferred(&order);
+                tracing::debug!(
+                    "deferred order for {}, which would arrive while it's closed",
+                    order.destination
+                );
+            }
+
+            for settled in stats.take_settled(current_time) {
+                let _ = settled_orders.start_send(settled);
+            }
+
+            for alert in alert_monitor.evaluate(current_time, scheduler.unfulfilled_orders()) {
+                tracing::warn!("alert: {}", alert.message);
+                let _ = alerts.start_send(alert);
+            }
+            let active_flights = scheduler.active_flights().count();
+            let gauges = Gauges {
+                pending_emergencies: scheduler
+                    .unfulfilled_orders()
+                    .filter(|order| matches!(order.priority, Priority::Emergency))
+                    .count(),
+                pending_orders: scheduler.unfulfilled_orders().count(),
+                available_carriers: scheduler.carriers().count().saturating_sub(active_flights),
+                active_flights,
+            };
+            for alert in alert_monitor.evaluate_rules(current_time, &gauges) {
+                tracing::warn!("alert: {}", alert.message);
+                stats.record_rule_alert();
+                let _ = alerts.start_send(alert);
+            }
+
+            if checkpoint_interval_s.is_some_and(|interval| current_time % interval == 0) {
+                let _ = checkpoints.unbounded_send(Checkpoint {
+                    time: current_time,
+                    started_at,
+                    destinations: destinations.clone(),
+                    deactivated: deactivated.clone(),
+                    scheduler: scheduler.checkpoint(),
+                    stats: stats.clone(),
+                });
+            }
+
+            let sector_occupancy = sectors::occupancy(
+                &sectors,
+                scheduler
+                    .active_flights()
+                    .filter(|flight| {
+                        flight.mode == CarrierMode::Aerial && flight.launch_time <= current_time
+                    })
+                    .map(|flight| scheduler.position(flight, current_time)),
+            );
+            stats.record_sector_occupancy(&sector_occupancy);
+
+            latest_update_sent = update_limit.try_take();
+            if latest_update_sent {
+                tracing::info!("sending update to channel");
+                let _ = updates.start_send(status_update(
+                    scheduler.as_ref(),
+                    current_time,
+                    throttle.applied(),
+                    sector_occupancy,
+                ));
+            }
+
+            // Compressed orders finish sooner than the last of them was originally placed
+            if !more_scripted && compress_gaps_s.is_some() {
+                let compressor = compressor.lock().expect("compressor");
+                let last = compressor
+                    .last_time
+                    .map_or(first_launch_time, |last| last.max(first_launch_time));
+                end_time = end_time.min(time::end_of_day(last));
+            }
+
+            // Finish early once there is nothing left to deliver, or to land while draining
+            let delivered = live_orders_closed
+                && !more_scripted
+                && scheduler.unfulfilled_orders().next().is_none();
+            let landed = match draining {
+                true => {
+                    airborne(scheduler.as_ref(), current_time) == 0
+                        && approvals
+                            .as_ref()
+                            .is_none_or(|approvals| approvals.pending().next().is_none())
+                }
+                false => scheduler.active_flights().next().is_none(),
+            };
+            if (delivered || draining) && landed {
+                end_time = current_time;
+                break;
+            }
+
+            // Sleep for whatever is left of the simulated second, after the work done during it
+            drop(minute);
+            throttle.observe(tick_started.elapsed());
+            tokio::time::sleep_until(throttle.next_deadline()).await;
+
+            // Nothing can happen until the next scripted order, launch slot, delivery, checkpoint,
+            // alert or reduction in range, so the seconds until then are played through without
+            // doing their work, only sending subscribers updates along the way. Occupancy of the
+            // sectors changes as carriers fly, so isn't skipped while any are airborne.
+            let next_event = [
+                Some((current_time / 60 + 1) * 60),
+                next_scripted,
+                live_orders.is_none().then_some(end_time + 1),
+                checkpoint_interval_s.map(|interval| (current_time / interval + 1) * interval),
+                alert_monitor.next_deadline(current_time, scheduler.unfulfilled_orders()),
+                safety_monitor.next_reduction(),
+                speed_schedule
+                    .as_ref()
+                    .and_then(|schedule| schedule.next_boundary(current_time)),
+                scheduler
+                    .active_flights()
+                    .flat_map(|flight| {
+                        std::iter::once(flight.launch_time).chain(scheduler.delivery_times(flight))
+                    })
+                    .filter(|time| *time > current_time)
+                    .min(),
+            ]
+            .into_iter()
+            .flatten()
+            .min()
+            .map_or(current_time + 1, |time| time.max(current_time + 1));
+            let airborne = scheduler.active_flights().any(|flight| {
+                flight.mode == CarrierMode::Aerial && flight.launch_time <= current_time
+            });
+            if next_event == current_time + 1 || (airborne && !sectors.is_empty()) {
+                continue;
+            }
+
+            let sector_occupancy = sectors::occupancy(&sectors, std::iter::empty());
+            let mut last_wake = tokio::time::Instant::now();
+            let mut quiet_time = current_time + 1;
+            while quiet_time < next_event {
+                stats.record_sector_occupancy(&sector_occupancy);
+                latest_update_sent = update_limit.try_take();
+                if latest_update_sent {
+                    let _ = updates.start_send(status_update(
+                        scheduler.as_ref(),
+                        quiet_time,
+                        throttle.applied(),
+                        sector_occupancy.clone(),
+                    ));
+                }
+                throttle.observe(Duration::ZERO);
+                let deadline = throttle.next_deadline();
+                quiet_time += 1;
+
+                // Only wake for the seconds due an update, & often enough to notice commands &
+                // live orders, which are then handled from the following second
+                if quiet_time == next_event
+                    || deadline >= update_limit.ready_at()
+                    || deadline >= last_wake + QUIET_POLL_INTERVAL
+                {
+                    tokio::time::sleep_until(deadline).await;
+                    last_wake = deadline;
+                    let arrived = commands
+                        .as_mut()
+                        .is_some_and(|rx| matches!(peek(rx), Some(Some(_))))
+                        || live_orders.as_mut().is_some_and(|rx| match peek(rx) {
+                            Some(Some(_)) => true,
+                            Some(None) => !live_orders_closed,
+                            None => false,
+                        });
+                    if arrived {
+                        break;
+                    }
+                }
+            }
+            tracing::debug!("skipped quiet seconds {}..{}", current_time + 1, quiet_time);
+            resume_at.set(quiet_time);
+        }
+
+        // Always leave subscribers with the final state of the run
+        if !latest_update_sent {
+            let sector_occupancy = sectors::occupancy(
+                &sectors,
+                scheduler
+                    .active_flights()
+                    .filter(|flight| {
+                        flight.mode == CarrierMode::Aerial && flight.launch_time <= end_time
+                    })
+                    .map(|flight| scheduler.position(flight, end_time)),
+            );
+            let _ = updates.start_send(status_update(
+                scheduler.as_ref(),
+                end_time,
+                throttle.applied(),
+                sector_occupancy,
+            ));
+        }
+
+        // Orders split across flights are queued as each of their unscheduled parts
+        let unfulfilled_orders = scheduler
+            .unfulfilled_orders()
+            .map(|order| order.id)
+            .collect::<HashSet<_>>()
+            .len();
+        let mut report = match draining {
+            true => RunReport {
+                drained_orders: unfulfilled_orders + turned_away,
+                ..stats.finish(started_at, end_time, 0)
+            },
+            false => stats.finish(started_at, end_time, unfulfilled_orders),
+        };
+        report.compressed_s = compressor.lock().expect("compressor").compressed_s;
+        Ok(report)
+    }
+}
+
+/// Returns the state of the run at a time, for subscribers
+fn status_update(
+    scheduler: &dyn SimulatedScheduler,
+    time: u64,
+    speed: Speed,
+    sectors: Vec<SectorOccupancy>,
+) -> StatusUpdate {
+    StatusUpdate {
+        time,
+        flights: scheduler.active_flights().cloned().collect(),
+        speed,
+        backlog: Some(Backlog::from_orders(scheduler.unfulfilled_orders())),
+        sectors,
+    }
+}
+
+/// Returns the next message waiting on a channel without taking it, `Some(None)` once the channel
+/// has closed, or `None` while there's nothing waiting
+fn peek<T>(receiver: &mut Peekable<mpsc::UnboundedReceiver<T>>) -> Option<Option<&T>> {
+    Pin::new(receiver).peek().now_or_never()
+}
+
+/// Holds newly launched flights on the ground for as long as it takes to keep their carriers out
+/// of the grid cells occupied by the others, if they're kept separated, & out of sectors which are
+/// full, returning alerts for the holds
+#[tracing::instrument(level = "debug", skip_all, fields(launched = launched.len()))]
+fn hold_launches(
+    scheduler: &mut dyn SimulatedScheduler,
+    launched: &mut [Flight],
+    separation_m: Option<NonZeroU64>,
+    sectors: &[Sector],
+    current_time: u64,
+    stats: &mut StatsCollector,
+    alert_monitor: &AlertMonitor,
+) -> Vec<Alert> {
+    let path = |scheduler: &dyn SimulatedScheduler, flight: &Flight, from: u64| {
+        (from..scheduler.end_time(flight))
+            .map(|time| scheduler.position(flight, time))
+            .collect::<Vec<_>>()
+    };
+
+    // Ground vehicles don't take up airspace
+    let origin = scheduler.origin();
+    let mut airspace = separation_m.map(|separation_m| {
+        Airspace::new(separation_m, (origin.east_m as f32, origin.north_m as f32))
+    });
+    let mut sector_map = SectorMap::new(sectors);
+    for flight in scheduler.active_flights().filter(|flight| {
+        flight.mode == CarrierMode::Aerial
+            && !launched.iter().any(|new| new.carrier == flight.carrier)
+    }) {
+        // Flights held on the ground only take up airspace once they launch
+        let from = flight.launch_time.max(current_time);
+        let flown = path(scheduler, flight, from);
+        if let Some(airspace) = airspace.as_mut() {
+            airspace.occupy(from, &flown);
+        }
+        sector_map.occupy(from, &flown);
+    }
+
+    let mut alerts = vec![];
+    for flight in launched
+        .iter_mut()
+        .filter(|flight| flight.mode == CarrierMode::Aerial)
+    {
+        let planned = path(scheduler, flight, flight.launch_time);
+        // Holding for a full sector may bring the carrier into conflict with another, & holding
+        // to keep it separated may bring it into a full sector, so hold until neither does
+        let (mut hold_s, mut separated, mut full_sector) = (0, airspace.is_some(), None);
+        let mut separation_held = false;
+        loop {
+            if let Some((sector_hold_s, sector)) =
+                sector_map.hold_s(flight.launch_time + hold_s, &planned)
+            {
+                hold_s += sector_hold_s;
+                full_sector.get_or_insert(sector);
+            }
+            if !separated {
+                break;
+            }
+            let separation_hold_s = airspace
+                .as_ref()
+                .and_then(|airspace| airspace.hold_s(flight.launch_time + hold_s, &planned))
+                .filter(|separation_hold_s| hold_s + separation_hold_s <= Airspace::MAX_HOLD_S);
+            match separation_hold_s {
+                Some(0) => break,
+                Some(separation_hold_s) => {
+                    hold_s += separation_hold_s;
+                    separation_held = true;
+                }
+                None => {
+                    tracing::warn!(
+                        "carrier {} can't be kept separated, launching anyway",
+                        flight.carrier
+                    );
+                    separated = false;
+                }
+            }
+        }
+
+        if hold_s > 0 {
+            if let Some(held) = scheduler.hold_launch(flight.carrier, flight.launch_time + hold_s) {
+                *flight = held.clone();
+            }
+            if separation_held {
+                stats.record_separation_hold();
+            }
+            if full_sector.is_some() {
+                stats.record_sector_hold();
+            }
+            alerts.push(alert_monitor.launch_held(current_time, flight, hold_s, full_sector));
+        }
+        if let Some(airspace) = airspace.as_mut() {
+            airspace.occupy(flight.launch_time, &planned);
+        }
+        sector_map.occupy(flight.launch_time, &planned);
+    }
+    alerts
+}
+
+/// Records a flight as it launches, along with the proof of each of its deliveries
+fn record_launch(
+    scheduler: &dyn SimulatedScheduler,
+    flight: &Flight,
+    destinations: &HashMap<DestinationId, Destination>,
+    stats: &mut StatsCollector,
+    alert_monitor: &mut AlertMonitor,
+) {
+    alert_monitor.observe_launch(flight, scheduler.delivery_times(flight));
+    stats.record_flight(
+        flight,
+        scheduler.flight_distance(flight),
+        &scheduler.delivery_times(flight),
+        &marginal_distances(scheduler, flight),
+        scheduler.end_time(flight),
+        scheduler.flight_energy_j(flight),
+    );
+    let proofs = flight
+        .orders
+        .iter()
+        .zip(scheduler.delivery_times(flight))
+        .filter_map(|(order, delivery_time)| {
+            let destination = destinations.get(&order.destination)?;
+            let proof = crate::proof::simulate(order, flight.carrier, destination, delivery_time);
+            Some((order.id, proof))
+        });
+    stats.record_proofs(proofs);
+}
+
+/// Returns the number of flights yet to land, including those still held on the ground. Flights
+/// which have landed are only retired by the scheduler's next launch, which doesn't come while a
+/// run is draining.
+fn airborne(scheduler: &dyn SimulatedScheduler, current_time: u64) -> usize {
+    scheduler
+        .active_flights()
+        .filter(|flight| {
+            flight.launch_time > current_time || scheduler.end_time(flight) > current_time
+        })
+        .count()
+}
+
+/// Returns the distance each of a flight's orders adds to it, i.e. how much shorter the flight
+/// would be without it. Orders sharing a stop with others add nothing for the stop itself.
+fn marginal_distances(scheduler: &dyn SimulatedScheduler, flight: &Flight) -> Vec<f32> {
+    let distance_m = scheduler.flight_distance(flight);
+    (0..flight.orders.len())
+        .map(|i| {
+            let mut without = flight.clone();
+            without.orders.remove(i);
+            (distance_m - scheduler.flight_distance(&without)).max(0.0)
+        })
+        .collect()
+}
+
+/// Resolves the destination of each order, ensuring that all of them are known & active
+fn resolve_destinations(
+    matcher: &DestinationMatcher,
+    destinations: &HashMap<DestinationId, Destination>,
+    deactivated: &HashSet<DestinationId>,
+    orders: &mut [Order],
+) -> Result<(), String> {
+    matcher.resolve_orders(orders, destinations)?;
+    for order in orders {
+        if deactivated.contains(&order.destination) {
+            return Err(format!("deactivated destination: {}", order.destination));
+        }
+    }
+    Ok(())
+}
+
+impl CsvRunner {
+    /// Run with a scheduler chosen at runtime
+    pub fn run_dyn(&self, scheduler: Box<dyn SimulatedScheduler>) -> Response {
+        let mut orders = self.orders.clone();
+        let orders_file = self.orders_file.clone();
+        let matcher = self.matcher.clone();
+        let names = self.names.clone();
+        let range_reductions = self.range_reductions.clone();
+        let alert_rules = self.alert_rules.clone();
+        let sectors = self.sectors.clone();
+        let options = self.options.clone();
+        let destinations = self.destinations.clone();
+        let channels = Channels {
+            updates: self.status_updates_sender.clone(),
+            alerts: self.alerts_sender.clone(),
+            destination_changes: self.destination_changes_sender.clone(),
+            checkpoints: self.checkpoints_sender.clone(),
+            settled_orders: self.settled_orders_sender.clone(),
+            live_orders: self.live_orders.lock().expect("live orders").take(),
+            commands: self.commands_receiver.lock().expect("commands").take(),
+        };
+        let resume = self.resume.lock().expect("resume").take();
+        Box::pin(async move {
+            let orders = match orders_file {
+                // Orders in the file are already sorted, & resolved as the run reaches them
+                Some(file) => {
+                    let (matcher, destinations) = (matcher.clone(), destinations.clone());
+                    let orders = file.orders()?.map(move |order| {
+                        let mut order = order?;
+                        if let Some(matcher) = &matcher {
+                            order.destination =
+                                matcher.resolve(order.destination.as_str(), &destinations)?;
+                        }
+                        Ok(order)
+                    });
+                    ScriptedOrders {
+                        orders: Box::new(orders),
+                        last_time: (file.count > 0).then_some(file.last_time),
+                    }
+                }
+                None => {
+                    if let Some(matcher) = &matcher {
+                        matcher.resolve_orders(&mut orders, &destinations)?;
+                    }
+                    orders.sort_by_key(|order| order.time);
+                    ScriptedOrders {
+                        last_time: orders.last().map(|order| order.time),
+                        orders: Box::new(orders.into_iter().map(Ok)),
+                    }
+                }
+            };
+            let inputs = RunInputs {
+                destinations,
+                orders,
+                range_reductions,
+                matcher: matcher.unwrap_or_default(),
+                names,
+                alert_rules,
+                sectors,
+                resume,
+            };
+            Self::run_inner(options, channels, inputs, scheduler).await
+        })
+    }
+}
+
+impl<S> Runner<S> for CsvRunner
+where
+    S: schema::Scheduler + SimulatedScheduler + 'static,
+{
+    type Response = Response;
+    /// Deliveries & carrier utilization over the course of the run
+    type Success = RunReport;
+    /// Description of what went wrong
+    type Error = String;
+
+    fn run(&self, scheduler: S) -> Self::Response {
+        self.run_dyn(Box::new(scheduler))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::num::{NonZeroU16, NonZeroU64};
+
+    use futures::StreamExt;
+    use schema::{testkit, AlertKind, OrderStatus, PackingOutcome, Priority};
+
+    use super::*;
+    use crate::OrderOutcome;
+
+    const DEST_PATH: &str = "../test_data/destinations.csv";
+    const ORDER_PATH: &str = "../test_data/orders.csv";
+
+    #[tokio::test(start_paused = true)]
+    async fn test_defaults() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let runner = CsvRunner::from_csv_paths(DEST_PATH, ORDER_PATH).await?;
+        let report = runner.run_with_defaults().await?;
+
+        // Every order is accounted for, including those placed on the same second as another
+        assert_eq!(report.receipts.len(), 300);
+        assert_eq!(report.orders_delivered + report.unfulfilled_orders, 300);
+        assert_eq!(report.carriers.len(), 10);
+        assert!(report.carriers.iter().any(|carrier| carrier.flights > 0));
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_binary_orders() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let path = std::env::temp_dir().join(format!("runner-orders-{}.bin", std::process::id()));
+        let path = path.to_str().expect("path");
+        let orders = Order::from_csv(ORDER_PATH).await?;
+        binary::write_orders(std::fs::File::create(path)?, &orders)?;
+
+        // Streaming the orders from a binary file runs just as loading them from the CSV does
+        let speed = Speed::fast_forward(200).expect("speed");
+        let mut streamed = CsvRunner::from_csv_paths(DEST_PATH, path)
+            .await?
+            .with_speed(speed)
+            .run_with_defaults()
+            .await?;
+        let mut loaded = CsvRunner::from_csv_paths(DEST_PATH, ORDER_PATH)
+            .await?
+            .with_speed(speed)
+            .run_with_defaults()
+            .await?;
+        std::fs::remove_file(path)?;
+        for receipt in streamed
+            .receipts
+            .values_mut()
+            .chain(loaded.receipts.values_mut())
+        {
+            receipt.flight = None;
+        }
+        for energy in streamed
+            .flight_energy
+            .iter_mut()
+            .chain(loaded.flight_energy.iter_mut())
+        {
+            energy.flight = Default::default();
+        }
+        assert_eq!(streamed.explanations.len(), loaded.explanations.len());
+        streamed.explanations.clear();
+        loaded.explanations.clear();
+        assert_eq!(streamed, loaded);
+        assert_eq!(streamed.receipts.len(), 300);
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_simultaneous_orders() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let destinations = testkit::destinations(&[("Near", 3_000, 0), ("Far", 0, 6_000)]);
+        // Several orders are placed in the same second, both between launches & on a launch
+        let orders = vec![
+            testkit::order(25_230, "Near", Priority::Resupply),
+            testkit::order(25_230, "Far", Priority::Emergency),
+            testkit::order(25_230, "Near", Priority::Emergency),
+            testkit::order(25_260, "Far", Priority::Resupply),
+            testkit::order(25_260, "Near", Priority::Resupply),
+            testkit::order(25_300, "Far", Priority::Emergency),
+        ];
+        let report = CsvRunner::new(destinations.into_values().collect(), orders)
+            .with_speed(Speed::fast_forward(200).expect("speed"))
+            .run_with_defaults()
+            .await?;
+
+        assert_eq!(report.receipts.len(), 6);
+        assert_eq!(report.orders_delivered, 6);
+        let mut queued_at = report
+            .receipts
+            .values()
+            .map(|receipt| receipt.queued_at)
+            .collect::<Vec<_>>();
+        queued_at.sort();
+        assert_eq!(queued_at, [25_230, 25_230, 25_230, 25_260, 25_260, 25_300]);
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_warm_start() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let speed = Speed::fast_forward(200).expect("speed");
+        let runner = CsvRunner::from_csv_paths(DEST_PATH, ORDER_PATH).await?;
+        let planned = runner.with_speed(speed).run_with_defaults().await?;
+        let plan = planned.flight_plan();
+        assert!(!plan.is_empty());
+
+        // Warm-starting from the flights of a previous run launches them just as they were
+        let report = CsvRunner::from_csv_paths(DEST_PATH, ORDER_PATH)
+            .await?
+            .with_speed(speed)
+            .with_plan(plan.clone())
+            .run_with_defaults()
+            .await?;
+        let launches = |plan: &[Flight]| {
+            plan.iter()
+                .map(|flight| {
+                    let orders = flight
+                        .orders
+                        .iter()
+                        .map(|order| (order.time, order.destination))
+                        .collect::<Vec<_>>();
+                    (flight.launch_time, flight.carrier, orders)
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(launches(&report.flight_plan()), launches(&plan));
+        assert_eq!(report.unfulfilled_orders, planned.unfulfilled_orders);
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_crossing_midnight() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let destinations = Destination::from_csv(DEST_PATH).await?;
+        let destination = destinations[0].name.to_string();
+        let start = time::parse_timestamp("2023-10-14T23:50:00Z")?;
+        let orders = ["2023-10-14T23:59:30Z", "2023-10-15T00:00:30Z"]
+            .into_iter()
+            .map(|timestamp| {
+                Ok(testkit::order(
+                    time::parse_timestamp(timestamp)?,
+                    &destination,
+                    Priority::Emergency,
+                ))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let report = CsvRunner::new(destinations, orders)
+            .with_speed(Speed::fast_forward(200).expect("speed"))
+            .with_start_time(start)
+            .run_with_defaults()
+            .await?;
+
+        // Both orders are delivered, and the run continues until the end of the last order's day
+        assert_eq!(report.orders_delivered, 2);
+        assert_eq!(report.unfulfilled_orders, 0);
+        assert_eq!(report.start_time, start);
+        assert_eq!(
+            time::format_timestamp(report.end_time),
+            "2023-10-16T00:00:00Z"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_gap_compression() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let destinations = Destination::from_csv(DEST_PATH).await?;
+        let destination = destinations[0].name.to_string();
+        let mut orders = [
+            "2023-10-14T10:00:00Z",
+            "2023-10-14T10:05:00Z",
+            "2023-10-21T09:00:00Z",
+        ]
+        .into_iter()
+        .map(|timestamp| {
+            Ok(testkit::order(
+                time::parse_timestamp(timestamp)?,
+                &destination,
+                Priority::Emergency,
+            ))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+        orders[2].latest_delivery = Some(orders[2].time + 3_600);
+
+        let report = CsvRunner::new(destinations, orders)
+            .with_speed(Speed::fast_forward(200).expect("speed"))
+            .with_gap_compression(NonZeroU32::new(60).expect("gap"))
+            .run_with_defaults()
+            .await?;
+
+        // The week-long gap is cut to an hour, so the last order arrives an hour after the second
+        // & the run ends on the first day
+        let last = &report.receipts[&OrderId(3)];
+        assert_eq!(
+            time::format_timestamp(last.queued_at),
+            "2023-10-14T11:05:00Z"
+        );
+        assert_eq!(last.order.latest_delivery, Some(last.queued_at + 3_600));
+        assert!(last
+            .delivery_time
+            .is_some_and(|delivered| delivered - last.queued_at < 3_600));
+        assert_eq!(report.orders_delivered, 3);
+        assert_eq!(report.compressed_s, 7 * 86_400 - 65 * 60 - 3_600);
+        assert_eq!(
+            time::format_timestamp(report.end_time),
+            "2023-10-15T00:00:00Z"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_resume_from_checkpoint() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut runner = CsvRunner::from_csv_paths(DEST_PATH, ORDER_PATH)
+            .await?
+            .with_speed(Speed::fast_forward(200).expect("speed"))
+            .with_checkpoints(NonZeroU32::new(30).expect("interval"));
+        let checkpoints = runner.stream_checkpoints().expect("checkpoints");
+        let mut report = runner.run_with_defaults().await?;
+        drop(runner);
+        let checkpoints = checkpoints.collect::<Vec<_>>().await;
+        assert!(checkpoints.len() > 2);
+        assert!(checkpoints
+            .windows(2)
+            .all(|pair| pair[1].time == pair[0].time + 1_800));
+
+        // Resuming from partway through the run finishes it just as the original did
+        let checkpoint = checkpoints[1].clone();
+        let checkpoint_time = checkpoint.time;
+        let mut resumed = CsvRunner::from_csv_paths(DEST_PATH, ORDER_PATH)
+            .await?
+            .with_speed(Speed::fast_forward(200).expect("speed"))
+            .with_resume(checkpoint)
+            .run_with_defaults()
+            .await?;
+        // Flights launched since the checkpoint are new to the resumed run, so have new ids
+        for receipt in report
+            .receipts
+            .values_mut()
+            .chain(resumed.receipts.values_mut())
+        {
+            if receipt.launch_time > Some(checkpoint_time) {
+                receipt.flight = None;
+            }
+        }
+        for energy in report
+            .flight_energy
+            .iter_mut()
+            .chain(resumed.flight_energy.iter_mut())
+            .filter(|energy| energy.launch_time > checkpoint_time)
+        {
+            energy.flight = Default::default();
+        }
+        assert_eq!(resumed.explanations.len(), report.explanations.len());
+        for explanations in [&mut report.explanations, &mut resumed.explanations] {
+            explanations.retain(|_, explanation| explanation.packed_at <= checkpoint_time);
+        }
+        assert_eq!(resumed, report);
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_idempotent_submission() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let destinations = Destination::from_csv(DEST_PATH).await?;
+        let orders = vec![testkit::order(25_200, "Toronto", Priority::Emergency)];
+        let runner = CsvRunner::new(destinations, orders)
+            .with_speed(Speed::fast_forward(200).expect("speed"));
+
+        // A submission is retried with the same key, alongside one without a key
+        let submit = |key: Option<&str>| {
+            let (tx, rx) = oneshot::channel();
+            let order = testkit::order(0, "Detroit", Priority::Resupply);
+            runner
+                .commands()
+                .unbounded_send(Command::QueueOrders(
+                    vec![order],
+                    key.map(str::to_string),
+                    tx,
+                ))
+                .expect("command");
+            rx
+        };
+        let (first, retry, unkeyed) = (submit(Some("a")), submit(Some("a")), submit(None));
+
+        let report = runner.run_with_defaults().await?;
+        assert_eq!(first.await?, Ok(Submitted::Accepted(vec![OrderId(1)])));
+        assert_eq!(retry.await?, Ok(Submitted::AlreadyKnown));
+        assert_eq!(unkeyed.await?, Ok(Submitted::Accepted(vec![OrderId(2)])));
+        assert_eq!(report.orders_delivered, 3);
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_destination_matching() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let destinations = Destination::from_csv(DEST_PATH).await?;
+        let orders = vec![testkit::order(25_200, "YYZ", Priority::Emergency)];
+        let matcher = DestinationMatcher::default()
+            .with_aliases([("YYZ".to_string(), DestinationId::intern("Toronto"))])
+            .with_matching(schema::Matching::CaseInsensitive);
+        let runner = CsvRunner::new(destinations, orders)
+            .with_speed(Speed::fast_forward(200).expect("speed"))
+            .with_destination_matcher(matcher);
+
+        // Submissions are resolved as loosely as scripted orders, or rejected as a whole
+        let submit = |name: &str| {
+            let (tx, rx) = oneshot::channel();
+            let order = testkit::order(0, name, Priority::Resupply);
+            runner
+                .commands()
+                .unbounded_send(Command::QueueOrders(vec![order], None, tx))
+                .expect("command");
+            rx
+        };
+        let (detroit, unknown) = (submit("detroit"), submit("Detroyt"));
+
+        let report = runner.run_with_defaults().await?;
+        assert_eq!(detroit.await?, Ok(Submitted::Accepted(vec![OrderId(1)])));
+        assert_eq!(
+            unknown.await?,
+            Err("unknown destination: Detroyt".to_string())
+        );
+        assert_eq!(report.orders_delivered, 2);
+        assert!(report
+            .receipts
+            .values()
+            .any(|receipt| receipt.order.destination.as_str() == "Toronto"));
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_get_order() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let destinations = Destination::from_csv(DEST_PATH).await?;
+        let orders = vec![testkit::order(25_200, "Toronto", Priority::Emergency)];
+        let runner = CsvRunner::new(destinations, orders)
+            .with_speed(Speed::fast_forward(200).expect("speed"));
+
+        // A submitted order can be looked up as soon as it's queued
+        let (tx, submitted) = oneshot::channel();
+        let order = testkit::order(0, "Detroit", Priority::Resupply);
+        runner
+            .commands()
+            .unbounded_send(Command::QueueOrders(vec![order], None, tx))
+            .expect("command");
+        let get = |id| {
+            let (tx, rx) = oneshot::channel();
+            runner
+                .commands()
+                .unbounded_send(Command::GetOrder(id, tx))
+                .expect("command");
+            rx
+        };
+        let (queued, unknown) = (get(OrderId(1)), get(OrderId(99)));
+        let (tx, unexplained) = oneshot::channel();
+        runner
+            .commands()
+            .unbounded_send(Command::ExplainFlight(FlightId::new(), tx))
+            .expect("command");
+
+        let report = runner.run_with_defaults().await?;
+        assert_eq!(submitted.await?, Ok(Submitted::Accepted(vec![OrderId(1)])));
+        let queued = queued.await?.expect("receipt");
+        assert_eq!(queued.status, OrderStatus::Queued);
+        assert_eq!(queued.queued_at, 25_200);
+        assert_eq!(unknown.await?, None);
+
+        // By the end of the run, the scripted order has been delivered by a scheduled flight
+        let delivered = report.receipts[&OrderId(2)].clone().as_of(report.end_time);
+        assert_eq!(delivered.status, OrderStatus::Delivered);
+        assert!(delivered.carrier.is_some());
+        assert!(delivered.delivery_time > delivered.launch_time);
+
+        // ...with proof of delivery, which isn't shown before then
+        assert!(delivered.proof.is_some());
+        let launched = report.receipts[&OrderId(2)]
+            .clone()
+            .as_of(delivered.launch_time.expect("launch time"));
+        assert_eq!(launched.proof, None);
+
+        // ...the decisions behind which are kept alongside the receipts
+        assert_eq!(unexplained.await?, None);
+        let explanation = &report.explanations[&delivered.flight.expect("flight")];
+        assert_eq!(explanation.carrier, delivered.carrier.expect("carrier"));
+        assert!(explanation
+            .considered
+            .iter()
+            .any(|considered| considered.order == OrderId(2)
+                && considered.outcome == PackingOutcome::Packed));
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cost_attribution() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let destinations = Destination::from_csv(DEST_PATH).await?;
+        let orders = vec![testkit::order(25_200, "Toronto", Priority::Resupply)];
+        let report = CsvRunner::new(destinations, orders)
+            .with_speed(Speed::fast_forward(200).expect("speed"))
+            .run_with_defaults()
+            .await?;
+
+        // An order flown by itself is charged its flight's whole distance, & the wait to launch
+        let receipt = report.receipts[&OrderId(1)].clone().as_of(report.end_time);
+        let flight_m = report.carriers.iter().map(|c| c.distance_m).sum::<f32>();
+        let launch_time = receipt.launch_time.expect("launch time");
+        assert_eq!(receipt.marginal_distance_m, Some(flight_m.round() as u32));
+        assert_eq!(receipt.wait_s, Some(launch_time - receipt.queued_at));
+
+        // ...each of which is exported alongside the rest of its receipt
+        let csv = report.orders_csv();
+        let mut lines = csv.lines();
+        assert!(lines
+            .next()
+            .expect("header")
+            .starts_with("order,destination,priority"));
+        let fields = lines.next().expect("order").split(',').collect::<Vec<_>>();
+        assert_eq!(fields[..4], ["1", "Toronto", "Resupply", "delivered"]);
+        assert_eq!(fields[6], (launch_time - receipt.queued_at).to_string());
+        assert_eq!(fields[10], (flight_m.round() as u32).to_string());
+        assert_eq!(lines.next(), None);
+
+        // Orders sharing a stop with others add nothing for it, unlike those with stops of their own
+        let scheduler = NaiveScheduler::new(
+            testkit::destinations(&[("A", 3_000, 0), ("B", 3_000, 4_000)]),
+            3,
+            3,
+            30,
+            100_000,
+        );
+        let flight = Flight {
+            orders: vec![
+                testkit::order(0, "A", Priority::Resupply),
+                testkit::order(0, "A", Priority::Resupply),
+                testkit::order(0, "B", Priority::Resupply),
+            ],
+            ..Default::default()
+        };
+        let marginal = marginal_distances(&scheduler, &flight);
+        assert_eq!(marginal[..2], [0.0, 0.0]);
+        // Out via A & B then straight back, rather than out & back to A
+        assert!((marginal[2] - (3_000.0 + 4_000.0 + 5_000.0 - 6_000.0)).abs() < 1.0);
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_split_orders() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let destinations = Destination::from_csv(DEST_PATH).await?;
+        let orders = vec![Order {
+            quantity: 7,
+            ..testkit::order(25_200, "Toronto", Priority::Emergency)
+        }];
+        let report = CsvRunner::new(destinations, orders)
+            .with_speed(Speed::fast_forward(200).expect("speed"))
+            .run_with_defaults()
+            .await?;
+
+        // The order is too big for any one carrier, so it's split across flights & counted once
+        assert_eq!(report.orders_delivered, 1);
+        assert_eq!(report.split_orders, 1);
+        let receipt = report.receipts[&OrderId(1)].clone().as_of(report.end_time);
+        assert_eq!(receipt.status, OrderStatus::Delivered);
+        assert_eq!(receipt.shipments.len(), 3);
+        assert!(receipt
+            .shipments
+            .iter()
+            .all(|shipment| shipment.packages as usize <= MAX_ORDERS_PER_CARRIER));
+        assert_eq!(
+            receipt.delivery_time,
+            receipt
+                .shipments
+                .iter()
+                .map(|shipment| shipment.delivery_time)
+                .max()
+        );
+        assert_eq!(receipt.packages_delivered(receipt.queued_at), 0);
+        assert_eq!(receipt.packages_delivered(report.end_time), 7);
+
+        // The distance its flights went for it is that of every shipment
+        assert_eq!(
+            receipt.marginal_distance_m,
+            Some(
+                receipt
+                    .shipments
+                    .iter()
+                    .map(|shipment| shipment.marginal_distance_m)
+                    .sum()
+            )
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_return_to_base() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let destinations = Destination::from_csv(DEST_PATH).await?;
+        let orders = vec![testkit::order(25_200, "Toronto", Priority::Emergency)];
+
+        // Ten minutes into the flight, a headwind halves the range left to the carrier
+        let report = CsvRunner::new(destinations, orders)
+            .with_speed(Speed::fast_forward(200).expect("speed"))
+            .with_range_reductions(vec![RangeReduction {
+                time: 25_800,
+                factor: 0.5,
+                carrier: None,
+            }])
+            .run_with_defaults()
+            .await?;
+
+        // The carrier turns back, & the order is delivered by another flight
+        assert_eq!(report.returns_to_base, 1);
+        assert_eq!(report.orders_delivered, 1);
+        assert_eq!(report.unfulfilled_orders, 0);
+        assert_eq!(
+            report
+                .carriers
+                .iter()
+                .map(|carrier| carrier.flights)
+                .sum::<usize>(),
+            2
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_operating_hours() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let hours = "09:00-20:00".parse()?;
+        let destinations = Destination::from_csv(DEST_PATH)
+            .await?
+            .into_iter()
+            .map(|destination| match destination.name.as_str() {
+                "Toronto" => Destination {
+                    hours: Some(hours),
+                    ..destination
+                },
+                _ => destination,
+            })
+            .collect();
+        let orders = vec![
+            testkit::order(25_200, "Toronto", Priority::Emergency),
+            testkit::order(25_260, "Detroit", Priority::Emergency),
+        ];
+        let report = CsvRunner::new(destinations, orders)
+            .with_speed(Speed::fast_forward(200).expect("speed"))
+            .run_with_defaults()
+            .await?;
+
+        // The clinic in Toronto is closed until 09:00, so its order waits to arrive once it opens
+        // while Detroit's goes straight away
+        assert_eq!(report.orders_delivered, 2);
+        assert_eq!(report.closed_deferrals, 1);
+        let toronto = report.receipts[&OrderId(1)].clone().as_of(report.end_time);
+        assert_eq!(toronto.status, OrderStatus::Delivered);
+        assert!(toronto.delivery_time.is_some_and(|time| time >= 32_400));
+        let detroit = report.receipts[&OrderId(2)].clone().as_of(report.end_time);
+        assert!(detroit.launch_time.is_some_and(|time| time < 26_000));
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_separation() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        // Two emergencies launched together at 07:00 along the same line out of the nest
+        let destinations = testkit::destinations(&[("Near", 3_000, 0), ("Far", 6_000, 0)]);
+        let orders = vec![
+            testkit::order(25_170, "Near", Priority::Emergency),
+            testkit::order(25_180, "Far", Priority::Emergency),
+        ];
+        let mut runner = CsvRunner::new(destinations.into_values().collect(), orders)
+            .with_speed(Speed::fast_forward(200).expect("speed"))
+            .with_separation(NonZeroU64::new(500).expect("separation"));
+        let alerts = runner.stream_alerts().expect("alert stream");
+        let run = runner.run_with_defaults();
+        drop(runner);
+
+        // One of the carriers is held until the other is clear, & both orders are delivered
+        let (report, alerts) = futures::join!(run, alerts.collect::<Vec<_>>());
+        let report = report?;
+        assert_eq!(report.separation_holds, 1);
+        assert_eq!(report.orders_delivered, 2);
+        assert_eq!(
+            alerts
+                .iter()
+                .filter(|alert| alert.kind == AlertKind::LaunchHeld)
+                .count(),
+            1
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_sectors() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        // Two emergencies launched together at 07:00 into a sector with room for one carrier
+        let destinations = testkit::destinations(&[("Near", 3_000, 0), ("Far", 6_000, 0)]);
+        let orders = vec![
+            testkit::order(25_170, "Near", Priority::Emergency),
+            testkit::order(25_180, "Far", Priority::Emergency),
+        ];
+        let sector = "North, -1000, 10000, 1000, 1000, 1".parse::<Sector>()?;
+        let mut runner = CsvRunner::new(destinations.into_values().collect(), orders)
+            .with_speed(Speed::fast_forward(200).expect("speed"))
+            .with_sectors(vec![sector]);
+        let updates = runner.stream_updates().expect("update stream");
+        let alerts = runner.stream_alerts().expect("alert stream");
+        let run = runner.run_with_defaults();
+        drop(runner);
+
+        // One of the carriers is held until the other has flown back out of the sector
+        let (report, updates, alerts) =
+            futures::join!(run, updates.collect::<Vec<_>>(), alerts.collect::<Vec<_>>());
+        let report = report?;
+        assert_eq!(report.sector_holds, 1);
+        assert_eq!(report.separation_holds, 0);
+        assert_eq!(report.orders_delivered, 2);
+        assert_eq!(report.sectors.len(), 1);
+        assert_eq!(report.sectors[0].peak_flights, 1);
+        assert!(report.sectors[0].full_s > 0);
+        assert!(alerts
+            .iter()
+            .any(|alert| alert.message.contains("until sector North has room")));
+
+        // Subscribers see the occupancy of the sector, which never exceeds its capacity
+        assert!(updates
+            .iter()
+            .all(|update| update.sectors.len() == 1 && update.sectors[0].flights <= 1));
+        assert!(updates.iter().any(|update| update.sectors[0].is_full()));
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_surge() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        // Three emergencies within a few seconds of each other, between resupplies
+        let destinations = testkit::destinations(&[("Near", 3_000, 0)]);
+        let orders = vec![
+            testkit::order(25_100, "Near", Priority::Resupply),
+            testkit::order(25_170, "Near", Priority::Emergency),
+            testkit::order(25_175, "Near", Priority::Emergency),
+            testkit::order(25_180, "Near", Priority::Emergency),
+            testkit::order(25_200, "Near", Priority::Resupply),
+        ];
+        let mut runner = CsvRunner::new(destinations.into_values().collect(), orders)
+            .with_speed(Speed::fast_forward(200).expect("speed"))
+            .with_surge("3/10".parse()?);
+        let alerts = runner.stream_alerts().expect("alert stream");
+        let run = runner.run_with_defaults();
+        drop(runner);
+
+        // The surge starts & ends, with the resupply placed during it delivered afterwards
+        let (report, alerts) = futures::join!(run, alerts.collect::<Vec<_>>());
+        let report = report?;
+        assert_eq!(report.orders_delivered, 5);
+        let surges = alerts
+            .iter()
+            .filter(|alert| matches!(alert.kind, AlertKind::SurgeStarted | AlertKind::SurgeEnded))
+            .collect::<Vec<_>>();
+        assert_eq!(surges.len(), 2);
+        assert_eq!(surges[0].kind, AlertKind::SurgeStarted);
+        assert_eq!(surges[1].kind, AlertKind::SurgeEnded);
+        assert!(surges[1].time - surges[0].time <= 10 * 60);
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_update_destinations() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let destinations = testkit::destinations(&[("Near", 3_000, 0)]);
+        let orders = vec![testkit::order(25_200, "Near", Priority::Emergency)];
+        let names = DestinationNames::default();
+        let mut runner = CsvRunner::new(destinations.into_values().collect(), orders)
+            .with_speed(Speed::fast_forward(200).expect("speed"))
+            .with_destination_names(names.clone());
+        let changes_stream = runner
+            .stream_destination_changes()
+            .expect("destination changes stream");
+        let settled_stream = runner
+            .stream_settled_orders()
+            .expect("settled orders stream");
+
+        let update = |changes: DestinationChanges| {
+            let (tx, rx) = oneshot::channel();
+            runner
+                .commands()
+                .unbounded_send(Command::UpdateDestinations(changes, tx))
+                .expect("command");
+            rx
+        };
+        let submit = |destination: &str| {
+            let (tx, rx) = oneshot::channel();
+            let order = testkit::order(0, destination, Priority::Resupply);
+            runner
+                .commands()
+                .unbounded_send(Command::QueueOrders(vec![order], None, tx))
+                .expect("command");
+            rx
+        };
+
+        // Replace the only destination with a new one before the first order is placed
+        let changes = DestinationChanges {
+            added: testkit::destinations(&[("New", 0, 3_000)])
+                .into_values()
+                .collect(),
+            deactivated: vec![DestinationId::from("Near")],
+        };
+        let applied = update(changes.clone());
+        let unknown = update(DestinationChanges {
+            deactivated: vec![DestinationId::from("Nowhere")],
+            ..Default::default()
+        });
+        let (new, near) = (submit("New"), submit("Near"));
+
+        let run = runner.run_with_defaults();
+        drop(runner);
+        let (report, published, settled) = futures::join!(
+            run,
+            changes_stream.collect::<Vec<_>>(),
+            settled_stream.collect::<Vec<_>>()
+        );
+        let report = report?;
+
+        assert_eq!(applied.await?, Ok(()));
+        assert!(unknown.await?.is_err());
+        assert_eq!(new.await?, Ok(Submitted::Accepted(vec![OrderId(1)])));
+        assert!(near.await?.is_err());
+        assert_eq!(published, vec![changes]);
+        // Names from outside resolve against the added destination too
+        assert_eq!(names.names(), ["Near", "New"]);
+        assert_eq!(names.resolve("New"), Ok(DestinationId::from("New")));
+
+        // Only the order for the new destination is delivered, the scripted order being rejected
+        assert_eq!(report.orders_delivered, 1);
+        assert_eq!(
+            report.receipts[&OrderId(2)].failure.as_deref(),
+            Some("destination deactivated")
+        );
+        let outcomes = settled
+            .iter()
+            .map(|settled| (settled.receipt.order.id, settled.outcome))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            outcomes,
+            vec![
+                (OrderId(2), OrderOutcome::Cancelled),
+                (OrderId(1), OrderOutcome::Delivered)
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_drain() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let destinations = testkit::destinations(&[("Near", 3_000, 0)]);
+        // The second order is placed while the only carrier is out delivering the first, & the
+        // third after the run has been drained
+        let orders = vec![
+            testkit::order(25_200, "Near", Priority::Emergency),
+            testkit::order(25_230, "Near", Priority::Resupply),
+            testkit::order(50_000, "Near", Priority::Resupply),
+        ];
+        let mut runner = CsvRunner::new(destinations.into_values().collect(), orders)
+            .with_speed(Speed::fast_forward(200).expect("speed"));
+        let updates = runner.stream_updates().expect("update stream");
+        let commands = runner.commands();
+        let (tx, resized) = oneshot::channel();
+        commands.unbounded_send(Command::SetFleetSize(1, tx))?;
+
+        let run = runner.run_with_defaults();
+        drop(runner);
+        let drain = async move {
+            let mut updates = Box::pin(updates);
+            while let Some(update) = updates.next().await {
+                if update.time >= 25_240 && !update.flights.is_empty() {
+                    break;
+                }
+            }
+            let (tx, drained) = oneshot::channel();
+            commands
+                .unbounded_send(Command::Drain(tx))
+                .expect("command");
+            let (tx, queued) = oneshot::channel();
+            let order = testkit::order(0, "Near", Priority::Emergency);
+            commands
+                .unbounded_send(Command::QueueOrders(vec![order], None, tx))
+                .expect("command");
+            updates.collect::<Vec<_>>().await;
+            (drained.await, queued.await)
+        };
+        let (report, (drained, queued)) = futures::join!(run, drain);
+        let report = report?;
+
+        assert_eq!(resized.await?, 1);
+        assert_eq!(drained?, 1);
+        assert!(queued?.is_err());
+
+        // The run finishes once the flight in the air lands, the order left queued being drained
+        assert_eq!(report.orders_delivered, 1);
+        assert_eq!(report.drained_orders, 1);
+        assert_eq!(report.unfulfilled_orders, 0);
+        assert!(report.end_time < 26_000);
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_drain_held_flights() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let destinations = testkit::destinations(&[("Near", 3_000, 0)]);
+        let orders = vec![
+            testkit::order(25_200, "Near", Priority::Emergency),
+            testkit::order(25_330, "Near", Priority::Resupply),
+            testkit::order(25_330, "Near", Priority::Resupply),
+        ];
+        let mut runner = CsvRunner::new(destinations.into_values().collect(), orders)
+            .with_speed(Speed::fast_forward(200).expect("speed"))
+            .with_approvals(NonZeroU64::new(300).expect("timeout"));
+        let alerts = runner.stream_alerts().expect("alert stream");
+        let commands = runner.commands();
+        let run = runner.run_with_defaults();
+        drop(runner);
+
+        // The run is drained while its only flight waits on the ground to be approved
+        let drain = async move {
+            let mut alerts = Box::pin(alerts);
+            let mut drained = None;
+            while let Some(alert) = alerts.next().await {
+                if alert.kind == AlertKind::ApprovalPending && drained.is_none() {
+                    let (tx, rx) = oneshot::channel();
+                    commands
+                        .unbounded_send(Command::Drain(tx))
+                        .expect("command");
+                    drained = Some(rx);
+                }
+            }
+            drained
+        };
+        let (report, drained) = futures::join!(run, drain);
+        let report = report?;
+
+        // It's approved automatically & lands before the run finishes, the orders placed in the
+        // meantime being turned away
+        assert_eq!(drained.expect("drained").await?, 1);
+        assert_eq!(report.orders_delivered, 1);
+        assert_eq!(report.receipts[&OrderId(1)].launch_time, Some(25_500));
+        assert!(report.end_time > 25_500);
+        assert_eq!(report.drained_orders, 2);
+        assert_eq!(report.unfulfilled_orders, 0);
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_approvals() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let destinations = testkit::destinations(&[("Near", 3_000, 0)]);
+        let orders = vec![
+            testkit::order(25_200, "Near", Priority::Emergency),
+            testkit::order(27_960, "Near", Priority::Resupply),
+            testkit::order(30_000, "Near", Priority::Emergency),
+        ];
+        let mut runner = CsvRunner::new(destinations.into_values().collect(), orders)
+            .with_speed(Speed::fast_forward(200).expect("speed"))
+            .with_approvals(NonZeroU64::new(300).expect("timeout"));
+        let alerts = runner.stream_alerts().expect("alert stream");
+        let commands = runner.commands();
+        let run = runner.run_with_defaults();
+        drop(runner);
+
+        // Only the first flight is approved, the other waiting to be approved automatically
+        let approve = async move {
+            let mut alerts = Box::pin(alerts);
+            let mut replies = vec![];
+            while let Some(alert) = alerts.next().await {
+                if alert.kind != AlertKind::ApprovalPending || !replies.is_empty() {
+                    continue;
+                }
+                for id in [alert.flight.expect("flight"), FlightId::new()] {
+                    let (tx, rx) = oneshot::channel();
+                    commands
+                        .unbounded_send(Command::ApproveFlight(id, tx))
+                        .expect("command");
+                    replies.push(rx);
+                }
+            }
+            replies
+        };
+        let (report, replies) = futures::join!(run, approve);
+        let report = report?;
+
+        let mut replies = replies.into_iter();
+        assert_eq!(replies.next().expect("reply").await?, Ok(()));
+        assert!(replies.next().expect("reply").await?.is_err());
+        assert_eq!(report.orders_delivered, 3);
+        let launch_time = |id| report.receipts[&OrderId(id)].launch_time;
+        assert_eq!(launch_time(1), Some(25_260));
+        assert_eq!(launch_time(3), Some(30_300));
+        // Flights carrying only resupplies launch straight away
+        assert_eq!(launch_time(2), Some(27_960));
+
+        Ok(())
+    }
+
+    /// Runs the sample scenario at the provided speed,
+    /// returning the number of updates emitted & the wall-clock seconds taken
+    async fn count_updates(
+        speed: Speed,
+        max_updates_per_second: u32,
+    ) -> Result<(usize, f64), Box<dyn std::error::Error + Send + Sync>> {
+        let mut runner = CsvRunner::from_csv_paths(DEST_PATH, ORDER_PATH)
+            .await?
+            .with_speed(speed)
+            .with_max_updates_per_second(NonZeroU32::new(max_updates_per_second).expect("rate"));
+        let updates = runner.stream_updates().expect("update stream");
+        let run = runner.run_with_defaults();
+        drop(runner);
+
+        let started = tokio::time::Instant::now();
+        let (report, updates) = futures::join!(run, updates.collect::<Vec<_>>());
+        report?;
+
+        Ok((updates.len(), started.elapsed().as_secs_f64()))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_quiet_stretches() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let destinations = Destination::from_csv(DEST_PATH).await?;
+        let destination = destinations[0].name.to_string();
+        let orders = ["2023-10-14T10:00:00Z", "2023-10-14T14:00:00Z"]
+            .into_iter()
+            .map(|timestamp| {
+                Ok(testkit::order(
+                    time::parse_timestamp(timestamp)?,
+                    &destination,
+                    Priority::Resupply,
+                ))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let (first, second) = (orders[0].time, orders[1].time);
+
+        let mut runner = CsvRunner::new(destinations, orders)
+            .with_speed(Speed::fast_forward(200).expect("speed"))
+            .with_max_updates_per_second(NonZeroU32::new(4).expect("rate"));
+        let updates = runner.stream_updates().expect("update stream");
+        let run = runner.run_with_defaults();
+        drop(runner);
+        let started = tokio::time::Instant::now();
+        let (report, updates) = futures::join!(run, updates.collect::<Vec<_>>());
+        let report = report?;
+        assert_eq!(report.orders_delivered, 2);
+
+        // The hours between the orders still pass at the configured speed...
+        let elapsed = started.elapsed().as_secs_f64();
+        let simulated = (report.end_time - report.start_time) as f64;
+        assert!((elapsed * 200.0 - simulated).abs() < simulated / 100.0);
+
+        // ...with updates sent through them at the configured rate
+        let quiet = updates
+            .iter()
+            .map(|update| update.time)
+            .filter(|time| (first + 3_600..second).contains(time))
+            .collect::<Vec<_>>();
+        assert!(quiet.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(quiet.windows(2).all(|pair| pair[1] - pair[0] <= 60));
+        assert!(quiet.len() >= 3 * 3_600 / 60);
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_speed_schedule() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let destinations = Destination::from_csv(DEST_PATH).await?;
+        let destination = destinations[0].name.to_string();
+        let orders = ["2023-10-14T10:00:00Z", "2023-10-14T14:00:00Z"]
+            .into_iter()
+            .map(|timestamp| {
+                Ok(testkit::order(
+                    time::parse_timestamp(timestamp)?,
+                    &destination,
+                    Priority::Resupply,
+                ))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let first = orders[0].time;
+
+        let mut runner = CsvRunner::new(destinations, orders)
+            .with_speed(Speed::fast_forward(200).expect("speed"))
+            .with_speed_schedule("11:00-12:00=1000x, 12:00-12:10=real-time".parse()?);
+        let updates = runner.stream_updates().expect("update stream");
+        let run = runner.run_with_defaults();
+        drop(runner);
+        let started = tokio::time::Instant::now();
+        let (report, updates) = futures::join!(run, updates.collect::<Vec<_>>());
+        let report = report?;
+        assert_eq!(report.orders_delivered, 2);
+
+        // Updates carry the speed scheduled at their time, or the run's own outside the windows
+        let speeds = |from: u64, to: u64| {
+            let mut speeds = updates
+                .iter()
+                .filter(|update| (first + from..first + to).contains(&update.time))
+                .map(|update| update.speed.to_string())
+                .collect::<Vec<_>>();
+            speeds.dedup();
+            speeds
+        };
+        assert_eq!(speeds(1_800, 3_600), ["200x"]);
+        assert_eq!(speeds(3_600, 7_200), ["1000x"]);
+        assert_eq!(speeds(7_200, 7_800), ["real-time"]);
+        assert_eq!(speeds(7_800, 14_400), ["200x"]);
+
+        // ...& each stretch passes at its speed
+        let simulated = (report.end_time - report.start_time - 3_600 - 600) as f64;
+        let expected = simulated / 200.0 + 3_600.0 / 1_000.0 + 600.0;
+        let elapsed = started.elapsed().as_secs_f64();
+        assert!((elapsed - expected).abs() < expected / 100.0);
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_backlog_in_updates() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut runner = CsvRunner::from_csv_paths(DEST_PATH, ORDER_PATH)
+            .await?
+            .with_speed(Speed::fast_forward(200).expect("speed"));
+        let updates = runner.stream_updates().expect("update stream");
+        let run = runner.run_with_defaults();
+        drop(runner);
+        let (report, updates) = futures::join!(run, updates.collect::<Vec<_>>());
+        let report = report?;
+
+        let backlogs = updates
+            .iter()
+            .map(|update| (update.time, update.backlog.expect("backlog")))
+            .collect::<Vec<_>>();
+        assert!(backlogs.iter().any(|(_, backlog)| !backlog.is_empty()));
+        assert!(backlogs
+            .iter()
+            .all(|(time, backlog)| backlog.oldest.is_none_or(|oldest| oldest <= *time)));
+        // Whatever is left by the end of the run is unfulfilled
+        assert_eq!(
+            backlogs.last().map(|(_, backlog)| backlog.len()),
+            Some(report.unfulfilled_orders)
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_update_rate_real_time() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        // Below the limit, an update is emitted for every simulated second
+        let (updates, elapsed) = count_updates(Speed::RealTime, 10).await?;
+        assert_eq!(updates, elapsed.round() as usize);
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_update_rate_slow_motion() -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+    {
+        let speed = Speed::SlowMotion(NonZeroU16::new(2).expect("speed"));
+        let (updates, elapsed) = count_updates(speed, 10).await?;
+        assert_eq!(updates, (elapsed / 2.0).round() as usize);
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_update_rate_fast_forward() -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+    {
+        for (factor, rate) in [(2, 10), (50, 10), (200, 10), (200, 4), (255, 1)] {
+            let speed = Speed::fast_forward(factor).expect("speed");
+            let (updates, elapsed) = count_updates(speed, rate).await?;
+
+            // Limited to the configured rate, plus the final update
+            let limit = (elapsed * rate.min(factor as u32) as f64).ceil() as usize + 1;
+            assert!(
+                updates <= limit,
+                "{}x @ {}Hz: {} > {}",
+                factor,
+                rate,
+                updates,
+                limit
+            );
+            assert!(
+                updates >= limit * 9 / 10,
+                "{}x @ {}Hz: {} < {}",
+                factor,
+                rate,
+                updates,
+                limit
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+
+use schema::{CarrierId, Flight, FlightId, RangeReduction};
+
+/// Tracks the range carriers have left as conditions change during their flights, picking out
+/// any which could no longer make it back to the origin so that they can be recalled early
+pub struct SafetyMonitor {
+    carrier_range_m: u64,
+    carrier_speed_mps: u64,
+    /// Reductions which haven't come into effect yet, latest first
+    reductions: Vec<RangeReduction>,
+    /// Range left to each carrier in flight
+    in_flight: HashMap<CarrierId, RemainingRange>,
+}
+
+struct RemainingRange {
+    flight: FlightId,
+    /// Meters the carrier could still travel as of a time
+    range_m: f32,
+    as_of: u64,
+    /// Whether the carrier has already been picked out for recall
+    recalled: bool,
+}
+
+impl RemainingRange {
+    /// Range left at the given time, having traveled since the last update
+    fn at(&self, time: u64, speed_mps: u64) -> f32 {
+        self.range_m - (time.saturating_sub(self.as_of) * speed_mps) as f32
+    }
+}
+
+impl SafetyMonitor {
+    /// Slack for the rounding of distances when flights are planned
+    const TOLERANCE_M: f32 = 10.0;
+
+    pub fn new(
+        carrier_range_m: u64,
+        carrier_speed_mps: u64,
+        mut reductions: Vec<RangeReduction>,
+    ) -> Self {
+        reductions.sort_by_key(|reduction| std::cmp::Reverse(reduction.time));
+
+        Self {
+            carrier_range_m,
+            carrier_speed_mps,
+            reductions,
+            in_flight: HashMap::new(),
+        }
+    }
+
+    /// Returns the carriers which, as of the current time, no longer have the range to complete
+    /// their flights, given each of the active flights along with its total distance.
+    /// Each carrier is only returned once per flight.
+    pub fn at_risk<'a>(
+        &mut self,
+        current_time: u64,
+        flights: impl Iterator<Item = (&'a Flight, f32)>,
+    ) -> Vec<CarrierId> {
+        let flights = flights.collect::<Vec<_>>();
+
+        // Carriers start each flight with their full range
+        self.in_flight.retain(|carrier, remaining| {
+            flights
+                .iter()
+                .any(|(flight, _)| flight.carrier == *carrier && flight.id == remaining.flight)
+        });
+        for (flight, _) in &flights {
+            self.in_flight
+                .entry(flight.carrier)
+                .or_insert(RemainingRange {
+                    flight: flight.id,
+                    range_m: self.carrier_range_m as f32,
+                    as_of: flight.launch_time,
+                    recalled: false,
+                });
+        }
+
+        while let Some(reduction) = self
+            .reductions
+            .pop_if(|reduction| reduction.time <= current_time)
+        {
+            for (carrier, remaining) in &mut self.in_flight {
+                let affected = reduction
+                    .carrier
+                    .is_none_or(|affected| affected == *carrier);
+                if affected && remaining.as_of <= reduction.time {
+                    remaining.range_m =
+                        remaining.at(reduction.time, self.carrier_speed_mps) * reduction.factor;
+                    remaining.as_of = reduction.time;
+                }
+            }
+        }
+
+        let mut at_risk = vec![];
+        for (flight, distance_m) in flights {
+            let Some(remaining) = self.in_flight.get_mut(&flight.carrier) else {
+                continue;
+            };
+            if remaining.recalled || flight.diversion.is_some() {
+                continue;
+            }
+
+            let traveled_m =
+                (current_time.saturating_sub(flight.launch_time) * self.carrier_speed_mps) as f32;
+            let range_m = remaining.at(current_time, self.carrier_speed_mps);
+            if distance_m - traveled_m > range_m + Self::TOLERANCE_M {
+                remaining.recalled = true;
+                at_risk.push(flight.carrier);
+            }
+        }
+        at_risk
+    }
+
+    /// Returns the time at which the next reduction comes into effect, if any are still to. Until
+    /// then carriers use up their range as fast as they cover their flights, so none which aren't
+    /// at risk now can become so before any new flights launch.
+    pub fn next_reduction(&self) -> Option<u64> {
+        self.reductions.last().map(|reduction| reduction.time)
+    }
+
+    /// Returns the range a carrier in flight has left at the current time, if known
+    pub fn remaining_range_m(&self, carrier: CarrierId, current_time: u64) -> Option<f32> {
+        self.in_flight
+            .get(&carrier)
+            .map(|remaining| remaining.at(current_time, self.carrier_speed_mps))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_at_risk() {
+        let flight = |carrier| Flight {
+            carrier: CarrierId(carrier),
+            launch_time: 0,
+            ..Default::default()
+        };
+        let (first, second) = (flight(0), flight(1));
+        let reduction = |time, carrier| RangeReduction {
+            time,
+            factor: 0.5,
+            carrier,
+        };
+        let mut monitor = SafetyMonitor::new(
+            10_000,
+            10,
+            vec![reduction(300, Some(CarrierId(0))), reduction(100, None)],
+        );
+        let flights = || [(&first, 5_000.0), (&second, 4_000.0)].into_iter();
+
+        assert!(monitor.at_risk(0, flights()).is_empty());
+
+        // Wind halves the range left to both carriers, which both still have enough to return
+        assert!(monitor.at_risk(100, flights()).is_empty());
+        assert_eq!(monitor.remaining_range_m(CarrierId(1), 100), Some(4_500.0));
+
+        // Only the first carrier's battery fails, leaving it without the range to finish
+        assert_eq!(monitor.at_risk(300, flights()), vec![CarrierId(0)]);
+        assert_eq!(monitor.remaining_range_m(CarrierId(0), 300), Some(1_250.0));
+        assert!(monitor.at_risk(301, flights()).is_empty());
+    }
+}
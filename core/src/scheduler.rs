@@ -0,0 +1,2343 @@
+use std::{
+    cmp::Ordering,
+    collections::{binary_heap, BTreeMap, BinaryHeap, HashMap, HashSet},
+    mem, slice,
+    sync::Arc,
+};
+
+use itertools::{Either, Itertools};
+use schema::geometry::{DistanceMetric, Euclidean, Manhattan};
+use schema::{
+    CarrierId, CarrierMode, Destination, DestinationChanges, DestinationId, Diversion, EnergyModel,
+    Flight, FlightExplanation, FlightId, FlightPhases, Forecaster, Order, PackingOutcome,
+    PackingStop, Priority, ReturnPolicy, Scheduler,
+};
+
+use crate::explain::{self, PackingTrace};
+use crate::packing::{Bin, Bins, PackingStrategy, PriorityFit};
+use crate::routing::{self, RoutePlanner};
+use crate::surge::{SurgeDetector, SurgePolicy};
+
+/// A naive scheduler which sorts the incoming orders by priority
+/// and packs them into the available carriers.
+/// __WARNING:__ this scheduler uses a naive algorithm which I pretty much made up as I went along.
+/// Its packing & scheduling quality, as well as its performance characteristics, are relatively
+/// untested, and likely poor.
+#[derive(Clone)]
+pub struct NaiveScheduler<M = Euclidean> {
+    /// Metric used to measure the distance carriers must travel between destinations
+    metric: M,
+    /// `Destination`s serviced by this `Scheduler`
+    destinations: HashMap<DestinationId, Destination>,
+    /// Destinations which no longer accept orders, but may still have orders in flight
+    deactivated: HashSet<DestinationId>,
+    /// Nest which carriers launch from & return to
+    origin: Destination,
+    /// Carriers controlled by this `Scheduler`, & whether each flies or drives
+    fleet: BTreeMap<CarrierId, CarrierMode>,
+    /// Carriers which are currently landed & available to make deliveries
+    idle_carriers: Vec<CarrierId>,
+    /// Where carriers go once they've made their last stop
+    return_policy: ReturnPolicy,
+    /// Destinations at which idle carriers are waiting, for those not at the origin
+    parked: HashMap<CarrierId, DestinationId>,
+    /// Total number of orders that can be held by carriers controlled by this scheduler
+    max_orders_per_carrier: usize,
+    /// Speed in meters per second for carriers controlled by this scheduler
+    carrier_speed_mps: u64,
+    /// Max range in meters that carriers controlled by this scheduler can travel
+    carrier_range_m: u64,
+    /// Ground vehicles making deliveries alongside the carriers
+    ground: GroundFleet,
+    /// Time carriers take to take off & land at each stop
+    flight_phases: FlightPhases,
+    /// Orders that have not yet been fulfilled, most urgent first
+    unfulfilled_orders: BinaryHeap<PendingOrder>,
+    /// Seconds a destination's resupplies wait without a flight to it before they're boosted, if
+    /// starved destinations are boosted at all
+    fairness: Option<u64>,
+    /// Last time a flight was launched to each destination
+    last_served: HashMap<DestinationId, u64>,
+    /// Number of orders queued so far, used to keep orders placed at the same time in sequence
+    orders_queued: u64,
+    /// Orders that are currently in-flight
+    active_flights: Vec<Flight>,
+    /// Orders given up on because they could no longer be delivered within their window
+    missed_windows: Vec<Order>,
+    /// Perishable orders given up on because they would spoil before being delivered
+    spoiled: Vec<Order>,
+    /// Orders held back from a launch because their destinations would be closed when its flights
+    /// arrived
+    closed_deferrals: Vec<Order>,
+    /// Orders turned away because their destination was deactivated
+    rejected_orders: Vec<Order>,
+    /// Decisions behind the flights launched since they were last taken
+    explanations: Vec<FlightExplanation>,
+    /// Forecast of upcoming demand used to decide how many carriers to reserve for emergencies
+    forecaster: Option<Arc<dyn Forecaster>>,
+    /// Decides which carrier each order is packed into
+    packing: Arc<dyn PackingStrategy>,
+    /// Decides the order each flight visits its stops in
+    route_planner: RoutePlanner,
+    /// Estimates the energy carriers spend on their flights
+    energy: EnergyModel,
+    /// Watches for surges of emergencies, during which every carrier is given over to them
+    surge: Option<SurgeDetector>,
+    /// Flights which hadn't launched yet when their carriers were commandeered for emergencies
+    commandeered: Vec<Flight>,
+    /// Flights planned ahead which are yet to launch, by launch time, followed until the queued
+    /// orders deviate from them
+    plan: Option<Vec<Flight>>,
+}
+
+impl NaiveScheduler {
+    pub fn new(
+        destinations: HashMap<DestinationId, Destination>,
+        num_carriers: usize,
+        max_orders_per_carrier: usize,
+        carrier_speed_mps: u64,
+        carrier_range_m: u64,
+    ) -> Self {
+        Self {
+            metric: Euclidean,
+            destinations,
+            deactivated: HashSet::new(),
+            origin: Destination::default_origin(),
+            fleet: (0..num_carriers as u32)
+                .map(|i| (CarrierId(i), CarrierMode::Aerial))
+                .collect(),
+            idle_carriers: (0..num_carriers as u32).rev().map(CarrierId).collect(),
+            return_policy: ReturnPolicy::default(),
+            parked: HashMap::new(),
+            max_orders_per_carrier,
+            carrier_speed_mps,
+            carrier_range_m,
+            ground: GroundFleet::default(),
+            flight_phases: FlightPhases::default(),
+            unfulfilled_orders: BinaryHeap::new(),
+            fairness: None,
+            last_served: HashMap::new(),
+            orders_queued: 0,
+            active_flights: Vec::new(),
+            missed_windows: Vec::new(),
+            spoiled: Vec::new(),
+            closed_deferrals: Vec::new(),
+            rejected_orders: Vec::new(),
+            explanations: Vec::new(),
+            forecaster: None,
+            packing: Arc::new(PriorityFit),
+            route_planner: RoutePlanner::default(),
+            energy: EnergyModel::default(),
+            surge: None,
+            commandeered: Vec::new(),
+            plan: None,
+        }
+    }
+}
+
+impl<M: DistanceMetric> NaiveScheduler<M> {
+    /// Number of carriers to keep in reserve for emergency orders
+    const NUM_RESERVE_CARRIERS: usize = 2;
+    /// Seconds ahead to look for expected emergency orders when forecasting
+    const FORECAST_WINDOW_S: u64 = 30 * 60;
+
+    /// Measure distances traveled by carriers using the provided `DistanceMetric`
+    pub fn with_metric<N: DistanceMetric>(self, metric: N) -> NaiveScheduler<N> {
+        NaiveScheduler {
+            metric,
+            destinations: self.destinations,
+            deactivated: self.deactivated,
+            origin: self.origin,
+            fleet: self.fleet,
+            idle_carriers: self.idle_carriers,
+            return_policy: self.return_policy,
+            parked: self.parked,
+            max_orders_per_carrier: self.max_orders_per_carrier,
+            carrier_speed_mps: self.carrier_speed_mps,
+            carrier_range_m: self.carrier_range_m,
+            ground: self.ground,
+            flight_phases: self.flight_phases,
+            unfulfilled_orders: self.unfulfilled_orders,
+            fairness: self.fairness,
+            last_served: self.last_served,
+            orders_queued: self.orders_queued,
+            active_flights: self.active_flights,
+            missed_windows: self.missed_windows,
+            spoiled: self.spoiled,
+            closed_deferrals: self.closed_deferrals,
+            rejected_orders: self.rejected_orders,
+            explanations: self.explanations,
+            forecaster: self.forecaster,
+            packing: self.packing,
+            route_planner: self.route_planner,
+            energy: self.energy,
+            surge: self.surge,
+            commandeered: self.commandeered,
+            plan: self.plan,
+        }
+    }
+
+    /// Launch carriers from the provided nest, rather than the default origin
+    pub fn with_origin(mut self, origin: Destination) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    /// Make deliveries with ground vehicles as well as the carriers, numbered after the carriers.
+    /// Emergencies go by whichever would arrive first, & resupplies by road wherever the vehicles
+    /// have the range, leaving the carriers free for emergencies.
+    pub fn with_ground_fleet(mut self, ground: GroundFleet) -> Self {
+        self.ground = ground;
+        self.fleet.retain(|_, mode| *mode == CarrierMode::Aerial);
+        let first = self
+            .fleet
+            .keys()
+            .next_back()
+            .map_or(0, |carrier| carrier.0 + 1);
+        self.fleet.extend(
+            (first..first + ground.vehicles as u32).map(|i| (CarrierId(i), CarrierMode::Ground)),
+        );
+        self.idle_carriers = self.carriers().rev().collect();
+        self
+    }
+
+    /// Have carriers wait at the last stop of each flight rather than return to the origin, or the
+    /// other way around. Waiting carriers launch their next flight from where they are, so neither
+    /// packing nor the flights themselves budget range for a return leg.
+    pub fn with_return_policy(mut self, return_policy: ReturnPolicy) -> Self {
+        self.return_policy = return_policy;
+        self
+    }
+
+    /// Pack the resupplies of destinations which have had orders waiting for the provided number of
+    /// seconds without a flight launched to them ahead of perishables & other resupplies, so that
+    /// destinations which keep losing out on carriers aren't starved. Emergencies still go first.
+    pub fn with_fairness(mut self, starvation_s: u64) -> Self {
+        self.fairness = Some(starvation_s);
+        self
+    }
+
+    /// Hold carriers in place while they take off from & land at each stop
+    pub fn with_flight_phases(mut self, flight_phases: FlightPhases) -> Self {
+        self.flight_phases = flight_phases;
+        self
+    }
+
+    /// Reserve carriers for the emergency orders expected by the provided `Forecaster`,
+    /// rather than a fixed number of carriers
+    pub fn with_forecaster(mut self, forecaster: impl Forecaster + 'static) -> Self {
+        self.forecaster = Some(Arc::new(forecaster));
+        self
+    }
+
+    /// Pack orders into carriers using the provided strategy, rather than by priority
+    pub fn with_packing(mut self, packing: impl PackingStrategy + 'static) -> Self {
+        self.packing = Arc::new(packing);
+        self
+    }
+
+    /// Order the stops of each flight with the provided planner, rather than as they were packed
+    pub fn with_route_planner(mut self, route_planner: RoutePlanner) -> Self {
+        self.route_planner = route_planner;
+        self
+    }
+
+    /// Estimate the energy carriers spend with the provided model, both to plan routes by & report
+    pub fn with_energy_model(mut self, energy: EnergyModel) -> Self {
+        self.energy = energy;
+        self
+    }
+
+    /// Give every carrier over to emergencies while they arrive faster than the provided policy
+    /// allows: none are held in reserve, resupplies wait for the surge to end, & carriers held on
+    /// the ground with only resupplies packed are unpacked for emergencies instead
+    pub fn with_surge(mut self, policy: SurgePolicy) -> Self {
+        self.surge = Some(SurgeDetector::new(policy));
+        self
+    }
+
+    /// Launch the flights of a plan computed ahead of time, e.g. by a previous run of the scenario,
+    /// rather than packing orders as they're queued. The plan is given up on for the rest of the
+    /// run as soon as the queued orders deviate from it, e.g. an order arrives which wasn't
+    /// planned for, or a planned one hasn't arrived by the time its flight is due.
+    pub fn with_plan(mut self, mut flights: Vec<Flight>) -> Self {
+        flights.sort_by_key(|flight| flight.launch_time);
+        self.plan = Some(flights);
+        self
+    }
+
+    /// Take over the queued orders & in-flight carriers of another scheduler,
+    /// e.g. when switching schedulers in the middle of a simulation
+    pub fn take_over(mut self, previous: impl Into<Handover>) -> Self {
+        let previous = previous.into();
+        let in_flight = previous
+            .active_flights
+            .iter()
+            .map(|flight| flight.carrier)
+            .collect::<Vec<_>>();
+        self.ground = previous.ground;
+        self.fleet = previous.fleet;
+        self.idle_carriers = self
+            .carriers()
+            .rev()
+            .filter(|carrier| !in_flight.contains(carrier))
+            .collect();
+        self.origin = previous.origin;
+        self.return_policy = previous.return_policy;
+        self.parked = previous.parked;
+        self.max_orders_per_carrier = previous.max_orders_per_carrier;
+        self.carrier_speed_mps = previous.carrier_speed_mps;
+        self.carrier_range_m = previous.carrier_range_m;
+        self.flight_phases = previous.flight_phases;
+        self.packing = previous.packing;
+        self.route_planner = previous.route_planner;
+        self.energy = previous.energy;
+        self.surge = previous.surge;
+        self.commandeered = previous.commandeered;
+        self.unfulfilled_orders = previous.unfulfilled_orders;
+        self.fairness = previous.fairness;
+        self.last_served = previous.last_served;
+        self.orders_queued = previous.orders_queued;
+        self.active_flights = previous.active_flights;
+        self.missed_windows = previous.missed_windows;
+        self.spoiled = previous.spoiled;
+        self.closed_deferrals = previous.closed_deferrals;
+        self.deactivated = previous.deactivated;
+        self.rejected_orders = previous.rejected_orders;
+        self.plan = previous.plan;
+        self
+    }
+
+    /// Returns the flights which would be launched at the given time if the extra orders were
+    /// queued, without committing to any of them
+    pub fn preview_flights(&self, extra_orders: Vec<Order>, current_time: u64) -> Vec<Flight>
+    where
+        M: Clone,
+    {
+        let mut scheduler = self.clone();
+        for order in extra_orders {
+            scheduler.queue_order(order);
+        }
+        scheduler.launch_flights(current_time).cloned().collect()
+    }
+
+    pub fn active_flights(&self) -> impl Iterator<Item = &Flight> {
+        self.active_flights.iter()
+    }
+
+    /// Returns the identifiers of all carriers controlled by this scheduler, including any ground
+    /// vehicles
+    pub fn carriers(&self) -> impl DoubleEndedIterator<Item = CarrierId> + '_ {
+        self.fleet.keys().copied()
+    }
+
+    /// Returns whether a carrier flies or drives
+    pub fn carrier_mode(&self, carrier: CarrierId) -> CarrierMode {
+        self.fleet.get(&carrier).copied().unwrap_or_default()
+    }
+
+    /// Returns the number of carriers, not counting ground vehicles
+    pub fn fleet_size(&self) -> usize {
+        self.fleet
+            .values()
+            .filter(|mode| **mode == CarrierMode::Aerial)
+            .count()
+    }
+
+    /// Adds or retires carriers (but not ground vehicles) until there are the provided number,
+    /// returning how many there are. Only idle carriers are retired, so the fleet never shrinks
+    /// below the carriers in flight. Added carriers take the lowest identifiers which are free.
+    pub fn set_fleet_size(&mut self, carriers: usize) -> usize {
+        let in_flight = self
+            .active_flights
+            .iter()
+            .filter(|flight| flight.mode == CarrierMode::Aerial)
+            .count();
+        let carriers = carriers.max(in_flight);
+
+        while self.fleet_size() < carriers {
+            let carrier = (0..u32::MAX)
+                .map(CarrierId)
+                .find(|carrier| !self.fleet.contains_key(carrier))
+                .expect("free carrier identifier");
+            self.fleet.insert(carrier, CarrierMode::Aerial);
+            self.idle_carriers.insert(0, carrier);
+        }
+        while self.fleet_size() > carriers {
+            // Carriers are retired from the highest identifier down
+            let (i, carrier) = self
+                .idle_carriers
+                .iter()
+                .copied()
+                .enumerate()
+                .filter(|(_, carrier)| self.carrier_mode(*carrier) == CarrierMode::Aerial)
+                .max_by_key(|(_, carrier)| *carrier)
+                .expect("idle carrier");
+            self.idle_carriers.remove(i);
+            self.parked.remove(&carrier);
+            self.fleet.remove(&carrier);
+        }
+        carriers
+    }
+
+    /// Returns the nest which carriers launch from & return to
+    pub fn origin(&self) -> &Destination {
+        &self.origin
+    }
+
+    /// Returns where an idle carrier is waiting to launch from, the origin unless it's waiting at
+    /// the last stop of its previous flight
+    pub fn carrier_location(&self, carrier: CarrierId) -> &Destination {
+        self.parked
+            .get(&carrier)
+            .and_then(|destination| self.destinations.get(destination))
+            .unwrap_or(&self.origin)
+    }
+
+    /// Returns the number of orders that a single carrier can hold
+    pub fn max_orders_per_carrier(&self) -> usize {
+        self.max_orders_per_carrier
+    }
+
+    /// Returns the speed in meters per second of the carriers
+    pub fn carrier_speed_mps(&self) -> u64 {
+        self.carrier_speed_mps
+    }
+
+    /// Returns the max range in meters of the carriers
+    pub fn carrier_range_m(&self) -> u64 {
+        self.carrier_range_m
+    }
+
+    /// Returns the time at which each of a flight's orders will be delivered by its carrier
+    pub fn delivery_times(&self, flight: &Flight) -> Vec<u64> {
+        let (metric, speed_mps) = self.travel(flight.mode);
+        flight.delivery_times(metric, &self.destinations, &self.origin, speed_mps)
+    }
+
+    /// Returns the total distance in meters which will be traveled by a flight
+    pub fn flight_distance(&self, flight: &Flight) -> f32 {
+        flight.total_distance(self.travel(flight.mode).0, &self.destinations, &self.origin)
+    }
+
+    /// Returns the energy in joules a flight's carrier will spend on it, unless it drives
+    pub fn flight_energy_j(&self, flight: &Flight) -> Option<f32> {
+        let (metric, speed_mps) = self.travel(flight.mode);
+        (flight.mode == CarrierMode::Aerial).then(|| {
+            flight.energy_j(
+                metric,
+                &self.destinations,
+                &self.origin,
+                &self.energy,
+                speed_mps,
+            )
+        })
+    }
+
+    /// Returns the time at which a flight's carrier will land back at the origin
+    pub fn end_time(&self, flight: &Flight) -> u64 {
+        let (metric, speed_mps) = self.travel(flight.mode);
+        flight.end_time(metric, &self.destinations, &self.origin, speed_mps)
+    }
+
+    /// Returns the easting & northing of a flight's carrier at the given time
+    pub fn position(&self, flight: &Flight, current_time: u64) -> (f32, f32) {
+        let (metric, speed_mps) = self.travel(flight.mode);
+        let (east_m, north_m, _) = flight.current_position(
+            metric,
+            &self.destinations,
+            &self.origin,
+            current_time,
+            speed_mps,
+        );
+        (east_m, north_m)
+    }
+
+    /// Holds a carrier on the ground until the provided time before it launches on its flight.
+    /// Returns the held flight, unless the carrier wasn't assigned one.
+    pub fn hold_launch(&mut self, carrier: CarrierId, launch_time: u64) -> Option<&Flight> {
+        let flight = self
+            .active_flights
+            .iter_mut()
+            .find(|flight| flight.carrier == carrier)?;
+        flight.launch_time = flight.launch_time.max(launch_time);
+        Some(flight)
+    }
+
+    /// Cuts a carrier's flight short, sending it straight back to the origin from wherever it is at
+    /// the given time. Orders it hasn't delivered yet are queued again.
+    /// Returns the diverted flight, unless the carrier wasn't in flight or had nothing left to deliver.
+    pub fn return_to_base(&mut self, carrier: CarrierId, current_time: u64) -> Option<&Flight> {
+        let i = self
+            .active_flights
+            .iter()
+            .position(|flight| flight.carrier == carrier && flight.diversion.is_none())?;
+        let flight = &self.active_flights[i];
+        let delivered = self
+            .delivery_times(flight)
+            .into_iter()
+            .take_while(|time| *time <= current_time)
+            .count();
+        if delivered == flight.orders.len() {
+            return None;
+        }
+
+        let (metric, speed_mps) = self.travel(flight.mode);
+        let (east_m, north_m, _) = flight.current_position(
+            metric,
+            &self.destinations,
+            &self.origin,
+            current_time,
+            speed_mps,
+        );
+        let flight = &mut self.active_flights[i];
+        flight.diversion = Some(Diversion {
+            north_m: north_m.round() as i64,
+            east_m: east_m.round() as i64,
+        });
+        let undelivered = flight.orders.split_off(delivered);
+        for order in undelivered {
+            self.requeue(order);
+        }
+
+        Some(&self.active_flights[i])
+    }
+
+    /// Returns the orders given up on since the last call because they could no longer be
+    /// delivered within their window
+    pub fn take_missed_windows(&mut self) -> Vec<Order> {
+        std::mem::take(&mut self.missed_windows)
+    }
+
+    /// Returns the perishable orders given up on since the last call because they would spoil
+    /// before being delivered, even when flown directly
+    pub fn take_spoiled(&mut self) -> Vec<Order> {
+        std::mem::take(&mut self.spoiled)
+    }
+
+    /// Returns the orders held back from launches since the last call because their destinations
+    /// would have been closed when the flights arrived
+    pub fn take_deferred(&mut self) -> Vec<Order> {
+        std::mem::take(&mut self.closed_deferrals)
+    }
+
+    /// Whether an order's destination is open to take deliveries at a time
+    pub(crate) fn is_open_at(&self, order: &Order, timestamp: u64) -> bool {
+        self.destinations
+            .get(&order.destination)
+            .is_none_or(|destination| destination.is_open_at(timestamp))
+    }
+
+    /// Returns the decisions behind each flight launched since the last call, i.e. the orders
+    /// considered while it was packed & why those it didn't carry were left out
+    pub fn take_explanations(&mut self) -> Vec<FlightExplanation> {
+        std::mem::take(&mut self.explanations)
+    }
+
+    /// Adds & deactivates destinations. Queued orders for deactivated destinations are rejected,
+    /// as are any orders for them queued later.
+    pub fn update_destinations(&mut self, changes: &DestinationChanges) {
+        for destination in &changes.added {
+            self.deactivated.remove(&destination.name);
+            self.destinations
+                .insert(destination.name, destination.clone());
+        }
+        self.deactivated.extend(changes.deactivated.iter().copied());
+
+        let (rejected, queued): (Vec<_>, Vec<_>) = std::mem::take(&mut self.unfulfilled_orders)
+            .into_iter()
+            .partition(|pending| self.deactivated.contains(&pending.order.destination));
+        self.unfulfilled_orders = queued.into();
+        self.rejected_orders
+            .extend(rejected.into_iter().map(|pending| pending.order));
+    }
+
+    /// Returns the orders rejected since the last call because their destination was deactivated
+    pub fn take_rejected_orders(&mut self) -> Vec<Order> {
+        std::mem::take(&mut self.rejected_orders)
+    }
+
+    /// Whether emergencies are arriving fast enough for every carrier to be given over to them
+    pub fn is_surging(&self) -> bool {
+        self.surge.as_ref().is_some_and(SurgeDetector::is_surging)
+    }
+
+    /// Returns the flights unpacked since the last call because their carriers were commandeered
+    /// for emergencies before they launched. Their orders have been queued again.
+    pub fn take_commandeered(&mut self) -> Vec<Flight> {
+        std::mem::take(&mut self.commandeered)
+    }
+
+    /// Returns the distance in meters a carrier of the provided mode travels between two stops,
+    /// either of which is the origin where it's `None`
+    #[cfg(feature = "vrp")]
+    pub(crate) fn stop_distance(
+        &self,
+        mode: CarrierMode,
+        from: Option<&DestinationId>,
+        to: Option<&DestinationId>,
+    ) -> f32 {
+        let stop = |stop: Option<&DestinationId>| {
+            stop.and_then(|stop| self.destinations.get(stop))
+                .unwrap_or(&self.origin)
+        };
+        self.travel(mode).0.distance(stop(from), stop(to))
+    }
+
+    /// Returns the flights launched from the provided position among the active flights onward
+    #[cfg(feature = "vrp")]
+    pub(crate) fn launched_since(&self, from: usize) -> &[Flight] {
+        &self.active_flights[from..]
+    }
+
+    /// Replaces the orders of flights which have just launched, e.g. as re-planned by a
+    /// `VrpScheduler`. Flights left without any orders are called off, their carriers idle again,
+    /// & the rest are explained as planned.
+    #[cfg(feature = "vrp")]
+    pub(crate) fn replan_launched(&mut self, mut replanned: HashMap<FlightId, Vec<Order>>) {
+        let ids = replanned.keys().copied().collect::<HashSet<_>>();
+        let mut called_off = vec![];
+        self.active_flights
+            .retain_mut(|flight| match replanned.remove(&flight.id) {
+                Some(orders) if orders.is_empty() => {
+                    called_off.push(flight.carrier);
+                    false
+                }
+                Some(orders) => {
+                    flight.orders = orders;
+                    true
+                }
+                None => true,
+            });
+        self.idle_carriers.extend(called_off);
+
+        let mut flights = mem::take(&mut self.active_flights);
+        if self.route_planner == RoutePlanner::Energy {
+            for flight in flights
+                .iter_mut()
+                .filter(|flight| ids.contains(&flight.id) && flight.mode == CarrierMode::Aerial)
+            {
+                self.plan_least_energy(flight);
+            }
+        }
+        self.explanations.retain_mut(|explanation| {
+            if !ids.contains(&explanation.flight) {
+                return true;
+            }
+            match flights
+                .iter()
+                .find(|flight| flight.id == explanation.flight)
+            {
+                Some(flight) => {
+                    *explanation = explain::explain_planned(
+                        flight,
+                        explanation.queued,
+                        explanation.available_carriers,
+                        self.max_orders_per_carrier,
+                    );
+                    true
+                }
+                None => false,
+            }
+        });
+        self.active_flights = flights;
+    }
+
+    /// Queues an order which has already been queued once, without counting it as a new arrival
+    fn requeue(&mut self, order: Order) {
+        if self.deactivated.contains(&order.destination) {
+            self.rejected_orders.push(order);
+            return;
+        }
+
+        self.unfulfilled_orders.push(PendingOrder {
+            order,
+            sequence: self.orders_queued,
+            boosted: false,
+        });
+        self.orders_queued += 1;
+    }
+
+    /// Boosts the queued resupplies of destinations which have had an order waiting for longer than
+    /// the fairness threshold without a flight launched to them since, if fairness is enabled
+    fn boost_starved(&mut self, current_time: u64) {
+        let Some(starvation_s) = self.fairness else {
+            return;
+        };
+        let mut queued = mem::take(&mut self.unfulfilled_orders).into_vec();
+        let is_resupply = |pending: &PendingOrder| pending.order.priority == Priority::Resupply;
+        let starved = queued
+            .iter()
+            .filter(|pending| is_resupply(pending))
+            .filter(|pending| {
+                let destination = pending.order.destination;
+                let waiting_since = match self.last_served.get(&destination) {
+                    Some(served) => pending.order.time.max(*served),
+                    None => pending.order.time,
+                };
+                current_time.saturating_sub(waiting_since) >= starvation_s
+            })
+            .map(|pending| pending.order.destination)
+            .collect::<HashSet<_>>();
+        for pending in &mut queued {
+            pending.boosted = is_resupply(pending) && starved.contains(&pending.order.destination);
+        }
+        self.unfulfilled_orders = queued.into();
+    }
+
+    /// Records the destinations of the flights launched from the provided index on as served
+    fn record_served(&mut self, from: usize, current_time: u64) {
+        for flight in &self.active_flights[from..] {
+            for order in &flight.orders {
+                self.last_served.insert(order.destination, current_time);
+            }
+        }
+    }
+
+    /// Launches the flights of the plan due by the provided time, returning whether the plan was
+    /// followed. The plan is dropped instead if the queued orders no longer match it.
+    fn follow_plan(&mut self, current_time: u64) -> bool {
+        let Some(mut plan) = self.plan.take() else {
+            return false;
+        };
+        // Orders are matched up with those planned by what they are, rather than their ids
+        let key = |order: &Order| {
+            (
+                order.time,
+                order.destination,
+                order.priority,
+                order.quantity,
+            )
+        };
+        let mut queued = HashMap::<_, Vec<PendingOrder>>::new();
+        for pending in std::mem::take(&mut self.unfulfilled_orders) {
+            queued.entry(key(&pending.order)).or_default().push(pending);
+        }
+        let mut planned = HashMap::<_, usize>::new();
+        for order in plan.iter().flat_map(|flight| &flight.orders) {
+            *planned.entry(key(order)).or_default() += 1;
+        }
+
+        let due = plan
+            .iter()
+            .take_while(|flight| flight.launch_time <= current_time)
+            .count();
+        let mut deviated = due > self.idle_carriers.len()
+            || queued
+                .iter()
+                .any(|(key, pending)| pending.len() > planned.get(key).copied().unwrap_or(0));
+        let mut launches = vec![];
+        for flight in &plan[..due] {
+            if deviated {
+                break;
+            }
+            let mut orders = vec![];
+            for order in &flight.orders {
+                match queued.get_mut(&key(order)).and_then(Vec::pop) {
+                    Some(pending) => orders.push(pending),
+                    None => deviated = true,
+                }
+            }
+            launches.push((flight.carrier, orders));
+        }
+
+        if deviated {
+            tracing::info!("queued orders deviated from the plan, re-planning");
+            self.unfulfilled_orders = queued
+                .into_values()
+                .flatten()
+                .chain(launches.into_iter().flat_map(|(_, orders)| orders))
+                .collect();
+            return false;
+        }
+        self.unfulfilled_orders = queued.into_values().flatten().collect();
+        let queued = self.unfulfilled_orders.len()
+            + launches
+                .iter()
+                .map(|(_, orders)| orders.len())
+                .sum::<usize>();
+        for (carrier, orders) in launches {
+            // Planned carriers are used where they're free, though any idle carrier will do,
+            // preferably one travelling the same way
+            let mode = self.carrier_mode(carrier);
+            let i = match self.idle_carriers.iter().position(|idle| *idle == carrier) {
+                Some(i) => i,
+                None => self
+                    .idle_carriers
+                    .iter()
+                    .rposition(|idle| self.carrier_mode(*idle) == mode)
+                    .unwrap_or(self.idle_carriers.len() - 1),
+            };
+            let available = self.available_carriers(self.carrier_mode(self.idle_carriers[i]));
+            let carrier = self.idle_carriers.remove(i);
+            let flight = Flight {
+                id: FlightId::new(),
+                carrier,
+                launch_time: current_time,
+                orders: orders.into_iter().map(|pending| pending.order).collect(),
+                diversion: None,
+                phases: self.flight_phases,
+                mode: self.carrier_mode(carrier),
+                departs_from: self.parked.remove(&carrier),
+                return_policy: self.return_policy,
+            };
+            self.explanations.push(explain::explain_planned(
+                &flight,
+                queued,
+                available,
+                self.max_orders_per_carrier,
+            ));
+            self.active_flights.push(flight);
+        }
+        plan.drain(..due);
+        self.plan = Some(plan);
+        true
+    }
+
+    /// Takes back the carriers of flights which are held on the ground with only resupplies
+    /// packed, queueing their orders again
+    fn commandeer_held_flights(&mut self, current_time: u64) {
+        let (held, active): (Vec<Flight>, Vec<Flight>) = std::mem::take(&mut self.active_flights)
+            .into_iter()
+            .partition(|flight| {
+                flight.launch_time > current_time
+                    && flight
+                        .orders
+                        .iter()
+                        .all(|order| matches!(order.priority, Priority::Resupply))
+            });
+        self.active_flights = active;
+
+        for flight in held {
+            self.idle_carriers.push(flight.carrier);
+            if let Some(destination) = flight.departs_from {
+                self.parked.insert(flight.carrier, destination);
+            }
+            for order in flight.orders.iter().cloned() {
+                self.requeue(order);
+            }
+            self.commandeered.push(flight);
+        }
+    }
+
+    /// Returns the metric distances are measured by, & the speed in meters per second, of carriers
+    /// travelling by the provided mode
+    fn travel(&self, mode: CarrierMode) -> (&dyn DistanceMetric, u64) {
+        match mode {
+            CarrierMode::Aerial => (&self.metric, self.carrier_speed_mps),
+            CarrierMode::Ground => (&Manhattan, self.ground.speed_mps),
+        }
+    }
+
+    /// Returns the modes an order may be delivered by, most suitable first: emergencies by
+    /// whichever arrives first, & resupplies by road where possible
+    fn preferred_modes(
+        &self,
+        order: &Order,
+        destination: &Destination,
+        current_time: u64,
+    ) -> Vec<CarrierMode> {
+        use CarrierMode::*;
+
+        if self.ground.vehicles == 0 {
+            return vec![Aerial];
+        }
+        let arrival = |mode| self.direct_arrival(mode, destination, current_time);
+        match order.priority {
+            Priority::Emergency if arrival(Ground) < arrival(Aerial) => vec![Ground, Aerial],
+            Priority::Emergency => vec![Aerial, Ground],
+            Priority::Resupply => vec![Ground, Aerial],
+        }
+    }
+
+    /// Returns the time at which a carrier of the provided mode launched now would arrive at a
+    /// destination, travelling there directly from the origin or wherever one is waiting nearer
+    fn direct_arrival(
+        &self,
+        mode: CarrierMode,
+        destination: &Destination,
+        current_time: u64,
+    ) -> u64 {
+        let (metric, speed_mps) = self.travel(mode);
+        let distance = self
+            .parked
+            .iter()
+            .filter(|(carrier, _)| self.carrier_mode(**carrier) == mode)
+            .filter_map(|(_, parked)| self.destinations.get(parked))
+            .chain([&self.origin])
+            .map(|start| metric.distance(start, destination) as u64)
+            .min()
+            .unwrap_or_default();
+        current_time + distance / speed_mps + self.flight_phases.per_stop_s()
+    }
+
+    /// Returns the idle carriers of the provided mode in the order they're launched, the first
+    /// few of which are packed into bins in turn
+    fn launch_order(&self, mode: CarrierMode) -> Vec<CarrierId> {
+        self.idle_carriers
+            .iter()
+            .rev()
+            .copied()
+            .filter(|carrier| self.carrier_mode(*carrier) == mode)
+            .collect()
+    }
+
+    /// Removes an idle carrier, to launch on a flight
+    fn take_idle_carrier(&mut self, carrier: CarrierId) {
+        let i = self
+            .idle_carriers
+            .iter()
+            .position(|idle| *idle == carrier)
+            .expect("idle carrier");
+        self.idle_carriers.remove(i);
+    }
+
+    /// Returns the distance to a destination from the last stop of a bin (or where its carrier
+    /// launches from), if the bin has room for another package & its carrier has the range to
+    /// reach the destination & then return home, unless it's to wait there. Carriers burn through
+    /// their range serving each stop as they would cruising.
+    fn leg_distance(
+        &self,
+        bin: &Bin,
+        start: &Destination,
+        destination: &Destination,
+        mode: CarrierMode,
+    ) -> Option<u64> {
+        if bin.packages >= self.max_orders_per_carrier {
+            return None;
+        }
+
+        let last_stop = bin
+            .orders
+            .last()
+            .and_then(|x| self.destinations.get(&x.destination))
+            .unwrap_or(start);
+        let (metric, speed_mps) = self.travel(mode);
+        let range_m = match mode {
+            CarrierMode::Aerial => self.carrier_range_m,
+            CarrierMode::Ground => self.ground.range_m,
+        };
+        let distance = metric.distance(last_stop, destination) as u64;
+        let return_distance = match self.return_policy {
+            ReturnPolicy::ReturnToOrigin => metric.distance(destination, &self.origin) as u64,
+            ReturnPolicy::WaitAtLastStop => 0,
+        };
+        let service_m =
+            (bin.orders.len() as u64 + 1) * self.flight_phases.service.max_s() * speed_mps;
+
+        (bin.distance_allocated + distance + return_distance + service_m <= range_m)
+            .then_some(distance)
+    }
+
+    /// Reorders the stops of a flight to spend the least energy, so long as its carrier still has
+    /// the range & every order is still delivered within its window, before it would spoil
+    fn plan_least_energy(&self, flight: &mut Flight) {
+        let (_, speed_mps) = self.travel(flight.mode);
+        let range_m = self.carrier_range_m as f32;
+        routing::plan_least_energy(
+            flight,
+            |flight| self.flight_energy_j(flight).unwrap_or_default(),
+            |flight| {
+                let service_m =
+                    flight.orders.len() as u64 * self.flight_phases.service.max_s() * speed_mps;
+                self.flight_distance(flight) + service_m as f32 <= range_m
+                    && flight.orders.iter().zip(self.delivery_times(flight)).all(
+                        |(order, arrival)| {
+                            order.accepts_delivery_at(arrival)
+                                && self.is_open_at(order, arrival)
+                                && !order.spoils(flight.launch_time, arrival)
+                        },
+                    )
+            },
+        );
+    }
+
+    /// Returns the number of carriers of the provided mode available to make deliveries
+    fn available_carriers(&self, mode: CarrierMode) -> usize {
+        self.idle_carriers
+            .iter()
+            .filter(|carrier| self.carrier_mode(**carrier) == mode)
+            .count()
+    }
+
+    /// Returns the number of carriers to hold back for emergency orders which haven't been placed yet
+    fn reserved_carriers(&self, current_time: u64) -> usize {
+        let Some(forecaster) = &self.forecaster else {
+            return Self::NUM_RESERVE_CARRIERS;
+        };
+
+        let expected = forecaster
+            .expected_orders(Priority::Emergency, current_time, Self::FORECAST_WINDOW_S)
+            .values()
+            .sum::<f32>();
+
+        // Never hold back more than half of the fleet
+        (expected.ceil() as usize).min(self.fleet_size() / 2)
+    }
+
+    /// Mark as landed those available those carriers which are no longer in flight
+    fn process_landings(&mut self, current_time: u64) {
+        let active_flights = std::mem::take(&mut self.active_flights);
+        let (finished, still_active): (Vec<Flight>, Vec<Flight>) =
+            active_flights.into_iter().partition_map(|flight| {
+                use std::cmp::Ordering::*;
+
+                match self.end_time(&flight).cmp(&current_time) {
+                    Less | Equal => Either::Left(flight),
+                    Greater => Either::Right(flight),
+                }
+            });
+
+        self.active_flights = still_active;
+        for flight in finished {
+            if let Some(last_stop) = flight.orders.last().filter(|_| !flight.returns()) {
+                self.parked.insert(flight.carrier, last_stop.destination);
+            }
+            self.idle_carriers.push(flight.carrier);
+        }
+    }
+}
+
+/// Ground vehicles making deliveries alongside a scheduler's carriers, see
+/// `NaiveScheduler::with_ground_fleet`. Vehicles drive along the road grid, measured by
+/// `Manhattan` distance.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GroundFleet {
+    /// Number of vehicles
+    pub vehicles: usize,
+    /// Speed in meters per second of the vehicles
+    pub speed_mps: u64,
+    /// Max range in meters the vehicles can drive
+    pub range_m: u64,
+}
+
+impl GroundFleet {
+    pub const DEFAULT_SPEED_MPS: u64 = 15;
+    pub const DEFAULT_RANGE_M: u64 = 60_000;
+}
+
+impl Default for GroundFleet {
+    fn default() -> Self {
+        Self {
+            vehicles: 0,
+            speed_mps: Self::DEFAULT_SPEED_MPS,
+            range_m: Self::DEFAULT_RANGE_M,
+        }
+    }
+}
+
+/// Parses a fleet as `vehicles[, speed_mps, range_m]`, e.g. `4` or `4, 12, 40000`
+impl std::str::FromStr for GroundFleet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid ground fleet `{}`, expected `vehicles[, speed_mps, range_m]`",
+                s
+            )
+        };
+        let values = s
+            .split(',')
+            .map(|value| value.trim().parse::<u64>().map_err(|_| invalid()))
+            .collect::<Result<Vec<_>, _>>()?;
+        match values[..] {
+            [vehicles] => Ok(Self {
+                vehicles: vehicles as usize,
+                ..Self::default()
+            }),
+            [vehicles, speed_mps, range_m] if speed_mps > 0 => Ok(Self {
+                vehicles: vehicles as usize,
+                speed_mps,
+                range_m,
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// State handed from a scheduler to the one replacing it, see `NaiveScheduler::take_over`
+#[derive(Clone)]
+pub struct Handover {
+    origin: Destination,
+    return_policy: ReturnPolicy,
+    parked: HashMap<CarrierId, DestinationId>,
+    fleet: BTreeMap<CarrierId, CarrierMode>,
+    max_orders_per_carrier: usize,
+    carrier_speed_mps: u64,
+    carrier_range_m: u64,
+    ground: GroundFleet,
+    flight_phases: FlightPhases,
+    packing: Arc<dyn PackingStrategy>,
+    route_planner: RoutePlanner,
+    energy: EnergyModel,
+    unfulfilled_orders: BinaryHeap<PendingOrder>,
+    fairness: Option<u64>,
+    last_served: HashMap<DestinationId, u64>,
+    orders_queued: u64,
+    active_flights: Vec<Flight>,
+    missed_windows: Vec<Order>,
+    spoiled: Vec<Order>,
+    closed_deferrals: Vec<Order>,
+    deactivated: HashSet<DestinationId>,
+    rejected_orders: Vec<Order>,
+    surge: Option<SurgeDetector>,
+    commandeered: Vec<Flight>,
+    plan: Option<Vec<Flight>>,
+}
+
+impl<M> From<NaiveScheduler<M>> for Handover {
+    fn from(scheduler: NaiveScheduler<M>) -> Self {
+        Self {
+            origin: scheduler.origin,
+            return_policy: scheduler.return_policy,
+            parked: scheduler.parked,
+            fleet: scheduler.fleet,
+            max_orders_per_carrier: scheduler.max_orders_per_carrier,
+            carrier_speed_mps: scheduler.carrier_speed_mps,
+            carrier_range_m: scheduler.carrier_range_m,
+            ground: scheduler.ground,
+            flight_phases: scheduler.flight_phases,
+            packing: scheduler.packing,
+            route_planner: scheduler.route_planner,
+            energy: scheduler.energy,
+            unfulfilled_orders: scheduler.unfulfilled_orders,
+            fairness: scheduler.fairness,
+            last_served: scheduler.last_served,
+            orders_queued: scheduler.orders_queued,
+            active_flights: scheduler.active_flights,
+            missed_windows: scheduler.missed_windows,
+            spoiled: scheduler.spoiled,
+            closed_deferrals: scheduler.closed_deferrals,
+            deactivated: scheduler.deactivated,
+            rejected_orders: scheduler.rejected_orders,
+            surge: scheduler.surge,
+            commandeered: scheduler.commandeered,
+            plan: scheduler.plan,
+        }
+    }
+}
+
+/// Everything a simulation needs from a scheduler beyond `DynScheduler`, so that schedulers chosen
+/// at runtime can be driven through a `Box<dyn SimulatedScheduler>`
+pub trait SimulatedScheduler: schema::DynScheduler {
+    fn active_flights(&self) -> Box<dyn Iterator<Item = &Flight> + '_>;
+
+    /// Returns the identifiers of all carriers controlled by the scheduler
+    fn carriers(&self) -> Box<dyn Iterator<Item = CarrierId> + '_>;
+
+    fn origin(&self) -> &Destination;
+
+    fn max_orders_per_carrier(&self) -> usize;
+
+    fn carrier_speed_mps(&self) -> u64;
+
+    fn carrier_range_m(&self) -> u64;
+
+    fn delivery_times(&self, flight: &Flight) -> Vec<u64>;
+
+    fn flight_distance(&self, flight: &Flight) -> f32;
+
+    fn flight_energy_j(&self, flight: &Flight) -> Option<f32>;
+
+    fn end_time(&self, flight: &Flight) -> u64;
+
+    fn position(&self, flight: &Flight, current_time: u64) -> (f32, f32);
+
+    fn hold_launch(&mut self, carrier: CarrierId, launch_time: u64) -> Option<&Flight>;
+
+    fn return_to_base(&mut self, carrier: CarrierId, current_time: u64) -> Option<&Flight>;
+
+    fn take_missed_windows(&mut self) -> Vec<Order>;
+
+    fn take_spoiled(&mut self) -> Vec<Order>;
+
+    fn take_deferred(&mut self) -> Vec<Order>;
+
+    fn take_explanations(&mut self) -> Vec<FlightExplanation>;
+
+    fn update_destinations(&mut self, changes: &DestinationChanges);
+
+    fn take_rejected_orders(&mut self) -> Vec<Order>;
+
+    fn is_surging(&self) -> bool;
+
+    fn take_commandeered(&mut self) -> Vec<Flight>;
+
+    fn preview_flights(&self, extra_orders: Vec<Order>, current_time: u64) -> Vec<Flight>;
+
+    /// See `NaiveScheduler::set_fleet_size`
+    fn set_fleet_size(&mut self, carriers: usize) -> usize;
+
+    /// Gives up the scheduler's queued orders & in-flight carriers to its replacement
+    fn hand_over(self: Box<Self>) -> Handover;
+
+    /// Returns a copy of everything the scheduler would hand over, to be restored later
+    fn checkpoint(&self) -> Handover;
+
+    /// Takes over from a checkpoint of another scheduler, see `NaiveScheduler::take_over`
+    fn restore(self: Box<Self>, checkpoint: Handover) -> Box<dyn SimulatedScheduler>;
+}
+
+impl<M: DistanceMetric + Clone + 'static> SimulatedScheduler for NaiveScheduler<M> {
+    fn active_flights(&self) -> Box<dyn Iterator<Item = &Flight> + '_> {
+        Box::new(NaiveScheduler::active_flights(self))
+    }
+
+    fn carriers(&self) -> Box<dyn Iterator<Item = CarrierId> + '_> {
+        Box::new(NaiveScheduler::carriers(self))
+    }
+
+    fn origin(&self) -> &Destination {
+        NaiveScheduler::origin(self)
+    }
+
+    fn max_orders_per_carrier(&self) -> usize {
+        NaiveScheduler::max_orders_per_carrier(self)
+    }
+
+    fn carrier_speed_mps(&self) -> u64 {
+        NaiveScheduler::carrier_speed_mps(self)
+    }
+
+    fn carrier_range_m(&self) -> u64 {
+        NaiveScheduler::carrier_range_m(self)
+    }
+
+    fn delivery_times(&self, flight: &Flight) -> Vec<u64> {
+        NaiveScheduler::delivery_times(self, flight)
+    }
+
+    fn flight_distance(&self, flight: &Flight) -> f32 {
+        NaiveScheduler::flight_distance(self, flight)
+    }
+
+    fn flight_energy_j(&self, flight: &Flight) -> Option<f32> {
+        NaiveScheduler::flight_energy_j(self, flight)
+    }
+
+    fn end_time(&self, flight: &Flight) -> u64 {
+        NaiveScheduler::end_time(self, flight)
+    }
+
+    fn position(&self, flight: &Flight, current_time: u64) -> (f32, f32) {
+        NaiveScheduler::position(self, flight, current_time)
+    }
+
+    fn hold_launch(&mut self, carrier: CarrierId, launch_time: u64) -> Option<&Flight> {
+        NaiveScheduler::hold_launch(self, carrier, launch_time)
+    }
+
+    fn return_to_base(&mut self, carrier: CarrierId, current_time: u64) -> Option<&Flight> {
+        NaiveScheduler::return_to_base(self, carrier, current_time)
+    }
+
+    fn take_missed_windows(&mut self) -> Vec<Order> {
+        NaiveScheduler::take_missed_windows(self)
+    }
+
+    fn take_spoiled(&mut self) -> Vec<Order> {
+        NaiveScheduler::take_spoiled(self)
+    }
+
+    fn take_deferred(&mut self) -> Vec<Order> {
+        NaiveScheduler::take_deferred(self)
+    }
+
+    fn take_explanations(&mut self) -> Vec<FlightExplanation> {
+        NaiveScheduler::take_explanations(self)
+    }
+
+    fn update_destinations(&mut self, changes: &DestinationChanges) {
+        NaiveScheduler::update_destinations(self, changes)
+    }
+
+    fn take_rejected_orders(&mut self) -> Vec<Order> {
+        NaiveScheduler::take_rejected_orders(self)
+    }
+
+    fn is_surging(&self) -> bool {
+        NaiveScheduler::is_surging(self)
+    }
+
+    fn take_commandeered(&mut self) -> Vec<Flight> {
+        NaiveScheduler::take_commandeered(self)
+    }
+
+    fn preview_flights(&self, extra_orders: Vec<Order>, current_time: u64) -> Vec<Flight> {
+        NaiveScheduler::preview_flights(self, extra_orders, current_time)
+    }
+
+    fn set_fleet_size(&mut self, carriers: usize) -> usize {
+        NaiveScheduler::set_fleet_size(self, carriers)
+    }
+
+    fn hand_over(self: Box<Self>) -> Handover {
+        (*self).into()
+    }
+
+    fn checkpoint(&self) -> Handover {
+        Handover {
+            origin: self.origin.clone(),
+            return_policy: self.return_policy,
+            parked: self.parked.clone(),
+            fleet: self.fleet.clone(),
+            max_orders_per_carrier: self.max_orders_per_carrier,
+            carrier_speed_mps: self.carrier_speed_mps,
+            carrier_range_m: self.carrier_range_m,
+            ground: self.ground,
+            flight_phases: self.flight_phases,
+            packing: self.packing.clone(),
+            route_planner: self.route_planner,
+            energy: self.energy,
+            unfulfilled_orders: self.unfulfilled_orders.clone(),
+            fairness: self.fairness,
+            last_served: self.last_served.clone(),
+            orders_queued: self.orders_queued,
+            active_flights: self.active_flights.clone(),
+            missed_windows: self.missed_windows.clone(),
+            spoiled: self.spoiled.clone(),
+            closed_deferrals: self.closed_deferrals.clone(),
+            deactivated: self.deactivated.clone(),
+            rejected_orders: self.rejected_orders.clone(),
+            surge: self.surge.clone(),
+            commandeered: self.commandeered.clone(),
+            plan: self.plan.clone(),
+        }
+    }
+
+    fn restore(self: Box<Self>, checkpoint: Handover) -> Box<dyn SimulatedScheduler> {
+        Box::new((*self).take_over(checkpoint))
+    }
+}
+
+/// An order waiting to be packed into a flight
+#[derive(Clone, Debug)]
+struct PendingOrder {
+    order: Order,
+    /// Position of the order in the queue, for orders placed at the same time
+    sequence: u64,
+    /// Whether the order's destination has been starved of flights, see `with_fairness`
+    boosted: bool,
+}
+
+/// Emergencies are the most urgent, followed by the orders of starved destinations, then
+/// perishables so that they're packed into the earliest stops, then the orders which have been
+/// waiting the longest
+impl Ord for PendingOrder {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let is_emergency = |pending: &Self| matches!(pending.order.priority, Priority::Emergency);
+        let is_perishable = |pending: &Self| pending.order.shelf_life.is_some();
+
+        is_emergency(self)
+            .cmp(&is_emergency(other))
+            .then_with(|| self.boosted.cmp(&other.boosted))
+            .then_with(|| is_perishable(self).cmp(&is_perishable(other)))
+            .then_with(|| other.order.time.cmp(&self.order.time))
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+impl PartialOrd for PendingOrder {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for PendingOrder {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for PendingOrder {}
+
+/// Iterator over the orders queued by a `NaiveScheduler`, in no particular order
+pub struct UnfulfilledOrders<'a>(binary_heap::Iter<'a, PendingOrder>);
+
+impl<'a> Iterator for UnfulfilledOrders<'a> {
+    type Item = &'a Order;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|pending| &pending.order)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<M: DistanceMetric> Scheduler for NaiveScheduler<M> {
+    type UnfulfilledOrders<'a>
+        = UnfulfilledOrders<'a>
+    where
+        Self: 'a;
+    type LaunchedFlights<'a>
+        = slice::Iter<'a, Flight>
+    where
+        Self: 'a;
+
+    fn unfulfilled_orders(&self) -> Self::UnfulfilledOrders<'_> {
+        UnfulfilledOrders(self.unfulfilled_orders.iter())
+    }
+
+    fn queue_order(&mut self, order: Order) {
+        if let Some(surge) = self
+            .surge
+            .as_mut()
+            .filter(|_| matches!(order.priority, Priority::Emergency))
+        {
+            surge.observe(order.time);
+        }
+        self.requeue(order);
+    }
+
+    fn launch_flights(&mut self, current_time: u64) -> slice::Iter<'_, Flight> {
+        let span = tracing::debug_span!(
+            "launch_flights",
+            time = current_time,
+            queued = self.unfulfilled_orders.len(),
+            launched = tracing::field::Empty,
+        );
+        let _entered = span.enter();
+        self.process_landings(current_time);
+
+        let num_in_flight = self.active_flights.len();
+        if self.follow_plan(current_time) {
+            self.record_served(num_in_flight, current_time);
+            span.record("launched", self.active_flights.len() - num_in_flight);
+            return self.active_flights[num_in_flight..].iter();
+        }
+        self.boost_starved(current_time);
+
+        let surging = self
+            .surge
+            .as_mut()
+            .is_some_and(|surge| surge.update(current_time));
+        let emergency_waiting = matches!(
+            self.unfulfilled_orders.peek(),
+            Some(pending) if matches!(pending.order.priority, Priority::Emergency)
+        );
+        if surging && emergency_waiting {
+            self.commandeer_held_flights(current_time);
+        }
+
+        // Reserve a certain number of carriers to use for emergency orders, unless in a surge.
+        // Aerial carriers are held back first, being the faster.
+        let modes = [CarrierMode::Aerial, CarrierMode::Ground];
+        let mut available_carriers = modes.map(|mode| self.available_carriers(mode));
+        let mut held_carriers = [0; 2];
+        if !surging && !emergency_waiting {
+            let mut reserved = self.reserved_carriers(current_time);
+            for (available, held) in available_carriers.iter_mut().zip(&mut held_carriers) {
+                *held = reserved.min(*available);
+                *available -= *held;
+                reserved -= *held;
+            }
+        }
+        let mut trace = PackingTrace::new(
+            current_time,
+            self.unfulfilled_orders.len(),
+            available_carriers,
+            held_carriers,
+        );
+
+        // Bins for each mode, indexed by the mode, & the carriers they're launched on in turn
+        let mut bins = available_carriers.map(Bins::new);
+        let bin_carriers = modes.map(|mode| self.launch_order(mode));
+
+        // Orders which can't be delivered within their window, or before spoiling, by any flight
+        // launched now
+        let mut deferred = vec![];
+
+        // Pack orders into the bins, most urgent first, until reaching an order that doesn't fit
+        while let Some(pending) = self.unfulfilled_orders.pop() {
+            // Resupplies wait for a surge to end, leaving every carrier for emergencies
+            if surging && matches!(pending.order.priority, Priority::Resupply) {
+                self.unfulfilled_orders.push(pending);
+                trace.stop(PackingStop::Surge);
+                break;
+            }
+            trace.consider(&pending.order);
+
+            let order = &pending.order;
+            let destination = self
+                .destinations
+                .get(&order.destination)
+                .expect("destination");
+
+            // Give up on orders which would miss their window even when flown directly
+            let preferred_modes = self.preferred_modes(order, destination, current_time);
+            let direct_arrival = preferred_modes
+                .iter()
+                .map(|mode| self.direct_arrival(*mode, destination, current_time))
+                .min()
+                .expect("mode");
+            if order
+                .latest_delivery
+                .is_some_and(|latest| direct_arrival > latest)
+            {
+                trace.give_up(PackingOutcome::MissedWindow);
+                self.missed_windows.push(pending.order);
+                continue;
+            }
+            // ...or spoil, their flight launching now
+            if order.spoils(current_time, direct_arrival) {
+                trace.give_up(PackingOutcome::Spoiled);
+                self.spoiled.push(pending.order);
+                continue;
+            }
+
+            let mut fits_outside_window = false;
+            // Whether the order would be flown in time, but reach its destination while it's closed
+            let mut closed = false;
+            // Bin with room for only some of the order's packages, which the order is split across
+            // if it doesn't fit whole anywhere
+            let mut split = None;
+            let packed = preferred_modes.into_iter().find_map(|mode| {
+                let bins = &bins[mode as usize];
+                let (_, speed_mps) = self.travel(mode);
+                self.packing.candidates(bins, order).find_map(|i| {
+                    let bin = bins.get(i);
+                    let start = self.carrier_location(bin_carriers[mode as usize][i]);
+                    let Some(distance) = self.leg_distance(bin, start, destination, mode) else {
+                        trace.reject(
+                            mode,
+                            i,
+                            match bin.packages >= self.max_orders_per_carrier {
+                                true => PackingOutcome::Full,
+                                false => PackingOutcome::OutOfRange,
+                            },
+                        );
+                        return None;
+                    };
+                    let arrival = current_time
+                        + (bin.distance_allocated + distance) / speed_mps
+                        + (bin.orders.len() as u64 + 1) * self.flight_phases.per_stop_s();
+                    let fresh = !order.spoils(current_time, arrival);
+                    if !fresh || !order.accepts_delivery_at(arrival) {
+                        trace.reject(mode, i, PackingOutcome::Late);
+                        fits_outside_window = true;
+                        return None;
+                    }
+                    if !destination.is_open_at(arrival) {
+                        trace.reject(mode, i, PackingOutcome::DestinationClosed);
+                        closed = true;
+                        return None;
+                    }
+                    let room = (self.max_orders_per_carrier - bin.packages) as u32;
+                    if room < order.quantity {
+                        trace.reject(mode, i, PackingOutcome::Full);
+                        split.get_or_insert((mode, i, distance, room));
+                        return None;
+                    }
+                    Some((mode, i, distance, order.quantity))
+                })
+            });
+
+            match packed.or(split) {
+                Some((mode, i, distance, packages)) => {
+                    trace.pack(mode, i);
+                    let mut pending = pending;
+                    if packages < pending.order.quantity {
+                        // The rest of the order's packages are considered next, for another bin
+                        let mut rest = pending.clone();
+                        rest.order.quantity -= packages;
+                        pending.order.quantity = packages;
+                        self.unfulfilled_orders.push(rest);
+                    }
+                    bins[mode as usize].push(i, pending.order, distance);
+                }
+                // Wait for a later launch which arrives within the window, or a flight with fewer stops
+                None if fits_outside_window => deferred.push(pending),
+                // ...or once its destination opens
+                None if closed => {
+                    self.closed_deferrals.push(pending.order.clone());
+                    deferred.push(pending);
+                }
+                None => {
+                    // Leave the order queued for a later launch
+                    trace.stop(PackingStop::DidNotFit(pending.order.id));
+                    self.unfulfilled_orders.push(pending);
+                    break;
+                }
+            }
+        }
+        self.unfulfilled_orders.extend(deferred);
+
+        let num_in_flight = self.active_flights.len();
+
+        // Map packed bins to flights and add them to the active list
+        let mut packed_from = vec![];
+        for (mode, bins) in modes.into_iter().zip(bins) {
+            for (i, bin) in bins
+                .into_bins()
+                .into_iter()
+                .enumerate()
+                .filter(|(_, bin)| !bin.orders.is_empty())
+            {
+                let carrier = bin_carriers[mode as usize][i];
+                self.take_idle_carrier(carrier);
+                let mut flight = Flight {
+                    id: FlightId::new(),
+                    carrier,
+                    launch_time: current_time,
+                    orders: bin.orders,
+                    diversion: None,
+                    phases: self.flight_phases,
+                    mode,
+                    departs_from: self.parked.remove(&carrier),
+                    return_policy: self.return_policy,
+                };
+                if self.route_planner == RoutePlanner::Energy && mode == CarrierMode::Aerial {
+                    self.plan_least_energy(&mut flight);
+                }
+                packed_from.push((mode, i));
+                self.active_flights.push(flight);
+            }
+        }
+        let launched = packed_from
+            .into_iter()
+            .zip(&self.active_flights[num_in_flight..])
+            .map(|((mode, i), flight)| (mode, i, flight))
+            .collect::<Vec<_>>();
+        self.explanations
+            .extend(trace.explain(&launched, self.max_orders_per_carrier));
+        self.record_served(num_in_flight, current_time);
+        span.record("launched", self.active_flights.len() - num_in_flight);
+        self.active_flights[num_in_flight..].iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use schema::testkit::{self, Harness};
+    use schema::{FlightPhase, OrderId, ServiceTime};
+
+    use super::*;
+
+    #[test]
+    fn test_packing() {
+        let destinations = testkit::destinations(&[
+            ("Near", 1_000, 0),
+            ("Far", 70_000, 0),
+            ("Unreachable", 0, 200_000),
+        ]);
+        let mut harness = Harness::new(NaiveScheduler::new(destinations, 3, 2, 30, 160_000));
+
+        // Resupplies are packed together, leaving the rest of the carriers in reserve
+        harness
+            .at(0)
+            .resupply("Near")
+            .resupply("Near")
+            .resupply("Near");
+        assert_eq!(harness.at(60).launch().len(), 1);
+        harness.assert_launched_to(&["Near", "Near"]);
+        harness.assert_unfulfilled(1);
+
+        // Emergencies are delivered first, with resupplies filling any remaining space
+        harness.emergency("Far");
+        assert_eq!(harness.at(120).launch().len(), 1);
+        harness.assert_launched_to(&["Far", "Near"]);
+        harness.assert_unfulfilled(0);
+
+        // Orders which can't be packed remain queued
+        harness.resupply("Unreachable");
+        assert!(harness.launch_every(60, 600).is_empty());
+        harness.assert_unfulfilled(1);
+    }
+
+    #[test]
+    fn test_quantities() {
+        let destinations = testkit::destinations(&[("Near", 1_000, 0)]);
+        let order = |id, quantity, priority| Order {
+            id: OrderId(id),
+            quantity,
+            ..testkit::order(0, "Near", priority)
+        };
+
+        // Orders of more packages than a carrier has room for are split across flights
+        let mut harness =
+            Harness::new(NaiveScheduler::new(destinations.clone(), 3, 2, 30, 160_000));
+        harness
+            .scheduler_mut()
+            .queue_order(order(1, 5, Priority::Emergency));
+        let flights = harness.at(60).launch();
+        let mut packages = flights
+            .iter()
+            .map(|flight| {
+                assert!(flight.orders.iter().all(|order| order.id == OrderId(1)));
+                flight
+                    .orders
+                    .iter()
+                    .map(|order| order.quantity)
+                    .sum::<u32>()
+            })
+            .collect::<Vec<_>>();
+        packages.sort();
+        assert_eq!(packages, vec![1, 2, 2]);
+        harness.assert_unfulfilled(0);
+
+        // ...though they're packed whole where a carrier has room for them
+        let mut harness = Harness::new(NaiveScheduler::new(destinations, 2, 2, 30, 160_000));
+        harness
+            .scheduler_mut()
+            .queue_order(order(1, 1, Priority::Emergency));
+        harness
+            .scheduler_mut()
+            .queue_order(order(2, 2, Priority::Resupply));
+        let flights = harness.at(60).launch();
+        assert_eq!(flights.len(), 2);
+        assert!(flights
+            .iter()
+            .any(|flight| flight.orders == vec![order(2, 2, Priority::Resupply)]));
+    }
+
+    #[test]
+    fn test_explanations() {
+        let destinations =
+            testkit::destinations(&[("Near", 1_000, 0), ("Unreachable", 0, 200_000)]);
+        let mut harness = Harness::new(NaiveScheduler::new(destinations, 3, 2, 30, 160_000));
+        let outcomes = |explanation: &FlightExplanation| {
+            explanation
+                .considered
+                .iter()
+                .map(|considered| considered.outcome)
+                .collect::<Vec<_>>()
+        };
+
+        // The carrier left with room for two resupplies, the rest being held in reserve
+        harness
+            .at(0)
+            .resupply("Near")
+            .resupply("Near")
+            .resupply("Near");
+        let launched = harness.at(60).launch();
+        let explanations = harness.scheduler_mut().take_explanations();
+        assert_eq!(explanations.len(), 1);
+        let explanation = &explanations[0];
+        assert_eq!(explanation.flight, launched[0].id);
+        assert_eq!((explanation.queued, explanation.available_carriers), (3, 1));
+        assert_eq!(explanation.reserved_carriers, 2);
+        assert_eq!(
+            outcomes(explanation),
+            vec![
+                PackingOutcome::Packed,
+                PackingOutcome::Packed,
+                PackingOutcome::Full
+            ]
+        );
+        assert!(matches!(explanation.stopped, PackingStop::DidNotFit(_)));
+
+        // Emergencies release the reserves, but none has the range for the unreachable one
+        harness.emergency("Near").emergency("Unreachable");
+        let launched = harness.at(120).launch();
+        let explanations = harness.scheduler_mut().take_explanations();
+        assert_eq!(launched.len(), 1);
+        assert_eq!(explanations[0].reserved_carriers, 0);
+        assert_eq!(
+            outcomes(&explanations[0]),
+            vec![PackingOutcome::Packed, PackingOutcome::OutOfRange]
+        );
+    }
+
+    #[test]
+    fn test_queue_order() {
+        let destinations = testkit::destinations(&[("Near", 1_000, 0), ("Far", 70_000, 0)]);
+        let mut harness = Harness::new(NaiveScheduler::new(destinations, 3, 1, 30, 160_000));
+
+        // With a single carrier available, the order which has waited longest goes first
+        harness.at(0).resupply("Far");
+        harness.at(30).resupply("Near");
+        harness.at(60).launch();
+        harness.assert_launched_to(&["Far"]);
+
+        // Emergencies release the reserved carriers for every queued order
+        harness.at(20_000).resupply("Far").emergency("Far");
+        assert_eq!(harness.launch().len(), 3);
+        harness.assert_unfulfilled(0);
+    }
+
+    #[test]
+    fn test_plan() {
+        let destinations = testkit::destinations(&[("Near", 1_000, 0), ("Far", 70_000, 0)]);
+        let planned = |carrier, destination| Flight {
+            carrier: CarrierId(carrier),
+            launch_time: 60,
+            orders: vec![testkit::order(0, destination, Priority::Resupply)],
+            ..Default::default()
+        };
+        let plan = vec![planned(0, "Near"), planned(1, "Far")];
+        let mut harness =
+            Harness::new(NaiveScheduler::new(destinations, 3, 2, 30, 160_000).with_plan(plan));
+
+        // Planned flights are launched as planned, rather than packing the orders together
+        harness.at(0).resupply("Near").resupply("Far");
+        assert!(harness.at(30).launch().is_empty());
+        let launched = harness.at(60).launch();
+        assert_eq!(
+            launched
+                .iter()
+                .map(|flight| flight.carrier)
+                .collect::<Vec<_>>(),
+            vec![CarrierId(0), CarrierId(1)]
+        );
+        harness.assert_unfulfilled(0);
+
+        // Orders which weren't planned for are packed as usual
+        harness.at(120).emergency("Near");
+        assert_eq!(harness.at(180).launch().len(), 1);
+        harness.assert_launched_to(&["Near"]);
+    }
+
+    #[test]
+    fn test_delivery_windows() {
+        let destinations = testkit::destinations(&[("Near", 1_000, 0), ("Far", 70_000, 0)]);
+        let mut harness = Harness::new(NaiveScheduler::new(destinations, 3, 2, 30, 160_000));
+        let scheduler = harness.scheduler_mut();
+        scheduler.queue_order(Order {
+            earliest_delivery: Some(600),
+            ..testkit::order(0, "Near", Priority::Emergency)
+        });
+        scheduler.queue_order(Order {
+            latest_delivery: Some(600),
+            ..testkit::order(0, "Far", Priority::Emergency)
+        });
+
+        // Orders aren't launched into flights arriving before their window opens
+        assert!(harness.at(0).launch().is_empty());
+        harness.assert_unfulfilled(1);
+
+        // Orders which can't arrive before their window closes are given up on
+        let missed = harness.scheduler_mut().take_missed_windows();
+        assert_eq!(missed.len(), 1);
+        assert_eq!(missed[0].destination, DestinationId::from("Far"));
+
+        assert_eq!(harness.at(600).launch().len(), 1);
+        harness.assert_launched_to(&["Near"]);
+    }
+
+    #[test]
+    fn test_perishables() {
+        let destinations = testkit::destinations(&[("Mid", 20_000, 0), ("Far", 70_000, 0)]);
+        let mut harness = Harness::new(NaiveScheduler::new(destinations, 3, 2, 30, 160_000));
+        let scheduler = harness.scheduler_mut();
+        scheduler.queue_order(testkit::order(0, "Far", Priority::Resupply));
+        scheduler.queue_order(Order {
+            shelf_life: Some(15 * 60),
+            ..testkit::order(60, "Mid", Priority::Resupply)
+        });
+        scheduler.queue_order(Order {
+            shelf_life: Some(60),
+            ..testkit::order(60, "Far", Priority::Resupply)
+        });
+
+        // Perishables are dropped off before the older resupply
+        let flights = harness.at(60).launch();
+        assert_eq!(flights.len(), 1);
+        let stops = flights[0]
+            .orders
+            .iter()
+            .map(|order| order.destination.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(stops, vec!["Mid", "Far"]);
+        assert!(flights[0].orders[0].shelf_life.is_some());
+
+        // Orders which would spoil even when flown directly are given up on
+        let spoiled = harness.scheduler_mut().take_spoiled();
+        assert_eq!(spoiled.len(), 1);
+        assert_eq!(spoiled[0].shelf_life, Some(60));
+        harness.assert_unfulfilled(0);
+    }
+
+    #[test]
+    fn test_fairness() {
+        let destinations = testkit::destinations(&[("Mid", 20_000, 0), ("Far", 70_000, 0)]);
+        let scheduler = NaiveScheduler::new(destinations, 3, 1, 30, 160_000);
+        let perishable = |time| Order {
+            shelf_life: Some(60 * 60),
+            ..testkit::order(time, "Mid", Priority::Resupply)
+        };
+        let run = |scheduler: NaiveScheduler| {
+            let mut harness = Harness::new(scheduler);
+            let scheduler = harness.scheduler_mut();
+            scheduler.queue_order(testkit::order(0, "Far", Priority::Resupply));
+            scheduler.queue_order(perishable(0));
+            assert_eq!(harness.at(0).launch().len(), 1);
+            harness.scheduler_mut().queue_order(perishable(1_400));
+            let flights = harness.at(1_400).launch();
+            assert_eq!(flights.len(), 1);
+            flights[0].orders[0].destination.to_string()
+        };
+
+        // Perishables keep the only carrier free of reserve away from the older resupply...
+        assert_eq!(run(scheduler.clone()), "Mid");
+        // ...unless its destination has been starved of flights for long enough
+        assert_eq!(run(scheduler.clone().with_fairness(600)), "Far");
+        assert_eq!(run(scheduler.with_fairness(3_600)), "Mid");
+    }
+
+    #[test]
+    fn test_preview_flights() {
+        let destinations = testkit::destinations(&[("Near", 1_000, 0), ("Far", 70_000, 0)]);
+        let mut harness = Harness::new(NaiveScheduler::new(destinations, 3, 2, 30, 160_000));
+        harness.at(0).resupply("Near");
+
+        // A hypothetical emergency would be launched immediately, along with the queued resupply
+        let preview = harness
+            .scheduler()
+            .preview_flights(vec![testkit::order(0, "Far", Priority::Emergency)], 0);
+        assert_eq!(preview.len(), 1);
+        assert_eq!(preview[0].orders.len(), 2);
+
+        // Nothing was committed
+        harness.assert_unfulfilled(1);
+        assert_eq!(harness.scheduler().active_flights().count(), 0);
+        assert_eq!(harness.launch().len(), 1);
+    }
+
+    #[test]
+    fn test_take_over() {
+        let destinations = testkit::destinations(&[("Far", 70_000, 0)]);
+        let scheduler = || NaiveScheduler::new(destinations.clone(), 3, 1, 30, 160_000);
+
+        let mut harness = Harness::new(scheduler());
+        harness.at(0).emergency("Far").emergency("Far");
+        assert_eq!(harness.at(60).launch().len(), 2);
+
+        // Carriers which are still in flight aren't available to the new scheduler
+        let mut harness = Harness::new(scheduler().take_over(harness.into_scheduler()));
+        assert_eq!(harness.scheduler().active_flights().count(), 2);
+        harness.at(120).emergency("Far").emergency("Far");
+        assert_eq!(harness.launch().len(), 1);
+        harness.assert_unfulfilled(1);
+
+        // The carriers keep their capacity, speed & range under the new scheduler
+        let previous = NaiveScheduler::new(destinations.clone(), 3, 2, 40, 90_000);
+        let scheduler = scheduler().take_over(previous);
+        assert_eq!(scheduler.max_orders_per_carrier(), 2);
+        assert_eq!(scheduler.carrier_speed_mps(), 40);
+        assert_eq!(scheduler.carrier_range_m(), 90_000);
+    }
+
+    #[test]
+    fn test_take_over_closed_destination() {
+        let mut destinations = testkit::destinations(&[("Clinic", 3_000, 0)]);
+        for destination in destinations.values_mut() {
+            destination.hours = Some("06:00-20:00".parse().expect("hours"));
+        }
+        let scheduler = || NaiveScheduler::new(destinations.clone(), 3, 1, 30, 160_000);
+
+        // The order is deferred while the clinic is closed, the deferral surviving a swap of
+        // schedulers & a checkpoint
+        let mut harness = Harness::new(scheduler());
+        harness.at(0).emergency("Clinic");
+        assert!(harness.at(60).launch().is_empty());
+        let swapped = scheduler().take_over(harness.into_scheduler());
+        let checkpoint = SimulatedScheduler::checkpoint(&swapped);
+        let mut restored = Box::new(scheduler()).restore(checkpoint);
+        assert_eq!(restored.take_deferred().len(), 1);
+        assert_eq!(restored.unfulfilled_orders().count(), 1);
+
+        // ...& it's launched to arrive once the clinic opens
+        let mut harness = Harness::new(scheduler().take_over(swapped));
+        assert_eq!(harness.scheduler_mut().take_deferred().len(), 1);
+        assert_eq!(harness.at(21_600).launch().len(), 1);
+        assert!(harness.scheduler_mut().take_deferred().is_empty());
+    }
+
+    #[test]
+    fn test_update_destinations() {
+        let destinations = testkit::destinations(&[("Near", 1_000, 0)]);
+        let mut harness = Harness::new(NaiveScheduler::new(destinations, 3, 1, 30, 160_000));
+        harness.at(0).resupply("Near");
+
+        // The queued order for the deactivated destination is rejected, as are any later ones
+        let changes = DestinationChanges {
+            added: testkit::destinations(&[("New", 0, 2_000)])
+                .into_values()
+                .collect(),
+            deactivated: vec![DestinationId::from("Near")],
+        };
+        harness.scheduler_mut().update_destinations(&changes);
+        harness.at(30).emergency("Near").emergency("New");
+        assert_eq!(harness.scheduler_mut().take_rejected_orders().len(), 2);
+
+        harness.at(60).launch();
+        harness.assert_launched_to(&["New"]);
+        harness.assert_unfulfilled(0);
+    }
+
+    #[test]
+    fn test_dyn_scheduler() {
+        let destinations = testkit::destinations(&[("Near", 1_000, 0)]);
+        let scheduler: Box<dyn schema::DynScheduler> =
+            Box::new(NaiveScheduler::new(destinations, 3, 2, 30, 160_000));
+
+        // Boxed schedulers behave the same as the scheduler they wrap
+        let mut harness = Harness::new(scheduler);
+        harness.at(0).emergency("Near").resupply("Near");
+        assert_eq!(harness.at(60).launch().len(), 1);
+        harness.assert_launched_to(&["Near", "Near"]);
+        harness.assert_unfulfilled(0);
+    }
+
+    #[test]
+    fn test_origin() {
+        let destinations = testkit::destinations(&[("Near", 71_000, 0), ("Far", 142_000, 0)]);
+        let origin = Destination {
+            north_m: 70_000,
+            ..Destination::default_origin()
+        };
+        let mut harness =
+            Harness::new(NaiveScheduler::new(destinations, 3, 2, 30, 160_000).with_origin(origin));
+
+        // Distances are measured from the nest, which puts both destinations within range
+        harness.at(0).resupply("Near").resupply("Far");
+        assert_eq!(harness.at(60).launch().len(), 1);
+        harness.assert_launched_to(&["Near", "Far"]);
+
+        let flight = harness.scheduler().active_flights().next().expect("flight");
+        assert_eq!(harness.scheduler().flight_distance(flight), 144_000.0);
+        assert_eq!(harness.scheduler().end_time(flight), 60 + 4_800);
+    }
+
+    #[test]
+    fn test_return_policy() {
+        let destinations = testkit::destinations(&[("Near", 30_000, 0), ("Far", 90_000, 0)]);
+        let scheduler = NaiveScheduler::new(destinations, 1, 1, 30, 100_000);
+
+        // Carriers which must return don't have the range to reach the far destination...
+        let mut returning = Harness::new(scheduler.clone());
+        returning.at(0).emergency("Far");
+        assert!(returning.launch().is_empty());
+
+        // ...but those waiting there until their next flight do
+        let mut waiting = Harness::new(scheduler.with_return_policy(ReturnPolicy::WaitAtLastStop));
+        waiting.at(0).emergency("Far");
+        let flight = waiting.launch().remove(0);
+        assert!(!flight.returns());
+        assert_eq!(waiting.scheduler().flight_distance(&flight), 90_000.0);
+        assert_eq!(waiting.scheduler().end_time(&flight), 3_000);
+        assert_eq!(
+            waiting.scheduler().position(&flight, 4_000),
+            (0.0, 90_000.0)
+        );
+
+        // The carrier launches its next flight from where it waited, available as soon as it landed
+        waiting.at(3_000).emergency("Near");
+        let flight = waiting.launch().remove(0);
+        assert_eq!(flight.departs_from, Some(DestinationId::from("Far")));
+        assert_eq!(waiting.scheduler().delivery_times(&flight), vec![5_000]);
+        assert_eq!(waiting.scheduler().end_time(&flight), 5_000);
+
+        // Diverted carriers still fly home, from wherever they turned back
+        let flight = waiting
+            .scheduler_mut()
+            .return_to_base(CarrierId(0), 4_000)
+            .cloned()
+            .expect("diverted");
+        assert!(flight.returns());
+        assert_eq!(waiting.scheduler().end_time(&flight), 6_000);
+    }
+
+    #[test]
+    fn test_flight_phases() {
+        let destinations = testkit::destinations(&[("Near", 3_000, 0), ("Far", 6_000, 0)]);
+        let phases = FlightPhases {
+            takeoff_s: 20,
+            landing_s: 40,
+            ..Default::default()
+        };
+        let mut harness = Harness::new(
+            NaiveScheduler::new(destinations, 3, 2, 30, 160_000).with_flight_phases(phases),
+        );
+        harness.at(0).resupply("Near").resupply("Far");
+        assert_eq!(harness.at(60).launch().len(), 1);
+
+        // Each order is delivered once the carrier has landed, adding a minute for each stop
+        let scheduler = harness.scheduler();
+        let flight = scheduler.active_flights().next().expect("flight");
+        assert_eq!(flight.phases, phases);
+        assert_eq!(scheduler.delivery_times(flight), vec![60 + 160, 60 + 320]);
+        assert_eq!(scheduler.end_time(flight), 60 + 400 + 180);
+
+        let phase = |time| {
+            let position = flight.current_position(
+                &Euclidean,
+                &scheduler.destinations,
+                scheduler.origin(),
+                time,
+                30,
+            );
+            let phase = flight.phase(
+                &Euclidean,
+                &scheduler.destinations,
+                scheduler.origin(),
+                time,
+                30,
+            );
+            (position, phase)
+        };
+        assert_eq!(phase(70), ((0.0, 0.0, 2), FlightPhase::TakingOff));
+        assert_eq!(phase(130), ((0.0, 1_500.0, 2), FlightPhase::Cruising));
+        assert_eq!(phase(200), ((0.0, 3_000.0, 2), FlightPhase::Landing));
+        assert_eq!(phase(230), ((0.0, 3_000.0, 1), FlightPhase::TakingOff));
+        assert_eq!(phase(420), ((0.0, 5_400.0, 0), FlightPhase::Cruising));
+        assert_eq!(phase(620), ((0.0, 0.0, 0), FlightPhase::Landing));
+    }
+
+    #[test]
+    fn test_kinematics() {
+        let destinations = testkit::destinations(&[("Near", 3_000, 0), ("Far", 6_000, 0)]);
+        let phases = FlightPhases {
+            accelerate_s: 20,
+            decelerate_s: 20,
+            ..Default::default()
+        };
+        let mut harness = Harness::new(
+            NaiveScheduler::new(destinations, 3, 2, 30, 160_000).with_flight_phases(phases),
+        );
+        harness.at(0).resupply("Near").resupply("Far");
+        assert_eq!(harness.at(60).launch().len(), 1);
+
+        // Getting up to speed & slowing down for each stop adds twenty seconds to each leg
+        let scheduler = harness.scheduler();
+        let flight = scheduler.active_flights().next().expect("flight");
+        assert_eq!(scheduler.delivery_times(flight), vec![60 + 120, 60 + 240]);
+        assert_eq!(scheduler.end_time(flight), 60 + 400 + 60);
+
+        let position = |time| {
+            flight.current_position(
+                &Euclidean,
+                &scheduler.destinations,
+                scheduler.origin(),
+                time,
+                30,
+            )
+        };
+        // Accelerating away from the origin, cruising, then slowing for the first stop
+        assert_eq!(position(70), (0.0, 75.0, 2));
+        assert_eq!(position(120), (0.0, 1_500.0, 2));
+        assert_eq!(position(175), (0.0, 2_981.25, 2));
+        assert_eq!(position(180), (0.0, 3_000.0, 2));
+        assert_eq!(position(520), (0.0, 0.0, 0));
+    }
+
+    #[test]
+    fn test_service_times() {
+        let destinations =
+            testkit::destinations(&[("Near", 3_000, 0), ("Far", 6_000, 0), ("Edge", 79_500, 0)]);
+        let phases = FlightPhases {
+            service: "45".parse().expect("service time"),
+            ..Default::default()
+        };
+        let mut harness = Harness::new(
+            NaiveScheduler::new(destinations, 3, 2, 30, 160_000).with_flight_phases(phases),
+        );
+        harness.at(0).resupply("Near").resupply("Far");
+        assert_eq!(harness.at(60).launch().len(), 1);
+
+        // Each stop is served before the carrier moves on, though not the origin
+        let scheduler = harness.scheduler();
+        let flight = scheduler.active_flights().next().expect("flight");
+        assert_eq!(scheduler.delivery_times(flight), vec![60 + 145, 60 + 290]);
+        assert_eq!(scheduler.end_time(flight), 60 + 400 + 90);
+        let phase = |time| {
+            flight.phase(
+                &Euclidean,
+                &scheduler.destinations,
+                scheduler.origin(),
+                time,
+                30,
+            )
+        };
+        assert_eq!(phase(190), FlightPhase::Landing);
+        assert_eq!(phase(210), FlightPhase::Cruising);
+
+        // Time spent serving a stop eats into the range, so the edge can't be reached & served
+        harness.at(600).emergency("Edge");
+        assert!(harness.at(660).launch().is_empty());
+
+        // Sampled service times are the same wherever they're computed for a flight
+        let uniform = "20-60".parse::<ServiceTime>().expect("service time");
+        assert_eq!(
+            uniform,
+            ServiceTime::Uniform {
+                min_s: 20,
+                max_s: 60
+            }
+        );
+        let id = FlightId::new();
+        let samples = (0..20)
+            .map(|stop| uniform.sample(id, stop))
+            .collect::<Vec<_>>();
+        assert!(samples.iter().all(|s| (20..=60).contains(s)));
+        assert!(samples.iter().any(|s| *s != samples[0]));
+        assert_eq!(uniform.sample(id, 3), samples[3]);
+        assert!("60-20".parse::<ServiceTime>().is_err());
+    }
+
+    #[test]
+    fn test_ground_fleet() {
+        let destinations = testkit::destinations(&[("Corner", 3_000, 4_000), ("Far", 70_000, 0)]);
+        let ground = "1".parse::<GroundFleet>().expect("ground fleet");
+        let mut harness = Harness::new(
+            NaiveScheduler::new(destinations, 3, 2, 30, 160_000).with_ground_fleet(ground),
+        );
+        assert_eq!(harness.scheduler().carriers().count(), 4);
+
+        // Resupplies go by road where the vehicles have the range, otherwise by air
+        harness.at(0).resupply("Corner").resupply("Far");
+        let launched = harness.at(60).launch();
+        let mode = |destination: &str| {
+            launched
+                .iter()
+                .find(|flight| flight.orders[0].destination.as_str() == destination)
+                .map(|flight| (flight.carrier, flight.mode))
+        };
+        assert_eq!(mode("Corner"), Some((CarrierId(3), CarrierMode::Ground)));
+        assert_eq!(mode("Far"), Some((CarrierId(0), CarrierMode::Aerial)));
+
+        // Ground vehicles drive the road grid at their own speed
+        let scheduler = harness.scheduler();
+        let driven = launched
+            .iter()
+            .find(|flight| flight.mode == CarrierMode::Ground)
+            .expect("ground flight");
+        assert_eq!(scheduler.flight_distance(driven), 14_000.0);
+        assert_eq!(scheduler.delivery_times(driven), vec![60 + 7_000 / 15]);
+
+        // Emergencies go by whichever arrives first
+        harness.at(120).emergency("Corner");
+        assert_eq!(harness.at(180).launch()[0].mode, CarrierMode::Aerial);
+
+        assert_eq!(
+            "4, 12, 40000".parse::<GroundFleet>(),
+            Ok(GroundFleet {
+                vehicles: 4,
+                speed_mps: 12,
+                range_m: 40_000
+            })
+        );
+        assert!("4, 0, 40000".parse::<GroundFleet>().is_err());
+        assert!("four".parse::<GroundFleet>().is_err());
+    }
+
+    #[test]
+    fn test_set_fleet_size() {
+        let destinations = testkit::destinations(&[("Near", 1_000, 0)]);
+        let mut harness = Harness::new(NaiveScheduler::new(destinations, 3, 1, 30, 160_000));
+        let carriers = |harness: &Harness<NaiveScheduler>| {
+            harness
+                .scheduler()
+                .carriers()
+                .map(|carrier| carrier.0)
+                .collect::<Vec<_>>()
+        };
+        harness
+            .at(0)
+            .emergency("Near")
+            .emergency("Near")
+            .emergency("Near");
+        assert_eq!(harness.at(60).launch().len(), 3);
+
+        // The fleet never shrinks below the carriers in flight
+        assert_eq!(harness.scheduler_mut().set_fleet_size(1), 3);
+        assert_eq!(harness.scheduler_mut().set_fleet_size(5), 5);
+        assert_eq!(carriers(&harness), vec![0, 1, 2, 3, 4]);
+        assert_eq!(harness.scheduler_mut().set_fleet_size(2), 3);
+        assert_eq!(carriers(&harness), vec![0, 1, 2]);
+
+        // Carriers can be retired once they've landed, & added carriers launch like any other
+        harness.at(600).launch();
+        assert_eq!(harness.scheduler_mut().set_fleet_size(1), 1);
+        assert_eq!(carriers(&harness), vec![0]);
+        assert_eq!(harness.scheduler_mut().set_fleet_size(2), 2);
+        harness.at(660).emergency("Near").emergency("Near");
+        let launched = harness.at(720).launch();
+        assert_eq!(launched.len(), 2);
+        assert!(launched.iter().any(|flight| flight.carrier == CarrierId(1)));
+    }
+
+    #[test]
+    fn test_surge() {
+        let destinations = testkit::destinations(&[("Near", 1_000, 0), ("Far", 70_000, 0)]);
+        let policy = SurgePolicy {
+            threshold: std::num::NonZeroUsize::new(3).expect("threshold"),
+            window_s: 600,
+        };
+        let mut harness =
+            Harness::new(NaiveScheduler::new(destinations, 6, 2, 30, 160_000).with_surge(policy));
+        harness.at(0).resupply("Near").resupply("Near");
+        let held = harness.at(60).launch().remove(0);
+        harness.scheduler_mut().hold_launch(held.carrier, 900);
+
+        // A surge of emergencies takes back the held carrier, & resupplies wait for it to end
+        harness
+            .at(120)
+            .emergency("Near")
+            .emergency("Far")
+            .emergency("Far");
+        let launched = harness.at(180).launch();
+        assert!(harness.scheduler().is_surging());
+        let commandeered = harness.scheduler_mut().take_commandeered();
+        assert_eq!(commandeered.len(), 1);
+        assert_eq!(commandeered[0].carrier, held.carrier);
+        assert!(launched
+            .iter()
+            .flat_map(|flight| &flight.orders)
+            .all(|order| matches!(order.priority, Priority::Emergency)));
+        harness.assert_unfulfilled(2);
+
+        // Once the emergencies have left the window, resupplies are flown again
+        assert_eq!(harness.at(720).launch().len(), 1);
+        assert!(!harness.scheduler().is_surging());
+        harness.assert_launched_to(&["Near", "Near"]);
+        harness.assert_unfulfilled(0);
+    }
+
+    #[test]
+    fn test_energy_route_planner() {
+        let destinations = testkit::destinations(&[("Near", 0, 1_000), ("Far", 5_000, 0)]);
+        let energy = "10, 2, 1".parse::<EnergyModel>().expect("energy model");
+        let scheduler = |planner| {
+            NaiveScheduler::new(destinations.clone(), 3, 2, 30, 160_000)
+                .with_energy_model(energy)
+                .with_route_planner(planner)
+        };
+
+        // Packed as they were queued, the payload for Near is carried all the way to Far first
+        let mut packed = Harness::new(scheduler(RoutePlanner::Packed));
+        packed.at(0).resupply("Far").resupply("Near");
+        let flight = packed.at(60).launch().remove(0);
+        packed.assert_launched_to(&["Far", "Near"]);
+        let packed_j = packed.scheduler().flight_energy_j(&flight).expect("energy");
+        assert_eq!(packed_j.round(), 141_188.0);
+
+        // The energy planner drops it on the way instead, flying the same distance
+        let mut planned = Harness::new(scheduler(RoutePlanner::Energy));
+        planned.at(0).resupply("Far").resupply("Near");
+        let flight = planned.at(60).launch().remove(0);
+        planned.assert_launched_to(&["Near", "Far"]);
+        let planned_j = planned
+            .scheduler()
+            .flight_energy_j(&flight)
+            .expect("energy");
+        assert_eq!(planned_j.round(), 125_188.0);
+        assert_eq!(
+            planned.scheduler().flight_distance(&flight).round(),
+            11_099.0
+        );
+
+        // ...unless the detour would miss a delivery window
+        let mut windowed = Harness::new(scheduler(RoutePlanner::Energy));
+        windowed.scheduler_mut().queue_order(Order {
+            latest_delivery: Some(240),
+            ..testkit::order(0, "Far", Priority::Resupply)
+        });
+        windowed.at(0).resupply("Near");
+        windowed.at(60).launch();
+        windowed.assert_launched_to(&["Far", "Near"]);
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+
+use schema::{Sector, SectorOccupancy};
+
+/// Carriers flying in each sector of airspace at each simulated second, from which launches which
+/// would overfill a sector are held until it has room
+pub struct SectorMap<'a> {
+    sectors: &'a [Sector],
+    /// Number of carriers in each sector at each second, by index of the sector
+    occupied: HashMap<(u64, usize), usize>,
+    /// Last second at which any sector is occupied
+    last_time: u64,
+}
+
+impl<'a> SectorMap<'a> {
+    pub fn new(sectors: &'a [Sector]) -> Self {
+        Self {
+            sectors,
+            occupied: HashMap::new(),
+            last_time: 0,
+        }
+    }
+
+    /// Counts a carrier in each sector along a path, with a position for each second from the
+    /// start time
+    pub fn occupy(&mut self, start_time: u64, path: &[(f32, f32)]) {
+        for (offset_s, sector) in visits(self.sectors, path) {
+            let time = start_time + offset_s;
+            *self.occupied.entry((time, sector)).or_default() += 1;
+            self.last_time = self.last_time.max(time);
+        }
+    }
+
+    /// Returns how long a carrier must hold before flying a path, with a position for each second
+    /// from the start time, so that it never enters a sector which is already full, along with the
+    /// first sector that was full. Returns `None` if it needn't hold at all.
+    pub fn hold_s(&self, start_time: u64, path: &[(f32, f32)]) -> Option<(u64, &'a Sector)> {
+        let visits = visits(self.sectors, path).collect::<Vec<_>>();
+        let full = |hold_s: u64| {
+            visits.iter().find_map(|(offset_s, sector)| {
+                let flights = self
+                    .occupied
+                    .get(&(start_time + hold_s + offset_s, *sector))
+                    .copied()
+                    .unwrap_or_default();
+                (flights >= self.sectors[*sector].max_flights).then_some(*sector)
+            })
+        };
+
+        let sector = full(0)?;
+        // Every sector is empty after the last second any is occupied, so holding until then
+        // always makes room
+        let latest_s = (self.last_time + 1).saturating_sub(start_time);
+        let hold_s = (1..latest_s)
+            .find(|hold_s| full(*hold_s).is_none())
+            .unwrap_or(latest_s);
+        Some((hold_s, &self.sectors[sector]))
+    }
+}
+
+/// Returns the second along a path & index of each sector it's in at that second
+fn visits<'a>(
+    sectors: &'a [Sector],
+    path: &'a [(f32, f32)],
+) -> impl Iterator<Item = (u64, usize)> + 'a {
+    path.iter().enumerate().flat_map(move |(i, position)| {
+        sectors
+            .iter()
+            .enumerate()
+            .filter(|(_, sector)| sector.contains(*position))
+            .map(move |(sector, _)| (i as u64, sector))
+    })
+}
+
+/// Counts the carriers at each of the provided positions which are in each of the sectors
+pub fn occupancy(
+    sectors: &[Sector],
+    positions: impl IntoIterator<Item = (f32, f32)>,
+) -> Vec<SectorOccupancy> {
+    let mut occupancy = sectors
+        .iter()
+        .map(|sector| SectorOccupancy {
+            sector: sector.clone(),
+            flights: 0,
+        })
+        .collect::<Vec<_>>();
+    for position in positions {
+        for occupancy in occupancy
+            .iter_mut()
+            .filter(|occupancy| occupancy.sector.contains(position))
+        {
+            occupancy.flights += 1;
+        }
+    }
+    occupancy
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sector_holds() {
+        // A sector east of the nest with room for one carrier at a time
+        let sectors = vec!["East, 100, 1000, 1000, -1000, 1"
+            .parse::<Sector>()
+            .expect("sector")];
+        let mut map = SectorMap::new(&sectors);
+
+        // A carrier flying east from the nest at 50 m/s enters the sector after 2s & leaves it
+        // after 19s, launched at t=10
+        let east = (0..21).map(|i| (i as f32 * 50.0, 0.0)).collect::<Vec<_>>();
+        assert!(map.hold_s(10, &east).is_none());
+        map.occupy(10, &east);
+
+        // A second carrier along the same path waits for the first to leave
+        let (hold_s, sector) = map.hold_s(10, &east).expect("hold");
+        assert_eq!(hold_s, 18);
+        assert_eq!(sector.name, "East");
+
+        // Paths which never enter the sector needn't hold
+        let west = (0..21).map(|i| (i as f32 * -50.0, 0.0)).collect::<Vec<_>>();
+        assert!(map.hold_s(10, &west).is_none());
+
+        assert_eq!(
+            occupancy(&sectors, [(500.0, 0.0), (600.0, 10.0), (-500.0, 0.0)])
+                .iter()
+                .map(|occupancy| occupancy.flights)
+                .collect::<Vec<_>>(),
+            vec![2]
+        );
+    }
+}
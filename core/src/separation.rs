@@ -0,0 +1,95 @@
+use std::collections::HashSet;
+use std::num::NonZeroU64;
+
+/// Column & row of a grid cell, counted from the cell containing the easting & northing (0, 0)
+type Cell = (i64, i64);
+
+/// Airspace divided into a grid of square cells, tracking which cells are occupied by a carrier at
+/// each simulated second. No two carriers may occupy the same cell at the same second, except for
+/// the cell containing the nest, which every carrier takes off from & lands at.
+pub struct Airspace {
+    cell_m: f32,
+    nest: Cell,
+    occupied: HashSet<(u64, Cell)>,
+}
+
+impl Airspace {
+    /// Longest a launch is held to keep its carrier separated from the others, after which it
+    /// launches regardless
+    pub const MAX_HOLD_S: u64 = 10 * 60;
+
+    /// Divides the airspace into cells with sides of the provided length in meters, with the nest
+    /// at the provided easting & northing
+    pub fn new(cell_m: NonZeroU64, nest: (f32, f32)) -> Self {
+        let cell_m = cell_m.get() as f32;
+        Self {
+            cell_m,
+            nest: Self::cell_at(cell_m, nest),
+            occupied: HashSet::new(),
+        }
+    }
+
+    /// Marks the cells along a path, with a position for each second from the start time, as
+    /// occupied
+    pub fn occupy(&mut self, start_time: u64, path: &[(f32, f32)]) {
+        let cells = self.cells(start_time, path).collect::<Vec<_>>();
+        self.occupied.extend(cells);
+    }
+
+    /// Returns how long a carrier must hold before flying a path, with a position for each second
+    /// from the start time, so that it never shares a cell with another carrier.
+    /// Returns `None` if it would still conflict after holding for `MAX_HOLD_S`.
+    pub fn hold_s(&self, start_time: u64, path: &[(f32, f32)]) -> Option<u64> {
+        (0..=Self::MAX_HOLD_S).find(|hold_s| {
+            !self
+                .cells(start_time + hold_s, path)
+                .any(|occupied| self.occupied.contains(&occupied))
+        })
+    }
+
+    /// Returns the cell occupied at each second along a path, other than those at the nest
+    fn cells<'a>(
+        &'a self,
+        start_time: u64,
+        path: &'a [(f32, f32)],
+    ) -> impl Iterator<Item = (u64, Cell)> + 'a {
+        path.iter().enumerate().filter_map(move |(i, position)| {
+            let cell = Self::cell_at(self.cell_m, *position);
+            (cell != self.nest).then_some((start_time + i as u64, cell))
+        })
+    }
+
+    fn cell_at(cell_m: f32, (east_m, north_m): (f32, f32)) -> Cell {
+        (
+            (east_m / cell_m).floor() as i64,
+            (north_m / cell_m).floor() as i64,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_hold() {
+        let mut airspace = Airspace::new(NonZeroU64::new(100).expect("cell size"), (0.0, 0.0));
+
+        // A carrier flying east from the nest at 50 m/s, launched at t=10
+        let east = (0..6).map(|i| (i as f32 * 50.0, 0.0)).collect::<Vec<_>>();
+        airspace.occupy(10, &east);
+
+        // Flying the same path at the same time conflicts once clear of the nest, so the second
+        // carrier holds until the first has moved on to the next cell
+        assert_eq!(airspace.hold_s(10, &east), Some(2));
+
+        // Carriers may share the nest, & paths which never cross don't conflict
+        let north = (0..6).map(|i| (0.0, i as f32 * 50.0)).collect::<Vec<_>>();
+        assert_eq!(airspace.hold_s(10, &north), Some(0));
+
+        // Cells occupied for longer than the longest hold can't be flown through
+        let hover = vec![(150.0, 0.0); Airspace::MAX_HOLD_S as usize + 1];
+        airspace.occupy(20, &hover);
+        assert_eq!(airspace.hold_s(20, &[(150.0, 0.0)]), None);
+    }
+}
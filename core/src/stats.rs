@@ -0,0 +1,1153 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt,
+};
+
+use schema::units::{self, Units};
+use schema::{
+    json::Value, CarrierId, DeliveryProof, DestinationId, Flight, FlightExplanation, FlightId,
+    Order, OrderId, OrderReceipt, OrderStatus, Priority, SectorOccupancy, Shipment,
+};
+
+/// Utilization statistics for a single carrier over the course of a run
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct CarrierStats {
+    pub carrier: CarrierId,
+    /// Number of flights launched by the carrier
+    pub flights: usize,
+    /// Total distance in meters traveled by the carrier
+    pub distance_m: f32,
+    /// Total seconds the carrier spent in the air
+    pub airborne_s: u64,
+    /// Total seconds the carrier spent landed at the origin
+    pub idle_s: u64,
+    /// Average fraction of the carrier's capacity which was used by its flights
+    pub average_load_factor: f32,
+}
+
+impl CarrierStats {
+    pub fn from_json(value: &Value) -> Result<Self, String> {
+        let number = |name: &str| {
+            value
+                .get(name)
+                .and_then(Value::as_f64)
+                .ok_or_else(|| format!("missing `{}`", name))
+        };
+
+        Ok(Self {
+            carrier: CarrierId(number("carrier")? as u32),
+            flights: number("flights")? as usize,
+            distance_m: number("distance_m")? as f32,
+            airborne_s: number("airborne_s")? as u64,
+            idle_s: number("idle_s")? as u64,
+            average_load_factor: number("average_load_factor")? as f32,
+        })
+    }
+
+    pub fn to_json(&self) -> Value {
+        Value::Object(vec![
+            ("carrier".to_string(), (self.carrier.0 as u64).into()),
+            ("flights".to_string(), self.flights.into()),
+            ("distance_m".to_string(), self.distance_m.into()),
+            ("airborne_s".to_string(), self.airborne_s.into()),
+            ("idle_s".to_string(), self.idle_s.into()),
+            (
+                "average_load_factor".to_string(),
+                self.average_load_factor.into(),
+            ),
+        ])
+    }
+}
+
+/// How busy a sector of airspace was over the course of a run
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct SectorStats {
+    pub name: String,
+    /// Most carriers which were allowed in the sector at the same time
+    pub max_flights: usize,
+    /// Most carriers which were in the sector at the same time
+    pub peak_flights: usize,
+    /// Total seconds the sector was full, so that no more carriers could enter it
+    pub full_s: u64,
+}
+
+impl SectorStats {
+    pub fn from_json(value: &Value) -> Result<Self, String> {
+        let number = |name: &str| {
+            value
+                .get(name)
+                .and_then(Value::as_u64)
+                .ok_or_else(|| format!("missing `{}`", name))
+        };
+
+        Ok(Self {
+            name: value
+                .get("name")
+                .and_then(Value::as_str)
+                .ok_or("missing `name`")?
+                .to_string(),
+            max_flights: number("max_flights")? as usize,
+            peak_flights: number("peak_flights")? as usize,
+            full_s: number("full_s")?,
+        })
+    }
+
+    pub fn to_json(&self) -> Value {
+        Value::Object(vec![
+            ("name".to_string(), self.name.as_str().into()),
+            ("max_flights".to_string(), self.max_flights.into()),
+            ("peak_flights".to_string(), self.peak_flights.into()),
+            ("full_s".to_string(), self.full_s.into()),
+        ])
+    }
+}
+
+/// How long the orders delivered to a single destination waited, from being queued to being
+/// delivered
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct DestinationLatency {
+    pub destination: DestinationId,
+    /// Number of orders delivered to the destination
+    pub deliveries: usize,
+    /// Average seconds its orders waited
+    pub average_s: f64,
+    /// Most seconds any of its orders waited
+    pub max_s: u64,
+}
+
+impl DestinationLatency {
+    pub fn from_json(value: &Value) -> Result<Self, String> {
+        let number = |name: &str| {
+            value
+                .get(name)
+                .and_then(Value::as_f64)
+                .ok_or_else(|| format!("missing `{}`", name))
+        };
+
+        Ok(Self {
+            destination: value
+                .get("destination")
+                .and_then(Value::as_str)
+                .ok_or("missing `destination`")?
+                .into(),
+            deliveries: number("deliveries")? as usize,
+            average_s: number("average_s")?,
+            max_s: number("max_s")? as u64,
+        })
+    }
+
+    pub fn to_json(&self) -> Value {
+        Value::Object(vec![
+            ("destination".to_string(), self.destination.as_str().into()),
+            ("deliveries".to_string(), self.deliveries.into()),
+            ("average_s".to_string(), self.average_s.into()),
+            ("max_s".to_string(), self.max_s.into()),
+        ])
+    }
+}
+
+/// Energy spent by the carrier of a single flight, see `schema::EnergyModel`
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct FlightEnergy {
+    pub flight: FlightId,
+    pub carrier: CarrierId,
+    /// Timestamp in __seconds__ at which the flight launched
+    pub launch_time: u64,
+    /// Number of orders delivered by the flight
+    pub orders: usize,
+    pub energy_j: f32,
+}
+
+impl FlightEnergy {
+    pub fn from_json(value: &Value) -> Result<Self, String> {
+        let number = |name: &str| {
+            value
+                .get(name)
+                .and_then(Value::as_f64)
+                .ok_or_else(|| format!("missing `{}`", name))
+        };
+
+        Ok(Self {
+            flight: value
+                .get("flight")
+                .and_then(Value::as_str)
+                .ok_or("missing `flight`")?
+                .parse()?,
+            carrier: CarrierId(number("carrier")? as u32),
+            launch_time: number("launch_time")? as u64,
+            orders: number("orders")? as usize,
+            energy_j: number("energy_j")? as f32,
+        })
+    }
+
+    pub fn to_json(&self) -> Value {
+        Value::Object(vec![
+            ("flight".to_string(), self.flight.to_string().into()),
+            ("carrier".to_string(), (self.carrier.0 as u64).into()),
+            ("launch_time".to_string(), self.launch_time.into()),
+            ("orders".to_string(), self.orders.into()),
+            ("energy_j".to_string(), self.energy_j.into()),
+        ])
+    }
+}
+
+/// Number of orders placed for each destination, by priority & time of day
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct DemandProfile {
+    counts: HashMap<(Priority, DestinationId), Vec<u32>>,
+}
+
+impl DemandProfile {
+    /// Width in seconds of each of the time-of-day buckets orders are counted in
+    pub const BUCKET_S: u64 = 30 * 60;
+    /// Number of buckets making up a day
+    pub const BUCKETS_PER_DAY: usize = (24 * 60 * 60 / Self::BUCKET_S) as usize;
+
+    pub fn record(&mut self, order: &Order) {
+        let bucket = (order.time / Self::BUCKET_S) as usize % Self::BUCKETS_PER_DAY;
+        self.counts
+            .entry((order.priority, order.destination))
+            .or_insert_with(|| vec![0; Self::BUCKETS_PER_DAY])[bucket] += 1;
+    }
+
+    /// Returns the order counts in each time-of-day bucket, by priority & destination
+    pub fn counts(&self) -> impl Iterator<Item = (&(Priority, DestinationId), &[u32])> {
+        self.counts
+            .iter()
+            .map(|(key, counts)| (key, counts.as_slice()))
+    }
+}
+
+/// Summary of a completed simulation run
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct RunReport {
+    /// Timestamp in __seconds__ at which the run started
+    pub start_time: u64,
+    /// Timestamp in __seconds__ at which the run ended
+    pub end_time: u64,
+    /// Number of orders which were launched for delivery, & won't spoil before being delivered
+    pub orders_delivered: usize,
+    /// Number of orders which were never launched
+    pub unfulfilled_orders: usize,
+    /// Number of orders left unscheduled as the run was drained, rather than unfulfilled
+    pub drained_orders: usize,
+    /// Number of orders given up on because they couldn't be delivered within their window
+    pub missed_windows: usize,
+    /// Number of perishable orders which spoil before being delivered, or were given up on because
+    /// they would have
+    pub spoiled: usize,
+    /// Number of orders deferred at least once as their destinations would have been closed
+    pub closed_deferrals: usize,
+    /// Number of flights cut short because their carriers no longer had the range to complete them
+    pub returns_to_base: usize,
+    /// Number of orders whose packages were split across several flights
+    pub split_orders: usize,
+    /// Number of launches held to keep carriers separated
+    pub separation_holds: usize,
+    /// Number of launches held until the sectors they'd fly through had room
+    pub sector_holds: usize,
+    /// Number of alerts raised by the alert rules configured for the run
+    pub rule_alerts: usize,
+    /// Seconds skipped by compressing long gaps between the scripted orders
+    pub compressed_s: u64,
+    /// Utilization of each of the carriers, ordered by identifier
+    pub carriers: Vec<CarrierStats>,
+    /// Occupancy of each sector of airspace, if the run divided it into sectors
+    pub sectors: Vec<SectorStats>,
+    /// Energy spent on each aerial flight, by launch time
+    pub flight_energy: Vec<FlightEnergy>,
+    /// Latency of the orders delivered to each destination, by name
+    pub destinations: Vec<DestinationLatency>,
+    /// Orders placed over the course of the run
+    pub demand: DemandProfile,
+    /// Lifecycle of each order queued during the run, by identifier
+    pub receipts: HashMap<OrderId, OrderReceipt>,
+    /// Decisions behind each flight launched during the run, by identifier
+    pub explanations: HashMap<FlightId, FlightExplanation>,
+}
+
+impl RunReport {
+    const CSV_HEADER: &'static str =
+        "carrier,flights,distance_m,airborne_s,idle_s,average_load_factor";
+    const ORDERS_CSV_HEADER: &'static str =
+        "order,destination,priority,status,queued_at,launch_time,wait_s,delivery_time,flight,carrier,marginal_distance_m";
+
+    /// Reads a report previously written with `to_json`. The demand profile, receipts &
+    /// explanations aren't included.
+    pub fn from_json(value: &Value) -> Result<Self, String> {
+        let count = |name: &str| {
+            value
+                .get(name)
+                .and_then(Value::as_u64)
+                .ok_or_else(|| format!("missing `{}`", name))
+        };
+
+        Ok(Self {
+            start_time: count("start_time")?,
+            end_time: count("end_time")?,
+            orders_delivered: count("orders_delivered")? as usize,
+            unfulfilled_orders: count("unfulfilled_orders")? as usize,
+            drained_orders: count("drained_orders").unwrap_or_default() as usize,
+            // Reports from before delivery windows were supported have none missed
+            missed_windows: count("missed_windows").unwrap_or_default() as usize,
+            spoiled: count("spoiled").unwrap_or_default() as usize,
+            closed_deferrals: count("closed_deferrals").unwrap_or_default() as usize,
+            returns_to_base: count("returns_to_base").unwrap_or_default() as usize,
+            split_orders: count("split_orders").unwrap_or_default() as usize,
+            separation_holds: count("separation_holds").unwrap_or_default() as usize,
+            sector_holds: count("sector_holds").unwrap_or_default() as usize,
+            rule_alerts: count("rule_alerts").unwrap_or_default() as usize,
+            compressed_s: count("compressed_s").unwrap_or_default(),
+            carriers: value
+                .get("carriers")
+                .and_then(Value::as_array)
+                .ok_or("missing `carriers`")?
+                .iter()
+                .map(CarrierStats::from_json)
+                .collect::<Result<_, _>>()?,
+            sectors: match value.get("sectors").and_then(Value::as_array) {
+                Some(sectors) => sectors
+                    .iter()
+                    .map(SectorStats::from_json)
+                    .collect::<Result<_, _>>()?,
+                None => vec![],
+            },
+            flight_energy: match value.get("flight_energy").and_then(Value::as_array) {
+                Some(flights) => flights
+                    .iter()
+                    .map(FlightEnergy::from_json)
+                    .collect::<Result<_, _>>()?,
+                None => vec![],
+            },
+            destinations: match value.get("destinations").and_then(Value::as_array) {
+                Some(destinations) => destinations
+                    .iter()
+                    .map(DestinationLatency::from_json)
+                    .collect::<Result<_, _>>()?,
+                None => vec![],
+            },
+            demand: DemandProfile::default(),
+            receipts: HashMap::new(),
+            explanations: HashMap::new(),
+        })
+    }
+
+    pub fn to_json(&self) -> Value {
+        Value::Object(vec![
+            ("start_time".to_string(), self.start_time.into()),
+            ("end_time".to_string(), self.end_time.into()),
+            ("orders_delivered".to_string(), self.orders_delivered.into()),
+            (
+                "unfulfilled_orders".to_string(),
+                self.unfulfilled_orders.into(),
+            ),
+            ("drained_orders".to_string(), self.drained_orders.into()),
+            ("missed_windows".to_string(), self.missed_windows.into()),
+            ("spoiled".to_string(), self.spoiled.into()),
+            ("closed_deferrals".to_string(), self.closed_deferrals.into()),
+            ("returns_to_base".to_string(), self.returns_to_base.into()),
+            ("split_orders".to_string(), self.split_orders.into()),
+            ("separation_holds".to_string(), self.separation_holds.into()),
+            ("sector_holds".to_string(), self.sector_holds.into()),
+            ("rule_alerts".to_string(), self.rule_alerts.into()),
+            ("compressed_s".to_string(), self.compressed_s.into()),
+            (
+                "carriers".to_string(),
+                Value::Array(self.carriers.iter().map(CarrierStats::to_json).collect()),
+            ),
+            (
+                "sectors".to_string(),
+                Value::Array(self.sectors.iter().map(SectorStats::to_json).collect()),
+            ),
+            (
+                "flight_energy".to_string(),
+                Value::Array(
+                    self.flight_energy
+                        .iter()
+                        .map(FlightEnergy::to_json)
+                        .collect(),
+                ),
+            ),
+            (
+                "destinations".to_string(),
+                Value::Array(
+                    self.destinations
+                        .iter()
+                        .map(DestinationLatency::to_json)
+                        .collect(),
+                ),
+            ),
+        ])
+    }
+
+    /// Returns the average seconds the delivered orders waited, from being queued to being delivered
+    pub fn average_latency_s(&self) -> f64 {
+        let deliveries = self
+            .destinations
+            .iter()
+            .map(|latency| latency.deliveries)
+            .sum::<usize>();
+        match deliveries {
+            0 => 0.0,
+            n => {
+                self.destinations
+                    .iter()
+                    .map(|latency| latency.average_s * latency.deliveries as f64)
+                    .sum::<f64>()
+                    / n as f64
+            }
+        }
+    }
+
+    /// Returns the most seconds any delivered order waited
+    pub fn max_latency_s(&self) -> u64 {
+        self.destinations
+            .iter()
+            .map(|latency| latency.max_s)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Returns the Gini coefficient of the destinations' average latencies, from 0 when every
+    /// destination waited as long as the others on average, towards 1 as the waiting falls on
+    /// fewer of them
+    pub fn latency_gini(&self) -> f64 {
+        let mut averages = self
+            .destinations
+            .iter()
+            .map(|latency| latency.average_s)
+            .collect::<Vec<_>>();
+        let total = averages.iter().sum::<f64>();
+        if averages.len() < 2 || total <= 0.0 {
+            return 0.0;
+        }
+
+        averages.sort_by(f64::total_cmp);
+        let n = averages.len() as f64;
+        let weighted = averages
+            .iter()
+            .enumerate()
+            .map(|(i, average)| (2.0 * (i + 1) as f64 - n - 1.0) * average)
+            .sum::<f64>();
+        weighted / (n * total)
+    }
+
+    /// Returns the total energy in joules spent on the run's aerial flights
+    pub fn energy_j(&self) -> f64 {
+        self.flight_energy
+            .iter()
+            .map(|flight| flight.energy_j as f64)
+            .sum()
+    }
+
+    /// Returns the flights launched over the course of the run, rebuilt from the receipts of the
+    /// orders they delivered, e.g. to warm-start another run of the scenario from
+    pub fn flight_plan(&self) -> Vec<Flight> {
+        let mut receipts = self
+            .receipts
+            .values()
+            .filter_map(|receipt| {
+                Some((
+                    receipt.flight?,
+                    receipt.carrier?,
+                    receipt.launch_time?,
+                    receipt,
+                ))
+            })
+            .collect::<Vec<_>>();
+        receipts.sort_by_key(|(.., receipt)| (receipt.delivery_time, receipt.order.id));
+
+        let mut flights = HashMap::<FlightId, Flight>::new();
+        for (id, carrier, launch_time, receipt) in receipts {
+            flights
+                .entry(id)
+                .or_insert_with(|| Flight {
+                    id,
+                    carrier,
+                    launch_time,
+                    ..Default::default()
+                })
+                .orders
+                .push(receipt.order.clone());
+        }
+        let mut flights = flights.into_values().collect::<Vec<_>>();
+        flights.sort_by_key(|flight| (flight.launch_time, flight.carrier));
+        flights
+    }
+
+    /// Returns the per-carrier statistics as CSV, with a header row
+    pub fn carriers_csv(&self) -> String {
+        let mut csv = format!("{}\n", Self::CSV_HEADER);
+        for stats in &self.carriers {
+            csv.push_str(&format!(
+                "{},{},{:.0},{},{},{:.3}\n",
+                stats.carrier,
+                stats.flights,
+                stats.distance_m,
+                stats.airborne_s,
+                stats.idle_s,
+                stats.average_load_factor
+            ));
+        }
+        csv
+    }
+
+    /// Returns what each order cost to deliver as CSV, with a header row: how long it waited to be
+    /// launched & how much further its flights went for it, by identifier. Fields are left empty
+    /// for orders which were never launched.
+    pub fn orders_csv(&self) -> String {
+        let optional = |value: Option<String>| value.unwrap_or_default();
+        let mut receipts = self.receipts.values().collect::<Vec<_>>();
+        receipts.sort_by_key(|receipt| receipt.order.id);
+
+        let mut csv = format!("{}\n", Self::ORDERS_CSV_HEADER);
+        for receipt in receipts {
+            let receipt = receipt.clone().as_of(self.end_time);
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{},{},{},{},{}\n",
+                receipt.order.id,
+                receipt.order.destination,
+                receipt.order.priority,
+                format!("{:?}", receipt.status).to_lowercase(),
+                receipt.queued_at,
+                optional(receipt.launch_time.map(|time| time.to_string())),
+                optional(receipt.wait_s.map(|wait_s| wait_s.to_string())),
+                optional(receipt.delivery_time.map(|time| time.to_string())),
+                optional(receipt.flight.map(|flight| flight.to_string())),
+                optional(receipt.carrier.map(|carrier| carrier.to_string())),
+                optional(receipt.marginal_distance_m.map(|m| m.to_string())),
+            ));
+        }
+        csv
+    }
+}
+
+impl fmt::Display for RunReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.display(Units::default()).fmt(f)
+    }
+}
+
+/// A `RunReport` displayed in a particular system of units, see `RunReport::display`
+pub struct ReportDisplay<'a> {
+    report: &'a RunReport,
+    units: Units,
+}
+
+impl RunReport {
+    /// Displays the report with distances & times in the provided units
+    pub fn display(&self, units: Units) -> ReportDisplay<'_> {
+        ReportDisplay {
+            report: self,
+            units,
+        }
+    }
+}
+
+impl fmt::Display for ReportDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { report, units } = self;
+        writeln!(
+            f,
+            "{} to {}: {} orders delivered, {} unfulfilled, {} missed delivery windows, {} spoiled, {} returns to base, {} separation holds, {} sector holds, {} rule alerts",
+            units.format_time(report.start_time),
+            units.format_end_time(report.end_time),
+            report.orders_delivered,
+            report.unfulfilled_orders,
+            report.missed_windows,
+            report.spoiled,
+            report.returns_to_base,
+            report.separation_holds,
+            report.sector_holds,
+            report.rule_alerts
+        )?;
+        if report.compressed_s > 0 {
+            writeln!(
+                f,
+                "{} of idle time skipped between orders",
+                units::format_duration(report.compressed_s)
+            )?;
+        }
+        if report.split_orders > 0 {
+            writeln!(f, "{} orders split across flights", report.split_orders)?;
+        }
+        if report.closed_deferrals > 0 {
+            writeln!(
+                f,
+                "{} orders deferred as their destinations were closed",
+                report.closed_deferrals
+            )?;
+        }
+        if report.drained_orders > 0 {
+            writeln!(
+                f,
+                "{} orders left unscheduled as the run was drained",
+                report.drained_orders
+            )?;
+        }
+        for stats in &report.carriers {
+            writeln!(
+                f,
+                "carrier {}: {} flights, {}, {} airborne, {} idle, {:.0}% average load",
+                stats.carrier,
+                stats.flights,
+                units.format_distance(stats.distance_m as f64),
+                units::format_duration(stats.airborne_s),
+                units::format_duration(stats.idle_s),
+                stats.average_load_factor * 100.0
+            )?;
+        }
+        if !report.flight_energy.is_empty() {
+            let energy_kwh = report.energy_j() / 3.6e6;
+            writeln!(
+                f,
+                "{:.2} kWh spent over {} flights, {:.3} kWh per flight",
+                energy_kwh,
+                report.flight_energy.len(),
+                energy_kwh / report.flight_energy.len() as f64
+            )?;
+        }
+        if !report.destinations.is_empty() {
+            writeln!(
+                f,
+                "orders waited {} on average & at most {} to be delivered, {:.3} latency gini across {} destinations",
+                units::format_duration(report.average_latency_s().round() as u64),
+                units::format_duration(report.max_latency_s()),
+                report.latency_gini(),
+                report.destinations.len()
+            )?;
+        }
+        for stats in &report.sectors {
+            writeln!(
+                f,
+                "sector {}: peak of {} of {} carriers, full for {}",
+                stats.name,
+                stats.peak_flights,
+                stats.max_flights,
+                units::format_duration(stats.full_s)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// How an order's lifecycle ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrderOutcome {
+    Delivered,
+    /// Given up on (e.g. as it would miss its window), or delivered after it spoiled
+    Failed,
+    /// Turned away, as its destination was deactivated
+    Cancelled,
+}
+
+/// An order whose lifecycle has ended, along with its receipt as of then
+#[derive(Clone, Debug, PartialEq)]
+pub struct SettledOrder {
+    pub outcome: OrderOutcome,
+    pub receipt: OrderReceipt,
+}
+
+/// Accumulates statistics about the flights launched during a run
+#[derive(Clone)]
+pub struct StatsCollector {
+    max_orders_per_carrier: usize,
+    carriers: BTreeMap<CarrierId, Accumulated>,
+    orders_delivered: usize,
+    missed_windows: usize,
+    /// Perishable orders given up on before launch, see `record_spoiled`
+    spoiled: usize,
+    /// Orders deferred at least once as their destinations would have been closed
+    closed_deferrals: HashSet<OrderId>,
+    returns_to_base: usize,
+    separation_holds: usize,
+    sector_holds: usize,
+    sectors: Vec<SectorStats>,
+    flight_energy: Vec<FlightEnergy>,
+    rule_alerts: usize,
+    demand: DemandProfile,
+    receipts: HashMap<OrderId, OrderReceipt>,
+    explanations: HashMap<FlightId, FlightExplanation>,
+    /// Orders scheduled into flights which haven't been delivered yet, see `take_settled`
+    awaiting_delivery: BTreeSet<OrderId>,
+    /// Orders given up on since `take_settled` was last called
+    given_up: Vec<(OrderOutcome, OrderId)>,
+    /// Identifier assigned to the next order placed
+    next_order_id: u64,
+}
+
+#[derive(Default, Clone)]
+struct Accumulated {
+    flights: usize,
+    distance_m: f32,
+    /// Launch & end times of each flight
+    intervals: Vec<(u64, u64)>,
+    /// Packages carried by the flights, each taking up one of the carrier's slots
+    packages_carried: usize,
+}
+
+impl StatsCollector {
+    pub fn new(carriers: impl Iterator<Item = CarrierId>, max_orders_per_carrier: usize) -> Self {
+        Self {
+            max_orders_per_carrier,
+            carriers: carriers.map(|id| (id, Accumulated::default())).collect(),
+            orders_delivered: 0,
+            missed_windows: 0,
+            spoiled: 0,
+            closed_deferrals: HashSet::new(),
+            returns_to_base: 0,
+            separation_holds: 0,
+            sector_holds: 0,
+            sectors: vec![],
+            flight_energy: vec![],
+            rule_alerts: 0,
+            demand: DemandProfile::default(),
+            receipts: HashMap::new(),
+            explanations: HashMap::new(),
+            awaiting_delivery: BTreeSet::new(),
+            given_up: vec![],
+            next_order_id: 1,
+        }
+    }
+
+    /// Record an order as it is queued at the provided time, returning it with its identifier
+    pub fn record_order(&mut self, order: Order, queued_at: u64) -> Order {
+        let order = Order {
+            id: OrderId(self.next_order_id),
+            ..order
+        };
+        self.next_order_id += 1;
+        self.demand.record(&order);
+        self.receipts.insert(
+            order.id,
+            OrderReceipt {
+                order: order.clone(),
+                queued_at,
+                ..Default::default()
+            },
+        );
+        order
+    }
+
+    /// Record an order which couldn't be delivered within its window
+    pub fn record_missed_window(&mut self, order: &Order) {
+        self.missed_windows += 1;
+        self.record_failure(order, "delivery window missed");
+    }
+
+    /// Record a perishable order which would have spoiled before it could be delivered
+    pub fn record_spoiled(&mut self, order: &Order) {
+        self.spoiled += 1;
+        self.record_failure(order, "would spoil before delivery");
+    }
+
+    /// Record an order held back from a launch as its destination would have been closed when the
+    /// flight arrived
+    pub fn record_deferred(&mut self, order: &Order) {
+        self.closed_deferrals.insert(order.id);
+        if let Some(receipt) = self.receipts.get_mut(&order.id) {
+            if receipt.status == OrderStatus::Queued {
+                receipt.status = OrderStatus::Deferred;
+            }
+        }
+    }
+
+    /// Record an order which was given up on for the provided reason
+    pub fn record_failure(&mut self, order: &Order, reason: &str) {
+        self.give_up(order, reason, OrderOutcome::Failed);
+    }
+
+    /// Record an order which was turned away for the provided reason, e.g. as its destination was
+    /// deactivated
+    pub fn record_cancelled(&mut self, order: &Order, reason: &str) {
+        self.give_up(order, reason, OrderOutcome::Cancelled);
+    }
+
+    fn give_up(&mut self, order: &Order, reason: &str, outcome: OrderOutcome) {
+        if let Some(receipt) = self.receipts.get_mut(&order.id) {
+            receipt.status = OrderStatus::Failed;
+            receipt.failure = Some(reason.to_string());
+            self.given_up.push((outcome, order.id));
+        }
+    }
+
+    /// Returns the orders whose lifecycles have ended since the last call, as of the provided time:
+    /// those given up on, & those their flights have delivered by then
+    pub fn take_settled(&mut self, current_time: u64) -> Vec<SettledOrder> {
+        let given_up = std::mem::take(&mut self.given_up);
+        let receipts = &self.receipts;
+        let receipt = |id| {
+            receipts
+                .get(&id)
+                .map(|receipt: &OrderReceipt| receipt.clone().as_of(current_time))
+        };
+        let mut settled = given_up
+            .into_iter()
+            .filter_map(|(outcome, id)| {
+                Some(SettledOrder {
+                    outcome,
+                    receipt: receipt(id)?,
+                })
+            })
+            .collect::<Vec<_>>();
+        self.awaiting_delivery.retain(|id| {
+            let Some(receipt) = receipt(*id) else {
+                return false;
+            };
+            let outcome = match receipt.status {
+                OrderStatus::Delivered => OrderOutcome::Delivered,
+                OrderStatus::Spoiled => OrderOutcome::Failed,
+                _ => return true,
+            };
+            settled.push(SettledOrder { outcome, receipt });
+            false
+        });
+        settled
+    }
+
+    /// Returns the lifecycle of an order as of the provided time, if it was queued during the run
+    pub fn receipt(&self, id: OrderId, current_time: u64) -> Option<OrderReceipt> {
+        self.receipts
+            .get(&id)
+            .map(|receipt| receipt.clone().as_of(current_time))
+    }
+
+    /// Record the decisions behind flights as they're launched
+    pub fn record_explanations(&mut self, explanations: Vec<FlightExplanation>) {
+        self.explanations.extend(
+            explanations
+                .into_iter()
+                .map(|explanation| (explanation.flight, explanation)),
+        );
+    }
+
+    /// Returns the decisions behind a flight, if it was launched during the run
+    pub fn explanation(&self, id: FlightId) -> Option<FlightExplanation> {
+        self.explanations.get(&id).cloned()
+    }
+
+    /// Record a launch which was held to keep its carrier separated from the others
+    pub fn record_separation_hold(&mut self) {
+        self.separation_holds += 1;
+    }
+
+    /// Record a launch which was held until the sectors its carrier would fly through had room
+    pub fn record_sector_hold(&mut self) {
+        self.sector_holds += 1;
+    }
+
+    /// Record the carriers in each sector for a second of the run
+    pub fn record_sector_occupancy(&mut self, occupancy: &[SectorOccupancy]) {
+        for occupancy in occupancy {
+            let stats = match self
+                .sectors
+                .iter_mut()
+                .position(|stats| stats.name == occupancy.sector.name)
+            {
+                Some(i) => &mut self.sectors[i],
+                None => {
+                    self.sectors.push(SectorStats {
+                        name: occupancy.sector.name.clone(),
+                        ..Default::default()
+                    });
+                    self.sectors.last_mut().expect("sector")
+                }
+            };
+            stats.max_flights = occupancy.sector.max_flights;
+            stats.peak_flights = stats.peak_flights.max(occupancy.flights);
+            if occupancy.is_full() {
+                stats.full_s += 1;
+            }
+        }
+    }
+
+    /// Record an alert raised by one of the run's alert rules
+    pub fn record_rule_alert(&mut self) {
+        self.rule_alerts += 1;
+    }
+
+    /// Record the proof that each of a flight's orders will be delivered with, which is revealed
+    /// in their receipts once they've been delivered
+    pub fn record_proofs(&mut self, proofs: impl IntoIterator<Item = (OrderId, DeliveryProof)>) {
+        for (id, proof) in proofs {
+            if let Some(receipt) = self.receipts.get_mut(&id) {
+                receipt.proof = Some(proof);
+            }
+        }
+    }
+
+    /// Record a newly launched flight, along with its total distance, the time each of its orders
+    /// will be delivered & the distance each adds to the flight, the time it will land & the
+    /// energy its carrier will spend, unless it drives
+    pub fn record_flight(
+        &mut self,
+        flight: &Flight,
+        distance_m: f32,
+        delivery_times: &[u64],
+        marginal_distances_m: &[f32],
+        end_time: u64,
+        energy_j: Option<f32>,
+    ) {
+        let orders = flight.orders.iter().zip(delivery_times);
+        for ((order, delivery_time), marginal_m) in orders.zip(marginal_distances_m) {
+            let Some(receipt) = self.receipts.get_mut(&order.id) else {
+                continue;
+            };
+            let shipment = Shipment {
+                flight: flight.id,
+                carrier: flight.carrier,
+                packages: order.quantity,
+                launch_time: flight.launch_time,
+                delivery_time: *delivery_time,
+                marginal_distance_m: marginal_m.round() as u32,
+            };
+            // Orders split across flights are scheduled once the last of their packages are, as
+            // the flight which delivers last
+            let split = order.quantity < receipt.order.quantity || !receipt.shipments.is_empty();
+            let last = match split {
+                true => {
+                    receipt.shipments.push(shipment);
+                    let scheduled = receipt
+                        .shipments
+                        .iter()
+                        .map(|shipment| shipment.packages)
+                        .sum::<u32>();
+                    if scheduled < receipt.order.quantity {
+                        continue;
+                    }
+                    receipt
+                        .shipments
+                        .iter()
+                        .max_by_key(|shipment| shipment.delivery_time)
+                        .cloned()
+                        .expect("shipment")
+                }
+                false => shipment,
+            };
+            receipt.status = OrderStatus::Scheduled;
+            receipt.flight = Some(last.flight);
+            receipt.carrier = Some(last.carrier);
+            receipt.launch_time = Some(last.launch_time);
+            receipt.delivery_time = Some(last.delivery_time);
+            receipt.marginal_distance_m = Some(match split {
+                true => receipt
+                    .shipments
+                    .iter()
+                    .map(|shipment| shipment.marginal_distance_m)
+                    .sum(),
+                false => last.marginal_distance_m,
+            });
+            receipt.wait_s = Some(last.launch_time.saturating_sub(receipt.queued_at));
+            self.awaiting_delivery.insert(order.id);
+            self.orders_delivered += 1;
+        }
+
+        let carrier = self.carriers.entry(flight.carrier).or_default();
+        carrier.flights += 1;
+        carrier.distance_m += distance_m;
+        carrier.intervals.push((flight.launch_time, end_time));
+        carrier.packages_carried += packages(flight);
+        if let Some(energy_j) = energy_j {
+            self.flight_energy.push(FlightEnergy {
+                flight: flight.id,
+                carrier: flight.carrier,
+                launch_time: flight.launch_time,
+                orders: flight.orders.len(),
+                energy_j,
+            });
+        }
+    }
+
+    /// Record a flight which was cut short to return to the origin early, along with the distance
+    /// it was planned to travel, its new total distance, the time it will now land & the energy
+    /// its carrier will now spend. Orders it didn't deliver no longer count as delivered, & are
+    /// queued again.
+    pub fn record_return_to_base(
+        &mut self,
+        planned: &Flight,
+        planned_distance_m: f32,
+        diverted: &Flight,
+        distance_m: f32,
+        end_time: u64,
+        energy_j: Option<f32>,
+    ) {
+        for order in planned
+            .orders
+            .iter()
+            .filter(|order| !diverted.orders.iter().any(|kept| kept.id == order.id))
+        {
+            self.mark_queued(order.id, planned.id);
+        }
+        let carrier = self.carriers.entry(diverted.carrier).or_default();
+        carrier.distance_m += distance_m - planned_distance_m;
+        if let Some(interval) = carrier
+            .intervals
+            .iter_mut()
+            .rev()
+            .find(|(launch, _)| *launch == diverted.launch_time)
+        {
+            interval.1 = end_time;
+        }
+        carrier.packages_carried -= packages(planned) - packages(diverted);
+        self.returns_to_base += 1;
+        if let (Some(flight), Some(energy_j)) = (
+            self.flight_energy
+                .iter_mut()
+                .find(|flight| flight.flight == diverted.id),
+            energy_j,
+        ) {
+            flight.orders = diverted.orders.len();
+            flight.energy_j = energy_j;
+        }
+    }
+
+    /// Record a flight which was unpacked before it launched, so that its carrier could be given
+    /// over to emergencies. None of its orders count as delivered, & they are queued again.
+    pub fn record_commandeered(&mut self, flight: &Flight, distance_m: f32) {
+        for order in &flight.orders {
+            self.mark_queued(order.id, flight.id);
+        }
+        let carrier = self.carriers.entry(flight.carrier).or_default();
+        carrier.flights -= 1;
+        carrier.distance_m -= distance_m;
+        carrier
+            .intervals
+            .retain(|(launch, _)| *launch != flight.launch_time);
+        carrier.packages_carried -= packages(flight);
+        self.flight_energy
+            .retain(|energy| energy.flight != flight.id);
+    }
+
+    /// Produce a report for a run spanning the provided times.
+    /// Flights still airborne at the end of the run only count the time spent in the air until then.
+    pub fn finish(self, start_time: u64, end_time: u64, unfulfilled_orders: usize) -> RunReport {
+        let duration = end_time.saturating_sub(start_time);
+        // Perishable orders launched too late in their flights spoil rather than being delivered
+        let spoiled_deliveries = self
+            .receipts
+            .values()
+            .filter(|receipt| {
+                receipt
+                    .launch_time
+                    .zip(receipt.delivery_time)
+                    .is_some_and(|(launch, delivery)| receipt.order.spoils(launch, delivery))
+            })
+            .count();
+        let split_orders = self
+            .receipts
+            .values()
+            .filter(|receipt| !receipt.shipments.is_empty())
+            .count();
+        let capacity = self.max_orders_per_carrier.max(1) as f32;
+
+        let mut latencies = BTreeMap::<&str, DestinationLatency>::new();
+        for receipt in self.receipts.values() {
+            let Some(delivery_time) = receipt.delivery_time else {
+                continue;
+            };
+            if receipt
+                .launch_time
+                .is_some_and(|launch| receipt.order.spoils(launch, delivery_time))
+            {
+                continue;
+            }
+            let latency_s = delivery_time.saturating_sub(receipt.queued_at);
+            let latency = latencies
+                .entry(receipt.order.destination.as_str())
+                .or_insert_with(|| DestinationLatency {
+                    destination: receipt.order.destination,
+                    ..Default::default()
+                });
+            latency.deliveries += 1;
+            // Summed here, & divided by the number of deliveries below
+            latency.average_s += latency_s as f64;
+            latency.max_s = latency.max_s.max(latency_s);
+        }
+        let destinations = latencies
+            .into_values()
+            .map(|latency| DestinationLatency {
+                average_s: latency.average_s / latency.deliveries as f64,
+                ..latency
+            })
+            .collect();
+
+        let carriers = self
+            .carriers
+            .into_iter()
+            .map(|(carrier, acc)| {
+                let airborne_s = acc
+                    .intervals
+                    .iter()
+                    .map(|(launch, land)| (*land).min(end_time).saturating_sub(*launch))
+                    .sum::<u64>()
+                    .min(duration);
+
+                CarrierStats {
+                    carrier,
+                    flights: acc.flights,
+                    distance_m: acc.distance_m,
+                    airborne_s,
+                    idle_s: duration - airborne_s,
+                    average_load_factor: match acc.flights {
+                        0 => 0.0,
+                        n => acc.packages_carried as f32 / (n as f32 * capacity),
+                    },
+                }
+            })
+            .collect();
+
+        RunReport {
+            start_time,
+            end_time,
+            orders_delivered: self.orders_delivered - spoiled_deliveries,
+            unfulfilled_orders,
+            drained_orders: 0,
+            missed_windows: self.missed_windows,
+            spoiled: self.spoiled + spoiled_deliveries,
+            closed_deferrals: self.closed_deferrals.len(),
+            returns_to_base: self.returns_to_base,
+            split_orders,
+            separation_holds: self.separation_holds,
+            sector_holds: self.sector_holds,
+            rule_alerts: self.rule_alerts,
+            compressed_s: 0,
+            carriers,
+            sectors: self.sectors,
+            flight_energy: self.flight_energy,
+            destinations,
+            demand: self.demand,
+            receipts: self.receipts,
+            explanations: self.explanations,
+        }
+    }
+
+    /// Returns an order's receipt to the queue, after it was taken off a flight before delivery.
+    /// Orders split across flights keep their other shipments.
+    fn mark_queued(&mut self, id: OrderId, flight: FlightId) {
+        if self.awaiting_delivery.remove(&id) {
+            self.orders_delivered -= 1;
+        }
+        if let Some(receipt) = self.receipts.get_mut(&id) {
+            receipt
+                .shipments
+                .retain(|shipment| shipment.flight != flight);
+            receipt.status = OrderStatus::Queued;
+            receipt.flight = None;
+            receipt.carrier = None;
+            receipt.launch_time = None;
+            receipt.delivery_time = None;
+            receipt.proof = None;
+            receipt.marginal_distance_m = None;
+            receipt.wait_s = None;
+        }
+    }
+}
+
+/// Returns the number of packages carried by a flight
+fn packages(flight: &Flight) -> usize {
+    flight
+        .orders
+        .iter()
+        .map(|order| order.quantity as usize)
+        .sum()
+}
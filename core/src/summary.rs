@@ -0,0 +1,682 @@
+//! Renders a `RunReport` as a standalone HTML or Markdown document, e.g. at the end of each day's
+//! run: the headline counts, how well the delivery SLOs were met, charts of demand & carrier
+//! utilization drawn as inline SVG, & tables of the destinations & carriers.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use schema::units::{self, Units};
+use schema::{DestinationId, OrderReceipt, OrderStatus, Priority};
+
+use crate::{AlertMonitor, DemandProfile, RunReport};
+
+const CHART_WIDTH: f64 = 720.0;
+const CHART_HEIGHT: f64 = 200.0;
+/// Space left around the bars of a chart for its axis labels
+const CHART_MARGIN: f64 = 30.0;
+const EMERGENCY_COLOR: &str = "#c80000";
+const RESUPPLY_COLOR: &str = "#006ec8";
+
+/// Format of a rendered summary
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SummaryFormat {
+    Html,
+    Markdown,
+}
+
+impl SummaryFormat {
+    /// Picks the format by the extension of the path a summary is written to, `.html` or `.md`
+    pub fn from_path(path: &str) -> Result<Self, String> {
+        match std::path::Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+        {
+            Some("html" | "htm") => Ok(Self::Html),
+            Some("md" | "markdown") => Ok(Self::Markdown),
+            _ => Err(format!(
+                "can't tell the format of summary {}, expected a .html or .md extension",
+                path
+            )),
+        }
+    }
+}
+
+/// Part of a rendered summary
+enum Block {
+    Heading(String),
+    /// Labelled values
+    Facts(Vec<(String, String)>),
+    Table {
+        header: Vec<&'static str>,
+        rows: Vec<Vec<String>>,
+    },
+    Chart {
+        title: String,
+        svg: String,
+    },
+}
+
+impl RunReport {
+    /// Renders the report as a document in the provided format, with distances & times in the
+    /// provided units. Reports read back with `from_json` have no demand or receipts to chart.
+    pub fn summary(&self, format: SummaryFormat, units: Units) -> String {
+        let title = format!(
+            "Run report: {} to {}",
+            units.format_time(self.start_time),
+            units.format_end_time(self.end_time)
+        );
+        let blocks = self.summary_blocks(units);
+        match format {
+            SummaryFormat::Html => render_html(&title, &blocks),
+            SummaryFormat::Markdown => render_markdown(&title, &blocks),
+        }
+    }
+
+    fn summary_blocks(&self, units: Units) -> Vec<Block> {
+        let receipts = self
+            .receipts
+            .values()
+            .map(|receipt| receipt.clone().as_of(self.end_time))
+            .collect::<Vec<_>>();
+
+        let mut totals = vec![
+            ("Orders delivered", self.orders_delivered.to_string()),
+            ("Unfulfilled orders", self.unfulfilled_orders.to_string()),
+            ("Missed delivery windows", self.missed_windows.to_string()),
+            ("Spoiled orders", self.spoiled.to_string()),
+            ("Returns to base", self.returns_to_base.to_string()),
+            ("Separation holds", self.separation_holds.to_string()),
+            ("Rule alerts", self.rule_alerts.to_string()),
+        ];
+        if !self.sectors.is_empty() {
+            totals.push(("Sector holds", self.sector_holds.to_string()));
+        }
+        if self.split_orders > 0 {
+            totals.push(("Orders split across flights", self.split_orders.to_string()));
+        }
+        if self.closed_deferrals > 0 {
+            totals.push((
+                "Orders deferred for closed destinations",
+                self.closed_deferrals.to_string(),
+            ));
+        }
+        if self.drained_orders > 0 {
+            totals.push((
+                "Orders drained unscheduled",
+                self.drained_orders.to_string(),
+            ));
+        }
+        if !self.flight_energy.is_empty() {
+            totals.push((
+                "Energy spent",
+                format!("{:.2} kWh", self.energy_j() / 3.6e6),
+            ));
+        }
+        if self.compressed_s > 0 {
+            totals.push((
+                "Idle time skipped",
+                units::format_duration(self.compressed_s),
+            ));
+        }
+
+        let mut blocks = vec![
+            Block::Heading("Summary".to_string()),
+            Block::Facts(
+                totals
+                    .into_iter()
+                    .map(|(label, value)| (label.to_string(), value))
+                    .collect(),
+            ),
+            Block::Heading("Delivery SLOs".to_string()),
+            Block::Facts(slo_facts(&receipts)),
+            Block::Heading("Demand".to_string()),
+            demand_chart(&self.demand),
+            Block::Heading("Destinations".to_string()),
+            destinations_table(&receipts),
+            Block::Heading("Carriers".to_string()),
+        ];
+
+        if !self.carriers.is_empty() {
+            blocks.push(Block::Chart {
+                title: "Share of the run each carrier spent airborne".to_string(),
+                svg: stacked_bars(
+                    &self
+                        .carriers
+                        .iter()
+                        .map(|stats| stats.carrier.to_string())
+                        .collect::<Vec<_>>(),
+                    &[(
+                        "Airborne",
+                        RESUPPLY_COLOR,
+                        self.carriers
+                            .iter()
+                            .map(|stats| {
+                                let total = (stats.airborne_s + stats.idle_s).max(1);
+                                stats.airborne_s as f64 / total as f64
+                            })
+                            .collect(),
+                    )],
+                ),
+            });
+        }
+        blocks.push(Block::Table {
+            header: vec![
+                "Carrier",
+                "Flights",
+                "Distance",
+                "Airborne",
+                "Idle",
+                "Average load",
+            ],
+            rows: self
+                .carriers
+                .iter()
+                .map(|stats| {
+                    vec![
+                        stats.carrier.to_string(),
+                        stats.flights.to_string(),
+                        units.format_distance(stats.distance_m as f64),
+                        units::format_duration(stats.airborne_s),
+                        units::format_duration(stats.idle_s),
+                        format!("{:.0}%", stats.average_load_factor * 100.0),
+                    ]
+                })
+                .collect(),
+        });
+
+        if !self.sectors.is_empty() {
+            blocks.push(Block::Heading("Sectors".to_string()));
+            blocks.push(Block::Table {
+                header: vec!["Sector", "Capacity", "Peak", "Full for"],
+                rows: self
+                    .sectors
+                    .iter()
+                    .map(|stats| {
+                        vec![
+                            stats.name.clone(),
+                            stats.max_flights.to_string(),
+                            stats.peak_flights.to_string(),
+                            units::format_duration(stats.full_s),
+                        ]
+                    })
+                    .collect(),
+            });
+        }
+        blocks
+    }
+}
+
+/// Seconds from each delivered order being placed to its delivery
+fn latency_s(receipt: &OrderReceipt) -> Option<u64> {
+    let delivered = receipt
+        .delivery_time
+        .filter(|_| receipt.status == OrderStatus::Delivered)?;
+    Some(delivered.saturating_sub(receipt.order.time))
+}
+
+/// Value below which the provided fraction of the sorted values fall, by nearest rank
+fn percentile(sorted: &[u64], fraction: f64) -> Option<u64> {
+    let rank = (fraction * sorted.len() as f64).ceil() as usize;
+    sorted.get(rank.max(1) - 1).copied()
+}
+
+/// Describes the median & 95th percentile of the sorted latencies
+fn describe_latencies(sorted: &[u64]) -> String {
+    match (percentile(sorted, 0.5), percentile(sorted, 0.95)) {
+        (Some(median), Some(p95)) => format!(
+            "median {}, 95th percentile {}",
+            units::format_duration(median),
+            units::format_duration(p95)
+        ),
+        _ => "none delivered".to_string(),
+    }
+}
+
+fn share(count: usize, total: usize) -> String {
+    match total {
+        0 => "none".to_string(),
+        _ => format!(
+            "{} of {} ({:.1}%)",
+            count,
+            total,
+            count as f64 * 100.0 / total as f64
+        ),
+    }
+}
+
+/// How quickly orders of each priority were delivered, against the emergency delivery SLO, & how
+/// many delivery windows were met
+fn slo_facts(receipts: &[OrderReceipt]) -> Vec<(String, String)> {
+    let slo_s = AlertMonitor::DEFAULT_DELIVERY_SLO_S;
+    let latencies = |priority: Priority| {
+        let mut latencies = receipts
+            .iter()
+            .filter(|receipt| receipt.order.priority == priority)
+            .filter_map(latency_s)
+            .collect::<Vec<_>>();
+        latencies.sort_unstable();
+        latencies
+    };
+    let (emergencies, resupplies) = (
+        latencies(Priority::Emergency),
+        latencies(Priority::Resupply),
+    );
+    let placed_emergencies = receipts
+        .iter()
+        .filter(|receipt| receipt.order.priority == Priority::Emergency)
+        .count();
+    let windows = receipts
+        .iter()
+        .filter(|receipt| receipt.order.has_delivery_window())
+        .collect::<Vec<_>>();
+    let windows_met = windows
+        .iter()
+        .filter(|receipt| {
+            receipt.status == OrderStatus::Delivered
+                && receipt
+                    .delivery_time
+                    .is_some_and(|time| receipt.order.accepts_delivery_at(time))
+        })
+        .count();
+
+    vec![
+        (
+            format!(
+                "Emergencies delivered within {}",
+                units::format_duration(slo_s)
+            ),
+            share(
+                emergencies
+                    .iter()
+                    .filter(|latency| **latency <= slo_s)
+                    .count(),
+                placed_emergencies,
+            ),
+        ),
+        (
+            "Emergency delivery time".to_string(),
+            describe_latencies(&emergencies),
+        ),
+        (
+            "Resupply delivery time".to_string(),
+            describe_latencies(&resupplies),
+        ),
+        (
+            "Delivery windows met".to_string(),
+            share(windows_met, windows.len()),
+        ),
+    ]
+}
+
+/// Orders placed in each half hour of the day, by priority
+fn demand_chart(demand: &DemandProfile) -> Block {
+    let mut counts = [
+        vec![0.0; DemandProfile::BUCKETS_PER_DAY],
+        vec![0.0; DemandProfile::BUCKETS_PER_DAY],
+    ];
+    for ((priority, _), buckets) in demand.counts() {
+        let series = &mut counts[matches!(priority, Priority::Resupply) as usize];
+        for (total, count) in series.iter_mut().zip(buckets) {
+            *total += *count as f64;
+        }
+    }
+    // Label every other hour
+    let labels = (0..DemandProfile::BUCKETS_PER_DAY)
+        .map(|bucket| {
+            let minutes = bucket as u64 * DemandProfile::BUCKET_S / 60;
+            match minutes % 120 {
+                0 => format!("{:02}:00", minutes / 60),
+                _ => String::new(),
+            }
+        })
+        .collect::<Vec<_>>();
+    let [emergencies, resupplies] = counts;
+
+    Block::Chart {
+        title: "Orders placed by time of day".to_string(),
+        svg: stacked_bars(
+            &labels,
+            &[
+                ("Emergency", EMERGENCY_COLOR, emergencies),
+                ("Resupply", RESUPPLY_COLOR, resupplies),
+            ],
+        ),
+    }
+}
+
+/// Orders for each destination & how they fared
+fn destinations_table(receipts: &[OrderReceipt]) -> Block {
+    let mut by_destination = HashMap::<DestinationId, Vec<&OrderReceipt>>::new();
+    for receipt in receipts {
+        by_destination
+            .entry(receipt.order.destination)
+            .or_default()
+            .push(receipt);
+    }
+
+    let mut rows = by_destination
+        .into_iter()
+        .map(|(destination, receipts)| {
+            let count = |status: OrderStatus| {
+                receipts
+                    .iter()
+                    .filter(|receipt| receipt.status == status)
+                    .count()
+            };
+            let mut latencies = receipts
+                .iter()
+                .filter_map(|receipt| latency_s(receipt))
+                .collect::<Vec<_>>();
+            latencies.sort_unstable();
+            let latency = |fraction| {
+                percentile(&latencies, fraction).map_or("–".to_string(), units::format_duration)
+            };
+
+            vec![
+                destination.to_string(),
+                receipts.len().to_string(),
+                receipts
+                    .iter()
+                    .filter(|receipt| receipt.order.priority == Priority::Emergency)
+                    .count()
+                    .to_string(),
+                count(OrderStatus::Delivered).to_string(),
+                count(OrderStatus::Failed).to_string(),
+                count(OrderStatus::Spoiled).to_string(),
+                latency(0.5),
+                latency(1.0),
+            ]
+        })
+        .collect::<Vec<_>>();
+    rows.sort_by(|a, b| a[0].cmp(&b[0]));
+
+    Block::Table {
+        header: vec![
+            "Destination",
+            "Orders",
+            "Emergencies",
+            "Delivered",
+            "Failed",
+            "Spoiled",
+            "Median delivery time",
+            "Longest delivery time",
+        ],
+        rows,
+    }
+}
+
+/// Draws a bar for each label, stacking the values of each series, with a legend naming the series
+fn stacked_bars(labels: &[String], series: &[(&str, &str, Vec<f64>)]) -> String {
+    let totals = (0..labels.len())
+        .map(|i| series.iter().map(|(_, _, values)| values[i]).sum::<f64>())
+        .collect::<Vec<_>>();
+    let max = totals.iter().copied().fold(0.0, f64::max).max(f64::EPSILON);
+    let plot_height = CHART_HEIGHT - 2.0 * CHART_MARGIN;
+    let slot = (CHART_WIDTH - 2.0 * CHART_MARGIN) / labels.len().max(1) as f64;
+    let baseline = CHART_HEIGHT - CHART_MARGIN;
+
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="sans-serif" font-size="10">"#,
+        w = CHART_WIDTH,
+        h = CHART_HEIGHT
+    );
+    let _ = write!(
+        svg,
+        r##"<line x1="{m}" y1="{b}" x2="{x}" y2="{b}" stroke="#888"/>"##,
+        m = CHART_MARGIN,
+        b = baseline,
+        x = CHART_WIDTH - CHART_MARGIN
+    );
+    let _ = write!(
+        svg,
+        r#"<text x="{}" y="{}" text-anchor="end">{}</text>"#,
+        CHART_MARGIN - 4.0,
+        CHART_MARGIN + 4.0,
+        trim_number(max)
+    );
+    for (i, label) in labels.iter().enumerate() {
+        let x = CHART_MARGIN + i as f64 * slot;
+        let mut top = baseline;
+        for (_, color, values) in series {
+            let height = values[i] / max * plot_height;
+            if height > 0.0 {
+                top -= height;
+                let _ = write!(
+                    svg,
+                    r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{}"/>"#,
+                    x + slot * 0.1,
+                    top,
+                    slot * 0.8,
+                    height,
+                    color
+                );
+            }
+        }
+        if !label.is_empty() {
+            let _ = write!(
+                svg,
+                r#"<text x="{:.1}" y="{:.1}" text-anchor="middle">{}</text>"#,
+                x + slot / 2.0,
+                baseline + 14.0,
+                escape_html(label)
+            );
+        }
+    }
+    for (i, (name, color, _)) in series.iter().enumerate() {
+        let x = CHART_MARGIN + i as f64 * 100.0;
+        let _ = write!(
+            svg,
+            r#"<rect x="{x}" y="6" width="10" height="10" fill="{color}"/><text x="{}" y="15">{}</text>"#,
+            x + 14.0,
+            escape_html(name)
+        );
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+/// Formats a number with at most two decimal places, dropping any trailing zeros
+fn trim_number(value: f64) -> String {
+    let formatted = format!("{:.2}", value);
+    formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn render_html(title: &str, blocks: &[Block]) -> String {
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+        <style>body {{ font-family: sans-serif; margin: 2em; }} \
+        table {{ border-collapse: collapse; }} \
+        th, td {{ border: 1px solid #ccc; padding: 4px 8px; text-align: left; }}</style>\n\
+        </head>\n<body>\n<h1>{}</h1>\n",
+        escape_html(title),
+        escape_html(title)
+    );
+    for block in blocks {
+        match block {
+            Block::Heading(heading) => {
+                let _ = writeln!(html, "<h2>{}</h2>", escape_html(heading));
+            }
+            Block::Facts(facts) => {
+                html.push_str("<ul>\n");
+                for (label, value) in facts {
+                    let _ = writeln!(
+                        html,
+                        "<li><strong>{}</strong>: {}</li>",
+                        escape_html(label),
+                        escape_html(value)
+                    );
+                }
+                html.push_str("</ul>\n");
+            }
+            Block::Table { header, rows } => {
+                html.push_str("<table>\n<tr>");
+                for cell in header {
+                    let _ = write!(html, "<th>{}</th>", escape_html(cell));
+                }
+                html.push_str("</tr>\n");
+                for row in rows {
+                    html.push_str("<tr>");
+                    for cell in row {
+                        let _ = write!(html, "<td>{}</td>", escape_html(cell));
+                    }
+                    html.push_str("</tr>\n");
+                }
+                html.push_str("</table>\n");
+            }
+            Block::Chart { title, svg } => {
+                let _ = writeln!(
+                    html,
+                    "<figure>\n{}\n<figcaption>{}</figcaption>\n</figure>",
+                    svg,
+                    escape_html(title)
+                );
+            }
+        }
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// Markdown has no escaping for table cells beyond their separators
+fn escape_markdown(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+fn render_markdown(title: &str, blocks: &[Block]) -> String {
+    let mut markdown = format!("# {}\n", title);
+    for block in blocks {
+        markdown.push('\n');
+        match block {
+            Block::Heading(heading) => {
+                let _ = writeln!(markdown, "## {}", heading);
+            }
+            Block::Facts(facts) => {
+                for (label, value) in facts {
+                    let _ = writeln!(markdown, "- **{}**: {}", label, value);
+                }
+            }
+            Block::Table { header, rows } => {
+                let _ = writeln!(markdown, "| {} |", header.join(" | "));
+                let _ = writeln!(markdown, "|{}", " --- |".repeat(header.len()));
+                for row in rows {
+                    let cells = row
+                        .iter()
+                        .map(|cell| escape_markdown(cell))
+                        .collect::<Vec<_>>();
+                    let _ = writeln!(markdown, "| {} |", cells.join(" | "));
+                }
+            }
+            // Inline HTML is passed through by most renderers
+            Block::Chart { title, svg } => {
+                let _ = writeln!(markdown, "**{}**\n\n{}", title, svg);
+            }
+        }
+    }
+    markdown
+}
+
+#[cfg(test)]
+mod test {
+    use schema::{CarrierId, Order, OrderId};
+
+    use super::*;
+    use crate::CarrierStats;
+
+    fn receipt(
+        id: u64,
+        destination: &str,
+        priority: Priority,
+        delivered: Option<u64>,
+    ) -> OrderReceipt {
+        OrderReceipt {
+            order: Order {
+                id: OrderId(id),
+                time: 0,
+                destination: DestinationId::intern(destination),
+                priority,
+                ..Default::default()
+            },
+            status: match delivered {
+                Some(_) => OrderStatus::Delivered,
+                None => OrderStatus::Failed,
+            },
+            queued_at: 0,
+            flight: None,
+            carrier: Some(CarrierId(0)),
+            launch_time: Some(0),
+            delivery_time: delivered,
+            failure: None,
+            proof: None,
+            shipments: vec![],
+            marginal_distance_m: None,
+            wait_s: None,
+        }
+    }
+
+    #[test]
+    fn test_summary() {
+        let receipts = [
+            receipt(1, "Toronto", Priority::Emergency, Some(600)),
+            receipt(2, "Toronto", Priority::Emergency, Some(3_600)),
+            receipt(3, "A&B <Depot>", Priority::Resupply, Some(1_200)),
+            receipt(4, "A&B <Depot>", Priority::Resupply, None),
+        ]
+        .into_iter()
+        .map(|receipt| (receipt.order.id, receipt))
+        .collect::<HashMap<_, _>>();
+        let mut demand = DemandProfile::default();
+        for receipt in receipts.values() {
+            demand.record(&receipt.order);
+        }
+        let report = RunReport {
+            end_time: 86_400,
+            orders_delivered: 3,
+            carriers: vec![CarrierStats {
+                carrier: CarrierId(0),
+                flights: 3,
+                distance_m: 12_500.0,
+                airborne_s: 21_600,
+                idle_s: 64_800,
+                average_load_factor: 0.5,
+            }],
+            demand,
+            receipts,
+            ..Default::default()
+        };
+
+        let facts = slo_facts(&report.receipts.values().cloned().collect::<Vec<_>>());
+        assert_eq!(facts[0].0, "Emergencies delivered within 30m 00s");
+        assert_eq!(facts[0].1, "1 of 2 (50.0%)");
+        assert_eq!(facts[2].1, "median 20m 00s, 95th percentile 20m 00s");
+
+        let html = report.summary(SummaryFormat::Html, Units::Metric);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        // Names are escaped, & both charts are drawn
+        assert!(html.contains("<td>A&amp;B &lt;Depot&gt;</td>"));
+        assert_eq!(html.matches("<svg").count(), 2);
+
+        let markdown = report.summary(SummaryFormat::Markdown, Units::Metric);
+        assert!(markdown.contains("| Toronto | 2 | 2 | 2 | 0 | 0 | 10m 00s | 1h 00m |"));
+        assert!(markdown.contains("| 0 | 3 | 12.5 km | 6h 00m | 18h 00m | 50% |"));
+
+        assert_eq!(
+            SummaryFormat::from_path("reports/day.md"),
+            Ok(SummaryFormat::Markdown)
+        );
+        assert_eq!(
+            SummaryFormat::from_path("day.html"),
+            Ok(SummaryFormat::Html)
+        );
+        assert!(SummaryFormat::from_path("day.txt").is_err());
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::VecDeque;
+use std::num::NonZeroUsize;
+
+/// Rate of emergency arrivals at which every carrier is given over to emergencies
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SurgePolicy {
+    /// Emergencies which must arrive within the window for a surge to start
+    pub threshold: NonZeroUsize,
+    /// Seconds over which emergency arrivals are counted
+    pub window_s: u64,
+}
+
+impl std::str::FromStr for SurgePolicy {
+    type Err = String;
+
+    /// Parses a policy like `6/30`, for six emergencies arriving within thirty minutes
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid surge policy `{}`, expected <EMERGENCIES>/<MINUTES>",
+                s
+            )
+        };
+        let (threshold, minutes) = s.split_once('/').ok_or_else(invalid)?;
+        let minutes = minutes.parse::<u64>().ok().filter(|minutes| *minutes > 0);
+        Ok(Self {
+            threshold: threshold.parse().map_err(|_| invalid())?,
+            window_s: minutes.ok_or_else(invalid)? * 60,
+        })
+    }
+}
+
+/// Tracks the arrival rate of emergencies, entering a surge once it reaches the policy's threshold.
+/// The surge ends once the rate falls to half the threshold, so that it doesn't flap while
+/// arrivals hover around it.
+#[derive(Clone, Debug)]
+pub struct SurgeDetector {
+    policy: SurgePolicy,
+    /// Arrival times of the emergencies within the window, oldest first
+    arrivals: VecDeque<u64>,
+    surging: bool,
+}
+
+impl SurgeDetector {
+    pub fn new(policy: SurgePolicy) -> Self {
+        Self {
+            policy,
+            arrivals: VecDeque::new(),
+            surging: false,
+        }
+    }
+
+    pub fn policy(&self) -> SurgePolicy {
+        self.policy
+    }
+
+    /// Whether emergencies are currently arriving fast enough to be in a surge
+    pub fn is_surging(&self) -> bool {
+        self.surging
+    }
+
+    /// Records the arrival of an emergency at the provided time
+    pub fn observe(&mut self, time: u64) {
+        self.arrivals.push_back(time);
+    }
+
+    /// Forgets arrivals which have left the window, entering or leaving a surge as of the current
+    /// time. Returns whether the scheduler is in a surge.
+    pub fn update(&mut self, current_time: u64) -> bool {
+        let window_start = current_time.saturating_sub(self.policy.window_s);
+        while self
+            .arrivals
+            .front()
+            .is_some_and(|arrival| *arrival <= window_start)
+        {
+            self.arrivals.pop_front();
+        }
+
+        let threshold = self.policy.threshold.get();
+        self.surging = match self.surging {
+            false => self.arrivals.len() >= threshold,
+            true => self.arrivals.len() > threshold / 2,
+        };
+        self.surging
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_surge() {
+        let policy: SurgePolicy = "4/10".parse().expect("policy");
+        assert_eq!(policy.threshold.get(), 4);
+        assert_eq!(policy.window_s, 600);
+        assert!("4".parse::<SurgePolicy>().is_err());
+        assert!("0/10".parse::<SurgePolicy>().is_err());
+        assert!("4/0".parse::<SurgePolicy>().is_err());
+
+        let mut detector = SurgeDetector::new(policy);
+        for time in [50, 100, 200] {
+            detector.observe(time);
+        }
+        assert!(!detector.update(200));
+
+        // Reaching the threshold within the window starts a surge
+        detector.observe(300);
+        assert!(detector.update(300));
+
+        // Which continues while more than half the threshold are still within the window
+        assert!(detector.update(650));
+        assert!(!detector.update(750));
+
+        // Arrivals which have left the window no longer count towards a surge
+        detector.observe(800);
+        detector.observe(900);
+        assert!(!detector.update(900));
+    }
+}
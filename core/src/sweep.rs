@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use schema::{Destination, Order, Runner, Speed};
+
+use crate::compare::{kpi_names, report_kpis};
+use crate::runner::{
+    CARRIER_RANGE_M, DEFAULT_CARRIERS, DEFAULT_CARRIER_SPEED_MPS, MAX_ORDERS_PER_CARRIER,
+};
+use crate::{CsvRunner, DemandProfile, HistoricalAverage, NaiveScheduler, RunReport};
+
+/// Scheduler used for a configuration of a sweep
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SweepScheduler {
+    Naive,
+    /// Forecasting from the scenario's own demand, as if the previous day had the same orders
+    Forecasting,
+}
+
+impl std::str::FromStr for SweepScheduler {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "naive" => Ok(Self::Naive),
+            "forecasting" => Ok(Self::Forecasting),
+            _ => Err(format!("unknown scheduler: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for SweepScheduler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Naive => f.write_str("naive"),
+            Self::Forecasting => f.write_str("forecasting"),
+        }
+    }
+}
+
+/// A single point in the parameter grid of a sweep
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SweepConfig {
+    pub scheduler: SweepScheduler,
+    pub carriers: usize,
+    pub carrier_speed_mps: u64,
+}
+
+/// Runs a scenario once for every combination of parameters, several simulations at a time.
+/// Each simulation runs on its own manually advanced clock, so a day takes seconds rather than
+/// a day.
+pub struct Sweep {
+    destinations: Vec<Destination>,
+    orders: Vec<Order>,
+    schedulers: Vec<SweepScheduler>,
+    carriers: Vec<usize>,
+    carrier_speeds_mps: Vec<u64>,
+    jobs: NonZeroUsize,
+}
+
+/// Outcome of the simulation of each configuration of a sweep, in the order of the grid
+pub type SweepResults = Vec<(SweepConfig, Result<RunReport, String>)>;
+
+impl Sweep {
+    pub fn new(destinations: Vec<Destination>, orders: Vec<Order>) -> Self {
+        Self {
+            destinations,
+            orders,
+            schedulers: vec![SweepScheduler::Naive],
+            carriers: vec![DEFAULT_CARRIERS],
+            carrier_speeds_mps: vec![DEFAULT_CARRIER_SPEED_MPS],
+            jobs: std::thread::available_parallelism().unwrap_or(NonZeroUsize::MIN),
+        }
+    }
+
+    pub fn with_schedulers(mut self, schedulers: Vec<SweepScheduler>) -> Self {
+        self.schedulers = schedulers;
+        self
+    }
+
+    /// Sweep over the provided numbers of carriers
+    pub fn with_carriers(mut self, carriers: Vec<usize>) -> Self {
+        self.carriers = carriers;
+        self
+    }
+
+    /// Sweep over the provided carrier speeds in meters per second
+    pub fn with_carrier_speeds_mps(mut self, carrier_speeds_mps: Vec<u64>) -> Self {
+        self.carrier_speeds_mps = carrier_speeds_mps;
+        self
+    }
+
+    /// Run at most the provided number of simulations at once (defaults to the available cores)
+    pub fn with_jobs(mut self, jobs: NonZeroUsize) -> Self {
+        self.jobs = jobs;
+        self
+    }
+
+    /// Returns every combination of the parameters
+    pub fn configs(&self) -> Vec<SweepConfig> {
+        let mut configs = vec![];
+        for &scheduler in &self.schedulers {
+            for &carriers in &self.carriers {
+                for &carrier_speed_mps in &self.carrier_speeds_mps {
+                    configs.push(SweepConfig {
+                        scheduler,
+                        carriers,
+                        carrier_speed_mps,
+                    });
+                }
+            }
+        }
+        configs
+    }
+
+    /// Simulates every configuration, blocking until all of them have finished
+    pub fn run(&self) -> SweepResults {
+        let configs = self.configs();
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new(vec![None; configs.len()]);
+
+        std::thread::scope(|scope| {
+            for _ in 0..self.jobs.get().min(configs.len()) {
+                scope.spawn(|| loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(config) = configs.get(i) else {
+                        break;
+                    };
+
+                    tracing::info!("simulating {:?}", config);
+                    let result = self.simulate(config);
+                    results.lock().expect("results")[i] = Some(result);
+                });
+            }
+        });
+
+        configs
+            .into_iter()
+            .zip(results.into_inner().expect("results"))
+            .map(|(config, result)| (config, result.expect("result")))
+            .collect()
+    }
+
+    fn simulate(&self, config: &SweepConfig) -> Result<RunReport, String> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .start_paused(true)
+            .build()
+            .map_err(|e| e.to_string())?;
+
+        let destinations: HashMap<_, _> = self
+            .destinations
+            .iter()
+            .map(|destination| (destination.name, destination.clone()))
+            .collect();
+        let scheduler = NaiveScheduler::new(
+            destinations,
+            config.carriers,
+            MAX_ORDERS_PER_CARRIER,
+            config.carrier_speed_mps,
+            CARRIER_RANGE_M,
+        );
+        let scheduler = match config.scheduler {
+            SweepScheduler::Naive => scheduler,
+            SweepScheduler::Forecasting => {
+                let mut demand = DemandProfile::default();
+                for order in &self.orders {
+                    demand.record(order);
+                }
+                let mut history = HistoricalAverage::default();
+                history.observe(&demand);
+                scheduler.with_forecaster(history)
+            }
+        };
+
+        runtime.block_on(async {
+            // Nobody is watching, so updates & alerts are dropped as they're sent
+            let mut runner = CsvRunner::new(self.destinations.clone(), self.orders.clone())
+                .with_speed(Speed::RealTime);
+            drop(runner.stream_updates());
+            drop(runner.stream_alerts());
+
+            runner.run(scheduler).await
+        })
+    }
+}
+
+/// Formats the results of a sweep as CSV, with a row of KPIs for each configuration
+pub fn sweep_csv(results: &SweepResults) -> String {
+    let names = kpi_names().collect::<Vec<_>>();
+    let mut csv = format!(
+        "scheduler,carriers,carrier_speed_mps,{},error\n",
+        names.join(",")
+    );
+    for (config, result) in results {
+        let (kpis, error) = match result {
+            Ok(report) => (report_kpis(report).collect(), String::new()),
+            Err(e) => (
+                vec![String::new(); names.len()],
+                e.replace([',', '\n'], " "),
+            ),
+        };
+        csv.push_str(&format!(
+            "{},{},{},{},{}\n",
+            config.scheduler,
+            config.carriers,
+            config.carrier_speed_mps,
+            kpis.join(","),
+            error
+        ));
+    }
+    csv
+}
+
+#[cfg(test)]
+mod test {
+    use schema::{testkit, Priority};
+
+    use super::*;
+
+    #[test]
+    fn test_sweep() {
+        let destinations = testkit::destinations(&[("Near", 1_000, 0), ("Far", 70_000, 0)]);
+        let orders = vec![
+            testkit::order(25_200, "Near", Priority::Emergency),
+            testkit::order(25_800, "Far", Priority::Emergency),
+        ];
+        let sweep = Sweep::new(destinations.into_values().collect(), orders)
+            .with_schedulers(vec![SweepScheduler::Naive, SweepScheduler::Forecasting])
+            .with_carriers(vec![1, 4])
+            .with_jobs(NonZeroUsize::new(2).expect("jobs"));
+
+        let results = sweep.run();
+        assert_eq!(results.len(), 4);
+        assert_eq!(
+            results[1].0,
+            SweepConfig {
+                scheduler: SweepScheduler::Naive,
+                carriers: 4,
+                carrier_speed_mps: DEFAULT_CARRIER_SPEED_MPS,
+            }
+        );
+        for (_, result) in &results {
+            assert_eq!(result.as_ref().expect("report").orders_delivered, 2);
+        }
+
+        // A header, followed by a row for each configuration
+        let csv = sweep_csv(&results);
+        let rows = csv.lines().collect::<Vec<_>>();
+        assert_eq!(rows.len(), 5);
+        assert!(rows[0].starts_with("scheduler,carriers,carrier_speed_mps,orders_delivered,"));
+        assert!(rows[1].starts_with("naive,1,30,2,0,"));
+    }
+}
@@ -0,0 +1,213 @@
+use std::num::NonZeroU16;
+use std::time::Duration;
+
+use schema::Speed;
+use tokio::time::Instant;
+
+/// Lowers the speed a run is played at while the runner can't keep up with it in wall-clock time,
+/// & raises it back towards the requested speed once it comfortably can again
+pub struct Throttle {
+    requested: Speed,
+    applied: Speed,
+    /// Consecutive ticks which took longer than they were meant to at the applied speed
+    behind: u32,
+    /// Consecutive ticks which would have had time to spare at the next speed up
+    ahead: u32,
+    /// Wall-clock time at which the applied speed was set
+    since: Instant,
+    /// Simulated seconds played since then
+    ticks: u64,
+}
+
+impl Throttle {
+    /// Ticks in a row which must fall behind before slowing down
+    const SLOW_DOWN_AFTER: u32 = 5;
+    /// Ticks in a row which must be comfortably ahead before speeding back up. Much longer than
+    /// slowing down, so that the speed doesn't flap when the runner is only just keeping up.
+    const SPEED_UP_AFTER: u32 = 60;
+    /// Most of a tick at the next speed up which the work may take for the tick to count as ahead
+    const HEADROOM: f64 = 0.5;
+
+    pub fn new(requested: Speed) -> Self {
+        Self {
+            requested,
+            applied: requested,
+            behind: 0,
+            ahead: 0,
+            since: Instant::now(),
+            ticks: 0,
+        }
+    }
+
+    /// Speed the run is currently being played at
+    pub fn applied(&self) -> Speed {
+        self.applied
+    }
+
+    /// Speed the run was asked to be played at
+    pub fn requested(&self) -> Speed {
+        self.requested
+    }
+
+    /// Plays the run at another speed from now on, slowing down again if it can't keep up
+    pub fn request(&mut self, speed: Speed) {
+        *self = Self::new(speed);
+    }
+
+    /// Wall-clock duration of a simulated second at the applied speed
+    pub fn tick(&self) -> Duration {
+        self.applied.adjust_duration(Duration::from_secs(1))
+    }
+
+    /// Wall-clock time at which the next simulated second should start. Worked out from when the
+    /// applied speed was set rather than by adding up ticks, so that seconds which don't divide
+    /// evenly into milliseconds at the speed (or sleeps which overrun) don't drift. Runs which
+    /// have fallen more than a tick behind, e.g. while blocked, carry on from now without rushing.
+    pub fn next_deadline(&mut self) -> Instant {
+        self.ticks += 1;
+        let deadline =
+            self.since + Duration::from_millis(self.applied.wall_millis(self.ticks * 1_000));
+        let now = Instant::now();
+        if deadline + self.tick() < now {
+            self.restart(now);
+        }
+        deadline.max(now)
+    }
+
+    fn restart(&mut self, now: Instant) {
+        self.since = now;
+        self.ticks = 0;
+    }
+
+    /// Records the wall-clock time the work of a simulated second took, adjusting the applied
+    /// speed if the runner has been falling behind (or has caught up) for long enough
+    pub fn observe(&mut self, work: Duration) {
+        if work > self.tick() {
+            self.ahead = 0;
+            self.behind += 1;
+            if self.behind >= Self::SLOW_DOWN_AFTER {
+                self.behind = 0;
+                self.applied = slower(self.applied);
+                self.restart(Instant::now());
+                tracing::warn!(
+                    "falling behind at {}, slowing down to {}",
+                    self.requested,
+                    self.applied
+                );
+            }
+            return;
+        }
+
+        self.behind = 0;
+        if self.applied == self.requested {
+            return;
+        }
+
+        let faster = faster(self.applied, self.requested);
+        match work.as_secs_f64() <= tick_s(faster) * Self::HEADROOM {
+            true => self.ahead += 1,
+            false => self.ahead = 0,
+        }
+        if self.ahead >= Self::SPEED_UP_AFTER {
+            self.ahead = 0;
+            self.applied = faster;
+            self.restart(Instant::now());
+            tracing::info!("caught up, speeding back up to {}", self.applied);
+        }
+    }
+}
+
+fn tick_s(speed: Speed) -> f64 {
+    speed.adjust_duration(Duration::from_secs(1)).as_secs_f64()
+}
+
+/// Returns the speed at half the rate
+fn slower(speed: Speed) -> Speed {
+    match speed {
+        Speed::FastForward(x) if x.get() > 2 => Speed::fast_forward(x.get() / 2).expect("speed"),
+        Speed::FastForward(_) => Speed::RealTime,
+        Speed::RealTime => Speed::SlowMotion(NonZeroU16::new(2).expect("speed")),
+        Speed::SlowMotion(x) => {
+            Speed::SlowMotion(x.saturating_mul(NonZeroU16::new(2).expect("speed")))
+        }
+    }
+}
+
+/// Returns the speed at double the rate, up to at most the requested speed
+fn faster(speed: Speed, requested: Speed) -> Speed {
+    let faster = match speed {
+        Speed::SlowMotion(x) if x.get() > 2 => {
+            Speed::SlowMotion(NonZeroU16::new(x.get() / 2).expect("speed"))
+        }
+        Speed::SlowMotion(_) => Speed::RealTime,
+        Speed::RealTime => Speed::fast_forward(2).expect("speed"),
+        Speed::FastForward(x) => {
+            Speed::FastForward(x.saturating_mul(NonZeroU16::new(2).expect("speed")))
+        }
+    };
+    match tick_s(faster) < tick_s(requested) {
+        true => requested,
+        false => faster,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_throttle() {
+        let requested = Speed::fast_forward(200).expect("speed");
+        let mut throttle = Throttle::new(requested);
+
+        // A single slow tick isn't enough to slow down, but falling behind for a while is
+        throttle.observe(Duration::from_millis(10));
+        throttle.observe(Duration::ZERO);
+        assert_eq!(throttle.applied(), requested);
+        for _ in 0..Throttle::SLOW_DOWN_AFTER {
+            throttle.observe(Duration::from_millis(10));
+        }
+        assert_eq!(throttle.applied(), Speed::fast_forward(100).expect("speed"));
+
+        // Keeping up at the lower speed, but without the headroom to go faster, holds the speed
+        for _ in 0..Throttle::SPEED_UP_AFTER {
+            throttle.observe(Duration::from_millis(4));
+        }
+        assert_eq!(throttle.applied(), Speed::fast_forward(100).expect("speed"));
+
+        // Once there's plenty of time to spare, the speed is restored up to what was requested
+        for _ in 0..Throttle::SPEED_UP_AFTER {
+            throttle.observe(Duration::from_millis(1));
+        }
+        assert_eq!(throttle.applied(), requested);
+
+        // Runs which can't keep up in real-time drop into slow motion
+        let mut throttle = Throttle::new(Speed::RealTime);
+        for _ in 0..Throttle::SLOW_DOWN_AFTER {
+            throttle.observe(Duration::from_secs(2));
+        }
+        assert_eq!(
+            throttle.applied(),
+            Speed::SlowMotion(NonZeroU16::new(2).expect("speed"))
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_deadlines_dont_drift() {
+        for speed in ["real-time", "3x", "7x", "200x", "1/3x"] {
+            let speed = speed.parse::<Speed>().expect("speed");
+            let mut throttle = Throttle::new(speed);
+            let started = Instant::now();
+            // Ticks which take a while, & sleeps which can only wake on the millisecond
+            for _ in 0..600 {
+                tokio::time::advance(Duration::from_micros(700)).await;
+                throttle.observe(Duration::from_micros(700));
+                tokio::time::sleep_until(throttle.next_deadline()).await;
+            }
+
+            let expected = speed.adjust_duration(Duration::from_secs(600));
+            let drift = started.elapsed().abs_diff(expected);
+            assert!(drift <= Duration::from_millis(1), "{}: {:?}", speed, drift);
+        }
+    }
+}
@@ -0,0 +1,414 @@
+use std::collections::HashMap;
+use std::slice;
+
+use itertools::Itertools;
+use schema::geometry::{DistanceMetric, Euclidean};
+use schema::{
+    CarrierId, CarrierMode, Destination, DestinationChanges, Flight, FlightExplanation, FlightId,
+    Order, Priority, Scheduler,
+};
+
+use crate::scheduler::UnfulfilledOrders;
+use crate::{Handover, NaiveScheduler, SimulatedScheduler};
+
+/// Most orders routed at once, beyond which the flights of a launch are left as they were packed
+const MAX_ROUTED_ORDERS: usize = 200;
+
+/// Re-plans the flights packed by a `NaiveScheduler` at each launch as a capacitated vehicle
+/// routing problem, solved by the savings algorithm. The orders packed into the aerial flights
+/// leaving the origin are routed afresh across as many of their carriers as it takes, within each
+/// carrier's capacity & range & every order's delivery window & shelf life. Emergencies are never
+/// delivered later than they were packed to be, & the packed flights are kept unless the routes
+/// cover less distance in all. Carriers left without a route stay behind for later launches.
+#[derive(Clone)]
+pub struct VrpScheduler<M = Euclidean> {
+    scheduler: NaiveScheduler<M>,
+}
+
+impl<M: DistanceMetric> VrpScheduler<M> {
+    /// Routes the flights packed by the provided scheduler, which decides everything else: which
+    /// orders launch when, the carriers held in reserve, surges & so on
+    pub fn new(scheduler: NaiveScheduler<M>) -> Self {
+        Self { scheduler }
+    }
+
+    /// Returns the orders of each flight just launched as they'd be routed, unless the flights
+    /// are better left as they were packed
+    fn route(&self, launched: &[Flight]) -> Option<HashMap<FlightId, Vec<Order>>> {
+        // Carriers waiting elsewhere don't share the origin as their depot
+        let flights = launched
+            .iter()
+            .filter(|flight| flight.mode == CarrierMode::Aerial && flight.departs_from.is_none())
+            .collect::<Vec<_>>();
+        let template = *flights.first()?;
+        let orders = flights
+            .iter()
+            .flat_map(|flight| flight.orders.iter().cloned())
+            .collect::<Vec<_>>();
+        if orders.len() > MAX_ROUTED_ORDERS {
+            return None;
+        }
+        let packed_arrivals = flights
+            .iter()
+            .flat_map(|flight| self.scheduler.delivery_times(flight))
+            .collect::<Vec<_>>();
+
+        let flight = |stops: &[usize]| Flight {
+            orders: stops.iter().map(|i| orders[*i].clone()).collect(),
+            ..template.clone()
+        };
+        let speed_mps = self.scheduler.carrier_speed_mps();
+        let feasible = |stops: &[usize]| {
+            let flight = flight(stops);
+            let packages = flight
+                .orders
+                .iter()
+                .map(|order| order.quantity as usize)
+                .sum::<usize>();
+            let service_m = stops.len() as u64 * flight.phases.service.max_s() * speed_mps;
+            packages <= self.scheduler.max_orders_per_carrier()
+                && self.scheduler.flight_distance(&flight) + service_m as f32
+                    <= self.scheduler.carrier_range_m() as f32
+                && stops
+                    .iter()
+                    .zip(self.scheduler.delivery_times(&flight))
+                    .all(|(i, arrival)| {
+                        let order = &orders[*i];
+                        order.accepts_delivery_at(arrival)
+                            && self.scheduler.is_open_at(order, arrival)
+                            && !order.spoils(flight.launch_time, arrival)
+                            && (order.priority == Priority::Resupply
+                                || arrival <= packed_arrivals[*i])
+                    })
+        };
+        let distance = |from: Option<usize>, to: Option<usize>| {
+            let stop = |stop: Option<usize>| stop.map(|i| &orders[i].destination);
+            self.scheduler
+                .stop_distance(CarrierMode::Aerial, stop(from), stop(to))
+        };
+        let mut routes = savings(orders.len(), distance, feasible);
+
+        let packed_m = flights
+            .iter()
+            .map(|flight| self.scheduler.flight_distance(flight))
+            .sum::<f32>();
+        let routed_m = routes
+            .iter()
+            .map(|stops| self.scheduler.flight_distance(&flight(stops)))
+            .sum::<f32>();
+        if routes.len() > flights.len() || routed_m >= packed_m {
+            return None;
+        }
+        tracing::debug!(
+            "routed {} orders over {:.0}m rather than {:.0}m",
+            orders.len(),
+            routed_m,
+            packed_m
+        );
+
+        // Routes carrying emergencies go on the carriers launched first
+        routes.sort_by_key(|stops| {
+            !stops
+                .iter()
+                .any(|i| orders[*i].priority == Priority::Emergency)
+        });
+        let mut routes = routes.into_iter();
+        Some(
+            flights
+                .iter()
+                .map(|launched| {
+                    let orders = routes
+                        .next()
+                        .map(|stops| flight(&stops).orders)
+                        .unwrap_or_default();
+                    (launched.id, orders)
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Joins stops into routes by the savings algorithm of Clarke & Wright. Each stop starts out on a
+/// route of its own, to & from the depot, & the routes whose joining saves the most distance are
+/// joined in turn wherever the joined route is feasible. Stops are indices, & distances are
+/// between them, or the depot where the stop is `None`.
+pub(crate) fn savings(
+    stops: usize,
+    distance: impl Fn(Option<usize>, Option<usize>) -> f32,
+    feasible: impl Fn(&[usize]) -> bool,
+) -> Vec<Vec<usize>> {
+    let mut pairs = (0..stops)
+        .tuple_combinations()
+        .map(|(i, j)| {
+            let saving =
+                distance(Some(i), None) + distance(None, Some(j)) - distance(Some(i), Some(j));
+            (saving, i, j)
+        })
+        .filter(|(saving, _, _)| *saving >= 0.0)
+        .collect::<Vec<_>>();
+    pairs.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let mut routes = (0..stops).map(|i| Some(vec![i])).collect::<Vec<_>>();
+    let mut route_of = (0..stops).collect::<Vec<_>>();
+    for (_, i, j) in pairs {
+        let (a, b) = (route_of[i], route_of[j]);
+        if a == b {
+            continue;
+        }
+        let mut joined = routes[a].clone().expect("route");
+        let mut second = routes[b].clone().expect("route");
+        // Routes are only joined at their ends, `i` last on the first & `j` first on the second
+        if joined.first() == Some(&i) {
+            joined.reverse();
+        }
+        if second.last() == Some(&j) {
+            second.reverse();
+        }
+        if joined.last() != Some(&i) || second.first() != Some(&j) {
+            continue;
+        }
+        joined.extend(second);
+        // ...though the joined route may be flown either way round
+        if !feasible(&joined) {
+            joined.reverse();
+            if !feasible(&joined) {
+                continue;
+            }
+        }
+
+        for stop in &joined {
+            route_of[*stop] = a;
+        }
+        routes[a] = Some(joined);
+        routes[b] = None;
+    }
+    routes.into_iter().flatten().collect()
+}
+
+impl<M: DistanceMetric> Scheduler for VrpScheduler<M> {
+    type UnfulfilledOrders<'a>
+        = UnfulfilledOrders<'a>
+    where
+        Self: 'a;
+    type LaunchedFlights<'a>
+        = slice::Iter<'a, Flight>
+    where
+        Self: 'a;
+
+    fn unfulfilled_orders(&self) -> Self::UnfulfilledOrders<'_> {
+        self.scheduler.unfulfilled_orders()
+    }
+
+    fn queue_order(&mut self, order: Order) {
+        self.scheduler.queue_order(order)
+    }
+
+    fn launch_flights(&mut self, current_time: u64) -> slice::Iter<'_, Flight> {
+        let launched = self.scheduler.launch_flights(current_time).len();
+        let from = self.scheduler.active_flights().count() - launched;
+        if let Some(routed) = self.route(self.scheduler.launched_since(from)) {
+            self.scheduler.replan_launched(routed);
+        }
+        self.scheduler.launched_since(from).iter()
+    }
+}
+
+impl<M: DistanceMetric + Clone + 'static> SimulatedScheduler for VrpScheduler<M> {
+    fn active_flights(&self) -> Box<dyn Iterator<Item = &Flight> + '_> {
+        Box::new(self.scheduler.active_flights())
+    }
+
+    fn carriers(&self) -> Box<dyn Iterator<Item = CarrierId> + '_> {
+        Box::new(self.scheduler.carriers())
+    }
+
+    fn origin(&self) -> &Destination {
+        self.scheduler.origin()
+    }
+
+    fn max_orders_per_carrier(&self) -> usize {
+        self.scheduler.max_orders_per_carrier()
+    }
+
+    fn carrier_speed_mps(&self) -> u64 {
+        self.scheduler.carrier_speed_mps()
+    }
+
+    fn carrier_range_m(&self) -> u64 {
+        self.scheduler.carrier_range_m()
+    }
+
+    fn delivery_times(&self, flight: &Flight) -> Vec<u64> {
+        self.scheduler.delivery_times(flight)
+    }
+
+    fn flight_distance(&self, flight: &Flight) -> f32 {
+        self.scheduler.flight_distance(flight)
+    }
+
+    fn flight_energy_j(&self, flight: &Flight) -> Option<f32> {
+        self.scheduler.flight_energy_j(flight)
+    }
+
+    fn end_time(&self, flight: &Flight) -> u64 {
+        self.scheduler.end_time(flight)
+    }
+
+    fn position(&self, flight: &Flight, current_time: u64) -> (f32, f32) {
+        self.scheduler.position(flight, current_time)
+    }
+
+    fn hold_launch(&mut self, carrier: CarrierId, launch_time: u64) -> Option<&Flight> {
+        self.scheduler.hold_launch(carrier, launch_time)
+    }
+
+    fn return_to_base(&mut self, carrier: CarrierId, current_time: u64) -> Option<&Flight> {
+        self.scheduler.return_to_base(carrier, current_time)
+    }
+
+    fn take_missed_windows(&mut self) -> Vec<Order> {
+        self.scheduler.take_missed_windows()
+    }
+
+    fn take_spoiled(&mut self) -> Vec<Order> {
+        self.scheduler.take_spoiled()
+    }
+
+    fn take_deferred(&mut self) -> Vec<Order> {
+        self.scheduler.take_deferred()
+    }
+
+    fn take_explanations(&mut self) -> Vec<FlightExplanation> {
+        self.scheduler.take_explanations()
+    }
+
+    fn update_destinations(&mut self, changes: &DestinationChanges) {
+        self.scheduler.update_destinations(changes)
+    }
+
+    fn take_rejected_orders(&mut self) -> Vec<Order> {
+        self.scheduler.take_rejected_orders()
+    }
+
+    fn is_surging(&self) -> bool {
+        self.scheduler.is_surging()
+    }
+
+    fn take_commandeered(&mut self) -> Vec<Flight> {
+        self.scheduler.take_commandeered()
+    }
+
+    fn preview_flights(&self, extra_orders: Vec<Order>, current_time: u64) -> Vec<Flight> {
+        let mut scheduler = self.clone();
+        for order in extra_orders {
+            Scheduler::queue_order(&mut scheduler, order);
+        }
+        Scheduler::launch_flights(&mut scheduler, current_time)
+            .cloned()
+            .collect()
+    }
+
+    fn set_fleet_size(&mut self, carriers: usize) -> usize {
+        self.scheduler.set_fleet_size(carriers)
+    }
+
+    fn hand_over(self: Box<Self>) -> Handover {
+        self.scheduler.into()
+    }
+
+    fn checkpoint(&self) -> Handover {
+        SimulatedScheduler::checkpoint(&self.scheduler)
+    }
+
+    fn restore(self: Box<Self>, checkpoint: Handover) -> Box<dyn SimulatedScheduler> {
+        Box::new(Self::new(self.scheduler.take_over(checkpoint)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use schema::testkit::{self, Harness};
+
+    use super::*;
+
+    #[test]
+    fn test_savings() {
+        // Stops on either side of the depot, along a line
+        let positions = [10.0, -10.0, 12.0, -11.0];
+        let distance = |from: Option<usize>, to: Option<usize>| {
+            let position = |stop: Option<usize>| stop.map_or(0.0, |i| positions[i]);
+            f32::abs(position(from) - position(to))
+        };
+
+        // Stops on the same side are joined, & only so many stops fit on a route
+        let routes = savings(4, distance, |stops| stops.len() <= 2);
+        let mut routes = routes
+            .into_iter()
+            .map(|mut stops| {
+                stops.sort();
+                stops
+            })
+            .collect::<Vec<_>>();
+        routes.sort();
+        assert_eq!(routes, vec![vec![0, 2], vec![1, 3]]);
+
+        // Infeasible routes are never joined
+        assert_eq!(savings(4, distance, |stops| stops.len() <= 1).len(), 4);
+    }
+
+    #[test]
+    fn test_routing() {
+        let destinations = testkit::destinations(&[("East", 0, 10_000), ("West", 0, -10_000)]);
+        let scheduler = || NaiveScheduler::new(destinations.clone(), 4, 2, 30, 160_000);
+        fn queue<S: Scheduler>(harness: &mut Harness<S>) {
+            harness
+                .at(0)
+                .resupply("East")
+                .resupply("West")
+                .resupply("East")
+                .resupply("West");
+        }
+
+        // Resupplies are packed as they arrive, each flight flying out both ways...
+        let mut naive = Harness::new(scheduler());
+        queue(&mut naive);
+        let packed = naive.at(60).launch();
+        assert_eq!(packed.len(), 2);
+        naive.assert_launched_to(&["East", "West"]);
+
+        // ...where routing them sends each flight one way
+        let mut vrp = Harness::new(VrpScheduler::new(scheduler()));
+        queue(&mut vrp);
+        let routed = vrp.at(60).launch();
+        assert_eq!(routed.len(), 2);
+        vrp.assert_launched_to(&["East", "East"]);
+        vrp.assert_launched_to(&["West", "West"]);
+        vrp.assert_unfulfilled(0);
+
+        let distance = |scheduler: &dyn SimulatedScheduler, flights: &[Flight]| {
+            flights
+                .iter()
+                .map(|flight| scheduler.flight_distance(flight))
+                .sum::<f32>()
+        };
+        assert!(distance(vrp.scheduler(), &routed) < distance(naive.scheduler(), &packed));
+
+        // Emergencies aren't held up by being routed through other stops first
+        let mut vrp = Harness::new(VrpScheduler::new(scheduler()));
+        vrp.at(0)
+            .resupply("East")
+            .emergency("East")
+            .resupply("West");
+        let routed = vrp.at(60).launch();
+        let emergency = routed
+            .iter()
+            .find(|flight| {
+                flight
+                    .orders
+                    .iter()
+                    .any(|order| order.priority == Priority::Emergency)
+            })
+            .expect("emergency");
+        assert_eq!(emergency.orders[0].priority, Priority::Emergency);
+    }
+}
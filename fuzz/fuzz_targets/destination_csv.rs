@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use schema::Destination;
+
+// Each line of the CSVs read by `Destination::from_csv` is parsed as a destination
+fuzz_target!(|line: &str| {
+    if let Ok(destination) = line.parse::<Destination>() {
+        let _ = destination.distance_from_other(&Destination::default_origin());
+        let _ = destination.to_csv_line().parse::<Destination>();
+    }
+});
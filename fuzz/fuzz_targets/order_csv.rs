@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use schema::{DestinationId, Order};
+
+// Each line of the CSVs read by `Order::from_csv` is parsed as an order, with every destination
+// resolved to the same one so that the fuzzed names aren't interned
+fuzz_target!(|line: &str| {
+    let resolve = |_: &str| Ok(DestinationId::intern("Fuzzed"));
+    if let Ok(order) = Order::parse_with(line, resolve) {
+        let _ = Order::parse_with(&order.to_csv_line(), resolve);
+    }
+});
@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use prost::Message;
+use schema::proto::server as proto;
+use schema::{StatusUpdate, ToFromProto};
+
+fuzz_target!(|bytes: &[u8]| {
+    if let Ok(message) = proto::StatusUpdate::decode(bytes) {
+        if let Ok(update) = StatusUpdate::try_from_proto(message) {
+            let _ = StatusUpdate::try_from_proto(update.into_proto());
+        }
+    }
+});
@@ -0,0 +1,305 @@
+//! Python bindings for simulating scenarios with the same engine as the server, e.g. to sweep
+//! parameters & analyze the results from a notebook:
+//!
+//! ```python
+//! import scheduler
+//!
+//! scenario = scheduler.Scenario("test_data/destinations.csv", "test_data/orders.csv")
+//! engine = scheduler.SimulationEngine(scenario)
+//! report = engine.run(carriers=5)
+//! print(report.kpis["orders_delivered"])
+//!
+//! for result in engine.sweep(carriers=[5, 10, 20], carrier_speeds_mps=[20, 30]):
+//!     print(result.carriers, result.carrier_speed_mps, result.report.kpis)
+//! ```
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::num::NonZeroUsize;
+
+use pyo3::exceptions::{PyIOError, PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use scheduler_core::{SummaryFormat, Sweep, SweepConfig, SweepScheduler};
+use schema::units::Units;
+use schema::{Destination, Order};
+
+fn block_on<T>(future: impl Future<Output = T>) -> PyResult<T> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+    Ok(runtime.block_on(future))
+}
+
+/// Destinations & the orders placed for them, loaded from CSV files. Defaults to the sample
+/// scenario.
+#[pyclass(frozen)]
+#[derive(Clone)]
+struct Scenario {
+    destinations: Vec<Destination>,
+    orders: Vec<Order>,
+}
+
+#[pymethods]
+impl Scenario {
+    #[new]
+    #[pyo3(signature = (destinations_csv = None, orders_csv = None))]
+    fn new(destinations_csv: Option<&str>, orders_csv: Option<&str>) -> PyResult<Self> {
+        let destinations_csv = destinations_csv.unwrap_or(schema::SAMPLE_DESTINATIONS_CSV_PATH);
+        let orders_csv = orders_csv.unwrap_or(schema::SAMPLE_ORDERS_CSV_PATH);
+        let (destinations, orders) = block_on(async {
+            (
+                Destination::from_csv(destinations_csv).await,
+                Order::from_csv(orders_csv).await,
+            )
+        })?;
+
+        Ok(Self {
+            destinations: destinations.map_err(|e| PyIOError::new_err(e.to_string()))?,
+            orders: orders.map_err(|e| PyIOError::new_err(e.to_string()))?,
+        })
+    }
+
+    /// Names of the destinations
+    #[getter]
+    fn destinations(&self) -> Vec<&'static str> {
+        self.destinations
+            .iter()
+            .map(|destination| destination.name.as_str())
+            .collect()
+    }
+
+    /// Orders as `(time, destination, priority)` tuples, in the order they're listed
+    #[getter]
+    fn orders(&self) -> Vec<(u64, &'static str, String)> {
+        self.orders
+            .iter()
+            .map(|order| {
+                (
+                    order.time,
+                    order.destination.as_str(),
+                    order.priority.to_string(),
+                )
+            })
+            .collect()
+    }
+
+    fn __len__(&self) -> usize {
+        self.orders.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Scenario({} destinations, {} orders)",
+            self.destinations.len(),
+            self.orders.len()
+        )
+    }
+}
+
+/// Simulates a scenario as quickly as possible on a simulated clock, several simulations at a
+/// time when sweeping parameters
+#[pyclass(frozen)]
+struct SimulationEngine {
+    scenario: Scenario,
+    jobs: Option<NonZeroUsize>,
+}
+
+#[pymethods]
+impl SimulationEngine {
+    #[new]
+    #[pyo3(signature = (scenario, jobs = None))]
+    fn new(scenario: Scenario, jobs: Option<usize>) -> Self {
+        Self {
+            scenario,
+            jobs: jobs.and_then(NonZeroUsize::new),
+        }
+    }
+
+    /// Simulates the scenario once with the provided parameters, defaulting to those of the
+    /// server
+    #[pyo3(signature = (scheduler = "naive", carriers = None, carrier_speed_mps = None))]
+    fn run(
+        &self,
+        py: Python<'_>,
+        scheduler: &str,
+        carriers: Option<usize>,
+        carrier_speed_mps: Option<u64>,
+    ) -> PyResult<Report> {
+        let results = self.sweep(
+            py,
+            vec![scheduler.to_string()],
+            carriers.map(|carriers| vec![carriers]),
+            carrier_speed_mps.map(|speed| vec![speed]),
+        )?;
+        let result = results.into_iter().next().expect("result");
+        match (result.report, result.error) {
+            (Some(report), _) => Ok(report),
+            (None, error) => Err(PyRuntimeError::new_err(error.unwrap_or_default())),
+        }
+    }
+
+    /// Simulates the scenario once for every combination of the provided parameters, returning
+    /// the result of each in the order of the grid
+    #[pyo3(signature = (schedulers = vec!["naive".to_string()], carriers = None, carrier_speeds_mps = None))]
+    fn sweep(
+        &self,
+        py: Python<'_>,
+        schedulers: Vec<String>,
+        carriers: Option<Vec<usize>>,
+        carrier_speeds_mps: Option<Vec<u64>>,
+    ) -> PyResult<Vec<SweepResult>> {
+        let schedulers = schedulers
+            .iter()
+            .map(|scheduler| scheduler.parse::<SweepScheduler>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(PyValueError::new_err)?;
+        let mut sweep = Sweep::new(
+            self.scenario.destinations.clone(),
+            self.scenario.orders.clone(),
+        )
+        .with_schedulers(schedulers);
+        if let Some(carriers) = carriers {
+            sweep = sweep.with_carriers(carriers);
+        }
+        if let Some(speeds) = carrier_speeds_mps {
+            sweep = sweep.with_carrier_speeds_mps(speeds);
+        }
+        if let Some(jobs) = self.jobs {
+            sweep = sweep.with_jobs(jobs);
+        }
+
+        // Other Python threads may carry on while the simulations run
+        let results = py.allow_threads(|| sweep.run());
+        Ok(results
+            .into_iter()
+            .map(|(config, result)| SweepResult::new(config, result))
+            .collect())
+    }
+}
+
+/// Outcome of simulating one combination of parameters of a sweep
+#[pyclass(frozen, get_all)]
+struct SweepResult {
+    scheduler: String,
+    carriers: usize,
+    carrier_speed_mps: u64,
+    /// Report of the run, unless it failed
+    report: Option<Report>,
+    /// Why the run failed, if it did
+    error: Option<String>,
+}
+
+impl SweepResult {
+    fn new(config: SweepConfig, result: Result<scheduler_core::RunReport, String>) -> Self {
+        let (report, error) = match result {
+            Ok(report) => (Some(Report(report)), None),
+            Err(e) => (None, Some(e)),
+        };
+        Self {
+            scheduler: config.scheduler.to_string(),
+            carriers: config.carriers,
+            carrier_speed_mps: config.carrier_speed_mps,
+            report,
+            error,
+        }
+    }
+}
+
+/// Report of a completed run
+#[pyclass(frozen)]
+#[derive(Clone)]
+struct Report(scheduler_core::RunReport);
+
+#[pymethods]
+impl Report {
+    #[getter]
+    fn start_time(&self) -> u64 {
+        self.0.start_time
+    }
+
+    #[getter]
+    fn end_time(&self) -> u64 {
+        self.0.end_time
+    }
+
+    /// Value of each of the KPIs compared between runs, by name
+    #[getter]
+    fn kpis(&self) -> HashMap<&'static str, f64> {
+        self.0.kpis().collect()
+    }
+
+    /// Utilization of each of the carriers, ordered by identifier
+    #[getter]
+    fn carriers(&self) -> Vec<HashMap<&'static str, f64>> {
+        self.0
+            .carriers
+            .iter()
+            .map(|stats| {
+                HashMap::from([
+                    ("carrier", stats.carrier.0 as f64),
+                    ("flights", stats.flights as f64),
+                    ("distance_m", stats.distance_m as f64),
+                    ("airborne_s", stats.airborne_s as f64),
+                    ("idle_s", stats.idle_s as f64),
+                    ("average_load_factor", stats.average_load_factor as f64),
+                ])
+            })
+            .collect()
+    }
+
+    /// Lifecycle of each order queued during the run, as `(order, status, delivery_time)` tuples
+    /// ordered by identifier
+    #[getter]
+    fn receipts(&self) -> Vec<(u64, String, Option<u64>)> {
+        let mut receipts = self
+            .0
+            .receipts
+            .iter()
+            .map(|(id, receipt)| {
+                let receipt = receipt.clone().as_of(self.0.end_time);
+                (id.0, format!("{:?}", receipt.status), receipt.delivery_time)
+            })
+            .collect::<Vec<_>>();
+        receipts.sort_unstable_by_key(|(id, _, _)| *id);
+        receipts
+    }
+
+    /// The report as it's written by the server, to be parsed with e.g. `json.loads`
+    fn to_json(&self) -> String {
+        self.0.to_json().to_string()
+    }
+
+    /// Renders the report as an HTML or Markdown document, see `SUMMARY_PATH`
+    #[pyo3(signature = (format = "markdown", units = "metric"))]
+    fn summary(&self, format: &str, units: &str) -> PyResult<String> {
+        let format = match format {
+            "html" => SummaryFormat::Html,
+            "markdown" => SummaryFormat::Markdown,
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "unknown format `{}`, expected `html` or `markdown`",
+                    format
+                )))
+            }
+        };
+        let units = units.parse::<Units>().map_err(PyValueError::new_err)?;
+        Ok(self.0.summary(format, units))
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Report({} delivered, {} unfulfilled)",
+            self.0.orders_delivered, self.0.unfulfilled_orders
+        )
+    }
+}
+
+#[pymodule]
+fn scheduler(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
+    m.add_class::<Scenario>()?;
+    m.add_class::<SimulationEngine>()?;
+    m.add_class::<SweepResult>()?;
+    m.add_class::<Report>()?;
+    Ok(())
+}
@@ -1,9 +1,15 @@
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    tonic_build::configure().out_dir("src/proto").compile(
-        &[
-            "proto/server.proto",
-        ],
-        &["proto/"],
-    )?;
+    // Generated into `OUT_DIR` rather than the source tree, as what's generated depends on the
+    // features the crate is built with
+    let grpc = std::env::var_os("CARGO_FEATURE_GRPC").is_some();
+    tonic_build::configure()
+        .build_client(grpc)
+        .build_server(grpc)
+        .compile(
+            &[
+                "proto/server.proto",
+            ],
+            &["proto/"],
+        )?;
     Ok(())
 }
@@ -0,0 +1,39 @@
+use crate::FlightId;
+
+/// Conditions which monitoring clients should bring to an operator's attention
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AlertKind {
+    /// An emergency order has been waiting too long for a carrier
+    EmergencyWaiting,
+    /// A carrier has failed during a flight
+    CarrierFailure,
+    /// A delivery has not been made within its service level objective
+    SloViolated,
+    /// An order could no longer be delivered within its delivery window
+    DeliveryWindowMissed,
+    /// A launch has been held to keep its carrier separated from the others
+    LaunchHeld,
+    /// Emergencies have started arriving fast enough for every carrier to be given over to them
+    SurgeStarted,
+    /// Emergencies are no longer arriving fast enough to be in a surge
+    SurgeEnded,
+    /// The simulation has stopped unexpectedly, e.g. as its scheduler panicked
+    SimulationFaulted,
+    /// One of the alert rules configured by the operator has held for as long as it allows
+    RuleTriggered,
+    /// An emergency flight is held on the ground until its launch is approved
+    ApprovalPending,
+}
+
+/// An `Alert` notifies monitoring clients of a notable event during the simulation
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Alert {
+    /// Timestamp in __seconds__ at which the alert was raised
+    pub time: u64,
+    /// The kind of condition which raised the alert
+    pub kind: AlertKind,
+    /// Human-readable description of the alert
+    pub message: String,
+    /// Flight the alert concerns, e.g. one awaiting approval to launch
+    pub flight: Option<FlightId>,
+}
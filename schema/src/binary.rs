@@ -0,0 +1,359 @@
+//! A compact binary format for scenarios with far more orders than are practical to keep in a CSV.
+//! Each order is a fixed-width record, sorted by the time it's placed, so that a run can stream
+//! the orders as it reaches them rather than loading them all up front.
+//!
+//! All values are little-endian. A file starts with a header:
+//! - the magic bytes `SCHORD` followed by the format version
+//! - the number of orders, & the times the first & last are placed at (`u64`s)
+//! - the number of destinations named by the orders (`u32`), then each name as a `u16` length
+//!   followed by its UTF-8 bytes
+//!
+//! Followed by a 36 byte record for each order: its time (`u64`), the index of its destination
+//! (`u32`), its priority & which of its delivery window bounds & shelf life are set (`u8`s), its
+//! shelf life in minutes (`u16`), the earliest & latest delivery times (`u64`s), then the number of
+//! packages making up the order (`u32`). Values which aren't set are zero. Files of the first
+//! version have 32 byte records, lacking the number of packages.
+
+use std::collections::HashMap;
+use std::io::{BufReader, BufWriter, Read, Write};
+
+use crate::{DestinationId, Order, Priority};
+
+const MAGIC: &[u8; 6] = b"SCHORD";
+const VERSION: u16 = 2;
+const RECORD_BYTES: usize = 36;
+/// Size of the records of the first version, which had no number of packages
+const V1_RECORD_BYTES: usize = 32;
+const EARLIEST_SET: u8 = 0b01;
+const LATEST_SET: u8 = 0b10;
+const SHELF_LIFE_SET: u8 = 0b100;
+
+/// Whether the file at the provided path is in the binary format, rather than e.g. a CSV
+pub fn is_binary(path: &str) -> bool {
+    let mut magic = [0; MAGIC.len()];
+    std::fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .is_ok_and(|_| &magic == MAGIC)
+}
+
+/// Writes orders in the binary format, sorted by the time they're placed
+pub fn write_orders(writer: impl Write, orders: &[Order]) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(writer);
+    let mut sorted = orders.iter().collect::<Vec<_>>();
+    sorted.sort_by_key(|order| order.time);
+
+    let mut names = vec![];
+    let mut indices = HashMap::new();
+    for order in &sorted {
+        indices.entry(order.destination).or_insert_with(|| {
+            names.push(order.destination.as_str());
+            names.len() as u32 - 1
+        });
+    }
+
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    writer.write_all(&(sorted.len() as u64).to_le_bytes())?;
+    for order in [sorted.first(), sorted.last()] {
+        writer.write_all(&order.map_or(0, |order| order.time).to_le_bytes())?;
+    }
+    writer.write_all(&(names.len() as u32).to_le_bytes())?;
+    for name in names {
+        let length = u16::try_from(name.len())
+            .map_err(|_| std::io::Error::other(format!("destination name too long: {}", name)))?;
+        writer.write_all(&length.to_le_bytes())?;
+        writer.write_all(name.as_bytes())?;
+    }
+
+    for order in sorted {
+        let mut record = [0; RECORD_BYTES];
+        record[0..8].copy_from_slice(&order.time.to_le_bytes());
+        record[8..12].copy_from_slice(&indices[&order.destination].to_le_bytes());
+        record[12] = match order.priority {
+            Priority::Emergency => 0,
+            Priority::Resupply => 1,
+        };
+        record[13] = order.earliest_delivery.map_or(0, |_| EARLIEST_SET)
+            | order.latest_delivery.map_or(0, |_| LATEST_SET)
+            | order.shelf_life.map_or(0, |_| SHELF_LIFE_SET);
+        let shelf_life_minutes = order.shelf_life.map_or(0, |shelf_life| shelf_life / 60);
+        record[14..16].copy_from_slice(
+            &u16::try_from(shelf_life_minutes)
+                .map_err(|_| std::io::Error::other("shelf life too long"))?
+                .to_le_bytes(),
+        );
+        record[16..24].copy_from_slice(&order.earliest_delivery.unwrap_or(0).to_le_bytes());
+        record[24..32].copy_from_slice(&order.latest_delivery.unwrap_or(0).to_le_bytes());
+        record[32..36].copy_from_slice(&order.quantity.to_le_bytes());
+        writer.write_all(&record)?;
+    }
+    writer.flush()
+}
+
+/// Summary of the orders in a binary orders file, read from its header
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OrdersFile {
+    path: String,
+    /// Number of orders in the file
+    pub count: u64,
+    /// Time in __seconds__ the first order is placed at, zero if there are none
+    pub first_time: u64,
+    /// Time in __seconds__ the last order is placed at, zero if there are none
+    pub last_time: u64,
+}
+
+impl OrdersFile {
+    /// Reads the header of the binary orders file at the provided path
+    pub fn open(path: &str) -> Result<Self, String> {
+        Ok(OrderReader::open(path)?.file)
+    }
+
+    /// Starts streaming the orders from the file, however many times it's been read before
+    pub fn orders(&self) -> Result<OrderReader, String> {
+        OrderReader::open(&self.path)
+    }
+
+    /// Reads every order from the file
+    pub fn read_all(&self) -> Result<Vec<Order>, String> {
+        self.orders()?.collect()
+    }
+}
+
+/// Streams the orders from a binary orders file in the order they're placed, see `OrdersFile`
+pub struct OrderReader {
+    file: OrdersFile,
+    reader: BufReader<std::fs::File>,
+    destinations: Vec<DestinationId>,
+    /// Size of each order's record, which depends on the version of the file
+    record_bytes: usize,
+    /// Orders yet to be read
+    remaining: u64,
+    /// Time the most recently read order was placed at
+    last_time: u64,
+}
+
+impl OrderReader {
+    fn open(path: &str) -> Result<Self, String> {
+        let invalid = |e: std::io::Error| format!("invalid orders file {}: {}", path, e);
+        let file = std::fs::File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut reader = BufReader::with_capacity(1 << 16, file);
+
+        let mut magic = [0; MAGIC.len()];
+        reader.read_exact(&mut magic).map_err(invalid)?;
+        if &magic != MAGIC {
+            return Err(format!("{} isn't a binary orders file", path));
+        }
+        let version = u16::from_le_bytes(read(&mut reader).map_err(invalid)?);
+        if !(1..=VERSION).contains(&version) {
+            return Err(format!(
+                "unsupported version {} of orders file {}",
+                version, path
+            ));
+        }
+
+        let mut u64 = || read(&mut reader).map(u64::from_le_bytes).map_err(invalid);
+        let (count, first_time, last_time) = (u64()?, u64()?, u64()?);
+        let destinations = (0..u32::from_le_bytes(read(&mut reader).map_err(invalid)?))
+            .map(|_| {
+                let length = u16::from_le_bytes(read(&mut reader)?);
+                let mut name = vec![0; length as usize];
+                reader.read_exact(&mut name)?;
+                String::from_utf8(name)
+                    .map(|name| DestinationId::intern(&name))
+                    .map_err(std::io::Error::other)
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(invalid)?;
+
+        Ok(Self {
+            file: OrdersFile {
+                path: path.to_string(),
+                count,
+                first_time,
+                last_time,
+            },
+            reader,
+            destinations,
+            record_bytes: match version {
+                1 => V1_RECORD_BYTES,
+                _ => RECORD_BYTES,
+            },
+            remaining: count,
+            last_time: 0,
+        })
+    }
+
+    fn read_order(&mut self) -> Result<Order, String> {
+        let path = &self.file.path;
+        let mut record = [0; RECORD_BYTES];
+        self.reader
+            .read_exact(&mut record[..self.record_bytes])
+            .map_err(|e| format!("invalid orders file {}: {}", path, e))?;
+        let u64_at = |i: usize| u64::from_le_bytes(record[i..i + 8].try_into().expect("u64"));
+        let index = u32::from_le_bytes(record[8..12].try_into().expect("u32"));
+
+        let order = Order {
+            time: u64_at(0),
+            destination: *self
+                .destinations
+                .get(index as usize)
+                .ok_or_else(|| format!("invalid destination {} in {}", index, path))?,
+            priority: match record[12] {
+                0 => Priority::Emergency,
+                1 => Priority::Resupply,
+                priority => return Err(format!("invalid priority {} in {}", priority, path)),
+            },
+            earliest_delivery: (record[13] & EARLIEST_SET != 0).then(|| u64_at(16)),
+            latest_delivery: (record[13] & LATEST_SET != 0).then(|| u64_at(24)),
+            shelf_life: (record[13] & SHELF_LIFE_SET != 0)
+                .then(|| u16::from_le_bytes([record[14], record[15]]) as u64 * 60),
+            quantity: match u32::from_le_bytes(record[32..36].try_into().expect("u32")) {
+                // Orders of the first version are of a single package
+                0 => 1,
+                quantity => quantity,
+            },
+            ..Default::default()
+        };
+        if order.time < self.last_time {
+            return Err(format!("orders in {} aren't sorted by time", path));
+        }
+        self.last_time = order.time;
+        Ok(order)
+    }
+}
+
+impl Iterator for OrderReader {
+    type Item = Result<Order, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let order = self.read_order();
+        // Nothing more can be read once the file turns out to be invalid
+        if order.is_err() {
+            self.remaining = 0;
+        }
+        Some(order)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining as usize, Some(self.remaining as usize))
+    }
+}
+
+fn read<const N: usize>(reader: &mut impl Read) -> std::io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let order = |time, destination, priority| Order {
+            time,
+            destination: DestinationId::intern(destination),
+            priority,
+            ..Default::default()
+        };
+        let orders = vec![
+            order(600, "Toronto", Priority::Resupply),
+            Order {
+                earliest_delivery: Some(1_200),
+                latest_delivery: Some(3_600),
+                ..order(60, "Rochester", Priority::Emergency)
+            },
+            Order {
+                latest_delivery: Some(7_200),
+                ..order(600, "Toronto", Priority::Emergency)
+            },
+            Order {
+                shelf_life: Some(45 * 60),
+                ..order(900, "Toronto", Priority::Resupply)
+            },
+            Order {
+                quantity: 3,
+                ..order(1_200, "Rochester", Priority::Resupply)
+            },
+        ];
+
+        let path = std::env::temp_dir().join(format!("orders-{}.bin", std::process::id()));
+        let path = path.to_str().expect("path");
+        write_orders(std::fs::File::create(path).expect("create"), &orders).expect("write");
+        assert!(is_binary(path));
+
+        let file = OrdersFile::open(path).expect("open");
+        assert_eq!(
+            (file.count, file.first_time, file.last_time),
+            (5, 60, 1_200)
+        );
+        // Orders are read back in the order they're placed
+        let read = file.read_all().expect("read");
+        assert_eq!(
+            read,
+            vec![
+                orders[1].clone(),
+                orders[0].clone(),
+                orders[2].clone(),
+                orders[3].clone(),
+                orders[4].clone()
+            ]
+        );
+
+        // Files cut off part way through an order are rejected
+        let bytes = std::fs::read(path).expect("read");
+        std::fs::write(path, &bytes[..bytes.len() - 1]).expect("truncate");
+        assert!(OrdersFile::open(path).expect("open").read_all().is_err());
+        std::fs::remove_file(path).expect("remove");
+
+        assert!(!is_binary("../test_data/orders.csv"));
+        // Orders convert back to the CSV lines they're loaded from
+        assert_eq!(orders[0].to_csv_line(), "600, Toronto, Resupply");
+        assert_eq!(orders[2].to_csv_line(), "600, Toronto, Emergency, , 7200");
+        assert_eq!(orders[3].to_csv_line(), "900, Toronto, Resupply, , , 45");
+        assert_eq!(
+            orders[4].to_csv_line(),
+            "1200, Rochester, Resupply, , , , 3"
+        );
+    }
+
+    #[test]
+    fn test_first_version() {
+        // Files of the first version are still read, their orders being of a single package
+        let path = std::env::temp_dir().join(format!("orders-v1-{}.bin", std::process::id()));
+        let path = path.to_str().expect("path");
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(1u16.to_le_bytes());
+        for value in [1u64, 600, 600] {
+            bytes.extend(value.to_le_bytes());
+        }
+        bytes.extend(1u32.to_le_bytes());
+        bytes.extend(7u16.to_le_bytes());
+        bytes.extend(b"Toronto");
+        let mut record = [0; V1_RECORD_BYTES];
+        record[0..8].copy_from_slice(&600u64.to_le_bytes());
+        record[12] = 1;
+        bytes.extend(record);
+        std::fs::write(path, bytes).expect("write");
+
+        let read = OrdersFile::open(path)
+            .expect("open")
+            .read_all()
+            .expect("read");
+        std::fs::remove_file(path).expect("remove");
+        assert_eq!(
+            read,
+            vec![Order {
+                time: 600,
+                destination: DestinationId::intern("Toronto"),
+                priority: Priority::Resupply,
+                ..Default::default()
+            }]
+        );
+    }
+}
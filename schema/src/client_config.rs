@@ -0,0 +1,120 @@
+use crate::units::Units;
+
+/// Settings pushed to each monitoring client as it subscribes, so that operators can tune how
+/// clients behave from the server. Anything left unset is up to each client.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ClientConfig {
+    /// Bounds the map frames, rather than fitting itself to the destinations
+    pub extents: Option<MapExtents>,
+    pub units: Option<Units>,
+    pub theme: Option<ThemeHint>,
+    /// Names of optional features to turn on, which clients ignore if they don't know them
+    pub features: Vec<String>,
+    /// Names of the destinations in the server's scenario, which its orders & flights are for.
+    /// Destinations are only identified by name on the wire, so clients can check theirs match.
+    pub destination_names: Vec<String>,
+}
+
+impl ClientConfig {
+    pub fn has_feature(&self, name: &str) -> bool {
+        self.features.iter().any(|feature| feature == name)
+    }
+}
+
+/// Bounds of a map in meters, in the same frame as the destinations
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MapExtents {
+    pub west_m: i64,
+    pub north_m: i64,
+    pub east_m: i64,
+    pub south_m: i64,
+}
+
+impl std::str::FromStr for MapExtents {
+    type Err = String;
+
+    /// Parses extents listed as `west_m, north_m, east_m, south_m`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid extents `{}`, expected `west, north, east, south`",
+                s
+            )
+        };
+        let bounds = s
+            .split(',')
+            .map(|bound| bound.trim().parse::<i64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| invalid())?;
+        let [west_m, north_m, east_m, south_m] = bounds[..] else {
+            return Err(invalid());
+        };
+        if west_m >= east_m || south_m >= north_m {
+            return Err(format!("extents `{}` are empty", s));
+        }
+
+        Ok(Self {
+            west_m,
+            north_m,
+            east_m,
+            south_m,
+        })
+    }
+}
+
+/// Theme clients are asked to display in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ThemeHint {
+    Light,
+    Dark,
+}
+
+impl std::str::FromStr for ThemeHint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "light" => Ok(Self::Light),
+            "dark" => Ok(Self::Dark),
+            _ => Err(format!("unknown theme `{}`, expected `light` or `dark`", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ToFromProto;
+
+    #[test]
+    fn test_client_config() {
+        assert_eq!(
+            "-5000, 4000, 6000, -3000".parse::<MapExtents>(),
+            Ok(MapExtents {
+                west_m: -5000,
+                north_m: 4000,
+                east_m: 6000,
+                south_m: -3000,
+            })
+        );
+        assert!("0, 0, 10, 10".parse::<MapExtents>().is_err());
+        assert!("0, 10, 10".parse::<MapExtents>().is_err());
+
+        let config = ClientConfig {
+            extents: Some("0, 10, 10, 0".parse().expect("extents")),
+            units: Some(Units::Imperial),
+            theme: Some(ThemeHint::Dark),
+            features: vec!["admin".to_string()],
+            destination_names: vec!["Rochester".to_string(), "Toronto".to_string()],
+        };
+        assert_eq!(
+            ClientConfig::try_from_proto(config.clone().into_proto()),
+            Ok(config)
+        );
+        // Unset fields are left unset, rather than defaulting
+        assert_eq!(
+            ClientConfig::try_from_proto(ClientConfig::default().into_proto()),
+            Ok(ClientConfig::default())
+        );
+    }
+}
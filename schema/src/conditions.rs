@@ -0,0 +1,50 @@
+use crate::csv;
+use crate::{time, CarrierId};
+
+/// A `RangeReduction` models conditions which cut the distance carriers can travel on what remains
+/// of their charge, such as a headwind or a failing battery
+#[derive(Clone, Debug, PartialEq)]
+pub struct RangeReduction {
+    /// Timestamp in __seconds__ at which the range is reduced
+    pub time: u64,
+    /// Fraction of their remaining range which the affected carriers keep
+    pub factor: f32,
+    /// Affected carrier, or every carrier in flight at the time if `None` (e.g. for wind)
+    pub carrier: Option<CarrierId>,
+}
+
+impl RangeReduction {
+    /// Loads range reductions from a CSV file with `time, factor` columns, where the time is
+    /// either in seconds or a UTC date & time, and optionally a `carrier` column
+    pub async fn from_csv(
+        path: &str,
+    ) -> Result<Vec<Self>, Box<dyn std::error::Error + Send + Sync>> {
+        csv::parse_lines(
+            path,
+            |line| {
+                let values = line.split(", ").collect::<Vec<_>>();
+                let factor = values
+                    .get(1)
+                    .ok_or("missing factor")?
+                    .trim()
+                    .parse::<f32>()?;
+                if !(0.0..=1.0).contains(&factor) {
+                    return Err(format!("range factor {} is not between 0 & 1", factor).into());
+                }
+
+                Ok(Self {
+                    time: time::parse_timestamp(values[0])?,
+                    factor,
+                    carrier: values
+                        .get(2)
+                        .map(|carrier| carrier.trim())
+                        .filter(|carrier| !carrier.is_empty())
+                        .map(|carrier| carrier.parse().map(CarrierId))
+                        .transpose()?,
+                })
+            },
+            |_| {},
+        )
+        .await
+    }
+}
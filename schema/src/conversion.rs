@@ -0,0 +1,210 @@
+use std::fmt;
+
+use crate::{time, ToFromProto};
+
+/// Why a protobuf message couldn't be converted, see `ToFromProto::try_from_proto`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConversionError {
+    /// A field which is required was left unset
+    MissingField(&'static str),
+    /// An enum field holds a value unknown to this version, e.g. one added by a newer server
+    UnknownEnumValue { field: &'static str, value: i32 },
+    /// A timestamp or duration can't be represented, e.g. as it's before the epoch
+    TimeOutOfRange(&'static str),
+    /// A field holds a value which can't be parsed, e.g. a malformed identifier
+    InvalidValue { field: &'static str, value: String },
+    /// A nested message, or an element of a repeated field, couldn't be converted
+    Nested {
+        field: &'static str,
+        index: Option<usize>,
+        error: Box<ConversionError>,
+    },
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingField(field) => write!(f, "missing `{}`", field),
+            Self::UnknownEnumValue { field, value } => {
+                write!(f, "unknown value {} for `{}`", value, field)
+            }
+            Self::TimeOutOfRange(field) => write!(f, "`{}` is out of range", field),
+            Self::InvalidValue { field, value } => write!(f, "invalid `{}`: {}", field, value),
+            Self::Nested {
+                field,
+                index: Some(index),
+                error,
+            } => write!(f, "in `{}[{}]`: {}", field, index, error),
+            Self::Nested {
+                field,
+                index: None,
+                error,
+            } => write!(f, "in `{}`: {}", field, error),
+        }
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+impl ConversionError {
+    /// Places the error within the provided field of the message containing it
+    pub fn within(self, field: &'static str) -> Self {
+        Self::Nested {
+            field,
+            index: None,
+            error: Box::new(self),
+        }
+    }
+}
+
+/// Returns a field which must be set
+pub(crate) fn required<T>(field: &'static str, value: Option<T>) -> Result<T, ConversionError> {
+    value.ok_or(ConversionError::MissingField(field))
+}
+
+/// Converts a nested message which must be set
+pub(crate) fn message<T: ToFromProto<P>, P: prost::Message>(
+    field: &'static str,
+    message: Option<P>,
+) -> Result<T, ConversionError> {
+    T::try_from_proto(required(field, message)?).map_err(|e| e.within(field))
+}
+
+/// Converts every element of a repeated field, failing on the first which can't be
+pub(crate) fn repeated<T: ToFromProto<P>, P: prost::Message>(
+    field: &'static str,
+    messages: Vec<P>,
+) -> Result<Vec<T>, ConversionError> {
+    messages
+        .into_iter()
+        .enumerate()
+        .map(|(index, message)| {
+            T::try_from_proto(message).map_err(|error| ConversionError::Nested {
+                field,
+                index: Some(index),
+                error: Box::new(error),
+            })
+        })
+        .collect()
+}
+
+/// Converts the raw value of an enum field, rejecting values unknown to this version
+pub(crate) fn enumeration<E: TryFrom<i32>>(
+    field: &'static str,
+    value: i32,
+) -> Result<E, ConversionError> {
+    E::try_from(value).map_err(|_| ConversionError::UnknownEnumValue { field, value })
+}
+
+pub(crate) fn timestamp(
+    field: &'static str,
+    timestamp: prost_types::Timestamp,
+) -> Result<u64, ConversionError> {
+    time::from_proto_timestamp(timestamp).ok_or(ConversionError::TimeOutOfRange(field))
+}
+
+pub(crate) fn optional_timestamp(
+    field: &'static str,
+    value: Option<prost_types::Timestamp>,
+) -> Result<Option<u64>, ConversionError> {
+    value.map(|value| timestamp(field, value)).transpose()
+}
+
+pub(crate) fn optional_duration(
+    field: &'static str,
+    value: Option<prost_types::Duration>,
+) -> Result<Option<u64>, ConversionError> {
+    value
+        .map(|value| time::from_proto_duration(value).ok_or(ConversionError::TimeOutOfRange(field)))
+        .transpose()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::proto::server as proto;
+    use crate::{Flight, StatusUpdate};
+
+    #[test]
+    fn test_conversion_errors() {
+        let order = proto::Order {
+            destination: "Toronto".to_string(),
+            priority: 7,
+            ..Default::default()
+        };
+        let update = proto::StatusUpdate {
+            time: Some(time::to_proto_timestamp(60)),
+            flights: vec![
+                Flight::default().into_proto(),
+                proto::Flight {
+                    launch_time: Some(time::to_proto_timestamp(0)),
+                    orders: vec![order],
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        // Errors point to the field which couldn't be converted, rather than flights being
+        // dropped from the update
+        let error = StatusUpdate::try_from_proto(update).expect_err("unknown priority");
+        assert_eq!(
+            error.to_string(),
+            "in `flights[1]`: in `orders[0]`: unknown value 7 for `priority`"
+        );
+
+        let update = proto::StatusUpdate::default();
+        assert_eq!(
+            StatusUpdate::try_from_proto(update).expect_err("missing time"),
+            ConversionError::MissingField("time")
+        );
+    }
+
+    #[test]
+    fn test_malformed_updates() {
+        // Truncated messages fail to decode, rather than panicking
+        let update = proto::StatusUpdate {
+            time: Some(time::to_proto_timestamp(60)),
+            flights: vec![Flight::default().into_proto()],
+            ..Default::default()
+        };
+        let bytes = prost::Message::encode_to_vec(&update);
+        assert!(
+            <proto::StatusUpdate as prost::Message>::decode(&bytes[..bytes.len() - 1]).is_err()
+        );
+
+        let update = proto::StatusUpdate {
+            time: Some(prost_types::Timestamp {
+                seconds: -60,
+                nanos: 0,
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            StatusUpdate::try_from_proto(update).expect_err("negative time"),
+            ConversionError::TimeOutOfRange("time")
+        );
+
+        // Coordinates which would overflow the distances to them are rejected
+        let flight = proto::Flight {
+            diversion: Some(proto::Diversion {
+                north_m: i64::MIN,
+                east_m: 0,
+            }),
+            ..Flight::default().into_proto()
+        };
+        assert_eq!(
+            Flight::try_from_proto(flight)
+                .expect_err("coordinates")
+                .to_string(),
+            "in `diversion`: invalid `north_m`: -9223372036854775808"
+        );
+
+        let flight = proto::Flight {
+            service_min_s: 60,
+            service_max_s: 30,
+            ..Flight::default().into_proto()
+        };
+        assert!(Flight::try_from_proto(flight).is_err());
+    }
+}
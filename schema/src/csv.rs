@@ -0,0 +1,89 @@
+use std::path::Path;
+
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+/// Progress of a CSV file being loaded
+#[derive(Default, Clone, Copy, Debug)]
+pub struct LoadProgress {
+    /// Number of records parsed so far
+    pub records: usize,
+    /// Number of bytes read from the file so far (or from its worksheet, for a workbook)
+    pub bytes_read: u64,
+    /// Total size of the file in bytes (or of its worksheet, for a workbook)
+    pub total_bytes: u64,
+}
+
+impl LoadProgress {
+    /// Returns the percentage of the file that has been loaded
+    pub fn percent(&self) -> u64 {
+        match self.total_bytes {
+            0 => 100,
+            total => self.bytes_read * 100 / total,
+        }
+    }
+}
+
+/// Whether a file is an Excel workbook rather than a CSV, going by its extension
+pub fn is_xlsx(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("xlsx"))
+}
+
+/// Streams the lines of a CSV file, parsing each non-empty line into a record.
+/// Progress is reported every 10% of the file, and once more when loading has completed.
+/// Excel workbooks are read a row at a time instead, with the `xlsx` feature.
+pub(crate) async fn parse_lines<T>(
+    path: &str,
+    mut parse: impl FnMut(&str) -> Result<T, Box<dyn std::error::Error + Send + Sync>>,
+    mut on_progress: impl FnMut(LoadProgress),
+) -> Result<Vec<T>, Box<dyn std::error::Error + Send + Sync>> {
+    if is_xlsx(path) {
+        #[cfg(feature = "xlsx")]
+        return crate::xlsx::parse_rows(path, parse, on_progress).await;
+        #[cfg(not(feature = "xlsx"))]
+        return Err(format!(
+            "{} is an Excel workbook, which needs the `xlsx` feature",
+            path
+        )
+        .into());
+    }
+
+    let file = tokio::fs::File::open(path).await?;
+    let mut progress = LoadProgress {
+        total_bytes: file.metadata().await?.len(),
+        ..Default::default()
+    };
+
+    let mut reader = BufReader::new(file);
+    let mut line = String::new();
+    let mut records = vec![];
+    let mut reported_percent = 0;
+    let mut line_number = 0;
+
+    loop {
+        line.clear();
+        match reader.read_line(&mut line).await? {
+            0 => break,
+            n => progress.bytes_read += n as u64,
+        }
+        line_number += 1;
+
+        let record = line.trim_end_matches(['\r', '\n']);
+        if record.is_empty() {
+            continue;
+        }
+        records.push(parse(record).map_err(|e| format!("line {}: {}", line_number, e))?);
+        progress.records = records.len();
+
+        let percent = progress.percent() / 10 * 10;
+        if percent > reported_percent && percent < 100 {
+            reported_percent = percent;
+            on_progress(progress);
+        }
+    }
+
+    on_progress(progress);
+
+    Ok(records)
+}
@@ -0,0 +1,131 @@
+/// An `EnergyModel` estimates the energy carriers spend flying between stops, which grows with the
+/// mass they carry & the wind they fly against. Carriers hold their ground speed whatever the wind,
+/// adjusting their airspeed to do so, so the wind changes the energy spent on a flight but never
+/// its timing. Time spent taking off, landing & at each stop isn't counted.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EnergyModel {
+    /// Mass in kg of a carrier without any orders aboard
+    pub carrier_kg: f32,
+    /// Mass in kg of each order's payload, dropped as the order is delivered
+    pub payload_kg: f32,
+    /// Energy in joules spent carrying a kg for a meter through still air
+    pub j_per_kg_m: f32,
+    /// Velocity of the wind east & north in m/s, i.e. the direction it blows towards
+    pub wind_mps: (f32, f32),
+}
+
+impl EnergyModel {
+    pub const DEFAULT_CARRIER_KG: f32 = 20.0;
+    pub const DEFAULT_PAYLOAD_KG: f32 = 1.75;
+    pub const DEFAULT_J_PER_KG_M: f32 = 0.5;
+
+    /// Returns the energy in joules spent flying a leg of the provided length between two positions
+    /// (east & north in meters) at the provided ground speed, with the provided number of orders
+    /// aboard
+    pub fn leg_j(
+        &self,
+        from: (f32, f32),
+        to: (f32, f32),
+        distance_m: f32,
+        orders: usize,
+        speed_mps: u64,
+    ) -> f32 {
+        let (east_m, north_m) = (to.0 - from.0, to.1 - from.1);
+        let length_m = east_m.hypot(north_m);
+        if distance_m <= 0.0 || length_m <= 0.0 {
+            return 0.0;
+        }
+
+        // Airspeed as a multiple of ground speed, flying along the leg while the wind blows the
+        // carrier off it
+        let speed_mps = speed_mps.max(1) as f32;
+        let airspeed = (east_m / length_m - self.wind_mps.0 / speed_mps)
+            .hypot(north_m / length_m - self.wind_mps.1 / speed_mps);
+        let mass_kg = self.carrier_kg + self.payload_kg * orders as f32;
+        self.j_per_kg_m * mass_kg * distance_m * airspeed
+    }
+}
+
+impl Default for EnergyModel {
+    fn default() -> Self {
+        Self {
+            carrier_kg: Self::DEFAULT_CARRIER_KG,
+            payload_kg: Self::DEFAULT_PAYLOAD_KG,
+            j_per_kg_m: Self::DEFAULT_J_PER_KG_M,
+            wind_mps: (0.0, 0.0),
+        }
+    }
+}
+
+/// Parses a model as `carrier_kg, payload_kg, j_per_kg_m[, wind_east_mps, wind_north_mps]`, e.g.
+/// `20, 1.75, 0.5` or `20, 1.75, 0.5, -4, 2`
+impl std::str::FromStr for EnergyModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid energy model `{}`, expected `carrier_kg, payload_kg, j_per_kg_m[, wind_east_mps, wind_north_mps]`",
+                s
+            )
+        };
+        let values = s
+            .split(',')
+            .map(|value| value.trim().parse::<f32>().map_err(|_| invalid()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let (masses, wind_mps) = match values[..] {
+            [carrier_kg, payload_kg, j_per_kg_m] => {
+                ([carrier_kg, payload_kg, j_per_kg_m], (0.0, 0.0))
+            }
+            [carrier_kg, payload_kg, j_per_kg_m, east, north] => {
+                ([carrier_kg, payload_kg, j_per_kg_m], (east, north))
+            }
+            _ => return Err(invalid()),
+        };
+        if masses
+            .iter()
+            .any(|value| !value.is_finite() || *value < 0.0)
+            || !(wind_mps.0.is_finite() && wind_mps.1.is_finite())
+        {
+            return Err(invalid());
+        }
+
+        let [carrier_kg, payload_kg, j_per_kg_m] = masses;
+        Ok(Self {
+            carrier_kg,
+            payload_kg,
+            j_per_kg_m,
+            wind_mps,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_energy_model() {
+        let model = "10, 2, 0.5".parse::<EnergyModel>().expect("model");
+        assert_eq!(model.wind_mps, (0.0, 0.0));
+
+        // 1km east in still air with two orders aboard: 0.5 J/kg/m * 14kg * 1000m
+        assert_eq!(
+            model.leg_j((0.0, 0.0), (1000.0, 0.0), 1000.0, 2, 20),
+            7000.0
+        );
+        // A carrier which doesn't move spends nothing
+        assert_eq!(model.leg_j((0.0, 0.0), (0.0, 0.0), 0.0, 2, 20), 0.0);
+
+        // A 5 m/s wind blowing east helps carriers east, & hinders them back west
+        let windy = "10, 2, 0.5, 5, 0".parse::<EnergyModel>().expect("model");
+        let east = windy.leg_j((0.0, 0.0), (1000.0, 0.0), 1000.0, 0, 20);
+        let west = windy.leg_j((1000.0, 0.0), (0.0, 0.0), 1000.0, 0, 20);
+        assert_eq!((east, west), (3750.0, 6250.0));
+        // ...& crosswinds cost a little more than still air
+        assert!(windy.leg_j((0.0, 0.0), (0.0, 1000.0), 1000.0, 0, 20) > 5000.0);
+
+        assert!("10, 2".parse::<EnergyModel>().is_err());
+        assert!("10, -2, 0.5".parse::<EnergyModel>().is_err());
+    }
+}
@@ -1,14 +1,13 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
 
-use once_cell::sync::Lazy;
+use ulid::Ulid;
 
-pub static ORIGIN: Lazy<Destination> = Lazy::new(|| Destination {
-    name: DestinationName("ORIGIN".to_string()),
-    north_m: 0,
-    east_m: 0,
-});
+use crate::csv::{self, LoadProgress};
+use crate::geometry::{DistanceMetric, Euclidean};
+use crate::{json, time, DestinationId, EnergyModel};
 
-#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Priority {
     Emergency,
     #[default]
@@ -20,23 +19,19 @@ impl<'a> TryFrom<&'a str> for Priority {
 
     fn try_from(s: &'a str) -> Result<Self, Self::Error> {
         match s {
-            s if s == "Emergency" => Ok(Self::Emergency),
-            s if s == "Resupply" => Ok(Self::Resupply),
+            "Emergency" => Ok(Self::Emergency),
+            "Resupply" => Ok(Self::Resupply),
             _ => Err("invalid priority".to_string()),
         }
     }
 }
 
-#[derive(Default, Clone, Debug, PartialEq, Eq, Hash)]
-pub struct DestinationName(String);
-
-impl DestinationName {
-    pub fn from_str(s: &str) -> Self {
-        Self(s.to_string())
-    }
-
-    pub fn to_string(&self) -> String {
-        self.0.clone()
+impl std::fmt::Display for Priority {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Emergency => f.write_str("Emergency"),
+            Self::Resupply => f.write_str("Resupply"),
+        }
     }
 }
 
@@ -44,152 +39,1146 @@ impl DestinationName {
 #[derive(Default, Clone, Debug, PartialEq, Eq)]
 pub struct Destination {
     /// The name of the destination
-    pub name: DestinationName,
-    /// Destination's y-offset from the origin/nest in meters
+    pub name: DestinationId,
+    /// Destination's y-coordinate in meters, in the same frame as the origin/nest
     pub north_m: i64,
-    /// Destination's x-offset from the origin/nest in meters
+    /// Destination's x-coordinate in meters, in the same frame as the origin/nest
     pub east_m: i64,
+    /// Region in which the destination is grouped with its neighbors, if any
+    pub region: Option<String>,
+    /// Time of day the destination accepts deliveries, if it isn't always open
+    pub hours: Option<OperatingHours>,
 }
 
 impl Destination {
-    pub fn from_csv(path: &str) -> Result<Vec<Self>, Box<dyn std::error::Error>> {
-        let csv_bytes = std::fs::read(path)?;
-        let mut destinations = vec![];
-
-        for line in String::from_utf8(csv_bytes)?.lines() {
-            let values = line.split(", ").collect::<Vec<_>>();
-            destinations.push(Self {
-                name: DestinationName(values[0].to_string()),
-                north_m: values[1].parse::<i64>()?,
-                east_m: values[2].parse::<i64>()?,
-            });
+    /// The nest which carriers launch from & return to, unless a scenario places it elsewhere
+    pub fn default_origin() -> Self {
+        Self {
+            name: DestinationId::from("ORIGIN"),
+            ..Default::default()
         }
+    }
 
-        Ok(destinations)
+    /// Furthest in meters a destination may be from the origin along either axis, so that the
+    /// distances between destinations can't overflow
+    pub const MAX_COORDINATE_M: i64 = 1_000_000_000;
+
+    /// Loads destinations from a CSV file with `name, north_m, east_m` columns, optionally a
+    /// `region` column & then an `hours` column of the time it's open, e.g. `06:00-20:00`. With
+    /// the `xlsx` feature, the file may instead be an Excel workbook whose first worksheet has the
+    /// same columns.
+    pub async fn from_csv(
+        path: &str,
+    ) -> Result<Vec<Self>, Box<dyn std::error::Error + Send + Sync>> {
+        Self::from_csv_with_progress(path, |_| {}).await
+    }
+
+    /// Loads destinations from a CSV file, periodically reporting progress
+    pub async fn from_csv_with_progress(
+        path: &str,
+        on_progress: impl FnMut(LoadProgress),
+    ) -> Result<Vec<Self>, Box<dyn std::error::Error + Send + Sync>> {
+        csv::parse_lines(path, |line| Ok(line.parse()?), on_progress).await
     }
 
-    /// Returns the destination's distance from somewhere else in meters
-    fn distance_from(&self, other_north: i64, other_east: i64) -> f32 {
-        // TODO: in real-world applications the precision may become important here,
-        // we'd probably want to use a decimal type for speeds, distances, etc
-        (((self.north_m.abs() - other_north.abs()).pow(2)
-            + (self.east_m.abs() - other_east.abs()).pow(2)) as f32)
-            .sqrt()
+    /// Whether the destination accepts deliveries at a timestamp
+    pub fn is_open_at(&self, timestamp: u64) -> bool {
+        self.hours.is_none_or(|hours| hours.is_open_at(timestamp))
     }
 
-    /// Returns the destination's distance from another destination in meters
+    /// Returns the destination's straight-line distance from another destination in meters
     pub fn distance_from_other(&self, other: &Self) -> f32 {
-        self.distance_from(other.north_m, other.east_m)
+        Euclidean.distance(other, self)
     }
 
-    /// Returns the destination's distance from the origin in meters
-    pub fn distance_from_origin(&self) -> f32 {
-        self.distance_from_other(&ORIGIN)
+    /// Formats the destination as a line of the CSVs read by `from_csv`
+    pub fn to_csv_line(&self) -> String {
+        let mut line = format!("{}, {}, {}", self.name, self.north_m, self.east_m);
+        if self.region.is_some() || self.hours.is_some() {
+            line += &format!(", {}", self.region.as_deref().unwrap_or_default());
+        }
+        if let Some(hours) = self.hours {
+            line += &format!(", {}", hours);
+        }
+        line
     }
 }
 
-/// An `Order` is a request for delivery of _something_ to a particular `Destination`
+impl std::str::FromStr for Destination {
+    type Err = String;
+
+    /// Parses a destination from a line of `name, north_m, east_m[, region[, hours]]` values
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s.split(", ").collect::<Vec<_>>();
+        let coordinate = |i: usize| {
+            values
+                .get(i)
+                .and_then(|value| value.trim().parse::<i64>().ok())
+                .filter(|value| value.unsigned_abs() <= Self::MAX_COORDINATE_M as u64)
+                .ok_or_else(|| format!("invalid destination: {}", s))
+        };
+
+        Ok(Self {
+            name: DestinationId::intern(values[0]),
+            north_m: coordinate(1)?,
+            east_m: coordinate(2)?,
+            region: values
+                .get(3)
+                .map(|region| region.trim())
+                .filter(|region| !region.is_empty())
+                .map(str::to_string),
+            hours: values
+                .get(4)
+                .map(|hours| hours.trim())
+                .filter(|hours| !hours.is_empty())
+                .map(str::parse)
+                .transpose()?,
+        })
+    }
+}
+
+/// Time of day through which a destination accepts deliveries, e.g. a clinic closed overnight
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct OperatingHours {
+    /// Seconds since midnight at which the destination opens
+    pub opens_s: u64,
+    /// Seconds since midnight at which the destination closes, before it opens if it's open past
+    /// midnight
+    pub closes_s: u64,
+}
+
+impl OperatingHours {
+    pub fn is_open_at(&self, timestamp: u64) -> bool {
+        let time_of_day = time::time_of_day(timestamp);
+        match self.opens_s <= self.closes_s {
+            true => (self.opens_s..self.closes_s).contains(&time_of_day),
+            false => time_of_day >= self.opens_s || time_of_day < self.closes_s,
+        }
+    }
+}
+
+/// Parses hours as `OPENS-CLOSES`, with times of day as `HH:MM[:SS]`, e.g. `06:00-20:00`
+impl std::str::FromStr for OperatingHours {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (opens, closes) = s
+            .split_once('-')
+            .ok_or_else(|| format!("invalid operating hours `{}`", s))?;
+        let hours = Self {
+            opens_s: time::parse_time_of_day(opens)? % time::SECONDS_PER_DAY,
+            closes_s: time::parse_time_of_day(closes)? % time::SECONDS_PER_DAY,
+        };
+        match hours.opens_s == hours.closes_s {
+            true => Err(format!("operating hours `{}` are empty", s.trim())),
+            false => Ok(hours),
+        }
+    }
+}
+
+impl std::fmt::Display for OperatingHours {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            time::format_time_of_day(self.opens_s),
+            time::format_time_of_day(self.closes_s)
+        )
+    }
+}
+
+/// Destinations added to & deactivated in a scenario while it's running. Added destinations
+/// replace any existing destination of the same name, reactivating it if it was deactivated.
+/// Orders are no longer accepted for deactivated destinations, though flights already carrying
+/// orders for them still deliver them.
 #[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct DestinationChanges {
+    pub added: Vec<Destination>,
+    pub deactivated: Vec<DestinationId>,
+}
+
+/// An `Order` is a request for delivery of _something_ to a particular `Destination`
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Order {
-    /// Time in __seconds__ _since midnight_ that the order was placed
+    /// Identifier assigned by the runner as the order is queued
+    pub id: OrderId,
+    /// Timestamp in __seconds__ at which the order was placed, see `crate::time`
     pub time: u64,
     /// Unique-ish identifier for the destination
-    pub destination: DestinationName,
+    pub destination: DestinationId,
     /// Priority of the order, used by scheduling logic
     pub priority: Priority,
+    /// Timestamp before which the order must not be delivered, if any
+    pub earliest_delivery: Option<u64>,
+    /// Timestamp by which the order must be delivered, if any
+    pub latest_delivery: Option<u64>,
+    /// Seconds after the flight carrying the order launches by which it must be delivered, after
+    /// which it spoils, if the order is perishable
+    pub shelf_life: Option<u64>,
+    /// Number of packages making up the order, each taking up one of a carrier's slots. Orders of
+    /// more packages than a carrier has room for are split across flights.
+    pub quantity: u32,
+}
+
+impl Default for Order {
+    fn default() -> Self {
+        Self {
+            id: OrderId::default(),
+            time: 0,
+            destination: DestinationId::default(),
+            priority: Priority::default(),
+            earliest_delivery: None,
+            latest_delivery: None,
+            shelf_life: None,
+            quantity: 1,
+        }
+    }
 }
 
 impl Order {
-    pub fn from_csv(path: &str) -> Result<Vec<Self>, Box<dyn std::error::Error>> {
-        let csv_bytes = std::fs::read(path)?;
-        let mut orders = vec![];
+    /// Loads orders from a CSV file with `time, destination, priority` columns, where the time is
+    /// either in seconds or a UTC date & time. Optional `earliest, latest` columns restrict when the
+    /// order may be delivered, either of which may be left empty. An optional `shelf_life` column
+    /// after them marks the order perishable, given in minutes, & an optional `quantity` column
+    /// after that gives the number of packages making up the order (one if left empty). With the
+    /// `xlsx` feature, the file may instead be an Excel workbook whose first worksheet has the same
+    /// columns, where times may also be cells formatted as dates or times of day.
+    pub async fn from_csv(
+        path: &str,
+    ) -> Result<Vec<Self>, Box<dyn std::error::Error + Send + Sync>> {
+        Self::from_csv_with_progress(path, |_| {}).await
+    }
+
+    /// Loads orders from a CSV file, periodically reporting progress. The names of destinations are
+    /// interned as they're read, to be resolved as the run reaches them, so this is only for
+    /// trusted files such as a scenario's, see `from_csv_with` for others.
+    pub async fn from_csv_with_progress(
+        path: &str,
+        on_progress: impl FnMut(LoadProgress),
+    ) -> Result<Vec<Self>, Box<dyn std::error::Error + Send + Sync>> {
+        csv::parse_lines(
+            path,
+            |line| {
+                Ok(Self::parse_with(line, |name| {
+                    Ok(DestinationId::intern(name))
+                })?)
+            },
+            on_progress,
+        )
+        .await
+    }
+
+    /// Loads orders from a CSV file, resolving the names of their destinations with the provided
+    /// function (e.g. `DestinationNames::resolve`) rather than interning them
+    pub async fn from_csv_with(
+        path: &str,
+        resolve: impl Fn(&str) -> Result<DestinationId, String>,
+    ) -> Result<Vec<Self>, Box<dyn std::error::Error + Send + Sync>> {
+        csv::parse_lines(path, |line| Ok(Self::parse_with(line, &resolve)?), |_| {}).await
+    }
 
-        for line in String::from_utf8(csv_bytes)?.lines() {
-            let values = line.split(", ").collect::<Vec<_>>();
-            orders.push(Self {
-                time: values[0].parse::<u64>()?,
-                destination: DestinationName(values[1].to_string()),
-                priority: values[2].try_into()?,
-            });
+    /// Formats the order as a line of the CSVs read by `from_csv`, leaving the delivery window,
+    /// shelf life & quantity columns off entirely if none are set
+    pub fn to_csv_line(&self) -> String {
+        let mut line = format!("{}, {}, {}", self.time, self.destination, self.priority);
+        let quantity = self.quantity != 1;
+        if self.has_delivery_window() || self.shelf_life.is_some() || quantity {
+            let timestamp = |t: Option<u64>| t.map(|t| t.to_string()).unwrap_or_default();
+            line += &format!(
+                ", {}, {}",
+                timestamp(self.earliest_delivery),
+                timestamp(self.latest_delivery)
+            );
         }
+        if self.shelf_life.is_some() || quantity {
+            line += ", ";
+        }
+        if let Some(shelf_life) = self.shelf_life {
+            line += &(shelf_life / 60).to_string();
+        }
+        if quantity {
+            line += &format!(", {}", self.quantity);
+        }
+        line
+    }
 
-        Ok(orders)
+    /// Parses an order from a JSON object with `destination`, `priority` & optional `time`,
+    /// `earliest_delivery`, `latest_delivery`, `shelf_life_minutes` & `quantity` fields, resolving
+    /// the name of its destination with the provided function
+    pub fn from_json(
+        value: &json::Value,
+        resolve: impl FnOnce(&str) -> Result<DestinationId, String>,
+    ) -> Result<Self, String> {
+        let field = |name: &str| {
+            value
+                .get(name)
+                .and_then(json::Value::as_str)
+                .ok_or_else(|| format!("missing `{}`", name))
+        };
+        let timestamp = |name: &str| match value.get(name) {
+            Some(json::Value::String(timestamp)) => time::parse_timestamp(timestamp).map(Some),
+            Some(json::Value::Null) | None => Ok(None),
+            Some(seconds) => seconds
+                .as_u64()
+                .map(Some)
+                .ok_or_else(|| format!("invalid `{}`", name)),
+        };
+
+        Ok(Self {
+            time: timestamp("time")?.unwrap_or_default(),
+            destination: resolve(field("destination")?)?,
+            priority: field("priority")?.try_into()?,
+            earliest_delivery: timestamp("earliest_delivery")?,
+            latest_delivery: timestamp("latest_delivery")?,
+            shelf_life: match value.get("shelf_life_minutes") {
+                Some(json::Value::Null) | None => None,
+                Some(minutes) => Some(minutes.as_u64().ok_or("invalid `shelf_life_minutes`")? * 60),
+            },
+            quantity: match value.get("quantity") {
+                Some(json::Value::Null) | None => 1,
+                Some(quantity) => quantity
+                    .as_u64()
+                    .and_then(|quantity| u32::try_from(quantity).ok())
+                    .filter(|quantity| *quantity > 0)
+                    .ok_or("invalid `quantity`")?,
+            },
+            ..Default::default()
+        })
+    }
+
+    /// Whether a delivery at the given time falls within the order's delivery window
+    pub fn accepts_delivery_at(&self, time: u64) -> bool {
+        self.earliest_delivery
+            .is_none_or(|earliest| time >= earliest)
+            && self.latest_delivery.is_none_or(|latest| time <= latest)
+    }
+
+    /// Whether the order restricts when it may be delivered
+    pub fn has_delivery_window(&self) -> bool {
+        self.earliest_delivery.is_some() || self.latest_delivery.is_some()
+    }
+
+    /// Whether the order is spoiled by the time it's delivered, having launched at the given time
+    pub fn spoils(&self, launch_time: u64, delivery_time: u64) -> bool {
+        self.shelf_life
+            .is_some_and(|shelf_life| delivery_time > launch_time + shelf_life)
+    }
+
+    pub fn to_json(&self) -> json::Value {
+        json::Value::Object(vec![
+            ("time".to_string(), self.time.into()),
+            (
+                "destination".to_string(),
+                self.destination.to_string().into(),
+            ),
+            ("priority".to_string(), self.priority.to_string().into()),
+            (
+                "earliest_delivery".to_string(),
+                self.earliest_delivery.into(),
+            ),
+            ("latest_delivery".to_string(), self.latest_delivery.into()),
+            (
+                "shelf_life_minutes".to_string(),
+                self.shelf_life.map(|shelf_life| shelf_life / 60).into(),
+            ),
+            ("quantity".to_string(), (self.quantity as u64).into()),
+        ])
     }
 }
 
+impl Order {
+    /// Parses an order from a line of the CSVs read by `Order::from_csv`, resolving the name of its
+    /// destination with the provided function
+    pub fn parse_with(
+        s: &str,
+        resolve: impl FnOnce(&str) -> Result<DestinationId, String>,
+    ) -> Result<Self, String> {
+        let values = s.split(", ").collect::<Vec<_>>();
+        if values.len() < 3 {
+            return Err(format!(
+                "expected `time, destination, priority`, found `{}`",
+                s
+            ));
+        }
+        let value = |i: usize| {
+            values
+                .get(i)
+                .map(|value| value.trim())
+                .filter(|value| !value.is_empty())
+        };
+
+        Ok(Self {
+            time: time::parse_timestamp(values[0])?,
+            destination: resolve(values[1])?,
+            priority: values[2].try_into()?,
+            earliest_delivery: value(3).map(time::parse_timestamp).transpose()?,
+            latest_delivery: value(4).map(time::parse_timestamp).transpose()?,
+            shelf_life: value(5)
+                .map(|value| {
+                    value
+                        .parse::<u64>()
+                        .ok()
+                        .and_then(|minutes| minutes.checked_mul(60))
+                        .ok_or_else(|| format!("invalid shelf life `{}`", value))
+                })
+                .transpose()?,
+            quantity: value(6).map(parse_quantity).transpose()?.unwrap_or(1),
+            ..Default::default()
+        })
+    }
+}
+
+impl std::str::FromStr for Order {
+    type Err = String;
+
+    /// Parses an order from a line of the CSVs read by `Order::from_csv`, for a destination whose
+    /// name has already been interned (e.g. as the scenario's destinations were loaded)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse_with(s, |name| {
+            DestinationId::get(name).ok_or_else(|| format!("unknown destination: {}", name))
+        })
+    }
+}
+
+/// Parses the number of packages making up an order, of which there must be at least one
+fn parse_quantity(value: &str) -> Result<u32, String> {
+    value
+        .parse::<u32>()
+        .ok()
+        .filter(|quantity| *quantity > 0)
+        .ok_or_else(|| format!("invalid quantity `{}`", value))
+}
+
+/// Identifies an individual carrier controlled by a scheduler
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CarrierId(pub u32);
+
+impl std::fmt::Display for CarrierId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Identifies an individual order within a run. Orders which haven't been queued yet have the
+/// default identifier of zero.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OrderId(pub u64);
+
+impl std::fmt::Display for OrderId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Identifies an individual flight, across every update it appears in & after it has landed.
+/// Flights which haven't launched yet have the default nil identifier.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FlightId(pub Ulid);
+
+impl FlightId {
+    /// Returns a new, unique identifier
+    pub fn new() -> Self {
+        Self(Ulid::new())
+    }
+}
+
+impl std::fmt::Display for FlightId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::str::FromStr for FlightId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse()
+            .map(Self)
+            .map_err(|_| format!("invalid flight id `{}`", s))
+    }
+}
+
+/// Stage an order has reached in its lifecycle
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrderStatus {
+    /// Waiting to be scheduled into a flight
+    #[default]
+    Queued,
+    /// Waiting for a later launch, as its destination would be closed when flights arrive
+    Deferred,
+    /// Scheduled into a flight which hasn't launched yet, e.g. because it's held for separation
+    Scheduled,
+    /// On board a flight which has launched
+    Launched,
+    Delivered,
+    /// Given up on, e.g. because its window was missed
+    Failed,
+    /// Delivered after its shelf life ran out, see `Order::shelf_life`
+    Spoiled,
+}
+
+/// Lifecycle of an order, from being queued to being delivered or given up on
 #[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct OrderReceipt {
+    pub order: Order,
+    pub status: OrderStatus,
+    /// Timestamp at which the order was queued
+    pub queued_at: u64,
+    /// Flight the order was scheduled into, if any
+    pub flight: Option<FlightId>,
+    /// Carrier of that flight
+    pub carrier: Option<CarrierId>,
+    /// Timestamp at which that flight launches
+    pub launch_time: Option<u64>,
+    /// Timestamp at which the flight delivers the order
+    pub delivery_time: Option<u64>,
+    /// Why the order was given up on, if it was
+    pub failure: Option<String>,
+    /// Confirmation collected as the order was dropped off, once it's been delivered
+    pub proof: Option<DeliveryProof>,
+    /// Flights carrying part of the order each, if its packages were split across several. The
+    /// flight, carrier & times above are those of the last to deliver, set once every package
+    /// has been scheduled.
+    pub shipments: Vec<Shipment>,
+    /// Meters the order added to the distance flown by its flights, i.e. how much shorter they'd
+    /// have been without it, set once every package has been scheduled
+    pub marginal_distance_m: Option<u32>,
+    /// Seconds the order waited from being queued until its flight launched, set alongside it
+    pub wait_s: Option<u64>,
+}
+
+/// Part of an order carried by one of the flights its packages were split across
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Shipment {
+    pub flight: FlightId,
+    pub carrier: CarrierId,
+    /// Number of the order's packages on board
+    pub packages: u32,
+    /// Timestamp at which the flight launches
+    pub launch_time: u64,
+    /// Timestamp at which the flight delivers its packages
+    pub delivery_time: u64,
+    /// Meters the packages added to the distance flown by the flight
+    pub marginal_distance_m: u32,
+}
+
+/// Confirmation a carrier collects as it drops off an order, as with real deliveries
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeliveryProof {
+    /// Where the order was dropped, in meters north & east of the origin
+    pub drop_north_m: i64,
+    pub drop_east_m: i64,
+    /// Hex digest of the photo taken of the order where it was dropped
+    pub photo_hash: String,
+    /// Code given by the recipient to confirm that they received the order
+    pub recipient_code: String,
+}
+
+impl OrderReceipt {
+    /// Returns the receipt as of the provided time, with scheduled orders moving on to launched &
+    /// then delivered (or spoiled) as their flights reach those points. Proof of delivery is only
+    /// included once the order has been delivered.
+    pub fn as_of(mut self, current_time: u64) -> Self {
+        if let (OrderStatus::Scheduled, Some(launch_time)) = (self.status, self.launch_time) {
+            if current_time >= launch_time {
+                self.status = OrderStatus::Launched;
+            }
+        }
+        if let (OrderStatus::Launched, Some(launch_time), Some(delivery_time)) =
+            (self.status, self.launch_time, self.delivery_time)
+        {
+            if current_time >= delivery_time {
+                self.status = match self.order.spoils(launch_time, delivery_time) {
+                    true => OrderStatus::Spoiled,
+                    false => OrderStatus::Delivered,
+                };
+            }
+        }
+        if !matches!(self.status, OrderStatus::Delivered | OrderStatus::Spoiled) {
+            self.proof = None;
+        }
+        self
+    }
+
+    /// Returns the number of the order's packages delivered as of the provided time
+    pub fn packages_delivered(&self, current_time: u64) -> u32 {
+        match self.shipments.is_empty() {
+            true => match self.delivery_time {
+                Some(delivery_time) if current_time >= delivery_time => self.order.quantity,
+                _ => 0,
+            },
+            false => self
+                .shipments
+                .iter()
+                .filter(|shipment| current_time >= shipment.delivery_time)
+                .map(|shipment| shipment.packages)
+                .sum(),
+        }
+    }
+}
+
+/// How a carrier travels between stops
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CarrierMode {
+    /// Flies straight between stops
+    #[default]
+    Aerial,
+    /// Drives along the road grid between stops, see `geometry::Manhattan`
+    Ground,
+}
+
+/// Where a carrier goes once it has made the last stop of its flight
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ReturnPolicy {
+    /// Fly back to the origin, only being available for another flight once it has landed there
+    #[default]
+    ReturnToOrigin,
+    /// Land at the last stop & wait there, available straight away for another flight which
+    /// launches from there rather than the origin. Diverted carriers still return to the origin.
+    WaitAtLastStop,
+}
+
+impl std::str::FromStr for ReturnPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "return" => Ok(Self::ReturnToOrigin),
+            "wait" => Ok(Self::WaitAtLastStop),
+            _ => Err(format!(
+                "unknown return policy `{}`, expected `return` or `wait`",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Default, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Flight {
-    /// Time in __seconds__ _since midnight_ that the flight was launched
+    pub id: FlightId,
+    /// Carrier which is making the flight
+    pub carrier: CarrierId,
+    /// Timestamp in __seconds__ at which the flight was launched
     pub launch_time: u64,
     /// Orders carried by the flight
     pub orders: Vec<Order>,
+    /// Where the flight turned back for the origin without delivering the rest of its orders, if
+    /// it was cut short
+    pub diversion: Option<Diversion>,
+    /// How long the carrier takes to take off & land
+    pub phases: FlightPhases,
+    /// Whether the carrier flies or drives
+    pub mode: CarrierMode,
+    /// Destination at which the carrier waited since its last flight, which it launches from
+    /// rather than the origin
+    pub departs_from: Option<DestinationId>,
+    /// Whether the carrier returns to the origin after its last stop, or waits there
+    pub return_policy: ReturnPolicy,
+}
+
+/// Time a carrier spends taking off from the origin & each stop, and landing at each stop &
+/// back at the origin, during which it holds its position. Between them, the carrier accelerates
+/// from rest up to its cruise speed, then decelerates to come to rest again before each stop (or to
+/// turn back at a diversion).
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FlightPhases {
+    pub takeoff_s: u64,
+    pub landing_s: u64,
+    /// Seconds taken to reach cruise speed from rest, none for an instant start
+    pub accelerate_s: u64,
+    /// Seconds taken to come to rest from cruise speed, none for an instant stop
+    pub decelerate_s: u64,
+    /// Time spent at each destination handing over orders, between landing & taking off again
+    pub service: ServiceTime,
+}
+
+/// Seconds a carrier spends hovering or landed at a stop to hand over its orders
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ServiceTime {
+    /// The same time at every stop
+    Fixed(u64),
+    /// A time sampled uniformly from the inclusive range for each stop. Samples are drawn from the
+    /// flight's id, so every ETA & position computed for the flight agrees on them.
+    Uniform { min_s: u64, max_s: u64 },
+}
+
+impl Default for ServiceTime {
+    fn default() -> Self {
+        Self::Fixed(0)
+    }
+}
+
+impl ServiceTime {
+    /// Longest the carrier may spend at a stop
+    pub fn max_s(&self) -> u64 {
+        match *self {
+            Self::Fixed(s) => s,
+            Self::Uniform { max_s, .. } => max_s,
+        }
+    }
+
+    /// Time the carrier spends at one of the stops of a flight, by the index of the stop's order
+    pub fn sample(&self, flight: FlightId, stop: usize) -> u64 {
+        match *self {
+            Self::Fixed(s) => s,
+            Self::Uniform { min_s, max_s } => {
+                // SplitMix64 of the flight & stop
+                let id = flight.0 .0;
+                let mut x = (id as u64 ^ (id >> 64) as u64)
+                    .wrapping_add((stop as u64 + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+                x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+                x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+                x ^= x >> 31;
+                min_s + x % max_s.saturating_sub(min_s).saturating_add(1)
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for ServiceTime {
+    type Err = String;
+
+    /// Parses either a fixed number of seconds, e.g. `30`, or a range to sample from, e.g. `20-60`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let seconds = |value: &str| {
+            value
+                .trim()
+                .parse::<u64>()
+                .map_err(|_| format!("invalid service time `{}`, expected e.g. 30 or 20-60", s))
+        };
+        match s.split_once('-') {
+            Some((min_s, max_s)) => {
+                let (min_s, max_s) = (seconds(min_s)?, seconds(max_s)?);
+                match min_s <= max_s {
+                    true => Ok(Self::Uniform { min_s, max_s }),
+                    false => Err(format!("invalid service time `{}`, range is reversed", s)),
+                }
+            }
+            None => seconds(s).map(Self::Fixed),
+        }
+    }
+}
+
+impl FlightPhases {
+    /// Seconds spent on the ground for each stop of a flight, including the return to the origin,
+    /// along with at most the time lost getting up to speed & slowing down for it & serving it
+    pub fn per_stop_s(&self) -> u64 {
+        self.takeoff_s
+            + self.landing_s
+            + (self.accelerate_s + self.decelerate_s).div_ceil(2)
+            + self.service.max_s()
+    }
+
+    /// Seconds a leg of the provided length takes from rest to rest, beyond the time it would take
+    /// at a constant cruise speed. Legs too short to reach cruise speed peak below it.
+    fn leg_overhead_s(&self, distance_m: f32, speed_mps: u64) -> f32 {
+        let ramp_s = (self.accelerate_s + self.decelerate_s) as f32;
+        let speed_mps = speed_mps as f32;
+        match distance_m >= speed_mps * ramp_s / 2.0 {
+            true => ramp_s / 2.0,
+            false => (2.0 * distance_m * ramp_s / speed_mps).sqrt() - distance_m / speed_mps,
+        }
+    }
+
+    /// Returns the fraction of a leg of the provided length which has been covered once the
+    /// carrier has flown it for long enough to cover the provided distance at cruise speed
+    fn leg_progress(&self, distance_m: u64, cruise_distance_m: u64, speed_mps: u64) -> f32 {
+        if self.accelerate_s + self.decelerate_s == 0 {
+            return cruise_distance_m as f32 / distance_m as f32;
+        }
+
+        let (distance_m, v) = (distance_m as f32, speed_mps as f32);
+        let (accelerate_s, decelerate_s) = (self.accelerate_s as f32, self.decelerate_s as f32);
+        let duration_s = distance_m / v + self.leg_overhead_s(distance_m, speed_mps);
+        let t = (cruise_distance_m as f32 / v).min(duration_s);
+        // Speed reached before slowing down, & how long is spent reaching it & coming back down
+        let peak_mps = v * duration_s / (accelerate_s + decelerate_s).max(duration_s);
+        let (rising_s, falling_s) = (accelerate_s * peak_mps / v, decelerate_s * peak_mps / v);
+
+        let covered_m = if t < rising_s {
+            peak_mps * t * t / (2.0 * rising_s)
+        } else if t <= duration_s - falling_s {
+            peak_mps * (t - rising_s / 2.0)
+        } else {
+            let left_s = duration_s - t;
+            distance_m - peak_mps * left_s * left_s / (2.0 * falling_s)
+        };
+        (covered_m / distance_m).clamp(0.0, 1.0)
+    }
+}
+
+/// What a carrier is doing at some point during its flight
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlightPhase {
+    TakingOff,
+    Cruising,
+    Landing,
+}
+
+/// Point at which a flight was cut short to return straight to the origin
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Diversion {
+    /// Position in meters, in the same frame as the destinations
+    pub north_m: i64,
+    pub east_m: i64,
+}
+
+impl Diversion {
+    fn waypoint(&self) -> Destination {
+        Destination {
+            name: DestinationId::from("diversion"),
+            north_m: self.north_m,
+            east_m: self.east_m,
+            region: None,
+            hours: None,
+        }
+    }
 }
 
 impl Flight {
-    /// Returns the total distance that will be traveled by the flight
-    fn total_distance(&self, destinations: &HashMap<DestinationName, Destination>) -> f32 {
+    /// Returns where the carrier launches from, the origin unless it waited elsewhere
+    fn start<'a>(
+        &self,
+        destinations: &'a HashMap<DestinationId, Destination>,
+        origin: &'a Destination,
+    ) -> &'a Destination {
+        self.departs_from
+            .and_then(|destination| destinations.get(&destination))
+            .unwrap_or(origin)
+    }
+
+    /// Whether the carrier flies back to the origin at the end of the flight
+    pub fn returns(&self) -> bool {
+        self.diversion.is_some() || self.return_policy == ReturnPolicy::ReturnToOrigin
+    }
+
+    /// Returns each of the points the flight travels between, from the first stop back to the origin
+    /// (unless it waits at its last stop), along with whether the carrier lands there (it turns back
+    /// at a diversion without landing) & the seconds it spends serving the stop once it has
+    fn route<'a>(
+        &'a self,
+        destinations: &'a HashMap<DestinationId, Destination>,
+        origin: &'a Destination,
+    ) -> impl Iterator<Item = (Cow<'a, Destination>, bool, u64)> {
         self.orders
             .iter()
-            .map(|order| destinations.get(&order.destination).expect("destination"))
-            .chain(std::iter::once(Lazy::force(&ORIGIN)))
-            .fold((0.0, Lazy::force(&ORIGIN)), |(traveled, prev), cur| {
-                (traveled + cur.distance_from_other(prev), cur)
+            .enumerate()
+            .map(|(i, order)| {
+                (
+                    Cow::Borrowed(destinations.get(&order.destination).expect("destination")),
+                    true,
+                    self.phases.service.sample(self.id, i),
+                )
             })
+            .chain(
+                self.diversion
+                    .map(|diversion| (Cow::Owned(diversion.waypoint()), false, 0)),
+            )
+            .chain(self.returns().then_some((Cow::Borrowed(origin), true, 0)))
+    }
+
+    /// Returns the seconds spent taking off, then landing & serving the stop, on a leg of the
+    /// route, none if the carrier doesn't move (e.g. several orders for the same destination)
+    fn holds_s(&self, distance_m: u64, takes_off: bool, lands: bool, service_s: u64) -> (u64, u64) {
+        match distance_m {
+            0 => (0, 0),
+            _ => (
+                if takes_off { self.phases.takeoff_s } else { 0 },
+                if lands {
+                    self.phases.landing_s + service_s
+                } else {
+                    0
+                },
+            ),
+        }
+    }
+
+    /// Returns the total distance that will be traveled by the flight
+    pub fn total_distance<M: DistanceMetric + ?Sized>(
+        &self,
+        metric: &M,
+        destinations: &HashMap<DestinationId, Destination>,
+        origin: &Destination,
+    ) -> f32 {
+        self.route(destinations, origin)
+            .fold(
+                (0.0, Cow::Borrowed(self.start(destinations, origin))),
+                |(traveled, prev), (cur, ..)| (traveled + metric.distance(&prev, &cur), cur),
+            )
+            .0
+    }
+
+    /// Returns the energy in joules the carrier will spend on the flight at the provided speed,
+    /// carrying one fewer payload after each stop. Diverted flights are taken to have delivered
+    /// everything aboard by the time they turn back.
+    pub fn energy_j<M: DistanceMetric + ?Sized>(
+        &self,
+        metric: &M,
+        destinations: &HashMap<DestinationId, Destination>,
+        origin: &Destination,
+        model: &EnergyModel,
+        speed_mps: u64,
+    ) -> f32 {
+        let position = |stop: &Destination| (stop.east_m as f32, stop.north_m as f32);
+        self.route(destinations, origin)
+            .enumerate()
+            .fold(
+                (0.0, Cow::Borrowed(self.start(destinations, origin))),
+                |(spent, prev), (i, (cur, ..))| {
+                    let aboard = self.orders.len().saturating_sub(i);
+                    let distance = metric.distance(&prev, &cur);
+                    let leg_j =
+                        model.leg_j(position(&prev), position(&cur), distance, aboard, speed_mps);
+                    (spent + leg_j, cur)
+                },
+            )
+            .0
+    }
+
+    /// Returns the distance which could be covered at cruise speed in the time the flight spends
+    /// traveling, including getting up to speed & slowing down for each stop
+    fn total_travel_m<M: DistanceMetric + ?Sized>(
+        &self,
+        metric: &M,
+        destinations: &HashMap<DestinationId, Destination>,
+        origin: &Destination,
+        speed_mps: u64,
+    ) -> f32 {
+        self.route(destinations, origin)
+            .fold(
+                (0.0, Cow::Borrowed(self.start(destinations, origin))),
+                |(traveled, prev), (cur, ..)| {
+                    let distance = metric.distance(&prev, &cur);
+                    let overhead_s = self.phases.leg_overhead_s(distance, speed_mps);
+                    (traveled + distance + overhead_s * speed_mps as f32, cur)
+                },
+            )
+            .0
+    }
+
+    /// Returns the total seconds the flight will spend taking off, landing & serving its stops
+    fn total_holds_s<M: DistanceMetric + ?Sized>(
+        &self,
+        metric: &M,
+        destinations: &HashMap<DestinationId, Destination>,
+        origin: &Destination,
+    ) -> u64 {
+        self.route(destinations, origin)
+            .fold(
+                (0, Cow::Borrowed(self.start(destinations, origin)), true),
+                |(holds_s, prev, takes_off), (cur, lands, service_s)| {
+                    let distance = metric.distance(&prev, &cur) as u64;
+                    let (takeoff_s, landing_s) =
+                        self.holds_s(distance, takes_off, lands, service_s);
+                    (holds_s + takeoff_s + landing_s, cur, lands)
+                },
+            )
             .0
     }
 
     /// Returns current east/north pos & orders based on the time since launch (x, y, order_num)
     /// TODO: Make a proper `Point` type
-    pub fn current_position(
+    pub fn current_position<M: DistanceMetric + ?Sized>(
         &self,
-        destinations: &HashMap<DestinationName, Destination>,
+        metric: &M,
+        destinations: &HashMap<DestinationId, Destination>,
+        origin: &Destination,
         current_time: u64,
         speed_mps: u64,
     ) -> (f32, f32, usize) {
-        let seconds = current_time - self.launch_time;
+        let (x, y, n, _) = self.locate(metric, destinations, origin, current_time, speed_mps);
+        (x, y, n)
+    }
 
+    /// Returns whether the carrier is taking off, cruising or landing at the current time
+    pub fn phase<M: DistanceMetric + ?Sized>(
+        &self,
+        metric: &M,
+        destinations: &HashMap<DestinationId, Destination>,
+        origin: &Destination,
+        current_time: u64,
+        speed_mps: u64,
+    ) -> FlightPhase {
+        self.locate(metric, destinations, origin, current_time, speed_mps)
+            .3
+    }
+
+    fn locate<M: DistanceMetric + ?Sized>(
+        &self,
+        metric: &M,
+        destinations: &HashMap<DestinationId, Destination>,
+        origin: &Destination,
+        current_time: u64,
+        speed_mps: u64,
+    ) -> (f32, f32, usize, FlightPhase) {
+        // Carriers held before launch wait where they launch from
+        let seconds = current_time.saturating_sub(self.launch_time);
+
+        // Time spent holding position is measured in the distance which could have been covered
         let total_distance_traveled = seconds * speed_mps;
         let mut distance = total_distance_traveled;
-        let mut prev = Lazy::force(&ORIGIN);
-        for (i, dest) in self
-            .orders
-            .iter()
-            .map(|order| destinations.get(&order.destination).expect("destination"))
-            .chain(std::iter::once(Lazy::force(&ORIGIN)))
-            .enumerate()
-        {
-            let dist_between = dest.distance_from_other(prev) as u64;
+        let mut prev = Cow::Borrowed(self.start(destinations, origin));
+        let mut takes_off = true;
+        for (i, (dest, lands, service_s)) in self.route(destinations, origin).enumerate() {
+            let remaining = self.orders.len().saturating_sub(i);
+            let dist_between = metric.distance(&prev, &dest) as u64;
+            if dist_between == 0 {
+                // e.g. a flight diverted right at one of its stops
+                prev = dest;
+                takes_off = lands;
+                continue;
+            }
+
+            let (takeoff_s, landing_s) = self.holds_s(dist_between, takes_off, lands, service_s);
+            if distance < takeoff_s * speed_mps {
+                let (x, y) = (prev.east_m as f32, prev.north_m as f32);
+                return (x, y, remaining, FlightPhase::TakingOff);
+            }
+            distance -= takeoff_s * speed_mps;
 
-            match distance.saturating_sub(dist_between) {
-                d if d == 0 => {
+            let overhead_s = self.phases.leg_overhead_s(dist_between as f32, speed_mps);
+            let travel = dist_between + (overhead_s * speed_mps as f32) as u64;
+            match distance.saturating_sub(travel) {
+                0 => {
                     // Point is on this path
-                    let f = distance as f32 / dist_between as f32;
-                    let north_comp = dest.north_m - prev.north_m;
-                    let east_comp = dest.east_m - prev.east_m;
-
-                    return (
-                        (east_comp as f32 * f) + prev.east_m as f32,
-                        (north_comp as f32 * f) + prev.north_m as f32,
-                        self.orders.len() - i,
-                    );
+                    let progress = self.phases.leg_progress(dist_between, distance, speed_mps);
+                    let (x, y) = metric.interpolate(&prev, &dest, progress);
+
+                    return (x, y, remaining, FlightPhase::Cruising);
+                }
+                d if d < landing_s * speed_mps => {
+                    let (x, y) = (dest.east_m as f32, dest.north_m as f32);
+                    return (x, y, remaining, FlightPhase::Landing);
                 }
                 d => {
-                    distance = d;
+                    distance = d - landing_s * speed_mps;
                 }
             }
 
             prev = dest;
+            takes_off = lands;
         }
 
-        (0.0, 0.0, self.orders.len())
+        // The flight is over, leaving the carrier back at the origin or waiting at its last stop
+        (
+            prev.east_m as f32,
+            prev.north_m as f32,
+            self.orders.len(),
+            FlightPhase::Landing,
+        )
     }
 
-    /// Returns the time that the flight will arrive back at the origin
-    pub fn end_time(
+    /// Returns the time at which each of the flight's orders will be delivered, once the carrier
+    /// has landed at its destination & served the stop
+    pub fn delivery_times<M: DistanceMetric + ?Sized>(
         &self,
-        destinations: &HashMap<DestinationName, Destination>,
+        metric: &M,
+        destinations: &HashMap<DestinationId, Destination>,
+        origin: &Destination,
+        speed_mps: u64,
+    ) -> Vec<u64> {
+        self.orders
+            .iter()
+            .map(|order| destinations.get(&order.destination).expect("destination"))
+            .enumerate()
+            .scan(
+                (0.0, 0, self.start(destinations, origin)),
+                |(traveled, holds_s, prev), (i, cur)| {
+                    let distance = metric.distance(prev, cur);
+                    let service_s = self.phases.service.sample(self.id, i);
+                    let (takeoff_s, landing_s) =
+                        self.holds_s(distance as u64, true, true, service_s);
+                    let overhead_s = self.phases.leg_overhead_s(distance, speed_mps);
+                    *traveled += distance + overhead_s * speed_mps as f32;
+                    *holds_s += takeoff_s + landing_s;
+                    *prev = cur;
+                    Some(self.launch_time + *traveled as u64 / speed_mps + *holds_s)
+                },
+            )
+            .collect()
+    }
+
+    /// Returns the time that the flight will land back at the origin, or finish serving its last
+    /// stop if the carrier waits there
+    pub fn end_time<M: DistanceMetric + ?Sized>(
+        &self,
+        metric: &M,
+        destinations: &HashMap<DestinationId, Destination>,
+        origin: &Destination,
         speed_mps: u64,
     ) -> u64 {
-        self.launch_time + self.total_distance(destinations) as u64 / speed_mps
+        self.launch_time
+            + self.total_travel_m(metric, destinations, origin, speed_mps) as u64 / speed_mps
+            + self.total_holds_s(metric, destinations, origin)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testkit;
+
+    #[test]
+    fn test_malformed_lines() {
+        let order = Order {
+            shelf_life: Some(1_800),
+            quantity: 3,
+            ..testkit::order(25_200, "Toronto", Priority::Emergency)
+        };
+        assert_eq!(order.to_csv_line().parse::<Order>(), Ok(order));
+
+        // Malformed lines are rejected, rather than overflowing or panicking
+        for line in [
+            "25200, Toronto",
+            "-60, Toronto, Resupply",
+            "25200, Toronto, Resupply, , , 18446744073709551615",
+            "25200, Toronto, Resupply, , , , 0",
+            "99999999999-01-01T00:00:00Z, Toronto, Resupply",
+        ] {
+            assert!(line.parse::<Order>().is_err(), "{}", line);
+        }
+        // Names which haven't been interned are rejected, rather than interned as they're parsed
+        assert!("25200, Never Loaded, Resupply".parse::<Order>().is_err());
+        assert_eq!(DestinationId::get("Never Loaded"), None);
+
+        assert!("Toronto, 1000, -2000".parse::<Destination>().is_ok());
+        for line in [
+            "Toronto",
+            "Toronto, 1000",
+            "Toronto, -9223372036854775808, 0",
+            "Toronto, 0, 1000000001",
+            "Toronto, 0, 0, , 06:00",
+            "Toronto, 0, 0, , 06:00-30:00",
+            "Toronto, 0, 0, , 00:00-24:00",
+        ] {
+            assert!(line.parse::<Destination>().is_err(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_operating_hours() {
+        let clinic = "Clinic, 1000, -2000, , 06:00-20:00"
+            .parse::<Destination>()
+            .expect("destination");
+        assert_eq!(clinic.region, None);
+        assert_eq!(
+            clinic.to_csv_line().parse::<Destination>(),
+            Ok(clinic.clone())
+        );
+        assert!(!clinic.is_open_at(5 * 3600));
+        assert!(clinic.is_open_at(6 * 3600));
+        assert!(!clinic.is_open_at(20 * 3600));
+        // Hours recur each day
+        assert!(clinic.is_open_at(time::SECONDS_PER_DAY + 12 * 3600));
+
+        // Open overnight, past midnight
+        let hours = "22:00-06:00".parse::<OperatingHours>().expect("hours");
+        assert!(hours.is_open_at(23 * 3600));
+        assert!(hours.is_open_at(3600));
+        assert!(!hours.is_open_at(12 * 3600));
+        assert!("18:00-24:00"
+            .parse::<OperatingHours>()
+            .expect("hours")
+            .is_open_at(23 * 3600));
     }
 }
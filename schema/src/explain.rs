@@ -0,0 +1,103 @@
+use crate::{CarrierId, CarrierMode, DestinationId, FlightId, OrderId, Priority};
+
+/// Decisions a scheduler made while packing a flight, so that operators can look back on why it
+/// flew as it did, e.g. why it left half-empty
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FlightExplanation {
+    pub flight: FlightId,
+    pub carrier: CarrierId,
+    pub mode: CarrierMode,
+    /// Timestamp in __seconds__ at which the flight was packed, before any hold on its launch
+    pub packed_at: u64,
+    /// Most orders the carrier could carry
+    pub capacity: usize,
+    /// Number of orders queued when packing started
+    pub queued: usize,
+    /// Carriers of the flight's mode which were free to be packed
+    pub available_carriers: usize,
+    /// Carriers of the flight's mode held in reserve for emergencies yet to be placed
+    pub reserved_carriers: usize,
+    /// Orders considered while the flight was packed, most urgent first
+    pub considered: Vec<ConsideredOrder>,
+    /// Why no more orders were considered
+    pub stopped: PackingStop,
+}
+
+/// An order considered while a flight was packed, & how it fared as far as that flight goes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConsideredOrder {
+    pub order: OrderId,
+    pub destination: DestinationId,
+    pub priority: Priority,
+    pub outcome: PackingOutcome,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PackingOutcome {
+    /// Packed into the flight
+    Packed,
+    /// Packed into the flight of another carrier, which was tried first
+    PackedElsewhere(CarrierId),
+    /// The carrier had no room left for another order
+    Full,
+    /// The carrier didn't have the range to reach the destination & return home
+    OutOfRange,
+    /// The carrier would have arrived outside the order's delivery window, or after it spoiled, so
+    /// the order waited for a later launch
+    Late,
+    /// The carrier wasn't tried, e.g. the order was sent by another mode, or the packing strategy
+    /// only offered other carriers
+    NotTried,
+    /// Given up on, as any flight would miss the order's delivery window
+    MissedWindow,
+    /// Given up on, as the order would spoil before any flight could deliver it
+    Spoiled,
+    /// The carrier would have arrived while the order's destination was closed, so the order
+    /// waited for a later launch
+    DestinationClosed,
+}
+
+/// Why a scheduler stopped packing orders into the flights it launched at a time
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PackingStop {
+    /// Every queued order was considered
+    QueueEmpty,
+    /// The order didn't fit into any available carrier, so it & the orders queued behind it were
+    /// left for a later launch
+    DidNotFit(OrderId),
+    /// Resupplies were left queued until a surge of emergencies ends
+    Surge,
+    /// The flight was launched as planned ahead of time, rather than packed
+    Planned,
+}
+
+impl std::fmt::Display for PackingOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Packed => write!(f, "packed"),
+            Self::PackedElsewhere(carrier) => write!(f, "packed into carrier {}", carrier),
+            Self::Full => write!(f, "no room left"),
+            Self::OutOfRange => write!(f, "out of range"),
+            Self::Late => write!(f, "would arrive outside its window, waited"),
+            Self::NotTried => write!(f, "carrier not tried"),
+            Self::MissedWindow => write!(f, "given up, delivery window missed"),
+            Self::Spoiled => write!(f, "given up, would spoil"),
+            Self::DestinationClosed => write!(f, "deferred due to closed destination"),
+        }
+    }
+}
+
+impl std::fmt::Display for PackingStop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::QueueEmpty => write!(f, "every queued order was considered"),
+            Self::DidNotFit(order) => write!(
+                f,
+                "order {} didn't fit any carrier, leaving it & those behind it queued",
+                order
+            ),
+            Self::Surge => write!(f, "resupplies held for a surge of emergencies"),
+            Self::Planned => write!(f, "launched as planned"),
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+
+use crate::csv;
+use crate::{DestinationId, Priority};
+
+/// Predicts upcoming demand so that a `Scheduler` can plan ahead of incoming orders
+pub trait Forecaster {
+    /// Returns the number of orders of the given priority expected for each destination
+    /// during the `window_s` seconds following `current_time`
+    fn expected_orders(
+        &self,
+        priority: Priority,
+        current_time: u64,
+        window_s: u64,
+    ) -> HashMap<DestinationId, f32>;
+}
+
+/// Demand a scenario expects for a destination, for forecasts to start from before there's any
+/// history of the orders actually placed
+#[derive(Clone, Debug, PartialEq)]
+pub struct DemandPrior {
+    pub destination: DestinationId,
+    pub priority: Priority,
+    /// Number of orders expected each day, spread evenly through it
+    pub orders_per_day: f32,
+}
+
+impl DemandPrior {
+    /// Loads priors from a CSV file with `destination, priority, orders_per_day` columns
+    pub async fn from_csv(
+        path: &str,
+    ) -> Result<Vec<Self>, Box<dyn std::error::Error + Send + Sync>> {
+        csv::parse_lines(
+            path,
+            |line| {
+                let values = line.split(", ").collect::<Vec<_>>();
+                let orders_per_day = values
+                    .get(2)
+                    .ok_or("missing orders per day")?
+                    .trim()
+                    .parse::<f32>()?;
+                if !orders_per_day.is_finite() || orders_per_day < 0.0 {
+                    return Err(format!("invalid orders per day: {}", orders_per_day).into());
+                }
+
+                Ok(Self {
+                    destination: DestinationId::intern(values[0]),
+                    priority: values.get(1).ok_or("missing priority")?.trim().try_into()?,
+                    orders_per_day,
+                })
+            },
+            |_| {},
+        )
+        .await
+    }
+}
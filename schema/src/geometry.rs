@@ -0,0 +1,150 @@
+use crate::Destination;
+
+/// Mean radius of the earth in meters, used by `Haversine`
+const EARTH_RADIUS_M: f64 = 6_371_000.0;
+
+/// A `DistanceMetric` determines how far a carrier must travel between two destinations,
+/// and where along that route it will be at any point during the journey.
+pub trait DistanceMetric {
+    /// Returns the distance in meters that must be traveled to get from `from` to `to`
+    fn distance(&self, from: &Destination, to: &Destination) -> f32;
+
+    /// Returns the east/north position (x, y) in meters after traveling the provided
+    /// fraction (0.0 - 1.0) of the route from `from` to `to`.
+    /// Defaults to a straight line between the two destinations.
+    fn interpolate(&self, from: &Destination, to: &Destination, fraction: f32) -> (f32, f32) {
+        (
+            from.east_m as f32 + (to.east_m - from.east_m) as f32 * fraction,
+            from.north_m as f32 + (to.north_m - from.north_m) as f32 * fraction,
+        )
+    }
+}
+
+impl<M: DistanceMetric + ?Sized> DistanceMetric for &M {
+    fn distance(&self, from: &Destination, to: &Destination) -> f32 {
+        (**self).distance(from, to)
+    }
+
+    fn interpolate(&self, from: &Destination, to: &Destination, fraction: f32) -> (f32, f32) {
+        (**self).interpolate(from, to, fraction)
+    }
+}
+
+/// Straight-line distance, suitable for aerial carriers over short ranges
+#[derive(Default, Clone, Copy, Debug)]
+pub struct Euclidean;
+
+impl DistanceMetric for Euclidean {
+    fn distance(&self, from: &Destination, to: &Destination) -> f32 {
+        // TODO: in real-world applications the precision may become important here,
+        // we'd probably want to use a decimal type for speeds, distances, etc
+        (((to.north_m - from.north_m).pow(2) + (to.east_m - from.east_m).pow(2)) as f32).sqrt()
+    }
+}
+
+/// Distance along a north/east aligned road grid, suitable for ground vehicles.
+/// Vehicles travel the east/west leg of each route first, followed by the north/south leg.
+#[derive(Default, Clone, Copy, Debug)]
+pub struct Manhattan;
+
+impl DistanceMetric for Manhattan {
+    fn distance(&self, from: &Destination, to: &Destination) -> f32 {
+        ((to.north_m - from.north_m).abs() + (to.east_m - from.east_m).abs()) as f32
+    }
+
+    fn interpolate(&self, from: &Destination, to: &Destination, fraction: f32) -> (f32, f32) {
+        let east_leg = (to.east_m - from.east_m) as f32;
+        let north_leg = (to.north_m - from.north_m) as f32;
+        let total = east_leg.abs() + north_leg.abs();
+        if total == 0.0 {
+            return (from.east_m as f32, from.north_m as f32);
+        }
+
+        let traveled = total * fraction;
+        if traveled <= east_leg.abs() {
+            (
+                from.east_m as f32 + traveled * east_leg.signum(),
+                from.north_m as f32,
+            )
+        } else {
+            (
+                to.east_m as f32,
+                from.north_m as f32 + (traveled - east_leg.abs()) * north_leg.signum(),
+            )
+        }
+    }
+}
+
+/// Great-circle distance, with destination offsets projected from the latitude & longitude
+/// of the origin. Suitable for aerial carriers covering larger areas.
+#[derive(Clone, Copy, Debug)]
+pub struct Haversine {
+    /// Latitude of the origin in degrees
+    origin_lat_deg: f64,
+    /// Longitude of the origin in degrees
+    origin_lon_deg: f64,
+}
+
+impl Haversine {
+    pub fn new(origin_lat_deg: f64, origin_lon_deg: f64) -> Self {
+        Self {
+            origin_lat_deg,
+            origin_lon_deg,
+        }
+    }
+
+    /// Returns the latitude & longitude of a destination in radians
+    fn lat_lon(&self, destination: &Destination) -> (f64, f64) {
+        let origin_lat = self.origin_lat_deg.to_radians();
+        let lat = origin_lat + destination.north_m as f64 / EARTH_RADIUS_M;
+        let lon = self.origin_lon_deg.to_radians()
+            + destination.east_m as f64 / (EARTH_RADIUS_M * origin_lat.cos());
+
+        (lat, lon)
+    }
+}
+
+impl DistanceMetric for Haversine {
+    fn distance(&self, from: &Destination, to: &Destination) -> f32 {
+        let (lat_a, lon_a) = self.lat_lon(from);
+        let (lat_b, lon_b) = self.lat_lon(to);
+
+        let a = ((lat_b - lat_a) / 2.0).sin().powi(2)
+            + lat_a.cos() * lat_b.cos() * ((lon_b - lon_a) / 2.0).sin().powi(2);
+
+        (2.0 * EARTH_RADIUS_M * a.sqrt().asin()) as f32
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn destination(north_m: i64, east_m: i64) -> Destination {
+        Destination {
+            north_m,
+            east_m,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_metrics() {
+        let (a, b) = (destination(-3000, 0), destination(0, 4000));
+
+        assert_eq!(Euclidean.distance(&a, &b), 5000.0);
+        assert_eq!(Manhattan.distance(&a, &b), 7000.0);
+
+        let haversine = Haversine::new(45.0, -93.0).distance(&a, &b);
+        assert!((haversine - 5000.0).abs() < 10.0, "{}", haversine);
+    }
+
+    #[test]
+    fn test_manhattan_interpolation() {
+        let (a, b) = (destination(-3000, 0), destination(0, 4000));
+
+        assert_eq!(Manhattan.interpolate(&a, &b, 0.5), (3500.0, -3000.0));
+        assert_eq!(Manhattan.interpolate(&a, &b, 1.0), (4000.0, 0.0));
+        assert_eq!(Euclidean.interpolate(&a, &b, 0.5), (2000.0, -1500.0));
+    }
+}
@@ -0,0 +1,98 @@
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+/// Names of every destination interned so far, shared by everything in the process so that
+/// orders & flights only need to carry a `DestinationId`
+static NAMES: RwLock<NameTable> = RwLock::new(NameTable {
+    ids: BTreeMap::new(),
+    names: Vec::new(),
+});
+
+struct NameTable {
+    ids: BTreeMap<&'static str, DestinationId>,
+    /// Name of each id, offset by one as the id `0` is reserved for the empty name
+    names: Vec<&'static str>,
+}
+
+/// Interned name of a `Destination`, which is cheap to copy, compare & hash.
+/// Each distinct name is allocated once (& kept for the life of the process), no matter how many
+/// orders refer to it. Ids are only meaningful within a process, so names are used on the wire.
+/// Only names from trusted sources such as a scenario's files are interned: those arriving from
+/// outside are resolved against a scenario's `DestinationNames`, so that they're never kept.
+#[derive(Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DestinationId(u32);
+
+impl DestinationId {
+    /// Returns the id of a name, interning it if it hasn't been seen before
+    pub fn intern(name: &str) -> Self {
+        if name.is_empty() {
+            return Self(0);
+        }
+        if let Some(id) = NAMES.read().expect("names").ids.get(name) {
+            return *id;
+        }
+
+        let mut table = NAMES.write().expect("names");
+        if let Some(id) = table.ids.get(name) {
+            return *id;
+        }
+        // Names live for the rest of the process, so they can be handed out without the lock
+        let name: &'static str = Box::leak(name.into());
+        let id = Self(table.names.len() as u32 + 1);
+        table.names.push(name);
+        table.ids.insert(name, id);
+        id
+    }
+
+    /// Returns the id of a name, if it has already been interned
+    pub fn get(name: &str) -> Option<Self> {
+        match name {
+            "" => Some(Self(0)),
+            name => NAMES.read().expect("names").ids.get(name).copied(),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self.0 {
+            0 => "",
+            id => NAMES.read().expect("names").names[id as usize - 1],
+        }
+    }
+}
+
+impl From<&str> for DestinationId {
+    fn from(s: &str) -> Self {
+        Self::intern(s)
+    }
+}
+
+impl std::fmt::Display for DestinationId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::fmt::Debug for DestinationId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "DestinationId({:?})", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_intern() {
+        let id = DestinationId::intern("Interned");
+
+        // The same name always maps to the same id
+        assert_eq!(DestinationId::from("Interned"), id);
+        assert_eq!(DestinationId::get("Interned"), Some(id));
+        assert_eq!(id.as_str(), "Interned");
+        assert_ne!(DestinationId::intern("Other"), id);
+
+        assert_eq!(DestinationId::get("Never interned"), None);
+        assert_eq!(DestinationId::default().as_str(), "");
+    }
+}
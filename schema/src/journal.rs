@@ -0,0 +1,619 @@
+//! A journal records the events of a monitor stream to a file, so that a run can be played back
+//! later without a server. Each event is stored as a length-delimited `MonitorEvent` proto.
+//! Flight plans, from which a scheduler can be warm-started, are stored the same way as a sequence
+//! of length-delimited `Flight` protos.
+//!
+//! Long-running servers may instead record to a `SegmentedJournal`, a directory of journals each
+//! covering a stretch of the run, so that old segments can be compacted & eventually removed.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use prost::Message;
+
+use crate::proto::server as proto;
+use crate::{Flight, MonitorEvent, ToFromProto};
+use proto::monitor_event::Event;
+
+/// Appends monitor events to a journal as they are sent
+pub struct JournalWriter<W> {
+    writer: W,
+}
+
+impl JournalWriter<std::io::BufWriter<std::fs::File>> {
+    /// Starts a journal at the provided path, replacing any existing file
+    pub fn create(path: &str) -> std::io::Result<Self> {
+        Ok(Self::new(std::io::BufWriter::new(std::fs::File::create(
+            path,
+        )?)))
+    }
+}
+
+impl<W: Write> JournalWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// Records an event, flushing it so that the journal is complete even if the process exits
+    pub fn append(&mut self, event: &proto::MonitorEvent) -> std::io::Result<()> {
+        self.writer
+            .write_all(&event.encode_length_delimited_to_vec())?;
+        self.writer.flush()
+    }
+}
+
+/// Decodes the events recorded in a journal, in the order they were sent
+pub fn decode(mut bytes: &[u8]) -> Result<Vec<MonitorEvent>, String> {
+    let mut events = vec![];
+    while !bytes.is_empty() {
+        let event = proto::MonitorEvent::decode_length_delimited(&mut bytes)
+            .map_err(|e| format!("invalid journal: {}", e))?;
+        let event = MonitorEvent::try_from_proto(event)
+            .map_err(|e| format!("invalid event {} in journal: {}", events.len(), e))?;
+        events.push(event);
+    }
+    Ok(events)
+}
+
+/// Decodes the events recorded in a journal file, or in each of the segments of a journal directory
+/// one after another
+pub fn read(path: impl AsRef<Path>) -> Result<Vec<MonitorEvent>, String> {
+    let path = path.as_ref();
+    if !path.is_dir() {
+        return decode(&std::fs::read(path).map_err(|e| e.to_string())?);
+    }
+
+    let mut events = vec![];
+    for (_, path) in segments(path).map_err(|e| e.to_string())? {
+        let bytes = std::fs::read(&path).map_err(|e| e.to_string())?;
+        events.extend(decode(&bytes).map_err(|e| format!("{}: {}", path.display(), e))?);
+    }
+    Ok(events)
+}
+
+/// Extension of the files holding each segment of a journal directory
+const SEGMENT_EXTENSION: &str = "journal";
+
+/// When a `SegmentedJournal` starts new segments, & how long it keeps its old ones in full or at all
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Retention {
+    /// Size in bytes past which a new segment is started
+    pub segment_bytes: u64,
+    /// Age past which a new segment is started, however small the current one is
+    pub segment_age: Duration,
+    /// Age of a segment past which it's compacted, keeping only its keyframes
+    pub compact_after: Option<Duration>,
+    /// Simulated seconds between the status updates kept when a segment is compacted
+    pub keyframe_interval_s: u64,
+    /// Total size in bytes past which the oldest segments are removed
+    pub max_bytes: Option<u64>,
+    /// Age of a segment past which it's removed
+    pub max_age: Option<Duration>,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Self {
+            segment_bytes: 64 * 1024 * 1024,
+            segment_age: Duration::from_secs(60 * 60),
+            compact_after: None,
+            keyframe_interval_s: 10 * 60,
+            max_bytes: None,
+            max_age: None,
+        }
+    }
+}
+
+/// Records monitor events to a directory of segments, each a journal of its own. New segments are
+/// started before a status update once the current one is large or old enough, & begin with the
+/// events a subscriber joining at that point would be replayed (the run's start & destination
+/// changes), so that each can be played back even once those before it are gone. Segments older
+/// than the `Retention` allows are compacted into summaries holding just their keyframes, then
+/// removed along with the oldest segments while the journal is too large.
+pub struct SegmentedJournal {
+    dir: PathBuf,
+    retention: Retention,
+    /// Segments which are no longer written to, oldest first
+    closed: Vec<Segment>,
+    current: Segment,
+    writer: JournalWriter<std::io::BufWriter<std::fs::File>>,
+    opened: SystemTime,
+    /// Events since the last reset which are replayed at the start of each segment
+    context: Vec<proto::MonitorEvent>,
+}
+
+struct Segment {
+    sequence: u64,
+    path: PathBuf,
+    bytes: u64,
+    /// When the segment was last written to
+    closed: SystemTime,
+    compacted: bool,
+}
+
+impl SegmentedJournal {
+    /// Continues a journal in the provided directory, creating it if need be. Any segments already
+    /// there are kept, subject to the retention, & new ones are numbered after them.
+    pub fn open(dir: impl Into<PathBuf>, retention: Retention) -> std::io::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        let closed = segments(&dir)?
+            .into_iter()
+            .map(|(sequence, path)| {
+                let metadata = std::fs::metadata(&path)?;
+                Ok(Segment {
+                    sequence,
+                    bytes: metadata.len(),
+                    closed: metadata.modified()?,
+                    compacted: is_compacted(&path),
+                    path,
+                })
+            })
+            .collect::<std::io::Result<Vec<_>>>()?;
+        let sequence = closed.last().map_or(0, |segment| segment.sequence + 1);
+
+        let now = SystemTime::now();
+        let (current, writer) = Self::create_segment(&dir, sequence, now)?;
+        let mut journal = Self {
+            dir,
+            retention,
+            closed,
+            current,
+            writer,
+            opened: now,
+            context: vec![],
+        };
+        journal.enforce(now)?;
+        Ok(journal)
+    }
+
+    /// Records an event, first starting a new segment if the current one is due to be closed
+    pub fn append(&mut self, event: &proto::MonitorEvent) -> std::io::Result<()> {
+        self.append_at(event, SystemTime::now())
+    }
+
+    fn append_at(&mut self, event: &proto::MonitorEvent, now: SystemTime) -> std::io::Result<()> {
+        let due = self.current.bytes >= self.retention.segment_bytes
+            || now.duration_since(self.opened).unwrap_or_default() >= self.retention.segment_age;
+        if due && self.current.bytes > 0 && matches!(event.event, Some(Event::StatusUpdate(_))) {
+            self.roll(now)?;
+        }
+
+        match event.event {
+            Some(Event::SimulationStarted(_) | Event::DestinationChanges(_)) => {
+                self.context.push(event.clone())
+            }
+            Some(Event::SimulationReset(_)) => self.context.clear(),
+            _ => {}
+        }
+        self.write(event, now)
+    }
+
+    fn write(&mut self, event: &proto::MonitorEvent, now: SystemTime) -> std::io::Result<()> {
+        self.writer.append(event)?;
+        let len = event.encoded_len();
+        self.current.bytes += (prost::length_delimiter_len(len) + len) as u64;
+        self.current.closed = now;
+        Ok(())
+    }
+
+    /// Closes the current segment & starts the next, applying the retention to the closed ones
+    fn roll(&mut self, now: SystemTime) -> std::io::Result<()> {
+        let (next, writer) = Self::create_segment(&self.dir, self.current.sequence + 1, now)?;
+        self.closed.push(std::mem::replace(&mut self.current, next));
+        self.writer = writer;
+        self.opened = now;
+        for event in std::mem::take(&mut self.context) {
+            self.write(&event, now)?;
+            self.context.push(event);
+        }
+        self.enforce(now)
+    }
+
+    fn create_segment(
+        dir: &Path,
+        sequence: u64,
+        now: SystemTime,
+    ) -> std::io::Result<(Segment, JournalWriter<std::io::BufWriter<std::fs::File>>)> {
+        let path = dir.join(format!("{:08}.{}", sequence, SEGMENT_EXTENSION));
+        let writer = JournalWriter::new(std::io::BufWriter::new(std::fs::File::create(&path)?));
+        let segment = Segment {
+            sequence,
+            path,
+            bytes: 0,
+            closed: now,
+            compacted: false,
+        };
+        Ok((segment, writer))
+    }
+
+    /// Compacts & removes closed segments as the retention requires
+    fn enforce(&mut self, now: SystemTime) -> std::io::Result<()> {
+        let age = |segment: &Segment| now.duration_since(segment.closed).unwrap_or_default();
+
+        if let Some(compact_after) = self.retention.compact_after {
+            let interval = self.retention.keyframe_interval_s;
+            for segment in &mut self.closed {
+                if !segment.compacted && age(segment) >= compact_after {
+                    segment.compact(interval)?;
+                }
+            }
+        }
+
+        let max_age = self.retention.max_age.unwrap_or(Duration::MAX);
+        let max_bytes = self.retention.max_bytes.unwrap_or(u64::MAX);
+        let mut bytes = self.current.bytes + self.closed.iter().map(|s| s.bytes).sum::<u64>();
+        while let Some(oldest) = self.closed.first() {
+            if age(oldest) < max_age && bytes <= max_bytes {
+                break;
+            }
+            bytes -= oldest.bytes;
+            std::fs::remove_file(&self.closed.remove(0).path)?;
+        }
+        Ok(())
+    }
+}
+
+impl Segment {
+    /// Replaces the segment with a summary of it, see `summarize`
+    fn compact(&mut self, keyframe_interval_s: u64) -> std::io::Result<()> {
+        let invalid = |e| std::io::Error::new(std::io::ErrorKind::InvalidData, e);
+        let mut bytes = &std::fs::read(&self.path)?[..];
+        let mut events = vec![];
+        while !bytes.is_empty() {
+            events.push(proto::MonitorEvent::decode_length_delimited(&mut bytes).map_err(invalid)?);
+        }
+
+        let mut summary = vec![];
+        for event in summarize(events, keyframe_interval_s) {
+            summary.extend(event.encode_length_delimited_to_vec());
+        }
+        let path = self
+            .path
+            .with_extension(format!("compacted.{}", SEGMENT_EXTENSION));
+        std::fs::write(&path, &summary)?;
+        std::fs::remove_file(&self.path)?;
+
+        self.path = path;
+        self.bytes = summary.len() as u64;
+        self.compacted = true;
+        Ok(())
+    }
+}
+
+/// Summarizes a stretch of the monitor stream by its keyframes: every event besides status updates,
+/// along with the first & last update & otherwise only those at least the interval in simulated
+/// seconds apart. Each update holds the state of the whole run, so playback still shows where
+/// every carrier was at each keyframe.
+fn summarize(
+    events: Vec<proto::MonitorEvent>,
+    keyframe_interval_s: u64,
+) -> Vec<proto::MonitorEvent> {
+    let time = |event: &proto::MonitorEvent| match &event.event {
+        Some(Event::StatusUpdate(update)) => Some(update.time.as_ref().map_or(0, |t| t.seconds)),
+        _ => None,
+    };
+    let last = events.iter().rposition(|event| time(event).is_some());
+    let interval = keyframe_interval_s.min(i64::MAX as u64) as i64;
+
+    let mut keyframe: Option<i64> = None;
+    let mut summary = vec![];
+    for (i, event) in events.into_iter().enumerate() {
+        if matches!(event.event, Some(Event::SimulationReset(_))) {
+            keyframe = None;
+        }
+        if let Some(time) = time(&event) {
+            // Updates from before the last keyframe come from a new run
+            let due =
+                keyframe.is_none_or(|kept| time < kept || time >= kept.saturating_add(interval));
+            if !due && Some(i) != last {
+                continue;
+            }
+            keyframe = Some(time);
+        }
+        summary.push(event);
+    }
+    summary
+}
+
+/// The segments of a journal directory, by their sequence numbers
+fn segments(dir: &Path) -> std::io::Result<Vec<(u64, PathBuf)>> {
+    let mut segments = vec![];
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some(SEGMENT_EXTENSION) {
+            continue;
+        }
+        let name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default();
+        if let Some(Ok(sequence)) = name.split('.').next().map(str::parse) {
+            segments.push((sequence, path));
+        }
+    }
+    segments.sort();
+    Ok(segments)
+}
+
+fn is_compacted(path: &Path) -> bool {
+    path.file_stem()
+        .and_then(|stem| Path::new(stem).extension())
+        .is_some_and(|e| e == "compacted")
+}
+
+/// Writes a flight plan, e.g. the flights launched by a previous run
+pub fn write_plan(mut writer: impl Write, flights: &[Flight]) -> std::io::Result<()> {
+    for flight in flights {
+        let flight: proto::Flight = flight.clone().into_proto();
+        writer.write_all(&flight.encode_length_delimited_to_vec())?;
+    }
+    writer.flush()
+}
+
+/// Decodes the flights of a plan written with `write_plan`
+pub fn decode_plan(mut bytes: &[u8]) -> Result<Vec<Flight>, String> {
+    let mut flights = vec![];
+    while !bytes.is_empty() {
+        let flight = proto::Flight::decode_length_delimited(&mut bytes)
+            .map_err(|e| format!("invalid flight plan: {}", e))?;
+        let flight = Flight::try_from_proto(flight)
+            .map_err(|e| format!("invalid flight {} in plan: {}", flights.len(), e))?;
+        flights.push(flight);
+    }
+    Ok(flights)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use ulid::Ulid;
+
+    use super::*;
+    use crate::{
+        CarrierId, Destination, DestinationChanges, DestinationId, FlightId, Speed, StatusUpdate,
+    };
+
+    fn update(time: u64) -> proto::MonitorEvent {
+        MonitorEvent::StatusUpdate(StatusUpdate {
+            time,
+            flights: vec![],
+            speed: Speed::RealTime,
+            backlog: None,
+            sectors: vec![],
+        })
+        .into_proto()
+    }
+
+    fn changes(added: &[&str], deactivated: &[&str]) -> proto::MonitorEvent {
+        MonitorEvent::DestinationChanges(DestinationChanges {
+            added: crate::testkit::destinations(
+                &added.iter().map(|name| (*name, 0, 0)).collect::<Vec<_>>(),
+            )
+            .into_values()
+            .collect::<Vec<Destination>>(),
+            deactivated: deactivated
+                .iter()
+                .map(|n| DestinationId::from(*n))
+                .collect(),
+        })
+        .into_proto()
+    }
+
+    /// What a client playing back the events ends up showing: the run, its active destinations
+    /// & the times of the updates in the order they were shown
+    fn play(events: &[MonitorEvent]) -> (Option<Ulid>, HashSet<DestinationId>, Vec<u64>) {
+        let (mut run, mut destinations, mut times) = (None, HashSet::new(), vec![]);
+        for event in events {
+            match event {
+                MonitorEvent::SimulationStarted(id) => run = Some(*id),
+                MonitorEvent::SimulationReset => {
+                    run = None;
+                    destinations.clear();
+                }
+                MonitorEvent::DestinationChanges(changes) => {
+                    destinations.extend(changes.added.iter().map(|d| d.name));
+                    for name in &changes.deactivated {
+                        destinations.remove(name);
+                    }
+                }
+                MonitorEvent::StatusUpdate(update) => times.push(update.time),
+                _ => {}
+            }
+        }
+        (run, destinations, times)
+    }
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("journal-{}", Ulid::new()))
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut writer = JournalWriter::new(vec![]);
+        for event in [
+            update(60),
+            MonitorEvent::SimulationReset.into_proto(),
+            update(120),
+        ] {
+            writer.append(&event).expect("append");
+        }
+
+        let events = decode(&writer.writer).expect("journal");
+        assert_eq!(events.len(), 3);
+        assert!(matches!(events[1], MonitorEvent::SimulationReset));
+        assert!(matches!(&events[2], MonitorEvent::StatusUpdate(update) if update.time == 120));
+
+        // Journals cut off part way through an event are rejected
+        assert!(decode(&writer.writer[..writer.writer.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_segments() {
+        let dir = temp_dir();
+        let retention = Retention {
+            segment_bytes: 1,
+            ..Default::default()
+        };
+        let mut journal = SegmentedJournal::open(&dir, retention.clone()).expect("open");
+        let (first, second) = (Ulid::new(), Ulid::new());
+        let events = [
+            MonitorEvent::SimulationStarted(first).into_proto(),
+            changes(&["Toronto", "Ottawa"], &[]),
+            update(60),
+            update(120),
+            changes(&[], &["Ottawa"]),
+            update(180),
+            MonitorEvent::SimulationReset.into_proto(),
+            MonitorEvent::SimulationStarted(second).into_proto(),
+            update(60),
+            changes(&["Kingston"], &[]),
+            update(120),
+        ];
+        for event in &events {
+            journal.append(event).expect("append");
+        }
+        drop(journal);
+
+        // A new segment was started before each update
+        let files = segments(&dir).expect("segments");
+        assert_eq!(files.len(), 6);
+
+        // Playing back the segments one after another shows just what the events did
+        let sent = events
+            .iter()
+            .cloned()
+            .map(|event| MonitorEvent::try_from_proto(event).expect("event"))
+            .collect::<Vec<_>>();
+        let recorded = read(&dir).expect("read");
+        assert_eq!(play(&recorded), play(&sent));
+        assert_eq!(play(&recorded).2, vec![60, 120, 180, 60, 120]);
+
+        // Each segment can be played back by itself, starting from where the run was
+        let (run, destinations, _) = play(&read(&files[2].1).expect("segment"));
+        assert_eq!(run, Some(first));
+        assert_eq!(
+            destinations,
+            HashSet::from([DestinationId::from("Toronto")])
+        );
+        let (run, destinations, _) = play(&read(&files[5].1).expect("segment"));
+        assert_eq!(run, Some(second));
+        assert_eq!(
+            destinations,
+            HashSet::from([DestinationId::from("Kingston")])
+        );
+
+        // Reopening the journal carries on after the segments already there
+        let mut journal = SegmentedJournal::open(&dir, retention.clone()).expect("reopen");
+        journal.append(&update(180)).expect("append");
+        drop(journal);
+        assert_eq!(segments(&dir).expect("segments").len(), 7);
+        assert_eq!(play(&read(&dir).expect("read")).2.last(), Some(&180));
+
+        // Once the journal is too large, the oldest segments are removed
+        let retention = Retention {
+            max_bytes: Some(0),
+            ..retention
+        };
+        SegmentedJournal::open(&dir, retention).expect("reopen");
+        assert_eq!(segments(&dir).expect("segments").len(), 1);
+
+        std::fs::remove_dir_all(&dir).expect("remove");
+    }
+
+    #[test]
+    fn test_retention() {
+        let dir = temp_dir();
+        let minutes = |m: u64| Duration::from_secs(m * 60);
+        let mut journal = SegmentedJournal::open(
+            &dir,
+            Retention {
+                segment_age: minutes(10),
+                compact_after: Some(minutes(30)),
+                keyframe_interval_s: 5 * 60,
+                max_age: Some(minutes(60)),
+                ..Default::default()
+            },
+        )
+        .expect("open");
+
+        // An update each simulated minute, sent a minute apart for two hours
+        let start = SystemTime::now();
+        let run = Ulid::new();
+        journal
+            .append_at(&MonitorEvent::SimulationStarted(run).into_proto(), start)
+            .expect("append");
+        for minute in 0..=120 {
+            journal
+                .append_at(&update(minute * 60), start + minutes(minute))
+                .expect("append");
+        }
+        drop(journal);
+
+        // Segments of ten minutes are kept for an hour, those older than half an hour compacted
+        let files = segments(&dir).expect("segments");
+        let compacted = files.iter().filter(|(_, path)| is_compacted(path));
+        assert_eq!(files.len(), 7);
+        assert_eq!(compacted.count(), 3);
+
+        // What remains plays back in order, from the start of the oldest segment kept, with only
+        // keyframes from the compacted segments
+        let events = read(&dir).expect("read");
+        assert!(matches!(events[0], MonitorEvent::SimulationStarted(id) if id == run));
+        let (_, _, times) = play(&events);
+        assert_eq!(times[..4], [60 * 60, 65 * 60, 69 * 60, 70 * 60]);
+        assert!(times.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(times.last(), Some(&(120 * 60)));
+        assert_eq!(times.len(), 3 * 3 + 31);
+
+        std::fs::remove_dir_all(&dir).expect("remove");
+    }
+
+    #[test]
+    fn test_summarize() {
+        let events = [
+            update(0),
+            update(60),
+            update(300),
+            update(360),
+            MonitorEvent::SimulationReset.into_proto(),
+            update(60),
+            update(120),
+            // A new run, without a reset
+            update(0),
+            update(30),
+        ];
+        let times = summarize(events.to_vec(), 300)
+            .into_iter()
+            .map(|event| MonitorEvent::try_from_proto(event).expect("event"))
+            .map(|event| match event {
+                MonitorEvent::StatusUpdate(update) => update.time as i64,
+                _ => -1,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(times, [0, 300, -1, 60, 0, 30]);
+    }
+
+    #[test]
+    fn test_plan_round_trip() {
+        let flights = (0..3)
+            .map(|i| Flight {
+                id: FlightId::new(),
+                carrier: CarrierId(i),
+                launch_time: 60 * i as u64,
+                orders: vec![crate::testkit::order(
+                    30,
+                    "Toronto",
+                    crate::Priority::Emergency,
+                )],
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        let mut plan = vec![];
+        write_plan(&mut plan, &flights).expect("write");
+        assert_eq!(decode_plan(&plan).expect("plan"), flights);
+        assert!(decode_plan(&plan[..plan.len() - 1]).is_err());
+    }
+}
@@ -1,35 +1,73 @@
 use prost::Message as ProstMessage;
 
+mod alert;
+pub mod binary;
+mod client_config;
+mod conditions;
+mod conversion;
+mod csv;
+mod energy;
 mod entities;
+mod explain;
+mod forecast;
+pub mod geometry;
+mod intern;
+pub mod journal;
+pub mod json;
+mod matching;
 mod runner;
+pub mod scenario;
 mod scheduler;
+mod sector;
+pub mod testkit;
+pub mod time;
+pub mod units;
+#[cfg(feature = "xlsx")]
+mod xlsx;
 
 pub mod proto {
-    pub mod server;
+    pub mod server {
+        include!(concat!(env!("OUT_DIR"), "/server.rs"));
+    }
 }
 
-pub use entities::{Destination, DestinationName, Flight, Order, Priority, ORIGIN};
-pub use runner::{Runner, Speed};
-pub use scheduler::Scheduler;
+pub use alert::{Alert, AlertKind};
+pub use client_config::{ClientConfig, MapExtents, ThemeHint};
+pub use conditions::RangeReduction;
+pub use conversion::ConversionError;
+pub use csv::{is_xlsx, LoadProgress};
+pub use energy::EnergyModel;
+pub use entities::{
+    CarrierId, CarrierMode, DeliveryProof, Destination, DestinationChanges, Diversion, Flight,
+    FlightId, FlightPhase, FlightPhases, OperatingHours, Order, OrderId, OrderReceipt, OrderStatus,
+    Priority, ReturnPolicy, ServiceTime, Shipment,
+};
+pub use explain::{ConsideredOrder, FlightExplanation, PackingOutcome, PackingStop};
+pub use forecast::{DemandPrior, Forecaster};
+pub use intern::DestinationId;
+pub use matching::{DestinationMatcher, DestinationNames, Matching};
+pub use runner::{Runner, SimulatedClock, Speed, SpeedSchedule, SpeedWindow};
+pub use scheduler::{DynScheduler, Scheduler};
+pub use sector::{Sector, SectorOccupancy};
 
-pub const SAMPLE_DESTINATIONS_CSV_PATH: &'static str = "./test_data/destinations.csv";
-pub const SAMPLE_ORDERS_CSV_PATH: &'static str = "./test_data/orders.csv";
+pub const SAMPLE_DESTINATIONS_CSV_PATH: &str = "./test_data/destinations.csv";
+pub const SAMPLE_ORDERS_CSV_PATH: &str = "./test_data/orders.csv";
 
 pub trait ToFromProto<Proto>: Sized
 where
     Proto: ProstMessage,
 {
-    fn try_from_proto(proto: Proto) -> Option<Self>;
+    fn try_from_proto(proto: Proto) -> Result<Self, ConversionError>;
     fn into_proto(self) -> Proto;
 }
 
 impl<T, Proto> ToFromProto<Proto> for T
 where
     Proto: ProstMessage,
-    T: TryFrom<Proto> + Into<Proto>,
+    T: TryFrom<Proto, Error = ConversionError> + Into<Proto>,
 {
-    fn try_from_proto(proto: Proto) -> Option<Self> {
-        proto.try_into().ok()
+    fn try_from_proto(proto: Proto) -> Result<Self, ConversionError> {
+        proto.try_into()
     }
 
     fn into_proto(self) -> Proto {
@@ -37,74 +75,783 @@ where
     }
 }
 
+/// Events streamed to monitoring clients
+#[derive(Clone, Debug)]
+pub enum MonitorEvent {
+    StatusUpdate(StatusUpdate),
+    /// The simulation has been restarted, any state from before the reset should be discarded
+    SimulationReset,
+    /// Sent periodically so that the server can detect clients which have stopped taking events
+    Heartbeat,
+    /// Destinations have been added to or deactivated in the running scenario
+    DestinationChanges(DestinationChanges),
+    /// A simulation has started, identified so that clients can tell it apart from other runs
+    SimulationStarted(ulid::Ulid),
+    /// How the server would like the client to behave, sent before any other event
+    ClientConfig(ClientConfig),
+}
+
+impl ToFromProto<proto::server::MonitorEvent> for MonitorEvent {
+    fn into_proto(self) -> proto::server::MonitorEvent {
+        use proto::server::monitor_event::Event;
+
+        proto::server::MonitorEvent {
+            event: Some(match self {
+                Self::StatusUpdate(update) => Event::StatusUpdate(update.into_proto()),
+                Self::SimulationReset => Event::SimulationReset(proto::server::SimulationReset {}),
+                Self::Heartbeat => Event::Heartbeat(proto::server::Heartbeat {}),
+                Self::DestinationChanges(changes) => {
+                    Event::DestinationChanges(changes.into_proto())
+                }
+                Self::SimulationStarted(id) => {
+                    Event::SimulationStarted(proto::server::SimulationStarted {
+                        id: id.to_string(),
+                    })
+                }
+                Self::ClientConfig(config) => Event::ClientConfig(config.into_proto()),
+            }),
+        }
+    }
+
+    fn try_from_proto(message: proto::server::MonitorEvent) -> Result<Self, ConversionError> {
+        use proto::server::monitor_event::Event;
+
+        match conversion::required("event", message.event)? {
+            Event::StatusUpdate(update) => StatusUpdate::try_from_proto(update)
+                .map(Self::StatusUpdate)
+                .map_err(|e| e.within("status_update")),
+            Event::SimulationReset(_) => Ok(Self::SimulationReset),
+            Event::Heartbeat(_) => Ok(Self::Heartbeat),
+            Event::DestinationChanges(changes) => DestinationChanges::try_from_proto(changes)
+                .map(Self::DestinationChanges)
+                .map_err(|e| e.within("destination_changes")),
+            Event::SimulationStarted(started) => started
+                .id
+                .parse()
+                .map(Self::SimulationStarted)
+                .map_err(|_| ConversionError::InvalidValue {
+                    field: "id",
+                    value: started.id,
+                }),
+            Event::ClientConfig(config) => ClientConfig::try_from_proto(config)
+                .map(Self::ClientConfig)
+                .map_err(|e| e.within("client_config")),
+        }
+    }
+}
+
+impl ToFromProto<proto::server::DestinationChanges> for DestinationChanges {
+    fn into_proto(self) -> proto::server::DestinationChanges {
+        proto::server::DestinationChanges {
+            added: self
+                .added
+                .into_iter()
+                .map(Destination::into_proto)
+                .collect(),
+            deactivated: self
+                .deactivated
+                .into_iter()
+                .map(|name| name.to_string())
+                .collect(),
+        }
+    }
+
+    fn try_from_proto(message: proto::server::DestinationChanges) -> Result<Self, ConversionError> {
+        Ok(Self {
+            added: conversion::repeated("added", message.added)?,
+            deactivated: message
+                .deactivated
+                .iter()
+                .map(|name| DestinationId::from(name.as_str()))
+                .collect(),
+        })
+    }
+}
+
+impl ToFromProto<proto::server::Destination> for Destination {
+    fn into_proto(self) -> proto::server::Destination {
+        proto::server::Destination {
+            name: self.name.to_string(),
+            north_m: self.north_m,
+            east_m: self.east_m,
+            region: self.region.unwrap_or_default(),
+            hours: self.hours.map(|hours| proto::server::OperatingHours {
+                opens_s: hours.opens_s as u32,
+                closes_s: hours.closes_s as u32,
+            }),
+        }
+    }
+
+    fn try_from_proto(message: proto::server::Destination) -> Result<Self, ConversionError> {
+        if message.name.is_empty() {
+            return Err(ConversionError::MissingField("name"));
+        }
+
+        Ok(Self {
+            name: DestinationId::from(message.name.as_str()),
+            north_m: coordinate("north_m", message.north_m)?,
+            east_m: coordinate("east_m", message.east_m)?,
+            region: Some(message.region).filter(|region| !region.is_empty()),
+            hours: message
+                .hours
+                .map(|hours| {
+                    let (opens_s, closes_s) = (hours.opens_s as u64, hours.closes_s as u64);
+                    // Hours must be times of day, & not empty
+                    match opens_s != closes_s && opens_s.max(closes_s) < time::SECONDS_PER_DAY {
+                        true => Ok(OperatingHours { opens_s, closes_s }),
+                        false => Err(ConversionError::InvalidValue {
+                            field: "hours",
+                            value: format!("{}-{}", opens_s, closes_s),
+                        }),
+                    }
+                })
+                .transpose()?,
+        })
+    }
+}
+
+impl ToFromProto<proto::server::DemandPrior> for DemandPrior {
+    fn into_proto(self) -> proto::server::DemandPrior {
+        proto::server::DemandPrior {
+            destination: self.destination.to_string(),
+            priority: match self.priority {
+                Priority::Emergency => proto::server::Priority::Emergency.into(),
+                Priority::Resupply => proto::server::Priority::Resupply.into(),
+            },
+            orders_per_day: self.orders_per_day,
+        }
+    }
+
+    fn try_from_proto(message: proto::server::DemandPrior) -> Result<Self, ConversionError> {
+        Ok(Self {
+            destination: DestinationId::from(message.destination.as_str()),
+            priority: priority_from_proto(message.priority)?,
+            orders_per_day: message.orders_per_day,
+        })
+    }
+}
+
+impl ToFromProto<proto::server::ClientConfig> for ClientConfig {
+    fn into_proto(self) -> proto::server::ClientConfig {
+        proto::server::ClientConfig {
+            extents: self.extents.map(extents_into_proto),
+            units: self
+                .units
+                .map(|units| units.to_string())
+                .unwrap_or_default(),
+            theme: match self.theme {
+                None => proto::server::Theme::DefaultTheme,
+                Some(ThemeHint::Light) => proto::server::Theme::Light,
+                Some(ThemeHint::Dark) => proto::server::Theme::Dark,
+            }
+            .into(),
+            features: self.features,
+            destination_names: self.destination_names,
+        }
+    }
+
+    fn try_from_proto(message: proto::server::ClientConfig) -> Result<Self, ConversionError> {
+        let units = match message.units.as_str() {
+            "" => None,
+            units => Some(units.parse().map_err(|_| ConversionError::InvalidValue {
+                field: "units",
+                value: message.units.clone(),
+            })?),
+        };
+        let theme = match conversion::enumeration("theme", message.theme)? {
+            proto::server::Theme::DefaultTheme => None,
+            proto::server::Theme::Light => Some(ThemeHint::Light),
+            proto::server::Theme::Dark => Some(ThemeHint::Dark),
+        };
+
+        Ok(Self {
+            extents: message.extents.map(extents_from_proto),
+            units,
+            theme,
+            features: message.features,
+            destination_names: message.destination_names,
+        })
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct StatusUpdate {
     pub time: u64,
     pub flights: Vec<Flight>,
     pub speed: runner::Speed,
+    /// Orders waiting to be scheduled, unless the update came from a server which doesn't say
+    pub backlog: Option<Backlog>,
+    /// Carriers in each sector of airspace, if the scenario divides it into sectors
+    pub sectors: Vec<SectorOccupancy>,
+}
+
+/// Summary of the orders waiting to be scheduled
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Backlog {
+    pub emergencies: usize,
+    pub resupplies: usize,
+    /// Timestamp in __seconds__ the order which has waited longest was placed at, if any are waiting
+    pub oldest: Option<u64>,
+}
+
+impl Backlog {
+    pub fn from_orders<'a>(orders: impl IntoIterator<Item = &'a Order>) -> Self {
+        orders
+            .into_iter()
+            .fold(Self::default(), |mut backlog, order| {
+                match order.priority {
+                    Priority::Emergency => backlog.emergencies += 1,
+                    Priority::Resupply => backlog.resupplies += 1,
+                }
+                backlog.oldest = Some(backlog.oldest.map_or(order.time, |t| t.min(order.time)));
+                backlog
+            })
+    }
+
+    /// Number of orders waiting
+    pub fn len(&self) -> usize {
+        self.emergencies + self.resupplies
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Seconds the order which has waited longest has been waiting at the provided time
+    pub fn oldest_wait_s(&self, current_time: u64) -> Option<u64> {
+        self.oldest
+            .map(|oldest| current_time.saturating_sub(oldest))
+    }
 }
 
 impl ToFromProto<proto::server::StatusUpdate> for StatusUpdate {
     fn into_proto(self) -> proto::server::StatusUpdate {
         proto::server::StatusUpdate {
-            time: self.time as i64,
+            time: Some(time::to_proto_timestamp(self.time)),
             flights: self.flights.into_iter().map(Flight::into_proto).collect(),
             speed: self.speed.to_i32(),
+            backlog: self.backlog.map(|backlog| proto::server::Backlog {
+                emergencies: backlog.emergencies as u32,
+                resupplies: backlog.resupplies as u32,
+                oldest: backlog.oldest.map(time::to_proto_timestamp),
+            }),
+            sectors: self
+                .sectors
+                .into_iter()
+                .map(SectorOccupancy::into_proto)
+                .collect(),
         }
     }
 
-    fn try_from_proto(message: proto::server::StatusUpdate) -> Option<Self> {
-        Some(Self {
-            time: message.time as u64,
-            flights: message
-                .flights
-                .into_iter()
-                .filter_map(|flight| Flight::try_from_proto(flight))
-                .collect(),
+    fn try_from_proto(message: proto::server::StatusUpdate) -> Result<Self, ConversionError> {
+        Ok(Self {
+            time: conversion::timestamp("time", conversion::required("time", message.time)?)?,
+            flights: conversion::repeated("flights", message.flights)?,
             speed: runner::Speed::from_i32(message.speed),
+            backlog: match message.backlog {
+                Some(backlog) => Some(Backlog {
+                    emergencies: backlog.emergencies as usize,
+                    resupplies: backlog.resupplies as usize,
+                    oldest: conversion::optional_timestamp("oldest", backlog.oldest)
+                        .map_err(|e| e.within("backlog"))?,
+                }),
+                None => None,
+            },
+            sectors: conversion::repeated("sectors", message.sectors)?,
+        })
+    }
+}
+
+impl ToFromProto<proto::server::SectorOccupancy> for SectorOccupancy {
+    fn into_proto(self) -> proto::server::SectorOccupancy {
+        proto::server::SectorOccupancy {
+            sector: Some(proto::server::Sector {
+                name: self.sector.name,
+                extents: Some(extents_into_proto(self.sector.extents)),
+                max_flights: self.sector.max_flights as u32,
+            }),
+            flights: self.flights as u32,
+        }
+    }
+
+    fn try_from_proto(message: proto::server::SectorOccupancy) -> Result<Self, ConversionError> {
+        let sector = conversion::required("sector", message.sector)?;
+        let extents =
+            conversion::required("extents", sector.extents).map_err(|e| e.within("sector"))?;
+        Ok(Self {
+            sector: Sector {
+                name: sector.name,
+                extents: extents_from_proto(extents),
+                max_flights: sector.max_flights as usize,
+            },
+            flights: message.flights as usize,
         })
     }
 }
 
+fn extents_into_proto(extents: MapExtents) -> proto::server::MapExtents {
+    proto::server::MapExtents {
+        west_m: extents.west_m,
+        north_m: extents.north_m,
+        east_m: extents.east_m,
+        south_m: extents.south_m,
+    }
+}
+
+fn extents_from_proto(extents: proto::server::MapExtents) -> MapExtents {
+    MapExtents {
+        west_m: extents.west_m,
+        north_m: extents.north_m,
+        east_m: extents.east_m,
+        south_m: extents.south_m,
+    }
+}
+
 impl ToFromProto<proto::server::Flight> for Flight {
     fn into_proto(self) -> proto::server::Flight {
         proto::server::Flight {
-            launch_time: self.launch_time as i64,
+            launch_time: Some(time::to_proto_timestamp(self.launch_time)),
             orders: self.orders.into_iter().map(Order::into_proto).collect(),
+            carrier: self.carrier.0,
+            diversion: self.diversion.map(|diversion| proto::server::Diversion {
+                north_m: diversion.north_m,
+                east_m: diversion.east_m,
+            }),
+            takeoff_s: self.phases.takeoff_s,
+            landing_s: self.phases.landing_s,
+            accelerate_s: self.phases.accelerate_s,
+            decelerate_s: self.phases.decelerate_s,
+            id: flight_id_into_proto(self.id),
+            service_min_s: match self.phases.service {
+                ServiceTime::Fixed(s) => s,
+                ServiceTime::Uniform { min_s, .. } => min_s,
+            },
+            service_max_s: self.phases.service.max_s(),
+            mode: match self.mode {
+                CarrierMode::Aerial => proto::server::CarrierMode::Aerial.into(),
+                CarrierMode::Ground => proto::server::CarrierMode::Ground.into(),
+            },
+            departs_from: self
+                .departs_from
+                .map(|destination| destination.to_string())
+                .unwrap_or_default(),
+            return_policy: match self.return_policy {
+                ReturnPolicy::ReturnToOrigin => proto::server::ReturnPolicy::ReturnToOrigin.into(),
+                ReturnPolicy::WaitAtLastStop => proto::server::ReturnPolicy::WaitAtLastStop.into(),
+            },
         }
     }
 
-    fn try_from_proto(message: proto::server::Flight) -> Option<Self> {
-        Some(Self {
-            launch_time: message.launch_time as u64,
-            orders: message
-                .orders
-                .into_iter()
-                .filter_map(|order| Order::try_from_proto(order))
-                .collect(),
+    fn try_from_proto(message: proto::server::Flight) -> Result<Self, ConversionError> {
+        if message.service_min_s > message.service_max_s {
+            return Err(ConversionError::InvalidValue {
+                field: "service_max_s",
+                value: format!("{} is less than the minimum", message.service_max_s),
+            });
+        }
+        let mode = match conversion::enumeration("mode", message.mode)? {
+            proto::server::CarrierMode::Aerial => CarrierMode::Aerial,
+            proto::server::CarrierMode::Ground => CarrierMode::Ground,
+        };
+        let return_policy = match conversion::enumeration("return_policy", message.return_policy)? {
+            proto::server::ReturnPolicy::ReturnToOrigin => ReturnPolicy::ReturnToOrigin,
+            proto::server::ReturnPolicy::WaitAtLastStop => ReturnPolicy::WaitAtLastStop,
+        };
+        Ok(Self {
+            id: flight_id_from_proto(&message.id)?,
+            carrier: CarrierId(message.carrier),
+            launch_time: conversion::timestamp(
+                "launch_time",
+                conversion::required("launch_time", message.launch_time)?,
+            )?,
+            orders: conversion::repeated("orders", message.orders)?,
+            diversion: match message.diversion {
+                Some(diversion) => Some(Diversion {
+                    north_m: coordinate("north_m", diversion.north_m)
+                        .map_err(|e| e.within("diversion"))?,
+                    east_m: coordinate("east_m", diversion.east_m)
+                        .map_err(|e| e.within("diversion"))?,
+                }),
+                None => None,
+            },
+            phases: FlightPhases {
+                takeoff_s: message.takeoff_s,
+                landing_s: message.landing_s,
+                accelerate_s: message.accelerate_s,
+                decelerate_s: message.decelerate_s,
+                service: match message.service_min_s == message.service_max_s {
+                    true => ServiceTime::Fixed(message.service_min_s),
+                    false => ServiceTime::Uniform {
+                        min_s: message.service_min_s,
+                        max_s: message.service_max_s,
+                    },
+                },
+            },
+            mode,
+            departs_from: (!message.departs_from.is_empty())
+                .then(|| DestinationId::from(message.departs_from.as_str())),
+            return_policy,
         })
     }
 }
 
+/// Rejects coordinates too far from the origin for the distances to them to be computed
+fn coordinate(field: &'static str, value: i64) -> Result<i64, ConversionError> {
+    match value.unsigned_abs() <= Destination::MAX_COORDINATE_M as u64 {
+        true => Ok(value),
+        false => Err(ConversionError::InvalidValue {
+            field,
+            value: value.to_string(),
+        }),
+    }
+}
+
+/// Flights without an identifier, e.g. in journals recorded before flights had them, are sent as
+/// an empty string
+fn flight_id_into_proto(id: FlightId) -> String {
+    match id.0.is_nil() {
+        true => String::new(),
+        false => id.to_string(),
+    }
+}
+
+fn flight_id_from_proto(id: &str) -> Result<FlightId, ConversionError> {
+    match id.is_empty() {
+        true => Ok(FlightId::default()),
+        false => id.parse().map_err(|_| ConversionError::InvalidValue {
+            field: "id",
+            value: id.to_string(),
+        }),
+    }
+}
+
+fn priority_from_proto(value: i32) -> Result<Priority, ConversionError> {
+    Ok(match conversion::enumeration("priority", value)? {
+        proto::server::Priority::Emergency => Priority::Emergency,
+        proto::server::Priority::Resupply => Priority::Resupply,
+    })
+}
+
 impl ToFromProto<proto::server::Order> for Order {
     fn into_proto(self) -> proto::server::Order {
         proto::server::Order {
-            time: self.time as i64,
+            id: self.id.0,
+            time: Some(time::to_proto_timestamp(self.time)),
             destination: self.destination.to_string(),
             priority: match self.priority {
                 Priority::Emergency => proto::server::Priority::Emergency.into(),
                 Priority::Resupply => proto::server::Priority::Resupply.into(),
             },
+            earliest_delivery: self.earliest_delivery.map(time::to_proto_timestamp),
+            latest_delivery: self.latest_delivery.map(time::to_proto_timestamp),
+            shelf_life: self.shelf_life.map(time::to_proto_duration),
+            // Left unset for single packages, as orders were before they had quantities
+            quantity: match self.quantity {
+                1 => 0,
+                quantity => quantity,
+            },
+        }
+    }
+
+    fn try_from_proto(message: proto::server::Order) -> Result<Self, ConversionError> {
+        Ok(Self {
+            id: OrderId(message.id),
+            time: conversion::optional_timestamp("time", message.time)?.unwrap_or_default(),
+            destination: DestinationId::from(message.destination.as_str()),
+            priority: priority_from_proto(message.priority)?,
+            earliest_delivery: conversion::optional_timestamp(
+                "earliest_delivery",
+                message.earliest_delivery,
+            )?,
+            latest_delivery: conversion::optional_timestamp(
+                "latest_delivery",
+                message.latest_delivery,
+            )?,
+            shelf_life: conversion::optional_duration("shelf_life", message.shelf_life)?,
+            quantity: message.quantity.max(1),
+        })
+    }
+}
+
+impl ToFromProto<proto::server::OrderReceipt> for OrderReceipt {
+    fn into_proto(self) -> proto::server::OrderReceipt {
+        use proto::server::OrderStatus as Status;
+
+        proto::server::OrderReceipt {
+            order: Some(self.order.into_proto()),
+            status: match self.status {
+                OrderStatus::Queued => Status::Queued.into(),
+                OrderStatus::Deferred => Status::Deferred.into(),
+                OrderStatus::Scheduled => Status::Scheduled.into(),
+                OrderStatus::Launched => Status::Launched.into(),
+                OrderStatus::Delivered => Status::Delivered.into(),
+                OrderStatus::Failed => Status::Failed.into(),
+                OrderStatus::Spoiled => Status::Spoiled.into(),
+            },
+            queued_at: Some(time::to_proto_timestamp(self.queued_at)),
+            carrier: self.carrier.map_or(0, |carrier| carrier.0),
+            flight: self.flight.map(flight_id_into_proto).unwrap_or_default(),
+            launch_time: self.launch_time.map(time::to_proto_timestamp),
+            delivery_time: self.delivery_time.map(time::to_proto_timestamp),
+            failure: self.failure.unwrap_or_default(),
+            proof: self.proof.map(ToFromProto::into_proto),
+            shipments: self
+                .shipments
+                .into_iter()
+                .map(ToFromProto::into_proto)
+                .collect(),
+            marginal_distance_m: self.marginal_distance_m.unwrap_or_default(),
+            wait_s: self.wait_s.unwrap_or_default(),
+        }
+    }
+
+    fn try_from_proto(message: proto::server::OrderReceipt) -> Result<Self, ConversionError> {
+        use proto::server::OrderStatus as Status;
+
+        let launch_time = conversion::optional_timestamp("launch_time", message.launch_time)?;
+        Ok(Self {
+            status: match conversion::enumeration("status", message.status)? {
+                Status::Queued => OrderStatus::Queued,
+                Status::Deferred => OrderStatus::Deferred,
+                Status::Scheduled => OrderStatus::Scheduled,
+                Status::Launched => OrderStatus::Launched,
+                Status::Delivered => OrderStatus::Delivered,
+                Status::Failed => OrderStatus::Failed,
+                Status::Spoiled => OrderStatus::Spoiled,
+            },
+            order: conversion::message("order", message.order)?,
+            queued_at: conversion::timestamp(
+                "queued_at",
+                conversion::required("queued_at", message.queued_at)?,
+            )?,
+            // Carriers are only set alongside the launch time of their flight
+            carrier: launch_time.map(|_| CarrierId(message.carrier)),
+            flight: match launch_time {
+                Some(_) => Some(flight_id_from_proto(&message.flight)?),
+                None => None,
+            },
+            launch_time,
+            delivery_time: conversion::optional_timestamp("delivery_time", message.delivery_time)?,
+            failure: Some(message.failure).filter(|failure| !failure.is_empty()),
+            proof: match message.proof {
+                Some(proof) => Some(conversion::message("proof", Some(proof))?),
+                None => None,
+            },
+            shipments: conversion::repeated("shipments", message.shipments)?,
+            marginal_distance_m: launch_time.map(|_| message.marginal_distance_m),
+            wait_s: launch_time.map(|_| message.wait_s),
+        })
+    }
+}
+
+impl ToFromProto<proto::server::Shipment> for Shipment {
+    fn into_proto(self) -> proto::server::Shipment {
+        proto::server::Shipment {
+            flight: flight_id_into_proto(self.flight),
+            carrier: self.carrier.0,
+            packages: self.packages,
+            launch_time: Some(time::to_proto_timestamp(self.launch_time)),
+            delivery_time: Some(time::to_proto_timestamp(self.delivery_time)),
+            marginal_distance_m: self.marginal_distance_m,
         }
     }
 
-    fn try_from_proto(message: proto::server::Order) -> Option<Self> {
-        Some(Self {
-            time: message.time as u64,
-            destination: DestinationName::from_str(&message.destination),
-            priority: match message.priority() {
-                proto::server::Priority::Emergency => Priority::Emergency,
-                proto::server::Priority::Resupply => Priority::Resupply,
+    fn try_from_proto(message: proto::server::Shipment) -> Result<Self, ConversionError> {
+        Ok(Self {
+            flight: flight_id_from_proto(&message.flight)?,
+            carrier: CarrierId(message.carrier),
+            packages: message.packages,
+            launch_time: conversion::timestamp(
+                "launch_time",
+                conversion::required("launch_time", message.launch_time)?,
+            )?,
+            delivery_time: conversion::timestamp(
+                "delivery_time",
+                conversion::required("delivery_time", message.delivery_time)?,
+            )?,
+            marginal_distance_m: message.marginal_distance_m,
+        })
+    }
+}
+
+impl ToFromProto<proto::server::DeliveryProof> for DeliveryProof {
+    fn into_proto(self) -> proto::server::DeliveryProof {
+        proto::server::DeliveryProof {
+            drop_north_m: self.drop_north_m,
+            drop_east_m: self.drop_east_m,
+            photo_hash: self.photo_hash,
+            recipient_code: self.recipient_code,
+        }
+    }
+
+    fn try_from_proto(message: proto::server::DeliveryProof) -> Result<Self, ConversionError> {
+        Ok(Self {
+            drop_north_m: message.drop_north_m,
+            drop_east_m: message.drop_east_m,
+            photo_hash: message.photo_hash,
+            recipient_code: message.recipient_code,
+        })
+    }
+}
+
+impl ToFromProto<proto::server::FlightExplanation> for FlightExplanation {
+    fn into_proto(self) -> proto::server::FlightExplanation {
+        use proto::server::PackingStop as Stop;
+
+        proto::server::FlightExplanation {
+            flight: flight_id_into_proto(self.flight),
+            carrier: self.carrier.0,
+            mode: match self.mode {
+                CarrierMode::Aerial => proto::server::CarrierMode::Aerial.into(),
+                CarrierMode::Ground => proto::server::CarrierMode::Ground.into(),
+            },
+            packed_at: Some(time::to_proto_timestamp(self.packed_at)),
+            capacity: self.capacity as u32,
+            queued: self.queued as u32,
+            available_carriers: self.available_carriers as u32,
+            reserved_carriers: self.reserved_carriers as u32,
+            considered: self
+                .considered
+                .into_iter()
+                .map(ConsideredOrder::into_proto)
+                .collect(),
+            stopped: match self.stopped {
+                PackingStop::QueueEmpty => Stop::QueueEmpty.into(),
+                PackingStop::DidNotFit(_) => Stop::DidNotFit.into(),
+                PackingStop::Surge => Stop::Surge.into(),
+                PackingStop::Planned => Stop::Planned.into(),
+            },
+            stopped_at_order: match self.stopped {
+                PackingStop::DidNotFit(order) => order.0,
+                _ => 0,
+            },
+        }
+    }
+
+    fn try_from_proto(message: proto::server::FlightExplanation) -> Result<Self, ConversionError> {
+        use proto::server::PackingStop as Stop;
+
+        Ok(Self {
+            flight: flight_id_from_proto(&message.flight)?,
+            carrier: CarrierId(message.carrier),
+            mode: match conversion::enumeration("mode", message.mode)? {
+                proto::server::CarrierMode::Aerial => CarrierMode::Aerial,
+                proto::server::CarrierMode::Ground => CarrierMode::Ground,
+            },
+            packed_at: conversion::timestamp(
+                "packed_at",
+                conversion::required("packed_at", message.packed_at)?,
+            )?,
+            capacity: message.capacity as usize,
+            queued: message.queued as usize,
+            available_carriers: message.available_carriers as usize,
+            reserved_carriers: message.reserved_carriers as usize,
+            considered: conversion::repeated("considered", message.considered)?,
+            stopped: match conversion::enumeration("stopped", message.stopped)? {
+                Stop::QueueEmpty => PackingStop::QueueEmpty,
+                Stop::DidNotFit => PackingStop::DidNotFit(OrderId(message.stopped_at_order)),
+                Stop::Surge => PackingStop::Surge,
+                Stop::Planned => PackingStop::Planned,
+            },
+        })
+    }
+}
+
+impl ToFromProto<proto::server::ConsideredOrder> for ConsideredOrder {
+    fn into_proto(self) -> proto::server::ConsideredOrder {
+        use proto::server::PackingOutcome as Outcome;
+
+        proto::server::ConsideredOrder {
+            order: self.order.0,
+            destination: self.destination.to_string(),
+            priority: match self.priority {
+                Priority::Emergency => proto::server::Priority::Emergency.into(),
+                Priority::Resupply => proto::server::Priority::Resupply.into(),
+            },
+            outcome: match self.outcome {
+                PackingOutcome::Packed => Outcome::Packed.into(),
+                PackingOutcome::PackedElsewhere(_) => Outcome::PackedElsewhere.into(),
+                PackingOutcome::Full => Outcome::Full.into(),
+                PackingOutcome::OutOfRange => Outcome::OutOfRange.into(),
+                PackingOutcome::Late => Outcome::Late.into(),
+                PackingOutcome::NotTried => Outcome::NotTried.into(),
+                PackingOutcome::MissedWindow => Outcome::MissedWindow.into(),
+                PackingOutcome::Spoiled => Outcome::SpoiledBeforeDelivery.into(),
+                PackingOutcome::DestinationClosed => Outcome::DestinationClosed.into(),
+            },
+            other_carrier: match self.outcome {
+                PackingOutcome::PackedElsewhere(carrier) => carrier.0,
+                _ => 0,
+            },
+        }
+    }
+
+    fn try_from_proto(message: proto::server::ConsideredOrder) -> Result<Self, ConversionError> {
+        use proto::server::PackingOutcome as Outcome;
+
+        Ok(Self {
+            order: OrderId(message.order),
+            destination: DestinationId::from(message.destination.as_str()),
+            priority: priority_from_proto(message.priority)?,
+            outcome: match conversion::enumeration("outcome", message.outcome)? {
+                Outcome::Packed => PackingOutcome::Packed,
+                Outcome::PackedElsewhere => {
+                    PackingOutcome::PackedElsewhere(CarrierId(message.other_carrier))
+                }
+                Outcome::Full => PackingOutcome::Full,
+                Outcome::OutOfRange => PackingOutcome::OutOfRange,
+                Outcome::Late => PackingOutcome::Late,
+                Outcome::NotTried => PackingOutcome::NotTried,
+                Outcome::MissedWindow => PackingOutcome::MissedWindow,
+                Outcome::SpoiledBeforeDelivery => PackingOutcome::Spoiled,
+                Outcome::DestinationClosed => PackingOutcome::DestinationClosed,
+            },
+        })
+    }
+}
+
+impl ToFromProto<proto::server::Alert> for Alert {
+    fn into_proto(self) -> proto::server::Alert {
+        proto::server::Alert {
+            time: Some(time::to_proto_timestamp(self.time)),
+            kind: match self.kind {
+                AlertKind::EmergencyWaiting => proto::server::AlertKind::EmergencyWaiting.into(),
+                AlertKind::CarrierFailure => proto::server::AlertKind::CarrierFailure.into(),
+                AlertKind::SloViolated => proto::server::AlertKind::SloViolated.into(),
+                AlertKind::DeliveryWindowMissed => {
+                    proto::server::AlertKind::DeliveryWindowMissed.into()
+                }
+                AlertKind::LaunchHeld => proto::server::AlertKind::LaunchHeld.into(),
+                AlertKind::SurgeStarted => proto::server::AlertKind::SurgeStarted.into(),
+                AlertKind::SurgeEnded => proto::server::AlertKind::SurgeEnded.into(),
+                AlertKind::SimulationFaulted => proto::server::AlertKind::SimulationFaulted.into(),
+                AlertKind::RuleTriggered => proto::server::AlertKind::RuleTriggered.into(),
+                AlertKind::ApprovalPending => proto::server::AlertKind::ApprovalPending.into(),
+            },
+            message: self.message,
+            flight: self.flight.map(flight_id_into_proto).unwrap_or_default(),
+        }
+    }
+
+    fn try_from_proto(message: proto::server::Alert) -> Result<Self, ConversionError> {
+        Ok(Self {
+            time: conversion::timestamp("time", conversion::required("time", message.time)?)?,
+            kind: match conversion::enumeration("kind", message.kind)? {
+                proto::server::AlertKind::EmergencyWaiting => AlertKind::EmergencyWaiting,
+                proto::server::AlertKind::CarrierFailure => AlertKind::CarrierFailure,
+                proto::server::AlertKind::SloViolated => AlertKind::SloViolated,
+                proto::server::AlertKind::DeliveryWindowMissed => AlertKind::DeliveryWindowMissed,
+                proto::server::AlertKind::LaunchHeld => AlertKind::LaunchHeld,
+                proto::server::AlertKind::SurgeStarted => AlertKind::SurgeStarted,
+                proto::server::AlertKind::SurgeEnded => AlertKind::SurgeEnded,
+                proto::server::AlertKind::SimulationFaulted => AlertKind::SimulationFaulted,
+                proto::server::AlertKind::RuleTriggered => AlertKind::RuleTriggered,
+                proto::server::AlertKind::ApprovalPending => AlertKind::ApprovalPending,
+            },
+            message: message.message,
+            flight: match message.flight.as_str() {
+                "" => None,
+                flight => Some(flight_id_from_proto(flight)?),
             },
         })
     }
@@ -0,0 +1,305 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, RwLock};
+
+use crate::csv;
+use crate::{Destination, DestinationId, Order};
+
+/// How loosely the destination names in orders are matched against a scenario's destinations
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Matching {
+    /// Names must match a destination or alias exactly
+    #[default]
+    Exact,
+    /// Names may differ from a destination or alias in case
+    CaseInsensitive,
+    /// Names may also differ in punctuation & whitespace, or by a typo or two
+    Fuzzy,
+}
+
+impl std::str::FromStr for Matching {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "exact" => Ok(Self::Exact),
+            "case-insensitive" => Ok(Self::CaseInsensitive),
+            "fuzzy" => Ok(Self::Fuzzy),
+            _ => Err(format!(
+                "invalid matching `{}`, expected exact, case-insensitive or fuzzy",
+                s
+            )),
+        }
+    }
+}
+
+/// Resolves the destination names used in orders to a scenario's destinations, through a table of
+/// aliases & optionally by looser matching. Names which loosely match more than one destination are
+/// rejected as ambiguous, rather than guessing between them.
+#[derive(Default, Clone, Debug)]
+pub struct DestinationMatcher {
+    aliases: HashMap<String, DestinationId>,
+    matching: Matching,
+}
+
+impl DestinationMatcher {
+    /// Loads aliases from a CSV file of `alias, destination` values
+    pub async fn from_aliases_csv(
+        path: &str,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let aliases = csv::parse_lines(
+            path,
+            |line| {
+                let (alias, destination) = line
+                    .split_once(", ")
+                    .ok_or_else(|| format!("invalid alias: {}", line))?;
+                Ok((
+                    alias.trim().to_string(),
+                    DestinationId::intern(destination.trim()),
+                ))
+            },
+            |_| {},
+        )
+        .await?;
+
+        Ok(Self::default().with_aliases(aliases))
+    }
+
+    /// Resolve each of the provided aliases to its destination
+    pub fn with_aliases(
+        mut self,
+        aliases: impl IntoIterator<Item = (String, DestinationId)>,
+    ) -> Self {
+        self.aliases.extend(aliases);
+        self
+    }
+
+    /// Match names against the destinations & aliases as loosely as provided
+    pub fn with_matching(mut self, matching: Matching) -> Self {
+        self.matching = matching;
+        self
+    }
+
+    /// Returns the destination a name refers to, either by its own name or one of its aliases
+    pub fn resolve(
+        &self,
+        name: &str,
+        destinations: &HashMap<DestinationId, Destination>,
+    ) -> Result<DestinationId, String> {
+        let unknown = || format!("unknown destination: {}", name);
+        let known = |id: DestinationId| match destinations.contains_key(&id) {
+            true => Ok(id),
+            false => Err(unknown()),
+        };
+        if let Some(id) = DestinationId::get(name).filter(|id| destinations.contains_key(id)) {
+            return Ok(id);
+        }
+        if let Some(id) = self.aliases.get(name) {
+            return known(*id);
+        }
+
+        // Every name a destination goes by, & the destination it refers to
+        let candidates = || {
+            destinations
+                .keys()
+                .map(|id| (id.as_str(), *id))
+                .chain(self.aliases.iter().map(|(alias, id)| (alias.as_str(), *id)))
+        };
+        let single = |matches: BTreeSet<&'static str>| match matches.len() {
+            0 => None,
+            1 => matches
+                .first()
+                .map(|name| DestinationId::get(name).ok_or_else(unknown).and_then(known)),
+            _ => Some(Err(format!(
+                "ambiguous destination `{}`, could be any of: {}",
+                name,
+                matches.into_iter().collect::<Vec<_>>().join(", ")
+            ))),
+        };
+
+        if self.matching == Matching::Exact {
+            return Err(unknown());
+        }
+        let lowercase = name.to_lowercase();
+        let matches = candidates()
+            .filter(|(candidate, _)| candidate.to_lowercase() == lowercase)
+            .map(|(_, id)| id.as_str())
+            .collect();
+        if let Some(result) = single(matches) {
+            return result;
+        }
+
+        if self.matching == Matching::CaseInsensitive {
+            return Err(unknown());
+        }
+        let normalized = normalize(name);
+        let max_edits = (normalized.chars().count() / 5).clamp(1, 3);
+        let distances = candidates()
+            .map(|(candidate, id)| (edit_distance(&normalized, &normalize(candidate)), id))
+            .filter(|(distance, _)| *distance <= max_edits)
+            .collect::<Vec<_>>();
+        let closest = distances.iter().map(|(distance, _)| *distance).min();
+        let matches = distances
+            .iter()
+            .filter(|(distance, _)| Some(*distance) == closest)
+            .map(|(_, id)| id.as_str())
+            .collect();
+        single(matches).unwrap_or_else(|| Err(unknown()))
+    }
+
+    /// Replaces the destination of each order with the one it resolves to
+    pub fn resolve_orders(
+        &self,
+        orders: &mut [Order],
+        destinations: &HashMap<DestinationId, Destination>,
+    ) -> Result<(), String> {
+        for order in orders {
+            order.destination = self.resolve(order.destination.as_str(), destinations)?;
+        }
+        Ok(())
+    }
+}
+
+/// Destinations a scenario's orders may be for & the matcher resolving names to them, shared by its
+/// runner (keeping it up to date as destinations change) with whatever takes orders from outside,
+/// so that names which don't refer to any destination are turned away as they arrive, rather than
+/// interned
+#[derive(Clone, Debug, Default)]
+pub struct DestinationNames(Arc<RwLock<KnownDestinations>>);
+
+#[derive(Debug, Default)]
+struct KnownDestinations {
+    matcher: DestinationMatcher,
+    destinations: HashMap<DestinationId, Destination>,
+}
+
+impl DestinationNames {
+    /// Resolve names with the provided matcher, against the provided destinations
+    pub fn update(
+        &self,
+        matcher: &DestinationMatcher,
+        destinations: &HashMap<DestinationId, Destination>,
+    ) {
+        *self.0.write().expect("names") = KnownDestinations {
+            matcher: matcher.clone(),
+            destinations: destinations.clone(),
+        };
+    }
+
+    /// Returns the destination a name refers to, as `DestinationMatcher::resolve` does
+    pub fn resolve(&self, name: &str) -> Result<DestinationId, String> {
+        let known = self.0.read().expect("names");
+        known.matcher.resolve(name, &known.destinations)
+    }
+
+    /// Names of each of the destinations, sorted
+    pub fn names(&self) -> Vec<String> {
+        let known = self.0.read().expect("names");
+        let mut names = known
+            .destinations
+            .keys()
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+}
+
+/// Lowercases a name, dropping anything other than letters & digits
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Levenshtein distance between two strings, in characters
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let destinations = ["St Paul", "St Pauls", "Toronto", "Rochester"]
+            .into_iter()
+            .map(|name| {
+                let destination = Destination {
+                    name: DestinationId::intern(name),
+                    ..Destination::default_origin()
+                };
+                (destination.name, destination)
+            })
+            .collect::<HashMap<_, _>>();
+        let aliases = [("YYZ", "Toronto"), ("Old Nest", "Winnipeg")]
+            .map(|(alias, name)| (alias.to_string(), DestinationId::intern(name)));
+        let matcher = DestinationMatcher::default().with_aliases(aliases);
+        let resolve = |matcher: &DestinationMatcher, name| {
+            matcher.resolve(name, &destinations).map(|id| id.as_str())
+        };
+
+        assert_eq!(resolve(&matcher, "Toronto"), Ok("Toronto"));
+        assert_eq!(resolve(&matcher, "YYZ"), Ok("Toronto"));
+        assert!(resolve(&matcher, "toronto").is_err());
+        // Aliases for destinations which aren't in the scenario don't resolve
+        assert!(resolve(&matcher, "Old Nest").is_err());
+
+        let matcher = matcher.with_matching(Matching::CaseInsensitive);
+        assert_eq!(resolve(&matcher, "toronto"), Ok("Toronto"));
+        assert_eq!(resolve(&matcher, "yyz"), Ok("Toronto"));
+        assert!(resolve(&matcher, "Tornto").is_err());
+
+        let matcher = matcher.with_matching(Matching::Fuzzy);
+        assert_eq!(resolve(&matcher, "Tornto"), Ok("Toronto"));
+        assert_eq!(resolve(&matcher, "ROCHESTER, "), Ok("Rochester"));
+        assert_eq!(resolve(&matcher, "st. paul"), Ok("St Paul"));
+        assert!(resolve(&matcher, "Minneapolis").is_err());
+        // Names as close to one destination as another are reported rather than guessed at
+        let ambiguous = resolve(&matcher, "St Paulz").expect_err("ambiguous");
+        assert!(ambiguous.contains("St Paul, St Pauls"), "{}", ambiguous);
+    }
+
+    #[test]
+    fn test_destination_names() {
+        let names = DestinationNames::default();
+        assert!(names.resolve("Toronto").is_err());
+
+        let destinations = ["Toronto", "Rochester"]
+            .into_iter()
+            .map(|name| {
+                let destination = Destination {
+                    name: DestinationId::intern(name),
+                    ..Destination::default_origin()
+                };
+                (destination.name, destination)
+            })
+            .collect::<HashMap<_, _>>();
+        let matcher = DestinationMatcher::default().with_matching(Matching::Fuzzy);
+        names.clone().update(&matcher, &destinations);
+        assert_eq!(names.names(), ["Rochester", "Toronto"]);
+        assert_eq!(names.resolve("Tornto").map(|id| id.as_str()), Ok("Toronto"));
+
+        // Names which don't refer to any destination are rejected without being interned
+        assert!(names.resolve("Nowhere Interned").is_err());
+        assert_eq!(DestinationId::get("Nowhere Interned"), None);
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("toronto", "toronto"), 0);
+    }
+}
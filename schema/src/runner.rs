@@ -1,9 +1,9 @@
-use std::num::NonZeroU8;
+use std::num::NonZeroU16;
 use std::time::Duration;
 
 use futures::Future;
 
-use crate::Scheduler;
+use crate::{time, Scheduler};
 
 /// A `Runner` dispatches carriers to fulfill orders using a provided `Scheduler`.
 /// It returns a `Response` future, which may be polled to drive its operation
@@ -18,20 +18,20 @@ pub trait Runner<S: Scheduler> {
 }
 
 /// Allows running in fast-forward or slow-motion instead of real-time
-#[derive(Default, Clone, Copy, Debug)]
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Speed {
     #[default]
     RealTime,
     /// Speed up the runner by the provided multiplier (e.g. `2` gives double speed)
-    FastForward(NonZeroU8),
+    FastForward(NonZeroU16),
     /// Slow down the runner by the provided multiplier (e.g. `2` gives half speed)
     #[allow(unused)]
-    SlowMotion(NonZeroU8),
+    SlowMotion(NonZeroU16),
 }
 
 impl Speed {
-    pub fn fast_forward(rate: u8) -> Option<Self> {
-        NonZeroU8::new(rate).map(Self::FastForward)
+    pub fn fast_forward(rate: u16) -> Option<Self> {
+        NonZeroU16::new(rate).map(Self::FastForward)
     }
 
     pub fn adjust_duration(&self, duration: Duration) -> Duration {
@@ -42,19 +42,311 @@ impl Speed {
         }
     }
 
-    pub(crate) fn to_i32(&self) -> i32 {
+    /// Simulated milliseconds which pass in the provided wall-clock milliseconds at the speed,
+    /// rounded down
+    pub fn simulated_millis(&self, wall_millis: u64) -> u64 {
+        match self {
+            Self::RealTime => wall_millis,
+            Self::FastForward(x) => wall_millis * x.get() as u64,
+            Self::SlowMotion(x) => wall_millis / x.get() as u64,
+        }
+    }
+
+    /// Wall-clock milliseconds it takes the provided simulated milliseconds to pass at the speed,
+    /// rounded up
+    pub fn wall_millis(&self, simulated_millis: u64) -> u64 {
+        match self {
+            Self::RealTime => simulated_millis,
+            Self::FastForward(x) => simulated_millis.div_ceil(x.get() as u64),
+            Self::SlowMotion(x) => simulated_millis * x.get() as u64,
+        }
+    }
+
+    pub(crate) fn to_i32(self) -> i32 {
         match self {
             Self::RealTime => 0,
             Self::FastForward(x) => x.get() as i32,
-            Self::SlowMotion(x) => -1 * x.get() as i32,
+            Self::SlowMotion(x) => -(x.get() as i32),
         }
     }
 
     pub(crate) fn from_i32(n: i32) -> Self {
-        match n {
-            n if n == 0 => Self::RealTime,
-            n if n > 0 => Self::FastForward(NonZeroU8::new(n as u8).expect("speed")),
-            _ => Self::SlowMotion(NonZeroU8::new(n as u8).expect("speed")),
+        // Rates beyond what a speed can hold are clamped to the fastest (or slowest) it can
+        let rate = NonZeroU16::new(n.unsigned_abs().min(u16::MAX as u32) as u16);
+        match rate {
+            None => Self::RealTime,
+            Some(rate) if n > 0 => Self::FastForward(rate),
+            Some(rate) => Self::SlowMotion(rate),
+        }
+    }
+}
+
+impl std::fmt::Display for Speed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RealTime => write!(f, "real-time"),
+            Self::FastForward(x) => write!(f, "{}x", x),
+            Self::SlowMotion(x) => write!(f, "1/{}x", x),
+        }
+    }
+}
+
+/// Parses speeds in the same format they are displayed, e.g. `real-time`, `2x` or `1/2x`
+impl std::str::FromStr for Speed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid speed: {}", s);
+        let rate = |n: &str| n.parse::<NonZeroU16>().map_err(|_| invalid());
+
+        match s.strip_suffix('x') {
+            _ if s == "real-time" => Ok(Self::RealTime),
+            Some("1") => Ok(Self::RealTime),
+            Some(n) => match n.strip_prefix("1/") {
+                Some(n) => rate(n).map(Self::SlowMotion),
+                None => rate(n).map(Self::FastForward),
+            },
+            None => Err(invalid()),
+        }
+    }
+}
+
+/// Simulated time in milliseconds, advanced by steps of wall-clock time at a speed. Time is worked
+/// out from the wall-clock time elapsed since the speed was set, rather than by adding up each
+/// step, so that steps which don't divide evenly at the speed (e.g. 50ms at `1/3x`) don't drift.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SimulatedClock {
+    speed: Speed,
+    /// Simulated time at which the speed was set
+    origin_millis: u64,
+    /// Wall-clock milliseconds elapsed since the speed was set
+    elapsed_millis: u64,
+}
+
+impl SimulatedClock {
+    pub fn new(time_millis: u64, speed: Speed) -> Self {
+        Self {
+            speed,
+            origin_millis: time_millis,
+            elapsed_millis: 0,
+        }
+    }
+
+    /// Current simulated time in milliseconds
+    pub fn now_millis(&self) -> u64 {
+        self.origin_millis + self.speed.simulated_millis(self.elapsed_millis)
+    }
+
+    pub fn speed(&self) -> Speed {
+        self.speed
+    }
+
+    /// Advances the clock by the provided wall-clock milliseconds, returning the new time
+    pub fn advance(&mut self, wall_millis: u64) -> u64 {
+        self.elapsed_millis += wall_millis;
+        self.now_millis()
+    }
+
+    /// Carries on from the current time at the provided speed
+    pub fn set_speed(&mut self, speed: Speed) {
+        if speed != self.speed {
+            *self = Self::new(self.now_millis(), speed);
+        }
+    }
+
+    /// Wall-clock milliseconds since the speed was set at which the clock reaches the provided
+    /// simulated time, or zero if it was set after it
+    pub fn wall_millis_until(&self, time_millis: u64) -> u64 {
+        self.speed
+            .wall_millis(time_millis.saturating_sub(self.origin_millis))
+    }
+}
+
+/// Speeds to play a run at through each simulated day, e.g. `22:00-07:00=500x, 07:00-22:00=50x,
+/// 10:00-10:30=real-time`. Windows may wrap past midnight, & later windows take precedence over
+/// the earlier ones they overlap. Outside all of them, the run's own speed applies.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SpeedSchedule {
+    windows: Vec<SpeedWindow>,
+}
+
+/// Time of day through which a `SpeedSchedule` plays a run at a speed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SpeedWindow {
+    /// Seconds since midnight at which the window starts
+    pub start_s: u64,
+    /// Seconds since midnight at which the window ends, before its start if it wraps past midnight
+    pub end_s: u64,
+    pub speed: Speed,
+}
+
+impl SpeedWindow {
+    fn contains(&self, time_of_day: u64) -> bool {
+        match self.start_s <= self.end_s {
+            true => (self.start_s..self.end_s).contains(&time_of_day),
+            false => time_of_day >= self.start_s || time_of_day < self.end_s,
         }
     }
 }
+
+impl SpeedSchedule {
+    pub fn windows(&self) -> &[SpeedWindow] {
+        &self.windows
+    }
+
+    /// Returns the speed scheduled at a timestamp, if it falls within any of the windows
+    pub fn speed_at(&self, timestamp: u64) -> Option<Speed> {
+        let time_of_day = time::time_of_day(timestamp);
+        self.windows
+            .iter()
+            .rev()
+            .find(|window| window.contains(time_of_day))
+            .map(|window| window.speed)
+    }
+
+    /// Returns the next timestamp after the provided one at which any of the windows starts or
+    /// ends, if there are any
+    pub fn next_boundary(&self, after: u64) -> Option<u64> {
+        let midnight = after - time::time_of_day(after);
+        [midnight, midnight + time::SECONDS_PER_DAY]
+            .into_iter()
+            .flat_map(|midnight| {
+                self.windows
+                    .iter()
+                    .flat_map(move |window| [midnight + window.start_s, midnight + window.end_s])
+            })
+            .filter(|boundary| *boundary > after)
+            .min()
+    }
+}
+
+/// Parses comma-separated windows of `START-END=SPEED`, with times of day as `HH:MM[:SS]` & speeds
+/// as `Speed`s are parsed
+impl std::str::FromStr for SpeedSchedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let windows = s
+            .split(',')
+            .map(str::trim)
+            .filter(|window| !window.is_empty())
+            .map(|window| {
+                let invalid = || format!("invalid speed window `{}`", window);
+                let (times, speed) = window.split_once('=').ok_or_else(invalid)?;
+                let (start, end) = times.split_once('-').ok_or_else(invalid)?;
+                let window = SpeedWindow {
+                    start_s: time::parse_time_of_day(start)?,
+                    end_s: time::parse_time_of_day(end)?,
+                    speed: speed.trim().parse()?,
+                };
+                match window.start_s % time::SECONDS_PER_DAY == window.end_s % time::SECONDS_PER_DAY
+                {
+                    true => Err(format!("speed window `{}` is empty", times.trim())),
+                    false => Ok(window),
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { windows })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SPEEDS: [&str; 8] = [
+        "real-time",
+        "2x",
+        "3x",
+        "7x",
+        "200x",
+        "1/2x",
+        "1/3x",
+        "1/7x",
+    ];
+
+    #[test]
+    fn test_simulated_clock_drift() {
+        // A minute of client ticks, which don't divide evenly at most of the speeds
+        for speed in SPEEDS.map(|speed| speed.parse::<Speed>().expect("speed")) {
+            let mut clock = SimulatedClock::new(1_000, speed);
+            for _ in 0..1_200 {
+                clock.advance(50);
+            }
+            let expected = 1_000 + speed.simulated_millis(60_000);
+            assert_eq!(clock.now_millis(), expected, "{}", speed);
+            // Exactly a minute of wall-clock time, less any rounding at slow speeds
+            let exact = match speed {
+                Speed::RealTime => 60_000.0,
+                Speed::FastForward(x) => 60_000.0 * x.get() as f64,
+                Speed::SlowMotion(x) => 60_000.0 / x.get() as f64,
+            };
+            assert!(
+                (1_000.0 + exact - clock.now_millis() as f64).abs() < 1.0,
+                "{}",
+                speed
+            );
+        }
+    }
+
+    #[test]
+    fn test_simulated_clock_deadlines() {
+        // Each simulated second the server sleeps until is reached within a millisecond
+        for speed in SPEEDS.map(|speed| speed.parse::<Speed>().expect("speed")) {
+            let clock = SimulatedClock::new(60_000, speed);
+            for second in 61..=3_660_u64 {
+                let wall = clock.wall_millis_until(second * 1_000);
+                let mut reached = clock;
+                assert!(reached.advance(wall) >= second * 1_000, "{}", speed);
+                let mut before = clock;
+                assert!(
+                    before.advance(wall.saturating_sub(1)) < second * 1_000,
+                    "{}",
+                    speed
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_simulated_clock_speed_change() {
+        let mut clock =
+            SimulatedClock::new(0, Speed::SlowMotion(NonZeroU16::new(3).expect("speed")));
+        assert_eq!(clock.advance(50), 16);
+        assert_eq!(clock.advance(50), 33);
+        // Time carries on from where it was at the new speed
+        clock.set_speed(Speed::fast_forward(4).expect("speed"));
+        assert_eq!(clock.advance(50), 233);
+        assert_eq!(clock.wall_millis_until(1_000), 242);
+        assert_eq!(clock.wall_millis_until(0), 0);
+    }
+
+    #[test]
+    fn test_speed_schedule() {
+        let schedule = "22:00-07:00=500x, 07:00-22:00=50x, 10:00-10:30=real-time"
+            .parse::<SpeedSchedule>()
+            .expect("schedule");
+        let at = |time: &str| schedule.speed_at(time::parse_time_of_day(time).expect("time"));
+        assert_eq!(at("03:00"), Speed::fast_forward(500));
+        assert_eq!(at("07:00"), Speed::fast_forward(50));
+        // The demo window takes precedence over the one it falls within
+        assert_eq!(at("10:15"), Some(Speed::RealTime));
+        assert_eq!(at("10:30"), Speed::fast_forward(50));
+        assert_eq!(at("23:59"), Speed::fast_forward(500));
+
+        // Windows repeat every day
+        let day = time::SECONDS_PER_DAY;
+        assert_eq!(schedule.speed_at(day + 36_900), Some(Speed::RealTime));
+        assert_eq!(schedule.next_boundary(36_000), Some(37_800));
+        assert_eq!(schedule.next_boundary(79_200), Some(day + 25_200));
+
+        // Times outside every window are left to the run
+        let demo = "10:00-10:30=real-time"
+            .parse::<SpeedSchedule>()
+            .expect("schedule");
+        assert_eq!(demo.speed_at(0), None);
+        assert!("10:00-10:00=2x".parse::<SpeedSchedule>().is_err());
+        assert!("10:00=2x".parse::<SpeedSchedule>().is_err());
+        assert!("10:00-11:00=fast".parse::<SpeedSchedule>().is_err());
+    }
+}
@@ -0,0 +1,342 @@
+//! Diffs & merges of the files making up a scenario, for keeping variants of a scenario in step.
+//! Destinations are matched up with their counterparts by name, & orders by the time they're
+//! placed, their destination & priority, so that an entry whose other values differ counts as
+//! changed rather than as removed & added again.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::{json, Destination, Order};
+
+/// An entry of a scenario file, matched up with its counterpart in a variant of the scenario by
+/// its key
+pub trait Entry: Clone + PartialEq {
+    /// Whether no two entries of a file may share a key
+    const UNIQUE: bool;
+
+    fn key(&self) -> String;
+
+    /// Formats the entry as a line of the file it's read from
+    fn to_csv_line(&self) -> String;
+}
+
+impl Entry for Destination {
+    const UNIQUE: bool = true;
+
+    fn key(&self) -> String {
+        self.name.to_string()
+    }
+
+    fn to_csv_line(&self) -> String {
+        Destination::to_csv_line(self)
+    }
+}
+
+impl Entry for Order {
+    const UNIQUE: bool = false;
+
+    fn key(&self) -> String {
+        format!("{}, {}, {}", self.time, self.destination, self.priority)
+    }
+
+    fn to_csv_line(&self) -> String {
+        Order::to_csv_line(self)
+    }
+}
+
+/// Entries added to, removed from & changed in a variant of a scenario file
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diff<T> {
+    /// Entries of the variant with no counterpart in the base, in the variant's order
+    pub added: Vec<T>,
+    /// Entries of the base with no counterpart in the variant, in the base's order
+    pub removed: Vec<T>,
+    /// Entries of the base whose counterparts in the variant differ, before & after
+    pub changed: Vec<(T, T)>,
+    /// Positions in the base of the removed & changed entries, for merging
+    removed_at: Vec<usize>,
+    changed_at: Vec<usize>,
+}
+
+impl<T: Entry> Diff<T> {
+    pub fn new(base: &[T], variant: &[T]) -> Self {
+        let mut counterparts = HashMap::<String, (Vec<usize>, Vec<usize>)>::new();
+        for (i, entry) in base.iter().enumerate() {
+            counterparts.entry(entry.key()).or_default().0.push(i);
+        }
+        for (i, entry) in variant.iter().enumerate() {
+            counterparts.entry(entry.key()).or_default().1.push(i);
+        }
+
+        let (mut removed_at, mut changed_at, mut added_at) = (vec![], vec![], Vec::<usize>::new());
+        for (mut from, mut to) in counterparts.into_values() {
+            // Entries left as they were are matched up first, & the rest in the order they appear
+            from.retain(|i| match to.iter().position(|j| variant[*j] == base[*i]) {
+                Some(k) => {
+                    to.remove(k);
+                    false
+                }
+                None => true,
+            });
+            changed_at.extend(from.iter().copied().zip(to.iter().copied()));
+            removed_at.extend(from.iter().copied().skip(to.len()));
+            added_at.extend(to.iter().copied().skip(from.len()));
+        }
+        removed_at.sort();
+        changed_at.sort();
+        added_at.sort();
+
+        Self {
+            added: added_at.iter().map(|j| variant[*j].clone()).collect(),
+            removed: removed_at.iter().map(|i| base[*i].clone()).collect(),
+            changed: changed_at
+                .iter()
+                .map(|(i, j)| (base[*i].clone(), variant[*j].clone()))
+                .collect(),
+            removed_at,
+            changed_at: changed_at.into_iter().map(|(i, _)| i).collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Returns the diff with each entry as a line of its file
+    pub fn to_json(&self) -> json::Value {
+        let lines = |entries: &[T]| {
+            json::Value::Array(
+                entries
+                    .iter()
+                    .map(|entry| entry.to_csv_line().into())
+                    .collect(),
+            )
+        };
+        json::Value::Object(vec![
+            ("added".to_string(), lines(&self.added)),
+            ("removed".to_string(), lines(&self.removed)),
+            (
+                "changed".to_string(),
+                json::Value::Array(
+                    self.changed
+                        .iter()
+                        .map(|(before, after)| {
+                            json::Value::Object(vec![
+                                ("before".to_string(), before.to_csv_line().into()),
+                                ("after".to_string(), after.to_csv_line().into()),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+        ])
+    }
+}
+
+/// Lists removed entries as `- line`, changed as `~ before => after` & added as `+ line`
+impl<T: Entry> fmt::Display for Diff<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.removed {
+            writeln!(f, "- {}", entry.to_csv_line())?;
+        }
+        for (before, after) in &self.changed {
+            writeln!(f, "~ {} => {}", before.to_csv_line(), after.to_csv_line())?;
+        }
+        for entry in &self.added {
+            writeln!(f, "+ {}", entry.to_csv_line())?;
+        }
+        Ok(())
+    }
+}
+
+/// What a variant did to an entry of the base
+#[derive(PartialEq)]
+enum Edit<T> {
+    Removed,
+    Changed(T),
+}
+
+/// Merges named variants of the same base into one, applying the changes each made to the base in
+/// place & appending the entries they added. Entries added alike by several variants are only
+/// added once. Fails, listing every conflict, if variants made different changes to the same
+/// entry, or added different entries under a key which must be unique.
+pub fn merge<T: Entry>(base: &[T], variants: &[(&str, Vec<T>)]) -> Result<Vec<T>, String> {
+    let mut edits = HashMap::<usize, (&str, Edit<T>)>::new();
+    let mut added = Vec::<(&str, T)>::new();
+    let mut conflicts = vec![];
+
+    for (name, variant) in variants {
+        let diff = Diff::new(base, variant);
+        let changes = diff.removed_at.iter().map(|i| (*i, Edit::Removed)).chain(
+            diff.changed_at
+                .iter()
+                .zip(diff.changed)
+                .map(|(i, (_, after))| (*i, Edit::Changed(after))),
+        );
+        for (i, edit) in changes {
+            match edits.get(&i) {
+                Some((_, existing)) if *existing == edit => {}
+                Some((other, existing)) => conflicts.push(format!(
+                    "`{}` {} by {} but {} by {}",
+                    base[i].to_csv_line(),
+                    describe(existing),
+                    other,
+                    describe(&edit),
+                    name
+                )),
+                None => {
+                    edits.insert(i, (name, edit));
+                }
+            }
+        }
+
+        // Entries which earlier variants added alike are claimed rather than added again
+        let mut unclaimed = (0..added.len()).collect::<Vec<_>>();
+        for entry in diff.added {
+            match unclaimed.iter().position(|k| added[*k].1 == entry) {
+                Some(k) => {
+                    unclaimed.remove(k);
+                }
+                None => added.push((name, entry)),
+            }
+        }
+    }
+
+    if T::UNIQUE {
+        let mut keys = HashMap::<String, &(&str, T)>::new();
+        for addition in &added {
+            match keys.get(&addition.1.key()) {
+                Some((other, entry)) => conflicts.push(format!(
+                    "`{}` added by {} but `{}` by {}",
+                    entry.to_csv_line(),
+                    other,
+                    addition.1.to_csv_line(),
+                    addition.0
+                )),
+                None => {
+                    keys.insert(addition.1.key(), addition);
+                }
+            }
+        }
+    }
+    if !conflicts.is_empty() {
+        return Err(format!(
+            "{} conflicts merging variants:\n{}",
+            conflicts.len(),
+            conflicts.join("\n")
+        ));
+    }
+
+    let mut merged = base
+        .iter()
+        .enumerate()
+        .filter_map(|(i, entry)| match edits.remove(&i) {
+            Some((_, Edit::Removed)) => None,
+            Some((_, Edit::Changed(after))) => Some(after),
+            None => Some(entry.clone()),
+        })
+        .collect::<Vec<_>>();
+    merged.extend(added.into_iter().map(|(_, entry)| entry));
+    Ok(merged)
+}
+
+fn describe<T: Entry>(edit: &Edit<T>) -> String {
+    match edit {
+        Edit::Removed => "removed".to_string(),
+        Edit::Changed(after) => format!("changed to `{}`", after.to_csv_line()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{testkit, Priority};
+
+    fn destination(line: &str) -> Destination {
+        line.parse().expect("destination")
+    }
+
+    #[test]
+    fn test_diff() {
+        let base = [
+            destination("Toronto, 100, 200"),
+            destination("Detroit, -300, 50"),
+            destination("Rochester, 0, 900"),
+        ];
+        let variant = [
+            destination("Rochester, 0, 900"),
+            destination("Toronto, 100, 250, Ontario"),
+            destination("Buffalo, 10, 700"),
+        ];
+        let diff = Diff::new(&base, &variant);
+
+        // Destinations are matched up by name, wherever they appear
+        assert_eq!(diff.removed, vec![base[1].clone()]);
+        assert_eq!(diff.changed, vec![(base[0].clone(), variant[1].clone())]);
+        assert_eq!(diff.added, vec![variant[2].clone()]);
+        assert_eq!(
+            diff.to_string(),
+            "- Detroit, -300, 50\n~ Toronto, 100, 200 => Toronto, 100, 250, Ontario\n+ Buffalo, 10, 700\n"
+        );
+        assert!(Diff::new(&base, &base).is_empty());
+
+        // Orders placed alike are matched up with those left as they were first
+        let order = testkit::order(600, "Toronto", Priority::Resupply);
+        let later = Order {
+            latest_delivery: Some(3_600),
+            ..order.clone()
+        };
+        let diff = Diff::new(
+            &[order.clone(), order.clone()],
+            &[later.clone(), order.clone(), later.clone()],
+        );
+        assert_eq!(diff.removed, vec![]);
+        assert_eq!(diff.changed, vec![(order.clone(), later.clone())]);
+        assert_eq!(diff.added, vec![later]);
+    }
+
+    #[test]
+    fn test_merge() {
+        let base = vec![
+            destination("Toronto, 100, 200"),
+            destination("Detroit, -300, 50"),
+            destination("Rochester, 0, 900"),
+        ];
+        let ours = vec![
+            destination("Toronto, 100, 250"),
+            destination("Rochester, 0, 900"),
+            destination("Buffalo, 10, 700"),
+        ];
+        let theirs = vec![
+            destination("Toronto, 100, 250"),
+            destination("Detroit, -300, 50"),
+            destination("Rochester, 0, 950"),
+            destination("Buffalo, 10, 700"),
+        ];
+
+        // The changes of each variant are applied, & alike changes & additions only once
+        let merged = merge(&base, &[("ours", ours.clone()), ("theirs", theirs)]).expect("merge");
+        assert_eq!(
+            merged,
+            vec![
+                destination("Toronto, 100, 250"),
+                destination("Rochester, 0, 950"),
+                destination("Buffalo, 10, 700"),
+            ]
+        );
+
+        // ...unless they conflict
+        let conflicting = vec![
+            destination("Toronto, 100, 300"),
+            destination("Rochester, 0, 900"),
+            destination("Buffalo, 20, 700"),
+        ];
+        let error =
+            merge(&base, &[("ours", ours), ("theirs", conflicting)]).expect_err("conflicts");
+        assert!(error.starts_with("2 conflicts"));
+        assert!(error.contains(
+            "`Toronto, 100, 200` changed to `Toronto, 100, 250` by ours but changed to `Toronto, 100, 300` by theirs"
+        ));
+        assert!(error.contains("`Buffalo, 10, 700` added by ours but `Buffalo, 20, 700` by theirs"));
+    }
+}
@@ -21,3 +21,54 @@ pub trait Scheduler {
     /// Return a list of all flights that should be launched at the given time
     fn launch_flights<'a>(&'a mut self, current_time: u64) -> Self::LaunchedFlights<'a>;
 }
+
+/// Object-safe counterpart of `Scheduler`, returning boxed iterators so that schedulers can be
+/// chosen at runtime & used as a `Box<dyn DynScheduler>`. Every `Scheduler` is a `DynScheduler`.
+pub trait DynScheduler {
+    /// See `Scheduler::unfulfilled_orders`
+    fn unfulfilled_orders(&self) -> Box<dyn Iterator<Item = &Order> + '_>;
+
+    /// See `Scheduler::queue_order`
+    fn queue_order(&mut self, order: Order);
+
+    /// See `Scheduler::launch_flights`
+    fn launch_flights(&mut self, current_time: u64) -> Box<dyn Iterator<Item = &Flight> + '_>;
+}
+
+impl<S: Scheduler> DynScheduler for S {
+    fn unfulfilled_orders(&self) -> Box<dyn Iterator<Item = &Order> + '_> {
+        Box::new(Scheduler::unfulfilled_orders(self))
+    }
+
+    fn queue_order(&mut self, order: Order) {
+        Scheduler::queue_order(self, order)
+    }
+
+    fn launch_flights(&mut self, current_time: u64) -> Box<dyn Iterator<Item = &Flight> + '_> {
+        Box::new(Scheduler::launch_flights(self, current_time))
+    }
+}
+
+/// Boxed schedulers can be used wherever a `Scheduler` is expected, e.g. by a `Runner`
+impl<'s> Scheduler for Box<dyn DynScheduler + 's> {
+    type UnfulfilledOrders<'a>
+        = Box<dyn Iterator<Item = &'a Order> + 'a>
+    where
+        Self: 'a;
+    type LaunchedFlights<'a>
+        = Box<dyn Iterator<Item = &'a Flight> + 'a>
+    where
+        Self: 'a;
+
+    fn unfulfilled_orders<'a>(&'a self) -> Self::UnfulfilledOrders<'a> {
+        (**self).unfulfilled_orders()
+    }
+
+    fn queue_order(&mut self, order: Order) {
+        (**self).queue_order(order)
+    }
+
+    fn launch_flights<'a>(&'a mut self, current_time: u64) -> Self::LaunchedFlights<'a> {
+        (**self).launch_flights(current_time)
+    }
+}
@@ -0,0 +1,94 @@
+use crate::csv;
+use crate::MapExtents;
+
+/// A `Sector` of airspace, in which no more than a maximum number of carriers may fly at once
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sector {
+    pub name: String,
+    /// Bounds of the sector, in the same frame as the destinations
+    pub extents: MapExtents,
+    /// Most carriers which may be in the sector at the same time
+    pub max_flights: usize,
+}
+
+impl Sector {
+    /// Loads sectors from a CSV file with `name, west_m, north_m, east_m, south_m, max_flights`
+    /// columns
+    pub async fn from_csv(
+        path: &str,
+    ) -> Result<Vec<Self>, Box<dyn std::error::Error + Send + Sync>> {
+        csv::parse_lines(path, |line| Ok(line.parse()?), |_| {}).await
+    }
+
+    /// Whether an east/north position in meters is within the sector. Positions on its western &
+    /// southern edges are, those on its eastern & northern edges belong to its neighbors.
+    pub fn contains(&self, (east_m, north_m): (f32, f32)) -> bool {
+        let MapExtents {
+            west_m,
+            north_m: top,
+            east_m: right,
+            south_m,
+        } = self.extents;
+        (west_m as f32..right as f32).contains(&east_m)
+            && (south_m as f32..top as f32).contains(&north_m)
+    }
+}
+
+impl std::str::FromStr for Sector {
+    type Err = String;
+
+    /// Parses a sector from a line of `name, west_m, north_m, east_m, south_m, max_flights` values
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s.split(',').map(str::trim).collect::<Vec<_>>();
+        let [name, bounds @ .., max_flights] = &values[..] else {
+            return Err(format!("invalid sector: {}", s));
+        };
+        let max_flights = max_flights
+            .parse::<usize>()
+            .ok()
+            .filter(|max| *max > 0)
+            .ok_or_else(|| format!("invalid maximum flights for sector {}: {}", name, s))?;
+
+        Ok(Self {
+            name: name.to_string(),
+            extents: bounds.join(", ").parse()?,
+            max_flights,
+        })
+    }
+}
+
+/// Number of carriers flying in a sector at a moment of the simulation
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SectorOccupancy {
+    pub sector: Sector,
+    pub flights: usize,
+}
+
+impl SectorOccupancy {
+    /// Whether no more carriers may enter the sector
+    pub fn is_full(&self) -> bool {
+        self.flights >= self.sector.max_flights
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sector() {
+        let sector = "North, -5000, 4000, 5000, 0, 2"
+            .parse::<Sector>()
+            .expect("sector");
+        assert_eq!(sector.name, "North");
+        assert_eq!(sector.max_flights, 2);
+        assert!(sector.contains((0.0, 0.0)));
+        assert!(sector.contains((-5000.0, 3999.0)));
+        assert!(!sector.contains((0.0, 4000.0)));
+        assert!(!sector.contains((5000.0, 2000.0)));
+
+        // Sectors must admit at least one carrier, & have bounds
+        assert!("North, -5000, 4000, 5000, 0, 0".parse::<Sector>().is_err());
+        assert!("North, -5000, 4000, 2".parse::<Sector>().is_err());
+    }
+}
@@ -0,0 +1,191 @@
+//! Helpers for writing behavioral tests of `Scheduler` implementations.
+//!
+//! A `Harness` drives a scheduler with a fake clock, recording every flight it launches:
+//!
+//! ```
+//! # use schema::{testkit::Harness, Flight, Order, Scheduler};
+//! # struct OneOrderPerFlight(Vec<Order>, Vec<Flight>);
+//! # impl Scheduler for OneOrderPerFlight {
+//! #     type UnfulfilledOrders<'a> = std::slice::Iter<'a, Order>;
+//! #     type LaunchedFlights<'a> = std::slice::Iter<'a, Flight>;
+//! #     fn unfulfilled_orders(&self) -> Self::UnfulfilledOrders<'_> { self.0.iter() }
+//! #     fn queue_order(&mut self, order: Order) { self.0.push(order) }
+//! #     fn launch_flights(&mut self, current_time: u64) -> Self::LaunchedFlights<'_> {
+//! #         self.1 = self.0.drain(..).map(|order| Flight {
+//! #             launch_time: current_time,
+//! #             orders: vec![order],
+//! #             ..Default::default()
+//! #         }).collect();
+//! #         self.1.iter()
+//! #     }
+//! # }
+//! let mut harness = Harness::new(OneOrderPerFlight(vec![], vec![]));
+//!
+//! harness.at(60).emergency("Toronto").resupply("Detroit");
+//! let flights = harness.at(120).launch();
+//!
+//! assert_eq!(flights.len(), 2);
+//! harness.assert_unfulfilled(0);
+//! ```
+
+use std::collections::HashMap;
+
+use crate::{
+    Destination, DestinationId, DestinationMatcher, DestinationNames, Flight, Order, Priority,
+    Scheduler,
+};
+
+/// Builds a map of destinations from `(name, north_m, east_m)` tuples
+pub fn destinations(destinations: &[(&str, i64, i64)]) -> HashMap<DestinationId, Destination> {
+    destinations
+        .iter()
+        .map(|(name, north_m, east_m)| {
+            let dest = Destination {
+                name: DestinationId::from(*name),
+                north_m: *north_m,
+                east_m: *east_m,
+                region: None,
+                hours: None,
+            };
+            (dest.name, dest)
+        })
+        .collect()
+}
+
+/// Builds names resolving exactly to destinations of the provided names
+pub fn destination_names(names: &[&str]) -> DestinationNames {
+    let destinations = names
+        .iter()
+        .map(|name| {
+            let dest = Destination {
+                name: DestinationId::from(*name),
+                ..Destination::default_origin()
+            };
+            (dest.name, dest)
+        })
+        .collect();
+    let destination_names = DestinationNames::default();
+    destination_names.update(&DestinationMatcher::default(), &destinations);
+    destination_names
+}
+
+/// Builds an order placed at the given time
+pub fn order(time: u64, destination: &str, priority: Priority) -> Order {
+    Order {
+        time,
+        destination: DestinationId::from(destination),
+        priority,
+        ..Default::default()
+    }
+}
+
+/// Drives a `Scheduler` through a scripted sequence of orders & launches using a fake clock
+pub struct Harness<S> {
+    scheduler: S,
+    current_time: u64,
+    launched: Vec<Flight>,
+}
+
+impl<S: Scheduler> Harness<S> {
+    pub fn new(scheduler: S) -> Self {
+        Self {
+            scheduler,
+            current_time: 0,
+            launched: vec![],
+        }
+    }
+
+    /// Advance the clock to the given timestamp in __seconds__
+    pub fn at(&mut self, time: u64) -> &mut Self {
+        assert!(
+            time >= self.current_time,
+            "the clock can't go backwards from {} to {}",
+            self.current_time,
+            time
+        );
+        self.current_time = time;
+        self
+    }
+
+    /// Queue an order placed at the current time
+    pub fn queue(&mut self, destination: &str, priority: Priority) -> &mut Self {
+        self.scheduler
+            .queue_order(order(self.current_time, destination, priority));
+        self
+    }
+
+    /// Queue an emergency order placed at the current time
+    pub fn emergency(&mut self, destination: &str) -> &mut Self {
+        self.queue(destination, Priority::Emergency)
+    }
+
+    /// Queue a resupply order placed at the current time
+    pub fn resupply(&mut self, destination: &str) -> &mut Self {
+        self.queue(destination, Priority::Resupply)
+    }
+
+    /// Ask the scheduler to launch flights at the current time, returning any which were launched
+    pub fn launch(&mut self) -> Vec<Flight> {
+        let flights = self
+            .scheduler
+            .launch_flights(self.current_time)
+            .cloned()
+            .collect::<Vec<_>>();
+        self.launched.extend(flights.iter().cloned());
+        flights
+    }
+
+    /// Launch every `interval_s` seconds until the given time, returning all flights launched
+    pub fn launch_every(&mut self, interval_s: u64, until: u64) -> Vec<Flight> {
+        let mut flights = vec![];
+        let mut time = self.current_time;
+        while time <= until {
+            flights.extend(self.at(time).launch());
+            time += interval_s;
+        }
+        flights
+    }
+
+    /// Returns all of the flights launched so far
+    pub fn launched(&self) -> &[Flight] {
+        &self.launched
+    }
+
+    pub fn scheduler(&self) -> &S {
+        &self.scheduler
+    }
+
+    pub fn scheduler_mut(&mut self) -> &mut S {
+        &mut self.scheduler
+    }
+
+    pub fn into_scheduler(self) -> S {
+        self.scheduler
+    }
+
+    /// Assert that the scheduler has the given number of orders left to fulfill
+    pub fn assert_unfulfilled(&self, expected: usize) {
+        let unfulfilled = self.scheduler.unfulfilled_orders().count();
+        assert_eq!(
+            unfulfilled, expected,
+            "expected {} unfulfilled orders, found {}",
+            expected, unfulfilled
+        );
+    }
+
+    /// Assert that a flight launched so far visits exactly the given destinations, in order
+    pub fn assert_launched_to(&self, destinations: &[&str]) {
+        let found = self.launched.iter().any(|flight| {
+            flight
+                .orders
+                .iter()
+                .map(|order| order.destination.to_string())
+                .eq(destinations.iter().map(|name| name.to_string()))
+        });
+        assert!(
+            found,
+            "no flight was launched to {:?}, launched: {:?}",
+            destinations, self.launched
+        );
+    }
+}
@@ -0,0 +1,246 @@
+//! Timestamps are __seconds__ since the Unix epoch, in UTC.
+//!
+//! Scenarios which only span a single day may keep using plain seconds since midnight, which are
+//! simply treated as times on the first day of the epoch (1970-01-01).
+
+pub const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Latest year accepted by `parse_timestamp`, well short of where its seconds would overflow
+const MAX_YEAR: u64 = 9999;
+
+/// Parses either a number of seconds, or a UTC date & time such as `2023-10-14T23:59:00Z`
+pub fn parse_timestamp(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    if let Ok(seconds) = s.parse::<u64>() {
+        return Ok(seconds);
+    }
+
+    let invalid = || format!("invalid timestamp `{}`", s);
+    let s = s.strip_suffix('Z').unwrap_or(s);
+    let (date, time) = s.split_once(['T', ' ']).ok_or_else(invalid)?;
+
+    let fields = |value: &str, separator: char| {
+        value
+            .split(separator)
+            .map(|field| field.parse::<u64>().map_err(|_| invalid()))
+            .collect::<Result<Vec<_>, _>>()
+    };
+    let (year, month, day) = match *fields(date, '-')?.as_slice() {
+        [year, month, day] => (year, month, day),
+        _ => return Err(invalid()),
+    };
+    let (hour, minute, second) = match *fields(time, ':')?.as_slice() {
+        [hour, minute] => (hour, minute, 0),
+        [hour, minute, second] => (hour, minute, second),
+        _ => return Err(invalid()),
+    };
+
+    if !(1970..=MAX_YEAR).contains(&year) || !(1..=12).contains(&month) {
+        return Err(invalid());
+    }
+    if !(1..=days_in_month(year, month)).contains(&day) {
+        return Err(invalid());
+    }
+    if hour > 23 || minute > 59 || second > 59 {
+        return Err(invalid());
+    }
+
+    Ok(days_from_civil(year, month, day) * SECONDS_PER_DAY + hour * 3600 + minute * 60 + second)
+}
+
+/// Parses a time of day as `HH:MM` or `HH:MM:SS` into seconds since midnight, up to `24:00` for
+/// the end of the day
+pub fn parse_time_of_day(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let invalid = || format!("invalid time of day `{}`", s);
+    let fields = s
+        .split(':')
+        .map(|field| field.parse::<u64>().map_err(|_| invalid()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let (hour, minute, second) = match *fields.as_slice() {
+        [hour, minute] => (hour, minute, 0),
+        [hour, minute, second] => (hour, minute, second),
+        _ => return Err(invalid()),
+    };
+    if hour > 24 || minute > 59 || second > 59 {
+        return Err(invalid());
+    }
+
+    let seconds = hour * 3600 + minute * 60 + second;
+    match seconds <= SECONDS_PER_DAY {
+        true => Ok(seconds),
+        false => Err(invalid()),
+    }
+}
+
+/// Formats a timestamp as a UTC date & time, e.g. `2023-10-14T23:59:00Z`
+pub fn format_timestamp(timestamp: u64) -> String {
+    let (year, month, day) = civil_from_days(day(timestamp));
+    format!(
+        "{:04}-{:02}-{:02}T{}Z",
+        year,
+        month,
+        day,
+        format_time_of_day(timestamp)
+    )
+}
+
+/// Formats the time of day of a timestamp as `HH:MM:SS`
+pub fn format_time_of_day(timestamp: u64) -> String {
+    let seconds = time_of_day(timestamp);
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        (seconds / 60) % 60,
+        seconds % 60
+    )
+}
+
+/// Formats a timestamp as compactly as possible: just the time of day on the first day of the
+/// epoch (as with scenarios in seconds since midnight), otherwise the full date & time
+pub fn format_short(timestamp: u64) -> String {
+    match day(timestamp) {
+        0 => format_time_of_day(timestamp),
+        _ => format_timestamp(timestamp),
+    }
+}
+
+/// Returns the seconds since midnight of a timestamp
+pub fn time_of_day(timestamp: u64) -> u64 {
+    timestamp % SECONDS_PER_DAY
+}
+
+/// Returns the days since the epoch of a timestamp
+pub fn day(timestamp: u64) -> u64 {
+    timestamp / SECONDS_PER_DAY
+}
+
+/// Returns the timestamp of the midnight ending the day of a timestamp
+pub fn end_of_day(timestamp: u64) -> u64 {
+    (day(timestamp) + 1) * SECONDS_PER_DAY
+}
+
+/// Converts a timestamp to a protobuf `Timestamp`
+pub fn to_proto_timestamp(timestamp: u64) -> prost_types::Timestamp {
+    prost_types::Timestamp {
+        seconds: timestamp as i64,
+        nanos: 0,
+    }
+}
+
+/// Converts a protobuf `Timestamp` to a timestamp, truncated to the second.
+/// Returns `None` for times before the epoch, which can't be represented.
+pub fn from_proto_timestamp(mut timestamp: prost_types::Timestamp) -> Option<u64> {
+    timestamp.normalize();
+    u64::try_from(timestamp.seconds).ok()
+}
+
+/// Converts a number of seconds to a protobuf `Duration`
+pub fn to_proto_duration(seconds: u64) -> prost_types::Duration {
+    prost_types::Duration {
+        seconds: seconds as i64,
+        nanos: 0,
+    }
+}
+
+/// Converts a protobuf `Duration` to a number of seconds, truncated to the second.
+/// Returns `None` for negative durations.
+pub fn from_proto_duration(mut duration: prost_types::Duration) -> Option<u64> {
+    duration.normalize();
+    match duration.seconds {
+        0 if duration.nanos < 0 => None,
+        seconds => u64::try_from(seconds).ok(),
+    }
+}
+
+/// Number of days in a month of the proleptic Gregorian calendar, counting from 1 for January
+fn days_in_month(year: u64, month: u64) -> u64 {
+    let leap = year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400));
+    match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since the epoch of a date in the proleptic Gregorian calendar
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let (era, year_of_era) = (year / 400, year % 400);
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Date in the proleptic Gregorian calendar of a number of days since the epoch
+pub(crate) fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let days = days + 719_468;
+    let (era, day_of_era) = (days / 146_097, days % 146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = (shifted_month + 2) % 12 + 1;
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_timestamps() {
+        // Seconds since midnight are times on the first day
+        assert_eq!(parse_timestamp("25200"), Ok(25_200));
+        assert_eq!(format_short(25_200), "07:00:00");
+
+        let timestamp = parse_timestamp("2023-10-14T23:59:30Z").expect("timestamp");
+        assert_eq!(timestamp, 1_697_327_970);
+        assert_eq!(format_timestamp(timestamp), "2023-10-14T23:59:30Z");
+        assert_eq!(format_short(timestamp), "2023-10-14T23:59:30Z");
+        assert_eq!(parse_timestamp("2023-10-14 23:59"), Ok(timestamp - 30));
+
+        // Crossing midnight, including into a leap day
+        let midnight = end_of_day(parse_timestamp("2024-02-28T23:00:00Z").expect("timestamp"));
+        assert_eq!(format_timestamp(midnight), "2024-02-29T00:00:00Z");
+        assert_eq!(time_of_day(midnight), 0);
+
+        assert!(parse_timestamp("2023-13-01T00:00:00Z").is_err());
+        // Days past the end of the month don't roll over into the next
+        assert!(parse_timestamp("2023-02-31T00:00:00Z").is_err());
+        assert!(parse_timestamp("2023-04-31T00:00:00Z").is_err());
+        assert!(parse_timestamp("2023-02-29T00:00:00Z").is_err());
+        assert!(parse_timestamp("2100-02-29T00:00:00Z").is_err());
+        assert!(parse_timestamp("2000-02-29T00:00:00Z").is_ok());
+        assert!(parse_timestamp("2023-12-31T00:00:00Z").is_ok());
+        assert!(parse_timestamp("2023-06-00T00:00:00Z").is_err());
+        assert!(parse_timestamp("yesterday").is_err());
+        assert!(parse_timestamp("-60").is_err());
+        assert!(parse_timestamp("18446744073709551615-01-01T00:00:00Z").is_err());
+
+        assert_eq!(parse_time_of_day("10:30"), Ok(37_800));
+        assert_eq!(parse_time_of_day("24:00"), Ok(SECONDS_PER_DAY));
+        assert!(parse_time_of_day("24:01").is_err());
+        assert!(parse_time_of_day("10").is_err());
+    }
+
+    #[test]
+    fn test_proto_conversions() {
+        let timestamp = to_proto_timestamp(1_697_327_970);
+        assert_eq!(timestamp.to_string(), "2023-10-14T23:59:30Z");
+        assert_eq!(from_proto_timestamp(timestamp), Some(1_697_327_970));
+
+        // Fractions of a second are truncated, & times before the epoch are rejected
+        let timestamp = |seconds, nanos| prost_types::Timestamp { seconds, nanos };
+        assert_eq!(from_proto_timestamp(timestamp(60, 999_999_999)), Some(60));
+        assert_eq!(from_proto_timestamp(timestamp(0, -1)), None);
+
+        assert_eq!(from_proto_duration(to_proto_duration(1_800)), Some(1_800));
+        let duration = |seconds, nanos| prost_types::Duration { seconds, nanos };
+        assert_eq!(from_proto_duration(duration(1, 500_000_000)), Some(1));
+        assert_eq!(from_proto_duration(duration(0, -500_000_000)), None);
+    }
+}
@@ -0,0 +1,175 @@
+//! Formatting of distances, speeds & times for display, following a `Units` setting
+
+use crate::time;
+
+const METERS_PER_MILE: f64 = 1_609.344;
+
+/// System of units quantities are displayed in
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Units {
+    /// Meters & kilometers, with a 24-hour clock
+    #[default]
+    Metric,
+    /// Miles, with a 12-hour clock
+    Imperial,
+}
+
+impl Units {
+    /// Meters in the unit distances are displayed in, so that round distances can be chosen in it
+    pub fn distance_unit_m(&self) -> f64 {
+        match self {
+            Self::Metric => 1.0,
+            Self::Imperial => METERS_PER_MILE,
+        }
+    }
+
+    /// Formats a distance in meters, e.g. `750 m`, `12.5 km` or `7.77 mi`
+    pub fn format_distance(&self, meters: f64) -> String {
+        match self {
+            Self::Metric if meters.abs() < 1_000.0 => format!("{:.0} m", meters),
+            Self::Metric => format!("{} km", trimmed(meters / 1_000.0, 1)),
+            Self::Imperial => {
+                let miles = meters / METERS_PER_MILE;
+                let decimals = if miles.abs() < 10.0 { 2 } else { 1 };
+                format!("{} mi", trimmed(miles, decimals))
+            }
+        }
+    }
+
+    /// Formats a speed in meters per second, e.g. `108 km/h` or `67 mph`
+    pub fn format_speed(&self, mps: f64) -> String {
+        match self {
+            Self::Metric => format!("{:.0} km/h", mps * 3.6),
+            Self::Imperial => format!("{:.0} mph", mps * 3_600.0 / METERS_PER_MILE),
+        }
+    }
+
+    /// Formats a timestamp as compactly as possible, like `time::format_short`: just the time of
+    /// day on the first day of the epoch, otherwise with the date as well
+    pub fn format_time(&self, timestamp: u64) -> String {
+        match self {
+            Self::Metric => time::format_short(timestamp),
+            Self::Imperial => {
+                let seconds = time::time_of_day(timestamp);
+                let (hour, minute, second) = (seconds / 3600, (seconds / 60) % 60, seconds % 60);
+                let clock = format!(
+                    "{}:{:02}:{:02} {}",
+                    (hour + 11) % 12 + 1,
+                    minute,
+                    second,
+                    if hour < 12 { "AM" } else { "PM" }
+                );
+                match time::day(timestamp) {
+                    0 => clock,
+                    days => {
+                        let (year, month, day) = time::civil_from_days(days);
+                        format!("{:02}/{:02}/{:04} {}", month, day, year, clock)
+                    }
+                }
+            }
+        }
+    }
+
+    /// Formats the exclusive end of a period like `format_time`, except that a midnight ends the
+    /// day before it, e.g. as `24:00:00` rather than the next day's `00:00:00`, so that a period
+    /// within a single day is given without dates
+    pub fn format_end_time(&self, timestamp: u64) -> String {
+        if timestamp == 0 || time::time_of_day(timestamp) != 0 {
+            return self.format_time(timestamp);
+        }
+        let (last_second, midnight) = match self {
+            Self::Metric => ("23:59:59", "24:00:00"),
+            Self::Imperial => ("11:59:59 PM", "12:00:00 midnight"),
+        };
+        self.format_time(timestamp - 1)
+            .replace(last_second, midnight)
+    }
+}
+
+/// Formats a duration in seconds with its two most significant units, e.g. `1h 05m` or `45s`
+pub fn format_duration(seconds: u64) -> String {
+    match (seconds / 3600, (seconds / 60) % 60, seconds % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m {:02}s", m, s),
+        (h, m, _) => format!("{}h {:02}m", h, m),
+    }
+}
+
+impl std::str::FromStr for Units {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "metric" => Ok(Self::Metric),
+            "imperial" => Ok(Self::Imperial),
+            _ => Err(format!(
+                "unknown units `{}`, expected `metric` or `imperial`",
+                s
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for Units {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Metric => f.write_str("metric"),
+            Self::Imperial => f.write_str("imperial"),
+        }
+    }
+}
+
+/// Formats a number with at most the provided number of decimal places, without trailing zeros
+fn trimmed(value: f64, decimals: usize) -> String {
+    let formatted = format!("{:.*}", decimals, value);
+    match formatted.contains('.') {
+        true => formatted
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string(),
+        false => formatted,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        assert_eq!(Units::Metric.format_distance(0.0), "0 m");
+        assert_eq!(Units::Metric.format_distance(750.0), "750 m");
+        assert_eq!(Units::Metric.format_distance(2_000.0), "2 km");
+        assert_eq!(Units::Metric.format_distance(-12_500.0), "-12.5 km");
+        assert_eq!(Units::Imperial.format_distance(12_500.0), "7.77 mi");
+        assert_eq!(
+            Units::Imperial.format_distance(METERS_PER_MILE * 20.0),
+            "20 mi"
+        );
+
+        assert_eq!(Units::Metric.format_speed(30.0), "108 km/h");
+        assert_eq!(Units::Imperial.format_speed(30.0), "67 mph");
+
+        assert_eq!(Units::Metric.format_time(68_700), "19:05:00");
+        assert_eq!(Units::Imperial.format_time(68_700), "7:05:00 PM");
+        assert_eq!(Units::Imperial.format_time(1_800), "12:30:00 AM");
+        assert_eq!(
+            Units::Imperial.format_time(1_697_327_940),
+            "10/14/2023 11:59:00 PM"
+        );
+        assert_eq!(Units::Metric.format_end_time(86_400), "24:00:00");
+        assert_eq!(Units::Imperial.format_end_time(86_400), "12:00:00 midnight");
+        assert_eq!(
+            Units::Metric.format_end_time(1_697_328_000),
+            "2023-10-14T24:00:00Z"
+        );
+        assert_eq!(Units::Metric.format_end_time(68_700), "19:05:00");
+
+        assert_eq!(format_duration(45), "45s");
+        assert_eq!(format_duration(330), "5m 30s");
+        assert_eq!(format_duration(3_900), "1h 05m");
+
+        assert_eq!("imperial".parse(), Ok(Units::Imperial));
+        assert!("furlongs".parse::<Units>().is_err());
+    }
+}
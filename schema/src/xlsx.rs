@@ -0,0 +1,793 @@
+//! Reads the rows of Excel workbooks (.xlsx), so that orders & destinations can be loaded from the
+//! spreadsheets operations teams keep them in. Each row of the first worksheet is joined into a
+//! record of `, `-separated values & parsed as a line of the equivalent CSV would be, so the same
+//! columns are expected in the same order & validated in the same way.
+//!
+//! A workbook is a zip archive of XML parts: the worksheet's cells either hold their values or
+//! index into a table of shared strings, & number cells formatted as dates are days since 1900.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::Read;
+
+use flate2::read::DeflateDecoder;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use crate::csv::LoadProgress;
+use crate::time;
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+const CENTRAL_DIRECTORY_HEADER: u32 = 0x0201_4b50;
+const LOCAL_FILE_HEADER: u32 = 0x0403_4b50;
+/// Sizes & offsets past which archives need the zip64 extensions, which aren't supported
+const ZIP64_MARKER: u32 = u32::MAX;
+
+/// Day numbers of 1970-01-01 in workbooks counting days since 1900 & since 1904
+const UNIX_EPOCH_1900: f64 = 25_569.0;
+const UNIX_EPOCH_1904: f64 = 24_107.0;
+
+/// Parses each non-empty row of the first worksheet of a workbook into a record, reporting
+/// progress as `csv::parse_lines` does by the bytes of the worksheet parsed. A first row naming
+/// the columns is skipped, i.e. one holding only text which doesn't parse as a record.
+pub(crate) async fn parse_rows<T>(
+    path: &str,
+    parse: impl FnMut(&str) -> Result<T, Error>,
+    on_progress: impl FnMut(LoadProgress),
+) -> Result<Vec<T>, Error> {
+    parse_workbook(tokio::fs::read(path).await?, parse, on_progress)
+}
+
+fn parse_workbook<T>(
+    bytes: Vec<u8>,
+    mut parse: impl FnMut(&str) -> Result<T, Error>,
+    mut on_progress: impl FnMut(LoadProgress),
+) -> Result<Vec<T>, Error> {
+    let workbook = Workbook::new(bytes)?;
+    let mut progress = LoadProgress {
+        total_bytes: workbook.sheet.len() as u64,
+        ..Default::default()
+    };
+
+    let mut records = vec![];
+    let mut reported_percent = 0;
+    let mut is_first = true;
+    let mut rows = workbook.rows();
+    while let Some((row_number, cells)) = rows.next_row()? {
+        progress.bytes_read = rows.position() as u64;
+        let record = cells
+            .iter()
+            .map(|cell| cell.value.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        if record.is_empty() {
+            continue;
+        }
+
+        let is_header = std::mem::take(&mut is_first) && cells.iter().all(|cell| cell.is_text);
+        match parse(&record) {
+            Ok(record) => records.push(record),
+            Err(_) if is_header => continue,
+            Err(e) => return Err(format!("row {}: {}", row_number, e).into()),
+        }
+        progress.records = records.len();
+
+        let percent = progress.percent() / 10 * 10;
+        if percent > reported_percent && percent < 100 {
+            reported_percent = percent;
+            on_progress(progress);
+        }
+    }
+
+    progress.bytes_read = progress.total_bytes;
+    on_progress(progress);
+
+    Ok(records)
+}
+
+struct Workbook {
+    /// XML of the first worksheet
+    sheet: Vec<u8>,
+    shared_strings: Vec<String>,
+    /// Whether each of the workbook's cell formats displays numbers as dates
+    date_formats: Vec<bool>,
+    /// Day number of 1970-01-01, depending on whether the workbook counts from 1900 or 1904
+    unix_epoch: f64,
+}
+
+impl Workbook {
+    fn new(bytes: Vec<u8>) -> Result<Self, Error> {
+        let archive = Archive::new(bytes)?;
+        let workbook = archive
+            .read("xl/workbook.xml")?
+            .ok_or("not an Excel workbook, it has no xl/workbook.xml")?;
+
+        let (sheet_id, is_1904) = first_sheet(&workbook)?;
+        let relationships = archive
+            .read("xl/_rels/workbook.xml.rels")?
+            .ok_or("workbook has no xl/_rels/workbook.xml.rels")?;
+        let target = relationship_target(&relationships, &sheet_id)?;
+        let sheet_path = match target.strip_prefix('/') {
+            Some(absolute) => absolute.to_string(),
+            None => format!("xl/{}", target),
+        };
+
+        Ok(Self {
+            sheet: archive
+                .read(&sheet_path)?
+                .ok_or_else(|| format!("workbook has no worksheet at {}", sheet_path))?,
+            shared_strings: match archive.read("xl/sharedStrings.xml")? {
+                Some(xml) => shared_strings(&xml)?,
+                None => vec![],
+            },
+            date_formats: match archive.read("xl/styles.xml")? {
+                Some(xml) => date_formats(&xml)?,
+                None => vec![],
+            },
+            unix_epoch: match is_1904 {
+                true => UNIX_EPOCH_1904,
+                false => UNIX_EPOCH_1900,
+            },
+        })
+    }
+
+    fn rows(&self) -> Rows<'_> {
+        Rows {
+            workbook: self,
+            reader: Reader::from_reader(self.sheet.as_slice()),
+            row_number: 0,
+        }
+    }
+
+    /// Formats the value of a cell as it would be written in a CSV
+    fn value(&self, kind: &str, style: usize, raw: &str) -> Result<String, String> {
+        match kind {
+            "s" => raw
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| self.shared_strings.get(i).cloned())
+                .ok_or_else(|| format!("invalid shared string `{}`", raw)),
+            "b" => Ok((raw == "1").to_string()),
+            "e" => Err(format!("cell holds the error `{}`", raw)),
+            // Strings, including those from formulas & already formatted dates
+            "str" | "inlineStr" | "d" => Ok(raw.to_string()),
+            _ => {
+                let number = raw
+                    .parse::<f64>()
+                    .map_err(|_| format!("invalid number `{}`", raw))?;
+                if self.date_formats.get(style).copied().unwrap_or_default() {
+                    return self.timestamp(number, raw);
+                }
+                Ok(
+                    match number.fract() == 0.0 && number.abs() < 2f64.powi(53) {
+                        true => (number as i64).to_string(),
+                        false => raw.to_string(),
+                    },
+                )
+            }
+        }
+    }
+
+    /// Formats a number of days as a timestamp. Times without a date are kept as seconds since
+    /// midnight, as in scenarios which only span a single day.
+    fn timestamp(&self, days: f64, raw: &str) -> Result<String, String> {
+        if (0.0..1.0).contains(&days) {
+            return Ok(((days * time::SECONDS_PER_DAY as f64).round() as u64).to_string());
+        }
+        let seconds = ((days - self.unix_epoch) * time::SECONDS_PER_DAY as f64).round();
+        if seconds < 0.0 {
+            return Err(format!("date `{}` is before 1970", raw));
+        }
+        Ok(time::format_timestamp(seconds as u64))
+    }
+}
+
+struct Cell {
+    value: String,
+    /// Whether the cell holds a string rather than e.g. a number
+    is_text: bool,
+}
+
+/// Rows of a worksheet, read one at a time
+struct Rows<'a> {
+    workbook: &'a Workbook,
+    reader: Reader<&'a [u8]>,
+    row_number: usize,
+}
+
+impl Rows<'_> {
+    fn position(&self) -> usize {
+        self.reader.buffer_position()
+    }
+
+    /// Reads the next row along with its number, leaving the values of any cells it skips empty &
+    /// trimming its trailing empty cells
+    fn next_row(&mut self) -> Result<Option<(usize, Vec<Cell>)>, Error> {
+        loop {
+            match self.reader.read_event()? {
+                Event::Start(row) if row.local_name().as_ref() == b"row" => {
+                    self.row_number = row_number(&row)?.unwrap_or(self.row_number + 1);
+                    return Ok(Some((self.row_number, self.cells()?)));
+                }
+                Event::Empty(row) if row.local_name().as_ref() == b"row" => {
+                    self.row_number = row_number(&row)?.unwrap_or(self.row_number + 1);
+                }
+                Event::Eof => return Ok(None),
+                _ => {}
+            }
+        }
+    }
+
+    fn cells(&mut self) -> Result<Vec<Cell>, Error> {
+        let mut cells: Vec<Cell> = vec![];
+        // Cell being read: its reference, kind & style, & the text read from it so far
+        let mut cell: Option<(Option<String>, String, usize, String)> = None;
+        let mut in_value = false;
+        loop {
+            match self.reader.read_event()? {
+                Event::Start(start) => match start.local_name().as_ref() {
+                    b"c" => cell = Some(cell_attributes(&start)?),
+                    // The values of inline strings are in `t` elements
+                    b"v" | b"t" => in_value = true,
+                    // Formulas leave their result in the cell's value
+                    b"f" => {
+                        self.reader.read_to_end(start.name())?;
+                    }
+                    _ => {}
+                },
+                Event::Empty(start) if start.local_name().as_ref() == b"c" => {
+                    let (reference, ..) = cell_attributes(&start)?;
+                    place(&mut cells, reference.as_deref(), String::new(), false)?;
+                }
+                Event::Text(text) if in_value => {
+                    if let Some((.., raw)) = &mut cell {
+                        raw.push_str(&text.unescape()?);
+                    }
+                }
+                Event::End(end) if matches!(end.local_name().as_ref(), b"v" | b"t") => {
+                    in_value = false;
+                }
+                Event::End(end) if end.local_name().as_ref() == b"c" => {
+                    let Some((reference, kind, style, raw)) = cell.take() else {
+                        continue;
+                    };
+                    let at = || reference.as_deref().unwrap_or("?").to_string();
+                    let value = self
+                        .workbook
+                        .value(&kind, style, &raw)
+                        .map_err(|e| format!("row {}, cell {}: {}", self.row_number, at(), e))?;
+                    let is_text = matches!(kind.as_str(), "s" | "str" | "inlineStr");
+                    place(&mut cells, reference.as_deref(), value, is_text)?;
+                }
+                Event::End(end) if end.local_name().as_ref() == b"row" => break,
+                Event::Eof => return Err("worksheet ends mid-row".into()),
+                _ => {}
+            }
+        }
+
+        while cells.last().is_some_and(|cell| cell.value.is_empty()) {
+            cells.pop();
+        }
+        Ok(cells)
+    }
+}
+
+/// Places a cell at the column of its reference, or after the previous cell if it has none
+fn place(
+    cells: &mut Vec<Cell>,
+    reference: Option<&str>,
+    value: String,
+    is_text: bool,
+) -> Result<(), Error> {
+    let column = match reference {
+        Some(reference) => column_index(reference)?,
+        None => cells.len(),
+    };
+    while cells.len() < column {
+        cells.push(Cell {
+            value: String::new(),
+            is_text: false,
+        });
+    }
+    let cell = Cell {
+        value: value.trim().to_string(),
+        is_text,
+    };
+    match cells.get_mut(column) {
+        Some(existing) => *existing = cell,
+        None => cells.push(cell),
+    }
+    Ok(())
+}
+
+/// Zero-based column of a cell reference like `C12`
+fn column_index(reference: &str) -> Result<usize, Error> {
+    let letters = reference
+        .bytes()
+        .take_while(u8::is_ascii_uppercase)
+        .collect::<Vec<_>>();
+    // Excel stops at column XFD, the 16,384th
+    if letters.is_empty() || letters.len() > 3 {
+        return Err(format!("invalid cell reference `{}`", reference).into());
+    }
+    let column = letters.iter().fold(0, |column, letter| {
+        column * 26 + (letter - b'A') as usize + 1
+    });
+    Ok(column - 1)
+}
+
+fn attribute<'a>(element: &'a BytesStart, name: &str) -> Result<Option<Cow<'a, str>>, Error> {
+    for attribute in element.attributes() {
+        let attribute = attribute?;
+        if attribute.key.local_name().as_ref() == name.as_bytes() {
+            return Ok(Some(attribute.unescape_value()?));
+        }
+    }
+    Ok(None)
+}
+
+fn row_number(row: &BytesStart) -> Result<Option<usize>, Error> {
+    attribute(row, "r")?
+        .map(|r| {
+            r.parse()
+                .map_err(|_| format!("invalid row number `{}`", r).into())
+        })
+        .transpose()
+}
+
+fn cell_attributes(cell: &BytesStart) -> Result<(Option<String>, String, usize, String), Error> {
+    let style = match attribute(cell, "s")? {
+        Some(s) => s
+            .parse()
+            .map_err(|_| format!("invalid cell style `{}`", s))?,
+        None => 0,
+    };
+    Ok((
+        attribute(cell, "r")?.map(Cow::into_owned),
+        attribute(cell, "t")?.map_or_else(|| "n".to_string(), Cow::into_owned),
+        style,
+        String::new(),
+    ))
+}
+
+/// Relationship id of the first worksheet in a workbook, & whether it counts days from 1904
+fn first_sheet(xml: &[u8]) -> Result<(String, bool), Error> {
+    let mut reader = Reader::from_reader(xml);
+    let mut is_1904 = false;
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) | Event::Empty(element) => match element.local_name().as_ref() {
+                b"workbookPr" => {
+                    is_1904 = attribute(&element, "date1904")?
+                        .is_some_and(|value| value == "1" || value == "true");
+                }
+                b"sheet" => {
+                    let id = attribute(&element, "id")?.ok_or("worksheet has no relationship")?;
+                    return Ok((id.into_owned(), is_1904));
+                }
+                _ => {}
+            },
+            Event::Eof => return Err("workbook has no worksheets".into()),
+            _ => {}
+        }
+    }
+}
+
+fn relationship_target(xml: &[u8], id: &str) -> Result<String, Error> {
+    let mut reader = Reader::from_reader(xml);
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) | Event::Empty(element)
+                if element.local_name().as_ref() == b"Relationship"
+                    && attribute(&element, "Id")?.is_some_and(|value| value == id) =>
+            {
+                let target = attribute(&element, "Target")?
+                    .ok_or_else(|| format!("relationship {} has no target", id))?;
+                return Ok(target.into_owned());
+            }
+            Event::Eof => return Err(format!("workbook has no relationship {}", id).into()),
+            _ => {}
+        }
+    }
+}
+
+/// Strings shared between cells, skipping the phonetic guides of East Asian text
+fn shared_strings(xml: &[u8]) -> Result<Vec<String>, Error> {
+    let mut reader = Reader::from_reader(xml);
+    let mut strings = vec![];
+    let mut string: Option<String> = None;
+    let mut in_text = false;
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) => match element.local_name().as_ref() {
+                b"si" => string = Some(String::new()),
+                b"t" => in_text = true,
+                b"rPh" => {
+                    reader.read_to_end(element.name())?;
+                }
+                _ => {}
+            },
+            Event::Text(text) if in_text => {
+                if let Some(string) = &mut string {
+                    string.push_str(&text.unescape()?);
+                }
+            }
+            Event::End(element) if element.local_name().as_ref() == b"t" => in_text = false,
+            Event::End(element) if element.local_name().as_ref() == b"si" => {
+                strings.extend(string.take());
+            }
+            Event::Empty(element) if element.local_name().as_ref() == b"si" => {
+                strings.push(String::new());
+            }
+            Event::Eof => return Ok(strings),
+            _ => {}
+        }
+    }
+}
+
+/// Whether each cell format of a workbook displays numbers as dates, by index
+fn date_formats(xml: &[u8]) -> Result<Vec<bool>, Error> {
+    let mut reader = Reader::from_reader(xml);
+    let mut custom = HashMap::<u32, bool>::new();
+    let mut formats = vec![];
+    let mut in_cell_formats = false;
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) | Event::Empty(element) => match element.local_name().as_ref() {
+                b"numFmt" => {
+                    let id = attribute(&element, "numFmtId")?.and_then(|id| id.parse().ok());
+                    let code = attribute(&element, "formatCode")?;
+                    if let (Some(id), Some(code)) = (id, code) {
+                        custom.insert(id, is_date_format(&code));
+                    }
+                }
+                b"cellXfs" => in_cell_formats = true,
+                b"xf" if in_cell_formats => {
+                    let id = attribute(&element, "numFmtId")?
+                        .and_then(|id| id.parse::<u32>().ok())
+                        .unwrap_or_default();
+                    formats.push(match custom.get(&id) {
+                        Some(is_date) => *is_date,
+                        // Excel's built in date & time formats
+                        None => matches!(id, 14..=22 | 45..=47),
+                    });
+                }
+                _ => {}
+            },
+            Event::End(element) if element.local_name().as_ref() == b"cellXfs" => {
+                in_cell_formats = false;
+            }
+            Event::Eof => return Ok(formats),
+            _ => {}
+        }
+    }
+}
+
+/// Whether a custom number format displays dates or times, i.e. has any of their fields outside
+/// of quoted literals, escaped characters & bracketed colors or conditions
+fn is_date_format(code: &str) -> bool {
+    let mut chars = code.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                chars.by_ref().find(|&c| c == '"');
+            }
+            '\\' | '_' | '*' => {
+                chars.next();
+            }
+            '[' => {
+                // Elapsed hours, minutes or seconds, e.g. `[h]:mm`
+                let bracketed = chars.by_ref().take_while(|&c| c != ']').collect::<String>();
+                if bracketed.chars().all(|c| "hHmMsS".contains(c)) && !bracketed.is_empty() {
+                    return true;
+                }
+            }
+            'y' | 'Y' | 'd' | 'D' | 'h' | 'H' | 's' | 'S' => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Stored & deflated files of a zip archive
+struct Archive {
+    bytes: Vec<u8>,
+    entries: HashMap<String, Entry>,
+}
+
+struct Entry {
+    method: u16,
+    /// Offset of the entry's local file header
+    offset: usize,
+    compressed_size: usize,
+    size: usize,
+}
+
+impl Archive {
+    fn new(bytes: Vec<u8>) -> Result<Self, Error> {
+        let invalid = || "not an Excel workbook, it isn't a valid zip archive";
+        // The record ending the central directory is followed by a comment of up to 64KiB
+        let end = (0..bytes.len().saturating_sub(21))
+            .rev()
+            .take(u16::MAX as usize + 1)
+            .find(|&i| u32_at(&bytes, i) == Some(END_OF_CENTRAL_DIRECTORY))
+            .ok_or_else(invalid)?;
+        let count = u16_at(&bytes, end + 10).ok_or_else(invalid)?;
+        let mut offset = u32_at(&bytes, end + 16).ok_or_else(invalid)?;
+        if offset == ZIP64_MARKER {
+            return Err("zip64 workbooks aren't supported".into());
+        }
+
+        let mut entries = HashMap::new();
+        for _ in 0..count {
+            let at = offset as usize;
+            if u32_at(&bytes, at) != Some(CENTRAL_DIRECTORY_HEADER) {
+                return Err(invalid().into());
+            }
+            let field = |i: usize| u32_at(&bytes, at + i).ok_or_else(invalid);
+            let short = |i: usize| u16_at(&bytes, at + i).ok_or_else(invalid);
+            let (compressed_size, size, local) = (field(20)?, field(24)?, field(42)?);
+            if [compressed_size, size, local].contains(&ZIP64_MARKER) {
+                return Err("zip64 workbooks aren't supported".into());
+            }
+            let (name_len, extra_len, comment_len) = (short(28)?, short(30)?, short(32)?);
+            let name = bytes
+                .get(at + 46..at + 46 + name_len as usize)
+                .ok_or_else(invalid)?;
+            entries.insert(
+                String::from_utf8_lossy(name).into_owned(),
+                Entry {
+                    method: short(10)?,
+                    offset: local as usize,
+                    compressed_size: compressed_size as usize,
+                    size: size as usize,
+                },
+            );
+            offset = offset
+                .checked_add(46 + name_len as u32 + extra_len as u32 + comment_len as u32)
+                .ok_or_else(invalid)?;
+        }
+
+        Ok(Self { bytes, entries })
+    }
+
+    /// Reads the contents of a file in the archive, if it has one of that name
+    fn read(&self, name: &str) -> Result<Option<Vec<u8>>, Error> {
+        let Some(entry) = self.entries.get(name) else {
+            return Ok(None);
+        };
+        let invalid = || format!("{} is corrupt", name);
+        let at = entry.offset;
+        if u32_at(&self.bytes, at) != Some(LOCAL_FILE_HEADER) {
+            return Err(invalid().into());
+        }
+        let name_len = u16_at(&self.bytes, at + 26).ok_or_else(invalid)? as usize;
+        let extra_len = u16_at(&self.bytes, at + 28).ok_or_else(invalid)? as usize;
+        let start = at + 30 + name_len + extra_len;
+        let end = start
+            .checked_add(entry.compressed_size)
+            .ok_or_else(invalid)?;
+        let data = self.bytes.get(start..end).ok_or_else(invalid)?;
+
+        // The buffer grows as the file is read, rather than trusting the size the archive claims
+        let mut contents = Vec::with_capacity(entry.size.min(data.len()));
+        match entry.method {
+            0 => contents.extend_from_slice(data),
+            // Read no further than the size the archive claims, so a corrupt file can't balloon
+            8 => {
+                DeflateDecoder::new(data)
+                    .take(entry.size as u64)
+                    .read_to_end(&mut contents)?;
+            }
+            method => {
+                return Err(format!("{} uses unsupported compression {}", name, method).into())
+            }
+        }
+        Ok(Some(contents))
+    }
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+
+    use flate2::write::DeflateEncoder;
+    use flate2::Compression;
+
+    use super::*;
+    use crate::{Destination, DestinationId, Order, Priority};
+
+    /// Interns the names of the destinations in workbooks of orders, as a scenario's are
+    fn intern(name: &str) -> Result<DestinationId, String> {
+        Ok(DestinationId::intern(name))
+    }
+
+    /// Zips files into an archive, deflating all but the first
+    fn zip(files: &[(&str, &str)]) -> Vec<u8> {
+        let (mut archive, mut directory) = (vec![], vec![]);
+        for (i, (name, contents)) in files.iter().enumerate() {
+            let (method, data) = match i {
+                0 => (0u16, contents.as_bytes().to_vec()),
+                _ => {
+                    let mut encoder = DeflateEncoder::new(vec![], Compression::default());
+                    encoder.write_all(contents.as_bytes()).expect("deflate");
+                    (8, encoder.finish().expect("deflate"))
+                }
+            };
+            let offset = archive.len() as u32;
+            // Checksums are left zeroed, as they aren't checked
+            let header = |signature: u32, central: bool| {
+                let mut header = signature.to_le_bytes().to_vec();
+                if central {
+                    header.extend(20u16.to_le_bytes());
+                }
+                header.extend([20, 0, 0, 0]);
+                header.extend(method.to_le_bytes());
+                header.extend([0; 8]);
+                header.extend((data.len() as u32).to_le_bytes());
+                header.extend((contents.len() as u32).to_le_bytes());
+                header.extend((name.len() as u16).to_le_bytes());
+                header.extend([0, 0]);
+                if central {
+                    header.extend([0; 10]);
+                    header.extend(offset.to_le_bytes());
+                }
+                header.extend(name.as_bytes());
+                header
+            };
+            archive.extend(header(LOCAL_FILE_HEADER, false));
+            archive.extend(&data);
+            directory.extend(header(CENTRAL_DIRECTORY_HEADER, true));
+        }
+
+        let directory_offset = archive.len() as u32;
+        archive.extend(&directory);
+        archive.extend(END_OF_CENTRAL_DIRECTORY.to_le_bytes());
+        archive.extend([0; 4]);
+        archive.extend([(files.len() as u16).to_le_bytes(); 2].concat());
+        archive.extend((directory.len() as u32).to_le_bytes());
+        archive.extend(directory_offset.to_le_bytes());
+        archive.extend([0, 0]);
+        archive
+    }
+
+    fn workbook(sheet_data: &str) -> Vec<u8> {
+        let sheet = format!(
+            r#"<?xml version="1.0"?><worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData>{}</sheetData></worksheet>"#,
+            sheet_data
+        );
+        zip(&[
+            (
+                "xl/workbook.xml",
+                r#"<workbook xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets><sheet name="Orders" sheetId="1" r:id="rId3"/></sheets></workbook>"#,
+            ),
+            (
+                "xl/_rels/workbook.xml.rels",
+                r#"<Relationships><Relationship Id="rId1" Target="styles.xml"/><Relationship Id="rId3" Target="worksheets/sheet1.xml"/></Relationships>"#,
+            ),
+            (
+                "xl/sharedStrings.xml",
+                r#"<sst><si><t>time</t></si><si><t>destination</t></si><si><t>priority</t></si><si><t>Emergency</t></si><si><r><t>Res</t></r><r><t>upply</t></r></si><si><t>Alpha &amp; Omega</t></si></sst>"#,
+            ),
+            (
+                "xl/styles.xml",
+                r#"<styleSheet><numFmts><numFmt numFmtId="164" formatCode="yyyy\-mm\-dd hh:mm"/><numFmt numFmtId="165" formatCode="0.0&quot;d&quot;"/></numFmts><cellStyleXfs><xf numFmtId="14"/></cellStyleXfs><cellXfs><xf numFmtId="0"/><xf numFmtId="164"/><xf numFmtId="165"/><xf numFmtId="20"/></cellXfs></styleSheet>"#,
+            ),
+            ("xl/worksheets/sheet1.xml", &sheet),
+        ])
+    }
+
+    #[test]
+    fn test_orders() -> Result<(), Error> {
+        let bytes = workbook(concat!(
+            // Header naming the columns, then orders timed in seconds, by date & by time of day
+            r#"<row r="1"><c r="A1" t="s"><v>0</v></c><c r="B1" t="s"><v>1</v></c><c r="C1" t="s"><v>2</v></c></row>"#,
+            r#"<row r="2"><c r="A2"><v>25200</v></c><c r="B2" t="s"><v>5</v></c><c r="C2" t="s"><v>3</v></c></row>"#,
+            r#"<row r="4"><c r="A4" s="1"><v>45213.25</v></c><c r="B4" t="inlineStr"><is><t>Beta</t></is></c><c r="C4" t="s"><v>4</v></c><c r="D4"/></row>"#,
+            // Delivery window left empty before the shelf life, which a formula gives
+            r#"<row r="5"><c r="A5" s="3"><v>0.3125</v></c><c r="B5" t="str"><v>Beta</v></c><c r="C5" t="s"><v>4</v></c><c r="F5"><f>60+30</f><v>90</v></c></row>"#,
+            r#"<row r="6"/>"#,
+        ));
+        let orders = parse_workbook(bytes, |line| Ok(Order::parse_with(line, intern)?), |_| {})?;
+
+        let order = |time, destination, priority, shelf_life| Order {
+            time,
+            destination: DestinationId::from(destination),
+            priority,
+            shelf_life,
+            ..Default::default()
+        };
+        assert_eq!(
+            orders,
+            [
+                order(25_200, "Alpha & Omega", Priority::Emergency, None),
+                order(1_697_263_200, "Beta", Priority::Resupply, None),
+                order(27_000, "Beta", Priority::Resupply, Some(5_400)),
+            ]
+        );
+
+        // Rows are validated as the lines of a CSV would be
+        let bytes = workbook(concat!(
+            r#"<row r="1"><c r="A1"><v>25200</v></c><c r="B1" t="s"><v>5</v></c><c r="C1" t="s"><v>3</v></c></row>"#,
+            r#"<row r="2"><c r="A2"><v>25260</v></c><c r="B2" t="s"><v>5</v></c><c r="C2" t="s"><v>1</v></c></row>"#,
+        ));
+        let e = parse_workbook(bytes, |line| Ok(Order::parse_with(line, intern)?), |_| {})
+            .expect_err("invalid priority");
+        assert!(e.to_string().starts_with("row 2: "), "{}", e);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_destinations() -> Result<(), Error> {
+        let bytes = workbook(concat!(
+            r#"<row><c t="inlineStr"><is><t>North</t></is></c><c><v>3000</v></c><c><v>-250</v></c></row>"#,
+            r#"<row><c t="inlineStr"><is><t> East </t></is></c><c><v>0</v></c><c><v>4000</v></c><c t="inlineStr"><is><t>Coast</t></is></c></row>"#,
+        ));
+        let destinations = parse_workbook(bytes, |line| Ok(line.parse::<Destination>()?), |_| {})?;
+
+        assert_eq!(destinations.len(), 2);
+        assert_eq!(
+            (destinations[0].north_m, destinations[0].east_m),
+            (3_000, -250)
+        );
+        assert_eq!(destinations[1].name, DestinationId::from("East"));
+        assert_eq!(destinations[1].region.as_deref(), Some("Coast"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_corrupt_archives() {
+        let bytes = workbook("");
+        let end = bytes.len() - 22;
+        let patched = |at: usize, value: &[u8]| {
+            let mut bytes = bytes.clone();
+            bytes[at..at + value.len()].copy_from_slice(value);
+            bytes
+        };
+
+        // Central directories holding fewer entries than claimed, or past the end of the file
+        let truncated = [
+            patched(end + 10, &9u16.to_le_bytes()),
+            patched(end + 16, &(bytes.len() as u32).to_le_bytes()),
+            patched(end + 16, &(ZIP64_MARKER - 1).to_le_bytes()),
+        ];
+        for bytes in truncated {
+            assert!(Archive::new(bytes).is_err());
+        }
+
+        // A file claiming to be far larger than it is reads as what's actually there
+        let directory = u32_at(&bytes, end + 16).expect("directory") as usize;
+        let oversized = patched(directory + 24, &(ZIP64_MARKER - 1).to_le_bytes());
+        let archive = Archive::new(oversized).expect("archive");
+        let contents = archive
+            .read("xl/workbook.xml")
+            .expect("read")
+            .expect("file");
+        assert!(contents.starts_with(b"<workbook"));
+        assert!(archive.read("xl/missing.xml").expect("read").is_none());
+    }
+
+    #[test]
+    fn test_formats() {
+        assert!(is_date_format("yyyy\\-mm\\-dd hh:mm"));
+        assert!(is_date_format("[h]:mm"));
+        assert!(!is_date_format("0.0\"d\""));
+        assert!(!is_date_format("[Red]0.00"));
+        assert!(!is_date_format("General"));
+        assert_eq!(column_index("A1").expect("column"), 0);
+        assert_eq!(column_index("AB7").expect("column"), 27);
+        assert!(column_index("12").is_err());
+    }
+}
@@ -0,0 +1,303 @@
+//! Golden-file tests for the wire encoding of `StatusUpdate`s.
+//!
+//! Each snapshot holds the protobuf bytes of a representative update alongside a JSON rendering of
+//! the decoded message, so that accidental breaking changes to the proto (renumbered fields,
+//! changed types or signs) fail with a readable diff. After an intentional change to the proto,
+//! regenerate the snapshots with `UPDATE_SNAPSHOTS=1 cargo test -p schema --test snapshots`.
+
+use std::path::PathBuf;
+
+use prost::Message;
+use schema::json::Value;
+use schema::proto::server;
+use schema::testkit;
+use schema::{
+    Backlog, CarrierId, Flight, FlightId, MapExtents, Priority, Sector, SectorOccupancy, Speed,
+    StatusUpdate, ToFromProto,
+};
+
+fn flight_id(id: &str) -> FlightId {
+    FlightId(id.parse().expect("flight id"))
+}
+
+fn cases() -> Vec<(&'static str, StatusUpdate)> {
+    vec![
+        (
+            "empty",
+            StatusUpdate {
+                time: 0,
+                flights: vec![],
+                speed: Speed::RealTime,
+                backlog: None,
+                sectors: vec![],
+            },
+        ),
+        (
+            "fast_forward",
+            StatusUpdate {
+                time: 86_399,
+                flights: vec![
+                    Flight {
+                        id: flight_id("01HF4V8K3Q9Z6X2M7N5P0R1S2T"),
+                        carrier: CarrierId(0),
+                        launch_time: 25_200,
+                        orders: vec![
+                            testkit::order(25_190, "Toronto", Priority::Emergency),
+                            testkit::order(24_000, "Detroit", Priority::Resupply),
+                        ],
+                        diversion: None,
+                        phases: Default::default(),
+                        mode: Default::default(),
+                        departs_from: None,
+                        return_policy: Default::default(),
+                    },
+                    Flight {
+                        id: flight_id("01HF4V8K3Q9Z6X2M7N5P0R1S2V"),
+                        carrier: CarrierId(9),
+                        launch_time: 86_340,
+                        orders: vec![testkit::order(86_300, "St. Louis", Priority::Resupply)],
+                        diversion: None,
+                        phases: Default::default(),
+                        mode: Default::default(),
+                        departs_from: None,
+                        return_policy: Default::default(),
+                    },
+                ],
+                speed: Speed::fast_forward(200).expect("speed"),
+                backlog: None,
+                sectors: vec![],
+            },
+        ),
+        (
+            "slow_motion",
+            StatusUpdate {
+                time: 3_600,
+                flights: vec![Flight {
+                    id: flight_id("01HF4V8K3Q9Z6X2M7N5P0R1S2W"),
+                    carrier: CarrierId(3),
+                    launch_time: 3_540,
+                    orders: vec![testkit::order(3_500, "Tampa", Priority::Emergency)],
+                    diversion: None,
+                    phases: Default::default(),
+                    mode: Default::default(),
+                    departs_from: None,
+                    return_policy: Default::default(),
+                }],
+                speed: Speed::SlowMotion(2.try_into().expect("speed")),
+                backlog: None,
+                sectors: vec![],
+            },
+        ),
+        (
+            "backlog",
+            StatusUpdate {
+                time: 7_200,
+                flights: vec![],
+                speed: Speed::RealTime,
+                backlog: Some(Backlog {
+                    emergencies: 2,
+                    resupplies: 5,
+                    oldest: Some(6_000),
+                }),
+                sectors: vec![],
+            },
+        ),
+        (
+            "sectors",
+            StatusUpdate {
+                time: 7_200,
+                flights: vec![],
+                speed: Speed::RealTime,
+                backlog: None,
+                sectors: vec![SectorOccupancy {
+                    sector: Sector {
+                        name: "North".to_string(),
+                        extents: MapExtents {
+                            west_m: -5_000,
+                            north_m: 4_000,
+                            east_m: 5_000,
+                            south_m: 0,
+                        },
+                        max_flights: 2,
+                    },
+                    flights: 1,
+                }],
+            },
+        ),
+    ]
+}
+
+fn timestamp_json(timestamp: &Option<prost_types::Timestamp>) -> Value {
+    match timestamp {
+        Some(timestamp) => timestamp.to_string().into(),
+        None => Value::Null,
+    }
+}
+
+fn order_json(order: &server::Order) -> Value {
+    Value::Object(vec![
+        ("time".to_string(), timestamp_json(&order.time)),
+        ("destination".to_string(), order.destination.as_str().into()),
+        (
+            "priority".to_string(),
+            format!("{:?}", order.priority()).into(),
+        ),
+    ])
+}
+
+fn update_json(update: &server::StatusUpdate) -> Value {
+    let flights = update.flights.iter().map(|flight| {
+        Value::Object(vec![
+            ("id".to_string(), flight.id.as_str().into()),
+            (
+                "launch_time".to_string(),
+                timestamp_json(&flight.launch_time),
+            ),
+            (
+                "orders".to_string(),
+                Value::Array(flight.orders.iter().map(order_json).collect()),
+            ),
+            ("carrier".to_string(), Value::Number(flight.carrier as f64)),
+        ])
+    });
+
+    let mut fields = vec![
+        ("time".to_string(), timestamp_json(&update.time)),
+        ("flights".to_string(), Value::Array(flights.collect())),
+        ("speed".to_string(), Value::Number(update.speed as f64)),
+    ];
+    if let Some(backlog) = &update.backlog {
+        fields.push((
+            "backlog".to_string(),
+            Value::Object(vec![
+                (
+                    "emergencies".to_string(),
+                    Value::Number(backlog.emergencies as f64),
+                ),
+                (
+                    "resupplies".to_string(),
+                    Value::Number(backlog.resupplies as f64),
+                ),
+                ("oldest".to_string(), timestamp_json(&backlog.oldest)),
+            ]),
+        ));
+    }
+    if !update.sectors.is_empty() {
+        let sectors = update.sectors.iter().map(|occupancy| {
+            let sector = occupancy.sector.as_ref();
+            let extents = sector.and_then(|sector| sector.extents.as_ref());
+            Value::Object(vec![
+                (
+                    "name".to_string(),
+                    sector.map_or("", |sector| sector.name.as_str()).into(),
+                ),
+                (
+                    "extents".to_string(),
+                    Value::Array(
+                        extents
+                            .map(|e| vec![e.west_m, e.north_m, e.east_m, e.south_m])
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(|bound| Value::Number(bound as f64))
+                            .collect(),
+                    ),
+                ),
+                (
+                    "max_flights".to_string(),
+                    Value::Number(sector.map_or(0, |sector| sector.max_flights) as f64),
+                ),
+                (
+                    "flights".to_string(),
+                    Value::Number(occupancy.flights as f64),
+                ),
+            ])
+        });
+        fields.push(("sectors".to_string(), Value::Array(sectors.collect())));
+    }
+    Value::Object(fields)
+}
+
+/// Renders the encoded bytes of an update along with its decoded contents
+fn render(update: StatusUpdate) -> String {
+    let proto: server::StatusUpdate = update.into_proto();
+    let bytes = proto.encode_to_vec();
+    let decoded = server::StatusUpdate::decode(bytes.as_slice()).expect("decode");
+
+    let hex = bytes
+        .chunks(16)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!("# bytes\n{}\n\n# json\n{:#}\n", hex, update_json(&decoded))
+}
+
+/// Returns the lines which differ between the expected & actual snapshots
+fn diff(expected: &str, actual: &str) -> String {
+    let (expected, actual) = (
+        expected.lines().collect::<Vec<_>>(),
+        actual.lines().collect::<Vec<_>>(),
+    );
+
+    (0..expected.len().max(actual.len()))
+        .filter_map(|i| match (expected.get(i), actual.get(i)) {
+            (Some(e), Some(a)) if e == a => None,
+            (e, a) => Some(format!(
+                "line {}:\n- {}\n+ {}",
+                i + 1,
+                e.unwrap_or(&""),
+                a.unwrap_or(&"")
+            )),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[test]
+fn test_status_update_snapshots() {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/snapshots");
+    let update = std::env::var("UPDATE_SNAPSHOTS").is_ok();
+
+    let mut failures = vec![];
+    for (name, status_update) in cases() {
+        // The encoding must also survive the round trip back into the domain type
+        let proto: server::StatusUpdate = status_update.clone().into_proto();
+        let round_trip = StatusUpdate::try_from_proto(proto).expect("round trip");
+        assert_eq!(
+            render(round_trip),
+            render(status_update.clone()),
+            "{}",
+            name
+        );
+
+        let path = dir.join(format!("status_update_{}.txt", name));
+        let actual = render(status_update);
+
+        if update {
+            std::fs::create_dir_all(&dir).expect("create snapshot dir");
+            std::fs::write(&path, &actual).expect("write snapshot");
+            continue;
+        }
+
+        let expected = std::fs::read_to_string(&path).unwrap_or_default();
+        if expected != actual {
+            failures.push(format!(
+                "{} does not match:\n{}",
+                path.display(),
+                diff(&expected, &actual)
+            ));
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{}\n\nIf this change is intentional, run with UPDATE_SNAPSHOTS=1",
+        failures.join("\n\n")
+    );
+}
@@ -0,0 +1,168 @@
+//! Compares the cost of fanning status updates out to many subscribers by cloning the update for
+//! each of them, against sharing a single proto through a `Broadcaster`, & against sharing the
+//! bytes of a proto encoded just once. Each subscriber's share of the work includes encoding what
+//! it's sent, as tonic does, so only the last holds flat however many subscribers there are.
+//!
+//! Run with `cargo bench -p server --bench broadcast`.
+
+use std::hint::black_box;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures::channel::mpsc;
+use prost::Message;
+use schema::proto::server as proto;
+use schema::{CarrierId, DestinationId, Flight, FlightId, MonitorEvent, Order, Priority, Speed};
+use schema::{StatusUpdate, ToFromProto};
+use server::{Broadcaster, Encoded, Progress};
+use ulid::Ulid;
+
+const TICKS: u32 = 200;
+
+fn update() -> StatusUpdate {
+    StatusUpdate {
+        time: 43_200,
+        flights: (0..10)
+            .map(|carrier| Flight {
+                id: FlightId::new(),
+                carrier: CarrierId(carrier),
+                launch_time: 43_000,
+                orders: (0..3)
+                    .map(|i| Order {
+                        time: 42_000 + i,
+                        destination: DestinationId::from("Some Destination"),
+                        priority: Priority::Resupply,
+                        ..Default::default()
+                    })
+                    .collect(),
+                diversion: None,
+                phases: Default::default(),
+                mode: Default::default(),
+                departs_from: None,
+                return_policy: Default::default(),
+            })
+            .collect(),
+        speed: Speed::fast_forward(200).expect("speed"),
+        backlog: None,
+        sectors: vec![],
+    }
+}
+
+/// Sends each tick's update to every subscriber, returning the average time taken per tick. The
+/// subscribers taking their events is timed too, as their streams run on the server as well.
+fn time_ticks<T>(
+    subscribers: usize,
+    mut send: impl FnMut(&StatusUpdate),
+    receivers: &mut [mpsc::UnboundedReceiver<T>],
+    mut receive: impl FnMut(T),
+) -> Duration {
+    let update = update();
+    let mut elapsed = Duration::ZERO;
+    for _ in 0..TICKS {
+        let started = Instant::now();
+        send(&update);
+        for receiver in receivers.iter_mut() {
+            while let Ok(Some(event)) = receiver.try_next() {
+                receive(event);
+            }
+        }
+        elapsed += started.elapsed();
+    }
+    assert_eq!(receivers.len(), subscribers);
+
+    elapsed / TICKS
+}
+
+/// Every subscriber receives its own clone of the update, converted to a proto as it's sent
+fn clone_per_subscriber(subscribers: usize) -> Duration {
+    let (senders, mut receivers): (Vec<_>, Vec<_>) =
+        (0..subscribers).map(|_| mpsc::unbounded()).unzip();
+
+    time_ticks(
+        subscribers,
+        |update| {
+            for sender in &senders {
+                let _ = sender.unbounded_send(MonitorEvent::StatusUpdate(update.clone()));
+            }
+        },
+        &mut receivers,
+        |event: MonitorEvent| {
+            let event: proto::MonitorEvent = event.into_proto();
+            black_box(event.encode_to_vec());
+        },
+    )
+}
+
+/// The update is converted to a proto once & shared by every subscriber
+fn shared(subscribers: usize) -> Duration {
+    let mut broadcaster = Broadcaster::<proto::MonitorEvent>::default();
+    let mut receivers = (0..subscribers)
+        .map(|_| {
+            let (tx, rx) = mpsc::unbounded();
+            broadcaster.subscribe(Ulid::new(), tx, Progress::default());
+            rx
+        })
+        .collect::<Vec<_>>();
+
+    time_ticks(
+        subscribers,
+        |update| {
+            let event: proto::MonitorEvent =
+                MonitorEvent::StatusUpdate(update.clone()).into_proto();
+            broadcaster.send(event)
+        },
+        &mut receivers,
+        // As with the real subscribers, the proto is cloned & encoded off of the broadcasting task
+        |event: Arc<proto::MonitorEvent>| {
+            black_box(Arc::unwrap_or_clone(event).encode_to_vec());
+        },
+    )
+}
+
+/// The update is converted to a proto & encoded once, its bytes shared by every subscriber
+fn pre_encoded(subscribers: usize) -> Duration {
+    let mut broadcaster = Broadcaster::<Encoded>::default();
+    let mut receivers = (0..subscribers)
+        .map(|_| {
+            let (tx, rx) = mpsc::unbounded();
+            broadcaster.subscribe(Ulid::new(), tx, Progress::default());
+            rx
+        })
+        .collect::<Vec<_>>();
+
+    time_ticks(
+        subscribers,
+        |update| {
+            let event: proto::MonitorEvent =
+                MonitorEvent::StatusUpdate(update.clone()).into_proto();
+            broadcaster.send(Encoded::new(&event))
+        },
+        &mut receivers,
+        // Subscribers only copy the bytes into their connection's buffer
+        |event: Arc<Encoded>| {
+            black_box(event.as_bytes().to_vec());
+        },
+    )
+}
+
+fn main() {
+    println!(
+        "{:>12} {:>22} {:>14} {:>14} {:>8}",
+        "subscribers", "clone per subscriber", "shared", "pre-encoded", "speedup"
+    );
+    for subscribers in [1, 10, 100, 1_000] {
+        let (cloned, shared, encoded) = (
+            clone_per_subscriber(subscribers),
+            shared(subscribers),
+            pre_encoded(subscribers),
+        );
+        println!(
+            "{:>12} {:>22?} {:>14?} {:>14?} {:>7.1}x",
+            subscribers,
+            cloned,
+            shared,
+            encoded,
+            cloned.as_secs_f64() / encoded.as_secs_f64()
+        );
+    }
+}
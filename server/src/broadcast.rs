@@ -0,0 +1,416 @@
+use std::collections::{HashMap, VecDeque};
+use std::num::NonZeroU32;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures::channel::mpsc;
+use ulid::Ulid;
+
+/// Fans events out to each of the subscribers of a stream. Every event is wrapped in an `Arc`
+/// once & shared by all of the subscribers, rather than being cloned for each of them.
+/// Subscribers may limit how often they're sent events which can be coalesced (e.g. status
+/// updates, of which only the latest matters), see `send_latest`.
+pub struct Broadcaster<T> {
+    subscribers: HashMap<Ulid, Subscriber<T>>,
+    /// Number of subscribers removed so far for having stopped taking events
+    stale_removed: usize,
+}
+
+struct Subscriber<T> {
+    sender: mpsc::UnboundedSender<Arc<T>>,
+    progress: Progress,
+    client: Option<ClientIdentity>,
+    connected_at: Instant,
+    /// Number of events sent to the subscriber
+    sent: usize,
+    /// Events the subscriber had taken as of the last check for progress
+    consumed: usize,
+    /// When the subscriber was last caught up or seen taking events
+    last_progress: Instant,
+    /// When each of the events it's yet to take (as of the last check) were sent, oldest first
+    sent_at: VecDeque<Instant>,
+    /// Coalescable events replaced by a later one before they were sent
+    dropped: usize,
+    /// Shortest time between coalescable events sent to the subscriber, if it's throttled
+    min_interval: Option<Duration>,
+    /// When the subscriber may next be sent a coalescable event
+    next_due: Instant,
+    /// Latest coalescable event held back until it's due
+    pending: Option<Arc<T>>,
+}
+
+impl<T> Subscriber<T> {
+    /// Sends an event, returning whether the subscriber is still connected
+    fn send(&mut self, event: Arc<T>, now: Instant) -> bool {
+        // Subscribers which were caught up can't be blamed for the time before this event
+        if self.progress.get() == self.sent {
+            self.last_progress = now;
+        }
+        self.sent += 1;
+        self.sent_at.push_back(now);
+        self.sender.unbounded_send(event).is_ok()
+    }
+
+    /// Forgets when the events the subscriber has since taken were sent, returning the number of
+    /// events it's yet to take
+    fn catch_up(&mut self) -> usize {
+        let waiting = self.sent.saturating_sub(self.progress.get());
+        let taken = self.sent_at.len().saturating_sub(waiting);
+        self.sent_at.drain(..taken);
+        waiting
+    }
+
+    /// Sends any coalescable event which is being held back, whether or not it's due yet
+    fn flush(&mut self, now: Instant) -> bool {
+        match self.pending.take() {
+            Some(event) => {
+                self.next_due = now + self.min_interval.unwrap_or_default();
+                self.send(event, now)
+            }
+            None => true,
+        }
+    }
+}
+
+/// How a client introduced itself when it subscribed
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ClientIdentity {
+    pub name: String,
+    pub version: String,
+}
+
+/// How a subscriber of a `Broadcaster` is keeping up
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubscriberMetrics {
+    pub id: Ulid,
+    pub client: Option<ClientIdentity>,
+    /// How long the subscriber has been connected
+    pub connected: Duration,
+    /// Events sent to the subscriber which it's yet to take
+    pub waiting: usize,
+    /// How long the oldest of those events has been waiting, zero if it's caught up
+    pub lag: Duration,
+    /// Coalescable events replaced by a later one before they were sent, as it's throttled
+    pub dropped: usize,
+}
+
+/// Number of events a subscriber has taken from its stream, shared between the stream & the
+/// `Broadcaster` so that subscribers which stop taking events can be detected
+#[derive(Clone, Debug, Default)]
+pub struct Progress(Arc<AtomicUsize>);
+
+impl Progress {
+    /// Record that the subscriber has taken an event
+    pub fn consumed(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn get(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl<T> Default for Broadcaster<T> {
+    fn default() -> Self {
+        Self {
+            subscribers: HashMap::new(),
+            stale_removed: 0,
+        }
+    }
+}
+
+impl<T> Broadcaster<T> {
+    /// Adds a subscriber, whose stream should record its `Progress` as it takes each event
+    pub fn subscribe(
+        &mut self,
+        id: Ulid,
+        subscriber: mpsc::UnboundedSender<Arc<T>>,
+        progress: Progress,
+    ) {
+        let consumed = progress.get();
+        self.subscribers.insert(
+            id,
+            Subscriber {
+                sender: subscriber,
+                progress,
+                client: None,
+                connected_at: Instant::now(),
+                sent: consumed,
+                consumed,
+                last_progress: Instant::now(),
+                sent_at: VecDeque::new(),
+                dropped: 0,
+                min_interval: None,
+                next_due: Instant::now(),
+                pending: None,
+            },
+        );
+    }
+
+    /// Limits the events sent to a subscriber with `send_latest` to at most the provided rate,
+    /// holding back all but the latest of those sent in between
+    pub fn throttle(&mut self, id: &Ulid, max_per_second: NonZeroU32) {
+        if let Some(subscriber) = self.subscribers.get_mut(id) {
+            subscriber.min_interval = Some(Duration::from_secs(1) / max_per_second.get());
+        }
+    }
+
+    /// Records who a subscriber is, as the client told us when it subscribed
+    pub fn identify(&mut self, id: &Ulid, client: ClientIdentity) {
+        if let Some(subscriber) = self.subscribers.get_mut(id) {
+            subscriber.client = Some(client);
+        }
+    }
+
+    /// Returns the number of connected subscribers
+    pub fn len(&self) -> usize {
+        self.subscribers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.subscribers.is_empty()
+    }
+
+    /// Returns how each subscriber is keeping up
+    pub fn metrics(&mut self) -> impl Iterator<Item = SubscriberMetrics> + '_ {
+        let now = Instant::now();
+        self.subscribers.iter_mut().map(move |(id, subscriber)| {
+            let waiting = subscriber.catch_up();
+            SubscriberMetrics {
+                id: *id,
+                client: subscriber.client.clone(),
+                connected: now.duration_since(subscriber.connected_at),
+                waiting,
+                lag: subscriber
+                    .sent_at
+                    .front()
+                    .map_or(Duration::ZERO, |sent_at| now.duration_since(*sent_at)),
+                dropped: subscriber.dropped,
+            }
+        })
+    }
+
+    /// Returns the number of subscribers removed so far for having stopped taking events
+    pub fn stale_removed(&self) -> usize {
+        self.stale_removed
+    }
+
+    /// Sends an event to all of the subscribers, removing any which have disconnected. Any event
+    /// held back from a throttled subscriber is sent first, so that it doesn't arrive out of order.
+    pub fn send(&mut self, event: T) {
+        let event = Arc::new(event);
+        let now = Instant::now();
+        self.subscribers
+            .retain(|_, subscriber| subscriber.flush(now) && subscriber.send(event.clone(), now));
+    }
+
+    /// Sends an event which is superseded by the next one sent this way, e.g. a status update.
+    /// Throttled subscribers which aren't due another are sent it later instead, unless another
+    /// replaces it first.
+    pub fn send_latest(&mut self, event: T) {
+        let event = Arc::new(event);
+        let now = Instant::now();
+        self.subscribers.retain(|_, subscriber| {
+            if subscriber.pending.replace(event.clone()).is_some() {
+                subscriber.dropped += 1;
+            }
+            match now >= subscriber.next_due {
+                true => subscriber.flush(now),
+                false => true,
+            }
+        });
+    }
+
+    /// Sends the events held back from throttled subscribers which are now due
+    pub fn flush_due(&mut self) {
+        let now = Instant::now();
+        self.subscribers.retain(|_, subscriber| {
+            match subscriber.pending.is_some() && now >= subscriber.next_due {
+                true => subscriber.flush(now),
+                false => true,
+            }
+        });
+    }
+
+    /// Whether any subscriber has events held back until they're due
+    pub fn has_pending(&self) -> bool {
+        self.subscribers
+            .values()
+            .any(|subscriber| subscriber.pending.is_some())
+    }
+
+    /// Sends an event to just one of the subscribers, such as to catch it up when it subscribes
+    pub fn send_to(&mut self, id: &Ulid, event: T) {
+        let Some(subscriber) = self.subscribers.get_mut(id) else {
+            return;
+        };
+        let now = Instant::now();
+        if !(subscriber.flush(now) && subscriber.send(Arc::new(event), now)) {
+            self.subscribers.remove(id);
+        }
+    }
+
+    /// Removes subscribers which have had events waiting without taking any for at least the
+    /// timeout, closing their streams. Returns the number of subscribers removed.
+    pub fn remove_stale(&mut self, idle_timeout: Duration) -> usize {
+        let now = Instant::now();
+        let before = self.subscribers.len();
+        self.subscribers.retain(|id, subscriber| {
+            let consumed = subscriber.progress.get();
+            if consumed != subscriber.consumed {
+                subscriber.consumed = consumed;
+                subscriber.last_progress = now;
+                subscriber.catch_up();
+            }
+
+            let stale = consumed < subscriber.sent
+                && now.duration_since(subscriber.last_progress) >= idle_timeout;
+            if stale {
+                tracing::info!("removing stale subscriber {}", id);
+            }
+            !stale
+        });
+
+        let removed = before - self.subscribers.len();
+        self.stale_removed += removed;
+        removed
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::StreamExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_send() {
+        let mut broadcaster = Broadcaster::default();
+        let (tx, mut rx) = mpsc::unbounded();
+        let (disconnected_tx, disconnected_rx) = mpsc::unbounded();
+        broadcaster.subscribe(Ulid::new(), tx, Progress::default());
+        broadcaster.subscribe(Ulid::new(), disconnected_tx, Progress::default());
+        drop(disconnected_rx);
+
+        broadcaster.send("update".to_string());
+
+        // The same event is shared with each subscriber, & disconnected subscribers are removed
+        assert_eq!(
+            rx.next().await.as_deref().map(String::as_str),
+            Some("update")
+        );
+        assert_eq!(broadcaster.len(), 1);
+
+        // Events can be sent to just one subscriber, such as to catch up a later subscriber
+        let late = Ulid::new();
+        let (late_tx, mut late_rx) = mpsc::unbounded();
+        broadcaster.subscribe(late, late_tx, Progress::default());
+        broadcaster.send_to(&late, "catch up".to_string());
+        assert_eq!(
+            late_rx.next().await.as_deref().map(String::as_str),
+            Some("catch up")
+        );
+        assert!(rx.try_next().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_throttle() {
+        let mut broadcaster = Broadcaster::default();
+        let (full_tx, mut full_rx) = mpsc::unbounded();
+        let (slow_tx, mut slow_rx) = mpsc::unbounded();
+        let (fast_tx, mut fast_rx) = mpsc::unbounded();
+        let (slow, fast) = (Ulid::new(), Ulid::new());
+        broadcaster.subscribe(Ulid::new(), full_tx, Progress::default());
+        broadcaster.subscribe(slow, slow_tx, Progress::default());
+        broadcaster.subscribe(fast, fast_tx, Progress::default());
+        broadcaster.throttle(&slow, NonZeroU32::new(1).expect("rate"));
+        broadcaster.throttle(&fast, NonZeroU32::new(1_000).expect("rate"));
+        let next = |rx: &mut mpsc::UnboundedReceiver<Arc<String>>| {
+            rx.try_next().ok().flatten().map(|event| event.to_string())
+        };
+
+        for update in ["1", "2", "3"] {
+            broadcaster.send_latest(update.to_string());
+        }
+        // Unthrottled subscribers get every update, throttled ones the first & then the latest
+        for update in ["1", "2", "3"] {
+            assert_eq!(next(&mut full_rx).as_deref(), Some(update));
+        }
+        assert_eq!(next(&mut slow_rx).as_deref(), Some("1"));
+        assert_eq!(next(&mut slow_rx), None);
+        assert_eq!(next(&mut fast_rx).as_deref(), Some("1"));
+        assert!(broadcaster.has_pending());
+
+        // Held back updates are sent once they're due
+        std::thread::sleep(Duration::from_millis(5));
+        broadcaster.flush_due();
+        assert_eq!(next(&mut fast_rx).as_deref(), Some("3"));
+        assert_eq!(next(&mut slow_rx), None);
+
+        // Or before any other event, so that events arrive in the order they were sent
+        broadcaster.send("reset".to_string());
+        assert_eq!(next(&mut slow_rx).as_deref(), Some("3"));
+        assert_eq!(next(&mut slow_rx).as_deref(), Some("reset"));
+        assert_eq!(next(&mut fast_rx).as_deref(), Some("reset"));
+        assert!(!broadcaster.has_pending());
+    }
+
+    #[tokio::test]
+    async fn test_metrics() {
+        let mut broadcaster = Broadcaster::default();
+        let (tx, mut rx) = mpsc::unbounded();
+        let (id, progress) = (Ulid::new(), Progress::default());
+        broadcaster.subscribe(id, tx, progress.clone());
+        broadcaster.throttle(&id, NonZeroU32::new(1).expect("rate"));
+        let client = ClientIdentity {
+            name: "client".to_string(),
+            version: "0.1.0".to_string(),
+        };
+        broadcaster.identify(&id, client.clone());
+        let metrics = |broadcaster: &mut Broadcaster<String>| {
+            broadcaster.metrics().next().expect("subscriber")
+        };
+
+        // Updates superseded while the subscriber isn't due another are dropped
+        for update in ["1", "2", "3"] {
+            broadcaster.send_latest(update.to_string());
+        }
+        std::thread::sleep(Duration::from_millis(5));
+        let lagging = metrics(&mut broadcaster);
+        assert_eq!(lagging.client, Some(client));
+        assert_eq!((lagging.waiting, lagging.dropped), (1, 1));
+        assert!(lagging.lag >= Duration::from_millis(5));
+        assert!(lagging.connected >= lagging.lag);
+
+        // ...& it stops lagging once it's taken what it was sent
+        rx.next().await;
+        progress.consumed();
+        let caught_up = metrics(&mut broadcaster);
+        assert_eq!((caught_up.waiting, caught_up.lag), (0, Duration::ZERO));
+    }
+
+    #[tokio::test]
+    async fn test_remove_stale() {
+        let mut broadcaster = Broadcaster::default();
+        let (active_tx, mut active_rx) = mpsc::unbounded();
+        let (stalled_tx, _stalled_rx) = mpsc::unbounded();
+        let (idle_tx, _idle_rx) = mpsc::unbounded();
+        let active = Progress::default();
+        broadcaster.subscribe(Ulid::new(), active_tx, active.clone());
+        broadcaster.subscribe(Ulid::new(), stalled_tx, Progress::default());
+
+        broadcaster.send("heartbeat".to_string());
+        active_rx.next().await;
+        active.consumed();
+
+        // Subscribers with nothing waiting aren't stale, however long they've been idle
+        broadcaster.subscribe(Ulid::new(), idle_tx, Progress::default());
+
+        // Only the subscriber which hasn't taken its event is removed
+        assert_eq!(broadcaster.remove_stale(Duration::ZERO), 1);
+        assert_eq!(broadcaster.len(), 2);
+        assert_eq!(broadcaster.remove_stale(Duration::from_secs(60)), 0);
+        assert_eq!(broadcaster.stale_removed(), 1);
+    }
+}
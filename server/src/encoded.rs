@@ -0,0 +1,263 @@
+use std::convert::Infallible;
+use std::marker::PhantomData;
+use std::task::{Context, Poll};
+
+use prost::bytes::{BufMut, Bytes};
+use tonic::body::BoxBody;
+use tonic::codec::{Codec, EncodeBuf, Encoder, ProstCodec};
+use tonic::codegen::{http, BoxFuture, Service};
+use tonic::server::{Grpc, NamedService, ServerStreamingService};
+use tonic::transport::Body;
+use tonic::Status;
+
+/// A message encoded to its protobuf bytes ahead of time, so that it can be sent to any number of
+/// subscribers without being encoded again. Cloning it only bumps a reference count.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Encoded(Bytes);
+
+impl Encoded {
+    pub fn new(message: &impl prost::Message) -> Self {
+        Self(message.encode_to_vec().into())
+    }
+
+    pub fn as_bytes(&self) -> &Bytes {
+        &self.0
+    }
+}
+
+/// Sends messages encoded ahead of time as they are, while decoding requests as usual
+pub struct EncodedCodec<U>(PhantomData<U>);
+
+impl<U> Default for EncodedCodec<U> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<U> Codec for EncodedCodec<U>
+where
+    U: prost::Message + Default + Send + 'static,
+{
+    type Encode = Encoded;
+    type Decode = U;
+
+    type Encoder = EncodedEncoder;
+    type Decoder = <ProstCodec<(), U> as Codec>::Decoder;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        EncodedEncoder
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        ProstCodec::<(), U>::default().decoder()
+    }
+}
+
+pub struct EncodedEncoder;
+
+impl Encoder for EncodedEncoder {
+    type Item = Encoded;
+    type Error = Status;
+
+    fn encode(&mut self, item: Self::Item, dst: &mut EncodeBuf<'_>) -> Result<(), Self::Error> {
+        dst.put_slice(&item.0);
+        Ok(())
+    }
+}
+
+/// Serves one server streaming method of a gRPC service from messages encoded ahead of time,
+/// passing requests for each of its other methods on to the service. The handler takes the place
+/// of the service's own implementation of the method, which is never called.
+pub struct EncodedStreams<S, H, R> {
+    service: S,
+    /// Path of the method, e.g. `/server.Server/Monitor`
+    path: &'static str,
+    handler: H,
+    request: PhantomData<fn(R)>,
+}
+
+impl<S, H, R> EncodedStreams<S, H, R> {
+    pub fn new(service: S, path: &'static str, handler: H) -> Self {
+        Self {
+            service,
+            path,
+            handler,
+            request: PhantomData,
+        }
+    }
+}
+
+impl<S: Clone, H: Clone, R> Clone for EncodedStreams<S, H, R> {
+    fn clone(&self) -> Self {
+        Self::new(self.service.clone(), self.path, self.handler.clone())
+    }
+}
+
+impl<S: NamedService, H, R> NamedService for EncodedStreams<S, H, R> {
+    const NAME: &'static str = S::NAME;
+}
+
+impl<S, H, R> Service<http::Request<Body>> for EncodedStreams<S, H, R>
+where
+    S: Service<http::Request<Body>, Response = http::Response<BoxBody>, Error = Infallible>,
+    S::Future: Send + 'static,
+    H: ServerStreamingService<R, Response = Encoded> + Clone + Send + 'static,
+    H::ResponseStream: Send + 'static,
+    H::Future: Send,
+    R: prost::Message + Default + Send + 'static,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+        if request.uri().path() != self.path {
+            return Box::pin(self.service.call(request));
+        }
+
+        let handler = self.handler.clone();
+        Box::pin(async move {
+            let mut grpc = Grpc::new(EncodedCodec::<R>::default());
+            Ok(grpc.server_streaming(handler, request).await)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::pin::Pin;
+    use std::sync::Arc;
+
+    use futures::channel::mpsc;
+    use futures::{Stream, StreamExt};
+    use prost::Message;
+    use schema::proto::server as proto;
+    use schema::{MonitorEvent, ToFromProto};
+    use ulid::Ulid;
+
+    use super::*;
+    use crate::{Broadcaster, Progress};
+
+    #[tokio::test]
+    async fn test_encode_once() {
+        let mut broadcaster = Broadcaster::default();
+        let mut receivers = (0..100)
+            .map(|_| {
+                let (tx, rx) = mpsc::unbounded();
+                broadcaster.subscribe(Ulid::new(), tx, Progress::default());
+                rx
+            })
+            .collect::<Vec<_>>();
+
+        let event: proto::MonitorEvent = MonitorEvent::SimulationStarted(Ulid::new()).into_proto();
+        let encoded = Encoded::new(&event);
+        broadcaster.send(encoded.clone());
+
+        // Every subscriber is sent the very same bytes, rather than encoding the event again
+        for receiver in &mut receivers {
+            let received = Arc::unwrap_or_clone(receiver.next().await.expect("event"));
+            assert_eq!(received.as_bytes().as_ptr(), encoded.as_bytes().as_ptr());
+        }
+        assert_eq!(
+            proto::MonitorEvent::decode(encoded.as_bytes().clone()).expect("decode"),
+            event
+        );
+    }
+
+    /// Stands in for the rest of a service's methods
+    #[derive(Clone)]
+    struct NotFound;
+
+    impl Service<http::Request<Body>> for NotFound {
+        type Response = http::Response<BoxBody>;
+        type Error = Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _request: http::Request<Body>) -> Self::Future {
+            Box::pin(async {
+                Ok(http::Response::builder()
+                    .status(404)
+                    .body(tonic::codegen::empty_body())
+                    .expect("response"))
+            })
+        }
+    }
+
+    /// Streams as many heartbeats as updates per second requested
+    #[derive(Clone)]
+    struct Heartbeats;
+
+    type EncodedStream = Pin<Box<dyn Stream<Item = Result<Encoded, Status>> + Send>>;
+
+    impl Service<tonic::Request<proto::MonitorRequest>> for Heartbeats {
+        type Response = tonic::Response<EncodedStream>;
+        type Error = Status;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: tonic::Request<proto::MonitorRequest>) -> Self::Future {
+            let heartbeat = Encoded::new(&MonitorEvent::Heartbeat.into_proto());
+            let count = request.into_inner().max_updates_per_second as usize;
+            Box::pin(async move {
+                let stream = futures::stream::repeat(Ok(heartbeat)).take(count);
+                Ok(tonic::Response::new(stream.boxed() as EncodedStream))
+            })
+        }
+    }
+
+    /// Frames a message as gRPC does, behind a flag for compression & its length
+    fn frame(message: &[u8]) -> Vec<u8> {
+        let mut framed = vec![0];
+        framed.extend((message.len() as u32).to_be_bytes());
+        framed.extend(message);
+        framed
+    }
+
+    fn request(path: &str, message: &impl Message) -> http::Request<Body> {
+        http::Request::builder()
+            .uri(format!("http://localhost{}", path))
+            .method("POST")
+            .header("content-type", "application/grpc")
+            .body(Body::from(frame(&message.encode_to_vec())))
+            .expect("request")
+    }
+
+    #[tokio::test]
+    async fn test_encoded_streams() {
+        let path = "/server.Server/Monitor";
+        let mut service = EncodedStreams::new(NotFound, path, Heartbeats);
+
+        let monitor = proto::MonitorRequest {
+            max_updates_per_second: 2,
+            ..Default::default()
+        };
+        let response = service.call(request(path, &monitor)).await.expect("call");
+        assert_eq!(response.status(), 200);
+        let body = hyper::body::to_bytes(response.into_body())
+            .await
+            .expect("body");
+
+        // The encoded events are sent as they are, framed like any other message
+        let heartbeat: proto::MonitorEvent = MonitorEvent::Heartbeat.into_proto();
+        let expected = frame(&heartbeat.encode_to_vec()).repeat(2);
+        assert_eq!(body.to_vec(), expected);
+
+        // Any other method is left to the service
+        let response = service
+            .call(request("/server.Server/GetScenario", &()))
+            .await
+            .expect("call");
+        assert_eq!(response.status(), 404);
+    }
+}
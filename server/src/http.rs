@@ -0,0 +1,240 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+
+use futures::channel::{mpsc, oneshot};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, StatusCode};
+use scheduler_core::Submitted;
+use schema::{json, DestinationNames, Order};
+
+/// Orders received over HTTP with the idempotency key of the submission, if any, along with a
+/// channel for the result of queueing them
+pub type OrderSubmission = (
+    Vec<Order>,
+    Option<String>,
+    oneshot::Sender<Result<Submitted, String>>,
+);
+
+/// Header identifying retries of the same submission
+const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Serves a `POST /orders` endpoint accepting a JSON order, or an array of them, for systems
+/// which can't speak gRPC. Each submission is forwarded to be queued by the running simulation.
+/// Submissions retried with the same `Idempotency-Key` header are only queued once, & those for
+/// destinations which aren't among the provided names are rejected as a whole.
+pub async fn serve_orders(
+    addr: SocketAddr,
+    submissions: mpsc::UnboundedSender<OrderSubmission>,
+    names: DestinationNames,
+) -> Result<(), hyper::Error> {
+    let make_service = make_service_fn(move |_| {
+        let (submissions, names) = (submissions.clone(), names.clone());
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                handle(request, submissions.clone(), names.clone())
+            }))
+        }
+    });
+
+    tracing::info!("accepting orders over HTTP on {}", addr);
+    hyper::Server::bind(&addr).serve(make_service).await
+}
+
+async fn handle(
+    request: Request<Body>,
+    submissions: mpsc::UnboundedSender<OrderSubmission>,
+    names: DestinationNames,
+) -> Result<Response<Body>, Infallible> {
+    if request.uri().path() != "/orders" {
+        return Ok(respond(StatusCode::NOT_FOUND, "not found"));
+    }
+    if request.method() != Method::POST {
+        return Ok(respond(StatusCode::METHOD_NOT_ALLOWED, "expected POST"));
+    }
+
+    let key = match request.headers().get(IDEMPOTENCY_KEY_HEADER) {
+        Some(key) => match key.to_str() {
+            Ok(key) => Some(key.to_string()),
+            Err(_) => return Ok(respond(StatusCode::BAD_REQUEST, "invalid idempotency key")),
+        },
+        None => None,
+    };
+
+    let orders = match hyper::body::to_bytes(request.into_body()).await {
+        Ok(body) => match std::str::from_utf8(&body) {
+            Ok(body) => parse_orders(body, &names),
+            Err(_) => Err("body is not valid UTF-8".to_string()),
+        },
+        Err(e) => Err(format!("failed to read body: {}", e)),
+    };
+    let orders = match orders {
+        Ok(orders) => orders,
+        Err(e) => return Ok(respond(StatusCode::BAD_REQUEST, &e)),
+    };
+
+    let count = orders.len();
+    let (tx, rx) = oneshot::channel();
+    if submissions.unbounded_send((orders, key, tx)).is_err() {
+        return Ok(respond(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "not accepting orders",
+        ));
+    }
+
+    Ok(match rx.await {
+        Ok(Ok(Submitted::Accepted(ids))) => {
+            tracing::info!("queued {} orders received over HTTP", count);
+            let accepted = json::Value::Object(vec![
+                ("accepted".to_string(), count.into()),
+                ("duplicate".to_string(), false.into()),
+                (
+                    "order_ids".to_string(),
+                    json::Value::Array(ids.into_iter().map(|id| id.0.into()).collect()),
+                ),
+            ]);
+            json_response(StatusCode::ACCEPTED, accepted)
+        }
+        Ok(Ok(Submitted::AlreadyKnown)) => {
+            let duplicate = json::Value::Object(vec![
+                ("accepted".to_string(), 0usize.into()),
+                ("duplicate".to_string(), true.into()),
+            ]);
+            json_response(StatusCode::OK, duplicate)
+        }
+        Ok(Err(e)) => respond(StatusCode::BAD_REQUEST, &e),
+        Err(_) => respond(StatusCode::SERVICE_UNAVAILABLE, "no simulation is running"),
+    })
+}
+
+/// Parses a JSON order, or an array of them, resolving their destinations against the names
+pub(crate) fn parse_orders(body: &str, names: &DestinationNames) -> Result<Vec<Order>, String> {
+    let parse = |value: &json::Value| Order::from_json(value, |name| names.resolve(name));
+    match json::Value::parse(body)? {
+        json::Value::Array(values) => values.iter().map(parse).collect(),
+        value => Ok(vec![parse(&value)?]),
+    }
+}
+
+/// Responds with a JSON object describing an error
+fn respond(status: StatusCode, error: &str) -> Response<Body> {
+    json_response(
+        status,
+        json::Value::Object(vec![("error".to_string(), error.into())]),
+    )
+}
+
+fn json_response(status: StatusCode, body: json::Value) -> Response<Body> {
+    let mut response = Response::new(Body::from(body.to_string()));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        hyper::header::CONTENT_TYPE,
+        hyper::header::HeaderValue::from_static("application/json"),
+    );
+    response
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use schema::{testkit, DestinationId, OrderId, Priority};
+
+    #[test]
+    fn test_parse_orders() {
+        let names = testkit::destination_names(&["Toronto", "Detroit"]);
+        let single = parse_orders(
+            r#"{"destination": "Toronto", "priority": "Emergency"}"#,
+            &names,
+        )
+        .expect("single order");
+        assert_eq!(single.len(), 1);
+        assert_eq!(single[0].priority, Priority::Emergency);
+
+        let many = parse_orders(
+            r#"[{"destination": "Toronto", "priority": "Emergency"},
+                {"destination": "Detroit", "priority": "Resupply", "time": 60}]"#,
+            &names,
+        )
+        .expect("array of orders");
+        assert_eq!(many.len(), 2);
+        assert_eq!(many[1].time, 60);
+
+        assert!(parse_orders(r#"[{"destination": "Toronto"}]"#, &names).is_err());
+        assert!(parse_orders("not json", &names).is_err());
+        // Orders for destinations which aren't known are rejected, without interning their names
+        let unknown = r#"{"destination": "Nowhere Posted", "priority": "Resupply"}"#;
+        assert!(parse_orders(unknown, &names).is_err());
+        assert_eq!(DestinationId::get("Nowhere Posted"), None);
+    }
+
+    #[tokio::test]
+    async fn test_handle() {
+        let (tx, mut rx) = mpsc::unbounded::<OrderSubmission>();
+        tokio::spawn(async move {
+            use futures::StreamExt;
+            let mut keys = std::collections::HashSet::new();
+            while let Some((orders, key, reply)) = rx.next().await {
+                let result = match orders
+                    .iter()
+                    .all(|o| o.destination.to_string() == "Toronto")
+                {
+                    true if key.is_some_and(|key| !keys.insert(key)) => Ok(Submitted::AlreadyKnown),
+                    true => Ok(Submitted::Accepted(vec![OrderId(1)])),
+                    false => Err("unknown destination".to_string()),
+                };
+                let _ = reply.send(result);
+            }
+        });
+
+        let post = |path: &str, body: &str| {
+            Request::post(path)
+                .body(Body::from(body.to_string()))
+                .expect("request")
+        };
+        let order = r#"{"destination": "Toronto", "priority": "Resupply"}"#;
+        let names = testkit::destination_names(&["Toronto"]);
+        let handle = |request, tx| handle(request, tx, names.clone());
+
+        let accepted = handle(post("/orders", order), tx.clone()).await.unwrap();
+        assert_eq!(accepted.status(), StatusCode::ACCEPTED);
+
+        let rejected = handle(
+            post(
+                "/orders",
+                r#"{"destination": "Nowhere", "priority": "Resupply"}"#,
+            ),
+            tx.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(rejected.status(), StatusCode::BAD_REQUEST);
+
+        // Retries with the same key are only accepted once
+        let keyed = || {
+            Request::post("/orders")
+                .header("Idempotency-Key", "retry")
+                .body(Body::from(order))
+                .expect("request")
+        };
+        let first = handle(keyed(), tx.clone()).await.unwrap();
+        assert_eq!(first.status(), StatusCode::ACCEPTED);
+        let retry = handle(keyed(), tx.clone()).await.unwrap();
+        assert_eq!(retry.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(retry.into_body()).await.unwrap();
+        assert_eq!(
+            json::Value::parse(std::str::from_utf8(&body).unwrap())
+                .unwrap()
+                .get("duplicate")
+                .cloned(),
+            Some(true.into())
+        );
+
+        let missing = handle(post("/other", order), tx.clone()).await.unwrap();
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+
+        let get = Request::get("/orders").body(Body::empty()).unwrap();
+        assert_eq!(
+            handle(get, tx).await.unwrap().status(),
+            StatusCode::METHOD_NOT_ALLOWED
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::VecDeque;
+use std::env;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// Network conditions simulated between the simulation & its subscribers, e.g. to see how clients
+/// interpolate between updates which arrive late & unevenly
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NetworkConditions {
+    /// Least time each event is held back for
+    pub delay: Duration,
+    /// Most extra time an event may be held back for, sampled afresh for each event
+    pub jitter: Duration,
+}
+
+impl NetworkConditions {
+    /// Reads the conditions from `NETWORK_DELAY_MS` & `NETWORK_JITTER_MS` where they're set,
+    /// simulating no delay otherwise
+    pub fn from_env() -> Self {
+        let millis = |name: &str| {
+            env::var(name)
+                .ok()
+                .and_then(|value| value.parse::<u64>().ok())
+                .map_or(Duration::ZERO, Duration::from_millis)
+        };
+
+        Self {
+            delay: millis("NETWORK_DELAY_MS"),
+            jitter: millis("NETWORK_JITTER_MS"),
+        }
+    }
+
+    /// Whether events are held back at all
+    pub fn is_enabled(&self) -> bool {
+        !(self.delay.is_zero() && self.jitter.is_zero())
+    }
+
+    fn sample(&self) -> Duration {
+        let jitter = (ulid::Ulid::new().random() % 1_000) as u32;
+        self.delay + self.jitter * jitter / 1_000
+    }
+}
+
+/// Holds items back for the simulated network conditions before they're delivered. Items are
+/// delivered in the order they were pushed, as over a single connection: one which is held back
+/// for longer holds back those behind it too.
+pub struct DelayLine<T> {
+    conditions: NetworkConditions,
+    /// Items in flight, along with when they're due to be delivered
+    in_flight: VecDeque<(Instant, T)>,
+}
+
+impl<T> DelayLine<T> {
+    pub fn new(conditions: NetworkConditions) -> Self {
+        Self {
+            conditions,
+            in_flight: VecDeque::new(),
+        }
+    }
+
+    pub fn conditions(&self) -> NetworkConditions {
+        self.conditions
+    }
+
+    /// Changes the conditions for items pushed from now on, those in flight being delivered when
+    /// they were already due
+    pub fn set_conditions(&mut self, conditions: NetworkConditions) {
+        self.conditions = conditions;
+    }
+
+    pub fn push(&mut self, item: T) {
+        let now = Instant::now();
+        let mut due = now + self.conditions.sample();
+        if let Some((last_due, _)) = self.in_flight.back() {
+            due = due.max(*last_due);
+        }
+        self.in_flight.push_back((due, item));
+    }
+
+    /// When the next item is due to be delivered, if any are in flight
+    pub fn next_due(&self) -> Option<Instant> {
+        self.in_flight.front().map(|(due, _)| *due)
+    }
+
+    /// Takes the next item if it's due to be delivered
+    pub fn pop_due(&mut self) -> Option<T> {
+        match self.next_due()? <= Instant::now() {
+            true => self.in_flight.pop_front().map(|(_, item)| item),
+            false => None,
+        }
+    }
+
+    /// Returns the number of items in flight
+    pub fn len(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.in_flight.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_delay_line() {
+        let mut line = DelayLine::new(NetworkConditions::default());
+
+        // Without any delay, items are due as soon as they're pushed
+        line.push(1);
+        assert_eq!(line.pop_due(), Some(1));
+        assert!(line.is_empty());
+
+        line.set_conditions(NetworkConditions {
+            delay: Duration::from_millis(100),
+            jitter: Duration::from_millis(50),
+        });
+        for item in [2, 3, 4] {
+            line.push(item);
+        }
+        tokio::time::sleep(Duration::from_millis(99)).await;
+        assert_eq!(line.pop_due(), None);
+
+        // Items are no later than the delay & jitter, & arrive in the order they were pushed
+        tokio::time::sleep(Duration::from_millis(51)).await;
+        assert_eq!(
+            std::iter::from_fn(|| line.pop_due()).collect::<Vec<_>>(),
+            vec![2, 3, 4]
+        );
+
+        // Items in flight are still delivered when they were due once the delay is turned off
+        line.push(5);
+        line.set_conditions(NetworkConditions::default());
+        line.push(6);
+        assert_eq!(line.pop_due(), None);
+        assert_eq!(line.len(), 2);
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(line.pop_due(), Some(5));
+        assert_eq!(line.pop_due(), Some(6));
+    }
+}
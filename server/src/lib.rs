@@ -1,5 +1,26 @@
-mod runner;
-mod scheduler;
+//! Networking around the simulation engine of `scheduler_core`: fanning its events out to
+//! subscribers (encoding each just once), accepting orders over HTTP, exporting traces, reporting
+//! its status, tailing its logs, calling webhooks as orders settle & relaying its events between
+//! servers
 
-pub use runner::CsvRunner;
-pub use scheduler::NaiveScheduler;
+mod broadcast;
+mod encoded;
+mod http;
+mod latency;
+mod logs;
+mod relay;
+mod status;
+mod telemetry;
+mod watch;
+mod webhook;
+
+pub use broadcast::{Broadcaster, ClientIdentity, Progress, SubscriberMetrics};
+pub use encoded::{Encoded, EncodedCodec, EncodedStreams};
+pub use http::{serve_orders, OrderSubmission};
+pub use latency::{DelayLine, NetworkConditions};
+pub use logs::{LogRecord, LogStream, LogTail};
+pub use relay::{Relay, RelayRole};
+pub use status::{serve_status, RecordingLogger, ServerStatus, StatusBoard, SubscriberStatus};
+pub use telemetry::{init_otlp, OtlpSubscriber};
+pub use watch::OrderDirectory;
+pub use webhook::Webhooks;
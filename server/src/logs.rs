@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::SystemTime;
+
+use futures::channel::mpsc;
+use futures::Stream;
+use log::LevelFilter;
+use ulid::Ulid;
+
+/// Records buffered for each client tailing the logs, after which further records are dropped
+/// rather than holding up whatever is logging them
+const MAX_BUFFERED: usize = 1024;
+
+/// A record logged by the server, as sent to the clients tailing its logs
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogRecord {
+    pub time: SystemTime,
+    pub level: log::Level,
+    pub target: String,
+    pub message: String,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+}
+
+impl LogRecord {
+    fn new(record: &log::Record) -> Self {
+        Self {
+            time: SystemTime::now(),
+            level: record.level(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+            file: record.file().map(str::to_string),
+            line: record.line(),
+        }
+    }
+}
+
+/// Fans the records logged by the server out to the clients tailing them, each at its own level.
+/// The process-wide level is raised while a client tails more verbose records than are otherwise
+/// logged, & lowered again once it stops.
+#[derive(Default)]
+pub struct LogTail {
+    /// Most verbose level logged without any clients tailing, see `set_baseline`
+    baseline: AtomicUsize,
+    /// Most verbose level tailed by any client, checked before the clients are locked
+    tailed: AtomicUsize,
+    tailers: Mutex<HashMap<Ulid, Tailer>>,
+}
+
+struct Tailer {
+    level: LevelFilter,
+    /// Prefix of the targets tailed, empty for every target
+    target: String,
+    sender: mpsc::Sender<LogRecord>,
+}
+
+impl LogTail {
+    /// Sets the most verbose level logged without any clients tailing, e.g. as configured by
+    /// `RUST_LOG`
+    pub fn set_baseline(&self, level: LevelFilter) {
+        self.baseline.store(level as usize, Ordering::Relaxed);
+        self.update_max_level();
+    }
+
+    /// Whether any client tails records at the provided level
+    pub fn enabled(&self, level: log::Level) -> bool {
+        level as usize <= self.tailed.load(Ordering::Relaxed)
+    }
+
+    /// Starts tailing the records at or above a level, from targets starting with a prefix. The
+    /// client stops tailing once the stream is dropped.
+    pub fn subscribe(self: &Arc<Self>, level: LevelFilter, target: String) -> LogStream {
+        let id = Ulid::new();
+        let (sender, receiver) = mpsc::channel(MAX_BUFFERED);
+        self.tailers.lock().expect("tailers").insert(
+            id,
+            Tailer {
+                level,
+                target,
+                sender,
+            },
+        );
+        self.update_max_level();
+        LogStream {
+            id,
+            tail: self.clone(),
+            receiver,
+        }
+    }
+
+    /// Sends a record to each client tailing it, faster clients not waiting on slower ones
+    pub fn publish(&self, record: &log::Record) {
+        if !self.enabled(record.level()) {
+            return;
+        }
+        let mut tailed = None;
+        for tailer in self.tailers.lock().expect("tailers").values_mut() {
+            if record.level() <= tailer.level && record.target().starts_with(&tailer.target) {
+                let record = tailed.get_or_insert_with(|| LogRecord::new(record));
+                // Clients which have fallen too far behind miss the record
+                let _ = tailer.sender.try_send(record.clone());
+            }
+        }
+    }
+
+    fn unsubscribe(&self, id: &Ulid) {
+        self.tailers.lock().expect("tailers").remove(id);
+        self.update_max_level();
+    }
+
+    fn update_max_level(&self) {
+        let tailed = self
+            .tailers
+            .lock()
+            .expect("tailers")
+            .values()
+            .map(|tailer| tailer.level as usize)
+            .max()
+            .unwrap_or_default();
+        self.tailed.store(tailed, Ordering::Relaxed);
+        let max_level = tailed.max(self.baseline.load(Ordering::Relaxed));
+        log::set_max_level(
+            LevelFilter::iter()
+                .nth(max_level)
+                .unwrap_or(LevelFilter::Trace),
+        );
+        // Callsites which tracing decided weren't worth recording should be asked again
+        tracing::callsite::rebuild_interest_cache();
+    }
+}
+
+/// Records tailed by a client, see `LogTail::subscribe`
+pub struct LogStream {
+    id: Ulid,
+    tail: Arc<LogTail>,
+    receiver: mpsc::Receiver<LogRecord>,
+}
+
+impl Stream for LogStream {
+    type Item = LogRecord;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.receiver).poll_next(cx)
+    }
+}
+
+impl Drop for LogStream {
+    fn drop(&mut self) {
+        self.tail.unsubscribe(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::{FutureExt, StreamExt};
+
+    use super::*;
+
+    fn record(level: log::Level, target: &str, message: &str) -> LogRecord {
+        LogRecord {
+            time: SystemTime::UNIX_EPOCH,
+            level,
+            target: target.to_string(),
+            message: message.to_string(),
+            file: None,
+            line: None,
+        }
+    }
+
+    fn publish(tail: &LogTail, record: &LogRecord) {
+        tail.publish(
+            &log::Record::builder()
+                .level(record.level)
+                .target(&record.target)
+                .args(format_args!("{}", record.message))
+                .build(),
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tail() {
+        let tail = Arc::new(LogTail::default());
+        tail.set_baseline(LevelFilter::Warn);
+        assert!(!tail.enabled(log::Level::Error));
+
+        let mut everything = tail.subscribe(LevelFilter::Debug, String::new());
+        let mut scheduling = tail.subscribe(LevelFilter::Trace, "scheduler_core".to_string());
+        assert!(tail.enabled(log::Level::Trace));
+        assert_eq!(log::max_level(), LevelFilter::Trace);
+
+        // Each client gets the records at its level & from its targets
+        let records = [
+            record(log::Level::Info, "server", "<listening>"),
+            record(log::Level::Trace, "server", "<polled>"),
+            record(log::Level::Trace, "scheduler_core::scheduler", "<packed>"),
+        ];
+        for record in &records {
+            publish(&tail, record);
+        }
+        let message = |record: Option<LogRecord>| record.expect("record").message;
+        assert_eq!(message(everything.next().await), "<listening>");
+        assert_eq!(message(scheduling.next().await), "<packed>");
+
+        // The level falls back once the more verbose client stops tailing
+        drop(scheduling);
+        assert!(!tail.enabled(log::Level::Trace));
+        assert_eq!(log::max_level(), LevelFilter::Debug);
+        drop(everything);
+        assert_eq!(log::max_level(), LevelFilter::Warn);
+
+        // Clients which have fallen behind miss records, rather than holding up the logger
+        let mut slow = tail.subscribe(LevelFilter::Info, String::new());
+        for _ in 0..MAX_BUFFERED * 2 {
+            publish(&tail, &records[0]);
+        }
+        let mut received = 0;
+        while let Some(Some(_)) = slow.next().now_or_never() {
+            received += 1;
+        }
+        assert!((MAX_BUFFERED..=MAX_BUFFERED + 1).contains(&received));
+    }
+}
@@ -1,125 +1,1887 @@
-use std::collections::HashMap;
 use std::env;
+use std::num::{NonZeroU32, NonZeroU64};
+use std::panic::AssertUnwindSafe;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
-use futures::channel::mpsc;
-use futures::{Stream, StreamExt};
+use futures::channel::{mpsc, oneshot};
+use futures::future::{self, Either};
+use futures::{FutureExt, Stream, StreamExt};
+use schema::journal::{JournalWriter, Retention, SegmentedJournal};
+use schema::proto::server as proto;
 use schema::proto::server::server_server::{Server, ServerServer};
-use schema::{Speed, StatusUpdate, ToFromProto};
+use schema::units::Units;
+use schema::{
+    time, Alert, AlertKind, ClientConfig, DemandPrior, Destination, DestinationChanges,
+    DestinationMatcher, DestinationNames, EnergyModel, FlightExplanation, FlightId, FlightPhases,
+    Matching, MonitorEvent, OrderId, OrderReceipt, RangeReduction, ReturnPolicy, Sector,
+    ServiceTime, Speed, SpeedSchedule, StatusUpdate, ToFromProto,
+};
+use tokio_stream::wrappers::IntervalStream;
 use tonic::transport::Server as TonicServer;
 use tonic::{Response, Status};
 use ulid::Ulid;
 
-use server::CsvRunner;
+use scheduler_core::{
+    AlertRule, Checkpoint, Command, CsvRunner, GroundFleet, HistoricalAverage, PackingStrategy,
+    RoutePlanner, RunReport, SchedulerKind, SettledOrder, Submitted, SummaryFormat, SurgePolicy,
+};
+use server::{
+    Broadcaster, ClientIdentity, DelayLine, Encoded, EncodedStreams, LogRecord, LogTail,
+    NetworkConditions, OrderDirectory, OrderSubmission, Progress, RecordingLogger, Relay,
+    RelayRole, StatusBoard, SubscriberMetrics, SubscriberStatus, Webhooks,
+};
 
 // TODO: name server proto something other than "server", as it gets confusing here
 #[tokio::main]
 pub async fn main() -> Result<(), Box<dyn std::error::Error>> {
     dotenv::dotenv().ok();
-    env_logger::init();
+    let status = Arc::new(StatusBoard::default());
+    let logs = Arc::new(LogTail::default());
+    RecordingLogger::init(status.clone(), logs.clone())?;
+    if let Ok(endpoint) = env::var("OTEL_EXPORTER_OTLP_ENDPOINT") {
+        let service_name = env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| "scheduler".into());
+        server::init_otlp(&endpoint, &service_name).map_err(|e| e.to_string())?;
+    }
 
     let addr = env::var("SERVER_SOCKET")
         .unwrap_or_else(|_| "0.0.0.0:50051".to_string())
         .parse()?;
+    let orders_http_addr = env::var("ORDERS_HTTP_SOCKET")
+        .ok()
+        .map(|addr| addr.parse())
+        .transpose()?;
+    let status_http_addr = env::var("STATUS_HTTP_SOCKET")
+        .ok()
+        .map(|addr| addr.parse())
+        .transpose()?;
+    let orders_watch_dir = env::var("ORDERS_WATCH_DIR").ok();
+    let orders_watch_interval = match env::var("ORDERS_WATCH_INTERVAL_S") {
+        Ok(seconds) => Duration::from_secs(seconds.parse()?),
+        Err(_) => Duration::from_secs(5),
+    };
+    let mut scenario = Scenario::from_env()?;
+    if let Some(path) = &scenario.demand_priors_csv_path {
+        scenario.demand_priors = DemandPrior::from_csv(path)
+            .await
+            .map_err(|e| format!("failed to load demand priors: {}", e))?;
+    }
+    let scenario = Arc::new(scenario);
+    let destination_names = scenario.destination_names.clone();
+    let liveness = Liveness::from_env();
+    let webhooks = Webhooks::from_env()?;
+    let relay = Relay::from_env()?;
+    let following = relay
+        .as_ref()
+        .is_some_and(|relay| relay.role() == RelayRole::Follower);
+
+    let (events_sender, events_receiver) = mpsc::unbounded();
+    let (relay_sender, relay_receiver) = mpsc::unbounded();
+    let (registry_sender, registry_receiver) = mpsc::unbounded();
+    let (network_sender, network_receiver) = mpsc::unbounded();
+    let (controls_sender, controls_receiver) = mpsc::unbounded();
+    let (submissions_sender, submissions_receiver) = mpsc::unbounded::<OrderSubmission>();
+    let controls_receiver = futures::stream::select(
+        controls_receiver,
+        submissions_receiver.map(|(orders, key, reply)| Control::SubmitOrders(orders, key, reply)),
+    );
+    // Monitor streams are served from events encoded once for all of their subscribers
+    let server = EncodedStreams::new(
+        ServerServer::new(ServerService {
+            registry: registry_sender.clone(),
+            controls_sender,
+            network: network_sender.clone(),
+            scenario: scenario.clone(),
+            logs,
+        }),
+        "/server.Server/Monitor",
+        MonitorStreams {
+            registry: registry_sender.clone(),
+        },
+    );
+
+    let registry = SubscriptionRegistry::new(
+        liveness,
+        client_config_from_env()?,
+        destination_names.clone(),
+        status.clone(),
+    );
+    let broadcaster = EventBroadcaster {
+        journal: Journal::from_env()?,
+        network: network_sender,
+        status: status.clone(),
+        relay: (relay.is_some() && !following).then_some(relay_sender),
+    };
+    let network = NetworkSimulator::new(NetworkConditions::from_env(), registry_sender);
+    let (settled_sender, settled_receiver) = mpsc::unbounded();
+    let supervisor = RunnerSupervisor::new(
+        scenario,
+        events_sender.clone(),
+        webhooks.is_some().then_some(settled_sender),
+    );
+
+    tracing::info!("running server on {}", addr);
+
+    // Orders may optionally be dropped into a directory as files too
+    let watch_orders = {
+        let submissions = submissions_sender.clone();
+        let destination_names = destination_names.clone();
+        async move {
+            if let Some(dir) = orders_watch_dir {
+                OrderDirectory::new(dir, destination_names)
+                    .watch(orders_watch_interval, submissions)
+                    .await;
+            }
+        }
+    };
+
+    // Orders may optionally be submitted over plain HTTP as well as gRPC
+    let serve_orders = async move {
+        match orders_http_addr {
+            Some(addr) => {
+                if let Err(e) =
+                    server::serve_orders(addr, submissions_sender, destination_names).await
+                {
+                    tracing::error!("orders endpoint failed: {}", e);
+                }
+            }
+            None => drop(submissions_sender),
+        }
+    };
+
+    // The server's state may optionally be checked on at a status page
+    let serve_status = async move {
+        if let Some(addr) = status_http_addr {
+            if let Err(e) = server::serve_status(addr, status).await {
+                tracing::error!("status page failed: {}", e);
+            }
+        }
+    };
+
+    // Other systems may optionally be told as orders are delivered, fail or are cancelled
+    let call_webhooks = async move {
+        if let Some(webhooks) = webhooks {
+            webhooks.deliver(settled_receiver).await;
+        }
+    };
+
+    // The simulation is run here, unless it's followed from another server over the relay, in
+    // which case its events are passed on as they would be from the simulation
+    let run_simulation = async move {
+        match relay {
+            Some(relay) if following => {
+                tracing::info!("following the simulation relayed through {}", relay.addr());
+                let (followed_sender, followed_receiver) = mpsc::unbounded();
+                let events = followed_receiver
+                    .filter_map(|message| future::ready(relayed_event(message).map(Ok)))
+                    .forward(events_sender);
+                let _ = futures::join!(
+                    relay.follow(followed_sender),
+                    events,
+                    refuse_controls(controls_receiver)
+                );
+            }
+            Some(relay) => {
+                drop(events_sender);
+                futures::join!(
+                    relay.publish(relay_receiver),
+                    supervisor.run(controls_receiver)
+                );
+            }
+            None => {
+                drop(events_sender);
+                supervisor.run(controls_receiver).await
+            }
+        }
+    };
+
+    let _ = futures::join!(
+        TonicServer::builder()
+            // Also close connections to clients which have vanished without any streams open
+            .http2_keepalive_interval(Some(liveness.heartbeat_interval))
+            .http2_keepalive_timeout(Some(liveness.idle_timeout))
+            .add_service(server)
+            .serve(addr),
+        registry.run(registry_receiver),
+        broadcaster.run(events_receiver),
+        network.run(network_receiver),
+        serve_orders,
+        watch_orders,
+        serve_status,
+        call_webhooks,
+        run_simulation
+    );
 
-    let mut runner = CsvRunner::from_csv_paths(
-        schema::SAMPLE_DESTINATIONS_CSV_PATH,
-        schema::SAMPLE_ORDERS_CSV_PATH,
-    )?
-    .with_speed(Speed::fast_forward(200).expect("speed")); // run demo in fast-forward
-    let subscriptions = HashMap::<Ulid, mpsc::UnboundedSender<StatusUpdate>>::new();
-    let updates = runner.stream_updates().expect("update stream");
-    let (subscriptions_sender, subscriptions_receiver) = mpsc::unbounded();
-    let server = ServerServer::new(ServerService {
-        subscriptions_sender,
-    });
-
-    #[derive(Debug)]
-    enum Event {
-        Update(StatusUpdate),
-        NewSubscription(Ulid, mpsc::UnboundedSender<StatusUpdate>),
-    }
-
-    let updates = updates.map(Event::Update).boxed();
-    let new_subscriptions = subscriptions_receiver
-        .map(|(ulid, tx)| Event::NewSubscription(ulid, tx))
-        .boxed();
-
-    let event_stream = futures::stream::select_all(vec![updates, new_subscriptions]).fuse();
-    let stream_process = event_stream
-        .scan(subscriptions, |subscriptions, event| {
-            log::info!("processing event");
-            let fut = match event {
-                // Send each update to all of the subscribers
+    Ok(())
+}
+
+/// Events from the simulation, for the `EventBroadcaster` to send to subscribers
+#[derive(Debug)]
+enum Event {
+    /// A new simulation has started with the provided id
+    Started(Ulid),
+    Update(StatusUpdate),
+    Reset,
+    Alert(Alert),
+    DestinationChanges(DestinationChanges),
+}
+
+#[derive(Debug)]
+enum Subscription {
+    /// Along with the most status updates to send the subscriber each second, if it's limited, &
+    /// who the client said it was
+    Monitor(
+        mpsc::UnboundedSender<Arc<Encoded>>,
+        Progress,
+        Option<NonZeroU32>,
+        Option<ClientIdentity>,
+    ),
+    Alerts(mpsc::UnboundedSender<Arc<proto::Alert>>, Progress),
+}
+
+/// Messages handled by the `SubscriptionRegistry`
+#[derive(Debug)]
+enum RegistryMessage {
+    /// Track a new subscriber, catching it up on the events it should replay
+    Subscribe(Ulid, Subscription),
+    /// Send an event to all of the monitor subscribers
+    Monitor(Encoded, Replay),
+    /// Send a status update to all of the monitor subscribers, coalescing those sent faster than
+    /// a subscriber asked for
+    Update(Encoded),
+    /// Send an alert to all of the alert subscribers
+    Alert(proto::Alert),
+    /// Drop subscribers which have stopped taking events, & ping the rest
+    Heartbeat,
+    /// Send the status updates held back from subscribers which are now due them
+    Flush,
+    /// Report on each subscriber of either stream
+    ListSubscribers(oneshot::Sender<Vec<SubscriberStatus>>),
+}
+
+/// Whether a monitor event is replayed to later subscribers
+#[derive(Debug)]
+enum Replay {
+    /// Replay the event to every later subscriber during the current run
+    Keep,
+    /// Forget the events kept so far, e.g. as the simulation has been reset
+    Clear,
+}
+
+/// How often status updates held back from throttled monitor subscribers are checked for being due
+const FLUSH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Keeps track of the connected subscribers of each stream, sending each event it's passed on to
+/// them & removing those which stop taking events
+struct SubscriptionRegistry {
+    monitors: Broadcaster<Encoded>,
+    alerts: Broadcaster<proto::Alert>,
+    /// Events during the current run which are replayed to new monitor subscribers (e.g. changes
+    /// made to the destinations, so that their maps match the simulation)
+    replay: Vec<Encoded>,
+    /// Sent to each monitor subscriber before any other event, along with the names of the
+    /// simulation's destinations
+    client_config: ClientConfig,
+    destination_names: DestinationNames,
+    /// Sent to each monitor subscriber every heartbeat
+    heartbeat: Encoded,
+    liveness: Liveness,
+    status: Arc<StatusBoard>,
+}
+
+impl SubscriptionRegistry {
+    fn new(
+        liveness: Liveness,
+        client_config: ClientConfig,
+        destination_names: DestinationNames,
+        status: Arc<StatusBoard>,
+    ) -> Self {
+        Self {
+            monitors: Broadcaster::default(),
+            alerts: Broadcaster::default(),
+            replay: vec![],
+            client_config,
+            destination_names,
+            heartbeat: encode(MonitorEvent::Heartbeat),
+            liveness,
+            status,
+        }
+    }
+
+    /// Handles messages until every sender has gone away, with a heartbeat every interval
+    async fn run(mut self, messages: impl Stream<Item = RegistryMessage> + Unpin) {
+        let heartbeats =
+            IntervalStream::new(tokio::time::interval(self.liveness.heartbeat_interval))
+                .map(|_| RegistryMessage::Heartbeat);
+        let flushes = IntervalStream::new(tokio::time::interval(FLUSH_INTERVAL))
+            .map(|_| RegistryMessage::Flush);
+        let mut messages =
+            futures::stream::select(messages, futures::stream::select(heartbeats, flushes));
+        while let Some(message) = messages.next().await {
+            self.handle(message);
+            let subscribers = self.subscribers();
+            self.status.set_subscribers(subscribers);
+        }
+    }
+
+    /// Each subscriber of either stream, for the status page
+    fn subscribers(&mut self) -> Vec<SubscriberStatus> {
+        let status = |stream| {
+            move |metrics: SubscriberMetrics| SubscriberStatus {
+                id: metrics.id,
+                stream,
+                client: metrics.client,
+                connected: metrics.connected,
+                waiting: metrics.waiting,
+                lag: metrics.lag,
+                dropped: metrics.dropped,
+            }
+        };
+        let monitors = self.monitors.metrics().map(status("monitor"));
+        let alerts = self.alerts.metrics().map(status("alerts"));
+        monitors.chain(alerts).collect()
+    }
+
+    fn handle(&mut self, message: RegistryMessage) {
+        match message {
+            RegistryMessage::Subscribe(
+                id,
+                Subscription::Monitor(tx, progress, max_rate, client),
+            ) => {
+                self.monitors.subscribe(id, tx, progress);
+                if let Some(max_rate) = max_rate {
+                    self.monitors.throttle(&id, max_rate);
+                }
+                if let Some(client) = client {
+                    self.monitors.identify(&id, client);
+                }
+                let config = ClientConfig {
+                    destination_names: self.destination_names.names(),
+                    ..self.client_config.clone()
+                };
+                self.monitors
+                    .send_to(&id, encode(MonitorEvent::ClientConfig(config)));
+                for event in &self.replay {
+                    self.monitors.send_to(&id, event.clone());
+                }
+            }
+            RegistryMessage::Subscribe(id, Subscription::Alerts(tx, progress)) => {
+                self.alerts.subscribe(id, tx, progress);
+            }
+            RegistryMessage::Monitor(event, replay) => {
+                match replay {
+                    Replay::Keep => self.replay.push(event.clone()),
+                    Replay::Clear => self.replay.clear(),
+                }
+                self.monitors.send(event);
+            }
+            RegistryMessage::Update(event) => self.monitors.send_latest(event),
+            RegistryMessage::Alert(alert) => self.alerts.send(alert),
+            RegistryMessage::Heartbeat => self.heartbeat(),
+            RegistryMessage::Flush => {
+                if self.monitors.has_pending() {
+                    self.monitors.flush_due();
+                }
+            }
+            RegistryMessage::ListSubscribers(tx) => {
+                let _ = tx.send(self.subscribers());
+            }
+        }
+    }
+
+    /// Drops subscribers which have stopped taking events, & keeps the rest of the monitor
+    /// streams busy so that any which vanish are noticed
+    fn heartbeat(&mut self) {
+        let idle_timeout = self.liveness.idle_timeout;
+        let removed =
+            self.monitors.remove_stale(idle_timeout) + self.alerts.remove_stale(idle_timeout);
+        if removed > 0 {
+            tracing::info!(
+                "removed {} stale subscriptions ({} monitor & {} alerts removed in total)",
+                removed,
+                self.monitors.stale_removed(),
+                self.alerts.stale_removed()
+            );
+        }
+        tracing::debug!(
+            "{} monitor & {} alerts subscriptions",
+            self.monitors.len(),
+            self.alerts.len()
+        );
+        self.monitors.send(self.heartbeat.clone());
+    }
+}
+
+/// Converts each event from the simulation to its proto & encodes it just once, however many
+/// subscribers there are, passing it along (through the `NetworkSimulator`) to the `SubscriptionRegistry` to be sent
+/// to them
+struct EventBroadcaster {
+    /// Where the monitor stream is also recorded for playback, if anywhere
+    journal: Option<Journal>,
+    network: mpsc::UnboundedSender<NetworkMessage>,
+    status: Arc<StatusBoard>,
+    /// Where events are published for servers following this one, if any are
+    relay: Option<mpsc::UnboundedSender<proto::RelayMessage>>,
+}
+
+impl EventBroadcaster {
+    async fn run(mut self, mut events: impl Stream<Item = Event> + Unpin) {
+        while let Some(event) = events.next().await {
+            let message = match event {
                 Event::Update(update) => {
-                    let mut disconnected = vec![];
-                    for (id, tx) in subscriptions.iter() {
-                        match tx.clone().start_send(update.clone()) {
-                            Err(e) if e.is_disconnected() => {
-                                disconnected.push(*id);
-                            }
-                            _ => {}
+                    self.status.observe_update(&update);
+                    RegistryMessage::Update(self.record(MonitorEvent::StatusUpdate(update)))
+                }
+                // Subscribers joining part way through a run still learn which one it is
+                Event::Started(id) => {
+                    self.status.simulation_started(id);
+                    self.monitor_event(MonitorEvent::SimulationStarted(id), Replay::Keep)
+                }
+                // Let subscribers know that they should discard their current state
+                Event::Reset => {
+                    self.status.simulation_reset();
+                    self.monitor_event(MonitorEvent::SimulationReset, Replay::Clear)
+                }
+                Event::DestinationChanges(changes) => {
+                    self.monitor_event(MonitorEvent::DestinationChanges(changes), Replay::Keep)
+                }
+                Event::Alert(alert) => {
+                    let alert = alert.into_proto();
+                    self.relay(proto::relay_message::Message::Alert(alert.clone()));
+                    RegistryMessage::Alert(alert)
+                }
+            };
+            if self
+                .network
+                .unbounded_send(NetworkMessage::Deliver(message))
+                .is_err()
+            {
+                return;
+            }
+        }
+    }
+
+    /// Converts an event for the monitor subscribers, recording it in the journal
+    fn monitor_event(&mut self, event: MonitorEvent, replay: Replay) -> RegistryMessage {
+        RegistryMessage::Monitor(self.record(event), replay)
+    }
+
+    /// Encodes a monitor event, recording it in the journal
+    fn record(&mut self, event: MonitorEvent) -> Encoded {
+        let event: proto::MonitorEvent = event.into_proto();
+        if let Some(journal) = self.journal.as_mut() {
+            if let Err(e) = journal.append(&event) {
+                tracing::error!("failed to write journal, no longer recording: {}", e);
+                self.journal = None;
+            }
+        }
+        let encoded = Encoded::new(&event);
+        self.relay(proto::relay_message::Message::MonitorEvent(event));
+        encoded
+    }
+
+    /// Publishes an event for the servers following this one, if any are
+    fn relay(&mut self, message: proto::relay_message::Message) {
+        if let Some(relay) = &self.relay {
+            let message = proto::RelayMessage {
+                message: Some(message),
+            };
+            if relay.unbounded_send(message).is_err() {
+                tracing::error!("relay stopped, no longer publishing events");
+                self.relay = None;
+            }
+        }
+    }
+}
+
+/// Where the monitor stream is recorded for playback
+enum Journal {
+    File(JournalWriter<std::io::BufWriter<std::fs::File>>),
+    /// A directory of segments, compacted & removed as they age
+    Segmented(Box<SegmentedJournal>),
+}
+
+impl Journal {
+    /// Records to `JOURNAL_PATH` if it's set, as a directory of segments if any of
+    /// `JOURNAL_SEGMENT_MB`, `JOURNAL_SEGMENT_MINUTES`, `JOURNAL_COMPACT_AFTER_H`,
+    /// `JOURNAL_KEYFRAME_INTERVAL_S`, `JOURNAL_MAX_MB` or `JOURNAL_MAX_AGE_H` are set too
+    fn from_env() -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        let Ok(path) = env::var("JOURNAL_PATH") else {
+            return Ok(None);
+        };
+        let mut segmented = false;
+        let mut number = |name: &str| -> Result<Option<u64>, String> {
+            let Ok(value) = env::var(name) else {
+                return Ok(None);
+            };
+            segmented = true;
+            value
+                .parse()
+                .map(Some)
+                .map_err(|_| format!("invalid {}: {}", name, value))
+        };
+        let hours = |hours: u64| Duration::from_secs(hours * 60 * 60);
+
+        let defaults = Retention::default();
+        let retention = Retention {
+            segment_bytes: number("JOURNAL_SEGMENT_MB")?
+                .map_or(defaults.segment_bytes, |mb| mb * 1024 * 1024),
+            segment_age: number("JOURNAL_SEGMENT_MINUTES")?
+                .map_or(defaults.segment_age, |minutes| {
+                    Duration::from_secs(minutes * 60)
+                }),
+            compact_after: number("JOURNAL_COMPACT_AFTER_H")?.map(hours),
+            keyframe_interval_s: number("JOURNAL_KEYFRAME_INTERVAL_S")?
+                .unwrap_or(defaults.keyframe_interval_s),
+            max_bytes: number("JOURNAL_MAX_MB")?.map(|mb| mb * 1024 * 1024),
+            max_age: number("JOURNAL_MAX_AGE_H")?.map(hours),
+        };
+        Ok(Some(if segmented {
+            Self::Segmented(Box::new(SegmentedJournal::open(path, retention)?))
+        } else {
+            Self::File(JournalWriter::create(&path)?)
+        }))
+    }
+
+    fn append(&mut self, event: &proto::MonitorEvent) -> std::io::Result<()> {
+        match self {
+            Self::File(journal) => journal.append(event),
+            Self::Segmented(journal) => journal.append(event),
+        }
+    }
+}
+
+/// Converts an event relayed from the leader back to the event from its simulation, if it's valid
+fn relayed_event(message: proto::RelayMessage) -> Option<Event> {
+    let event = match message.message? {
+        proto::relay_message::Message::MonitorEvent(event) => MonitorEvent::try_from_proto(event)
+            .map(|event| match event {
+                MonitorEvent::StatusUpdate(update) => Some(Event::Update(update)),
+                MonitorEvent::SimulationStarted(id) => Some(Event::Started(id)),
+                MonitorEvent::SimulationReset => Some(Event::Reset),
+                MonitorEvent::DestinationChanges(changes) => {
+                    Some(Event::DestinationChanges(changes))
+                }
+                // Each server sends its own subscribers these
+                MonitorEvent::Heartbeat | MonitorEvent::ClientConfig(_) => None,
+            }),
+        proto::relay_message::Message::Alert(alert) => {
+            Alert::try_from_proto(alert).map(|alert| Some(Event::Alert(alert)))
+        }
+    };
+    event.unwrap_or_else(|e| {
+        tracing::warn!("ignoring invalid relayed event: {}", e);
+        None
+    })
+}
+
+/// Encodes a monitor event to be sent to subscribers as it is
+fn encode(event: MonitorEvent) -> Encoded {
+    let event: proto::MonitorEvent = event.into_proto();
+    Encoded::new(&event)
+}
+
+/// Messages handled by the `NetworkSimulator`
+#[derive(Debug)]
+enum NetworkMessage {
+    /// Pass a message on to the `SubscriptionRegistry` once the simulated delay has passed
+    Deliver(RegistryMessage),
+    /// Simulate different conditions for the messages passed on from now on
+    SetConditions(NetworkConditions),
+}
+
+/// Simulates a laggy network between the `EventBroadcaster` & the `SubscriptionRegistry`, holding
+/// events back for a while before they're sent to subscribers (e.g. to test how clients cope with
+/// updates arriving late & unevenly). Messages are passed straight on while no delay is simulated.
+struct NetworkSimulator {
+    line: DelayLine<RegistryMessage>,
+    registry: mpsc::UnboundedSender<RegistryMessage>,
+}
+
+impl NetworkSimulator {
+    fn new(
+        conditions: NetworkConditions,
+        registry: mpsc::UnboundedSender<RegistryMessage>,
+    ) -> Self {
+        if conditions.is_enabled() {
+            tracing::info!("simulating network conditions: {:?}", conditions);
+        }
+        Self {
+            line: DelayLine::new(conditions),
+            registry,
+        }
+    }
+
+    /// Handles messages until every sender has gone away, passing on each as it's due
+    async fn run(mut self, mut messages: impl Stream<Item = NetworkMessage> + Unpin) {
+        loop {
+            let next = messages.next();
+            let message = match self.line.next_due() {
+                Some(due) => match tokio::time::timeout_at(due, next).await {
+                    Ok(message) => message,
+                    Err(_) => {
+                        if !self.deliver_due() {
+                            return;
                         }
+                        continue;
                     }
+                },
+                None => next.await,
+            };
+
+            match message {
+                Some(NetworkMessage::Deliver(message)) => self.line.push(message),
+                Some(NetworkMessage::SetConditions(conditions)) => {
+                    tracing::info!(
+                        "simulating network conditions: {:?} ({} messages in flight)",
+                        conditions,
+                        self.line.len()
+                    );
+                    self.line.set_conditions(conditions);
+                }
+                None => return,
+            }
+            if !self.deliver_due() {
+                return;
+            }
+        }
+    }
+
+    /// Passes on the messages which are due, returning whether the registry is still running
+    fn deliver_due(&mut self) -> bool {
+        while let Some(message) = self.line.pop_due() {
+            if self.registry.unbounded_send(message).is_err() {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// How stale subscriptions are detected
+#[derive(Clone, Copy)]
+struct Liveness {
+    /// Interval between heartbeats on the monitor streams
+    heartbeat_interval: Duration,
+    /// How long a subscriber may have events waiting without taking any before it's removed
+    idle_timeout: Duration,
+}
+
+impl Liveness {
+    fn from_env() -> Self {
+        let seconds = |name: &str, default: u64| {
+            Duration::from_secs(
+                env::var(name)
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .filter(|seconds| *seconds > 0)
+                    .unwrap_or(default),
+            )
+        };
+
+        Self {
+            heartbeat_interval: seconds("HEARTBEAT_INTERVAL_S", 10),
+            idle_timeout: seconds("SUBSCRIBER_IDLE_TIMEOUT_S", 60),
+        }
+    }
+}
+
+/// Reads the settings pushed to monitoring clients from `CLIENT_MAP_EXTENTS` (as
+/// `west_m, north_m, east_m, south_m`), `CLIENT_UNITS`, `CLIENT_THEME` & `CLIENT_FEATURES`
+/// (comma-separated), leaving it to each client where they aren't set
+fn client_config_from_env() -> Result<ClientConfig, String> {
+    let parsed = |name: &str| env::var(name).ok().filter(|value| !value.is_empty());
+
+    Ok(ClientConfig {
+        extents: parsed("CLIENT_MAP_EXTENTS")
+            .map(|extents| extents.parse())
+            .transpose()?,
+        units: parsed("CLIENT_UNITS")
+            .map(|units| units.parse())
+            .transpose()?,
+        theme: parsed("CLIENT_THEME")
+            .map(|theme| theme.parse())
+            .transpose()?,
+        features: parsed("CLIENT_FEATURES")
+            .map(|features| {
+                features
+                    .split(',')
+                    .map(|feature| feature.trim().to_string())
+                    .filter(|feature| !feature.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default(),
+        // Filled in from the simulation as each subscriber subscribes
+        destination_names: vec![],
+    })
+}
+
+/// CSV paths for the scenario which will be simulated (and reloaded on reset)
+struct Scenario {
+    destinations_csv_path: String,
+    orders_csv_path: String,
+    /// Nest which carriers launch from & return to
+    origin: Destination,
+    /// Conditions reducing the range of carriers during the run, if any
+    range_reductions_csv_path: Option<String>,
+    /// Time carriers take to take off & land at each stop
+    flight_phases: FlightPhases,
+    /// Decides which carrier each order is packed into
+    packing: Arc<dyn PackingStrategy>,
+    /// Decides the order each flight visits its stops in
+    route_planner: RoutePlanner,
+    /// Where carriers go once they've made their last stop
+    return_policy: ReturnPolicy,
+    /// Speeds to run at through each simulated day, in place of the usual fast-forward, if any
+    speed_schedule: Option<SpeedSchedule>,
+    /// Seconds after which the resupplies of destinations starved of flights are boosted, if any
+    fairness_s: Option<NonZeroU64>,
+    /// Estimates the energy carriers spend on their flights
+    energy: EnergyModel,
+    /// Side in meters of the grid cells carriers are kept separated by, if they are
+    separation_m: Option<NonZeroU64>,
+    /// Seconds after which emergency flights awaiting approval are approved automatically, if
+    /// they need approval at all
+    approval_timeout_s: Option<NonZeroU64>,
+    /// Sectors of airspace which only so many carriers may fly in at once, if any
+    sectors_csv_path: Option<String>,
+    /// Emergency arrival rate at which every carrier is given over to emergencies, if any
+    surge: Option<SurgePolicy>,
+    /// Conditions raising alerts once they've held for long enough
+    alert_rules: Vec<AlertRule>,
+    /// Ground vehicles making deliveries alongside the carriers
+    ground: GroundFleet,
+    /// Longest gap in minutes between orders, beyond which the rest is skipped, if any is
+    compress_gaps_minutes: Option<NonZeroU32>,
+    /// Aliases for the destinations named in orders, if any
+    destination_aliases_csv_path: Option<String>,
+    /// How loosely the destinations named in orders are matched
+    matching: Matching,
+    /// Destinations of the simulation being run, against which the names of orders submitted from
+    /// outside are resolved as they arrive
+    destination_names: DestinationNames,
+    /// Where to write the JSON report for each completed run, if anywhere.
+    /// Per-carrier statistics are also written alongside as CSV.
+    report_path: Option<String>,
+    /// Where to write a summary of each completed run, if anywhere, & in which format
+    summary: Option<(String, SummaryFormat)>,
+    /// Units the summary is written in
+    units: Units,
+    /// Whether to reserve carriers using a forecast of demand based on the previous runs
+    forecasting: bool,
+    /// Demand expected for each destination, which forecasts start from, if any
+    demand_priors_csv_path: Option<String>,
+    /// Priors loaded from `demand_priors_csv_path`
+    demand_priors: Vec<DemandPrior>,
+    /// Limit on the status updates emitted each second, regardless of speed
+    max_updates_per_second: NonZeroU32,
+    /// Whether to resume the simulation from its last checkpoint if it faults, rather than
+    /// holding it until a reset
+    restart_on_fault: bool,
+}
 
-                    // Remove any disconnected subscribers
-                    for id in disconnected {
-                        subscriptions.remove(&id);
+impl Scenario {
+    fn from_env() -> Result<Self, String> {
+        let number = |var: &str| match env::var(var) {
+            Ok(value) => value
+                .parse::<u64>()
+                .map_err(|_| format!("invalid {}: {}", var, value)),
+            Err(_) => Ok(0),
+        };
+
+        Ok(Self {
+            destinations_csv_path: env::var("DESTINATIONS_CSV_PATH")
+                .unwrap_or_else(|_| schema::SAMPLE_DESTINATIONS_CSV_PATH.to_string()),
+            orders_csv_path: env::var("ORDERS_CSV_PATH")
+                .unwrap_or_else(|_| schema::SAMPLE_ORDERS_CSV_PATH.to_string()),
+            origin: match env::var("ORIGIN") {
+                Ok(origin) => origin.parse()?,
+                Err(_) => Destination::default_origin(),
+            },
+            range_reductions_csv_path: env::var("RANGE_REDUCTIONS_CSV_PATH").ok(),
+            flight_phases: FlightPhases {
+                takeoff_s: number("TAKEOFF_S")?,
+                landing_s: number("LANDING_S")?,
+                accelerate_s: number("ACCELERATE_S")?,
+                decelerate_s: number("DECELERATE_S")?,
+                service: match env::var("SERVICE_S") {
+                    Ok(service) => service.parse()?,
+                    Err(_) => ServiceTime::default(),
+                },
+            },
+            packing: scheduler_core::packing_strategy(
+                &env::var("PACKING").unwrap_or_else(|_| "priority".to_string()),
+            )?,
+            route_planner: match env::var("ROUTE_PLANNER") {
+                Ok(planner) => planner.parse()?,
+                Err(_) => RoutePlanner::default(),
+            },
+            return_policy: match env::var("RETURN_POLICY") {
+                Ok(policy) => policy.parse()?,
+                Err(_) => ReturnPolicy::default(),
+            },
+            speed_schedule: env::var("SPEED_SCHEDULE")
+                .ok()
+                .map(|schedule| schedule.parse())
+                .transpose()?,
+            fairness_s: NonZeroU64::new(number("FAIRNESS_S")?),
+            energy: match env::var("ENERGY_MODEL") {
+                Ok(energy) => energy.parse()?,
+                Err(_) => EnergyModel::default(),
+            },
+            separation_m: NonZeroU64::new(number("SEPARATION_M")?),
+            approval_timeout_s: NonZeroU64::new(number("APPROVAL_TIMEOUT_S")?),
+            sectors_csv_path: env::var("SECTORS_CSV_PATH").ok(),
+            surge: env::var("SURGE")
+                .ok()
+                .map(|policy| policy.parse())
+                .transpose()?,
+            alert_rules: match env::var("ALERT_RULES") {
+                Ok(rules) => AlertRule::parse_all(&rules)?,
+                Err(_) => vec![],
+            },
+            ground: match env::var("GROUND_FLEET") {
+                Ok(ground) => ground.parse()?,
+                Err(_) => GroundFleet::default(),
+            },
+            compress_gaps_minutes: NonZeroU32::new(number("COMPRESS_GAPS_MINUTES")? as u32),
+            destination_aliases_csv_path: env::var("DESTINATION_ALIASES_CSV_PATH").ok(),
+            matching: match env::var("DESTINATION_MATCHING") {
+                Ok(matching) => matching.parse()?,
+                Err(_) => Matching::Exact,
+            },
+            destination_names: DestinationNames::default(),
+            report_path: env::var("REPORT_PATH").ok(),
+            summary: env::var("SUMMARY_PATH")
+                .ok()
+                .map(|path| SummaryFormat::from_path(&path).map(|format| (path, format)))
+                .transpose()?,
+            units: match env::var("UNITS") {
+                Ok(units) => units.parse()?,
+                Err(_) => Units::default(),
+            },
+            forecasting: env::var("FORECASTING").is_ok_and(|v| v == "1" || v == "true"),
+            demand_priors_csv_path: env::var("DEMAND_PRIORS_CSV_PATH").ok(),
+            demand_priors: vec![],
+            max_updates_per_second: env::var("MAX_UPDATES_PER_SECOND")
+                .ok()
+                .and_then(|rate| rate.parse().ok())
+                .or(NonZeroU32::new(
+                    scheduler_core::DEFAULT_MAX_UPDATES_PER_SECOND,
+                ))
+                .expect("update rate"),
+            restart_on_fault: env::var("RESTART_ON_FAULT").is_ok_and(|v| v == "1" || v == "true"),
+        })
+    }
+
+    /// Writes the report & summary for a completed run to the configured paths
+    fn write_report(&self, report: &RunReport) -> std::io::Result<()> {
+        if let Some((path, format)) = &self.summary {
+            std::fs::write(path, report.summary(*format, self.units))?;
+        }
+        let Some(path) = &self.report_path else {
+            return Ok(());
+        };
+
+        std::fs::write(path, format!("{:#}\n", report.to_json()))?;
+        std::fs::write(
+            std::path::Path::new(path).with_extension("csv"),
+            report.carriers_csv(),
+        )?;
+        std::fs::write(
+            std::path::Path::new(path).with_extension("orders.csv"),
+            report.orders_csv(),
+        )
+    }
+}
+
+/// How a run came to an end
+enum RunEnd {
+    Finished(Box<Result<RunReport, String>>),
+    Reset,
+    /// The run panicked at the simulated time of its last update
+    Faulted {
+        time: u64,
+        message: String,
+        /// Last checkpoint taken before the run panicked, if there was one
+        checkpoint: Option<Box<Checkpoint>>,
+    },
+    /// Every client control has gone away, so the server is shutting down
+    ControlsClosed,
+}
+
+/// Simulated minutes between the checkpoints taken of runs which may be restarted
+const CHECKPOINT_INTERVAL_MINUTES: u32 = 10;
+/// Faults after which a run resuming from the same checkpoint is given up on
+const MAX_FAULTS_PER_CHECKPOINT: u32 = 3;
+
+/// Runs the scenario, restarting it from scratch each time a reset is requested, and passing the
+/// controls from clients along to the run in progress. Runs which panic are held until a reset,
+/// or resumed from their last checkpoint if the scenario restarts on faults.
+struct RunnerSupervisor {
+    scenario: Arc<Scenario>,
+    events: mpsc::UnboundedSender<Event>,
+    /// Where orders are sent as they settle, along with the id of their simulation, if anywhere
+    settled_orders: Option<mpsc::UnboundedSender<(Ulid, SettledOrder)>>,
+    /// Identifier of the simulation being run
+    simulation: Ulid,
+    /// Each completed run contributes a day of demand to the forecast used by later runs, on top
+    /// of the scenario's demand priors
+    history: HistoricalAverage,
+}
+
+impl RunnerSupervisor {
+    fn new(
+        scenario: Arc<Scenario>,
+        events: mpsc::UnboundedSender<Event>,
+        settled_orders: Option<mpsc::UnboundedSender<(Ulid, SettledOrder)>>,
+    ) -> Self {
+        Self {
+            history: HistoricalAverage::default().with_priors(&scenario.demand_priors),
+            scenario,
+            events,
+            settled_orders,
+            simulation: Ulid::new(),
+        }
+    }
+
+    async fn run(mut self, controls: impl Stream<Item = Control> + Unpin) {
+        let mut controls = controls.fuse();
+        let mut resume = None;
+        // Time of the checkpoint the latest faults resumed from, & how many there have been
+        let mut faults = (None, 0);
+        loop {
+            // Runs resumed after a fault carry on as the same simulation
+            if resume.is_none() {
+                self.simulation = Ulid::new();
+                let _ = self.events.unbounded_send(Event::Started(self.simulation));
+            }
+            let Some(runner) = self.load_runner(resume.take()).await else {
+                return;
+            };
+            match self.supervise(runner, &mut controls).await {
+                RunEnd::Finished(result) => {
+                    faults = (None, 0);
+                    let report = self.finish(*result);
+                    if !Self::hold_finished(report.as_ref(), &mut controls).await {
+                        return;
+                    }
+                }
+                RunEnd::Reset => faults = (None, 0),
+                RunEnd::Faulted {
+                    time,
+                    message,
+                    checkpoint,
+                } => {
+                    let checkpoint_time = checkpoint.as_ref().map(|checkpoint| checkpoint.time);
+                    faults = match faults {
+                        (time, count) if time == checkpoint_time => (time, count + 1),
+                        _ => (checkpoint_time, 1),
+                    };
+                    let restart =
+                        self.scenario.restart_on_fault && faults.1 < MAX_FAULTS_PER_CHECKPOINT;
+                    let message = match (restart, checkpoint_time) {
+                        (true, Some(time)) => format!(
+                            "simulation faulted: {}, resuming from {}",
+                            message,
+                            time::format_short(time)
+                        ),
+                        (true, None) => format!("simulation faulted: {}, restarting it", message),
+                        (false, _) => {
+                            format!("simulation faulted: {}, reset it to start over", message)
+                        }
+                    };
+                    tracing::error!("{}", message);
+                    let _ = self.events.unbounded_send(Event::Alert(Alert {
+                        time,
+                        kind: AlertKind::SimulationFaulted,
+                        message,
+                        flight: None,
+                    }));
+
+                    if !restart {
+                        faults = (None, 0);
+                        if !Self::hold_finished(None, &mut controls).await {
+                            return;
+                        }
+                    } else if checkpoint.is_some() {
+                        // Subscribers carry on from where the run left off
+                        resume = checkpoint.map(|checkpoint| *checkpoint);
+                        continue;
                     }
+                }
+                RunEnd::ControlsClosed => return,
+            }
 
-                    futures::future::ready(()) // Leave open the possibility of doing some other async work in response to each event
+            tracing::info!("resetting simulation");
+            let _ = self.events.unbounded_send(Event::Reset);
+        }
+    }
+
+    /// Loads the scenario afresh, into a runner configured for it which resumes from the provided
+    /// checkpoint if there is one
+    async fn load_runner(&self, resume: Option<Checkpoint>) -> Option<CsvRunner> {
+        let scenario = &self.scenario;
+        let range_reductions = match &scenario.range_reductions_csv_path {
+            Some(path) => match RangeReduction::from_csv(path).await {
+                Ok(reductions) => reductions,
+                Err(e) => {
+                    tracing::error!("failed to load range reductions: {}", e);
+                    return None;
+                }
+            },
+            None => vec![],
+        };
+        let sectors = match &scenario.sectors_csv_path {
+            Some(path) => match Sector::from_csv(path).await {
+                Ok(sectors) => sectors,
+                Err(e) => {
+                    tracing::error!("failed to load sectors: {}", e);
+                    return None;
+                }
+            },
+            None => vec![],
+        };
+        let matcher = match &scenario.destination_aliases_csv_path {
+            Some(path) => match DestinationMatcher::from_aliases_csv(path).await {
+                Ok(matcher) => matcher,
+                Err(e) => {
+                    tracing::error!("failed to load destination aliases: {}", e);
+                    return None;
                 }
+            },
+            None => DestinationMatcher::default(),
+        };
+        let mut runner = match CsvRunner::from_csv_paths(
+            &scenario.destinations_csv_path,
+            &scenario.orders_csv_path,
+        )
+        .await
+        {
+            Ok(runner) => runner
+                .with_speed(Speed::fast_forward(200).expect("speed")) // run demo in fast-forward
+                .with_max_updates_per_second(scenario.max_updates_per_second)
+                .with_origin(scenario.origin.clone())
+                .with_flight_phases(scenario.flight_phases)
+                .with_packing(scenario.packing.clone())
+                .with_route_planner(scenario.route_planner)
+                .with_return_policy(scenario.return_policy)
+                .with_energy_model(scenario.energy)
+                .with_range_reductions(range_reductions)
+                .with_sectors(sectors)
+                .with_alert_rules(scenario.alert_rules.clone())
+                .with_ground_fleet(scenario.ground)
+                .with_destination_matcher(matcher.with_matching(scenario.matching))
+                .with_destination_names(scenario.destination_names.clone()),
+            Err(e) => {
+                tracing::error!("failed to load scenario: {}", e);
+                return None;
+            }
+        };
+        if let Some(schedule) = scenario.speed_schedule.clone() {
+            runner = runner.with_speed_schedule(schedule);
+        }
+        if let Some(starvation_s) = scenario.fairness_s {
+            runner = runner.with_fairness(starvation_s.get());
+        }
+        if let Some(separation_m) = scenario.separation_m {
+            runner = runner.with_separation(separation_m);
+        }
+        if let Some(timeout_s) = scenario.approval_timeout_s {
+            runner = runner.with_approvals(timeout_s);
+        }
+        if let Some(policy) = scenario.surge {
+            runner = runner.with_surge(policy);
+        }
+        if let Some(minutes) = scenario.compress_gaps_minutes {
+            runner = runner.with_gap_compression(minutes);
+        }
+        if scenario.restart_on_fault {
+            runner = runner
+                .with_checkpoints(NonZeroU32::new(CHECKPOINT_INTERVAL_MINUTES).expect("interval"));
+        }
+        if let Some(checkpoint) = resume {
+            runner = runner.with_resume(checkpoint);
+        }
+        Some(runner)
+    }
+
+    /// Runs the simulation, forwarding its events & passing any controls along to it until it
+    /// finishes, panics or a reset is requested
+    async fn supervise(
+        &mut self,
+        mut runner: CsvRunner,
+        controls: &mut (impl Stream<Item = Control> + Unpin),
+    ) -> RunEnd {
+        // Forwarding completes once the run has finished & the runner has been dropped
+        let last_update_time = AtomicU64::new(0);
+        let updates = runner
+            .stream_updates()
+            .expect("update stream")
+            .inspect(|update| last_update_time.store(update.time, Ordering::Relaxed))
+            .map(Event::Update);
+        let alerts = runner
+            .stream_alerts()
+            .expect("alert stream")
+            .map(Event::Alert);
+        let destination_changes = runner
+            .stream_destination_changes()
+            .expect("destination changes stream")
+            .map(Event::DestinationChanges);
+        let forward_updates = futures::stream::select_all([
+            updates.boxed(),
+            alerts.boxed(),
+            destination_changes.boxed(),
+        ])
+        .map(Ok)
+        .forward(self.events.clone());
+        let forward_settled = match (&self.settled_orders, runner.stream_settled_orders()) {
+            (Some(settled_orders), Some(settled)) => {
+                let simulation = self.simulation;
+                settled
+                    .map(move |settled| Ok((simulation, settled)))
+                    .forward(settled_orders.clone())
+                    .left_future()
+            }
+            _ => future::ready(Ok(())).right_future(),
+        };
+        let forward_updates = future::join(forward_updates, forward_settled);
+        // Only the latest checkpoint is needed to resume the run
+        let last_checkpoint = match runner.stream_checkpoints() {
+            Some(checkpoints) => checkpoints
+                .fold(None, |_, checkpoint| future::ready(Some(checkpoint)))
+                .left_future(),
+            None => future::ready(None).right_future(),
+        };
+        let commands = runner.commands();
+        let run = match self.scenario.forecasting && !self.history.is_empty() {
+            true => {
+                tracing::info!(
+                    "forecasting from {} previous runs & {} demand priors",
+                    self.history.days(),
+                    self.scenario.demand_priors.len()
+                );
+                runner.run_with_forecaster(self.history.clone())
+            }
+            false => runner.run_with_defaults(),
+        };
+        drop(runner);
 
-                // Track any new subscriptions in the map
-                Event::NewSubscription(id, tx) => {
-                    subscriptions.insert(id, tx);
+        // Once the run panics it's dropped, closing the streams from it
+        let run = AssertUnwindSafe(run).catch_unwind();
+        let finished = future::join3(run, forward_updates, last_checkpoint);
+        futures::pin_mut!(finished);
 
-                    futures::future::ready(())
+        loop {
+            let control = match future::select(&mut finished, controls.next()).await {
+                Either::Left(((Ok(result), _, _), _)) => return RunEnd::Finished(Box::new(result)),
+                Either::Left(((Err(panic), _, checkpoint), _)) => {
+                    let message =
+                        match (panic.downcast_ref::<&str>(), panic.downcast_ref::<String>()) {
+                            (Some(message), _) => message.to_string(),
+                            (_, Some(message)) => message.clone(),
+                            _ => "the scheduler panicked".to_string(),
+                        };
+                    return RunEnd::Faulted {
+                        time: last_update_time.load(Ordering::Relaxed),
+                        message,
+                        checkpoint: checkpoint.map(Box::new),
+                    };
                 }
+                Either::Right((Some(Control::Reset), _)) => return RunEnd::Reset,
+                Either::Right((Some(control), _)) => control,
+                Either::Right((None, _)) => return RunEnd::ControlsClosed,
             };
+            // If the runner has gone away, dropping the reply lets the requester know
+            let _ = commands.unbounded_send(match control {
+                Control::SwapScheduler(name) => Command::SwapScheduler(self.scheduler_kind(name)),
+                Control::SubmitOrders(orders, key, reply) => {
+                    Command::QueueOrders(orders, key, reply)
+                }
+                Control::PreviewSchedule(orders, reply) => Command::PreviewSchedule(orders, reply),
+                Control::UpdateDestinations(changes, reply) => {
+                    Command::UpdateDestinations(changes, reply)
+                }
+                Control::GetOrder(id, reply) => Command::GetOrder(id, reply),
+                Control::ExplainFlight(id, reply) => Command::ExplainFlight(id, reply),
+                Control::SetFleetSize(carriers, reply) => Command::SetFleetSize(carriers, reply),
+                Control::Drain(reply) => Command::Drain(reply),
+                Control::ApproveFlight(id, reply) => Command::ApproveFlight(id, reply),
+                Control::Reset => unreachable!("reset ends the run"),
+            });
+        }
+    }
 
-            futures::future::ready(Some(fut))
-        })
-        .boxed()
-        .buffer_unordered(100) // For if there was other async work to be done
-        .collect::<()>();
+    fn scheduler_kind(&self, name: SchedulerName) -> SchedulerKind {
+        match name {
+            SchedulerName::Naive => SchedulerKind::Naive,
+            SchedulerName::Forecasting => {
+                if self.history.is_empty() {
+                    tracing::warn!("forecasting without any previous runs or demand priors");
+                }
+                SchedulerKind::Forecasting(self.history.clone())
+            }
+        }
+    }
 
-    log::info!("running server on {}", addr);
+    /// Records the result of a finished run, returning its report if it succeeded
+    fn finish(&mut self, result: Result<RunReport, String>) -> Option<RunReport> {
+        match result {
+            Ok(report) => {
+                tracing::info!("simulation finished: {}", report);
+                if let Err(e) = self.scenario.write_report(&report) {
+                    tracing::error!("failed to write report: {}", e);
+                }
+                self.history.observe(&report.demand);
+                Some(report)
+            }
+            Err(e) => {
+                tracing::error!("simulation failed: {}", e);
+                None
+            }
+        }
+    }
 
-    let _ = futures::join!(
-        TonicServer::builder().add_service(server).serve(addr),
-        stream_process,
-        runner.run_with_defaults()
-    );
+    /// Keeps the final state of a finished run around until someone asks for a reset, answering
+    /// controls in the meantime. Returns whether a reset was requested.
+    async fn hold_finished(
+        report: Option<&RunReport>,
+        controls: &mut (impl Stream<Item = Control> + Unpin),
+    ) -> bool {
+        let finished =
+            |action: &str| format!("the simulation has finished, reset it to {}", action);
+        loop {
+            match controls.next().await {
+                Some(Control::Reset) => return true,
+                Some(Control::SwapScheduler(_)) => {
+                    tracing::warn!("ignoring scheduler swap, the simulation has finished")
+                }
+                Some(Control::SubmitOrders(_, _, reply)) => {
+                    let _ = reply.send(Err(finished("submit more orders")));
+                }
+                Some(Control::PreviewSchedule(_, reply)) => {
+                    let _ = reply.send(Err(finished("preview a schedule")));
+                }
+                Some(Control::UpdateDestinations(_, reply)) => {
+                    let _ = reply.send(Err(finished("update destinations")));
+                }
+                Some(Control::SetFleetSize(..)) => {
+                    tracing::warn!("ignoring fleet resize, the simulation has finished")
+                }
+                Some(Control::ApproveFlight(_, reply)) => {
+                    let _ = reply.send(Err(finished("approve flights")));
+                }
+                // Nothing is left to land once the run has finished
+                Some(Control::Drain(reply)) => {
+                    let _ = reply.send(0);
+                }
+                // Orders from the finished run can still be looked up until it's reset
+                Some(Control::GetOrder(id, reply)) => {
+                    if let Some(report) = report {
+                        let receipt = report.receipts.get(&id).cloned();
+                        let _ = reply.send(receipt.map(|receipt| receipt.as_of(report.end_time)));
+                    }
+                }
+                Some(Control::ExplainFlight(id, reply)) => {
+                    if let Some(report) = report {
+                        let _ = reply.send(report.explanations.get(&id).cloned());
+                    }
+                }
+                None => return false,
+            }
+        }
+    }
+}
 
-    Ok(())
+/// Requests from clients to control the simulation
+#[derive(Debug)]
+enum Control {
+    Reset,
+    SwapScheduler(SchedulerName),
+    /// Orders submitted over HTTP or gRPC with their idempotency key, to be queued on the current run
+    SubmitOrders(
+        Vec<schema::Order>,
+        Option<String>,
+        oneshot::Sender<Result<Submitted, String>>,
+    ),
+    /// Hypothetical orders for which to preview the flights that would be launched
+    PreviewSchedule(
+        Vec<schema::Order>,
+        oneshot::Sender<Result<(u64, Vec<schema::Flight>), String>>,
+    ),
+    /// Destinations to add to & deactivate in the current run
+    UpdateDestinations(DestinationChanges, oneshot::Sender<Result<(), String>>),
+    /// Order whose lifecycle to look up in the current (or just finished) run
+    GetOrder(OrderId, oneshot::Sender<Option<OrderReceipt>>),
+    /// Flight whose packing decisions to look up in the current (or just finished) run
+    ExplainFlight(FlightId, oneshot::Sender<Option<FlightExplanation>>),
+    /// Number of carriers to resize the current run's fleet to
+    SetFleetSize(usize, oneshot::Sender<usize>),
+    /// Stop taking orders, finishing the current run once its flights have landed
+    Drain(oneshot::Sender<usize>),
+    /// Emergency flight awaiting approval to launch in the current run
+    ApproveFlight(FlightId, oneshot::Sender<Result<(), String>>),
+}
+
+/// Answers the controls sent to a server following a simulation run by another, as there's no
+/// simulation here to control. Lookups are left unanswered, as if no simulation were running.
+async fn refuse_controls(mut controls: impl Stream<Item = Control> + Unpin) {
+    let following = |action: &str| {
+        format!(
+            "this server follows a simulation run by another, ask the leader to {}",
+            action
+        )
+    };
+    while let Some(control) = controls.next().await {
+        match control {
+            Control::SubmitOrders(_, _, reply) => {
+                let _ = reply.send(Err(following("submit orders")));
+            }
+            Control::PreviewSchedule(_, reply) => {
+                let _ = reply.send(Err(following("preview a schedule")));
+            }
+            Control::UpdateDestinations(_, reply) => {
+                let _ = reply.send(Err(following("update destinations")));
+            }
+            Control::ApproveFlight(_, reply) => {
+                let _ = reply.send(Err(following("approve flights")));
+            }
+            Control::Reset => tracing::warn!("ignoring reset, the simulation is followed"),
+            Control::SwapScheduler(_) => {
+                tracing::warn!("ignoring scheduler swap, the simulation is followed")
+            }
+            Control::SetFleetSize(..) => {
+                tracing::warn!("ignoring fleet resize, the simulation is followed")
+            }
+            Control::GetOrder(..) | Control::ExplainFlight(..) | Control::Drain(_) => {}
+        }
+    }
+}
+
+/// Schedulers which clients may swap in while the simulation is running
+#[derive(Debug, Clone, Copy)]
+enum SchedulerName {
+    Naive,
+    Forecasting,
+}
+
+impl std::str::FromStr for SchedulerName {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "naive" => Ok(Self::Naive),
+            "forecasting" => Ok(Self::Forecasting),
+            _ => Err(format!(
+                "unknown scheduler `{}`, expected `naive` or `forecasting`",
+                s
+            )),
+        }
+    }
+}
+
+/// Serves the monitor streams, sending subscribers the events the `EventBroadcaster` encoded
+#[derive(Clone)]
+struct MonitorStreams {
+    registry: mpsc::UnboundedSender<RegistryMessage>,
+}
+
+type EncodedStream = Pin<Box<dyn Stream<Item = Result<Encoded, Status>> + Send>>;
+
+#[allow(clippy::result_large_err)]
+impl tonic::codegen::Service<tonic::Request<proto::MonitorRequest>> for MonitorStreams {
+    type Response = Response<EncodedStream>;
+    type Error = Status;
+    type Future = tonic::codegen::BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(
+        &mut self,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn call(&mut self, request: tonic::Request<proto::MonitorRequest>) -> Self::Future {
+        let subscription_id = Ulid::new();
+        let request = request.into_inner();
+        let max_rate = NonZeroU32::new(request.max_updates_per_second);
+        let client = (!request.client_name.is_empty()).then_some(ClientIdentity {
+            name: request.client_name,
+            version: request.client_version,
+        });
+        tracing::info!(
+            "received monitor request: {} from {:?} (at most {:?} updates/s)",
+            subscription_id,
+            client,
+            max_rate
+        );
+        let (tx, rx) = mpsc::unbounded();
+        let progress = Progress::default();
+        let subscribed = self.registry.unbounded_send(RegistryMessage::Subscribe(
+            subscription_id,
+            Subscription::Monitor(tx, progress.clone(), max_rate, client),
+        ));
+
+        // Events are shared with the other subscribers, so only a reference to their bytes is
+        // cloned as they're sent
+        let resp = rx
+            .map(move |event: Arc<Encoded>| {
+                progress.consumed();
+                Ok(Encoded::clone(&event))
+            })
+            .boxed();
+
+        Box::pin(async move {
+            subscribed.map_err(|_| Status::internal("send subscription"))?;
+            Ok(Response::new(resp))
+        })
+    }
 }
 
 struct ServerService {
-    subscriptions_sender: mpsc::UnboundedSender<(Ulid, mpsc::UnboundedSender<StatusUpdate>)>,
+    registry: mpsc::UnboundedSender<RegistryMessage>,
+    controls_sender: mpsc::UnboundedSender<Control>,
+    network: mpsc::UnboundedSender<NetworkMessage>,
+    scenario: Arc<Scenario>,
+    logs: Arc<LogTail>,
+}
+
+impl ServerService {
+    /// Converts an order submitted by a client, resolving the name of its destination against the
+    /// simulation's destinations, so that names which don't refer to any are rejected
+    #[allow(clippy::result_large_err)]
+    fn submitted_order(&self, mut order: proto::Order) -> Result<schema::Order, Status> {
+        let invalid = |e: String| Status::invalid_argument(format!("invalid order: {}", e));
+        order.destination = self
+            .scenario
+            .destination_names
+            .resolve(&order.destination)
+            .map_err(invalid)?
+            .to_string();
+        schema::Order::try_from_proto(order).map_err(|e| invalid(e.to_string()))
+    }
 }
 
 #[tonic::async_trait]
+#[allow(clippy::result_large_err)]
 impl Server for ServerService {
     type MonitorStream =
-        Pin<Box<dyn Stream<Item = Result<schema::proto::server::StatusUpdate, Status>> + Send>>;
+        Pin<Box<dyn Stream<Item = Result<schema::proto::server::MonitorEvent, Status>> + Send>>;
+    type AlertsStream =
+        Pin<Box<dyn Stream<Item = Result<schema::proto::server::Alert, Status>> + Send>>;
+    type StreamLogsStream =
+        Pin<Box<dyn Stream<Item = Result<schema::proto::server::LogEvent, Status>> + Send>>;
 
+    /// Never called, as monitor streams are served by `MonitorStreams` instead
     async fn monitor(
         &self,
-        _request: tonic::Request<()>,
+        _request: tonic::Request<proto::MonitorRequest>,
     ) -> Result<Response<Self::MonitorStream>, Status> {
+        Err(Status::unimplemented(
+            "monitor streams are served pre-encoded",
+        ))
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    async fn alerts(
+        &self,
+        _request: tonic::Request<()>,
+    ) -> Result<Response<Self::AlertsStream>, Status> {
         let subscription_id = Ulid::new();
-        log::info!("received monitor request: {}", subscription_id);
+        tracing::info!("received alerts request: {}", subscription_id);
         let (tx, rx) = mpsc::unbounded();
-        self.subscriptions_sender
-            .clone()
-            .start_send((subscription_id, tx))
+        let progress = Progress::default();
+        self.registry
+            .unbounded_send(RegistryMessage::Subscribe(
+                subscription_id,
+                Subscription::Alerts(tx, progress.clone()),
+            ))
             .map_err(|_| Status::internal("send subscription"))?;
 
         let resp = rx
-            .map(|update| Ok::<schema::proto::server::StatusUpdate, Status>(update.into_proto()))
+            .map(move |alert| {
+                progress.consumed();
+                Ok(Arc::unwrap_or_clone(alert))
+            })
             .boxed();
 
         Ok(tonic::Response::new(resp))
     }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    async fn reset_simulation(&self, _request: tonic::Request<()>) -> Result<Response<()>, Status> {
+        tracing::info!("received reset request");
+        self.controls_sender
+            .clone()
+            .start_send(Control::Reset)
+            .map_err(|_| Status::internal("send reset"))?;
+
+        Ok(tonic::Response::new(()))
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    async fn swap_scheduler(
+        &self,
+        request: tonic::Request<schema::proto::server::SwapSchedulerRequest>,
+    ) -> Result<Response<()>, Status> {
+        let name = request
+            .into_inner()
+            .scheduler
+            .parse::<SchedulerName>()
+            .map_err(Status::invalid_argument)?;
+        tracing::info!("received request to swap scheduler for {:?}", name);
+        self.controls_sender
+            .clone()
+            .start_send(Control::SwapScheduler(name))
+            .map_err(|_| Status::internal("send swap"))?;
+
+        Ok(tonic::Response::new(()))
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    async fn submit_order(
+        &self,
+        request: tonic::Request<schema::proto::server::SubmitOrderRequest>,
+    ) -> Result<Response<schema::proto::server::SubmitOrderResponse>, Status> {
+        let request = request.into_inner();
+        let order = request
+            .order
+            .ok_or_else(|| Status::invalid_argument("missing order"))
+            .and_then(|order| self.submitted_order(order))?;
+        let key = Some(request.idempotency_key).filter(|key| !key.is_empty());
+        tracing::info!("received order for {} (key {:?})", order.destination, key);
+
+        let (tx, rx) = oneshot::channel();
+        self.controls_sender
+            .clone()
+            .start_send(Control::SubmitOrders(vec![order], key, tx))
+            .map_err(|_| Status::internal("send order"))?;
+        let submitted = rx
+            .await
+            .map_err(|_| Status::unavailable("no simulation is running"))?
+            .map_err(Status::invalid_argument)?;
+
+        let (accepted, order_id) = match submitted {
+            Submitted::Accepted(ids) => (true, ids.first().map_or(0, |id| id.0)),
+            Submitted::AlreadyKnown => (false, 0),
+        };
+
+        Ok(tonic::Response::new(
+            schema::proto::server::SubmitOrderResponse { accepted, order_id },
+        ))
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    async fn preview_schedule(
+        &self,
+        request: tonic::Request<schema::proto::server::PreviewScheduleRequest>,
+    ) -> Result<Response<schema::proto::server::PreviewScheduleResponse>, Status> {
+        let orders = request
+            .into_inner()
+            .orders
+            .into_iter()
+            .map(|order| self.submitted_order(order))
+            .collect::<Result<Vec<_>, _>>()?;
+        tracing::info!("received request to preview {} orders", orders.len());
+
+        let (tx, rx) = oneshot::channel();
+        self.controls_sender
+            .clone()
+            .start_send(Control::PreviewSchedule(orders, tx))
+            .map_err(|_| Status::internal("send preview"))?;
+        let (time, flights) = rx
+            .await
+            .map_err(|_| Status::unavailable("no simulation is running"))?
+            .map_err(Status::invalid_argument)?;
+
+        Ok(tonic::Response::new(
+            schema::proto::server::PreviewScheduleResponse {
+                time: Some(schema::time::to_proto_timestamp(time)),
+                flights: flights.into_iter().map(ToFromProto::into_proto).collect(),
+            },
+        ))
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    async fn update_destinations(
+        &self,
+        request: tonic::Request<schema::proto::server::DestinationChanges>,
+    ) -> Result<Response<()>, Status> {
+        let changes = DestinationChanges::try_from_proto(request.into_inner())
+            .map_err(|e| Status::invalid_argument(format!("invalid destination changes: {}", e)))?;
+        tracing::info!(
+            "received request to add {} & deactivate {} destinations",
+            changes.added.len(),
+            changes.deactivated.len()
+        );
+
+        let (tx, rx) = oneshot::channel();
+        self.controls_sender
+            .clone()
+            .start_send(Control::UpdateDestinations(changes, tx))
+            .map_err(|_| Status::internal("send destination changes"))?;
+        rx.await
+            .map_err(|_| Status::unavailable("no simulation is running"))?
+            .map_err(Status::invalid_argument)?;
+
+        Ok(tonic::Response::new(()))
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    async fn get_order(
+        &self,
+        request: tonic::Request<schema::proto::server::GetOrderRequest>,
+    ) -> Result<Response<schema::proto::server::OrderReceipt>, Status> {
+        let id = OrderId(request.into_inner().order_id);
+        tracing::info!("received request for order {}", id);
+
+        let (tx, rx) = oneshot::channel();
+        self.controls_sender
+            .clone()
+            .start_send(Control::GetOrder(id, tx))
+            .map_err(|_| Status::internal("send order request"))?;
+        let receipt = rx
+            .await
+            .map_err(|_| Status::unavailable("no simulation is running"))?
+            .ok_or_else(|| Status::not_found(format!("unknown order: {}", id)))?;
+
+        Ok(tonic::Response::new(receipt.into_proto()))
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    async fn set_fleet_size(
+        &self,
+        request: tonic::Request<schema::proto::server::SetFleetSizeRequest>,
+    ) -> Result<Response<schema::proto::server::SetFleetSizeResponse>, Status> {
+        let carriers = request.into_inner().carriers;
+        tracing::info!(
+            "received request to resize the fleet to {} carriers",
+            carriers
+        );
+
+        let (tx, rx) = oneshot::channel();
+        self.controls_sender
+            .clone()
+            .start_send(Control::SetFleetSize(carriers as usize, tx))
+            .map_err(|_| Status::internal("send fleet size"))?;
+        let carriers = rx
+            .await
+            .map_err(|_| Status::unavailable("no simulation is running"))?;
+
+        Ok(tonic::Response::new(
+            schema::proto::server::SetFleetSizeResponse {
+                carriers: carriers as u32,
+            },
+        ))
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    async fn drain(
+        &self,
+        _request: tonic::Request<()>,
+    ) -> Result<Response<schema::proto::server::DrainResponse>, Status> {
+        tracing::info!("received request to drain the simulation");
+
+        let (tx, rx) = oneshot::channel();
+        self.controls_sender
+            .clone()
+            .start_send(Control::Drain(tx))
+            .map_err(|_| Status::internal("send drain"))?;
+        let flights = rx
+            .await
+            .map_err(|_| Status::unavailable("no simulation is running"))?;
+
+        Ok(tonic::Response::new(schema::proto::server::DrainResponse {
+            flights: flights as u32,
+        }))
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    async fn approve_flight(
+        &self,
+        request: tonic::Request<proto::ApproveFlightRequest>,
+    ) -> Result<Response<()>, Status> {
+        let flight_id = request.into_inner().flight_id;
+        let id = flight_id
+            .parse::<FlightId>()
+            .map_err(|_| Status::invalid_argument(format!("invalid flight id: {}", flight_id)))?;
+        tracing::info!("received request to approve flight {}", id);
+
+        let (tx, rx) = oneshot::channel();
+        self.controls_sender
+            .clone()
+            .start_send(Control::ApproveFlight(id, tx))
+            .map_err(|_| Status::internal("send approval"))?;
+        rx.await
+            .map_err(|_| Status::unavailable("no simulation is running"))?
+            .map_err(Status::failed_precondition)?;
+
+        Ok(tonic::Response::new(()))
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    async fn get_scenario(
+        &self,
+        _request: tonic::Request<()>,
+    ) -> Result<Response<schema::proto::server::Scenario>, Status> {
+        tracing::info!("received request for the scenario");
+
+        // Destinations are read afresh, as the next run would load them
+        let destinations = Destination::from_csv(&self.scenario.destinations_csv_path)
+            .await
+            .map_err(|e| Status::internal(format!("failed to load destinations: {}", e)))?;
+
+        Ok(tonic::Response::new(schema::proto::server::Scenario {
+            destinations: destinations
+                .into_iter()
+                .map(ToFromProto::into_proto)
+                .collect(),
+            origin: Some(self.scenario.origin.clone().into_proto()),
+            demand_priors: self
+                .scenario
+                .demand_priors
+                .iter()
+                .cloned()
+                .map(ToFromProto::into_proto)
+                .collect(),
+        }))
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    async fn set_network_conditions(
+        &self,
+        request: tonic::Request<proto::NetworkConditions>,
+    ) -> Result<Response<()>, Status> {
+        let request = request.into_inner();
+        let duration = |field: &str, value: Option<prost_types::Duration>| {
+            value.map_or(Ok(Duration::ZERO), |value| {
+                Duration::try_from(value)
+                    .map_err(|e| Status::invalid_argument(format!("invalid {}: {}", field, e)))
+            })
+        };
+        let conditions = NetworkConditions {
+            delay: duration("delay", request.delay)?,
+            jitter: duration("jitter", request.jitter)?,
+        };
+        tracing::info!("received request to simulate {:?}", conditions);
+
+        self.network
+            .unbounded_send(NetworkMessage::SetConditions(conditions))
+            .map_err(|_| Status::internal("send network conditions"))?;
+
+        Ok(tonic::Response::new(()))
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    async fn explain_flight(
+        &self,
+        request: tonic::Request<proto::ExplainFlightRequest>,
+    ) -> Result<Response<proto::FlightExplanation>, Status> {
+        let flight_id = request.into_inner().flight_id;
+        let id = flight_id
+            .parse::<FlightId>()
+            .map_err(|_| Status::invalid_argument(format!("invalid flight id: {}", flight_id)))?;
+        tracing::info!("received request to explain flight {}", id);
+
+        let (tx, rx) = oneshot::channel();
+        self.controls_sender
+            .clone()
+            .start_send(Control::ExplainFlight(id, tx))
+            .map_err(|_| Status::internal("send flight request"))?;
+        let explanation = rx
+            .await
+            .map_err(|_| Status::unavailable("no simulation is running"))?
+            .ok_or_else(|| Status::not_found(format!("unknown flight: {}", id)))?;
+
+        Ok(tonic::Response::new(explanation.into_proto()))
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    async fn list_subscribers(
+        &self,
+        _request: tonic::Request<()>,
+    ) -> Result<Response<proto::SubscriberList>, Status> {
+        tracing::info!("received request to list subscribers");
+        let (tx, rx) = oneshot::channel();
+        self.registry
+            .unbounded_send(RegistryMessage::ListSubscribers(tx))
+            .map_err(|_| Status::internal("send subscribers request"))?;
+        let subscribers = rx
+            .await
+            .map_err(|_| Status::internal("subscription registry stopped"))?;
+
+        let duration = |duration: Duration| prost_types::Duration::try_from(duration).ok();
+        Ok(tonic::Response::new(proto::SubscriberList {
+            subscribers: subscribers
+                .into_iter()
+                .map(|subscriber| {
+                    let client = subscriber.client.unwrap_or_default();
+                    proto::Subscriber {
+                        id: subscriber.id.to_string(),
+                        stream: subscriber.stream.to_string(),
+                        client_name: client.name,
+                        client_version: client.version,
+                        connected: duration(subscriber.connected),
+                        waiting: subscriber.waiting as u64,
+                        lag: duration(subscriber.lag),
+                        dropped_updates: subscriber.dropped as u64,
+                    }
+                })
+                .collect(),
+        }))
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    async fn stream_logs(
+        &self,
+        request: tonic::Request<proto::StreamLogsRequest>,
+    ) -> Result<Response<Self::StreamLogsStream>, Status> {
+        let request = request.into_inner();
+        let level = match proto::LogLevel::try_from(request.level) {
+            Ok(proto::LogLevel::Error) => log::LevelFilter::Error,
+            Ok(proto::LogLevel::Warning) => log::LevelFilter::Warn,
+            Ok(proto::LogLevel::Info) => log::LevelFilter::Info,
+            Ok(proto::LogLevel::Debug) => log::LevelFilter::Debug,
+            Ok(proto::LogLevel::Trace) => log::LevelFilter::Trace,
+            Err(_) => {
+                return Err(Status::invalid_argument(format!(
+                    "invalid log level: {}",
+                    request.level
+                )))
+            }
+        };
+        tracing::info!(
+            "received request to stream logs at {} from `{}`",
+            level,
+            request.target
+        );
+
+        let resp = self
+            .logs
+            .subscribe(level, request.target)
+            .map(|record| Ok(log_event(record)))
+            .boxed();
+        Ok(tonic::Response::new(resp))
+    }
+}
+
+fn log_event(record: LogRecord) -> proto::LogEvent {
+    let level = match record.level {
+        log::Level::Error => proto::LogLevel::Error,
+        log::Level::Warn => proto::LogLevel::Warning,
+        log::Level::Info => proto::LogLevel::Info,
+        log::Level::Debug => proto::LogLevel::Debug,
+        log::Level::Trace => proto::LogLevel::Trace,
+    };
+    proto::LogEvent {
+        time: Some(record.time.into()),
+        level: level as i32,
+        target: record.target,
+        message: record.message,
+        file: record.file.unwrap_or_default(),
+        line: record.line.unwrap_or_default(),
+    }
 }
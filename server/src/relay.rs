@@ -0,0 +1,728 @@
+//! Relays the simulation's events between servers over Redis pub/sub, so that the subscribers of
+//! a busy simulation can be spread over several servers: the leader runs the simulation &
+//! publishes its events, which each follower fans out to its own subscribers.
+
+use std::env;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::time::Duration;
+
+use futures::channel::mpsc;
+use futures::{Stream, StreamExt};
+use prost::Message;
+use schema::proto::server as proto;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufStream};
+use tokio::net::TcpStream;
+
+use proto::monitor_event::Event;
+use proto::relay_message::Message as Relayed;
+
+/// Longest wait between attempts to reconnect, the wait doubling from the relay's backoff
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// Time waited for Redis to accept a connection, so that an unreachable host isn't waited on
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Whether a server runs the simulation, or follows one run by another server
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RelayRole {
+    /// Runs the simulation, publishing its events for followers
+    Leader,
+    /// Sends its subscribers the events published by the leader, instead of running a simulation
+    Follower,
+}
+
+impl std::str::FromStr for RelayRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "leader" => Ok(Self::Leader),
+            "follower" => Ok(Self::Follower),
+            _ => Err(format!("unknown relay role: {}", s)),
+        }
+    }
+}
+
+/// Redis server & channel through which a leader relays the simulation's events to followers.
+/// Events replayed to subscribers joining part way through a run (the run's id & changes to its
+/// destinations) are also kept in a list, for followers joining part way through it.
+#[derive(Clone, Debug)]
+pub struct Relay {
+    /// `host:port` of the Redis server
+    addr: String,
+    username: Option<String>,
+    password: Option<String>,
+    database: Option<u32>,
+    channel: String,
+    role: RelayRole,
+    /// Time waited before reconnecting, doubling with each failed attempt
+    backoff: Duration,
+}
+
+impl Relay {
+    pub const DEFAULT_CHANNEL: &'static str = "scheduler";
+    pub const DEFAULT_BACKOFF: Duration = Duration::from_secs(1);
+
+    /// Relays through the Redis server at a `redis://[[username]:password@]host[:port][/database]`
+    /// URL
+    pub fn new(url: &str, role: RelayRole) -> Result<Self, String> {
+        let invalid = |reason: &str| format!("invalid relay url `{}`: {}", url, reason);
+        let rest = url
+            .strip_prefix("redis://")
+            .ok_or_else(|| invalid("expected redis://"))?;
+        let (rest, database) = match rest.split_once('/') {
+            Some((rest, "")) => (rest, None),
+            Some((rest, database)) => (
+                rest,
+                Some(database.parse().map_err(|_| invalid("invalid database"))?),
+            ),
+            None => (rest, None),
+        };
+        let (username, password, host) = match rest.rsplit_once('@') {
+            Some((userinfo, host)) => match userinfo.split_once(':') {
+                Some((username, password)) => (
+                    Some(username.to_string()).filter(|username| !username.is_empty()),
+                    Some(password.to_string()),
+                    host,
+                ),
+                None => (None, Some(userinfo.to_string()), host),
+            },
+            None => (None, None, rest),
+        };
+        if host.is_empty() {
+            return Err(invalid("missing host"));
+        }
+        // IPv6 hosts are bracketed, so a colon after the last bracket is the port's
+        let has_port = host
+            .rsplit_once(']')
+            .map_or(host, |(_, port)| port)
+            .contains(':');
+        let addr = match has_port {
+            true => host.to_string(),
+            false => format!("{}:6379", host),
+        };
+
+        Ok(Self {
+            addr,
+            username,
+            password,
+            database,
+            channel: Self::DEFAULT_CHANNEL.to_string(),
+            role,
+            backoff: Self::DEFAULT_BACKOFF,
+        })
+    }
+
+    /// Relays through the Redis server at `RELAY_URL` if it's set, as the `RELAY_ROLE` (`leader`,
+    /// the default, or `follower`) on the `RELAY_CHANNEL` where it's set
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let Ok(url) = env::var("RELAY_URL") else {
+            return Ok(None);
+        };
+        let role = match env::var("RELAY_ROLE") {
+            Ok(role) => role.parse()?,
+            Err(_) => RelayRole::Leader,
+        };
+        let mut relay = Self::new(&url, role)?;
+        if let Ok(channel) = env::var("RELAY_CHANNEL") {
+            relay = relay.with_channel(channel);
+        }
+        Ok(Some(relay))
+    }
+
+    /// Relay events on the provided channel, e.g. to run several simulations on the same server
+    pub fn with_channel(mut self, channel: impl Into<String>) -> Self {
+        self.channel = channel.into();
+        self
+    }
+
+    /// Wait the provided time (doubled each time) between attempts to reconnect
+    pub fn with_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    pub fn role(&self) -> RelayRole {
+        self.role
+    }
+
+    /// `host:port` of the Redis server
+    pub fn addr(&self) -> &str {
+        &self.addr
+    }
+
+    /// Key of the list of events replayed to followers joining part way through a run
+    fn replay_key(&self) -> String {
+        format!("{}:replay", self.channel)
+    }
+
+    /// Publishes each message for the followers until the stream ends. Messages are dropped while
+    /// Redis can't be reached, rather than piling up, the connection being retried as messages
+    /// come once the backoff has passed.
+    pub async fn publish(&self, mut messages: impl Stream<Item = proto::RelayMessage> + Unpin) {
+        let mut connection = None;
+        let mut backoff = self.backoff;
+        let mut retry_at = tokio::time::Instant::now();
+        let mut dropped = 0;
+        while let Some(message) = messages.next().await {
+            if connection.is_none() && tokio::time::Instant::now() >= retry_at {
+                match self.connect().await {
+                    Ok(connected) => {
+                        if dropped > 0 {
+                            tracing::warn!(
+                                "reconnected to relay {}, having dropped {} events",
+                                self.addr,
+                                dropped
+                            );
+                        }
+                        (connection, backoff, dropped) = (Some(connected), self.backoff, 0);
+                    }
+                    Err(e) => {
+                        tracing::warn!(
+                            "couldn't connect to relay {}, retrying in {:?}: {}",
+                            self.addr,
+                            backoff,
+                            e
+                        );
+                        retry_at = tokio::time::Instant::now() + backoff;
+                        backoff = (backoff * 2).min(MAX_BACKOFF);
+                    }
+                }
+            }
+            let Some(connected) = connection.as_mut() else {
+                dropped += 1;
+                continue;
+            };
+            if let Err(e) = self.relay(connected, &message).await {
+                tracing::warn!("lost connection to relay {}: {}", self.addr, e);
+                connection = None;
+                dropped += 1;
+            }
+        }
+    }
+
+    /// Publishes a message, first keeping it for later followers if it's replayed
+    async fn relay(
+        &self,
+        connection: &mut Connection<TcpStream>,
+        message: &proto::RelayMessage,
+    ) -> io::Result<()> {
+        let payload = message.encode_to_vec();
+        let key = self.replay_key();
+        match replay(message) {
+            Some(Replay::Keep) => {
+                connection
+                    .command(&[b"RPUSH", key.as_bytes(), &payload])
+                    .await?;
+            }
+            Some(Replay::Restart) => {
+                connection.command(&[b"DEL", key.as_bytes()]).await?;
+                connection
+                    .command(&[b"RPUSH", key.as_bytes(), &payload])
+                    .await?;
+            }
+            Some(Replay::Clear) => {
+                connection.command(&[b"DEL", key.as_bytes()]).await?;
+            }
+            None => {}
+        }
+        connection
+            .command(&[b"PUBLISH", self.channel.as_bytes(), &payload])
+            .await?;
+        Ok(())
+    }
+
+    /// Passes each message published by the leader on to the sender until it's closed, starting
+    /// with those replayed for the current run. Reconnects whenever the connection is lost, with
+    /// whatever is published in the meantime missed.
+    pub async fn follow(&self, messages: mpsc::UnboundedSender<proto::RelayMessage>) {
+        let mut backoff = self.backoff;
+        loop {
+            let e = match self.subscribe(&messages, &mut backoff).await {
+                Ok(()) => return,
+                Err(e) => e,
+            };
+            tracing::warn!(
+                "lost connection to relay {}, reconnecting in {:?}: {}",
+                self.addr,
+                backoff,
+                e
+            );
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+
+    /// Follows the leader until the connection is lost, or the sender is closed
+    async fn subscribe(
+        &self,
+        messages: &mpsc::UnboundedSender<proto::RelayMessage>,
+        backoff: &mut Duration,
+    ) -> io::Result<()> {
+        let mut subscription = self.connect().await?;
+        subscription
+            .command(&[b"SUBSCRIBE", self.channel.as_bytes()])
+            .await?;
+        // Caught up only once subscribed, so that nothing published in between is missed
+        let replayed = self
+            .connect()
+            .await?
+            .command(&[b"LRANGE", self.replay_key().as_bytes(), b"0", b"-1"])
+            .await?;
+        let Value::Array(Some(replayed)) = replayed else {
+            return Err(invalid(format!("unexpected replay: {:?}", replayed)));
+        };
+        tracing::info!(
+            "following relay {}, replaying {} events",
+            self.addr,
+            replayed.len()
+        );
+        *backoff = self.backoff;
+
+        for payload in replayed {
+            if !forward(messages, payload) {
+                return Ok(());
+            }
+        }
+        loop {
+            let Value::Array(Some(mut parts)) = subscription.read().await? else {
+                continue;
+            };
+            let is_message =
+                matches!(parts.first(), Some(Value::Bulk(Some(kind))) if kind == b"message");
+            if is_message && parts.len() == 3 && !forward(messages, parts.remove(2)) {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn connect(&self) -> io::Result<Connection<TcpStream>> {
+        let stream = tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(&self.addr))
+            .await
+            .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "timed out connecting"))??;
+        let mut connection = Connection::new(stream);
+        if let Some(password) = &self.password {
+            match &self.username {
+                Some(username) => {
+                    connection
+                        .command(&[b"AUTH", username.as_bytes(), password.as_bytes()])
+                        .await?
+                }
+                None => connection.command(&[b"AUTH", password.as_bytes()]).await?,
+            };
+        }
+        if let Some(database) = self.database {
+            connection
+                .command(&[b"SELECT", database.to_string().as_bytes()])
+                .await?;
+        }
+        Ok(connection)
+    }
+}
+
+/// Whether a message is replayed to later followers, as the `SubscriptionRegistry` replays it to
+/// later subscribers
+enum Replay {
+    Keep,
+    /// Keep only this message, as a new run has started (perhaps by a new leader)
+    Restart,
+    /// Forget the messages kept so far, as the simulation has been reset
+    Clear,
+}
+
+fn replay(message: &proto::RelayMessage) -> Option<Replay> {
+    let Some(Relayed::MonitorEvent(event)) = &message.message else {
+        return None;
+    };
+    match event.event.as_ref()? {
+        Event::SimulationStarted(_) => Some(Replay::Restart),
+        Event::DestinationChanges(_) => Some(Replay::Keep),
+        Event::SimulationReset(_) => Some(Replay::Clear),
+        _ => None,
+    }
+}
+
+/// Decodes a message relayed by the leader & sends it on, returning whether the sender is open
+fn forward(messages: &mpsc::UnboundedSender<proto::RelayMessage>, payload: Value) -> bool {
+    let Value::Bulk(Some(payload)) = payload else {
+        tracing::warn!("ignoring unexpected relay message: {:?}", payload);
+        return true;
+    };
+    match proto::RelayMessage::decode(payload.as_slice()) {
+        Ok(message) => messages.unbounded_send(message).is_ok(),
+        Err(e) => {
+            tracing::warn!("ignoring invalid relay message: {}", e);
+            true
+        }
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Value sent by a Redis server, in its RESP2 protocol
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Simple(String),
+    Error(String),
+    Integer(i64),
+    Bulk(Option<Vec<u8>>),
+    Array(Option<Vec<Value>>),
+}
+
+/// Connection to a Redis server, sending commands & reading the values sent back
+struct Connection<S> {
+    stream: BufStream<S>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin + Send> Connection<S> {
+    fn new(stream: S) -> Self {
+        Self {
+            stream: BufStream::new(stream),
+        }
+    }
+
+    /// Sends a command, returning its reply. Errors replied by the server are returned as errors.
+    async fn command(&mut self, args: &[&[u8]]) -> io::Result<Value> {
+        let mut request = format!("*{}\r\n", args.len()).into_bytes();
+        for arg in args {
+            request.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+            request.extend_from_slice(arg);
+            request.extend_from_slice(b"\r\n");
+        }
+        self.stream.write_all(&request).await?;
+        self.stream.flush().await?;
+
+        match self.read().await? {
+            Value::Error(e) => Err(io::Error::other(e)),
+            value => Ok(value),
+        }
+    }
+
+    /// Reads the next value sent by the server, e.g. a message published to a subscribed channel
+    fn read(&mut self) -> Pin<Box<dyn Future<Output = io::Result<Value>> + Send + '_>> {
+        Box::pin(async move {
+            let line = self.line().await?;
+            let length = |rest: &str| {
+                rest.parse::<i64>()
+                    .map_err(|_| invalid(format!("invalid length: {}", rest)))
+            };
+            let Some(kind) = line.chars().next() else {
+                return Err(invalid("empty reply".to_string()));
+            };
+            let rest = &line[kind.len_utf8()..];
+            match kind {
+                '+' => Ok(Value::Simple(rest.to_string())),
+                '-' => Ok(Value::Error(rest.to_string())),
+                ':' => length(rest).map(Value::Integer),
+                '$' => {
+                    let Ok(len) = usize::try_from(length(rest)?) else {
+                        return Ok(Value::Bulk(None));
+                    };
+                    // Followed by its own line ending
+                    let mut data = vec![0; len + 2];
+                    self.stream.read_exact(&mut data).await?;
+                    data.truncate(len);
+                    Ok(Value::Bulk(Some(data)))
+                }
+                '*' => {
+                    let Ok(len) = usize::try_from(length(rest)?) else {
+                        return Ok(Value::Array(None));
+                    };
+                    let mut values = Vec::with_capacity(len.min(1024));
+                    for _ in 0..len {
+                        values.push(self.read().await?);
+                    }
+                    Ok(Value::Array(Some(values)))
+                }
+                _ => Err(invalid(format!("unexpected reply: {}", line))),
+            }
+        })
+    }
+
+    /// Reads a line, without its line ending
+    async fn line(&mut self) -> io::Result<String> {
+        let mut line = String::new();
+        if self.stream.read_line(&mut line).await? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(line.trim_end_matches(['\r', '\n']).to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    use schema::{DestinationChanges, MonitorEvent, ToFromProto};
+    use tokio::net::TcpListener;
+    use ulid::Ulid;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_resp() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let (client, server) = tokio::io::duplex(1024);
+        let (mut client, mut server) = (Connection::new(client), Connection::new(server));
+
+        // Commands are sent as arrays of bulk strings, as clients must
+        let replies = async {
+            let request = server.read().await?;
+            let bulk = |s: &[u8]| Value::Bulk(Some(s.to_vec()));
+            assert_eq!(
+                request,
+                Value::Array(Some(vec![bulk(b"LRANGE"), bulk(b"a\r\nb"), bulk(b"0")]))
+            );
+            server
+                .stream
+                .write_all(b"*3\r\n$2\r\nhi\r\n$-1\r\n*1\r\n:7\r\n")
+                .await?;
+            server.stream.flush().await?;
+            server.read().await?;
+            server.stream.write_all(b"-ERR unknown command\r\n").await?;
+            server.stream.flush().await
+        };
+        let commands = async {
+            let reply = client.command(&[b"LRANGE", b"a\r\nb", b"0"]).await;
+            let error = client.command(&[b"NOPE"]).await;
+            (reply, error)
+        };
+        let (replied, (reply, error)) = futures::join!(replies, commands);
+        replied?;
+
+        assert_eq!(
+            reply?,
+            Value::Array(Some(vec![
+                Value::Bulk(Some(b"hi".to_vec())),
+                Value::Bulk(None),
+                Value::Array(Some(vec![Value::Integer(7)])),
+            ]))
+        );
+        assert_eq!(error.expect_err("error").to_string(), "ERR unknown command");
+
+        Ok(())
+    }
+
+    /// Lists & subscribers of a stand-in for Redis serving just the commands used by the relay
+    #[derive(Default)]
+    struct Broker {
+        lists: HashMap<Vec<u8>, Vec<Vec<u8>>>,
+        subscribers: Vec<mpsc::UnboundedSender<Vec<u8>>>,
+        published: usize,
+    }
+
+    async fn serve(listener: TcpListener, broker: Arc<Mutex<Broker>>) -> io::Result<()> {
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let broker = broker.clone();
+            tokio::spawn(async move {
+                let mut connection = Connection::new(stream);
+                while let Ok(Value::Array(Some(args))) = connection.read().await {
+                    let args = args
+                        .into_iter()
+                        .map(|arg| match arg {
+                            Value::Bulk(Some(arg)) => arg,
+                            arg => panic!("unexpected argument: {:?}", arg),
+                        })
+                        .collect::<Vec<_>>();
+                    let reply = match args[0].as_slice() {
+                        b"SUBSCRIBE" => {
+                            let (tx, mut rx) = mpsc::unbounded::<Vec<u8>>();
+                            broker.lock().unwrap().subscribers.push(tx);
+                            connection
+                                .stream
+                                .write_all(b"*3\r\n$9\r\nsubscribe\r\n$1\r\nc\r\n:1\r\n")
+                                .await?;
+                            connection.stream.flush().await?;
+                            while let Some(payload) = rx.next().await {
+                                let mut message = format!(
+                                    "*3\r\n$7\r\nmessage\r\n$1\r\nc\r\n${}\r\n",
+                                    payload.len()
+                                )
+                                .into_bytes();
+                                message.extend_from_slice(&payload);
+                                message.extend_from_slice(b"\r\n");
+                                connection.stream.write_all(&message).await?;
+                                connection.stream.flush().await?;
+                            }
+                            return Ok(());
+                        }
+                        b"PUBLISH" => {
+                            let mut broker = broker.lock().unwrap();
+                            broker.published += 1;
+                            broker
+                                .subscribers
+                                .retain(|tx| tx.unbounded_send(args[2].clone()).is_ok());
+                            b":1\r\n".to_vec()
+                        }
+                        b"RPUSH" => {
+                            let mut broker = broker.lock().unwrap();
+                            let list = broker.lists.entry(args[1].clone()).or_default();
+                            list.push(args[2].clone());
+                            format!(":{}\r\n", list.len()).into_bytes()
+                        }
+                        b"DEL" => {
+                            broker.lock().unwrap().lists.remove(&args[1]);
+                            b":1\r\n".to_vec()
+                        }
+                        b"LRANGE" => {
+                            let broker = broker.lock().unwrap();
+                            let list = broker.lists.get(&args[1]).cloned().unwrap_or_default();
+                            let mut reply = format!("*{}\r\n", list.len()).into_bytes();
+                            for item in list {
+                                reply.extend_from_slice(format!("${}\r\n", item.len()).as_bytes());
+                                reply.extend_from_slice(&item);
+                                reply.extend_from_slice(b"\r\n");
+                            }
+                            reply
+                        }
+                        command => panic!("unexpected command: {:?}", command),
+                    };
+                    connection.stream.write_all(&reply).await?;
+                    connection.stream.flush().await?;
+                }
+                io::Result::Ok(())
+            });
+        }
+    }
+
+    fn monitor_event(event: MonitorEvent) -> proto::RelayMessage {
+        proto::RelayMessage {
+            message: Some(Relayed::MonitorEvent(event.into_proto())),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_relay() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("redis://{}", listener.local_addr()?);
+        let broker = Arc::new(Mutex::new(Broker::default()));
+        tokio::spawn(serve(listener, broker.clone()));
+        let published = |count: usize| {
+            let broker = broker.clone();
+            async move {
+                while broker.lock().unwrap().published < count {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            }
+        };
+
+        let leader = Relay::new(&url, RelayRole::Leader)?;
+        let (publish, messages) = mpsc::unbounded();
+        tokio::spawn(async move { leader.publish(messages).await });
+
+        // The run's id & destination changes are replayed to a follower joining part way through
+        let started = monitor_event(MonitorEvent::SimulationStarted(Ulid::new()));
+        let changes = monitor_event(MonitorEvent::DestinationChanges(DestinationChanges {
+            added: vec![],
+            deactivated: vec!["Near".into()],
+        }));
+        let update = proto::RelayMessage {
+            message: Some(Relayed::MonitorEvent(proto::MonitorEvent {
+                event: Some(Event::StatusUpdate(Default::default())),
+            })),
+        };
+        for message in [&started, &changes, &update] {
+            publish.unbounded_send(message.clone())?;
+        }
+        published(3).await;
+
+        let follower = Relay::new(&url, RelayRole::Follower)?;
+        let (forward, mut followed) = mpsc::unbounded();
+        tokio::spawn(async move { follower.follow(forward).await });
+        assert_eq!(followed.next().await, Some(started));
+        assert_eq!(followed.next().await, Some(changes));
+
+        // Later messages are followed as they're published, the replay being cleared on reset
+        let alert = proto::RelayMessage {
+            message: Some(Relayed::Alert(proto::Alert {
+                message: "<alert>".to_string(),
+                ..Default::default()
+            })),
+        };
+        let reset = monitor_event(MonitorEvent::SimulationReset);
+        for message in [&alert, &reset] {
+            publish.unbounded_send(message.clone())?;
+        }
+        assert_eq!(followed.next().await, Some(alert));
+        assert_eq!(followed.next().await, Some(reset));
+        assert!(broker.lock().unwrap().lists.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_relay_new_leader() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("redis://{}", listener.local_addr()?);
+        let broker = Arc::new(Mutex::new(Broker::default()));
+        tokio::spawn(serve(listener, broker.clone()));
+        let published = |count: usize| {
+            let broker = broker.clone();
+            async move {
+                while broker.lock().unwrap().published < count {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            }
+        };
+
+        // A leader's run ends without a reset, e.g. as its server was restarted
+        let leader = Relay::new(&url, RelayRole::Leader)?;
+        let (publish, messages) = mpsc::unbounded();
+        tokio::spawn(async move { leader.publish(messages).await });
+        let changes = monitor_event(MonitorEvent::DestinationChanges(DestinationChanges {
+            added: vec![],
+            deactivated: vec!["Near".into()],
+        }));
+        for message in [
+            monitor_event(MonitorEvent::SimulationStarted(Ulid::new())),
+            changes.clone(),
+        ] {
+            publish.unbounded_send(message)?;
+        }
+        published(2).await;
+        drop(publish);
+
+        // Only the next leader's run is replayed to a follower joining part way through it
+        let leader = Relay::new(&url, RelayRole::Leader)?;
+        let (publish, messages) = mpsc::unbounded();
+        tokio::spawn(async move { leader.publish(messages).await });
+        let started = monitor_event(MonitorEvent::SimulationStarted(Ulid::new()));
+        publish.unbounded_send(started.clone())?;
+        published(3).await;
+
+        let follower = Relay::new(&url, RelayRole::Follower)?;
+        let (forward, mut followed) = mpsc::unbounded();
+        tokio::spawn(async move { follower.follow(forward).await });
+        assert_eq!(followed.next().await, Some(started));
+
+        // & the follower is then passed what's published as usual
+        publish.unbounded_send(changes.clone())?;
+        assert_eq!(followed.next().await, Some(changes));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_url() -> Result<(), String> {
+        let relay = Relay::new("redis://localhost", RelayRole::Leader)?;
+        assert_eq!(relay.addr, "localhost:6379");
+        assert_eq!(relay.password, None);
+
+        let relay = Relay::new("redis://user:secret@[::1]:6380/2", RelayRole::Follower)?;
+        assert_eq!(relay.addr, "[::1]:6380");
+        assert_eq!(relay.username.as_deref(), Some("user"));
+        assert_eq!(relay.password.as_deref(), Some("secret"));
+        assert_eq!(relay.database, Some(2));
+
+        let relay = Relay::new("redis://:secret@[::1]", RelayRole::Leader)?;
+        assert_eq!(relay.addr, "[::1]:6379");
+        assert_eq!(relay.username, None);
+
+        assert!(Relay::new("http://localhost", RelayRole::Leader).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,488 @@
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, StatusCode};
+use schema::json::Value;
+use schema::units::{self, Units};
+use schema::{Backlog, Speed, StatusUpdate};
+use ulid::Ulid;
+
+use crate::broadcast::ClientIdentity;
+use crate::logs::LogTail;
+
+/// Number of recent errors & warnings kept for the status page, after which the oldest are dropped
+const MAX_RECENT_ERRORS: usize = 20;
+/// How often the status page reloads itself, in seconds
+const REFRESH_S: u32 = 5;
+
+/// What the server is up to, kept up to date by its tasks for operators to check on without the
+/// GUI client, see `serve_status`
+pub struct StatusBoard {
+    started: Instant,
+    status: Mutex<ServerStatus>,
+}
+
+/// Snapshot of a `StatusBoard`
+#[derive(Clone, Debug, Default)]
+pub struct ServerStatus {
+    /// Simulation currently running, if one has started since the last reset
+    pub simulation: Option<Ulid>,
+    /// Simulated time in __seconds__ of the latest status update, if any
+    pub time: Option<u64>,
+    pub speed: Option<Speed>,
+    pub backlog: Option<Backlog>,
+    /// Flights in the air or held on the ground as of the latest status update
+    pub flights: usize,
+    pub subscribers: Vec<SubscriberStatus>,
+    /// Most recent errors & warnings logged by the server, oldest first
+    pub errors: VecDeque<RecentError>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SubscriberStatus {
+    pub id: Ulid,
+    /// Stream subscribed to, `monitor` or `alerts`
+    pub stream: &'static str,
+    pub client: Option<ClientIdentity>,
+    pub connected: Duration,
+    /// Events sent to the subscriber which it's yet to take, & how long the oldest has waited
+    pub waiting: usize,
+    pub lag: Duration,
+    /// Status updates replaced by a later one before they were sent, as it's throttled
+    pub dropped: usize,
+}
+
+impl SubscriberStatus {
+    /// The client's name & version, or `-` if it didn't identify itself
+    fn client_label(&self) -> String {
+        self.client
+            .as_ref()
+            .map_or("-".to_string(), |client| match client.version.is_empty() {
+                true => client.name.clone(),
+                false => format!("{} {}", client.name, client.version),
+            })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RecentError {
+    pub at: SystemTime,
+    pub level: log::Level,
+    pub message: String,
+}
+
+impl Default for StatusBoard {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            status: Mutex::new(ServerStatus::default()),
+        }
+    }
+}
+
+impl StatusBoard {
+    pub fn snapshot(&self) -> ServerStatus {
+        self.status.lock().expect("status").clone()
+    }
+
+    pub fn simulation_started(&self, id: Ulid) {
+        self.status.lock().expect("status").simulation = Some(id);
+    }
+
+    /// Forgets the state of the simulation which was reset, until the next has started
+    pub fn simulation_reset(&self) {
+        let mut status = self.status.lock().expect("status");
+        *status = ServerStatus {
+            subscribers: std::mem::take(&mut status.subscribers),
+            errors: std::mem::take(&mut status.errors),
+            ..Default::default()
+        };
+    }
+
+    pub fn observe_update(&self, update: &StatusUpdate) {
+        let mut status = self.status.lock().expect("status");
+        status.time = Some(update.time);
+        status.speed = Some(update.speed);
+        status.backlog = update.backlog;
+        status.flights = update.flights.len();
+    }
+
+    pub fn set_subscribers(&self, subscribers: Vec<SubscriberStatus>) {
+        self.status.lock().expect("status").subscribers = subscribers;
+    }
+
+    pub fn record_error(&self, level: log::Level, message: String) {
+        let mut status = self.status.lock().expect("status");
+        if status.errors.len() == MAX_RECENT_ERRORS {
+            status.errors.pop_front();
+        }
+        status.errors.push_back(RecentError {
+            at: SystemTime::now(),
+            level,
+            message,
+        });
+    }
+}
+
+/// Logs through `env_logger` as configured by `RUST_LOG`, also recording every warning & error on
+/// the status board whether or not they're logged, & sending records on to any clients tailing
+/// them at their own levels
+pub struct RecordingLogger {
+    inner: env_logger::Logger,
+    board: Arc<StatusBoard>,
+    tail: Arc<LogTail>,
+}
+
+impl RecordingLogger {
+    /// Installs the logger for the process, in place of `env_logger::init`
+    pub fn init(board: Arc<StatusBoard>, tail: Arc<LogTail>) -> Result<(), log::SetLoggerError> {
+        let inner = env_logger::Builder::from_default_env().build();
+        tail.set_baseline(inner.filter().max(log::LevelFilter::Warn));
+        log::set_boxed_logger(Box::new(Self { inner, board, tail }))
+    }
+}
+
+impl log::Log for RecordingLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::Level::Warn
+            || self.inner.enabled(metadata)
+            || self.tail.enabled(metadata.level())
+    }
+
+    fn log(&self, record: &log::Record) {
+        if record.level() <= log::Level::Warn {
+            self.board
+                .record_error(record.level(), record.args().to_string());
+        }
+        self.tail.publish(record);
+        if self.inner.enabled(record.metadata()) {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Serves the state of the server at `GET /status` as a page which refreshes itself, & as JSON at
+/// `GET /status.json`
+pub async fn serve_status(addr: SocketAddr, board: Arc<StatusBoard>) -> Result<(), hyper::Error> {
+    let make_service = make_service_fn(move |_| {
+        let board = board.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                std::future::ready(Ok::<_, Infallible>(handle(&request, &board)))
+            }))
+        }
+    });
+
+    tracing::info!("serving status on http://{}/status", addr);
+    hyper::Server::bind(&addr).serve(make_service).await
+}
+
+fn handle(request: &Request<Body>, board: &StatusBoard) -> Response<Body> {
+    let (content_type, body) = match (request.method(), request.uri().path()) {
+        (&Method::GET, "/status") => ("text/html; charset=utf-8", board.html()),
+        (&Method::GET, "/status.json") => ("application/json", board.json().to_string()),
+        _ => {
+            let mut response = Response::new(Body::from("not found"));
+            *response.status_mut() = StatusCode::NOT_FOUND;
+            return response;
+        }
+    };
+
+    let mut response = Response::new(Body::from(body));
+    response.headers_mut().insert(
+        hyper::header::CONTENT_TYPE,
+        hyper::header::HeaderValue::from_static(content_type),
+    );
+    response
+}
+
+impl StatusBoard {
+    fn json(&self) -> Value {
+        let status = self.snapshot();
+        let optional = |value: Option<Value>| value.unwrap_or(Value::Null);
+        Value::Object(vec![
+            (
+                "uptime_s".to_string(),
+                self.started.elapsed().as_secs().into(),
+            ),
+            (
+                "simulation".to_string(),
+                optional(status.simulation.map(|id| id.to_string().into())),
+            ),
+            ("time".to_string(), optional(status.time.map(Into::into))),
+            (
+                "speed".to_string(),
+                optional(status.speed.map(|speed| speed.to_string().into())),
+            ),
+            (
+                "backlog".to_string(),
+                optional(status.backlog.map(|backlog| {
+                    Value::Object(vec![
+                        ("emergencies".to_string(), backlog.emergencies.into()),
+                        ("resupplies".to_string(), backlog.resupplies.into()),
+                    ])
+                })),
+            ),
+            ("flights".to_string(), status.flights.into()),
+            (
+                "subscribers".to_string(),
+                Value::Array(
+                    status
+                        .subscribers
+                        .iter()
+                        .map(|subscriber| {
+                            let client = |field: fn(&ClientIdentity) -> &str| {
+                                subscriber
+                                    .client
+                                    .as_ref()
+                                    .map(|client| field(client).into())
+                            };
+                            Value::Object(vec![
+                                ("id".to_string(), subscriber.id.to_string().into()),
+                                ("stream".to_string(), subscriber.stream.into()),
+                                (
+                                    "client_name".to_string(),
+                                    optional(client(|client| &client.name)),
+                                ),
+                                (
+                                    "client_version".to_string(),
+                                    optional(client(|client| &client.version)),
+                                ),
+                                (
+                                    "connected_s".to_string(),
+                                    subscriber.connected.as_secs().into(),
+                                ),
+                                ("waiting".to_string(), subscriber.waiting.into()),
+                                (
+                                    "lag_ms".to_string(),
+                                    (subscriber.lag.as_millis() as u64).into(),
+                                ),
+                                ("dropped".to_string(), subscriber.dropped.into()),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+            (
+                "errors".to_string(),
+                Value::Array(
+                    status
+                        .errors
+                        .iter()
+                        .map(|error| {
+                            Value::Object(vec![
+                                ("at".to_string(), unix_s(error.at).into()),
+                                ("level".to_string(), error.level.to_string().into()),
+                                ("message".to_string(), error.message.as_str().into()),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+        ])
+    }
+
+    fn html(&self) -> String {
+        let status = self.snapshot();
+        let units = Units::default();
+        let mut facts = vec![
+            (
+                "Uptime",
+                units::format_duration(self.started.elapsed().as_secs()),
+            ),
+            (
+                "Simulation",
+                status
+                    .simulation
+                    .map_or("not running".to_string(), |id| id.to_string()),
+            ),
+            (
+                "Simulated time",
+                status
+                    .time
+                    .map_or("-".to_string(), |time| units.format_time(time)),
+            ),
+            (
+                "Speed",
+                status
+                    .speed
+                    .map_or("-".to_string(), |speed| speed.to_string()),
+            ),
+            ("Active flights", status.flights.to_string()),
+        ];
+        if let Some(backlog) = status.backlog {
+            facts.push((
+                "Backlog",
+                format!(
+                    "{} orders ({} emergencies, {} resupplies)",
+                    backlog.len(),
+                    backlog.emergencies,
+                    backlog.resupplies
+                ),
+            ));
+        }
+
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <meta http-equiv=\"refresh\" content=\"{}\">\n<title>Scheduler status</title>\n\
+             <style>body {{ font-family: sans-serif; margin: 2em; }} \
+             td, th {{ padding: 2px 12px 2px 0; text-align: left; }}</style>\n\
+             </head>\n<body>\n<h1>Scheduler status</h1>\n<table>\n",
+            REFRESH_S
+        );
+        for (label, value) in facts {
+            let _ = writeln!(
+                html,
+                "<tr><th>{}</th><td>{}</td></tr>",
+                label,
+                escape(&value)
+            );
+        }
+        html.push_str("</table>\n");
+
+        let _ = writeln!(html, "<h2>Subscribers ({})</h2>", status.subscribers.len());
+        html.push_str(
+            "<table>\n<tr><th>Id</th><th>Stream</th><th>Client</th><th>Connected</th>\
+             <th>Events waiting</th><th>Lag</th><th>Updates dropped</th></tr>\n",
+        );
+        for subscriber in &status.subscribers {
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{} ms</td>\
+                 <td>{}</td></tr>",
+                subscriber.id,
+                subscriber.stream,
+                escape(&subscriber.client_label()),
+                units::format_duration(subscriber.connected.as_secs()),
+                subscriber.waiting,
+                subscriber.lag.as_millis(),
+                subscriber.dropped
+            );
+        }
+        html.push_str("</table>\n");
+
+        html.push_str("<h2>Recent errors</h2>\n");
+        match status.errors.is_empty() {
+            true => html.push_str("<p>None</p>\n"),
+            false => {
+                html.push_str("<table>\n<tr><th>Time</th><th>Level</th><th>Message</th></tr>\n");
+                for error in status.errors.iter().rev() {
+                    let _ = writeln!(
+                        html,
+                        "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+                        units.format_time(unix_s(error.at)),
+                        error.level,
+                        escape(&error.message)
+                    );
+                }
+                html.push_str("</table>\n");
+            }
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+}
+
+fn unix_s(at: SystemTime) -> u64 {
+    at.duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_status() {
+        let board = StatusBoard::default();
+        let id = Ulid::new();
+        board.simulation_started(id);
+        board.observe_update(&StatusUpdate {
+            time: 25_200,
+            flights: vec![],
+            speed: Speed::RealTime,
+            backlog: Some(Backlog {
+                emergencies: 1,
+                resupplies: 2,
+                oldest: Some(25_000),
+            }),
+            sectors: vec![],
+        });
+        board.set_subscribers(vec![SubscriberStatus {
+            id,
+            stream: "monitor",
+            client: Some(ClientIdentity {
+                name: "<client>".to_string(),
+                version: "0.1.0".to_string(),
+            }),
+            connected: Duration::from_secs(90),
+            waiting: 3,
+            lag: Duration::from_millis(250),
+            dropped: 2,
+        }]);
+        for i in 0..MAX_RECENT_ERRORS + 1 {
+            board.record_error(log::Level::Error, format!("<failure {}>", i));
+        }
+
+        // Only the most recent errors are kept
+        let status = board.snapshot();
+        assert_eq!(status.errors.len(), MAX_RECENT_ERRORS);
+        assert_eq!(status.errors[0].message, "<failure 1>");
+
+        let get = |path: &str| Request::get(path).body(Body::empty()).expect("request");
+        let page = handle(&get("/status"), &board);
+        assert_eq!(page.status(), StatusCode::OK);
+        let page = hyper::body::to_bytes(page.into_body()).await.expect("body");
+        let page = std::str::from_utf8(&page).expect("utf-8");
+        assert!(page.contains("07:00:00"));
+        assert!(page.contains("3 orders (1 emergencies, 2 resupplies)"));
+        assert!(page.contains("&lt;failure 20&gt;"));
+        assert!(page.contains("&lt;client&gt; 0.1.0"));
+
+        let json = handle(&get("/status.json"), &board);
+        let json = hyper::body::to_bytes(json.into_body()).await.expect("body");
+        let json = Value::parse(std::str::from_utf8(&json).expect("utf-8")).expect("json");
+        assert_eq!(json.get("time").and_then(Value::as_u64), Some(25_200));
+        assert_eq!(
+            json.get("subscribers")
+                .and_then(Value::as_array)
+                .map(|subscribers| subscribers.len()),
+            Some(1)
+        );
+        let subscriber = json
+            .get("subscribers")
+            .and_then(Value::as_array)
+            .and_then(|subscribers| subscribers.first())
+            .expect("subscriber");
+        assert_eq!(subscriber.get("lag_ms").and_then(Value::as_u64), Some(250));
+        assert_eq!(subscriber.get("dropped").and_then(Value::as_u64), Some(2));
+
+        // The simulation's state is forgotten on reset, but not the subscribers or errors
+        board.simulation_reset();
+        let status = board.snapshot();
+        assert!(status.simulation.is_none() && status.time.is_none());
+        assert_eq!(status.subscribers.len(), 1);
+        assert_eq!(status.errors.len(), MAX_RECENT_ERRORS);
+
+        assert_eq!(
+            handle(&get("/other"), &board).status(),
+            StatusCode::NOT_FOUND
+        );
+    }
+}
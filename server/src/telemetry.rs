@@ -0,0 +1,379 @@
+//! A minimal `tracing` subscriber which exports finished spans to an OpenTelemetry collector over
+//! OTLP/HTTP, so that slow scheduling ticks can be broken down span by span. Events are forwarded
+//! to `log` as they would be without a subscriber.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use schema::json::Value;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::subscriber::{self, Subscriber};
+use tracing::{Event, Level, Metadata};
+
+/// How often finished spans are sent to the collector
+const EXPORT_INTERVAL: Duration = Duration::from_secs(5);
+/// Most finished spans held while waiting to be exported, after which the newest are dropped
+const MAX_BUFFERED_SPANS: usize = 50_000;
+
+thread_local! {
+    /// Spans entered on this thread, innermost last
+    static ENTERED: RefCell<Vec<Id>> = const { RefCell::new(vec![]) };
+}
+
+/// Traces the process with an `OtlpSubscriber`, exporting its spans to the collector at the
+/// provided endpoint (e.g. `http://localhost:4318`) from a background task.
+/// Must be called from within a tokio runtime.
+pub fn init_otlp(
+    endpoint: &str,
+    service_name: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let uri: hyper::Uri = format!("{}/v1/traces", endpoint.trim_end_matches('/')).parse()?;
+    let subscriber = OtlpSubscriber::default();
+    let finished = subscriber.finished.clone();
+    subscriber::set_global_default(subscriber)?;
+
+    let service_name = service_name.to_string();
+    tokio::spawn(async move {
+        let client = hyper::Client::new();
+        let mut interval = tokio::time::interval(EXPORT_INTERVAL);
+        loop {
+            interval.tick().await;
+            let batch = std::mem::take(&mut *finished.lock().expect("finished spans"));
+            if batch.is_empty() {
+                continue;
+            }
+
+            let request = hyper::Request::post(uri.clone())
+                .header("content-type", "application/json")
+                .body(hyper::Body::from(
+                    otlp_json(&service_name, &batch).to_string(),
+                ))
+                .expect("export request");
+            match client.request(request).await {
+                Ok(response) if response.status().is_success() => {}
+                Ok(response) => log::warn!("failed to export spans: {}", response.status()),
+                Err(e) => log::warn!("failed to export spans: {}", e),
+            }
+        }
+    });
+    log::info!("exporting traces to {}", endpoint);
+    Ok(())
+}
+
+/// A span which has been closed, ready to be exported
+#[derive(Clone, Debug)]
+struct FinishedSpan {
+    trace_id: u128,
+    span_id: u64,
+    parent: Option<u64>,
+    name: &'static str,
+    start: SystemTime,
+    end: SystemTime,
+    attributes: Vec<(&'static str, String)>,
+}
+
+struct OpenSpan {
+    name: &'static str,
+    trace_id: u128,
+    parent: Option<u64>,
+    start: SystemTime,
+    attributes: Vec<(&'static str, String)>,
+    /// Handles to the span which haven't been dropped yet
+    refs: usize,
+}
+
+/// Records spans at `DEBUG` & above, keeping each once it closes until it's exported
+#[derive(Default)]
+pub struct OtlpSubscriber {
+    next_id: AtomicU64,
+    open: Mutex<HashMap<u64, OpenSpan>>,
+    finished: Arc<Mutex<Vec<FinishedSpan>>>,
+}
+
+impl Subscriber for OtlpSubscriber {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        match metadata.is_span() {
+            true => *metadata.level() <= Level::DEBUG,
+            false => log::logger().enabled(
+                &log::Metadata::builder()
+                    .level(log_level(*metadata.level()))
+                    .target(metadata.target())
+                    .build(),
+            ),
+        }
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let span_id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let parent = match span.parent() {
+            Some(parent) => Some(parent.into_u64()),
+            None if span.is_contextual() => {
+                ENTERED.with(|entered| entered.borrow().last().map(Id::into_u64))
+            }
+            None => None,
+        };
+
+        let mut open = self.open.lock().expect("open spans");
+        let trace_id = parent
+            .and_then(|parent| open.get(&parent))
+            .map_or_else(|| ulid::Ulid::new().0, |parent| parent.trace_id);
+        let mut attributes = Fields(vec![]);
+        span.record(&mut attributes);
+        open.insert(
+            span_id,
+            OpenSpan {
+                name: span.metadata().name(),
+                trace_id,
+                parent,
+                start: SystemTime::now(),
+                attributes: attributes.0,
+                refs: 1,
+            },
+        );
+        Id::from_u64(span_id)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        if let Some(open) = self
+            .open
+            .lock()
+            .expect("open spans")
+            .get_mut(&span.into_u64())
+        {
+            let mut attributes = Fields(std::mem::take(&mut open.attributes));
+            values.record(&mut attributes);
+            open.attributes = attributes.0;
+        }
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let metadata = event.metadata();
+        let mut message = Message(String::new());
+        event.record(&mut message);
+        log::logger().log(
+            &log::Record::builder()
+                .level(log_level(*metadata.level()))
+                .target(metadata.target())
+                .module_path(metadata.module_path())
+                .file(metadata.file())
+                .line(metadata.line())
+                .args(format_args!("{}", message.0))
+                .build(),
+        );
+    }
+
+    fn enter(&self, span: &Id) {
+        ENTERED.with(|entered| entered.borrow_mut().push(span.clone()));
+    }
+
+    fn exit(&self, span: &Id) {
+        ENTERED.with(|entered| {
+            let mut entered = entered.borrow_mut();
+            if let Some(i) = entered.iter().rposition(|id| id == span) {
+                entered.remove(i);
+            }
+        });
+    }
+
+    fn clone_span(&self, span: &Id) -> Id {
+        if let Some(open) = self
+            .open
+            .lock()
+            .expect("open spans")
+            .get_mut(&span.into_u64())
+        {
+            open.refs += 1;
+        }
+        span.clone()
+    }
+
+    fn try_close(&self, span: Id) -> bool {
+        let mut open = self.open.lock().expect("open spans");
+        let Some(closing) = open.get_mut(&span.into_u64()) else {
+            return false;
+        };
+        closing.refs -= 1;
+        if closing.refs > 0 {
+            return false;
+        }
+
+        let closed = open.remove(&span.into_u64()).expect("open span");
+        drop(open);
+        let mut finished = self.finished.lock().expect("finished spans");
+        if finished.len() < MAX_BUFFERED_SPANS {
+            finished.push(FinishedSpan {
+                trace_id: closed.trace_id,
+                span_id: span.into_u64(),
+                parent: closed.parent,
+                name: closed.name,
+                start: closed.start,
+                end: SystemTime::now(),
+                attributes: closed.attributes,
+            });
+        }
+        true
+    }
+}
+
+/// Collects the fields of a span as attributes
+struct Fields(Vec<(&'static str, String)>);
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.record_str(field, &format!("{:?}", value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.retain(|(name, _)| *name != field.name());
+        self.0.push((field.name(), value.to_string()));
+    }
+}
+
+/// Formats the fields of an event like `log` would, the message followed by any other fields
+struct Message(String);
+
+impl Visit for Message {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        let _ = match field.name() {
+            "message" => write!(self.0, "{:?}", value),
+            name => write!(self.0, " {}={:?}", name, value),
+        };
+    }
+}
+
+fn log_level(level: Level) -> log::Level {
+    match level {
+        Level::ERROR => log::Level::Error,
+        Level::WARN => log::Level::Warn,
+        Level::INFO => log::Level::Info,
+        Level::DEBUG => log::Level::Debug,
+        Level::TRACE => log::Level::Trace,
+    }
+}
+
+/// Encodes a batch of spans as an OTLP/JSON `ExportTraceServiceRequest`
+fn otlp_json(service_name: &str, spans: &[FinishedSpan]) -> Value {
+    let nanos = |time: SystemTime| {
+        let nanos = time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        Value::from(nanos.to_string())
+    };
+    let attribute = |key: &str, value: &str| {
+        Value::Object(vec![
+            ("key".into(), key.into()),
+            (
+                "value".into(),
+                Value::Object(vec![("stringValue".into(), value.into())]),
+            ),
+        ])
+    };
+
+    let spans = spans
+        .iter()
+        .map(|span| {
+            let mut fields = vec![
+                ("traceId".into(), format!("{:032x}", span.trace_id).into()),
+                ("spanId".into(), format!("{:016x}", span.span_id).into()),
+            ];
+            if let Some(parent) = span.parent {
+                fields.push(("parentSpanId".into(), format!("{:016x}", parent).into()));
+            }
+            fields.extend([
+                ("name".into(), span.name.into()),
+                // SPAN_KIND_INTERNAL
+                ("kind".into(), 1_u64.into()),
+                ("startTimeUnixNano".into(), nanos(span.start)),
+                ("endTimeUnixNano".into(), nanos(span.end)),
+                (
+                    "attributes".into(),
+                    Value::Array(
+                        span.attributes
+                            .iter()
+                            .map(|(key, value)| attribute(key, value))
+                            .collect(),
+                    ),
+                ),
+            ]);
+            Value::Object(fields)
+        })
+        .collect();
+
+    Value::Object(vec![(
+        "resourceSpans".into(),
+        Value::Array(vec![Value::Object(vec![
+            (
+                "resource".into(),
+                Value::Object(vec![(
+                    "attributes".into(),
+                    Value::Array(vec![attribute("service.name", service_name)]),
+                )]),
+            ),
+            (
+                "scopeSpans".into(),
+                Value::Array(vec![Value::Object(vec![
+                    (
+                        "scope".into(),
+                        Value::Object(vec![("name".into(), "server".into())]),
+                    ),
+                    ("spans".into(), Value::Array(spans)),
+                ])]),
+            ),
+        ])]),
+    )])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_otlp_subscriber() {
+        let subscriber = OtlpSubscriber::default();
+        let finished = subscriber.finished.clone();
+
+        tracing::subscriber::with_default(subscriber, || {
+            let minute = tracing::debug_span!("minute", time = 60_u64).entered();
+            let launch = tracing::debug_span!("launch", launched = tracing::field::Empty);
+            launch.in_scope(|| launch.record("launched", 2));
+            drop(launch);
+            // Spans below `DEBUG` aren't recorded
+            drop(tracing::trace_span!("detail").entered());
+            drop(minute);
+        });
+
+        let finished = finished.lock().expect("finished spans");
+        assert_eq!(finished.len(), 2);
+        let (launch, minute) = (&finished[0], &finished[1]);
+        assert_eq!((launch.name, minute.name), ("launch", "minute"));
+        assert_eq!(launch.parent, Some(minute.span_id));
+        assert_eq!(launch.trace_id, minute.trace_id);
+        assert_eq!(launch.attributes, vec![("launched", "2".to_string())]);
+        assert_eq!(minute.attributes, vec![("time", "60".to_string())]);
+
+        let json = otlp_json("scheduler", &finished);
+        let first =
+            |value: &Value, key: &str| value.get(key).and_then(Value::as_array)?.first().cloned();
+        let scope_spans = first(&json, "resourceSpans")
+            .and_then(|resource| first(&resource, "scopeSpans"))
+            .expect("scope spans");
+        let spans = scope_spans
+            .get("spans")
+            .and_then(Value::as_array)
+            .expect("spans");
+        assert_eq!(spans[0].get("name").and_then(Value::as_str), Some("launch"));
+        assert_eq!(
+            spans[0].get("parentSpanId").and_then(Value::as_str),
+            Some(format!("{:016x}", minute.span_id).as_str())
+        );
+        assert!(spans[1].get("parentSpanId").is_none());
+    }
+}
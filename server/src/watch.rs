@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use futures::channel::{mpsc, oneshot};
+use scheduler_core::Submitted;
+use schema::{json, DestinationNames, Order};
+
+use crate::http::parse_orders;
+use crate::OrderSubmission;
+
+/// Extension of the results written alongside each file once it has been submitted
+const RESULT_EXTENSION: &str = "result.json";
+
+/// Watches a directory for files of orders dropped into it by other systems, e.g. the exports of
+/// legacy order management, forwarding each file's orders to be queued by the running simulation.
+/// Files are CSVs like `ORDERS_CSV_PATH` (or Excel workbooks of the same columns, with the `xlsx`
+/// feature) or JSON like the HTTP endpoint accepts, & are submitted
+/// as a whole: if any order is invalid, none of the file's orders are queued. Once a file has been
+/// submitted, why it was rejected or which orders it queued is written alongside it (with a
+/// `.result.json` extension), which also marks it so that it isn't submitted again.
+pub struct OrderDirectory {
+    dir: PathBuf,
+    /// Destinations the orders in files may be for, any others rejecting their file
+    names: DestinationNames,
+    /// Size & modification time of each file waiting to be submitted as of the last poll. Files
+    /// are only read once they've stopped changing, so that those still being written aren't.
+    pending: HashMap<PathBuf, (u64, SystemTime)>,
+}
+
+impl OrderDirectory {
+    pub fn new(dir: impl Into<PathBuf>, names: DestinationNames) -> Self {
+        Self {
+            dir: dir.into(),
+            names,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Polls the directory every interval until the simulation stops taking submissions
+    pub async fn watch(
+        mut self,
+        interval: Duration,
+        submissions: mpsc::UnboundedSender<OrderSubmission>,
+    ) {
+        tracing::info!("watching {} for orders", self.dir.display());
+        let mut interval = tokio::time::interval(interval);
+        while !submissions.is_closed() {
+            interval.tick().await;
+            if let Err(e) = self.poll(&submissions).await {
+                tracing::warn!("failed to read {}: {}", self.dir.display(), e);
+            }
+        }
+    }
+
+    /// Submits the files which haven't changed since the last poll, returning how many were
+    pub async fn poll(
+        &mut self,
+        submissions: &mpsc::UnboundedSender<OrderSubmission>,
+    ) -> std::io::Result<usize> {
+        let mut entries = tokio::fs::read_dir(&self.dir).await?;
+        let mut seen = HashMap::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let metadata = entry.metadata().await?;
+            if !metadata.is_file() || !is_order_file(&path) || result_path(&path).exists() {
+                continue;
+            }
+            seen.insert(path, (metadata.len(), metadata.modified()?));
+        }
+
+        let mut submitted = 0;
+        for (path, version) in &seen {
+            if self.pending.get(path) != Some(version) {
+                continue;
+            }
+            let Some(result) = submit(path, &self.names, submissions).await else {
+                // Left for a later poll, once there's a simulation to queue them
+                continue;
+            };
+            tokio::fs::write(result_path(path), format!("{:#}\n", result)).await?;
+            submitted += 1;
+        }
+        self.pending = seen;
+
+        Ok(submitted)
+    }
+}
+
+/// Whether a file looks like one to submit, rather than e.g. a result or a hidden temporary file
+fn is_order_file(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("");
+    // Excel keeps a `~$`-prefixed lock file beside each workbook it has open
+    !name.starts_with('.')
+        && !name.starts_with("~$")
+        && !name.ends_with(RESULT_EXTENSION)
+        && (name.ends_with(".csv") || name.ends_with(".xlsx") || name.ends_with(".json"))
+}
+
+fn result_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(RESULT_EXTENSION);
+    path.with_file_name(name)
+}
+
+/// Parses & submits the orders of a file, returning the result to write alongside it, unless
+/// no simulation is taking submissions
+async fn submit(
+    path: &Path,
+    names: &DestinationNames,
+    submissions: &mpsc::UnboundedSender<OrderSubmission>,
+) -> Option<json::Value> {
+    let file = path.display().to_string();
+    let orders = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("csv" | "xlsx") => Order::from_csv_with(&file, |name| names.resolve(name))
+            .await
+            .map_err(|e| e.to_string()),
+        _ => match tokio::fs::read_to_string(path).await {
+            Ok(contents) => parse_orders(&contents, names),
+            Err(e) => Err(e.to_string()),
+        },
+    };
+    let orders = match orders {
+        Ok(orders) => orders,
+        Err(e) => {
+            tracing::warn!("rejected orders from {}: {}", file, e);
+            return Some(rejected(&file, &e));
+        }
+    };
+
+    let count = orders.len();
+    let (tx, rx) = oneshot::channel();
+    submissions.unbounded_send((orders, None, tx)).ok()?;
+    match rx.await.ok()? {
+        Ok(Submitted::Accepted(ids)) => {
+            tracing::info!("queued {} orders from {}", count, file);
+            Some(json::Value::Object(vec![
+                ("file".to_string(), file.into()),
+                ("accepted".to_string(), count.into()),
+                (
+                    "order_ids".to_string(),
+                    json::Value::Array(ids.into_iter().map(|id| id.0.into()).collect()),
+                ),
+            ]))
+        }
+        // Only submissions with an idempotency key can be duplicates
+        Ok(Submitted::AlreadyKnown) => Some(rejected(&file, "already submitted")),
+        Err(e) => {
+            tracing::warn!("rejected orders from {}: {}", file, e);
+            Some(rejected(&file, &e))
+        }
+    }
+}
+
+fn rejected(file: &str, error: &str) -> json::Value {
+    json::Value::Object(vec![
+        ("file".to_string(), file.into()),
+        ("accepted".to_string(), 0usize.into()),
+        ("error".to_string(), error.into()),
+    ])
+}
+
+#[cfg(test)]
+mod test {
+    use futures::StreamExt;
+    use schema::{testkit, DestinationId, OrderId};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_order_directory() {
+        let dir = std::env::temp_dir().join(format!("orders-{}", ulid::Ulid::new()));
+        std::fs::create_dir_all(&dir).expect("create dir");
+        std::fs::write(
+            dir.join("export.csv"),
+            "0, Toronto, Emergency\n0, Ottawa, Resupply\n",
+        )
+        .expect("write csv");
+        std::fs::write(dir.join("bad.json"), r#"[{"destination": "Toronto"}]"#)
+            .expect("write json");
+        std::fs::write(dir.join("unknown.csv"), "0, Nowhere Dropped, Resupply\n")
+            .expect("write csv");
+        std::fs::write(dir.join("notes.txt"), "not orders").expect("write txt");
+
+        // Every submission is accepted, as the simulation would for known destinations
+        let (tx, mut rx) = mpsc::unbounded::<OrderSubmission>();
+        tokio::spawn(async move {
+            let mut next_id = 0;
+            while let Some((orders, _, reply)) = rx.next().await {
+                let ids = (next_id..next_id + orders.len() as u64)
+                    .map(OrderId)
+                    .collect();
+                next_id += orders.len() as u64;
+                let _ = reply.send(Ok(Submitted::Accepted(ids)));
+            }
+        });
+
+        // Files are only submitted once they're unchanged since the last poll
+        let names = testkit::destination_names(&["Toronto", "Ottawa"]);
+        let mut watcher = OrderDirectory::new(&dir, names);
+        assert_eq!(watcher.poll(&tx).await.expect("poll"), 0);
+        assert_eq!(watcher.poll(&tx).await.expect("poll"), 3);
+
+        let result = |name: &str| {
+            let contents = std::fs::read_to_string(dir.join(name)).expect("read result");
+            json::Value::parse(&contents).expect("parse result")
+        };
+        let accepted = result("export.csv.result.json");
+        assert_eq!(
+            accepted.get("accepted").and_then(json::Value::as_u64),
+            Some(2)
+        );
+        let rejected = result("bad.json.result.json");
+        assert_eq!(
+            rejected.get("accepted").and_then(json::Value::as_u64),
+            Some(0)
+        );
+        assert!(rejected
+            .get("error")
+            .and_then(json::Value::as_str)
+            .is_some());
+        // Files naming destinations which aren't known are rejected, without interning the names
+        let unknown = result("unknown.csv.result.json");
+        assert_eq!(
+            unknown.get("accepted").and_then(json::Value::as_u64),
+            Some(0)
+        );
+        assert_eq!(DestinationId::get("Nowhere Dropped"), None);
+        assert!(!dir.join("notes.txt.result.json").exists());
+
+        // Files with results aren't submitted again
+        assert_eq!(watcher.poll(&tx).await.expect("poll"), 0);
+        assert_eq!(watcher.poll(&tx).await.expect("poll"), 0);
+
+        std::fs::remove_dir_all(&dir).expect("remove dir");
+    }
+}
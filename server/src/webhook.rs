@@ -0,0 +1,473 @@
+//! Outbound webhooks, POSTing a JSON payload to each configured URL as orders are delivered, given
+//! up on or cancelled, so that other systems (e.g. hospital inventory) can react to them.
+
+use std::env;
+use std::num::NonZeroU32;
+use std::time::Duration;
+
+use futures::{Stream, StreamExt};
+use scheduler_core::{OrderOutcome, SettledOrder};
+use schema::json::Value;
+use ulid::Ulid;
+
+/// Header carrying the identifier of the event, the same for every attempt to deliver it & for
+/// events replayed after the simulation resumes from a fault
+pub const ID_HEADER: &str = "x-webhook-id";
+/// Header carrying `sha256=` followed by the hex HMAC-SHA256 of the body, keyed by the secret
+pub const SIGNATURE_HEADER: &str = "x-webhook-signature";
+
+/// URLs to POST settled orders to, along with how payloads are signed & retried
+#[derive(Clone, Debug)]
+pub struct Webhooks {
+    urls: Vec<hyper::Uri>,
+    /// Key payloads are signed with, unsigned if unset
+    secret: Option<Vec<u8>>,
+    max_attempts: NonZeroU32,
+    /// Time waited before retrying a failed delivery, doubling with each attempt
+    backoff: Duration,
+}
+
+impl Webhooks {
+    pub const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+    pub const DEFAULT_BACKOFF: Duration = Duration::from_secs(1);
+    /// Most settled orders being delivered at once, beyond which later ones wait their turn
+    pub const MAX_CONCURRENT_DELIVERIES: usize = 32;
+
+    pub fn new(urls: Vec<hyper::Uri>) -> Self {
+        Self {
+            urls,
+            secret: None,
+            max_attempts: NonZeroU32::new(Self::DEFAULT_MAX_ATTEMPTS).expect("attempts"),
+            backoff: Self::DEFAULT_BACKOFF,
+        }
+    }
+
+    /// Reads the comma separated URLs from `WEBHOOK_URLS`, signing payloads with `WEBHOOK_SECRET`
+    /// & making up to `WEBHOOK_MAX_ATTEMPTS` attempts to deliver each where they're set.
+    /// Returns `None` unless there are any URLs.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let urls = parse_urls(&env::var("WEBHOOK_URLS").unwrap_or_default())?;
+        if urls.is_empty() {
+            return Ok(None);
+        }
+
+        let mut webhooks = Self::new(urls);
+        if let Ok(secret) = env::var("WEBHOOK_SECRET") {
+            webhooks = webhooks.with_secret(secret);
+        }
+        if let Ok(attempts) = env::var("WEBHOOK_MAX_ATTEMPTS") {
+            let attempts = attempts
+                .parse()
+                .map_err(|_| format!("invalid WEBHOOK_MAX_ATTEMPTS: {}", attempts))?;
+            webhooks = webhooks.with_retries(attempts, Self::DEFAULT_BACKOFF);
+        }
+        Ok(Some(webhooks))
+    }
+
+    /// Sign each payload with the provided secret, see `SIGNATURE_HEADER`
+    pub fn with_secret(mut self, secret: impl Into<Vec<u8>>) -> Self {
+        self.secret = Some(secret.into());
+        self
+    }
+
+    /// Make up to the provided number of attempts to deliver each payload, waiting the backoff
+    /// (doubled each time) between them
+    pub fn with_retries(mut self, max_attempts: NonZeroU32, backoff: Duration) -> Self {
+        self.max_attempts = max_attempts;
+        self.backoff = backoff;
+        self
+    }
+
+    /// Delivers each order settled, along with the id of its simulation, to every URL until the
+    /// stream ends. Deliveries are made concurrently (up to `MAX_CONCURRENT_DELIVERIES` orders at
+    /// once), so that a slow or failing URL doesn't hold the others up, which means payloads may
+    /// arrive out of order.
+    pub async fn deliver(&self, orders: impl Stream<Item = (Ulid, SettledOrder)>) {
+        let client = hyper::Client::new();
+        orders
+            .for_each_concurrent(Self::MAX_CONCURRENT_DELIVERIES, |(simulation, settled)| {
+                let id = format!("{}-{}", simulation, settled.receipt.order.id);
+                let body = payload(&id, simulation, &settled).to_string();
+                let client = &client;
+                async move {
+                    let posts = self
+                        .urls
+                        .iter()
+                        .map(|url| self.post(client, url, &id, &body));
+                    futures::future::join_all(posts).await;
+                }
+            })
+            .await;
+    }
+
+    /// POSTs a payload to a URL, retrying on connection errors, server errors & rate limiting
+    async fn post(
+        &self,
+        client: &hyper::Client<hyper::client::HttpConnector>,
+        url: &hyper::Uri,
+        id: &str,
+        body: &str,
+    ) {
+        let mut backoff = self.backoff;
+        for attempt in 1..=self.max_attempts.get() {
+            let mut request = hyper::Request::post(url.clone())
+                .header("content-type", "application/json")
+                .header(ID_HEADER, id);
+            if let Some(secret) = &self.secret {
+                request = request.header(SIGNATURE_HEADER, sign(secret, body.as_bytes()));
+            }
+            let request = request
+                .body(hyper::Body::from(body.to_string()))
+                .expect("webhook request");
+
+            let error = match client.request(request).await {
+                Ok(response) if response.status().is_success() => return,
+                Ok(response)
+                    if !(response.status().is_server_error()
+                        || response.status() == hyper::StatusCode::TOO_MANY_REQUESTS) =>
+                {
+                    tracing::warn!("webhook {} rejected {}: {}", url, id, response.status());
+                    return;
+                }
+                Ok(response) => response.status().to_string(),
+                Err(e) => e.to_string(),
+            };
+            if attempt == self.max_attempts.get() {
+                tracing::error!(
+                    "gave up delivering {} to webhook {} after {} attempts: {}",
+                    id,
+                    url,
+                    attempt,
+                    error
+                );
+                return;
+            }
+            tracing::warn!(
+                "failed to deliver {} to webhook {}, retrying: {}",
+                id,
+                url,
+                error
+            );
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+        }
+    }
+}
+
+/// Parses comma separated webhook URLs, which must be `http` as there's no TLS support
+fn parse_urls(urls: &str) -> Result<Vec<hyper::Uri>, String> {
+    urls.split(',')
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .map(|url| {
+            let uri = url
+                .parse::<hyper::Uri>()
+                .map_err(|e| format!("invalid webhook url `{}`: {}", url, e))?;
+            match uri.scheme_str() {
+                Some("http") => Ok(uri),
+                _ => Err(format!(
+                    "invalid webhook url `{}`: only http is supported",
+                    url
+                )),
+            }
+        })
+        .collect()
+}
+
+/// Describes a settled order, e.g.
+/// `{"id": "...", "event": "order.delivered", "simulation": "...", "order": {...}, ...}`
+pub fn payload(id: &str, simulation: Ulid, settled: &SettledOrder) -> Value {
+    let receipt = &settled.receipt;
+    let event = match settled.outcome {
+        OrderOutcome::Delivered => "order.delivered",
+        OrderOutcome::Failed => "order.failed",
+        OrderOutcome::Cancelled => "order.cancelled",
+    };
+    let mut order = receipt.order.to_json();
+    if let Value::Object(fields) = &mut order {
+        fields.insert(0, ("id".to_string(), receipt.order.id.0.into()));
+    }
+
+    Value::Object(vec![
+        ("id".to_string(), id.into()),
+        ("event".to_string(), event.into()),
+        ("simulation".to_string(), simulation.to_string().into()),
+        ("order".to_string(), order),
+        (
+            "status".to_string(),
+            format!("{:?}", receipt.status).to_lowercase().into(),
+        ),
+        ("queued_at".to_string(), receipt.queued_at.into()),
+        (
+            "flight".to_string(),
+            receipt.flight.map(|flight| flight.to_string()).into(),
+        ),
+        (
+            "carrier".to_string(),
+            receipt.carrier.map(|carrier| carrier.0 as u64).into(),
+        ),
+        ("launch_time".to_string(), receipt.launch_time.into()),
+        ("delivery_time".to_string(), receipt.delivery_time.into()),
+        ("wait_s".to_string(), receipt.wait_s.into()),
+        (
+            "marginal_distance_m".to_string(),
+            receipt.marginal_distance_m.map(u64::from).into(),
+        ),
+        ("failure".to_string(), receipt.failure.clone().into()),
+        (
+            "proof".to_string(),
+            receipt
+                .proof
+                .as_ref()
+                .map(|proof| {
+                    Value::Object(vec![
+                        ("drop_north_m".to_string(), proof.drop_north_m.into()),
+                        ("drop_east_m".to_string(), proof.drop_east_m.into()),
+                        ("photo_hash".to_string(), proof.photo_hash.clone().into()),
+                        (
+                            "recipient_code".to_string(),
+                            proof.recipient_code.clone().into(),
+                        ),
+                    ])
+                })
+                .into(),
+        ),
+    ])
+}
+
+/// Returns the value of `SIGNATURE_HEADER` for a body signed with the provided secret
+pub fn sign(secret: &[u8], body: &[u8]) -> String {
+    let mac = hmac_sha256(secret, body);
+    let hex = mac
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+    format!("sha256={}", hex)
+}
+
+/// HMAC as of RFC 2104, over SHA-256
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    const BLOCK_SIZE: usize = 64;
+
+    let mut block = [0; BLOCK_SIZE];
+    match key.len() > BLOCK_SIZE {
+        true => block[..32].copy_from_slice(&sha256(key)),
+        false => block[..key.len()].copy_from_slice(key),
+    }
+    let pad = |byte: u8| block.iter().map(|b| b ^ byte).collect::<Vec<_>>();
+
+    let mut inner = pad(0x36);
+    inner.extend_from_slice(message);
+    let mut outer = pad(0x5c);
+    outer.extend_from_slice(&sha256(&inner));
+    sha256(&outer)
+}
+
+/// SHA-256 as of FIPS 180-4
+fn sha256(message: &[u8]) -> [u8; 32] {
+    const K: [u32; 64] = [
+        0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4,
+        0xab1c5ed5, 0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe,
+        0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f,
+        0x4a7484aa, 0x5cb0a9dc, 0x76f988da, 0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7,
+        0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc,
+        0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b,
+        0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070, 0x19a4c116,
+        0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+        0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7,
+        0xc67178f2,
+    ];
+    let mut hash: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+
+    // Padded with a one bit, zeros & the length in bits to a multiple of the block size
+    let mut padded = message.to_vec();
+    padded.push(0x80);
+    while padded.len() % 64 != 56 {
+        padded.push(0);
+    }
+    padded.extend_from_slice(&(message.len() as u64 * 8).to_be_bytes());
+
+    for chunk in padded.chunks_exact(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in chunk.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = hash;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (word, value) in hash.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *word = word.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0; 32];
+    for (bytes, word) in digest.chunks_exact_mut(4).zip(hash) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::Infallible;
+    use std::sync::{Arc, Mutex};
+
+    use hyper::service::{make_service_fn, service_fn};
+    use schema::{testkit, DeliveryProof, OrderReceipt, OrderStatus, Priority};
+
+    use super::*;
+
+    #[test]
+    fn test_sign() {
+        // RFC 4231, test case 2
+        assert_eq!(
+            sign(b"Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        // Keys longer than a block are hashed first (test case 6)
+        assert_eq!(
+            sign(
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            ),
+            "sha256=60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+    }
+
+    #[test]
+    fn test_parse_urls() -> Result<(), String> {
+        let urls = parse_urls(" http://localhost:8080/a, ,http://[::1]/b ")?;
+        assert_eq!(urls, ["http://localhost:8080/a", "http://[::1]/b"]);
+        assert!(parse_urls("")?.is_empty());
+
+        // Webhooks are POSTed over plain HTTP, so others would fail every delivery
+        for url in ["https://localhost/a", "localhost/a", "ftp://localhost"] {
+            assert!(parse_urls(url).is_err(), "{}", url);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_deliver() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        // An endpoint which fails the first request, recording each it receives
+        let received = Arc::new(Mutex::new(Vec::<(String, String, String)>::new()));
+        let make_service = make_service_fn({
+            let received = received.clone();
+            move |_| {
+                let received = received.clone();
+                async move {
+                    Ok::<_, Infallible>(service_fn(move |request: hyper::Request<hyper::Body>| {
+                        let received = received.clone();
+                        async move {
+                            let header = |name| {
+                                let value = request.headers().get(name).expect("header");
+                                value.to_str().expect("header").to_string()
+                            };
+                            let (id, signature) = (header(ID_HEADER), header(SIGNATURE_HEADER));
+                            let body = hyper::body::to_bytes(request.into_body()).await?;
+                            let mut received = received.lock().expect("received");
+                            let status = match received.is_empty() {
+                                true => hyper::StatusCode::SERVICE_UNAVAILABLE,
+                                false => hyper::StatusCode::OK,
+                            };
+                            received.push((id, signature, String::from_utf8_lossy(&body).into()));
+                            Ok::<_, hyper::Error>(
+                                hyper::Response::builder()
+                                    .status(status)
+                                    .body(hyper::Body::empty())
+                                    .expect("response"),
+                            )
+                        }
+                    }))
+                }
+            }
+        });
+        let endpoint = hyper::Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let url = format!("http://{}/deliveries", endpoint.local_addr()).parse()?;
+        tokio::spawn(endpoint);
+
+        let webhooks = Webhooks::new(vec![url]).with_secret("secret").with_retries(
+            NonZeroU32::new(3).expect("attempts"),
+            Duration::from_millis(10),
+        );
+        let simulation = Ulid::new();
+        let settled = SettledOrder {
+            outcome: OrderOutcome::Delivered,
+            receipt: OrderReceipt {
+                order: schema::Order {
+                    id: schema::OrderId(7),
+                    ..testkit::order(0, "Toronto", Priority::Emergency)
+                },
+                status: OrderStatus::Delivered,
+                delivery_time: Some(600),
+                proof: Some(DeliveryProof {
+                    drop_north_m: 1,
+                    drop_east_m: -2,
+                    photo_hash: "ab".repeat(32),
+                    recipient_code: "042137".to_string(),
+                }),
+                ..Default::default()
+            },
+        };
+        webhooks
+            .deliver(futures::stream::iter([(simulation, settled)]))
+            .await;
+
+        // The failed attempt is retried with the same id & signature
+        let received = received.lock().expect("received");
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[0], received[1]);
+        let (id, signature, body) = &received[1];
+        assert_eq!(id, &format!("{}-7", simulation));
+        assert_eq!(signature, &sign(b"secret", body.as_bytes()));
+
+        let payload = Value::parse(body)?;
+        let field = |name| payload.get(name).and_then(Value::as_str);
+        assert_eq!(field("event"), Some("order.delivered"));
+        assert_eq!(field("status"), Some("delivered"));
+        let order = payload.get("order").expect("order");
+        assert_eq!(order.get("id").and_then(Value::as_u64), Some(7));
+        assert_eq!(
+            payload.get("delivery_time").and_then(Value::as_u64),
+            Some(600)
+        );
+        let proof = payload.get("proof").expect("proof");
+        assert_eq!(
+            proof.get("recipient_code").and_then(Value::as_str),
+            Some("042137")
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,233 @@
+//! Soak test running a generated multi-day scenario as fast as possible, with subscribers
+//! continually attaching to & detaching from the monitor stream as it's broadcast & journaled.
+//!
+//! Run with `cargo test --release -p server --test soak -- --ignored`, setting `SOAK_DAYS` to
+//! change the length of the scenario (defaults to three days).
+
+use std::num::NonZeroU32;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::channel::mpsc;
+use futures::StreamExt;
+use scheduler_core::CsvRunner;
+use schema::journal::{self, JournalWriter};
+use schema::proto::server as proto;
+use schema::{Destination, MonitorEvent, Order, Priority, Speed, ToFromProto};
+use server::{Broadcaster, Progress};
+use ulid::Ulid;
+
+/// Midnight at the start of the first day of the scenario
+const START: u64 = 1_697_241_600;
+const DAY_S: u64 = 24 * 60 * 60;
+
+/// Most subscribers connected at once, after which the oldest are disconnected
+const MAX_SUBSCRIBERS: usize = 48;
+/// Most the resident memory of the process may grow after the first day of the scenario
+const MAX_GROWTH_BYTES: u64 = 64 * 1024 * 1024;
+
+/// How a simulated subscriber consumes its stream
+#[derive(Clone, Copy)]
+enum Behavior {
+    /// Takes every event as soon as it is sent
+    Reader,
+    /// Stops taking events, so should be removed as stale
+    Stalled,
+    /// Disconnects after taking the provided number of events
+    Leaver(usize),
+}
+
+struct SimulatedSubscriber {
+    receiver: mpsc::UnboundedReceiver<Arc<proto::MonitorEvent>>,
+    progress: Progress,
+    behavior: Behavior,
+    taken: usize,
+}
+
+impl SimulatedSubscriber {
+    /// Takes whatever events are waiting, returning whether the subscriber is still connected
+    fn poll(&mut self) -> bool {
+        let limit = match self.behavior {
+            Behavior::Reader => usize::MAX,
+            Behavior::Stalled => return true,
+            Behavior::Leaver(limit) => limit,
+        };
+
+        while self.taken < limit {
+            match self.receiver.try_next() {
+                Ok(Some(_)) => {
+                    self.progress.consumed();
+                    self.taken += 1;
+                }
+                // Removed by the broadcaster
+                Ok(None) => return false,
+                Err(_) => return true,
+            }
+        }
+        false
+    }
+}
+
+/// Destinations spread around the nest, within range of a round trip
+fn destinations() -> Vec<Destination> {
+    (0..24)
+        .map(|i| {
+            let angle = i as f64 * std::f64::consts::TAU / 24.0;
+            let distance_m = 5_000.0 + (i % 6) as f64 * 5_000.0;
+            Destination {
+                name: format!("Destination {}", i).as_str().into(),
+                north_m: (angle.sin() * distance_m) as i64,
+                east_m: (angle.cos() * distance_m) as i64,
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// Orders placed every few minutes over the provided number of days, for pseudo-random
+/// destinations, with one in five being an emergency
+fn orders(destinations: &[Destination], days: u64) -> Vec<Order> {
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let mut next = move || {
+        // xorshift, so that each soak runs the same scenario
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+
+    let mut orders = vec![];
+    let mut time = START;
+    while time < START + days * DAY_S {
+        let destination = &destinations[(next() % destinations.len() as u64) as usize];
+        orders.push(Order {
+            time,
+            destination: destination.name,
+            priority: match next() % 5 {
+                0 => Priority::Emergency,
+                _ => Priority::Resupply,
+            },
+            ..Default::default()
+        });
+        time += 120 + next() % 480;
+    }
+    orders
+}
+
+/// Returns the resident memory of the process, where it can be read
+fn resident_bytes() -> Option<u64> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages = statm.split_whitespace().nth(1)?.parse::<u64>().ok()?;
+    Some(pages * 4096)
+}
+
+#[tokio::test(start_paused = true)]
+#[ignore = "long-running soak test"]
+async fn soak() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let days = std::env::var("SOAK_DAYS")
+        .ok()
+        .and_then(|days| days.parse().ok())
+        .unwrap_or(3);
+    let destinations = destinations();
+    let orders = orders(&destinations, days);
+    let total_orders = orders.len();
+
+    let mut runner = CsvRunner::new(destinations, orders)
+        .with_speed(Speed::fast_forward(u8::MAX.into()).expect("speed"))
+        .with_max_updates_per_second(NonZeroU32::new(20).expect("rate"));
+    let mut updates = runner.stream_updates().expect("update stream");
+    drop(runner.stream_alerts());
+    let run = runner.run_with_defaults();
+    drop(runner);
+
+    let journal_path = std::env::temp_dir().join(format!("soak-{}.journal", Ulid::new()));
+    let mut journal = JournalWriter::create(journal_path.to_str().expect("journal path"))?;
+
+    let broadcast = async {
+        let mut broadcaster = Broadcaster::<proto::MonitorEvent>::default();
+        let mut subscribers: Vec<SimulatedSubscriber> = vec![];
+        let mut events_sent = 0;
+        let mut attached = 0;
+        let mut baseline = None;
+
+        while let Some(update) = updates.next().await {
+            let time = update.time;
+            let event = MonitorEvent::StatusUpdate(update).into_proto();
+            journal.append(&event).expect("journal");
+            broadcaster.send(event);
+            events_sent += 1;
+
+            // Attach a subscriber with every few updates, cycling through the behaviors
+            if events_sent % 3 == 0 {
+                let (tx, rx) = mpsc::unbounded();
+                let progress = Progress::default();
+                broadcaster.subscribe(Ulid::new(), tx, progress.clone());
+                subscribers.push(SimulatedSubscriber {
+                    receiver: rx,
+                    progress,
+                    behavior: match attached % 3 {
+                        0 => Behavior::Reader,
+                        1 => Behavior::Stalled,
+                        _ => Behavior::Leaver(attached % 50),
+                    },
+                    taken: 0,
+                });
+                attached += 1;
+            }
+            if subscribers.len() > MAX_SUBSCRIBERS {
+                subscribers.remove(0);
+            }
+            subscribers.retain_mut(SimulatedSubscriber::poll);
+
+            // Heartbeat, removing subscribers which have stopped taking events
+            if events_sent % 10 == 0 {
+                broadcaster.remove_stale(Duration::ZERO);
+                broadcaster.send(MonitorEvent::Heartbeat.into_proto());
+                subscribers.retain_mut(SimulatedSubscriber::poll);
+            }
+            assert!(
+                broadcaster.len() <= MAX_SUBSCRIBERS + 1,
+                "{} subscribers leaked",
+                broadcaster.len()
+            );
+
+            match baseline {
+                None if time >= START + DAY_S => baseline = resident_bytes(),
+                Some(baseline) => {
+                    if let Some(resident) = resident_bytes() {
+                        assert!(
+                            resident.saturating_sub(baseline) <= MAX_GROWTH_BYTES,
+                            "resident memory grew from {} to {} bytes",
+                            baseline,
+                            resident
+                        );
+                    }
+                }
+                None => {}
+            }
+        }
+
+        // Once every subscriber has gone, nothing is left behind
+        drop(subscribers);
+        broadcaster.send(MonitorEvent::SimulationReset.into_proto());
+        assert_eq!(broadcaster.len(), 0);
+        assert!(broadcaster.stale_removed() > 0);
+        (events_sent, attached)
+    };
+
+    let (report, (events_sent, attached)) = futures::join!(run, broadcast);
+    let report = report?;
+    assert_eq!(
+        report.orders_delivered + report.unfulfilled_orders,
+        total_orders
+    );
+    assert!(attached > MAX_SUBSCRIBERS);
+
+    // The journal holds exactly the updates which were recorded
+    drop(journal);
+    let recorded = journal::decode(&std::fs::read(&journal_path)?)?;
+    std::fs::remove_file(&journal_path)?;
+    assert_eq!(recorded.len(), events_sent);
+
+    Ok(())
+}